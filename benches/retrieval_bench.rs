@@ -1,3 +1,6 @@
+use a3s_context::config::VectorIndexConfig;
+use a3s_context::storage::{VectorIndex, VectorIndexBackend};
+use a3s_context::Pathway;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
@@ -49,5 +52,34 @@ fn bench_vector_search(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_cosine_similarity, bench_vector_search);
+fn bench_hnsw_search(c: &mut Criterion) {
+    let dim = 128;
+    let num_vectors = 100_000;
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let index = VectorIndex::new(&VectorIndexConfig::default());
+    runtime.block_on(async {
+        for j in 0..num_vectors {
+            let pathway = Pathway::parse(&format!("a3s://knowledge/doc{}", j)).unwrap();
+            let vector: Vec<f32> = (0..dim).map(|i| ((i * 7 + j * 13) as f32).sin()).collect();
+            index.add(&pathway, &vector).await.unwrap();
+        }
+    });
+
+    let query: Vec<f32> = (0..dim).map(|i| (i as f32).cos()).collect();
+    c.bench_function("hnsw_search_100k", |bencher| {
+        bencher.iter(|| {
+            runtime
+                .block_on(index.search(black_box(&query), None, 10, 0.0))
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_cosine_similarity,
+    bench_vector_search,
+    bench_hnsw_search
+);
 criterion_main!(benches);
@@ -0,0 +1,439 @@
+//! Local, opt-in analytics of query patterns
+//!
+//! With `analytics.enabled`, every query and every piece of result feedback
+//! is appended as one JSON line to a log on local disk; nothing leaves the
+//! machine. The report groups queries by normalized text so knowledge-base
+//! owners can see what agents search for and cannot find.
+//!
+//! With `retrieval.feedback.enabled`, each node's feedback also adjusts its
+//! score: the usefulness rate, smoothed toward one half, scales the score by
+//! at most `max_adjustment` either way. A reset event starts a subtree's
+//! counts over without rewriting the log.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::config::FeedbackConfig;
+use crate::core::Namespace;
+use crate::error::Result;
+use crate::pathway::Pathway;
+
+/// One line of the analytics log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AnalyticsEvent {
+    Query {
+        query_id: String,
+        query: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<Namespace>,
+        matches: usize,
+        at: DateTime<Utc>,
+    },
+    Feedback {
+        query_id: String,
+        pathway: Pathway,
+        useful: bool,
+        at: DateTime<Utc>,
+    },
+    /// Forget earlier feedback for nodes under `pathway`, or for every node
+    Reset {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pathway: Option<Pathway>,
+        at: DateTime<Utc>,
+    },
+}
+
+/// Feedback counted for a node since its last reset
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeedbackCount {
+    pub useful: u32,
+    pub not_useful: u32,
+}
+
+impl FeedbackCount {
+    /// Usefulness rate, smoothed toward 1/2 by `prior_weight` pseudo-votes
+    pub fn smoothed_rate(&self, prior_weight: f32) -> f32 {
+        let total = (self.useful + self.not_useful) as f32 + prior_weight;
+        if total <= 0.0 {
+            return 0.5;
+        }
+        (self.useful as f32 + prior_weight / 2.0) / total
+    }
+
+    /// Score multiplier within `1 ± max_adjustment`
+    pub fn boost(&self, config: &FeedbackConfig) -> f32 {
+        let rate = self.smoothed_rate(config.prior_weight);
+        1.0 + (rate - 0.5) * 2.0 * config.max_adjustment
+    }
+}
+
+/// How often a query was run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryCount {
+    /// Most recent wording of the query
+    pub query: String,
+    pub count: usize,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Feedback recorded for one node
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeFeedback {
+    pub pathway: Pathway,
+    pub useful: usize,
+    pub not_useful: usize,
+}
+
+/// Summary of the analytics log
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AnalyticsReport {
+    pub queries: usize,
+    pub zero_result_queries: usize,
+    pub feedback: usize,
+    /// Most frequent queries
+    pub top_queries: Vec<QueryCount>,
+    /// Queries that matched nothing, most frequent first
+    pub zero_results: Vec<QueryCount>,
+    /// Queries whose feedback never marked a result useful
+    pub unhelpful: Vec<QueryCount>,
+    /// Nodes with feedback, most often marked not useful first
+    pub nodes: Vec<NodeFeedback>,
+}
+
+/// Append-only analytics log on local disk
+pub struct QueryLog {
+    path: PathBuf,
+    /// Feedback counts, loaded from the log on first use
+    counts: parking_lot::Mutex<Option<HashMap<Pathway, FeedbackCount>>>,
+}
+
+impl QueryLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            counts: parking_lot::Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an event to the log
+    pub async fn record(&self, event: &AnalyticsEvent) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+
+        if let Some(counts) = self.counts.lock().as_mut() {
+            count_feedback(counts, event);
+        }
+        Ok(())
+    }
+
+    /// Feedback counts per node since each node's last reset
+    pub async fn feedback_counts(&self) -> Result<HashMap<Pathway, FeedbackCount>> {
+        if let Some(counts) = self.counts.lock().as_ref() {
+            return Ok(counts.clone());
+        }
+        let mut counts = HashMap::new();
+        for event in self.events().await? {
+            count_feedback(&mut counts, &event);
+        }
+        *self.counts.lock() = Some(counts.clone());
+        Ok(counts)
+    }
+
+    /// Every event in the log, skipping lines that do not parse
+    pub async fn events(&self) -> Result<Vec<AnalyticsEvent>> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    tracing::warn!(error = %e, "skipping malformed analytics event");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Summarize the log, listing at most `limit` entries per section
+    pub async fn report(&self, limit: usize) -> Result<AnalyticsReport> {
+        Ok(summarize(&self.events().await?, limit))
+    }
+}
+
+/// Apply a feedback or reset event to per-node counts
+fn count_feedback(counts: &mut HashMap<Pathway, FeedbackCount>, event: &AnalyticsEvent) {
+    match event {
+        AnalyticsEvent::Feedback {
+            pathway, useful, ..
+        } => {
+            let count = counts.entry(pathway.clone()).or_default();
+            if *useful {
+                count.useful += 1;
+            } else {
+                count.not_useful += 1;
+            }
+        }
+        AnalyticsEvent::Reset { pathway, .. } => match pathway {
+            Some(root) => counts.retain(|pathway, _| !root.is_prefix_of(pathway)),
+            None => counts.clear(),
+        },
+        AnalyticsEvent::Query { .. } => {}
+    }
+}
+
+/// Summarize events, listing at most `limit` entries per section
+pub fn summarize(events: &[AnalyticsEvent], limit: usize) -> AnalyticsReport {
+    let mut report = AnalyticsReport::default();
+    let mut all: HashMap<String, QueryCount> = HashMap::new();
+    let mut zero: HashMap<String, QueryCount> = HashMap::new();
+    let mut queries_by_id: HashMap<&str, &str> = HashMap::new();
+    let mut useful_by_query: HashMap<&str, bool> = HashMap::new();
+    let mut nodes: HashMap<&Pathway, NodeFeedback> = HashMap::new();
+
+    for event in events {
+        match event {
+            AnalyticsEvent::Query {
+                query_id,
+                query,
+                matches,
+                at,
+                ..
+            } => {
+                report.queries += 1;
+                queries_by_id.insert(query_id, query);
+                count(&mut all, query, *at);
+                if *matches == 0 {
+                    report.zero_result_queries += 1;
+                    count(&mut zero, query, *at);
+                }
+            }
+            AnalyticsEvent::Feedback {
+                query_id,
+                pathway,
+                useful,
+                ..
+            } => {
+                report.feedback += 1;
+                *useful_by_query.entry(query_id).or_default() |= *useful;
+                let node = nodes.entry(pathway).or_insert_with(|| NodeFeedback {
+                    pathway: pathway.clone(),
+                    useful: 0,
+                    not_useful: 0,
+                });
+                if *useful {
+                    node.useful += 1;
+                } else {
+                    node.not_useful += 1;
+                }
+            }
+            AnalyticsEvent::Reset { .. } => {}
+        }
+    }
+
+    let mut unhelpful: HashMap<String, QueryCount> = HashMap::new();
+    for (query_id, useful) in useful_by_query {
+        if let (false, Some(query)) = (useful, queries_by_id.get(query_id)) {
+            let last_seen = all[&normalize(query)].last_seen;
+            count(&mut unhelpful, query, last_seen);
+        }
+    }
+
+    report.top_queries = ranked(all, limit);
+    report.zero_results = ranked(zero, limit);
+    report.unhelpful = ranked(unhelpful, limit);
+    let mut nodes: Vec<_> = nodes.into_values().collect();
+    nodes.sort_by(|a, b| {
+        b.not_useful
+            .cmp(&a.not_useful)
+            .then(a.useful.cmp(&b.useful))
+            .then_with(|| a.pathway.cmp(&b.pathway))
+    });
+    nodes.truncate(limit);
+    report.nodes = nodes;
+    report
+}
+
+/// Queries are grouped by lowercase text with whitespace collapsed
+fn normalize(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn count(counts: &mut HashMap<String, QueryCount>, query: &str, at: DateTime<Utc>) {
+    let entry = counts
+        .entry(normalize(query))
+        .or_insert_with(|| QueryCount {
+            query: query.to_string(),
+            count: 0,
+            last_seen: at,
+        });
+    entry.count += 1;
+    if at >= entry.last_seen {
+        entry.query = query.to_string();
+        entry.last_seen = at;
+    }
+}
+
+/// Most frequent first, then most recent
+fn ranked(counts: HashMap<String, QueryCount>, limit: usize) -> Vec<QueryCount> {
+    let mut counts: Vec<_> = counts.into_values().collect();
+    counts.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(b.last_seen.cmp(&a.last_seen))
+            .then_with(|| a.query.cmp(&b.query))
+    });
+    counts.truncate(limit);
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: &str, text: &str, matches: usize) -> AnalyticsEvent {
+        AnalyticsEvent::Query {
+            query_id: id.to_string(),
+            query: text.to_string(),
+            namespace: None,
+            matches,
+            at: Utc::now(),
+        }
+    }
+
+    fn feedback(id: &str, pathway: &str, useful: bool) -> AnalyticsEvent {
+        AnalyticsEvent::Feedback {
+            query_id: id.to_string(),
+            pathway: Pathway::parse(pathway).unwrap(),
+            useful,
+            at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let events = vec![
+            query("3", "saml setup", 0),
+            query("4", "saml setup", 0),
+            query("1", "token refresh", 3),
+            query("2", "Token  Refresh", 2),
+            query("5", "rate limits", 1),
+            feedback("1", "a3s://knowledge/docs/oauth.md", true),
+            feedback("5", "a3s://knowledge/docs/quotas.md", false),
+        ];
+        let report = summarize(&events, 10);
+
+        assert_eq!(report.queries, 5);
+        assert_eq!(report.zero_result_queries, 2);
+        assert_eq!(report.feedback, 2);
+        assert_eq!(report.top_queries[0].count, 2);
+        assert_eq!(report.top_queries[0].query, "Token  Refresh");
+        assert_eq!(report.zero_results.len(), 1);
+        assert_eq!(report.zero_results[0].query, "saml setup");
+        assert_eq!(report.unhelpful.len(), 1);
+        assert_eq!(report.unhelpful[0].query, "rate limits");
+        assert_eq!(
+            report.nodes[0].pathway.to_string(),
+            "a3s://knowledge/docs/quotas.md"
+        );
+    }
+
+    #[test]
+    fn test_feedback_boost_is_smoothed_and_capped() {
+        let config = FeedbackConfig {
+            enabled: true,
+            max_adjustment: 0.2,
+            prior_weight: 4.0,
+        };
+        let unknown = FeedbackCount::default();
+        assert_eq!(unknown.boost(&config), 1.0);
+
+        let one_vote = FeedbackCount {
+            useful: 1,
+            not_useful: 0,
+        };
+        let many_votes = FeedbackCount {
+            useful: 1000,
+            not_useful: 0,
+        };
+        assert!(one_vote.boost(&config) > 1.0);
+        assert!(one_vote.boost(&config) < many_votes.boost(&config));
+        assert!(many_votes.boost(&config) <= 1.2);
+
+        let rejected = FeedbackCount {
+            useful: 0,
+            not_useful: 1000,
+        };
+        assert!(rejected.boost(&config) >= 0.8);
+        assert!(rejected.boost(&config) < 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_feedback_counts_honor_resets() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = QueryLog::new(dir.path().join("analytics.jsonl"));
+        log.record(&feedback("1", "a3s://knowledge/docs/a.md", true))
+            .await
+            .unwrap();
+        log.record(&feedback("1", "a3s://knowledge/notes/b.md", false))
+            .await
+            .unwrap();
+        assert_eq!(log.feedback_counts().await.unwrap().len(), 2);
+
+        log.record(&AnalyticsEvent::Reset {
+            pathway: Some(Pathway::parse("a3s://knowledge/docs").unwrap()),
+            at: Utc::now(),
+        })
+        .await
+        .unwrap();
+        let counts = log.feedback_counts().await.unwrap();
+        assert_eq!(counts.len(), 1);
+
+        // A fresh log replays the same history from disk
+        let reloaded = QueryLog::new(log.path());
+        assert_eq!(reloaded.feedback_counts().await.unwrap(), counts);
+    }
+
+    #[tokio::test]
+    async fn test_log_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = QueryLog::new(dir.path().join("nested/analytics.jsonl"));
+        assert!(log.events().await.unwrap().is_empty());
+
+        let event = query("1", "token refresh", 0);
+        log.record(&event).await.unwrap();
+        log.record(&feedback("1", "a3s://knowledge/docs/oauth.md", false))
+            .await
+            .unwrap();
+
+        let events = log.events().await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], event);
+    }
+}
@@ -0,0 +1,109 @@
+//! Grounded answers: retrieve, assemble context, and cite sources
+
+use serde::{Deserialize, Serialize};
+
+use crate::assembly::{AssembledContext, Citation};
+use crate::QueryOptions;
+
+/// Options for [`crate::A3SClient::ask`]
+#[derive(Debug, Clone)]
+pub struct AskOptions {
+    /// Retrieval options for finding context
+    pub query: QueryOptions,
+    /// Token budget for the context included in the prompt
+    pub max_context_tokens: usize,
+}
+
+impl Default for AskOptions {
+    fn default() -> Self {
+        Self {
+            query: QueryOptions::default(),
+            max_context_tokens: 3000,
+        }
+    }
+}
+
+/// Answer to a question, with the sources it cites
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Answer {
+    pub answer: String,
+    pub citations: Vec<Citation>,
+    /// Identifier of the retrieval query
+    pub query_id: String,
+}
+
+/// Build a prompt asking the question against assembled context
+pub fn build_prompt(question: &str, context: &AssembledContext) -> String {
+    format!(
+        "Answer the question using only the numbered context below. \
+         Cite the sources you use as [n]. If the context does not contain \
+         the answer, say so.\n\n{}Question: {}\nAnswer:",
+        context.text, question
+    )
+}
+
+/// Keep the citations an answer refers to as `[n]`, or all if it cites none
+pub fn cited(answer: &str, citations: Vec<Citation>) -> Vec<Citation> {
+    let referenced: Vec<bool> = (1..=citations.len())
+        .map(|n| answer.contains(&format!("[{}]", n)))
+        .collect();
+    if !referenced.contains(&true) {
+        return citations;
+    }
+
+    citations
+        .into_iter()
+        .zip(referenced)
+        .filter_map(|(citation, used)| used.then_some(citation))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembly::assemble;
+    use crate::core::NodeKind;
+    use crate::pathway::Pathway;
+    use crate::tokenizer::HeuristicTokenizer;
+    use crate::MatchedNode;
+
+    fn matched(pathway: &str, content: &str, score: f32) -> MatchedNode {
+        MatchedNode {
+            pathway: Pathway::parse(pathway).unwrap(),
+            node_kind: NodeKind::Document,
+            score,
+            brief: String::new(),
+            summary: None,
+            content: Some(content.to_string()),
+            highlights: Vec::new(),
+            age: 0,
+            stale: false,
+            symbols: Vec::new(),
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn test_build_prompt() {
+        let matches = vec![matched("a3s://knowledge/a", "alpha", 0.9)];
+        let prompt = build_prompt("Why?", &assemble(&matches, 100, &HeuristicTokenizer));
+        assert!(prompt.contains("[1] a3s://knowledge/a\nalpha"));
+        assert!(prompt.ends_with("Question: Why?\nAnswer:"));
+    }
+
+    #[test]
+    fn test_cited_filters_references() {
+        let matches = vec![
+            matched("a3s://knowledge/a", "alpha", 0.9),
+            matched("a3s://knowledge/b", "beta", 0.8),
+        ];
+        let citations = assemble(&matches, 100, &HeuristicTokenizer).citations;
+
+        let kept = cited("Beta is the answer [2].", citations.clone());
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].pathway.to_string(), "a3s://knowledge/b");
+        assert_eq!(kept[0].span, Some(0..4));
+
+        assert_eq!(cited("No idea.", citations).len(), 2);
+    }
+}
@@ -0,0 +1,201 @@
+//! Archives: whole subtrees exported to move a store between machines
+//!
+//! An archive is a JSON-lines stream. The first line is the
+//! [`ArchiveHeader`]; each following line is one node with its digest,
+//! embedding, metadata, and relations. Unlike a context pack, an imported
+//! archive is ordinary writable content, and it may be imported at another
+//! pathway than it was exported from.
+
+use std::io::{BufRead, Write};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::EmbeddingConfig;
+use crate::core::Node;
+use crate::drift::EmbeddingModel;
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+
+/// Newest archive format this build reads and the one it writes
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// Description of an archive's contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveHeader {
+    pub format_version: u32,
+    /// Pathway the archive was exported from
+    pub root: Pathway,
+    pub node_count: u64,
+    /// Model the archived embeddings were made with
+    pub embedding: EmbeddingModel,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of importing an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportResult {
+    pub header: ArchiveHeader,
+    /// Pathway the nodes were imported under
+    pub root: Pathway,
+    pub nodes_created: usize,
+    /// Existing nodes replaced by archived ones
+    pub nodes_replaced: usize,
+}
+
+/// Write every node at and below `root` to `writer`
+pub async fn export<W: Write>(
+    storage: &dyn StorageBackend,
+    root: &Pathway,
+    embedding: &EmbeddingConfig,
+    mut writer: W,
+) -> Result<ArchiveHeader> {
+    // Lazily loading backends may not have read the subtree yet
+    storage.warm(root).await?;
+    let mut nodes = Vec::new();
+    if let Ok(node) = storage.get(root).await {
+        nodes.push(node);
+    }
+    nodes.extend(storage.get_children(root, usize::MAX).await?);
+    nodes.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+    if nodes.is_empty() {
+        return Err(A3SError::NodeNotFound(root.to_string()));
+    }
+
+    let header = ArchiveHeader {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        root: root.clone(),
+        node_count: nodes.len() as u64,
+        embedding: EmbeddingModel::of(embedding),
+        created_at: Utc::now(),
+    };
+    serde_json::to_writer(&mut writer, &header)?;
+    writer.write_all(b"\n")?;
+    for node in &nodes {
+        serde_json::to_writer(&mut writer, node)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(header)
+}
+
+/// Read an archive, checking its header against the node lines
+pub fn read<R: BufRead>(reader: R) -> Result<(ArchiveHeader, Vec<Node>)> {
+    let mut lines = reader.lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| A3SError::Archive("Archive is empty".to_string()))??;
+    let header: ArchiveHeader = serde_json::from_str(&header)
+        .map_err(|e| A3SError::Archive(format!("Invalid header: {}", e)))?;
+    if header.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(A3SError::Archive(format!(
+            "Archive format {} is newer than supported format {}",
+            header.format_version, ARCHIVE_FORMAT_VERSION
+        )));
+    }
+
+    let mut nodes = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let node: Node = serde_json::from_str(&line)
+            .map_err(|e| A3SError::Archive(format!("Invalid node on line {}: {}", i + 2, e)))?;
+        nodes.push(node);
+    }
+    if nodes.len() as u64 != header.node_count {
+        return Err(A3SError::Archive(format!(
+            "Header lists {} nodes, archive contains {}",
+            header.node_count,
+            nodes.len()
+        )));
+    }
+    Ok((header, nodes))
+}
+
+/// Place archived nodes under `target`, along with chunk parents and
+/// relations between them
+pub fn rebase(nodes: Vec<Node>, root: &Pathway, target: &Pathway) -> Vec<Node> {
+    nodes
+        .into_iter()
+        .map(|node| {
+            let mut node = crate::relocate::rebase(node, root, target);
+            for relation in &mut node.relations {
+                if let Some(moved) = relation.target.rebase(root, target) {
+                    relation.target = moved;
+                }
+            }
+            node
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::{NodeKind, RelationKind};
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_archive_round_trip_and_rebase() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let guide = Pathway::parse("a3s://knowledge/docs/guide.md").unwrap();
+        let mut node = Node::new(
+            Pathway::parse("a3s://knowledge/docs/auth/tokens").unwrap(),
+            NodeKind::Document,
+            "Tokens expire after one hour".to_string(),
+        );
+        node.embedding = vec![1.0, 0.0];
+        node.add_relation(guide, RelationKind::References, "See the guide".to_string());
+        storage.put(&node).await.unwrap();
+
+        let embedding = EmbeddingConfig {
+            model: "mock".to_string(),
+            dimension: 2,
+            ..Default::default()
+        };
+        let root = Pathway::parse("a3s://knowledge/docs").unwrap();
+        let mut buffer = Vec::new();
+        let header = export(&storage, &root, &embedding, &mut buffer)
+            .await
+            .unwrap();
+        assert_eq!(header.node_count, 1);
+
+        let (header, nodes) = read(buffer.as_slice()).unwrap();
+        assert_eq!(header.embedding, EmbeddingModel::of(&embedding));
+        assert_eq!(nodes[0].embedding, vec![1.0, 0.0]);
+
+        let target = Pathway::parse("a3s://knowledge/imported").unwrap();
+        let nodes = rebase(nodes, &header.root, &target);
+        assert_eq!(
+            nodes[0].pathway.to_string(),
+            "a3s://knowledge/imported/auth/tokens"
+        );
+        assert_eq!(
+            nodes[0].relations[0].target.to_string(),
+            "a3s://knowledge/imported/guide.md"
+        );
+    }
+
+    #[test]
+    fn test_read_rejects_truncated_archive() {
+        let header = ArchiveHeader {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            root: Pathway::parse("a3s://knowledge").unwrap(),
+            node_count: 2,
+            embedding: EmbeddingModel {
+                model: "mock".to_string(),
+                dimension: 2,
+            },
+            created_at: Utc::now(),
+        };
+        let archive = format!("{}\n", serde_json::to_string(&header).unwrap());
+
+        let err = read(archive.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("lists 2 nodes"));
+        assert!(read("".as_bytes()).is_err());
+    }
+}
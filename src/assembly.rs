@@ -0,0 +1,169 @@
+//! Token-budgeted context assembly with citations
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pathway::Pathway;
+use crate::tokenizer::{CharTokenizer, Tokenizer};
+use crate::MatchedNode;
+
+/// Longest snippet quoted in a citation, in characters
+const MAX_SNIPPET_CHARS: usize = 300;
+
+/// Attribution of assembled text to the node it came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub pathway: Pathway,
+    /// Character range of the node's content included in the context;
+    /// `None` when content was not retrieved and a digest was used instead
+    pub span: Option<Range<usize>>,
+    /// Start of the included text
+    pub snippet: String,
+    /// Retrieval score of the node
+    pub score: f32,
+}
+
+/// Numbered sources ready to place in a prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssembledContext {
+    /// Sources as `[n] pathway` followed by their text
+    pub text: String,
+    /// One citation per numbered source, in order
+    pub citations: Vec<Citation>,
+    pub estimated_tokens: usize,
+}
+
+/// Number matches in score order until `max_tokens` of source text, as
+/// counted by `tokenizer`, is used
+pub fn assemble(
+    matches: &[MatchedNode],
+    max_tokens: usize,
+    tokenizer: &dyn Tokenizer,
+) -> AssembledContext {
+    let mut budget = max_tokens;
+    let mut text = String::new();
+    let mut citations = Vec::new();
+
+    for m in matches {
+        let (source, from_content) = source_text(m);
+        if source.is_empty() {
+            continue;
+        }
+        let included = tokenizer.truncate(source, budget);
+        if included.is_empty() {
+            break;
+        }
+        budget = budget.saturating_sub(tokenizer.count(included));
+        let included_chars = included.chars().count();
+
+        text.push_str(&format!(
+            "[{}] {}\n{}\n\n",
+            citations.len() + 1,
+            m.pathway,
+            included
+        ));
+        citations.push(Citation {
+            pathway: m.pathway.clone(),
+            span: from_content.then_some(0..included_chars),
+            snippet: CharTokenizer
+                .truncate(included, MAX_SNIPPET_CHARS)
+                .to_string(),
+            score: m.score,
+        });
+    }
+
+    AssembledContext {
+        estimated_tokens: tokenizer.count(&text),
+        text,
+        citations,
+    }
+}
+
+/// Most detailed text available for a match, and whether it is the content
+fn source_text(m: &MatchedNode) -> (&str, bool) {
+    if let Some(content) = m.content.as_deref().filter(|c| !c.is_empty()) {
+        return (content, true);
+    }
+    let digest = m
+        .summary
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .unwrap_or(&m.brief);
+    (digest, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+    use crate::tokenizer::HeuristicTokenizer;
+
+    fn matched(pathway: &str, content: Option<&str>, summary: &str, score: f32) -> MatchedNode {
+        MatchedNode {
+            pathway: Pathway::parse(pathway).unwrap(),
+            node_kind: NodeKind::Document,
+            score,
+            brief: String::new(),
+            summary: Some(summary.to_string()),
+            content: content.map(str::to_string),
+            highlights: Vec::new(),
+            age: 0,
+            stale: false,
+            symbols: Vec::new(),
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn test_assemble_respects_budget() {
+        let a = "a".repeat(30);
+        let b = "b".repeat(30);
+        let matches = vec![
+            matched("a3s://knowledge/a", Some(&a), "", 0.9),
+            matched("a3s://knowledge/b", Some(&b), "", 0.8),
+        ];
+
+        let context = assemble(&matches, 10, &HeuristicTokenizer);
+        assert_eq!(context.citations.len(), 2);
+        assert_eq!(context.citations[0].span, Some(0..30));
+        assert_eq!(context.citations[1].span, Some(0..8));
+        assert!(context.text.starts_with("[1] a3s://knowledge/a\n"));
+
+        assert_eq!(
+            assemble(&matches, 5, &HeuristicTokenizer).citations.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_assemble_counts_characters_and_digests() {
+        let matches = vec![
+            matched("a3s://knowledge/a", Some("héllo wörld"), "", 0.9),
+            matched("a3s://knowledge/b", None, "digest only", 0.8),
+        ];
+
+        let context = assemble(&matches, 100, &HeuristicTokenizer);
+        assert_eq!(context.citations[0].span, Some(0..11));
+        assert_eq!(context.citations[1].span, None);
+        assert_eq!(context.citations[1].snippet, "digest only");
+    }
+
+    #[test]
+    fn test_assemble_with_custom_tokenizer() {
+        struct WordTokenizer;
+        impl Tokenizer for WordTokenizer {
+            fn count(&self, text: &str) -> usize {
+                text.split_whitespace().count()
+            }
+        }
+
+        let matches = vec![
+            matched("a3s://knowledge/a", Some("one two three"), "", 0.9),
+            matched("a3s://knowledge/b", Some("four five six"), "", 0.8),
+        ];
+        let context = assemble(&matches, 4, &WordTokenizer);
+        assert_eq!(context.citations[0].span, Some(0..13));
+        assert_eq!(context.citations[1].snippet, "four ");
+    }
+}
@@ -0,0 +1,191 @@
+//! Write batches: many small writes committed together
+//!
+//! [`crate::A3SClient::batch`] returns a [`WriteBatch`] that queues puts,
+//! metadata updates, and removals. Committing embeds every queued node
+//! that lacks an embedding in one provider call, then stores the nodes
+//! with a single [`StorageBackend::put_batch`](crate::storage::StorageBackend::put_batch),
+//! which indexes their vectors in one pass. Agents that record many
+//! memories per turn pay for one round of embedding and indexing instead
+//! of one per fact.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bulk::{self, MetadataOp};
+use crate::core::Node;
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::A3SClient;
+
+enum BatchOp {
+    Put(Box<Node>),
+    Update(String, Vec<MetadataOp>),
+    Remove(String, bool),
+}
+
+/// Outcome of committing a [`WriteBatch`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// Nodes stored by puts
+    pub put: usize,
+    /// Nodes whose metadata changed
+    pub updated: usize,
+    /// Pathways removed
+    pub removed: usize,
+}
+
+/// Writes queued against a client, applied in order by [`WriteBatch::commit`]
+pub struct WriteBatch<'a> {
+    client: &'a A3SClient,
+    ops: Vec<BatchOp>,
+}
+
+impl<'a> WriteBatch<'a> {
+    pub(crate) fn new(client: &'a A3SClient) -> Self {
+        Self {
+            client,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Queue a node to store, embedding its content on commit unless it
+    /// carries an embedding
+    pub fn put(&mut self, node: Node) -> &mut Self {
+        self.ops.push(BatchOp::Put(Box::new(node)));
+        self
+    }
+
+    /// Queue a fact for a user's memory, returning the pathway it will have
+    pub fn remember(&mut self, fact: &str, user: &str, tags: &[String]) -> Result<Pathway> {
+        let node = self.client.memory_node(fact, user, tags)?;
+        let pathway = node.pathway.clone();
+        self.put(node);
+        Ok(pathway)
+    }
+
+    /// Queue metadata operations on a stored or queued node
+    pub fn update<P: AsRef<str>>(&mut self, pathway: P, ops: &[MetadataOp]) -> &mut Self {
+        self.ops
+            .push(BatchOp::Update(pathway.as_ref().to_string(), ops.to_vec()));
+        self
+    }
+
+    /// Queue a removal, of everything below the pathway too if `recursive`
+    pub fn remove<P: AsRef<str>>(&mut self, pathway: P, recursive: bool) -> &mut Self {
+        self.ops
+            .push(BatchOp::Remove(pathway.as_ref().to_string(), recursive));
+        self
+    }
+
+    /// Number of queued operations
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply the queued operations
+    ///
+    /// Every pathway is checked before anything is written, so a batch
+    /// touching a leased or read-only pathway writes nothing. Removals are
+    /// applied first and drop queued writes they cover, then all other
+    /// writes are stored together.
+    pub async fn commit(self) -> Result<BatchResult> {
+        let client = self.client;
+        let mut result = BatchResult::default();
+        // Final state of each written pathway, in first-write order
+        let mut writes: Vec<Node> = Vec::new();
+        let mut positions: HashMap<Pathway, usize> = HashMap::new();
+        let mut removals: Vec<(Pathway, bool)> = Vec::new();
+
+        for op in self.ops {
+            match op {
+                BatchOp::Put(node) => {
+                    client.ensure_writable(&node.pathway, false).await?;
+                    let node = client.policies.apply(*node).await?;
+                    match positions.get(&node.pathway) {
+                        Some(&i) => writes[i] = node,
+                        None => {
+                            positions.insert(node.pathway.clone(), writes.len());
+                            writes.push(node);
+                        }
+                    }
+                    result.put += 1;
+                }
+                BatchOp::Update(pathway, ops) => {
+                    let pathway = Pathway::parse(&pathway)?;
+                    client.ensure_writable(&pathway, false).await?;
+                    let removed = removals.iter().any(|(r, recursive)| {
+                        *r == pathway || (*recursive && r.is_prefix_of(&pathway))
+                    });
+                    let mut node = match positions.get(&pathway) {
+                        Some(&i) => writes[i].clone(),
+                        None if removed => {
+                            return Err(A3SError::NodeNotFound(pathway.to_string()));
+                        }
+                        None => client.storage.get(&pathway).await?,
+                    };
+                    if !bulk::apply_all(&ops, &mut node.metadata) {
+                        continue;
+                    }
+                    node.updated_at = client.now();
+                    let node = client.policies.apply(node).await?;
+                    match positions.get(&pathway) {
+                        Some(&i) => writes[i] = node,
+                        None => {
+                            positions.insert(pathway, writes.len());
+                            writes.push(node);
+                        }
+                    }
+                    result.updated += 1;
+                }
+                BatchOp::Remove(pathway, recursive) => {
+                    let pathway = Pathway::parse(&pathway)?;
+                    client.ensure_writable(&pathway, recursive).await?;
+                    let covered =
+                        |p: &Pathway| *p == pathway || (recursive && pathway.is_prefix_of(p));
+                    writes.retain(|node| !covered(&node.pathway));
+                    positions = writes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, node)| (node.pathway.clone(), i))
+                        .collect();
+                    removals.push((pathway, recursive));
+                    result.removed += 1;
+                }
+            }
+        }
+
+        for (pathway, recursive) in &removals {
+            client.storage.remove(pathway, *recursive).await?;
+        }
+
+        let unembedded: Vec<usize> = writes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| {
+                node.embedding.is_empty() && !node.is_directory && !node.content.is_empty()
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if !unembedded.is_empty() {
+            client.record_embedding_model_once().await?;
+            let texts: Vec<String> = unembedded
+                .iter()
+                .map(|&i| writes[i].content.clone())
+                .collect();
+            let embeddings = client.embedder.embed_batch(&texts).await?;
+            for (i, embedding) in unembedded.into_iter().zip(embeddings) {
+                writes[i].embedding = embedding;
+            }
+        }
+        if !writes.is_empty() {
+            client.storage.put_batch(&writes).await?;
+        }
+
+        Ok(result)
+    }
+}
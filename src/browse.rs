@@ -0,0 +1,409 @@
+//! Interactive terminal browser for the context store
+
+use std::collections::BTreeMap;
+use std::io::{self, Stdout};
+
+use a3s_context::{A3SClient, Namespace, Node, Pathway};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+type Term = Terminal<CrosstermBackend<Stdout>>;
+
+/// Run the browser until the user quits
+pub async fn run(client: &A3SClient) -> anyhow::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let mut browser = Browser::new(client);
+    let result = browser.event_loop(&mut terminal).await;
+
+    // Always restore the terminal, even if the loop failed
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreviewLevel {
+    Brief,
+    Summary,
+    Full,
+}
+
+impl PreviewLevel {
+    fn next(self) -> Self {
+        match self {
+            PreviewLevel::Brief => PreviewLevel::Summary,
+            PreviewLevel::Summary => PreviewLevel::Full,
+            PreviewLevel::Full => PreviewLevel::Brief,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Search,
+    Tag,
+    ConfirmDelete,
+}
+
+/// A row in the tree pane
+struct Row {
+    pathway: Pathway,
+    depth: usize,
+    is_directory: bool,
+    expanded: bool,
+}
+
+struct Browser<'a> {
+    client: &'a A3SClient,
+    rows: Vec<Row>,
+    list_state: ListState,
+    level: PreviewLevel,
+    mode: Mode,
+    search: String,
+    input: String,
+    preview: Option<Node>,
+    status: String,
+}
+
+impl<'a> Browser<'a> {
+    fn new(client: &'a A3SClient) -> Self {
+        let rows = [
+            Namespace::Knowledge,
+            Namespace::Memory,
+            Namespace::Capability,
+            Namespace::Session,
+        ]
+        .into_iter()
+        .map(|ns| Row {
+            pathway: Pathway::root(ns),
+            depth: 0,
+            is_directory: true,
+            expanded: false,
+        })
+        .collect();
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+
+        Self {
+            client,
+            rows,
+            list_state,
+            level: PreviewLevel::Brief,
+            mode: Mode::Normal,
+            search: String::new(),
+            input: String::new(),
+            preview: None,
+            status: String::new(),
+        }
+    }
+
+    async fn event_loop(&mut self, terminal: &mut Term) -> anyhow::Result<()> {
+        self.refresh_preview().await;
+
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if !self.handle_key(key.code).await {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handle a key press, returning false when the browser should exit
+    async fn handle_key(&mut self, code: KeyCode) -> bool {
+        match self.mode {
+            Mode::Normal => return self.handle_normal_key(code).await,
+            Mode::Search => match code {
+                KeyCode::Char(c) => {
+                    self.search.push(c);
+                    self.select(0).await;
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                    self.select(0).await;
+                }
+                KeyCode::Enter => self.mode = Mode::Normal,
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.mode = Mode::Normal;
+                    self.select(0).await;
+                }
+                _ => {}
+            },
+            Mode::Tag => match code {
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Enter => {
+                    self.tag_selected().await;
+                    self.mode = Mode::Normal;
+                }
+                KeyCode::Esc => self.mode = Mode::Normal,
+                _ => {}
+            },
+            Mode::ConfirmDelete => {
+                if code == KeyCode::Char('y') {
+                    self.delete_selected().await;
+                } else {
+                    self.status = "Delete cancelled".to_string();
+                }
+                self.mode = Mode::Normal;
+            }
+        }
+        true
+    }
+
+    async fn handle_normal_key(&mut self, code: KeyCode) -> bool {
+        self.status.clear();
+        let selected = self.list_state.selected().unwrap_or(0);
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.select(selected + 1).await,
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)).await,
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.expand_selected().await,
+            KeyCode::Left | KeyCode::Char('h') => self.collapse_selected(),
+            KeyCode::Tab => self.level = self.level.next(),
+            KeyCode::Char('b') => self.level = PreviewLevel::Brief,
+            KeyCode::Char('s') => self.level = PreviewLevel::Summary,
+            KeyCode::Char('f') => self.level = PreviewLevel::Full,
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('t') if self.preview.is_some() => {
+                self.input.clear();
+                self.mode = Mode::Tag;
+            }
+            KeyCode::Char('d') if self.selected_row().is_some() => {
+                self.mode = Mode::ConfirmDelete;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Indices into `rows` that match the current search filter
+    fn visible(&self) -> Vec<usize> {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                self.search.is_empty() || row.pathway.to_string().contains(&self.search)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let visible = self.visible();
+        self.list_state
+            .selected()
+            .and_then(|i| visible.get(i).copied())
+    }
+
+    fn selected_row(&self) -> Option<&Row> {
+        self.selected_index().map(|i| &self.rows[i])
+    }
+
+    async fn select(&mut self, index: usize) {
+        let last = self.visible().len().checked_sub(1);
+        self.list_state.select(last.map(|last| index.min(last)));
+        self.refresh_preview().await;
+    }
+
+    async fn refresh_preview(&mut self) {
+        self.preview = match self.selected_row() {
+            Some(row) => self.client.read(row.pathway.to_string()).await.ok(),
+            None => None,
+        };
+    }
+
+    async fn expand_selected(&mut self) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        if !self.rows[index].is_directory || self.rows[index].expanded {
+            return;
+        }
+
+        let pathway = self.rows[index].pathway.clone();
+        let depth = self.rows[index].depth + 1;
+
+        let nodes = match self.client.children(pathway.to_string(), usize::MAX).await {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                self.status = format!("Failed to list {}: {}", pathway, e);
+                return;
+            }
+        };
+
+        // Collapse descendants into immediate children, synthesizing directories
+        // for intermediate pathways that have no node of their own
+        let mut children: BTreeMap<Pathway, bool> = BTreeMap::new();
+        for node in nodes {
+            let child = pathway.join(&node.pathway.segments()[pathway.depth()]);
+            let is_directory = node.is_directory || node.pathway.depth() > child.depth();
+            *children.entry(child).or_insert(false) |= is_directory;
+        }
+
+        let rows = children.into_iter().map(|(pathway, is_directory)| Row {
+            pathway,
+            depth,
+            is_directory,
+            expanded: false,
+        });
+        self.rows.splice(index + 1..index + 1, rows);
+        self.rows[index].expanded = true;
+    }
+
+    fn collapse_selected(&mut self) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        let end = self.subtree_end(index);
+        self.rows.drain(index + 1..end);
+        self.rows[index].expanded = false;
+    }
+
+    /// Index one past the last descendant row of `index`
+    fn subtree_end(&self, index: usize) -> usize {
+        let depth = self.rows[index].depth;
+        self.rows[index + 1..]
+            .iter()
+            .position(|row| row.depth <= depth)
+            .map(|offset| index + 1 + offset)
+            .unwrap_or(self.rows.len())
+    }
+
+    async fn tag_selected(&mut self) {
+        let tag = self.input.trim().to_string();
+        let Some(node) = &self.preview else {
+            return;
+        };
+        if tag.is_empty() {
+            return;
+        }
+
+        let pathway = node.pathway.to_string();
+        self.status = match self
+            .client
+            .add_tags(&pathway, std::slice::from_ref(&tag))
+            .await
+        {
+            Ok(()) => format!("Tagged {} with '{}'", pathway, tag),
+            Err(e) => format!("Failed to tag {}: {}", pathway, e),
+        };
+        self.refresh_preview().await;
+    }
+
+    async fn delete_selected(&mut self) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        if self.rows[index].depth == 0 {
+            self.status = "Refusing to delete a namespace root".to_string();
+            return;
+        }
+
+        let pathway = self.rows[index].pathway.to_string();
+        match self.client.remove(&pathway, true).await {
+            Ok(()) => {
+                let end = self.subtree_end(index);
+                self.rows.drain(index..end);
+                self.status = format!("Removed {}", pathway);
+                let selected = self.list_state.selected().unwrap_or(0);
+                self.select(selected).await;
+            }
+            Err(e) => self.status = format!("Failed to remove {}: {}", pathway, e),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let outer = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(3)])
+            .split(frame.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(outer[0]);
+
+        let items: Vec<ListItem> = self
+            .visible()
+            .into_iter()
+            .map(|i| {
+                let row = &self.rows[i];
+                let marker = match (row.is_directory, row.expanded) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    (false, _) => "  ",
+                };
+                let name = row
+                    .pathway
+                    .name()
+                    .unwrap_or(row.pathway.namespace().as_str());
+                ListItem::new(format!("{}{}{}", "  ".repeat(row.depth), marker, name))
+            })
+            .collect();
+
+        let tree = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Context"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(tree, panes[0], &mut self.list_state);
+
+        let (title, body) = match &self.preview {
+            Some(node) => {
+                let body = match self.level {
+                    PreviewLevel::Brief => node.digest.brief.clone(),
+                    PreviewLevel::Summary => node.digest.summary.clone(),
+                    PreviewLevel::Full => node.content.clone(),
+                };
+                let mut title = format!("{:?} · {:?}", self.level, node.kind);
+                if !node.metadata.tags.is_empty() {
+                    title.push_str(&format!(" · #{}", node.metadata.tags.join(" #")));
+                }
+                (title, body)
+            }
+            None => ("Preview".to_string(), String::new()),
+        };
+        let preview = Paragraph::new(body)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(preview, panes[1]);
+
+        let prompt = match self.mode {
+            Mode::Normal if !self.status.is_empty() => self.status.clone(),
+            Mode::Normal => {
+                "↑↓ move · ⏎ expand · ← collapse · Tab level · / search · t tag · d delete · q quit"
+                    .to_string()
+            }
+            Mode::Search => format!("/{}", self.search),
+            Mode::Tag => format!("tag: {}", self.input),
+            Mode::ConfirmDelete => "Delete selected subtree? (y/N)".to_string(),
+        };
+        let status = Paragraph::new(prompt).block(Block::default().borders(Borders::ALL));
+        frame.render_widget(status, outer[1]);
+    }
+}
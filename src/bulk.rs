@@ -0,0 +1,169 @@
+//! Filters and metadata operations for bulk updates
+
+use chrono::{DateTime, Utc};
+use glob::{MatchOptions, Pattern};
+
+use crate::core::{Metadata, Namespace, Node, NodeKind};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+
+/// `*` stays within a segment; `**` spans segments
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Selects the nodes a bulk update applies to
+///
+/// All given criteria must hold. Directories are never selected.
+#[derive(Debug, Clone, Default)]
+pub struct NodeFilter {
+    pattern: Option<Pattern>,
+    tags: Vec<String>,
+    kind: Option<NodeKind>,
+}
+
+impl NodeFilter {
+    /// Match pathways against a glob, e.g. `a3s://knowledge/docs/**`
+    pub fn pathway(glob: &str) -> Result<Self> {
+        let pattern = Pattern::new(glob).map_err(|e| {
+            A3SError::InvalidPathway(format!("invalid pathway glob {}: {}", glob, e))
+        })?;
+        Ok(Self {
+            pattern: Some(pattern),
+            ..Default::default()
+        })
+    }
+
+    /// Require a tag
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Require a node kind
+    pub fn kind(mut self, kind: NodeKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Whether a node is selected
+    pub fn matches(&self, node: &Node) -> bool {
+        !node.is_directory
+            && self.kind.is_none_or(|kind| node.kind == kind)
+            && self.tags.iter().all(|tag| node.metadata.tags.contains(tag))
+            && self.pattern.as_ref().is_none_or(|pattern| {
+                pattern.matches_with(&node.pathway.to_string(), MATCH_OPTIONS)
+            })
+    }
+
+    /// Subtrees that can hold matching nodes: the glob's literal prefix,
+    /// or every namespace when there is none
+    pub fn roots(&self) -> Vec<Pathway> {
+        let literal = self.pattern.as_ref().and_then(|pattern| {
+            let rest = pattern.as_str().strip_prefix("a3s://")?;
+            let segments: Vec<&str> = rest
+                .split('/')
+                .take_while(|segment| !segment.contains(['*', '?', '[']))
+                .collect();
+            Pathway::parse(&format!("a3s://{}", segments.join("/"))).ok()
+        });
+
+        match literal {
+            Some(root) => vec![root],
+            None => Namespace::ALL.into_iter().map(Pathway::root).collect(),
+        }
+    }
+}
+
+/// A change to node metadata
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataOp {
+    AddTag(String),
+    RemoveTag(String),
+    SetCustom(String, serde_json::Value),
+    RemoveCustom(String),
+    /// Set or clear the expiry time
+    SetExpiry(Option<DateTime<Utc>>),
+}
+
+impl MetadataOp {
+    /// Apply to metadata, returning whether anything changed
+    pub fn apply(&self, metadata: &mut Metadata) -> bool {
+        match self {
+            MetadataOp::AddTag(tag) => {
+                if metadata.tags.contains(tag) {
+                    return false;
+                }
+                metadata.tags.push(tag.clone());
+                true
+            }
+            MetadataOp::RemoveTag(tag) => {
+                let before = metadata.tags.len();
+                metadata.tags.retain(|t| t != tag);
+                metadata.tags.len() != before
+            }
+            MetadataOp::SetCustom(key, value) => {
+                metadata.custom.insert(key.clone(), value.clone()).as_ref() != Some(value)
+            }
+            MetadataOp::RemoveCustom(key) => metadata.custom.remove(key).is_some(),
+            MetadataOp::SetExpiry(at) => {
+                let changed = metadata.expires_at != *at;
+                metadata.expires_at = *at;
+                changed
+            }
+        }
+    }
+}
+
+/// Apply operations in order, returning whether any of them changed the metadata
+pub fn apply_all(ops: &[MetadataOp], metadata: &mut Metadata) -> bool {
+    let mut changed = false;
+    for op in ops {
+        changed |= op.apply(metadata);
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(pathway: &str, kind: NodeKind) -> Node {
+        Node::new(Pathway::parse(pathway).unwrap(), kind, String::new())
+    }
+
+    #[test]
+    fn test_filter_glob_tags_and_kind() {
+        let filter = NodeFilter::pathway("a3s://knowledge/docs/*.md")
+            .unwrap()
+            .kind(NodeKind::Markdown);
+        assert_eq!(filter.roots()[0].to_string(), "a3s://knowledge/docs");
+
+        assert!(filter.matches(&node("a3s://knowledge/docs/a.md", NodeKind::Markdown)));
+        assert!(!filter.matches(&node("a3s://knowledge/docs/sub/a.md", NodeKind::Markdown)));
+        assert!(!filter.matches(&node("a3s://knowledge/docs/a.md", NodeKind::Code)));
+
+        let tagged = NodeFilter::default().tag("ops");
+        assert_eq!(tagged.roots().len(), Namespace::ALL.len());
+        let mut ops_node = node("a3s://memory/u/x", NodeKind::Memory);
+        assert!(!tagged.matches(&ops_node));
+        ops_node.metadata.tags.push("ops".to_string());
+        assert!(tagged.matches(&ops_node));
+    }
+
+    #[test]
+    fn test_apply_reports_changes() {
+        let mut metadata = Metadata::default();
+        let ops = vec![
+            MetadataOp::AddTag("a".to_string()),
+            MetadataOp::SetCustom("owner".to_string(), serde_json::json!("ops")),
+        ];
+        assert!(apply_all(&ops, &mut metadata));
+        assert!(!apply_all(&ops, &mut metadata));
+
+        assert!(MetadataOp::RemoveTag("a".to_string()).apply(&mut metadata));
+        assert!(metadata.tags.is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+//! Versioned capability definitions
+//!
+//! A capability node records its semantic version, whether it is
+//! deprecated, and which runtimes it works with as a [`CapabilitySpec`]
+//! under [`CAPABILITY_KEY`]. Capability searches take a [`CapabilityFilter`]
+//! so agents only see definitions that satisfy their version requirement
+//! and environment, and never deprecated ones unless they ask for them.
+
+use std::collections::BTreeMap;
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::core::Node;
+use crate::pathway::Pathway;
+
+/// Metadata key on a capability node holding its [`CapabilitySpec`]
+pub const CAPABILITY_KEY: &str = "capability";
+
+/// Version, deprecation, and compatibility of a capability definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapabilitySpec {
+    pub version: Version,
+    #[serde(default)]
+    pub deprecated: bool,
+    /// Capability to use instead, when deprecated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<Pathway>,
+    /// Runtimes the capability works with and the versions of each it
+    /// supports, e.g. `python` → `>=3.10`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub compatibility: BTreeMap<String, VersionReq>,
+}
+
+impl CapabilitySpec {
+    pub fn new(version: Version) -> Self {
+        Self {
+            version,
+            deprecated: false,
+            replaced_by: None,
+            compatibility: BTreeMap::new(),
+        }
+    }
+
+    /// Require a runtime at a version matching `requirement`
+    pub fn compatible_with(mut self, runtime: &str, requirement: VersionReq) -> Self {
+        self.compatibility.insert(runtime.to_string(), requirement);
+        self
+    }
+
+    /// Spec recorded on a node, if it is a versioned capability
+    pub fn of(node: &Node) -> Option<Self> {
+        serde_json::from_value(node.metadata.custom.get(CAPABILITY_KEY)?.clone()).ok()
+    }
+}
+
+/// Which capability definitions a search may return
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CapabilityFilter {
+    /// Required version; unversioned capabilities never match one
+    pub version: Option<VersionReq>,
+    /// Also return deprecated capabilities
+    pub include_deprecated: bool,
+    /// Runtime versions the caller has; a capability that lists one of
+    /// these runtimes must support the given version
+    pub environment: BTreeMap<String, Version>,
+}
+
+impl CapabilityFilter {
+    /// Require a version matching `requirement`
+    pub fn version(mut self, requirement: VersionReq) -> Self {
+        self.version = Some(requirement);
+        self
+    }
+
+    /// Declare that the caller runs `runtime` at `version`
+    pub fn runtime(mut self, runtime: &str, version: Version) -> Self {
+        self.environment.insert(runtime.to_string(), version);
+        self
+    }
+
+    /// Whether a node passes the filter
+    pub fn matches(&self, node: &Node) -> bool {
+        let Some(spec) = CapabilitySpec::of(node) else {
+            return self.version.is_none();
+        };
+        (self.include_deprecated || !spec.deprecated)
+            && self
+                .version
+                .as_ref()
+                .is_none_or(|requirement| requirement.matches(&spec.version))
+            && self.environment.iter().all(|(runtime, version)| {
+                spec.compatibility
+                    .get(runtime)
+                    .is_none_or(|requirement| requirement.matches(version))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+
+    fn capability(spec: Option<CapabilitySpec>) -> Node {
+        let mut node = Node::new(
+            Pathway::parse("a3s://capability/tools/search").unwrap(),
+            NodeKind::Capability,
+            "Search the web".to_string(),
+        );
+        if let Some(spec) = spec {
+            node.metadata.custom.insert(
+                CAPABILITY_KEY.to_string(),
+                serde_json::to_value(spec).unwrap(),
+            );
+        }
+        node
+    }
+
+    #[test]
+    fn test_spec_roundtrip() {
+        let spec = CapabilitySpec::new(Version::new(2, 1, 0))
+            .compatible_with("python", VersionReq::parse(">=3.10").unwrap());
+        assert_eq!(
+            CapabilitySpec::of(&capability(Some(spec.clone()))),
+            Some(spec)
+        );
+        assert_eq!(CapabilitySpec::of(&capability(None)), None);
+    }
+
+    #[test]
+    fn test_filter_version_and_deprecation() {
+        let current = capability(Some(CapabilitySpec::new(Version::new(2, 1, 0))));
+        let mut old = CapabilitySpec::new(Version::new(1, 4, 0));
+        old.deprecated = true;
+        let old = capability(Some(old));
+        let unversioned = capability(None);
+
+        let any = CapabilityFilter::default();
+        assert!(any.matches(&current));
+        assert!(!any.matches(&old));
+        assert!(any.matches(&unversioned));
+
+        let v1 = CapabilityFilter {
+            include_deprecated: true,
+            ..Default::default()
+        }
+        .version(VersionReq::parse("^1").unwrap());
+        assert!(!v1.matches(&current));
+        assert!(v1.matches(&old));
+        assert!(!v1.matches(&unversioned));
+    }
+
+    #[test]
+    fn test_filter_environment() {
+        let node = capability(Some(
+            CapabilitySpec::new(Version::new(1, 0, 0))
+                .compatible_with("python", VersionReq::parse(">=3.10").unwrap()),
+        ));
+
+        let old_python = CapabilityFilter::default().runtime("python", Version::new(3, 8, 0));
+        assert!(!old_python.matches(&node));
+        let new_python = CapabilityFilter::default().runtime("python", Version::new(3, 12, 1));
+        assert!(new_python.matches(&node));
+        let node_runtime = CapabilityFilter::default().runtime("node", Version::new(18, 0, 0));
+        assert!(node_runtime.matches(&node));
+    }
+}
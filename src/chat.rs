@@ -0,0 +1,410 @@
+//! Team chat exports: Slack export zips and Discord channel JSON
+//!
+//! Exports are read into threads. A Slack thread is a message and its
+//! replies; a Discord thread is a message and the chain of replies
+//! referencing it. Each thread becomes one node.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Deserialize;
+use walkdir::WalkDir;
+
+use crate::error::{A3SError, Result};
+
+/// Chat export formats accepted by `ingest` as `slack:<path>` or `discord:<path>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatFormat {
+    /// Workspace export: a zip (or its extracted directory) of
+    /// `users.json` and `<channel>/<date>.json` files
+    Slack,
+    /// One channel exported by DiscordChatExporter as JSON
+    Discord,
+}
+
+impl ChatFormat {
+    /// Split a prefixed ingest source into its format and path
+    pub fn parse_source(source: &str) -> Option<(Self, &str)> {
+        if let Some(path) = source.strip_prefix("slack:") {
+            Some((ChatFormat::Slack, path))
+        } else {
+            source
+                .strip_prefix("discord:")
+                .map(|path| (ChatFormat::Discord, path))
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatFormat::Slack => "slack",
+            ChatFormat::Discord => "discord",
+        }
+    }
+
+    /// Read every thread in an export
+    pub fn read(&self, path: &Path) -> Result<Vec<ChatThread>> {
+        match self {
+            ChatFormat::Slack => read_slack(path),
+            ChatFormat::Discord => read_discord(path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub author: String,
+    pub at: DateTime<Utc>,
+    pub text: String,
+}
+
+/// A root message and its replies, oldest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatThread {
+    pub channel: String,
+    /// Identifier of the root message, unique within the channel
+    pub id: String,
+    pub messages: Vec<ChatMessage>,
+}
+
+impl ChatThread {
+    /// Authors in order of first message
+    pub fn participants(&self) -> Vec<String> {
+        let mut participants: Vec<String> = Vec::new();
+        for message in &self.messages {
+            if !participants.contains(&message.author) {
+                participants.push(message.author.clone());
+            }
+        }
+        participants
+    }
+
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.messages.first().map(|m| m.at)
+    }
+
+    /// Messages as `[time] author: text` lines
+    pub fn content(&self) -> String {
+        self.messages
+            .iter()
+            .map(|m| {
+                format!(
+                    "[{}] {}: {}",
+                    m.at.format("%Y-%m-%d %H:%M"),
+                    m.author,
+                    m.text
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Deserialize)]
+struct SlackUser {
+    id: String,
+    name: String,
+    #[serde(default)]
+    profile: SlackProfile,
+}
+
+#[derive(Default, Deserialize)]
+struct SlackProfile {
+    #[serde(default)]
+    display_name: String,
+    #[serde(default)]
+    real_name: String,
+}
+
+#[derive(Deserialize)]
+struct SlackMessage {
+    user: Option<String>,
+    /// Author of bot messages
+    username: Option<String>,
+    #[serde(default)]
+    text: String,
+    ts: String,
+    thread_ts: Option<String>,
+    subtype: Option<String>,
+}
+
+/// Read a Slack export zip or extracted export directory
+pub fn read_slack(path: &Path) -> Result<Vec<ChatThread>> {
+    let files = if path.is_dir() {
+        read_dir_files(path)?
+    } else {
+        read_zip_files(path)?
+    };
+
+    let mut names: HashMap<String, String> = HashMap::new();
+    if let Some((_, users)) = files.iter().find(|(name, _)| name == "users.json") {
+        let users: Vec<SlackUser> = serde_json::from_str(users)?;
+        for user in users {
+            let name = [user.profile.display_name, user.profile.real_name]
+                .into_iter()
+                .find(|n| !n.is_empty())
+                .unwrap_or(user.name);
+            names.insert(user.id, name);
+        }
+    }
+
+    // (channel, thread ts) -> messages
+    let mut threads: HashMap<(String, String), Vec<(f64, ChatMessage)>> = HashMap::new();
+    for (name, contents) in &files {
+        let Some((channel, day)) = name.split_once('/') else {
+            continue;
+        };
+        if !day.ends_with(".json") || day.contains('/') {
+            continue;
+        }
+        let messages: Vec<SlackMessage> = serde_json::from_str(contents)
+            .map_err(|e| A3SError::Ingest(format!("{}: {}", name, e)))?;
+
+        for message in messages {
+            // Joins, leaves, topic changes, and the like
+            if message
+                .subtype
+                .as_deref()
+                .is_some_and(|s| s.starts_with("channel_"))
+                || message.text.is_empty()
+            {
+                continue;
+            }
+            let ts: f64 = message.ts.parse().unwrap_or_default();
+            let author = message
+                .user
+                .as_ref()
+                .and_then(|id| names.get(id).cloned())
+                .or(message.username)
+                .or(message.user)
+                .unwrap_or_else(|| "unknown".to_string());
+            let thread = message.thread_ts.unwrap_or(message.ts);
+
+            threads
+                .entry((channel.to_string(), thread))
+                .or_default()
+                .push((
+                    ts,
+                    ChatMessage {
+                        author,
+                        at: from_unix(ts),
+                        text: resolve_mentions(&message.text, &names),
+                    },
+                ));
+        }
+    }
+
+    let mut threads: Vec<ChatThread> = threads
+        .into_iter()
+        .map(|((channel, id), mut messages)| {
+            messages.sort_by(|a, b| a.0.total_cmp(&b.0));
+            ChatThread {
+                channel,
+                id,
+                messages: messages.into_iter().map(|(_, m)| m).collect(),
+            }
+        })
+        .collect();
+    threads.sort_by(|a, b| (&a.channel, &a.id).cmp(&(&b.channel, &b.id)));
+    Ok(threads)
+}
+
+fn read_dir_files(root: &Path) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(root).max_depth(2) {
+        let entry = entry.map_err(|e| A3SError::Ingest(format!("Walk error: {}", e)))?;
+        let is_json = entry.path().extension().is_some_and(|ext| ext == "json");
+        if !entry.file_type().is_file() || !is_json {
+            continue;
+        }
+        let name = entry
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        files.push((name, std::fs::read_to_string(entry.path())?));
+    }
+    Ok(files)
+}
+
+fn read_zip_files(path: &Path) -> Result<Vec<(String, String)>> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)
+        .map_err(|e| A3SError::Ingest(format!("Invalid Slack export {}: {}", path.display(), e)))?;
+
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|e| A3SError::Ingest(format!("Invalid Slack export entry: {}", e)))?;
+        if !file.is_file() || !file.name().ends_with(".json") {
+            continue;
+        }
+        let name = file.name().to_string();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        files.push((name, contents));
+    }
+    Ok(files)
+}
+
+/// Replace `<@U123>` and `<@U123|name>` with `@display name`
+fn resolve_mentions(text: &str, names: &HashMap<String, String>) -> String {
+    static MENTION: OnceLock<Regex> = OnceLock::new();
+    let mention = MENTION.get_or_init(|| Regex::new(r"<@([A-Z0-9]+)(?:\|[^>]*)?>").unwrap());
+    mention
+        .replace_all(text, |caps: &regex::Captures| {
+            let id = &caps[1];
+            format!("@{}", names.get(id).map_or(id, String::as_str))
+        })
+        .into_owned()
+}
+
+fn from_unix(ts: f64) -> DateTime<Utc> {
+    let secs = ts.trunc() as i64;
+    let nanos = (ts.fract() * 1e9) as u32;
+    DateTime::from_timestamp(secs, nanos).unwrap_or_default()
+}
+
+#[derive(Deserialize)]
+struct DiscordExport {
+    channel: DiscordChannel,
+    messages: Vec<DiscordMessage>,
+}
+
+#[derive(Deserialize)]
+struct DiscordChannel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct DiscordMessage {
+    id: String,
+    timestamp: DateTime<Utc>,
+    #[serde(default)]
+    content: String,
+    author: DiscordAuthor,
+    reference: Option<DiscordReference>,
+}
+
+#[derive(Deserialize)]
+struct DiscordAuthor {
+    name: String,
+    nickname: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscordReference {
+    message_id: Option<String>,
+}
+
+/// Read a DiscordChatExporter JSON export of one channel
+pub fn read_discord(path: &Path) -> Result<Vec<ChatThread>> {
+    let export: DiscordExport =
+        serde_json::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+            A3SError::Ingest(format!("Invalid Discord export {}: {}", path.display(), e))
+        })?;
+
+    // message id -> index of its thread
+    let mut thread_of: HashMap<String, usize> = HashMap::new();
+    let mut threads: Vec<ChatThread> = Vec::new();
+    for message in export.messages {
+        let replied_to = message
+            .reference
+            .and_then(|r| r.message_id)
+            .and_then(|id| thread_of.get(&id).copied());
+        let index = match replied_to {
+            Some(index) => index,
+            None => {
+                threads.push(ChatThread {
+                    channel: export.channel.name.clone(),
+                    id: message.id.clone(),
+                    messages: Vec::new(),
+                });
+                threads.len() - 1
+            }
+        };
+        thread_of.insert(message.id, index);
+
+        if message.content.is_empty() {
+            continue;
+        }
+        threads[index].messages.push(ChatMessage {
+            author: message.author.nickname.unwrap_or(message.author.name),
+            at: message.timestamp,
+            text: message.content,
+        });
+    }
+
+    threads.retain(|thread| !thread.messages.is_empty());
+    Ok(threads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_slack_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("users.json"),
+            r#"[{"id": "U1", "name": "ann", "profile": {"display_name": "Ann"}},
+                {"id": "U2", "name": "bo"}]"#,
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("ops")).unwrap();
+        std::fs::write(
+            dir.path().join("ops/2024-05-01.json"),
+            r#"[
+                {"type": "message", "user": "U1", "text": "Deploy failed <@U2>", "ts": "1714557600.000100"},
+                {"type": "message", "user": "U2", "text": "Rolling back", "ts": "1714557660.000200", "thread_ts": "1714557600.000100"},
+                {"type": "message", "subtype": "channel_join", "user": "U2", "text": "joined", "ts": "1714557000.000000"}
+            ]"#,
+        )
+        .unwrap();
+
+        let threads = read_slack(dir.path()).unwrap();
+        assert_eq!(threads.len(), 1);
+        let thread = &threads[0];
+        assert_eq!(thread.channel, "ops");
+        assert_eq!(thread.id, "1714557600.000100");
+        assert_eq!(thread.participants(), vec!["Ann", "bo"]);
+        assert_eq!(
+            thread.content(),
+            "[2024-05-01 10:00] Ann: Deploy failed @bo\n[2024-05-01 10:01] bo: Rolling back"
+        );
+    }
+
+    #[test]
+    fn test_read_discord_reply_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("channel.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "guild": {"name": "A3S"},
+                "channel": {"name": "support"},
+                "messages": [
+                    {"id": "1", "timestamp": "2024-05-01T10:00:00+00:00", "content": "Login broken?", "author": {"name": "ann"}},
+                    {"id": "2", "timestamp": "2024-05-01T10:05:00+00:00", "content": "Unrelated", "author": {"name": "cy"}},
+                    {"id": "3", "timestamp": "2024-05-01T10:06:00+00:00", "content": "Fixed now", "author": {"name": "bo", "nickname": "Bo"}, "reference": {"messageId": "1"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let threads = read_discord(&path).unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].id, "1");
+        assert_eq!(threads[0].participants(), vec!["ann", "Bo"]);
+        assert_eq!(
+            ChatFormat::parse_source("discord:./channel.json"),
+            Some((ChatFormat::Discord, "./channel.json"))
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! Splitting long content into overlapping chunks
+//!
+//! With `ingest.chunking` enabled, a node whose content is longer than
+//! `ingest.chunk_size` characters keeps its full content and gets one child
+//! per chunk, named `chunk-0001`, `chunk-0002`, and so on. Each chunk
+//! records its parent and position, so a retrieved chunk can be expanded
+//! into its surrounding context. The parent's digest outlines its chunks
+//! rather than summarizing only the start of the document.
+//!
+//! Sizes are in characters, or with `ingest.chunk_unit: tokens` in tokens
+//! of the client's [`crate::tokenizer::Tokenizer`], so chunks fit the
+//! context of the model that reads them.
+//!
+//! With `ingest.chunk_strategy` set to `markdown`, Markdown nodes are split
+//! at headings instead of at fixed sizes: a chunk never spans two sections
+//! or cuts through a code fence, and consecutive blocks of one section are
+//! packed up to `ingest.chunk_size`. Only a single block longer than that,
+//! such as a long code fence, is split by size.
+//!
+//! With the `code-chunking` feature, code nodes are split between function,
+//! struct, class, and impl definitions by [`crate::syntax`], so a retrieved
+//! chunk holds whole definitions and names them.
+//!
+//! With `ingest.chunk_headers` on, each chunk is embedded with a context
+//! line naming its file and Markdown section, e.g. `File guide.md, section
+//! Deploys > Rollback: `, so fragments that lose meaning in isolation still
+//! match queries about their document. The stored content is the bare chunk.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ChunkStrategy;
+use crate::core::{Node, NodeKind};
+use crate::language::CODE_LANGUAGE_KEY;
+use crate::pathway::Pathway;
+use crate::syntax;
+use crate::tokenizer::Tokenizer;
+
+/// Metadata key on a chunk holding its [`ChunkInfo`]
+pub const CHUNK_KEY: &str = "chunk";
+
+/// Metadata key on a chunked node holding its number of chunks
+pub const CHUNK_COUNT_KEY: &str = "chunk_count";
+
+/// A span of content
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Position among the parent's chunks, from 0
+    pub index: usize,
+    /// Byte offsets into the parent's content
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// Names of the definitions in a code chunk
+    pub symbols: Vec<String>,
+}
+
+/// Where a chunk node sits in its parent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    pub parent: Pathway,
+    pub index: usize,
+    pub start: usize,
+    pub end: usize,
+    /// Context line prepended to the chunk's embedded text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+}
+
+/// Outcome of re-deriving chunks from stored content
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RechunkResult {
+    /// Nodes whose content was re-split
+    pub nodes: usize,
+    /// Chunks written with a new embedding
+    pub embedded: usize,
+    /// Chunks whose text and header were unchanged
+    pub unchanged: usize,
+    /// Chunks left over from a longer split and removed
+    pub removed: usize,
+}
+
+impl ChunkInfo {
+    /// Chunk position recorded on a node, if it is a chunk
+    pub fn of(node: &Node) -> Option<Self> {
+        serde_json::from_value(node.metadata.custom.get(CHUNK_KEY)?.clone()).ok()
+    }
+}
+
+/// Number of chunks a node was split into
+pub fn chunk_count(node: &Node) -> usize {
+    node.metadata
+        .custom
+        .get(CHUNK_COUNT_KEY)
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as usize
+}
+
+/// Pathway of a node's chunk at `index`
+pub fn chunk_pathway(parent: &Pathway, index: usize) -> Pathway {
+    parent.join(&format!("chunk-{:04}", index + 1))
+}
+
+/// Split content longer than `size` into windows of at most `size`, each
+/// starting `overlap` before the previous one ended, with sizes counted by
+/// `measure`, e.g. [`CharTokenizer`](crate::tokenizer::CharTokenizer) for
+/// characters
+///
+/// Windows end at the last paragraph break, line break, sentence end, or
+/// space in their second half when there is one. Content that fits in one
+/// window is not split.
+pub fn split(content: &str, size: usize, overlap: usize, measure: &dyn Tokenizer) -> Vec<Chunk> {
+    let size = size.max(1);
+    let overlap = overlap.min(size - 1);
+    if measure.count(content) <= size {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let rest = &content[start..];
+        let limit = match measure.truncate(rest, size).len() {
+            // A single character longer than a window still makes progress
+            0 => rest.chars().next().map_or(rest.len(), char::len_utf8),
+            limit => limit,
+        };
+        let end = if limit == rest.len() {
+            content.len()
+        } else {
+            start + break_point(&rest[..limit])
+        };
+        chunks.push(Chunk {
+            index: chunks.len(),
+            start,
+            end,
+            text: content[start..end].to_string(),
+            symbols: Vec::new(),
+        });
+        if end == content.len() {
+            return chunks;
+        }
+
+        let next = end - measure.truncate_start(&content[start..end], overlap).len();
+        start = if next > start { next } else { end };
+    }
+}
+
+/// Split a node's content with `strategy`, which for `Markdown` only
+/// applies to Markdown nodes
+///
+/// Code in a language [`syntax::split_code`] parses is split between its
+/// definitions whatever the strategy.
+pub fn split_node(
+    node: &Node,
+    strategy: ChunkStrategy,
+    size: usize,
+    overlap: usize,
+    measure: &dyn Tokenizer,
+) -> Vec<Chunk> {
+    if node.kind == NodeKind::Code {
+        let language = node
+            .metadata
+            .custom
+            .get(CODE_LANGUAGE_KEY)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        if let Some(chunks) = syntax::split_code(&node.content, language, size, overlap, measure) {
+            return chunks;
+        }
+    }
+    match strategy {
+        ChunkStrategy::Markdown if node.kind == NodeKind::Markdown => {
+            split_markdown(&node.content, size, overlap, measure)
+        }
+        _ => split(&node.content, size, overlap, measure),
+    }
+}
+
+/// Split Markdown longer than `size` at headings, packing the blocks of
+/// each section into chunks of at most `size`, as counted by `measure`
+///
+/// Blocks are paragraphs, headings, and whole code fences. A block longer
+/// than `size` is split with [`split`] and `overlap`; chunks do not
+/// otherwise overlap.
+pub fn split_markdown(
+    content: &str,
+    size: usize,
+    overlap: usize,
+    measure: &dyn Tokenizer,
+) -> Vec<Chunk> {
+    let size = size.max(1);
+    if measure.count(content) <= size {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    let push = |chunks: &mut Vec<Chunk>, start: usize, end: usize| {
+        chunks.push(Chunk {
+            index: chunks.len(),
+            start,
+            end,
+            text: content[start..end].to_string(),
+            symbols: Vec::new(),
+        });
+    };
+
+    // Start and end of the pending chunk, and whether it has more than headings
+    let mut pending: Option<(usize, usize, bool)> = None;
+    for (start, end, heading) in markdown_blocks(content) {
+        if let Some((from, to, body)) = pending {
+            if (heading && body) || measure.count(&content[from..end]) > size {
+                push(&mut chunks, from, to);
+                pending = None;
+            }
+        }
+        if pending.is_none() && measure.count(&content[start..end]) > size {
+            for piece in split(&content[start..end], size, overlap, measure) {
+                push(&mut chunks, start + piece.start, start + piece.end);
+            }
+            continue;
+        }
+        let (from, _, body) = pending.unwrap_or((start, start, false));
+        pending = Some((from, end, body || !heading));
+    }
+    if let Some((from, to, _)) = pending {
+        push(&mut chunks, from, to);
+    }
+    chunks
+}
+
+/// Byte spans of the headings, paragraphs, and code fences of Markdown,
+/// each with its trailing blank lines, and whether it is a heading
+///
+/// The spans cover the whole content.
+fn markdown_blocks(content: &str) -> Vec<(usize, usize, bool)> {
+    let mut blocks: Vec<(usize, usize, bool)> = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut in_paragraph = false;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start();
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.trim_end().is_empty() {
+            in_paragraph = false;
+        } else if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            in_paragraph = false;
+            blocks.push((start, offset, false));
+            continue;
+        } else if heading(trimmed).is_some() {
+            in_paragraph = false;
+            blocks.push((start, offset, true));
+            continue;
+        } else if !in_paragraph {
+            in_paragraph = true;
+            blocks.push((start, offset, false));
+            continue;
+        }
+
+        match blocks.last_mut() {
+            Some(block) => block.1 = offset,
+            None => blocks.push((start, offset, false)),
+        }
+    }
+    blocks
+}
+
+/// Level and title of a Markdown heading line
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 || !line[level..].starts_with(' ') {
+        return None;
+    }
+    let title = line[level..].trim().trim_end_matches('#').trim();
+    (!title.is_empty()).then_some((level, title))
+}
+
+/// Context line for a chunk of `content` from the file or node `name`
+///
+/// The section is the trail of Markdown headings in effect where the chunk
+/// starts, including a heading on its first line; code has no sections.
+pub fn context_header(name: &str, content: &str, chunk: &Chunk, markdown: bool) -> String {
+    let first_line = chunk.text.find('\n').map_or(chunk.end, |n| chunk.start + n);
+    let section = if markdown {
+        section_trail(&content[..first_line])
+    } else {
+        Vec::new()
+    };
+
+    if section.is_empty() {
+        format!("File {}: ", name)
+    } else {
+        format!("File {}, section {}: ", name, section.join(" > "))
+    }
+}
+
+/// Headings enclosing the end of `markdown`, outermost first
+fn section_trail(markdown: &str) -> Vec<String> {
+    let mut trail: Vec<(usize, String)> = Vec::new();
+    let mut in_fence = false;
+    for line in markdown.lines() {
+        let line = line.trim_start();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        let Some((level, title)) = heading(line).filter(|_| !in_fence) else {
+            continue;
+        };
+        trail.retain(|(outer, _)| *outer < level);
+        trail.push((level, title.to_string()));
+    }
+    trail.into_iter().map(|(_, title)| title).collect()
+}
+
+/// Byte length of the window up to its preferred break
+fn break_point(window: &str) -> usize {
+    let min = window.len() / 2;
+    for separator in ["\n\n", "\n", ". ", " "] {
+        if let Some(at) = window.rfind(separator) {
+            if at + separator.len() > min {
+                return at + separator.len();
+            }
+        }
+    }
+    window.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::CharTokenizer;
+
+    #[test]
+    fn test_short_content_is_not_split() {
+        assert!(split("short", 10, 2, &CharTokenizer).is_empty());
+    }
+
+    #[test]
+    fn test_split_prefers_breaks_and_overlaps() {
+        let content = "First paragraph here.\n\nSecond one is a bit longer. It ends here.";
+        let chunks = split(content, 30, 5, &CharTokenizer);
+
+        assert_eq!(chunks[0].text, "First paragraph here.\n\n");
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 30));
+        for pair in chunks.windows(2) {
+            assert!(pair[1].start < pair[0].end);
+            assert!(pair[1].start > pair[0].start);
+        }
+        assert_eq!(chunks.last().unwrap().end, content.len());
+        assert_eq!(
+            chunks.iter().map(|c| c.index).collect::<Vec<_>>(),
+            (0..chunks.len()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_split_multibyte_without_spaces() {
+        let content = "日本語のテキスト".repeat(5);
+        let chunks = split(&content, 12, 3, &CharTokenizer);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 12));
+        assert_eq!(chunks.last().unwrap().end, content.len());
+    }
+
+    #[test]
+    fn test_split_markdown_by_sections() {
+        let content = "# Deploys\n\nRun the pipeline.\n\n## Rollback\n\n\
+                       ```sh\ngit revert HEAD\n\ngit push\n```\n\
+                       Then redeploy.\n\n## Monitoring\n\nWatch the dashboards.\n";
+        let texts: Vec<_> = split_markdown(content, 70, 0, &CharTokenizer)
+            .into_iter()
+            .map(|c| c.text)
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "# Deploys\n\nRun the pipeline.\n\n",
+                "## Rollback\n\n```sh\ngit revert HEAD\n\ngit push\n```\nThen redeploy.\n\n",
+                "## Monitoring\n\nWatch the dashboards.\n",
+            ]
+        );
+        assert!(split_markdown(content, 1000, 0, &CharTokenizer).is_empty());
+    }
+
+    #[test]
+    fn test_split_markdown_long_blocks() {
+        let paragraph = "Tokens expire hourly and refresh on use.";
+        let content = format!(
+            "# Auth\n\n{}\n\n{}\n\n{}",
+            paragraph,
+            paragraph,
+            "x ".repeat(60)
+        );
+        let chunks = split_markdown(&content, 50, 5, &CharTokenizer);
+
+        assert_eq!(chunks[0].text, format!("# Auth\n\n{}\n\n", paragraph));
+        assert_eq!(chunks[1].text, format!("{}\n\n", paragraph));
+        assert!(chunks[2..].iter().all(|c| c.text.chars().count() <= 50));
+        assert_eq!(chunks.last().unwrap().end, content.len());
+        assert_eq!(
+            chunks.iter().map(|c| c.index).collect::<Vec<_>>(),
+            (0..chunks.len()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_chunk_info_round_trip() {
+        let parent = Pathway::parse("a3s://knowledge/docs/guide.md").unwrap();
+        let pathway = chunk_pathway(&parent, 0);
+        assert_eq!(
+            pathway.to_string(),
+            "a3s://knowledge/docs/guide.md/chunk-0001"
+        );
+
+        let info = ChunkInfo {
+            parent,
+            index: 0,
+            start: 0,
+            end: 10,
+            header: None,
+        };
+        let mut node = Node::new(pathway, crate::core::NodeKind::Markdown, String::new());
+        node.metadata
+            .custom
+            .insert(CHUNK_KEY.to_string(), serde_json::to_value(&info).unwrap());
+        assert_eq!(ChunkInfo::of(&node), Some(info));
+        assert_eq!(chunk_count(&node), 0);
+    }
+
+    #[test]
+    fn test_context_header_sections() {
+        let content = "# Deploys\n\nIntro.\n\n## Rollback\n\n```sh\n# not a heading\n```\n\
+                       Revert the tag.\n\n# Monitoring\n\nDashboards.";
+        let at = |text: &str| {
+            let start = content.find(text).unwrap();
+            Chunk {
+                index: 0,
+                start,
+                end: start + text.len(),
+                text: text.to_string(),
+                symbols: Vec::new(),
+            }
+        };
+
+        assert_eq!(
+            context_header("guide.md", content, &at("Revert the tag."), true),
+            "File guide.md, section Deploys > Rollback: "
+        );
+        assert_eq!(
+            context_header(
+                "guide.md",
+                content,
+                &at("# Monitoring\n\nDashboards."),
+                true
+            ),
+            "File guide.md, section Monitoring: "
+        );
+        assert_eq!(
+            context_header("deploy.rs", content, &at("Revert the tag."), false),
+            "File deploy.rs: "
+        );
+    }
+}
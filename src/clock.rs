@@ -0,0 +1,24 @@
+//! Source of the current time
+//!
+//! The client reads the time from a [`Clock`] wherever it stamps a write
+//! or judges one: node timestamps, expiry, match age and staleness, view
+//! refreshes, lease expiry, and session messages and idle eviction.
+//! [`SystemClock`] is the default; tests swap in
+//! [`crate::testing::TestClock`] with [`crate::A3SClient::with_clock`].
+
+use chrono::{DateTime, Utc};
+
+/// Reports the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall-clock time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -0,0 +1,292 @@
+//! Topic maps: a namespace's nodes clustered by embedding
+//!
+//! [`crate::A3SClient::cluster`] groups the embedded nodes of a namespace
+//! with spherical k-means: each node joins the centroid it is most similar
+//! to, and each centroid is the normalized mean of its members. Centroids
+//! start from a farthest-first pick, so the same store always gives the
+//! same map. A cluster is labelled with the brief of the node nearest its
+//! centroid, or by the LLM when one is configured, so agents and people
+//! can see at a glance what a store holds. Chunks are left out, since
+//! their documents stand for them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::ChunkInfo;
+use crate::core::{Namespace, Node};
+use crate::error::Result;
+use crate::pathway::Pathway;
+use crate::retrieval::cosine_similarity;
+use crate::storage::StorageBackend;
+
+/// Most k-means rounds before the assignment is taken as it stands
+const MAX_ITERATIONS: usize = 50;
+
+/// Members listed as a cluster's representatives
+const REPRESENTATIVES: usize = 5;
+
+/// Clusters of a namespace's nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicMap {
+    pub namespace: Namespace,
+    /// Embedded, non-chunk nodes clustered
+    pub nodes_clustered: usize,
+    /// Largest first
+    pub topics: Vec<Topic>,
+}
+
+/// One cluster of similar nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Topic {
+    pub label: String,
+    /// Members nearest the centroid, nearest first
+    pub representatives: Vec<TopicMember>,
+    /// Every member, nearest the centroid first
+    pub members: Vec<Pathway>,
+    /// Mean similarity of the members to the centroid
+    pub cohesion: f32,
+}
+
+/// A node in a [`Topic`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicMember {
+    pub pathway: Pathway,
+    pub brief: String,
+    /// Similarity to the cluster's centroid
+    pub similarity: f32,
+}
+
+/// Cluster the embedded nodes of `namespace` into at most `k` topics
+pub async fn cluster(
+    storage: &dyn StorageBackend,
+    namespace: Namespace,
+    k: usize,
+) -> Result<TopicMap> {
+    let root = Pathway::root(namespace);
+    storage.warm(&root).await?;
+    let mut nodes: Vec<Node> = storage
+        .get_children(&root, usize::MAX)
+        .await?
+        .into_iter()
+        .filter(|node| {
+            !node.is_directory && !node.embedding.is_empty() && ChunkInfo::of(node).is_none()
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+    // Vectors from another model cannot be compared with the rest
+    if let Some(dimension) = nodes.first().map(|node| node.embedding.len()) {
+        nodes.retain(|node| node.embedding.len() == dimension);
+    }
+
+    let vectors: Vec<Vec<f32>> = nodes
+        .iter()
+        .map(|node| normalized(&node.embedding))
+        .collect();
+    let (assignments, centroids) = kmeans(&vectors, k);
+
+    let mut groups: Vec<Vec<TopicMember>> = vec![Vec::new(); centroids.len()];
+    for ((node, vector), &cluster) in nodes.iter().zip(&vectors).zip(&assignments) {
+        groups[cluster].push(TopicMember {
+            pathway: node.pathway.clone(),
+            brief: node.digest.brief.clone(),
+            similarity: cosine_similarity(vector, &centroids[cluster]),
+        });
+    }
+
+    let mut topics: Vec<Topic> = groups
+        .into_iter()
+        .filter(|members| !members.is_empty())
+        .map(|mut members| {
+            members.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+            let cohesion = members.iter().map(|m| m.similarity).sum::<f32>() / members.len() as f32;
+            let label = members
+                .iter()
+                .map(|m| m.brief.trim())
+                .find(|brief| !brief.is_empty())
+                .or_else(|| members[0].pathway.name())
+                .unwrap_or_default()
+                .to_string();
+            Topic {
+                label,
+                members: members.iter().map(|m| m.pathway.clone()).collect(),
+                representatives: members.into_iter().take(REPRESENTATIVES).collect(),
+                cohesion,
+            }
+        })
+        .collect();
+    topics.sort_by_key(|topic| std::cmp::Reverse(topic.members.len()));
+
+    Ok(TopicMap {
+        namespace,
+        nodes_clustered: nodes.len(),
+        topics,
+    })
+}
+
+/// Prompt asking the LLM to name the topic a cluster's representatives share
+pub fn label_prompt(topic: &Topic) -> String {
+    let briefs: String = topic
+        .representatives
+        .iter()
+        .map(|m| format!("- {}\n", m.brief.trim()))
+        .collect();
+    format!(
+        "These descriptions come from one group of related documents. Name \
+         the topic they share in at most six words. Answer with the name \
+         only.\n\n{}",
+        briefs
+    )
+}
+
+/// Spherical k-means over unit vectors, returning each vector's cluster
+/// and the centroids; `k` is clamped to the number of vectors
+pub fn kmeans(vectors: &[Vec<f32>], k: usize) -> (Vec<usize>, Vec<Vec<f32>>) {
+    let k = k.clamp(1, vectors.len().max(1));
+    if vectors.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+
+    // Farthest-first: start from the first vector, then repeatedly take the
+    // one least similar to every centroid so far
+    let mut centroids = vec![vectors[0].clone()];
+    let mut nearest: Vec<f32> = vectors
+        .iter()
+        .map(|v| cosine_similarity(v, &centroids[0]))
+        .collect();
+    while centroids.len() < k {
+        let (next, _) = nearest
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .expect("vectors is not empty");
+        centroids.push(vectors[next].clone());
+        let added = centroids.last().expect("just pushed");
+        for (similarity, v) in nearest.iter_mut().zip(vectors) {
+            *similarity = similarity.max(cosine_similarity(v, added));
+        }
+    }
+
+    let mut assignments = vec![usize::MAX; vectors.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (assignment, v) in assignments.iter_mut().zip(vectors) {
+            let best = closest(v, &centroids);
+            if *assignment != best {
+                *assignment = best;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let dimension = vectors[0].len();
+        let mut sums = vec![vec![0.0f32; dimension]; k];
+        for (&cluster, v) in assignments.iter().zip(vectors) {
+            for (sum, x) in sums[cluster].iter_mut().zip(v) {
+                *sum += x;
+            }
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(sums) {
+            // An emptied cluster keeps its centroid
+            if sum.iter().any(|x| *x != 0.0) {
+                *centroid = normalized(&sum);
+            }
+        }
+    }
+    (assignments, centroids)
+}
+
+fn closest(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .map(|c| cosine_similarity(vector, c))
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+pub(crate) fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::digest::Digest;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn test_kmeans_separates_groups() {
+        let vectors: Vec<Vec<f32>> = [
+            [1.0, 0.1, 0.0],
+            [0.9, 0.0, 0.1],
+            [0.0, 1.0, 0.1],
+            [0.1, 0.9, 0.0],
+            [0.0, 0.1, 1.0],
+        ]
+        .iter()
+        .map(|v| normalized(v))
+        .collect();
+
+        let (assignments, centroids) = kmeans(&vectors, 3);
+        assert_eq!(centroids.len(), 3);
+        assert_eq!(assignments[0], assignments[1]);
+        assert_eq!(assignments[2], assignments[3]);
+        assert_ne!(assignments[0], assignments[2]);
+        assert_ne!(assignments[4], assignments[0]);
+        assert_ne!(assignments[4], assignments[2]);
+
+        // More clusters than vectors
+        let (assignments, centroids) = kmeans(&vectors[..2], 10);
+        assert_eq!(centroids.len(), 2);
+        assert_ne!(assignments[0], assignments[1]);
+        assert!(kmeans(&[], 3).0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cluster_labels_topics() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        for (pathway, brief, embedding) in [
+            (
+                "a3s://knowledge/deploy/nightly",
+                "Nightly deploys",
+                [1.0, 0.0],
+            ),
+            (
+                "a3s://knowledge/deploy/rollback",
+                "Rolling back",
+                [0.9, 0.1],
+            ),
+            (
+                "a3s://knowledge/billing/invoices",
+                "Monthly invoices",
+                [0.0, 1.0],
+            ),
+        ] {
+            let mut node = Node::new(
+                Pathway::parse(pathway).unwrap(),
+                NodeKind::Document,
+                brief.to_string(),
+            );
+            node.digest = Digest::with_content(brief.to_string(), brief.to_string());
+            node.embedding = embedding.to_vec();
+            storage.put(&node).await.unwrap();
+        }
+
+        let map = cluster(&storage, Namespace::Knowledge, 2).await.unwrap();
+        assert_eq!(map.nodes_clustered, 3);
+        assert_eq!(map.topics.len(), 2);
+        assert_eq!(map.topics[0].members.len(), 2);
+        assert!(["Nightly deploys", "Rolling back"].contains(&map.topics[0].label.as_str()));
+        assert_eq!(map.topics[1].label, "Monthly invoices");
+        assert!(map.topics[1].cohesion > 0.99);
+        assert!(label_prompt(&map.topics[0]).contains("- Rolling back"));
+    }
+}
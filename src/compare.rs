@@ -0,0 +1,185 @@
+//! Comparing retrieval configurations on the same query
+//!
+//! `Config.retrieval_profiles` names alternative retrieval configurations;
+//! `default` always refers to `Config.retrieval`. Running a query against
+//! two profiles shows how a change would reorder results before rollout.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::pathway::Pathway;
+use crate::{MatchedNode, QueryResult};
+
+/// Name of the profile backed by `Config.retrieval`
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Persistence of the rank-biased overlap: how much weight stays on deeper
+/// ranks
+const RBO_PERSISTENCE: f32 = 0.9;
+
+/// A query's results under two retrieval profiles
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryComparison {
+    pub query: String,
+    pub a: ProfileResult,
+    pub b: ProfileResult,
+    pub overlap: Overlap,
+}
+
+/// Results under one profile
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileResult {
+    pub profile: String,
+    pub result: QueryResult,
+}
+
+/// How much two ranked result lists agree
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Overlap {
+    /// Pathways in both lists
+    pub shared: usize,
+    /// Shared pathways over pathways in either list
+    pub jaccard: f32,
+    /// Both lists rank the same pathway first
+    pub same_top: bool,
+    /// Rank-biased overlap, weighting agreement near the top most; 1 for
+    /// identical rankings
+    pub rank_biased_overlap: f32,
+    /// Pathways only the first profile returned
+    pub only_a: Vec<Pathway>,
+    /// Pathways only the second profile returned
+    pub only_b: Vec<Pathway>,
+}
+
+impl Overlap {
+    /// Compare two ranked match lists
+    pub fn between(a: &[MatchedNode], b: &[MatchedNode]) -> Self {
+        let a: Vec<&Pathway> = a.iter().map(|m| &m.pathway).collect();
+        let b: Vec<&Pathway> = b.iter().map(|m| &m.pathway).collect();
+        let set_a: HashSet<_> = a.iter().copied().collect();
+        let set_b: HashSet<_> = b.iter().copied().collect();
+
+        let shared = set_a.intersection(&set_b).count();
+        let union = set_a.union(&set_b).count();
+        Self {
+            shared,
+            jaccard: if union == 0 {
+                1.0
+            } else {
+                shared as f32 / union as f32
+            },
+            same_top: a.first() == b.first(),
+            rank_biased_overlap: rank_biased_overlap(&a, &b, RBO_PERSISTENCE),
+            only_a: a
+                .iter()
+                .filter(|p| !set_b.contains(*p))
+                .map(|p| (*p).clone())
+                .collect(),
+            only_b: b
+                .iter()
+                .filter(|p| !set_a.contains(*p))
+                .map(|p| (*p).clone())
+                .collect(),
+        }
+    }
+}
+
+/// Extrapolated rank-biased overlap (Webber et al., 2010) of two rankings
+fn rank_biased_overlap(a: &[&Pathway], b: &[&Pathway], p: f32) -> f32 {
+    let depth = a.len().max(b.len());
+    if depth == 0 {
+        return 1.0;
+    }
+
+    let mut seen_a = HashSet::new();
+    let mut seen_b = HashSet::new();
+    let mut agreement = 0usize;
+    let mut sum = 0.0;
+    let mut weight = 1.0;
+    for d in 1..=depth {
+        let x = a.get(d - 1);
+        let y = b.get(d - 1);
+        if let Some(x) = x {
+            if seen_b.contains(x) {
+                agreement += 1;
+            }
+            seen_a.insert(*x);
+        }
+        if let Some(y) = y {
+            if seen_a.contains(y) {
+                agreement += 1;
+            }
+            seen_b.insert(*y);
+        }
+        weight *= p;
+        sum += agreement as f32 / d as f32 * weight;
+    }
+    agreement as f32 / depth as f32 * weight + (1.0 - p) / p * sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+
+    fn matches(pathways: &[&str]) -> Vec<MatchedNode> {
+        pathways
+            .iter()
+            .map(|p| MatchedNode {
+                pathway: Pathway::parse(p).unwrap(),
+                node_kind: NodeKind::Document,
+                score: 0.5,
+                brief: String::new(),
+                summary: None,
+                content: None,
+                highlights: Vec::new(),
+                age: 0,
+                stale: false,
+                symbols: Vec::new(),
+                explanation: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_rankings() {
+        let a = matches(&["a3s://knowledge/a", "a3s://knowledge/b"]);
+        let overlap = Overlap::between(&a, &a);
+        assert_eq!(overlap.shared, 2);
+        assert_eq!(overlap.jaccard, 1.0);
+        assert!(overlap.same_top);
+        assert!((overlap.rank_biased_overlap - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_partial_overlap() {
+        let a = matches(&[
+            "a3s://knowledge/a",
+            "a3s://knowledge/b",
+            "a3s://knowledge/c",
+        ]);
+        let b = matches(&[
+            "a3s://knowledge/b",
+            "a3s://knowledge/a",
+            "a3s://knowledge/d",
+        ]);
+        let swapped = Overlap::between(&a, &b);
+        assert_eq!(swapped.shared, 2);
+        assert_eq!(swapped.jaccard, 0.5);
+        assert!(!swapped.same_top);
+        assert_eq!(
+            swapped.only_a,
+            vec![Pathway::parse("a3s://knowledge/c").unwrap()]
+        );
+        assert_eq!(
+            swapped.only_b,
+            vec![Pathway::parse("a3s://knowledge/d").unwrap()]
+        );
+
+        let disjoint = Overlap::between(&a, &matches(&["a3s://knowledge/x"]));
+        assert_eq!(disjoint.rank_biased_overlap, 0.0);
+        assert!(swapped.rank_biased_overlap > disjoint.rank_biased_overlap);
+        assert!(swapped.rank_biased_overlap < 1.0);
+    }
+}
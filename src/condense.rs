@@ -0,0 +1,190 @@
+//! Reading a node within a token budget
+//!
+//! [`crate::A3SClient::read_within`] returns a node's content when it fits
+//! the budget. Otherwise it returns the best shorter form it has: the
+//! node's summary, an LLM compression of its sections most relevant to an
+//! optional focus query, its brief, or, without an LLM, an excerpt of
+//! those sections. An agent reading one large node never gets more than it
+//! asked for.
+//!
+//! [`crate::A3SClient::extract`] instead returns only the passages of a
+//! node that bear on a question, for an agent after one fact.
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::{self, Chunk};
+use crate::pathway::Pathway;
+use crate::tokenizer::Tokenizer;
+
+/// Size of the sections content is ranked in, in tokens
+const SECTION_TOKENS: usize = 200;
+
+/// Most source text sent to the LLM for one compression, in tokens
+pub const MAX_COMPRESSION_INPUT_TOKENS: usize = 8000;
+
+/// Separator between non-adjacent sections of an excerpt
+const GAP: &str = "\n…\n";
+
+/// What the text of a [`BoundedRead`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadSource {
+    /// The full content, which fit
+    Content,
+    /// The node's summary digest
+    Summary,
+    /// The node's brief digest
+    Brief,
+    /// An LLM compression of the most relevant sections
+    Compressed,
+    /// The most relevant sections verbatim, cut to fit
+    Excerpt,
+}
+
+/// A node read within a token budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoundedRead {
+    pub pathway: Pathway,
+    pub source: ReadSource,
+    pub text: String,
+    /// Tokens of `text`
+    pub tokens: usize,
+}
+
+/// Passages of a node relevant to a question
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Extraction {
+    pub pathway: Pathway,
+    /// Most relevant passages that fit the budget, in document order
+    pub passages: Vec<Passage>,
+    /// Tokens of all passages together
+    pub tokens: usize,
+}
+
+/// A span of a node's content and how relevant it is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Passage {
+    /// Byte offsets into the node's content
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// Similarity to the question
+    pub score: f32,
+}
+
+/// Split content into sections of a few hundred tokens to rank
+pub fn sections(content: &str, tokenizer: &dyn Tokenizer) -> Vec<Chunk> {
+    let sections = chunk::split(content, SECTION_TOKENS, 0, tokenizer);
+    if sections.is_empty() && !content.is_empty() {
+        return vec![Chunk {
+            index: 0,
+            start: 0,
+            end: content.len(),
+            text: content.to_string(),
+            symbols: Vec::new(),
+        }];
+    }
+    sections
+}
+
+/// The highest-scoring sections that fit `max_tokens` together, in document
+/// order; without scores, the leading sections
+pub fn select<'a>(
+    sections: &'a [Chunk],
+    scores: Option<&[f32]>,
+    max_tokens: usize,
+    tokenizer: &dyn Tokenizer,
+) -> Vec<&'a Chunk> {
+    let mut ranked: Vec<usize> = (0..sections.len()).collect();
+    if let Some(scores) = scores {
+        ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    }
+
+    let mut budget = max_tokens;
+    let mut selected = Vec::new();
+    for index in ranked {
+        let tokens = tokenizer.count(&sections[index].text);
+        if tokens <= budget {
+            budget -= tokens;
+            selected.push(&sections[index]);
+        }
+    }
+    selected.sort_by_key(|section| section.index);
+    selected
+}
+
+/// Join sections, marking where text between them was left out
+pub fn join(sections: &[&Chunk]) -> String {
+    let mut text = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 && sections[i - 1].end < section.start {
+            text.push_str(GAP);
+        }
+        text.push_str(&section.text);
+    }
+    text
+}
+
+/// Build a prompt compressing `source` to at most `max_tokens`, keeping
+/// what bears on `focus`
+pub fn compression_prompt(source: &str, max_tokens: usize, focus: Option<&str>) -> String {
+    let focus = match focus {
+        Some(focus) => format!(" Keep everything relevant to: {}.", focus),
+        None => String::new(),
+    };
+    format!(
+        "Compress the following text to at most {} tokens. Keep facts, \
+         names, numbers, and instructions exactly; drop repetition and \
+         filler.{} Reply with the compressed text only.\n\n{}",
+        max_tokens, focus, source
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::CharTokenizer;
+
+    #[test]
+    fn test_select_by_score_in_document_order() {
+        let content = format!(
+            "{}\n\n{}\n\n{}",
+            "a".repeat(150),
+            "b".repeat(150),
+            "c".repeat(150)
+        );
+        let sections = sections(&content, &CharTokenizer);
+        assert_eq!(sections.len(), 3);
+
+        let picked = select(&sections, Some(&[0.9, 0.1, 0.8]), 320, &CharTokenizer);
+        assert_eq!(
+            picked.iter().map(|s| s.index).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        let text = join(&picked);
+        assert!(text.contains("\n…\nccc"));
+
+        let leading = select(&sections, None, 160, &CharTokenizer);
+        assert_eq!(leading.len(), 1);
+        assert_eq!(leading[0].index, 0);
+    }
+
+    #[test]
+    fn test_short_content_is_one_section() {
+        let sections = sections("Tokens expire hourly.", &CharTokenizer);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(
+            join(&sections.iter().collect::<Vec<_>>()),
+            "Tokens expire hourly."
+        );
+    }
+
+    #[test]
+    fn test_compression_prompt() {
+        let prompt = compression_prompt("Long text", 100, Some("rate limits"));
+        assert!(prompt.contains("at most 100 tokens"));
+        assert!(prompt.contains("relevant to: rate limits"));
+        assert!(prompt.ends_with("Long text"));
+        assert!(!compression_prompt("Long text", 100, None).contains("relevant"));
+    }
+}
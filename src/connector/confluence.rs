@@ -0,0 +1,321 @@
+//! Confluence connector using the REST content search API
+//!
+//! Pages are found with CQL ordered by last modification and paginated
+//! with the `next` links Confluence returns. CQL dates are minute-precision
+//! and in the account's time zone, so the query window is widened by a day
+//! and pages at or before the stored watermark are skipped client-side.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{slug, Connector, SourceDocument, SyncBatch};
+use crate::config::ConfluenceConfig;
+use crate::core::NodeKind;
+use crate::error::{A3SError, Result};
+use crate::web::html_to_text;
+
+const EXPAND: &str = "body.storage,version,space,ancestors,metadata.labels";
+
+/// Syncs pages from a Confluence site
+pub struct ConfluenceConnector {
+    base_url: String,
+    email: Option<String>,
+    api_token: String,
+    spaces: Vec<String>,
+    page_size: usize,
+    timeout_secs: u64,
+    http: reqwest::Client,
+}
+
+/// Sync position; `next` and `newest` are only set mid-sync
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ConfluenceCursor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    since: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    newest: Option<DateTime<Utc>>,
+    /// Next-page link relative to the base URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    results: Vec<ConfluencePage>,
+    #[serde(default, rename = "_links")]
+    links: Links,
+}
+
+#[derive(Default, Deserialize)]
+struct Links {
+    next: Option<String>,
+    webui: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConfluencePage {
+    id: String,
+    title: String,
+    space: Option<Space>,
+    version: Version,
+    body: Option<Body>,
+    #[serde(default)]
+    ancestors: Vec<Ancestor>,
+    metadata: Option<PageMetadata>,
+    #[serde(default, rename = "_links")]
+    links: Links,
+}
+
+#[derive(Deserialize)]
+struct Space {
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    when: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Body {
+    storage: Storage,
+}
+
+#[derive(Deserialize)]
+struct Storage {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Ancestor {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct PageMetadata {
+    labels: Option<Labels>,
+}
+
+#[derive(Deserialize)]
+struct Labels {
+    results: Vec<Label>,
+}
+
+#[derive(Deserialize)]
+struct Label {
+    name: String,
+}
+
+impl ConfluenceConnector {
+    pub fn new(config: &ConfluenceConfig) -> Result<Self> {
+        let api_token = config
+            .api_token
+            .clone()
+            .or_else(|| std::env::var("CONFLUENCE_API_TOKEN").ok())
+            .ok_or_else(|| A3SError::Config("Confluence API token not provided".to_string()))?;
+
+        Ok(Self {
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            email: config.email.clone(),
+            api_token,
+            spaces: config.spaces.clone(),
+            page_size: config.page_size.max(1),
+            timeout_secs: config.timeout_secs,
+            http: crate::http::default_client(),
+        })
+    }
+
+    /// Send requests through the given shared client
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// CQL selecting pages in the configured spaces modified near `since`
+    fn cql(&self, since: Option<DateTime<Utc>>) -> String {
+        let mut clauses = vec!["type = page".to_string()];
+        if !self.spaces.is_empty() {
+            let keys: Vec<String> = self.spaces.iter().map(|k| format!("\"{}\"", k)).collect();
+            clauses.push(format!("space in ({})", keys.join(", ")));
+        }
+        if let Some(since) = since {
+            let from = since - chrono::Duration::days(1);
+            clauses.push(format!(
+                "lastmodified >= \"{}\"",
+                from.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        format!("{} order by lastmodified asc", clauses.join(" and "))
+    }
+
+    async fn search(&self, state: &ConfluenceCursor) -> Result<SearchResponse> {
+        let request = match &state.next {
+            Some(next) => self.http.get(format!("{}{}", self.base_url, next)),
+            None => self
+                .http
+                .get(format!("{}/rest/api/content/search", self.base_url))
+                .query(&[
+                    ("cql", self.cql(state.since)),
+                    ("limit", self.page_size.to_string()),
+                    ("expand", EXPAND.to_string()),
+                ]),
+        };
+        let request = match &self.email {
+            Some(email) => request.basic_auth(email, Some(&self.api_token)),
+            None => request.bearer_auth(&self.api_token),
+        };
+
+        let response = request
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .send()
+            .await
+            .map_err(|e| A3SError::Connector(format!("Confluence request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(A3SError::Connector(format!(
+                "Confluence API error {}: {}",
+                status, body
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| A3SError::Connector(format!("Failed to parse Confluence response: {}", e)))
+    }
+
+    fn document(&self, page: ConfluencePage) -> SourceDocument {
+        let mut segments: Vec<String> = page.space.iter().map(|s| s.key.clone()).collect();
+        segments.extend(page.ancestors.iter().map(|a| slug(&a.title)));
+        segments.push(slug(&page.title));
+
+        let body = page
+            .body
+            .map(|body| html_to_text(&body.storage.value))
+            .unwrap_or_default();
+        let tags = page
+            .metadata
+            .and_then(|m| m.labels)
+            .map(|labels| labels.results.into_iter().map(|l| l.name).collect())
+            .unwrap_or_default();
+
+        SourceDocument {
+            id: page.id,
+            path: segments.join("/"),
+            kind: NodeKind::Markdown,
+            content: format!("# {}\n\n{}", page.title, body),
+            url: page
+                .links
+                .webui
+                .map(|webui| format!("{}{}", self.base_url, webui)),
+            tags,
+            updated_at: page.version.when,
+        }
+    }
+}
+
+#[async_trait]
+impl Connector for ConfluenceConnector {
+    fn name(&self) -> &str {
+        "confluence"
+    }
+
+    async fn fetch(&self, cursor: Option<&str>) -> Result<SyncBatch> {
+        let state: ConfluenceCursor = match cursor {
+            Some(cursor) => serde_json::from_str(cursor)
+                .map_err(|e| A3SError::Connector(format!("Invalid Confluence cursor: {}", e)))?,
+            None => ConfluenceCursor::default(),
+        };
+
+        let response = self.search(&state).await?;
+        let mut newest = state.newest;
+        let mut documents = Vec::new();
+        for page in response.results {
+            let when = page.version.when;
+            if state.since.is_some_and(|since| when <= since) {
+                continue;
+            }
+            newest = newest.max(Some(when));
+            documents.push(self.document(page));
+        }
+
+        let has_more = response.links.next.is_some();
+        let cursor = if has_more {
+            ConfluenceCursor {
+                since: state.since,
+                newest,
+                next: response.links.next,
+            }
+        } else {
+            ConfluenceCursor {
+                since: newest.or(state.since),
+                ..Default::default()
+            }
+        };
+
+        Ok(SyncBatch {
+            documents,
+            cursor: Some(serde_json::to_string(&cursor)?),
+            has_more,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ConfluenceConfig {
+        ConfluenceConfig {
+            base_url: "https://example.atlassian.net/wiki/".to_string(),
+            email: Some("bot@example.com".to_string()),
+            api_token: Some("token".to_string()),
+            spaces: vec!["ENG".to_string(), "OPS".to_string()],
+            page_size: 25,
+            timeout_secs: 30,
+        }
+    }
+
+    #[test]
+    fn test_cql() {
+        let connector = ConfluenceConnector::new(&config()).unwrap();
+        assert_eq!(connector.base_url, "https://example.atlassian.net/wiki");
+        assert_eq!(
+            connector.cql(None),
+            "type = page and space in (\"ENG\", \"OPS\") order by lastmodified asc"
+        );
+
+        let since = "2024-05-02T10:30:00Z".parse().unwrap();
+        assert!(connector
+            .cql(Some(since))
+            .contains("lastmodified >= \"2024-05-01 10:30\""));
+    }
+
+    #[test]
+    fn test_document_pathway_and_labels() {
+        let connector = ConfluenceConnector::new(&config()).unwrap();
+        let page: ConfluencePage = serde_json::from_value(serde_json::json!({
+            "id": "42",
+            "title": "Rollbacks",
+            "space": { "key": "ENG" },
+            "version": { "when": "2024-05-02T10:30:00.000Z" },
+            "ancestors": [{ "title": "Runbooks" }],
+            "metadata": { "labels": { "results": [{ "name": "ops" }] } },
+            "_links": { "webui": "/spaces/ENG/pages/42" }
+        }))
+        .unwrap();
+
+        let document = connector.document(page);
+        assert_eq!(document.path, "ENG/runbooks/rollbacks");
+        assert_eq!(document.tags, vec!["ops".to_string()]);
+        assert_eq!(
+            document.url.as_deref(),
+            Some("https://example.atlassian.net/wiki/spaces/ENG/pages/42")
+        );
+    }
+}
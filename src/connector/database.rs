@@ -0,0 +1,305 @@
+//! Database schema connector
+//!
+//! Introspects a PostgreSQL, MySQL, or SQLite database and describes each
+//! table, with its columns, types, and comments, as a Markdown document at
+//! `<schema>/<table>`. Every sync reads the whole catalog; the stored cursor
+//! is a hash of each table's description, so only changed tables are
+//! re-ingested.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::any::AnyPoolOptions;
+use sqlx::Row;
+
+use super::{Connector, SourceDocument, SyncBatch};
+use crate::config::DatabaseConfig;
+use crate::core::NodeKind;
+use crate::error::{A3SError, Result};
+use crate::provenance::sha256_hex;
+
+const POSTGRES_COLUMNS: &str = "\
+SELECT c.table_schema::text, c.table_name::text,
+       COALESCE(obj_description(format('%I.%I', c.table_schema, c.table_name)::regclass, 'pg_class'), ''),
+       c.column_name::text, c.data_type::text, c.is_nullable::text,
+       COALESCE(col_description(format('%I.%I', c.table_schema, c.table_name)::regclass, c.ordinal_position::int), '')
+FROM information_schema.columns c
+WHERE c.table_schema NOT IN ('pg_catalog', 'information_schema')
+ORDER BY c.table_schema, c.table_name, c.ordinal_position";
+
+const MYSQL_COLUMNS: &str = "\
+SELECT CAST(c.TABLE_SCHEMA AS CHAR), CAST(c.TABLE_NAME AS CHAR), CAST(t.TABLE_COMMENT AS CHAR),
+       CAST(c.COLUMN_NAME AS CHAR), CAST(c.COLUMN_TYPE AS CHAR), CAST(c.IS_NULLABLE AS CHAR),
+       CAST(c.COLUMN_COMMENT AS CHAR)
+FROM information_schema.COLUMNS c
+JOIN information_schema.TABLES t
+  ON t.TABLE_SCHEMA = c.TABLE_SCHEMA AND t.TABLE_NAME = c.TABLE_NAME
+WHERE c.TABLE_SCHEMA = DATABASE()
+ORDER BY c.TABLE_SCHEMA, c.TABLE_NAME, c.ORDINAL_POSITION";
+
+// SQLite has no comments; its schema is always `main`
+const SQLITE_COLUMNS: &str = "\
+SELECT 'main', m.name, '', p.name, p.type,
+       CASE WHEN p.\"notnull\" = 0 THEN 'YES' ELSE 'NO' END, ''
+FROM sqlite_master m JOIN pragma_table_info(m.name) p
+WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'
+ORDER BY m.name, p.cid";
+
+/// Syncs table descriptions from a relational database
+pub struct DatabaseConnector {
+    url: String,
+    schemas: Vec<String>,
+    timeout_secs: u64,
+}
+
+/// One row of the column catalog
+#[derive(Debug, Clone)]
+struct ColumnRow {
+    schema: String,
+    table: String,
+    table_comment: String,
+    column: String,
+    data_type: String,
+    nullable: bool,
+    comment: String,
+}
+
+/// A table and its columns in ordinal order
+#[derive(Debug)]
+struct Table {
+    schema: String,
+    name: String,
+    comment: String,
+    columns: Vec<ColumnRow>,
+}
+
+impl Table {
+    fn path(&self) -> String {
+        format!("{}/{}", self.schema, self.name)
+    }
+
+    /// Markdown description of the table
+    fn to_markdown(&self) -> String {
+        let mut content = format!("# Table {}.{}", self.schema, self.name);
+        if !self.comment.trim().is_empty() {
+            content.push_str("\n\n");
+            content.push_str(self.comment.trim());
+        }
+        content.push_str(
+            "\n\n| Column | Type | Nullable | Description |\n|--------|------|----------|-------------|",
+        );
+        for column in &self.columns {
+            content.push_str(&format!(
+                "\n| {} | {} | {} | {} |",
+                column.column,
+                column.data_type,
+                if column.nullable { "yes" } else { "no" },
+                column.comment.trim().replace('|', "\\|").replace('\n', " ")
+            ));
+        }
+        content
+    }
+}
+
+impl DatabaseConnector {
+    pub fn new(config: &DatabaseConfig) -> Result<Self> {
+        let url = config
+            .url
+            .clone()
+            .or_else(|| std::env::var("DATABASE_URL").ok())
+            .ok_or_else(|| A3SError::Config("Database URL not provided".to_string()))?;
+        columns_query(&url)?;
+
+        Ok(Self {
+            url,
+            schemas: config.schemas.clone(),
+            timeout_secs: config.timeout_secs,
+        })
+    }
+
+    async fn columns(&self) -> Result<Vec<ColumnRow>> {
+        sqlx::any::install_default_drivers();
+        let query = columns_query(&self.url)?;
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(Duration::from_secs(self.timeout_secs))
+            .connect(&self.url)
+            .await
+            .map_err(|e| A3SError::Connector(format!("Database connection failed: {}", e)))?;
+
+        let rows = sqlx::query(query)
+            .fetch_all(&pool)
+            .await
+            .map_err(|e| A3SError::Connector(format!("Schema query failed: {}", e)))?;
+        pool.close().await;
+
+        let mut columns = rows
+            .iter()
+            .map(|row| {
+                let text = |i: usize| -> Result<String> {
+                    row.try_get::<Option<String>, _>(i)
+                        .map(Option::unwrap_or_default)
+                        .map_err(|e| A3SError::Connector(format!("Unexpected schema row: {}", e)))
+                };
+                Ok(ColumnRow {
+                    schema: text(0)?,
+                    table: text(1)?,
+                    table_comment: text(2)?,
+                    column: text(3)?,
+                    data_type: text(4)?,
+                    nullable: text(5)?.eq_ignore_ascii_case("yes"),
+                    comment: text(6)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !self.schemas.is_empty() {
+            columns.retain(|row| self.schemas.contains(&row.schema));
+        }
+        Ok(columns)
+    }
+}
+
+#[async_trait]
+impl Connector for DatabaseConnector {
+    fn name(&self) -> &str {
+        "database"
+    }
+
+    async fn fetch(&self, cursor: Option<&str>) -> Result<SyncBatch> {
+        let synced: BTreeMap<String, String> = match cursor {
+            Some(cursor) => serde_json::from_str(cursor)
+                .map_err(|e| A3SError::Connector(format!("Invalid database cursor: {}", e)))?,
+            None => BTreeMap::new(),
+        };
+
+        let (documents, hashes) = changed_tables(group_tables(self.columns().await?), &synced);
+        Ok(SyncBatch {
+            documents,
+            cursor: Some(serde_json::to_string(&hashes)?),
+            has_more: false,
+        })
+    }
+}
+
+/// Catalog query for the database named by a connection URL's scheme
+fn columns_query(url: &str) -> Result<&'static str> {
+    let scheme = url.split(':').next().unwrap_or_default();
+    match scheme {
+        "postgres" | "postgresql" => Ok(POSTGRES_COLUMNS),
+        "mysql" | "mariadb" => Ok(MYSQL_COLUMNS),
+        "sqlite" => Ok(SQLITE_COLUMNS),
+        _ => Err(A3SError::Config(format!(
+            "Unsupported database URL scheme '{}' (expected postgres, mysql, or sqlite)",
+            scheme
+        ))),
+    }
+}
+
+/// Group ordered catalog rows into tables
+fn group_tables(rows: Vec<ColumnRow>) -> Vec<Table> {
+    let mut tables: Vec<Table> = Vec::new();
+    for row in rows {
+        match tables.last_mut() {
+            Some(table) if table.schema == row.schema && table.name == row.table => {
+                table.columns.push(row)
+            }
+            _ => tables.push(Table {
+                schema: row.schema.clone(),
+                name: row.table.clone(),
+                comment: row.table_comment.clone(),
+                columns: vec![row],
+            }),
+        }
+    }
+    tables
+}
+
+/// Documents for tables whose description changed since `synced`, and the
+/// description hash of every current table
+fn changed_tables(
+    tables: Vec<Table>,
+    synced: &BTreeMap<String, String>,
+) -> (Vec<SourceDocument>, BTreeMap<String, String>) {
+    let now = Utc::now();
+    let mut documents = Vec::new();
+    let mut hashes = BTreeMap::new();
+    for table in tables {
+        let path = table.path();
+        let content = table.to_markdown();
+        let hash = sha256_hex(content.as_bytes());
+        if synced.get(&path) != Some(&hash) {
+            documents.push(SourceDocument {
+                id: format!("{}.{}", table.schema, table.name),
+                path: path.clone(),
+                kind: NodeKind::Markdown,
+                content,
+                url: None,
+                tags: vec!["schema".to_string(), table.schema.clone()],
+                updated_at: now,
+            });
+        }
+        hashes.insert(path, hash);
+    }
+    (documents, hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(table: &str, column: &str, comment: &str) -> ColumnRow {
+        ColumnRow {
+            schema: "public".to_string(),
+            table: table.to_string(),
+            table_comment: format!("{} placed by customers", table),
+            column: column.to_string(),
+            data_type: "integer".to_string(),
+            nullable: column != "id",
+            comment: comment.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_columns_query_by_scheme() {
+        assert_eq!(
+            columns_query("postgres://u:p@localhost/app").unwrap(),
+            POSTGRES_COLUMNS
+        );
+        assert_eq!(columns_query("sqlite://app.db").unwrap(), SQLITE_COLUMNS);
+        assert!(columns_query("oracle://localhost").is_err());
+    }
+
+    #[test]
+    fn test_table_markdown() {
+        let tables = group_tables(vec![
+            row("orders", "id", ""),
+            row("orders", "total", "Amount in cents | tax included"),
+            row("refunds", "id", ""),
+        ]);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].path(), "public/orders");
+        assert_eq!(
+            tables[0].to_markdown(),
+            "# Table public.orders\n\norders placed by customers\n\n\
+| Column | Type | Nullable | Description |\n|--------|------|----------|-------------|\n\
+| id | integer | no |  |\n\
+| total | integer | yes | Amount in cents \\| tax included |"
+        );
+    }
+
+    #[test]
+    fn test_only_changed_tables_are_synced() {
+        let rows = vec![row("orders", "id", ""), row("refunds", "id", "")];
+        let (documents, hashes) = changed_tables(group_tables(rows.clone()), &BTreeMap::new());
+        assert_eq!(documents.len(), 2);
+
+        let mut changed = rows;
+        changed[1].comment = "Refund identifier".to_string();
+        let (documents, _) = changed_tables(group_tables(changed), &hashes);
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].path, "public/refunds");
+        assert_eq!(documents[0].tags, vec!["schema", "public"]);
+    }
+}
@@ -0,0 +1,391 @@
+//! GitHub connector for issues and pull requests
+//!
+//! Each repository's issues endpoint (which also lists pull requests) is
+//! read oldest update first, filtered by `since`, and paginated by page
+//! number. Each issue becomes `<owner>/<name>/issues/<n>` and each pull
+//! request `<owner>/<name>/pulls/<n>`, with their comments, review comments,
+//! and labels as tags. The stored cursor is the newest `updated_at` per
+//! repository.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Connector, SourceDocument, SyncBatch};
+use crate::config::GitHubConfig;
+use crate::core::NodeKind;
+use crate::error::{A3SError, Result};
+
+const API_VERSION: &str = "2022-11-28";
+
+/// Comments requested per call; GitHub's maximum
+const COMMENTS_PER_PAGE: usize = 100;
+
+/// Syncs issues and pull requests from GitHub repositories
+pub struct GitHubConnector {
+    api_base: String,
+    token: String,
+    repos: Vec<String>,
+    per_page: usize,
+    timeout_secs: u64,
+    http: reqwest::Client,
+}
+
+/// Sync position across repositories
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct GitHubCursor {
+    /// Newest `updated_at` synced, per repository
+    #[serde(default)]
+    since: BTreeMap<String, DateTime<Utc>>,
+    /// Set only mid-sync
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Position {
+    /// Index into the configured repositories
+    repo: usize,
+    page: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    newest: Option<DateTime<Utc>>,
+}
+
+impl Position {
+    fn start(repo: usize) -> Self {
+        Self {
+            repo,
+            page: 1,
+            newest: None,
+        }
+    }
+}
+
+impl GitHubCursor {
+    /// Move past a fetched page, finishing its repository on the last page
+    fn advance(&mut self, repos: &[String], mut position: Position, last_page: bool) {
+        if !last_page {
+            position.page += 1;
+            self.position = Some(position);
+            return;
+        }
+
+        if let Some(newest) = position.newest {
+            self.since.insert(repos[position.repo].clone(), newest);
+        }
+        let next = position.repo + 1;
+        self.position = (next < repos.len()).then(|| Position::start(next));
+    }
+}
+
+#[derive(Deserialize)]
+struct Issue {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    state: String,
+    html_url: String,
+    user: Option<User>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    updated_at: DateTime<Utc>,
+    /// Present when the issue is a pull request
+    pull_request: Option<serde_json::Value>,
+    #[serde(default)]
+    comments: u64,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Comment {
+    user: Option<User>,
+    body: Option<String>,
+    created_at: DateTime<Utc>,
+    /// File a review comment is attached to
+    path: Option<String>,
+}
+
+impl GitHubConnector {
+    pub fn new(config: &GitHubConfig) -> Result<Self> {
+        let token = config
+            .token
+            .clone()
+            .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+            .ok_or_else(|| A3SError::Config("GitHub token not provided".to_string()))?;
+        if config.repos.is_empty() {
+            return Err(A3SError::Config(
+                "connectors.github.repos is empty".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            api_base: config.api_base.trim_end_matches('/').to_string(),
+            token,
+            repos: config.repos.clone(),
+            per_page: config.page_size.clamp(1, 100),
+            timeout_secs: config.timeout_secs,
+            http: crate::http::default_client(),
+        })
+    }
+
+    /// Send requests through the given shared client
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T> {
+        let response = self
+            .http
+            .get(format!("{}{}", self.api_base, path))
+            .query(query)
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", API_VERSION)
+            .send()
+            .await
+            .map_err(|e| A3SError::Connector(format!("GitHub request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(A3SError::Connector(format!(
+                "GitHub API error {}: {}",
+                status, body
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| A3SError::Connector(format!("Failed to parse GitHub response: {}", e)))
+    }
+
+    /// Every comment at a comments endpoint
+    async fn comments(&self, path: &str) -> Result<Vec<Comment>> {
+        let mut comments = Vec::new();
+        for page in 1.. {
+            let batch: Vec<Comment> = self
+                .get(
+                    path,
+                    &[
+                        ("per_page", COMMENTS_PER_PAGE.to_string()),
+                        ("page", page.to_string()),
+                    ],
+                )
+                .await?;
+            let last = batch.len() < COMMENTS_PER_PAGE;
+            comments.extend(batch);
+            if last {
+                break;
+            }
+        }
+        Ok(comments)
+    }
+
+    async fn document(&self, repo: &str, issue: &Issue) -> Result<SourceDocument> {
+        let comments = if issue.comments > 0 {
+            self.comments(&format!("/repos/{}/issues/{}/comments", repo, issue.number))
+                .await?
+        } else {
+            Vec::new()
+        };
+        let review_comments = if issue.pull_request.is_some() {
+            self.comments(&format!("/repos/{}/pulls/{}/comments", repo, issue.number))
+                .await?
+        } else {
+            Vec::new()
+        };
+        Ok(to_document(repo, issue, &comments, &review_comments))
+    }
+}
+
+#[async_trait]
+impl Connector for GitHubConnector {
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    async fn fetch(&self, cursor: Option<&str>) -> Result<SyncBatch> {
+        let mut state: GitHubCursor = match cursor {
+            Some(cursor) => serde_json::from_str(cursor)
+                .map_err(|e| A3SError::Connector(format!("Invalid GitHub cursor: {}", e)))?,
+            None => GitHubCursor::default(),
+        };
+
+        let mut position = state.position.take().unwrap_or_else(|| Position::start(0));
+        let repo = self.repos.get(position.repo).ok_or_else(|| {
+            A3SError::Connector("cursor refers to a repository no longer configured".to_string())
+        })?;
+        let since = state.since.get(repo).copied();
+
+        let mut query = vec![
+            ("state", "all".to_string()),
+            ("sort", "updated".to_string()),
+            ("direction", "asc".to_string()),
+            ("per_page", self.per_page.to_string()),
+            ("page", position.page.to_string()),
+        ];
+        if let Some(since) = since {
+            query.push(("since", since.to_rfc3339()));
+        }
+        let issues: Vec<Issue> = self.get(&format!("/repos/{}/issues", repo), &query).await?;
+
+        let mut documents = Vec::new();
+        for issue in &issues {
+            // `since` is inclusive; the newest issue was synced last time
+            if since.is_some_and(|since| issue.updated_at <= since) {
+                continue;
+            }
+            position.newest = position.newest.max(Some(issue.updated_at));
+            documents.push(self.document(repo, issue).await?);
+        }
+
+        state.advance(&self.repos, position, issues.len() < self.per_page);
+        let has_more = state.position.is_some();
+        Ok(SyncBatch {
+            documents,
+            cursor: Some(serde_json::to_string(&state)?),
+            has_more,
+        })
+    }
+}
+
+/// Markdown document for an issue or pull request and its discussion
+fn to_document(
+    repo: &str,
+    issue: &Issue,
+    comments: &[Comment],
+    review_comments: &[Comment],
+) -> SourceDocument {
+    let (kind, dir) = match issue.pull_request {
+        Some(_) => ("Pull request", "pulls"),
+        None => ("Issue", "issues"),
+    };
+    let author = issue.user.as_ref().map_or("ghost", |u| u.login.as_str());
+
+    let mut content = format!(
+        "# {} (#{})\n\n{} · {} · opened by @{}",
+        issue.title, issue.number, kind, issue.state, author
+    );
+    if let Some(body) = issue.body.as_deref().filter(|b| !b.trim().is_empty()) {
+        content.push_str("\n\n");
+        content.push_str(body.trim());
+    }
+    for (heading, comments) in [("Comments", comments), ("Review comments", review_comments)] {
+        if comments.is_empty() {
+            continue;
+        }
+        content.push_str(&format!("\n\n## {}", heading));
+        for comment in comments {
+            let author = comment.user.as_ref().map_or("ghost", |u| u.login.as_str());
+            let location = comment
+                .path
+                .as_ref()
+                .map(|path| format!(" on `{}`", path))
+                .unwrap_or_default();
+            content.push_str(&format!(
+                "\n\n**@{}**{} · {}\n\n{}",
+                author,
+                location,
+                comment.created_at.format("%Y-%m-%d"),
+                comment.body.as_deref().unwrap_or_default().trim()
+            ));
+        }
+    }
+
+    SourceDocument {
+        id: issue.html_url.clone(),
+        path: format!("{}/{}/{}", repo, dir, issue.number),
+        kind: NodeKind::Markdown,
+        content,
+        url: Some(issue.html_url.clone()),
+        tags: issue.labels.iter().map(|l| l.name.clone()).collect(),
+        updated_at: issue.updated_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_connector_requires_repos() {
+        let config = GitHubConfig {
+            token: Some("token".to_string()),
+            ..Default::default()
+        };
+        assert!(GitHubConnector::new(&config).is_err());
+    }
+
+    #[test]
+    fn test_cursor_walks_repos_and_pages() {
+        let repos = vec!["a/one".to_string(), "a/two".to_string()];
+        let newest: DateTime<Utc> = "2024-05-01T00:00:00Z".parse().unwrap();
+        let mut cursor = GitHubCursor::default();
+
+        cursor.advance(&repos, Position::start(0), false);
+        assert_eq!(cursor.position.as_ref().unwrap().page, 2);
+
+        let mut position = cursor.position.take().unwrap();
+        position.newest = Some(newest);
+        cursor.advance(&repos, position, true);
+        assert_eq!(cursor.since.get("a/one"), Some(&newest));
+        assert_eq!(cursor.position, Some(Position::start(1)));
+
+        let position = cursor.position.take().unwrap();
+        cursor.advance(&repos, position, true);
+        assert_eq!(cursor.position, None);
+        assert!(!cursor.since.contains_key("a/two"));
+    }
+
+    #[test]
+    fn test_pull_request_document() {
+        let issue: Issue = serde_json::from_value(serde_json::json!({
+            "number": 7,
+            "title": "Retry failed uploads",
+            "body": "Adds backoff.",
+            "state": "closed",
+            "html_url": "https://github.com/a/one/pull/7",
+            "user": { "login": "dev" },
+            "labels": [{ "name": "storage" }],
+            "updated_at": "2024-05-01T00:00:00Z",
+            "pull_request": {}
+        }))
+        .unwrap();
+        let review: Comment = serde_json::from_value(serde_json::json!({
+            "user": { "login": "reviewer" },
+            "body": "Cap the delay?",
+            "created_at": "2024-04-30T12:00:00Z",
+            "path": "src/upload.rs"
+        }))
+        .unwrap();
+
+        let document = to_document("a/one", &issue, &[], &[review]);
+        assert_eq!(document.path, "a/one/pulls/7");
+        assert_eq!(document.tags, vec!["storage".to_string()]);
+        assert!(document
+            .content
+            .starts_with("# Retry failed uploads (#7)\n\nPull request · closed · opened by @dev"));
+        assert!(document
+            .content
+            .contains("## Review comments\n\n**@reviewer** on `src/upload.rs` · 2024-04-30"));
+    }
+}
@@ -0,0 +1,212 @@
+//! Connectors that sync documents from external services
+//!
+//! A connector lists documents changed since an opaque cursor. The client
+//! ingests each batch under a target pathway and stores the final cursor on
+//! the target directory, so the next sync only fetches what changed.
+//! Documents deleted at the source are not removed.
+
+#[cfg(feature = "connectors")]
+mod confluence;
+#[cfg(feature = "database")]
+mod database;
+#[cfg(feature = "connectors")]
+mod github;
+#[cfg(feature = "connectors")]
+mod notion;
+
+#[cfg(feature = "connectors")]
+pub use confluence::ConfluenceConnector;
+#[cfg(feature = "database")]
+pub use database::DatabaseConnector;
+#[cfg(feature = "connectors")]
+pub use github::GitHubConnector;
+#[cfg(feature = "connectors")]
+pub use notion::NotionConnector;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::config::ConnectorsConfig;
+use crate::core::{Node, NodeKind};
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+
+/// Connectors understood by [`create_connector`]
+pub const CONNECTORS: &[&str] = &["notion", "confluence", "github", "database"];
+
+/// Metadata key on a sync target holding each connector's cursor
+const CURSORS_KEY: &str = "connector_cursors";
+
+/// A document fetched from an external service
+#[derive(Debug, Clone)]
+pub struct SourceDocument {
+    /// Identifier at the source
+    pub id: String,
+    /// Pathway relative to the sync target, e.g. `ENG/runbooks/deploys`
+    pub path: String,
+    pub kind: NodeKind,
+    pub content: String,
+    /// Link back to the document
+    pub url: Option<String>,
+    pub tags: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One page of changed documents
+#[derive(Debug, Clone, Default)]
+pub struct SyncBatch {
+    pub documents: Vec<SourceDocument>,
+    /// Cursor to pass to the next fetch
+    pub cursor: Option<String>,
+    /// Whether another fetch with `cursor` returns more documents; once
+    /// false, `cursor` is where the next sync resumes
+    pub has_more: bool,
+}
+
+/// Source of documents for incremental sync
+#[async_trait]
+pub trait Connector: Send + Sync {
+    /// Name used to store the connector's cursor
+    fn name(&self) -> &str;
+
+    /// Fetch documents changed since `cursor`, or everything without one
+    async fn fetch(&self, cursor: Option<&str>) -> Result<SyncBatch>;
+}
+
+/// Create a configured connector, sending requests through `http`
+pub fn create_connector(
+    name: &str,
+    config: &ConnectorsConfig,
+    http: &HttpClient,
+) -> Result<Arc<dyn Connector>> {
+    let missing = || A3SError::Config(format!("connectors.{} is not configured", name));
+    match name {
+        #[cfg(feature = "connectors")]
+        "notion" => {
+            let config = config.notion.as_ref().ok_or_else(missing)?;
+            Ok(Arc::new(
+                NotionConnector::new(config)?.with_http_client(http.clone()),
+            ))
+        }
+        #[cfg(feature = "connectors")]
+        "confluence" => {
+            let config = config.confluence.as_ref().ok_or_else(missing)?;
+            Ok(Arc::new(
+                ConfluenceConnector::new(config)?.with_http_client(http.clone()),
+            ))
+        }
+        #[cfg(feature = "connectors")]
+        "github" => {
+            let config = config.github.as_ref().ok_or_else(missing)?;
+            Ok(Arc::new(
+                GitHubConnector::new(config)?.with_http_client(http.clone()),
+            ))
+        }
+        #[cfg(feature = "database")]
+        "database" => {
+            let config = config.database.as_ref().ok_or_else(missing)?;
+            Ok(Arc::new(DatabaseConnector::new(config)?))
+        }
+        #[cfg(not(feature = "connectors"))]
+        "notion" | "confluence" | "github" => {
+            let _ = (config, http, missing);
+            Err(A3SError::Config(format!(
+                "Connector '{}' requires the `connectors` feature",
+                name
+            )))
+        }
+        #[cfg(not(feature = "database"))]
+        "database" => {
+            let _ = (config, http, missing);
+            Err(A3SError::Config(
+                "Connector 'database' requires the `database` feature".to_string(),
+            ))
+        }
+        _ => Err(A3SError::Config(format!(
+            "Unknown connector: {} (expected one of: {})",
+            name,
+            CONNECTORS.join(", ")
+        ))),
+    }
+}
+
+/// Cursor a connector stored on a sync target
+pub fn cursor(target: &Node, connector: &str) -> Option<String> {
+    target
+        .metadata
+        .custom
+        .get(CURSORS_KEY)?
+        .get(connector)?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Store a connector's cursor on a sync target
+pub fn set_cursor(target: &mut Node, connector: &str, cursor: &str) {
+    let cursors = target
+        .metadata
+        .custom
+        .entry(CURSORS_KEY.to_string())
+        .or_insert_with(|| serde_json::json!({}));
+    if !cursors.is_object() {
+        *cursors = serde_json::json!({});
+    }
+    cursors[connector] = serde_json::json!(cursor);
+}
+
+/// Lowercase, dash-separated pathway segment for a title
+pub fn slug(title: &str) -> String {
+    let mut slug = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathway::Pathway;
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Deploy Runbook (v2)"), "deploy-runbook-v2");
+        assert_eq!(slug("  Ünïcode / Title "), "ünïcode-title");
+        assert_eq!(slug("???"), "untitled");
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let mut target = Node::directory(Pathway::parse("a3s://knowledge/notion").unwrap());
+        assert_eq!(cursor(&target, "notion"), None);
+
+        set_cursor(&mut target, "notion", "2024-05-01T00:00:00Z");
+        set_cursor(&mut target, "confluence", "abc");
+        assert_eq!(
+            cursor(&target, "notion").as_deref(),
+            Some("2024-05-01T00:00:00Z")
+        );
+        assert_eq!(cursor(&target, "confluence").as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_create_connector_unknown() {
+        let result = create_connector(
+            "dropbox",
+            &ConnectorsConfig::default(),
+            &crate::http::build_client(&Default::default()).unwrap(),
+        );
+        assert!(matches!(result, Err(A3SError::Config(_))));
+    }
+}
@@ -0,0 +1,311 @@
+//! Notion connector using the public REST API
+//!
+//! Pages are found with the search endpoint, newest edits first, and
+//! paginated with Notion's `next_cursor`. The stored cursor is the newest
+//! `last_edited_time` seen, so the next sync stops at the first unchanged
+//! page. Only top-level blocks of a page are rendered.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use super::{slug, Connector, SourceDocument, SyncBatch};
+use crate::config::NotionConfig;
+use crate::core::NodeKind;
+use crate::error::{A3SError, Result};
+
+const NOTION_VERSION: &str = "2022-06-28";
+
+/// Syncs the pages shared with a Notion integration
+pub struct NotionConnector {
+    api_base: String,
+    token: String,
+    page_size: usize,
+    timeout_secs: u64,
+    http: reqwest::Client,
+}
+
+/// Sync position; `next` and `newest` are only set mid-sync
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct NotionCursor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    since: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    newest: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListResponse<T> {
+    results: Vec<T>,
+    #[serde(default)]
+    has_more: bool,
+    next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NotionPage {
+    id: String,
+    url: Option<String>,
+    last_edited_time: DateTime<Utc>,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    properties: serde_json::Map<String, Value>,
+}
+
+impl NotionPage {
+    fn title(&self) -> String {
+        self.properties
+            .values()
+            .find(|property| property["type"] == "title")
+            .map(|property| rich_text(&property["title"]))
+            .unwrap_or_default()
+    }
+}
+
+impl NotionConnector {
+    pub fn new(config: &NotionConfig) -> Result<Self> {
+        let token = config
+            .token
+            .clone()
+            .or_else(|| std::env::var("NOTION_TOKEN").ok())
+            .ok_or_else(|| A3SError::Config("Notion token not provided".to_string()))?;
+
+        Ok(Self {
+            api_base: config.api_base.trim_end_matches('/').to_string(),
+            token,
+            page_size: config.page_size.clamp(1, 100),
+            timeout_secs: config.timeout_secs,
+            http: crate::http::default_client(),
+        })
+    }
+
+    /// Send requests through the given shared client
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let response = request
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .bearer_auth(&self.token)
+            .header("Notion-Version", NOTION_VERSION)
+            .send()
+            .await
+            .map_err(|e| A3SError::Connector(format!("Notion request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(A3SError::Connector(format!(
+                "Notion API error {}: {}",
+                status, body
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| A3SError::Connector(format!("Failed to parse Notion response: {}", e)))
+    }
+
+    /// Markdown for a page's top-level blocks
+    async fn page_content(&self, page_id: &str) -> Result<String> {
+        let mut lines = Vec::new();
+        let mut start_cursor: Option<String> = None;
+        loop {
+            let mut request = self
+                .http
+                .get(format!("{}/blocks/{}/children", self.api_base, page_id))
+                .query(&[("page_size", "100")]);
+            if let Some(cursor) = &start_cursor {
+                request = request.query(&[("start_cursor", cursor)]);
+            }
+
+            let blocks: ListResponse<Value> = self.send(request).await?;
+            lines.extend(blocks.results.iter().filter_map(render_block));
+            match blocks.next_cursor {
+                Some(cursor) if blocks.has_more => start_cursor = Some(cursor),
+                _ => break,
+            }
+        }
+        Ok(lines.join("\n\n"))
+    }
+
+    async fn document(&self, page: &NotionPage) -> Result<SourceDocument> {
+        let title = page.title();
+        let body = self.page_content(&page.id).await?;
+        let short_id: String = page.id.chars().filter(|c| *c != '-').take(8).collect();
+
+        Ok(SourceDocument {
+            id: page.id.clone(),
+            path: format!("{}-{}", slug(&title), short_id),
+            kind: NodeKind::Markdown,
+            content: format!("# {}\n\n{}", title, body),
+            url: page.url.clone(),
+            tags: Vec::new(),
+            updated_at: page.last_edited_time,
+        })
+    }
+}
+
+#[async_trait]
+impl Connector for NotionConnector {
+    fn name(&self) -> &str {
+        "notion"
+    }
+
+    async fn fetch(&self, cursor: Option<&str>) -> Result<SyncBatch> {
+        let state: NotionCursor = match cursor {
+            Some(cursor) => serde_json::from_str(cursor)
+                .map_err(|e| A3SError::Connector(format!("Invalid Notion cursor: {}", e)))?,
+            None => NotionCursor::default(),
+        };
+
+        let mut body = serde_json::json!({
+            "filter": { "property": "object", "value": "page" },
+            "sort": { "direction": "descending", "timestamp": "last_edited_time" },
+            "page_size": self.page_size,
+        });
+        if let Some(next) = &state.next {
+            body["start_cursor"] = serde_json::json!(next);
+        }
+        let search: ListResponse<NotionPage> = self
+            .send(
+                self.http
+                    .post(format!("{}/search", self.api_base))
+                    .json(&body),
+            )
+            .await?;
+
+        let mut newest = state.newest;
+        let mut reached_synced = false;
+        let mut documents = Vec::new();
+        for page in &search.results {
+            if state
+                .since
+                .is_some_and(|since| page.last_edited_time <= since)
+            {
+                reached_synced = true;
+                break;
+            }
+            newest = newest.max(Some(page.last_edited_time));
+            if !page.archived {
+                documents.push(self.document(page).await?);
+            }
+        }
+
+        let next = search
+            .next_cursor
+            .filter(|_| search.has_more && !reached_synced);
+        let has_more = next.is_some();
+        let cursor = if has_more {
+            NotionCursor {
+                since: state.since,
+                newest,
+                next,
+            }
+        } else {
+            NotionCursor {
+                since: newest.or(state.since),
+                ..Default::default()
+            }
+        };
+
+        Ok(SyncBatch {
+            documents,
+            cursor: Some(serde_json::to_string(&cursor)?),
+            has_more,
+        })
+    }
+}
+
+/// Concatenated plain text of a rich text array
+fn rich_text(value: &Value) -> String {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|span| span["plain_text"].as_str())
+        .collect()
+}
+
+/// Markdown for one block, or `None` for unsupported block types
+fn render_block(block: &Value) -> Option<String> {
+    let kind = block["type"].as_str()?;
+    let data = &block[kind];
+    let text = rich_text(&data["rich_text"]);
+
+    let line = match kind {
+        "paragraph" | "callout" | "toggle" => text,
+        "heading_1" => format!("# {}", text),
+        "heading_2" => format!("## {}", text),
+        "heading_3" => format!("### {}", text),
+        "bulleted_list_item" => format!("- {}", text),
+        "numbered_list_item" => format!("1. {}", text),
+        "to_do" if data["checked"] == true => format!("- [x] {}", text),
+        "to_do" => format!("- [ ] {}", text),
+        "quote" => format!("> {}", text),
+        "code" => format!(
+            "```{}\n{}\n```",
+            data["language"].as_str().unwrap_or_default(),
+            text
+        ),
+        "divider" => "---".to_string(),
+        _ => return None,
+    };
+    Some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notion_connector_new_without_token() {
+        std::env::remove_var("NOTION_TOKEN");
+        assert!(NotionConnector::new(&NotionConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_render_blocks() {
+        let heading = serde_json::json!({
+            "type": "heading_2",
+            "heading_2": { "rich_text": [{ "plain_text": "Deploys" }] }
+        });
+        let todo = serde_json::json!({
+            "type": "to_do",
+            "to_do": {
+                "rich_text": [{ "plain_text": "Tag " }, { "plain_text": "release" }],
+                "checked": true
+            }
+        });
+        let image = serde_json::json!({ "type": "image", "image": {} });
+
+        assert_eq!(render_block(&heading).as_deref(), Some("## Deploys"));
+        assert_eq!(render_block(&todo).as_deref(), Some("- [x] Tag release"));
+        assert_eq!(render_block(&image), None);
+    }
+
+    #[test]
+    fn test_page_title() {
+        let page: NotionPage = serde_json::from_value(serde_json::json!({
+            "id": "1c2b3a4d-0000-0000-0000-000000000000",
+            "last_edited_time": "2024-05-01T12:00:00.000Z",
+            "properties": {
+                "Status": { "type": "select", "select": null },
+                "Name": { "type": "title", "title": [{ "plain_text": "On-call guide" }] }
+            }
+        }))
+        .unwrap();
+        assert_eq!(page.title(), "On-call guide");
+    }
+}
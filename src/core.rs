@@ -2,7 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
 use crate::digest::Digest;
@@ -23,6 +23,14 @@ pub enum Namespace {
 }
 
 impl Namespace {
+    /// Every namespace, in declaration order
+    pub const ALL: [Namespace; 4] = [
+        Namespace::Knowledge,
+        Namespace::Memory,
+        Namespace::Capability,
+        Namespace::Session,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Namespace::Knowledge => "knowledge",
@@ -43,6 +51,45 @@ impl Namespace {
     }
 }
 
+/// Metadata key holding questions a node answers, embedded as its
+/// [`VectorName::Questions`] vector
+pub const QUESTIONS_KEY: &str = "questions";
+
+/// A named view of a node that can be embedded and searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorName {
+    /// Full content, stored in `Node::embedding`
+    Content,
+    /// Digest summary
+    Summary,
+    /// First heading, or the file name
+    Title,
+    /// Questions listed under [`QUESTIONS_KEY`]
+    Questions,
+}
+
+impl VectorName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorName::Content => "content",
+            VectorName::Summary => "summary",
+            VectorName::Title => "title",
+            VectorName::Questions => "questions",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "content" => Some(VectorName::Content),
+            "summary" => Some(VectorName::Summary),
+            "title" => Some(VectorName::Title),
+            "questions" => Some(VectorName::Questions),
+            _ => None,
+        }
+    }
+}
+
 /// Kind of node content
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -86,9 +133,13 @@ pub struct Node {
     /// Full content (may be empty for directories)
     pub content: String,
 
-    /// Embedding vector
+    /// Embedding vector of the content
     pub embedding: Vec<f32>,
 
+    /// Embeddings of the node's other named views
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vectors: BTreeMap<VectorName, Vec<f32>>,
+
     /// Metadata
     pub metadata: Metadata,
 
@@ -113,6 +164,7 @@ impl Node {
             digest: Digest::default(),
             content,
             embedding: Vec::new(),
+            vectors: BTreeMap::new(),
             metadata: Metadata::default(),
             created_at: Utc::now(),
             updated_at: Utc::now(),
@@ -130,6 +182,7 @@ impl Node {
             digest: Digest::default(),
             content: String::new(),
             embedding: Vec::new(),
+            vectors: BTreeMap::new(),
             metadata: Metadata::default(),
             created_at: Utc::now(),
             updated_at: Utc::now(),
@@ -152,6 +205,15 @@ impl Node {
         !self.embedding.is_empty()
     }
 
+    /// Embedding of a named view, if it has been computed
+    pub fn vector(&self, name: VectorName) -> Option<&[f32]> {
+        let vector = match name {
+            VectorName::Content => &self.embedding,
+            _ => self.vectors.get(&name)?,
+        };
+        (!vector.is_empty()).then_some(vector.as_slice())
+    }
+
     /// Update the content and reset digest
     pub fn update_content(&mut self, content: String) {
         self.content = content;
@@ -187,6 +249,22 @@ pub struct Metadata {
 
     /// Tags
     pub tags: Vec<String>,
+
+    /// When the node stops being returned by retrieval
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Metadata {
+    /// Whether the expiry time has passed
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Utc::now())
+    }
+
+    /// Whether the expiry time has passed as of `now`
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
 }
 
 /// Source information for ingested content
@@ -201,8 +279,31 @@ pub struct SourceInfo {
     /// File size
     pub size: u64,
 
-    /// Hash of original content
+    /// SHA-256 of original content
     pub hash: String,
+
+    /// When the content was retrieved from its origin
+    #[serde(default)]
+    pub retrieved_at: Option<DateTime<Utc>>,
+
+    /// Signature over the provenance record
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl SourceInfo {
+    /// Canonical string covered by the provenance signature
+    pub fn signing_payload(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}",
+            self.origin,
+            self.hash,
+            self.size,
+            self.retrieved_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default()
+        )
+    }
 }
 
 /// Relation between nodes
@@ -257,10 +358,7 @@ mod tests {
     fn test_namespace_from_str() {
         assert_eq!(Namespace::parse("knowledge"), Some(Namespace::Knowledge));
         assert_eq!(Namespace::parse("memory"), Some(Namespace::Memory));
-        assert_eq!(
-            Namespace::parse("capability"),
-            Some(Namespace::Capability)
-        );
+        assert_eq!(Namespace::parse("capability"), Some(Namespace::Capability));
         assert_eq!(Namespace::parse("session"), Some(Namespace::Session));
         assert_eq!(Namespace::parse("invalid"), None);
     }
@@ -319,6 +417,23 @@ mod tests {
         assert!(node.is_embedded());
     }
 
+    #[test]
+    fn test_node_named_vectors() {
+        let pathway = Pathway::parse("a3s://knowledge/test").unwrap();
+        let mut node = Node::new(pathway, NodeKind::Document, "Test".to_string());
+        node.embedding = vec![1.0, 0.0];
+        node.vectors.insert(VectorName::Title, vec![0.0, 1.0]);
+
+        assert_eq!(node.vector(VectorName::Content), Some(&[1.0, 0.0][..]));
+        assert_eq!(node.vector(VectorName::Title), Some(&[0.0, 1.0][..]));
+        assert_eq!(node.vector(VectorName::Summary), None);
+
+        let json = serde_json::to_string(&node).unwrap();
+        let restored: Node = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.vectors, node.vectors);
+        assert_eq!(VectorName::parse("title"), Some(VectorName::Title));
+    }
+
     #[test]
     fn test_node_update_content() {
         let pathway = Pathway::parse("a3s://knowledge/test").unwrap();
@@ -0,0 +1,325 @@
+//! Near-duplicate detection and merging within a namespace
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Namespace, Node};
+use crate::error::{A3SError, Result};
+use crate::lease::LeaseTable;
+use crate::pathway::Pathway;
+use crate::policy::PolicyChain;
+use crate::provenance::sha256_hex;
+use crate::retrieval::cosine_similarity;
+use crate::storage::StorageBackend;
+
+/// Near-duplicate groups found in a namespace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub namespace: Namespace,
+    /// Non-directory nodes compared
+    pub nodes_scanned: usize,
+    pub groups: Vec<DuplicateGroup>,
+}
+
+/// Nodes with identical or near-identical content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// Suggested node to keep: most accessed, then most recently updated
+    pub canonical: Pathway,
+    /// Nodes that would be merged into the canonical node
+    pub duplicates: Vec<Pathway>,
+    /// Lowest similarity between the canonical node and a duplicate
+    pub min_similarity: f32,
+    /// Every node in the group has byte-identical content
+    pub exact: bool,
+}
+
+struct Cluster {
+    members: Vec<Node>,
+    hashes: Vec<String>,
+    /// Embedding of the first member that has one
+    embedding: Vec<f32>,
+}
+
+/// Group nodes in a namespace by content hash and embedding similarity
+///
+/// Each node joins the first group holding a node with the same content
+/// hash, or whose first embedded member is at least `similarity_threshold`
+/// similar to it.
+pub async fn find_duplicates(
+    storage: &dyn StorageBackend,
+    namespace: Namespace,
+    similarity_threshold: f32,
+) -> Result<DuplicateReport> {
+    let mut nodes: Vec<Node> = storage
+        .get_children(&Pathway::root(namespace), usize::MAX)
+        .await?
+        .into_iter()
+        .filter(|node| !node.is_directory && !node.content.is_empty())
+        .collect();
+    nodes.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+    let nodes_scanned = nodes.len();
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+    for node in nodes {
+        let hash = sha256_hex(node.content.as_bytes());
+        let index = by_hash.get(&hash).copied().or_else(|| {
+            clusters.iter().position(|cluster| {
+                cosine_similarity(&cluster.embedding, &node.embedding) >= similarity_threshold
+            })
+        });
+
+        let index = match index {
+            Some(index) => index,
+            None => {
+                clusters.push(Cluster {
+                    members: Vec::new(),
+                    hashes: Vec::new(),
+                    embedding: Vec::new(),
+                });
+                clusters.len() - 1
+            }
+        };
+        let cluster = &mut clusters[index];
+        if cluster.embedding.is_empty() {
+            cluster.embedding = node.embedding.clone();
+        }
+        by_hash.entry(hash.clone()).or_insert(index);
+        cluster.hashes.push(hash);
+        cluster.members.push(node);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = clusters
+        .into_iter()
+        .filter(|cluster| cluster.members.len() > 1)
+        .map(to_group)
+        .collect();
+    groups.sort_by(|a, b| a.canonical.cmp(&b.canonical));
+
+    Ok(DuplicateReport {
+        namespace,
+        nodes_scanned,
+        groups,
+    })
+}
+
+fn to_group(cluster: Cluster) -> DuplicateGroup {
+    let exact = cluster.hashes.iter().all(|hash| *hash == cluster.hashes[0]);
+    let mut members = cluster.members;
+    members.sort_by(|a, b| {
+        b.metadata
+            .access_count
+            .cmp(&a.metadata.access_count)
+            .then(b.updated_at.cmp(&a.updated_at))
+            .then(a.pathway.depth().cmp(&b.pathway.depth()))
+    });
+
+    let canonical = members.remove(0);
+    let min_similarity = if exact {
+        1.0
+    } else {
+        members
+            .iter()
+            .map(|node| cosine_similarity(&canonical.embedding, &node.embedding))
+            .fold(1.0, f32::min)
+    };
+
+    DuplicateGroup {
+        canonical: canonical.pathway,
+        duplicates: members.into_iter().map(|node| node.pathway).collect(),
+        min_similarity,
+        exact,
+    }
+}
+
+/// Fold a group's duplicates into its canonical node and remove them
+///
+/// The canonical node gains the duplicates' tags and outgoing relations,
+/// and relations elsewhere that pointed at a duplicate are redirected to
+/// the canonical node. Every rewritten node passes the write policies
+/// before any is stored, and the merge fails before any write if another
+/// writer leases one. Returns the number of nodes removed.
+pub async fn merge(
+    storage: &dyn StorageBackend,
+    policies: &PolicyChain,
+    leases: &LeaseTable,
+    group: &DuplicateGroup,
+) -> Result<usize> {
+    let mut canonical = storage.get(&group.canonical).await?;
+
+    for pathway in &group.duplicates {
+        let duplicate = storage.get(pathway).await?;
+        for tag in duplicate.metadata.tags {
+            if !canonical.metadata.tags.contains(&tag) {
+                canonical.metadata.tags.push(tag);
+            }
+        }
+        for relation in duplicate.relations {
+            if relation.target == canonical.pathway {
+                continue;
+            }
+            let known = canonical
+                .relations
+                .iter()
+                .any(|r| r.target == relation.target && r.kind == relation.kind);
+            if !known {
+                canonical.relations.push(relation);
+            }
+        }
+    }
+    canonical
+        .relations
+        .retain(|r| !group.duplicates.contains(&r.target));
+    let canonical = policies.apply(canonical).await?;
+
+    // Redirect incoming relations in every namespace
+    let mut retargeted = Vec::new();
+    for namespace in Namespace::ALL {
+        for mut node in storage
+            .get_children(&Pathway::root(namespace), usize::MAX)
+            .await?
+        {
+            if node.pathway == canonical.pathway || group.duplicates.contains(&node.pathway) {
+                continue;
+            }
+            let mut changed = false;
+            for relation in &mut node.relations {
+                if group.duplicates.contains(&relation.target) {
+                    relation.target = canonical.pathway.clone();
+                    changed = true;
+                }
+            }
+            if changed {
+                let mut seen = Vec::new();
+                node.relations.retain(|r| {
+                    let key = (r.target.clone(), r.kind);
+                    let new = !seen.contains(&key);
+                    seen.push(key);
+                    new
+                });
+                leases.check(&node.pathway, false)?;
+                retargeted.push(policies.apply(node).await?);
+            }
+        }
+    }
+
+    storage.put(&canonical).await?;
+    for node in &retargeted {
+        // Mounted stores cannot be rewritten and keep their links
+        match storage.put(node).await {
+            Err(A3SError::ReadOnly(_)) => {}
+            result => result?,
+        }
+    }
+    for pathway in &group.duplicates {
+        storage.remove(pathway, false).await?;
+    }
+
+    Ok(group.duplicates.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::{NodeKind, RelationKind};
+    use crate::storage::MemoryStorage;
+
+    fn node(pathway: &str, content: &str, embedding: Vec<f32>) -> Node {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            content.to_string(),
+        );
+        node.embedding = embedding;
+        node
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_groups_by_hash_and_similarity() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let mut popular = node("a3s://knowledge/a", "same text", vec![1.0, 0.0]);
+        popular.metadata.access_count = 5;
+        storage.put(&popular).await.unwrap();
+        // Same content but no embedding: only the hash can match it
+        storage
+            .put(&node("a3s://knowledge/b", "same text", Vec::new()))
+            .await
+            .unwrap();
+        storage
+            .put(&node("a3s://knowledge/c", "similar text", vec![0.99, 0.1]))
+            .await
+            .unwrap();
+        storage
+            .put(&node("a3s://knowledge/d", "unrelated", vec![0.0, 1.0]))
+            .await
+            .unwrap();
+
+        let report = find_duplicates(&storage, Namespace::Knowledge, 0.95)
+            .await
+            .unwrap();
+        assert_eq!(report.nodes_scanned, 4);
+        assert_eq!(report.groups.len(), 1);
+
+        let group = &report.groups[0];
+        assert_eq!(group.canonical.to_string(), "a3s://knowledge/a");
+        assert_eq!(group.duplicates.len(), 2);
+        assert!(!group.exact);
+    }
+
+    #[tokio::test]
+    async fn test_merge_preserves_relations() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let canonical = node("a3s://knowledge/a", "same", vec![1.0, 0.0]);
+        let mut duplicate = node("a3s://knowledge/b", "same", vec![1.0, 0.0]);
+        duplicate.metadata.tags.push("ops".to_string());
+        duplicate.add_relation(
+            Pathway::parse("a3s://knowledge/c").unwrap(),
+            RelationKind::References,
+            "cites".to_string(),
+        );
+        let mut referrer = node("a3s://memory/u/x", "note", Vec::new());
+        referrer.add_relation(
+            duplicate.pathway.clone(),
+            RelationKind::RelatedTo,
+            "about".to_string(),
+        );
+        for n in [&canonical, &duplicate, &referrer] {
+            storage.put(n).await.unwrap();
+        }
+
+        let group = DuplicateGroup {
+            canonical: canonical.pathway.clone(),
+            duplicates: vec![duplicate.pathway.clone()],
+            min_similarity: 1.0,
+            exact: true,
+        };
+        // A lease on the referrer stops the merge before any write
+        let leases = LeaseTable::default();
+        let lease = leases
+            .acquire(referrer.pathway.clone(), std::time::Duration::from_secs(60))
+            .unwrap();
+        let err = merge(&storage, &PolicyChain::new(), &leases, &group)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, A3SError::Leased(_)));
+        assert!(storage.exists(&duplicate.pathway).await.unwrap());
+        leases.release(&lease);
+
+        assert_eq!(
+            merge(&storage, &PolicyChain::new(), &leases, &group)
+                .await
+                .unwrap(),
+            1
+        );
+
+        assert!(!storage.exists(&duplicate.pathway).await.unwrap());
+        let merged = storage.get(&canonical.pathway).await.unwrap();
+        assert_eq!(merged.metadata.tags, vec!["ops".to_string()]);
+        assert_eq!(merged.relations.len(), 1);
+        let referrer = storage.get(&referrer.pathway).await.unwrap();
+        assert_eq!(referrer.relations[0].target, canonical.pathway);
+    }
+}
@@ -0,0 +1,117 @@
+//! Differences between the content of two nodes
+//!
+//! A [`ContentDiff`] pairs a unified line diff with the similarity of the
+//! two contents' embeddings, so an agent reconciling an updated document
+//! with stored knowledge can tell a reworded passage, which stays close in
+//! meaning, from one that now says something else.
+
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+use crate::core::Node;
+use crate::pathway::Pathway;
+
+/// Lines of unchanged context around each change in a unified diff
+const CONTEXT_LINES: usize = 3;
+
+/// Line diff and semantic similarity between two nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentDiff {
+    /// Node diffed from
+    pub from: Pathway,
+    /// Node diffed to
+    pub to: Pathway,
+    /// Unified diff from `from` to `to`; empty when the contents are equal
+    pub unified: String,
+    /// Lines only in `to`
+    pub insertions: usize,
+    /// Lines only in `from`
+    pub deletions: usize,
+    /// Cosine similarity of the contents' embeddings
+    pub similarity: f32,
+}
+
+impl ContentDiff {
+    /// Whether the contents are line-for-line equal
+    pub fn is_identical(&self) -> bool {
+        self.insertions == 0 && self.deletions == 0
+    }
+}
+
+/// Diff the content of `from` against `to`, given their embedding similarity
+pub fn diff(from: &Node, to: &Node, similarity: f32) -> ContentDiff {
+    let lines = TextDiff::from_lines(&from.content, &to.content);
+    let (mut insertions, mut deletions) = (0, 0);
+    for change in lines.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => insertions += 1,
+            ChangeTag::Delete => deletions += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+
+    let unified = if insertions + deletions == 0 {
+        String::new()
+    } else {
+        lines
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&from.pathway.to_string(), &to.pathway.to_string())
+            .to_string()
+    };
+
+    ContentDiff {
+        from: from.pathway.clone(),
+        to: to.pathway.clone(),
+        unified,
+        insertions,
+        deletions,
+        similarity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+
+    fn node(pathway: &str, content: &str) -> Node {
+        Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            content.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let old = node(
+            "a3s://knowledge/docs/auth",
+            "Tokens expire after one hour.\nRefresh on use.\n",
+        );
+        let new = node(
+            "a3s://knowledge/imports/auth",
+            "Tokens expire after two hours.\nRefresh on use.\nRevoke on logout.\n",
+        );
+        let diff = diff(&old, &new, 0.9);
+
+        assert_eq!((diff.insertions, diff.deletions), (2, 1));
+        assert!(!diff.is_identical());
+        assert!(diff
+            .unified
+            .starts_with("--- a3s://knowledge/docs/auth\n+++ a3s://knowledge/imports/auth\n"));
+        assert!(diff.unified.contains("-Tokens expire after one hour.\n"));
+        assert!(diff.unified.contains("+Tokens expire after two hours.\n"));
+        assert!(diff.unified.contains(" Refresh on use.\n"));
+        assert_eq!(diff.similarity, 0.9);
+    }
+
+    #[test]
+    fn test_identical_content() {
+        let a = node("a3s://knowledge/a", "Same text\n");
+        let b = node("a3s://knowledge/b", "Same text\n");
+        let diff = diff(&a, &b, 1.0);
+        assert!(diff.is_identical());
+        assert!(diff.unified.is_empty());
+    }
+}
@@ -1,6 +1,26 @@
 //! Multi-level digest generation for efficient context retrieval
 
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "llm-digest")]
+use std::time::Duration;
+
+use crate::tokenizer::{HeuristicTokenizer, Tokenizer};
+
+/// Longest brief, in tokens
+pub const BRIEF_TOKENS: usize = 50;
+
+/// Longest summary, in tokens
+pub const SUMMARY_TOKENS: usize = 500;
+
+/// Most content sent to the LLM for a brief, in tokens
+#[cfg(feature = "llm-digest")]
+const BRIEF_INPUT_TOKENS: usize = 1000;
+
+/// Most content sent to the LLM for a summary, in tokens
+#[cfg(feature = "llm-digest")]
+const SUMMARY_INPUT_TOKENS: usize = 2000;
 
 /// Multi-level digest for a node
 ///
@@ -53,7 +73,8 @@ impl Digest {
 }
 
 /// Level of digest detail
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DigestLevel {
     /// Brief summary only
     Brief,
@@ -64,44 +85,129 @@ pub enum DigestLevel {
 }
 
 /// Generator for creating digests from content
+///
+/// Briefs and summaries are cut to [`BRIEF_TOKENS`] and [`SUMMARY_TOKENS`]
+/// as counted by its tokenizer, [`HeuristicTokenizer`] unless another is set
+/// with [`DigestGenerator::with_tokenizer`].
 pub struct DigestGenerator {
+    #[cfg(feature = "llm-digest")]
     llm_client: Option<LLMClient>,
+    tokenizer: Arc<dyn Tokenizer>,
 }
 
 impl DigestGenerator {
     /// Create a new digest generator
+    #[cfg(feature = "llm-digest")]
     pub fn new(llm_client: Option<LLMClient>) -> Self {
-        Self { llm_client }
+        Self {
+            llm_client,
+            tokenizer: Arc::new(HeuristicTokenizer),
+        }
+    }
+
+    /// Create a generator that extracts digests without an LLM
+    pub fn simple() -> Self {
+        Self {
+            #[cfg(feature = "llm-digest")]
+            llm_client: None,
+            tokenizer: Arc::new(HeuristicTokenizer),
+        }
+    }
+
+    /// Count digest and LLM input lengths with `tokenizer`
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
     }
 
     /// Generate a digest for the given content
+    #[cfg_attr(not(feature = "llm-digest"), allow(unused_variables))]
     pub async fn generate(
         &self,
         content: &str,
         kind: crate::core::NodeKind,
     ) -> crate::Result<Digest> {
+        #[cfg(feature = "llm-digest")]
+        if let Some(llm) = &self.llm_client {
+            return self.generate_with_llm(llm, content, kind).await;
+        }
+
         // If no LLM client, use simple extraction
-        if self.llm_client.is_none() {
-            return Ok(self.generate_simple(content));
+        Ok(self.generate_simple(content))
+    }
+
+    /// Generate a digest for a document from its chunks, the way a
+    /// directory is summarized from its children
+    ///
+    /// The summary covers every chunk instead of only the opening of the
+    /// document; the brief is still the document's first sentence, or an LLM
+    /// summary of the outline.
+    #[cfg_attr(not(feature = "llm-digest"), allow(unused_variables))]
+    pub async fn generate_from_chunks(
+        &self,
+        chunks: &[&str],
+        kind: crate::core::NodeKind,
+    ) -> crate::Result<Digest> {
+        let outline = chunks
+            .iter()
+            .map(|chunk| extract_first_sentence(chunk))
+            .filter(|sentence| !sentence.is_empty())
+            .map(|sentence| format!("- {}", sentence))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        #[cfg(feature = "llm-digest")]
+        if let Some(llm) = &self.llm_client {
+            return self.generate_with_llm(llm, &outline, kind).await;
         }
 
-        let llm = self.llm_client.as_ref().unwrap();
+        let brief = chunks
+            .first()
+            .map(|chunk| self.brief(chunk))
+            .unwrap_or_default();
+        Ok(Digest::with_content(
+            brief,
+            self.tokenizer
+                .truncate(&outline, SUMMARY_TOKENS)
+                .to_string(),
+        ))
+    }
+
+    #[cfg(feature = "llm-digest")]
+    async fn generate_with_llm(
+        &self,
+        llm: &LLMClient,
+        content: &str,
+        kind: crate::core::NodeKind,
+    ) -> crate::Result<Digest> {
+        // Answer in the content's own language
+        let respond_in = match kind {
+            crate::core::NodeKind::Code => None,
+            _ => crate::language::detect(content).and_then(crate::language::name),
+        }
+        .filter(|name| *name != "English")
+        .map(|name| format!(" Write it in {}.", name))
+        .unwrap_or_default();
 
         // Generate brief summary
         let brief_prompt = format!(
-            "Summarize the following {} in one concise sentence (max 50 tokens):\n\n{}",
+            "Summarize the following {} in one concise sentence (max {} tokens).{}\n\n{}",
             kind_to_str(kind),
-            truncate(content, 4000)
+            BRIEF_TOKENS,
+            respond_in,
+            self.tokenizer.truncate(content, BRIEF_INPUT_TOKENS)
         );
 
         let brief = llm.complete(&brief_prompt).await?;
 
         // Generate medium summary
         let summary_prompt = format!(
-            "Provide a comprehensive summary of the following {} (max 500 tokens). \
-             Include key points, main concepts, and important details:\n\n{}",
+            "Provide a comprehensive summary of the following {} (max {} tokens). \
+             Include key points, main concepts, and important details.{}\n\n{}",
             kind_to_str(kind),
-            truncate(content, 8000)
+            SUMMARY_TOKENS,
+            respond_in,
+            self.tokenizer.truncate(content, SUMMARY_INPUT_TOKENS)
         );
 
         let summary = llm.complete(&summary_prompt).await?;
@@ -111,32 +217,69 @@ impl DigestGenerator {
 
     /// Generate a simple digest without LLM
     fn generate_simple(&self, content: &str) -> Digest {
-        let brief = extract_first_sentence(content);
-        let summary = truncate(content, 2000).to_string();
+        let brief = self.brief(content);
+        let summary = self.tokenizer.truncate(content, SUMMARY_TOKENS).to_string();
 
         Digest::with_content(brief, summary)
     }
+
+    /// First sentence of `content`, within [`BRIEF_TOKENS`]
+    fn brief(&self, content: &str) -> String {
+        let sentence = extract_first_sentence(content);
+        self.tokenizer
+            .truncate(&sentence, BRIEF_TOKENS)
+            .trim_end()
+            .to_string()
+    }
 }
 
+#[cfg(feature = "llm-digest")]
 /// Simple LLM client interface
 pub struct LLMClient {
     api_base: String,
     api_key: String,
     model: String,
+    timeout_secs: u64,
+    http: crate::http::HttpClient,
+    limiter: std::sync::Arc<crate::http::RequestLimiter>,
 }
 
+#[cfg(feature = "llm-digest")]
 impl LLMClient {
     pub fn new(api_base: String, api_key: String, model: String) -> Self {
         Self {
             api_base,
             api_key,
             model,
+            timeout_secs: 60,
+            http: crate::http::default_client(),
+            limiter: std::sync::Arc::new(crate::http::RequestLimiter::new(
+                &crate::config::RequestPolicyConfig::default(),
+            )),
         }
     }
 
-    pub async fn complete(&self, prompt: &str) -> crate::Result<String> {
-        let client = reqwest::Client::new();
+    /// Retry, limit, and throttle requests as `policy` says, sharing the
+    /// limits with every client of the same endpoint and model
+    pub fn with_request_policy(mut self, policy: &crate::config::RequestPolicyConfig) -> Self {
+        self.limiter =
+            crate::http::shared_limiter(&limiter_key(&self.api_base, &self.model), policy);
+        self
+    }
+
+    /// Send requests through the given shared client
+    pub fn with_http_client(mut self, http: crate::http::HttpClient) -> Self {
+        self.http = http;
+        self
+    }
 
+    /// Fail requests that take longer than `timeout_secs`
+    pub fn with_timeout(mut self, timeout_secs: u64) -> Self {
+        self.timeout_secs = timeout_secs;
+        self
+    }
+
+    pub async fn complete(&self, prompt: &str) -> crate::Result<String> {
         let body = serde_json::json!({
             "model": self.model,
             "messages": [
@@ -146,11 +289,16 @@ impl LLMClient {
             "max_tokens": 1000,
         });
 
-        let response = client
-            .post(format!("{}/chat/completions", self.api_base))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&body)
-            .send()
+        let url = format!("{}/chat/completions", self.api_base);
+        let response = self
+            .limiter
+            .send(|| {
+                self.http
+                    .post(&url)
+                    .timeout(Duration::from_secs(self.timeout_secs))
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .json(&body)
+            })
             .await?;
 
         if !response.status().is_success() {
@@ -172,6 +320,12 @@ impl LLMClient {
     }
 }
 
+#[cfg(feature = "llm-digest")]
+fn limiter_key(api_base: &str, model: &str) -> String {
+    format!("{}/chat/completions {}", api_base, model)
+}
+
+#[cfg(feature = "llm-digest")]
 fn kind_to_str(kind: crate::core::NodeKind) -> &'static str {
     match kind {
         crate::core::NodeKind::Document => "document",
@@ -185,12 +339,12 @@ fn kind_to_str(kind: crate::core::NodeKind) -> &'static str {
     }
 }
 
-fn truncate(s: &str, max_chars: usize) -> &str {
-    if s.len() <= max_chars {
-        s
-    } else {
-        &s[..max_chars]
-    }
+/// Largest char boundary at or below `index`
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    (0..=index.min(s.len()))
+        .rev()
+        .find(|&i| s.is_char_boundary(i))
+        .unwrap_or(0)
 }
 
 fn extract_first_sentence(s: &str) -> String {
@@ -210,7 +364,7 @@ fn extract_first_sentence(s: &str) -> String {
     }
 
     // Limit to 200 chars
-    let end = min_pos.min(200);
+    let end = floor_char_boundary(s, min_pos.min(200));
     s[..end].trim().to_string()
 }
 
@@ -248,17 +402,51 @@ mod tests {
         assert_eq!(extract_first_sentence(text), "This has no sentence ending");
     }
 
-    #[test]
-    fn test_truncate() {
-        let text = "Hello, world!";
-        assert_eq!(truncate(text, 5), "Hello");
-        assert_eq!(truncate(text, 100), "Hello, world!");
+    #[tokio::test]
+    async fn test_generate_from_chunks() {
+        let digest = DigestGenerator::simple()
+            .generate_from_chunks(
+                &[
+                    "Deploys run nightly. They take an hour.",
+                    "",
+                    "Rollbacks restore the last release. Ask on-call first.",
+                ],
+                crate::core::NodeKind::Markdown,
+            )
+            .await
+            .unwrap();
+        assert_eq!(digest.brief, "Deploys run nightly.");
+        assert_eq!(
+            digest.summary,
+            "- Deploys run nightly.\n- Rollbacks restore the last release."
+        );
     }
 
     #[test]
-    fn test_truncate_exact() {
-        let text = "Hello";
-        assert_eq!(truncate(text, 5), "Hello");
+    fn test_extract_first_sentence_multibyte() {
+        assert_eq!(extract_first_sentence(&"ü".repeat(150)), "ü".repeat(100));
+    }
+
+    #[tokio::test]
+    async fn test_digest_lengths_in_tokens() {
+        use crate::tokenizer::CharTokenizer;
+
+        let content = format!("{} Second sentence.", "word ".repeat(600));
+        let digest = DigestGenerator::simple()
+            .generate(&content, crate::core::NodeKind::Document)
+            .await
+            .unwrap();
+        assert_eq!(digest.summary.chars().count(), SUMMARY_TOKENS * 4);
+
+        // Counted one token per character, the same digest is shorter
+        let digest = DigestGenerator::simple()
+            .with_tokenizer(Arc::new(CharTokenizer))
+            .generate(&content, crate::core::NodeKind::Document)
+            .await
+            .unwrap();
+        assert_eq!(digest.summary.chars().count(), SUMMARY_TOKENS);
+        assert!(digest.brief.chars().count() <= BRIEF_TOKENS);
+        assert!(digest.brief.starts_with("word word"));
     }
 
     #[test]
@@ -288,6 +476,7 @@ mod tests {
         assert_eq!(digest.get_level(2000), DigestLevel::Full);
     }
 
+    #[cfg(feature = "llm-digest")]
     #[test]
     fn test_kind_to_str() {
         assert_eq!(kind_to_str(crate::core::NodeKind::Document), "document");
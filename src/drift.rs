@@ -0,0 +1,144 @@
+//! Detecting stores embedded with a different model than configured
+//!
+//! The embedding model and dimension a store is filled with are recorded on
+//! an unembedded node at [`EMBEDDING_RECORD`] before a client first writes
+//! embeddings to it. Clients opening the store compare their configuration
+//! against the record: vectors from two models are not comparable, so a
+//! mismatch would otherwise surface only as meaningless similarities.
+//! Stores filled before the record existed adopt the model of the next
+//! client that writes to them.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::EmbeddingConfig;
+use crate::core::{Node, NodeKind};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+
+/// Pathway of the node recording the store's embedding model
+pub const EMBEDDING_RECORD: &str = "a3s://capability/embedding-model";
+
+/// Metadata key holding the [`EmbeddingModel`] on the record node
+const EMBEDDING_MODEL_KEY: &str = "embedding_model";
+
+/// Model and dimension of a set of embeddings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingModel {
+    pub model: String,
+    pub dimension: usize,
+}
+
+impl EmbeddingModel {
+    pub fn of(config: &EmbeddingConfig) -> Self {
+        Self {
+            model: config.model.clone(),
+            dimension: config.dimension,
+        }
+    }
+}
+
+/// The store's recorded embedding model differs from the configured one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingDrift {
+    pub stored: EmbeddingModel,
+    pub configured: EmbeddingModel,
+}
+
+impl std::fmt::Display for EmbeddingDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "store was embedded with {} ({} dimensions), configuration uses {} ({} dimensions)",
+            self.stored.model,
+            self.stored.dimension,
+            self.configured.model,
+            self.configured.dimension
+        )
+    }
+}
+
+/// The embedding model recorded for the store, if any
+pub async fn stored(storage: &dyn StorageBackend) -> Result<Option<EmbeddingModel>> {
+    match storage.get(&record_pathway()?).await {
+        Ok(node) => Ok(node
+            .metadata
+            .custom
+            .get(EMBEDDING_MODEL_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())),
+        Err(A3SError::NodeNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Drift between the store's recorded model and the configured one
+pub fn compare(stored: EmbeddingModel, config: &EmbeddingConfig) -> Option<EmbeddingDrift> {
+    let configured = EmbeddingModel::of(config);
+    (stored != configured).then_some(EmbeddingDrift { stored, configured })
+}
+
+/// Record the configured model as the one the store is embedded with
+pub async fn record(storage: &dyn StorageBackend, config: &EmbeddingConfig) -> Result<()> {
+    let mut node = Node::new(record_pathway()?, NodeKind::Data, String::new());
+    node.metadata.custom.insert(
+        EMBEDDING_MODEL_KEY.to_string(),
+        serde_json::to_value(EmbeddingModel::of(config))?,
+    );
+    storage.put(&node).await
+}
+
+/// Record the configured model unless `recorded` says the store has a
+/// record already
+pub async fn record_once(
+    storage: &dyn StorageBackend,
+    config: &EmbeddingConfig,
+    recorded: &AtomicBool,
+) -> Result<()> {
+    if recorded.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    record(storage, config).await?;
+    recorded.store(true, Ordering::Release);
+    Ok(())
+}
+
+fn record_pathway() -> Result<Pathway> {
+    Pathway::parse(EMBEDDING_RECORD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::storage::MemoryStorage;
+
+    fn config(model: &str, dimension: usize) -> EmbeddingConfig {
+        EmbeddingConfig {
+            model: model.to_string(),
+            dimension,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compare_with_record() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let small = config("text-embedding-3-small", 1536);
+        assert!(stored(&storage).await.unwrap().is_none());
+
+        record(&storage, &small).await.unwrap();
+        let model = stored(&storage).await.unwrap().unwrap();
+        assert_eq!(model, EmbeddingModel::of(&small));
+        assert!(compare(model.clone(), &small).is_none());
+
+        let drift = compare(model, &config("bge-small-en", 384)).unwrap();
+        assert_eq!(
+            drift.to_string(),
+            "store was embedded with text-embedding-3-small (1536 dimensions), \
+             configuration uses bge-small-en (384 dimensions)"
+        );
+        assert!(compare(drift.stored, &config("text-embedding-3-small", 512)).is_some());
+    }
+}
@@ -0,0 +1,199 @@
+//! Email extraction from `.eml` files and `.mbox` mailboxes
+//!
+//! Each message becomes one node: its headers go into metadata and its
+//! plain-text body, with quoted replies removed, into content.
+
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use mail_parser::{Address, MessageParser};
+use regex::Regex;
+
+use crate::bulk::MetadataOp;
+use crate::error::{A3SError, Result};
+
+/// A parsed email message
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub message_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub subject: Option<String>,
+    /// Plain-text body without quoted replies
+    pub body: String,
+}
+
+impl EmailMessage {
+    /// Subject line followed by the body
+    pub fn content(&self) -> String {
+        match &self.subject {
+            Some(subject) => format!("Subject: {}\n\n{}", subject, self.body),
+            None => self.body.clone(),
+        }
+    }
+
+    /// Metadata updates recording the headers
+    pub fn metadata(&self) -> Vec<MetadataOp> {
+        let mut ops = vec![MetadataOp::SetCustom(
+            "to".to_string(),
+            serde_json::json!(self.to),
+        )];
+        let headers = [
+            ("from", self.from.as_ref().map(|v| serde_json::json!(v))),
+            (
+                "subject",
+                self.subject.as_ref().map(|v| serde_json::json!(v)),
+            ),
+            ("date", self.date.map(|v| serde_json::json!(v))),
+            (
+                "message_id",
+                self.message_id.as_ref().map(|v| serde_json::json!(v)),
+            ),
+        ];
+        ops.extend(
+            headers
+                .into_iter()
+                .filter_map(|(key, value)| Some(MetadataOp::SetCustom(key.to_string(), value?))),
+        );
+        ops
+    }
+}
+
+/// Whether a file extension is read as email
+pub fn is_email(extension: &str) -> bool {
+    matches!(extension, "eml" | "mbox")
+}
+
+/// Parse one RFC 5322 message
+pub fn parse_message(raw: &[u8]) -> Result<EmailMessage> {
+    let message = MessageParser::default()
+        .parse(raw)
+        .ok_or_else(|| A3SError::Ingest("Unparseable email message".to_string()))?;
+
+    Ok(EmailMessage {
+        message_id: message.message_id().map(str::to_string),
+        from: message.from().and_then(|a| addresses(a).into_iter().next()),
+        to: message.to().map(addresses).unwrap_or_default(),
+        date: message
+            .date()
+            .and_then(|d| DateTime::from_timestamp(d.to_timestamp(), 0)),
+        subject: message.subject().map(str::to_string),
+        body: strip_quoted(&message.body_text(0).unwrap_or_default()),
+    })
+}
+
+/// Split an mbox mailbox into its messages and parse each
+pub fn parse_mbox(raw: &[u8]) -> Vec<Result<EmailMessage>> {
+    split_mbox(&String::from_utf8_lossy(raw))
+        .iter()
+        .map(|message| parse_message(message.as_bytes()))
+        .collect()
+}
+
+/// Messages of an mbox, split on `From ` separator lines and unescaped
+fn split_mbox(mbox: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current: Option<String> = None;
+    for line in mbox.lines() {
+        if line.starts_with("From ") {
+            messages.extend(current.take());
+            current = Some(String::new());
+            continue;
+        }
+        if let Some(message) = current.as_mut() {
+            // mboxrd escapes body lines starting with "From " as ">From "
+            let line = match line.strip_prefix('>') {
+                Some(rest) if rest.trim_start_matches('>').starts_with("From ") => rest,
+                _ => line,
+            };
+            message.push_str(line);
+            message.push('\n');
+        }
+    }
+    messages.extend(current);
+    messages
+}
+
+fn addresses(address: &Address) -> Vec<String> {
+    address
+        .iter()
+        .filter_map(|addr| match (addr.name(), addr.address()) {
+            (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+            (None, Some(email)) => Some(email.to_string()),
+            (Some(name), None) => Some(name.to_string()),
+            (None, None) => None,
+        })
+        .collect()
+}
+
+/// Drop quoted lines, and everything from a reply attribution or forwarded
+/// original onwards
+fn strip_quoted(body: &str) -> String {
+    static ATTRIBUTION: OnceLock<Regex> = OnceLock::new();
+    let attribution = ATTRIBUTION
+        .get_or_init(|| Regex::new(r"(?i)^(on\s.+\swrote:|-+\s*original message\s*-+)$").unwrap());
+
+    let mut lines = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if attribution.is_match(trimmed) {
+            break;
+        }
+        if !trimmed.starts_with('>') {
+            lines.push(line.trim_end());
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "Message-ID: <1@example.com>\r\n\
+From: Ann Lee <ann@example.com>\r\n\
+To: ops@example.com, Bo <bo@example.com>\r\n\
+Date: Wed, 1 May 2024 10:00:00 +0000\r\n\
+Subject: Upload timeouts\r\n\
+\r\n\
+Raised the limit to 60s.\r\n\
+\r\n\
+On Tue, 30 Apr 2024, Bo wrote:\r\n\
+> Uploads keep timing out.\r\n";
+
+    #[test]
+    fn test_parse_message() {
+        let message = parse_message(MESSAGE.as_bytes()).unwrap();
+        assert_eq!(message.from.as_deref(), Some("Ann Lee <ann@example.com>"));
+        assert_eq!(message.to, vec!["ops@example.com", "Bo <bo@example.com>"]);
+        assert_eq!(message.subject.as_deref(), Some("Upload timeouts"));
+        assert_eq!(message.message_id.as_deref(), Some("1@example.com"));
+        assert_eq!(
+            message.date.unwrap().to_rfc3339(),
+            "2024-05-01T10:00:00+00:00"
+        );
+        assert_eq!(message.body, "Raised the limit to 60s.");
+    }
+
+    #[test]
+    fn test_split_mbox() {
+        let mbox = "From ann@example.com Wed May  1 10:00:00 2024\n\
+Subject: One\n\nFirst\n>From the logs\n\n\
+From bo@example.com Wed May  1 11:00:00 2024\n\
+Subject: Two\n\nSecond\n";
+        let messages = split_mbox(mbox);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("\nFrom the logs\n"));
+
+        let parsed = parse_mbox(mbox.as_bytes());
+        assert_eq!(parsed[1].as_ref().unwrap().subject.as_deref(), Some("Two"));
+    }
+
+    #[test]
+    fn test_strip_quoted() {
+        let body = "Sounds good.\n\n-----Original Message-----\nFrom: Bo\nShip it?";
+        assert_eq!(strip_quoted(body), "Sounds good.");
+        assert_eq!(strip_quoted("Yes\n> no\nagreed"), "Yes\nagreed");
+    }
+}
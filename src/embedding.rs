@@ -1,15 +1,39 @@
 //! Embedding model abstraction
 
 use async_trait::async_trait;
+#[cfg(feature = "openai")]
+use serde::Deserialize;
 use std::sync::Arc;
+#[cfg(feature = "openai")]
+use std::time::Duration;
 
 use crate::config::EmbeddingConfig;
-use crate::error::Result;
-
-/// Create an embedder based on configuration
-pub async fn create_embedder(config: &EmbeddingConfig) -> Result<Arc<dyn Embedder>> {
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+
+/// Create an embedder based on configuration, sending requests through `http`
+pub async fn create_embedder(
+    config: &EmbeddingConfig,
+    http: &HttpClient,
+) -> Result<Arc<dyn Embedder>> {
     match config.provider.as_str() {
-        "openai" => Ok(Arc::new(OpenAIEmbedder::new(config)?)),
+        #[cfg(feature = "openai")]
+        "openai" => Ok(Arc::new(
+            OpenAIEmbedder::new(config)?.with_http_client(http.clone()),
+        )),
+        #[cfg(not(feature = "openai"))]
+        "openai" => {
+            let _ = http;
+            Err(A3SError::Config(
+                "Embedding provider 'openai' requires the `openai` feature".to_string(),
+            ))
+        }
+        #[cfg(feature = "local-embedding")]
+        "local" => Ok(Arc::new(LocalEmbedder::new(config).await?)),
+        #[cfg(not(feature = "local-embedding"))]
+        "local" => Err(A3SError::Config(
+            "Embedding provider 'local' requires the `local-embedding` feature".to_string(),
+        )),
         "mock" => Ok(Arc::new(MockEmbedder::new(config.dimension))),
         _ => Err(crate::A3SError::Config(format!(
             "Unknown embedding provider: {}",
@@ -31,6 +55,7 @@ pub trait Embedder: Send + Sync {
     fn dimension(&self) -> usize;
 }
 
+#[cfg(feature = "openai")]
 /// OpenAI embedder implementation
 pub struct OpenAIEmbedder {
     api_base: String,
@@ -39,8 +64,12 @@ pub struct OpenAIEmbedder {
     dimension: usize,
     #[allow(dead_code)]
     batch_size: usize,
+    timeout_secs: u64,
+    http: HttpClient,
+    limiter: Arc<crate::http::RequestLimiter>,
 }
 
+#[cfg(feature = "openai")]
 impl OpenAIEmbedder {
     pub fn new(config: &EmbeddingConfig) -> Result<Self> {
         let api_base = config
@@ -54,61 +83,237 @@ impl OpenAIEmbedder {
             .or_else(|| std::env::var("OPENAI_API_KEY").ok())
             .ok_or_else(|| crate::A3SError::Config("No API key provided".to_string()))?;
 
+        let limiter = crate::http::shared_limiter(
+            &format!("{}/embeddings {}", api_base, config.model),
+            &config.requests,
+        );
         Ok(Self {
             api_base,
             api_key,
+            limiter,
             model: config.model.clone(),
             dimension: config.dimension,
             batch_size: config.batch_size,
+            timeout_secs: config.timeout_secs,
+            http: crate::http::default_client(),
         })
     }
+
+    /// Send requests through the given shared client
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
 }
 
+#[cfg(feature = "openai")]
 #[async_trait]
 impl Embedder for OpenAIEmbedder {
     async fn embed(&self, text: &str) -> Result<Vec<f32>> {
         let results = self.embed_batch(&[text.to_string()]).await?;
-        Ok(results.into_iter().next().unwrap())
+        results
+            .into_iter()
+            .next()
+            .ok_or_else(|| A3SError::Embedding("Empty embedding response".to_string()))
     }
 
     async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
-        let client = reqwest::Client::new();
-
         let body = serde_json::json!({
             "model": self.model,
             "input": texts,
         });
 
-        let response = client
-            .post(format!("{}/embeddings", self.api_base))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&body)
-            .send()
+        let url = format!("{}/embeddings", self.api_base);
+        let response = self
+            .limiter
+            .send(|| {
+                self.http
+                    .post(&url)
+                    .timeout(Duration::from_secs(self.timeout_secs))
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .json(&body)
+            })
             .await?;
 
         if !response.status().is_success() {
-            return Err(crate::A3SError::Embedding(format!(
-                "API error: {}",
-                response.status()
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(A3SError::Embedding(format!(
+                "API error {}: {}",
+                status,
+                snippet(&body)
             )));
         }
 
-        let result: serde_json::Value = response.json().await?;
-
-        let embeddings: Vec<Vec<f32>> = result["data"]
-            .as_array()
-            .ok_or_else(|| crate::A3SError::Embedding("Invalid response format".to_string()))?
-            .iter()
-            .map(|item| {
-                item["embedding"]
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|v| v.as_f64().unwrap() as f32)
-                    .collect()
-            })
-            .collect();
+        let body = response.text().await?;
+        parse_embedding_response(&body, texts.len())
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+}
+
+#[cfg(feature = "openai")]
+/// Longest slice of a malformed response body quoted in errors
+const MAX_ERROR_SNIPPET: usize = 200;
+
+#[cfg(feature = "openai")]
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
 
+#[cfg(feature = "openai")]
+#[derive(Deserialize)]
+struct EmbeddingData {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[cfg(feature = "openai")]
+/// Parse an OpenAI-compatible embeddings response, in input order
+fn parse_embedding_response(body: &str, expected: usize) -> Result<Vec<Vec<f32>>> {
+    let mut response: EmbeddingResponse = serde_json::from_str(body).map_err(|e| {
+        A3SError::Embedding(format!(
+            "Invalid response format: {} (body: {})",
+            e,
+            snippet(body)
+        ))
+    })?;
+
+    if response.data.len() != expected {
+        return Err(A3SError::Embedding(format!(
+            "Expected {} embeddings, got {} (body: {})",
+            expected,
+            response.data.len(),
+            snippet(body)
+        )));
+    }
+
+    response.data.sort_by_key(|d| d.index);
+    Ok(response.data.into_iter().map(|d| d.embedding).collect())
+}
+
+#[cfg(feature = "openai")]
+fn snippet(body: &str) -> &str {
+    match body.char_indices().nth(MAX_ERROR_SNIPPET) {
+        Some((end, _)) => &body[..end],
+        None => body,
+    }
+}
+
+/// Tokenizer files read from a local model directory besides `model.onnx`
+#[cfg(feature = "local-embedding")]
+const TOKENIZER_FILES: [&str; 4] = [
+    "tokenizer.json",
+    "config.json",
+    "special_tokens_map.json",
+    "tokenizer_config.json",
+];
+
+/// In-process ONNX embedder, for bulk ingestion without network calls
+#[cfg(feature = "local-embedding")]
+pub struct LocalEmbedder {
+    model: Arc<fastembed::TextEmbedding>,
+    dimension: usize,
+    batch_size: usize,
+}
+
+#[cfg(feature = "local-embedding")]
+impl LocalEmbedder {
+    /// Load the model from `model_path`, or the fastembed model named by
+    /// `model` (downloaded on first use)
+    pub async fn new(config: &EmbeddingConfig) -> Result<Self> {
+        let config = config.clone();
+        let model = tokio::task::spawn_blocking(move || load_local_model(&config))
+            .await
+            .map_err(|e| A3SError::Embedding(format!("Model loading panicked: {}", e)))??;
+        Ok(Self {
+            model: Arc::new(model),
+            dimension: config.dimension,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+}
+
+#[cfg(feature = "local-embedding")]
+fn load_local_model(config: &EmbeddingConfig) -> Result<fastembed::TextEmbedding> {
+    use fastembed::{
+        InitOptions, InitOptionsUserDefined, TextEmbedding, TokenizerFiles,
+        UserDefinedEmbeddingModel,
+    };
+
+    let model = match &config.model_path {
+        Some(dir) => {
+            let read = |name: &str| {
+                std::fs::read(dir.join(name)).map_err(|e| {
+                    A3SError::Embedding(format!("Cannot read {}: {}", dir.join(name).display(), e))
+                })
+            };
+            let [tokenizer, model_config, special_tokens, tokenizer_config] =
+                TOKENIZER_FILES.map(read);
+            let tokenizer_files = TokenizerFiles {
+                tokenizer_file: tokenizer?,
+                config_file: model_config?,
+                special_tokens_map_file: special_tokens?,
+                tokenizer_config_file: tokenizer_config?,
+            };
+            TextEmbedding::try_new_from_user_defined(
+                UserDefinedEmbeddingModel::new(read("model.onnx")?, tokenizer_files),
+                InitOptionsUserDefined::default(),
+            )
+        }
+        None => {
+            let info = TextEmbedding::list_supported_models()
+                .into_iter()
+                .find(|info| info.model_code.eq_ignore_ascii_case(&config.model))
+                .ok_or_else(|| {
+                    A3SError::Config(format!(
+                        "Unknown local embedding model '{}'; set embedding.model_path",
+                        config.model
+                    ))
+                })?;
+            if info.dim != config.dimension {
+                return Err(A3SError::Config(format!(
+                    "Model '{}' produces {}-dimensional vectors, but dimension is {}",
+                    config.model, info.dim, config.dimension
+                )));
+            }
+            TextEmbedding::try_new(InitOptions::new(info.model))
+        }
+    };
+    model.map_err(|e| A3SError::Embedding(format!("Cannot load local model: {}", e)))
+}
+
+#[cfg(feature = "local-embedding")]
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut embeddings = self.embed_batch(&[text.to_string()]).await?;
+        embeddings
+            .pop()
+            .ok_or_else(|| A3SError::Embedding("Local model returned no embedding".to_string()))
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model = self.model.clone();
+        let texts = texts.to_vec();
+        let batch_size = self.batch_size;
+        // Inference is CPU-bound; keep it off the async workers
+        let embeddings = tokio::task::spawn_blocking(move || model.embed(texts, Some(batch_size)))
+            .await
+            .map_err(|e| A3SError::Embedding(format!("Local embedding panicked: {}", e)))?
+            .map_err(|e| A3SError::Embedding(format!("Local embedding failed: {}", e)))?;
+
+        if let Some(embedding) = embeddings.iter().find(|e| e.len() != self.dimension) {
+            return Err(A3SError::Embedding(format!(
+                "Local model produced {}-dimensional vectors, but dimension is {}",
+                embedding.len(),
+                self.dimension
+            )));
+        }
         Ok(embeddings)
     }
 
@@ -205,10 +410,68 @@ mod tests {
             api_key: None,
             model: "mock".to_string(),
             dimension: 128,
+            model_path: None,
             batch_size: 32,
+            timeout_secs: 30,
+            vectors: Vec::new(),
+            strict_check: false,
+            requests: Default::default(),
         };
 
-        let embedder = create_embedder(&config).await.unwrap();
+        let embedder = create_embedder(
+            &config,
+            &crate::http::build_client(&Default::default()).unwrap(),
+        )
+        .await
+        .unwrap();
         assert_eq!(embedder.dimension(), 128);
     }
+
+    #[cfg(not(feature = "local-embedding"))]
+    #[tokio::test]
+    async fn test_local_embedder_requires_feature() {
+        let config = EmbeddingConfig {
+            provider: "local".to_string(),
+            ..Default::default()
+        };
+        let result = create_embedder(
+            &config,
+            &crate::http::build_client(&Default::default()).unwrap(),
+        )
+        .await;
+        assert!(matches!(result, Err(A3SError::Config(_))));
+    }
+
+    #[cfg(feature = "openai")]
+    #[test]
+    fn test_parse_embedding_response_orders_by_index() {
+        let body = r#"{"data": [
+            {"index": 1, "embedding": [0.3, 0.4]},
+            {"index": 0, "embedding": [0.1, 0.2]}
+        ]}"#;
+
+        let embeddings = parse_embedding_response(body, 2).unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+
+    #[cfg(feature = "openai")]
+    #[test]
+    fn test_parse_embedding_response_malformed() {
+        let body = r#"{"data": [{"index": 0, "embedding": ["oops"]}]}"#;
+
+        let err = parse_embedding_response(body, 1).unwrap_err();
+        assert!(matches!(err, A3SError::Embedding(_)));
+        assert!(err.to_string().contains("oops"));
+
+        let err = parse_embedding_response(r#"{"error": "overloaded"}"#, 1).unwrap_err();
+        assert!(err.to_string().contains("overloaded"));
+    }
+
+    #[cfg(feature = "openai")]
+    #[test]
+    fn test_parse_embedding_response_count_mismatch() {
+        let body = r#"{"data": []}"#;
+        let err = parse_embedding_response(body, 1).unwrap_err();
+        assert!(err.to_string().contains("Expected 1 embeddings, got 0"));
+    }
 }
@@ -42,18 +42,58 @@ pub enum A3SError {
     #[error("Configuration error: {0}")]
     Config(String),
 
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+
+    #[error("Schema violation: {0}")]
+    SchemaViolation(String),
+
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Pack error: {0}")]
+    Pack(String),
+
+    #[error("Read-only pathway: {0}")]
+    ReadOnly(String),
+
+    #[error("Pathway is leased: {0}")]
+    Leased(String),
+
+    #[error("Template error: {0}")]
+    Template(String),
+
+    #[error("Connector error: {0}")]
+    Connector(String),
+
+    #[error("Embedding model mismatch: {0}")]
+    EmbeddingMismatch(String),
+
+    #[error("Archive error: {0}")]
+    Archive(String),
+
+    #[error("Backup error: {0}")]
+    Backup(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    #[cfg(feature = "http")]
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
     #[error("Not initialized")]
     NotInitialized,
 
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -115,7 +155,20 @@ mod tests {
         let _ = A3SError::Rerank("test".to_string());
         let _ = A3SError::Session("test".to_string());
         let _ = A3SError::Config("test".to_string());
+        let _ = A3SError::PolicyViolation("test".to_string());
+        let _ = A3SError::SchemaViolation("test".to_string());
+        let _ = A3SError::InvalidPattern("test".to_string());
+        let _ = A3SError::Pack("test".to_string());
+        let _ = A3SError::ReadOnly("test".to_string());
+        let _ = A3SError::Leased("test".to_string());
+        let _ = A3SError::Template("test".to_string());
+        let _ = A3SError::Connector("test".to_string());
+        let _ = A3SError::EmbeddingMismatch("test".to_string());
+        let _ = A3SError::Archive("test".to_string());
+        let _ = A3SError::Backup("test".to_string());
         let _ = A3SError::NotInitialized;
+        let _ = A3SError::Cancelled;
+        let _ = A3SError::Timeout("test".to_string());
         let _ = A3SError::Internal("test".to_string());
     }
 
@@ -0,0 +1,150 @@
+//! Line-oriented content search over stored nodes
+//!
+//! Patterns are literals or regular expressions, matched by the storage
+//! backend so only matching nodes leave it; see
+//! [`crate::storage::TextMatcher`] for how each kind matches. Each matching
+//! line reports where every match starts and ends, so callers can locate
+//! exact spans without re-running the pattern.
+
+use crate::error::Result;
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+pub use crate::storage::{
+    MatchSpan, TextLine as GrepLine, TextMatch as GrepMatch, TextSearchOptions as GrepOptions,
+};
+
+/// Search node contents under a pathway, returning matched lines per node
+/// in pathway order
+pub async fn grep(
+    storage: &dyn StorageBackend,
+    pattern: &str,
+    pathway: &Pathway,
+    options: &GrepOptions,
+) -> Result<Vec<GrepMatch>> {
+    storage.search_text(pattern, pathway, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::{Node, NodeKind};
+    use crate::error::A3SError;
+    use crate::storage::MemoryStorage;
+
+    async fn create_test_storage() -> MemoryStorage {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let docs = [
+            (
+                "a3s://knowledge/docs/auth",
+                "Login flow\nUse an API key\nDone",
+            ),
+            ("a3s://knowledge/docs/rate", "Rate limits\napi KEY quota"),
+            ("a3s://memory/user/prefs", "API key stored elsewhere"),
+            (
+                "a3s://capability/cafe/menu",
+                "Le Cafe\u{301} ouvre à 8h\nＡＰＩ ＫＥＹ rotation",
+            ),
+        ];
+        for (pathway, content) in docs {
+            let node = Node::new(
+                Pathway::parse(pathway).unwrap(),
+                NodeKind::Document,
+                content.to_string(),
+            );
+            storage.put(&node).await.unwrap();
+        }
+        storage
+    }
+
+    #[tokio::test]
+    async fn test_grep_literal() {
+        let storage = create_test_storage().await;
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+
+        let results = grep(&storage, "API key", &root, &GrepOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].pathway.to_string(), "a3s://knowledge/docs/auth");
+        assert_eq!(results[0].lines[0].line_number, 2);
+        assert_eq!(results[0].lines[0].text, "Use an API key");
+        assert_eq!(results[0].lines[0].offset, 11);
+        assert_eq!(
+            results[0].lines[0].spans,
+            vec![MatchSpan { start: 7, end: 14 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grep_normalizes_unicode() {
+        let storage = create_test_storage().await;
+        let root = Pathway::parse("a3s://capability/cafe").unwrap();
+        let options = GrepOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+
+        let results = grep(&storage, "café", &root, &options).await.unwrap();
+        assert_eq!(results.len(), 1);
+        let line = &results[0].lines[0];
+        let span = line.spans[0];
+        assert_eq!(&line.text[span.start..span.end], "Cafe\u{301}");
+
+        let results = grep(&storage, "api key", &root, &options).await.unwrap();
+        assert_eq!(results[0].lines[0].line_number, 2);
+    }
+
+    #[tokio::test]
+    async fn test_grep_case_insensitive() {
+        let storage = create_test_storage().await;
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+        let options = GrepOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+
+        let results = grep(&storage, "api KEY", &root, &GrepOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let results = grep(&storage, "api KEY", &root, &options).await.unwrap();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_grep_regex_and_limit() {
+        let storage = create_test_storage().await;
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+        let options = GrepOptions {
+            regex: true,
+            case_insensitive: true,
+            max_results: Some(1),
+        };
+
+        let results = grep(&storage, r"^(rate|login)\b", &root, &options)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].pathway.to_string(), "a3s://knowledge/docs/auth");
+    }
+
+    #[tokio::test]
+    async fn test_grep_invalid_regex() {
+        let storage = create_test_storage().await;
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+        let options = GrepOptions {
+            regex: true,
+            ..Default::default()
+        };
+
+        let err = grep(&storage, "(", &root, &options).await.unwrap_err();
+        assert!(matches!(err, A3SError::InvalidPattern(_)));
+
+        let nested = format!("{}a{}", "(".repeat(100), ")".repeat(100));
+        let err = grep(&storage, &nested, &root, &options).await.unwrap_err();
+        assert!(matches!(err, A3SError::InvalidPattern(_)));
+    }
+}
@@ -0,0 +1,305 @@
+//! Shared HTTP client for embedding, LLM, and rerank providers
+//!
+//! Embedding and LLM requests go through a [`RequestLimiter`], which
+//! retries transient failures with exponential backoff and bounds how
+//! many requests are in flight and how many start per minute. Providers
+//! reaching the same endpoint share one limiter, so a bulk ingest and the
+//! queries running beside it stay within one budget.
+//!
+//! Without the `http` feature no provider makes requests, and
+//! [`HttpClient`] is an empty placeholder.
+
+#[cfg(feature = "http")]
+use std::sync::{Arc, OnceLock};
+#[cfg(feature = "http")]
+use std::time::{Duration, Instant};
+
+use crate::config::HttpConfig;
+#[cfg(feature = "http")]
+use crate::config::RequestPolicyConfig;
+#[cfg(feature = "http")]
+use crate::error::A3SError;
+use crate::error::Result;
+
+/// Client shared by all HTTP providers
+#[cfg(feature = "http")]
+pub type HttpClient = reqwest::Client;
+
+/// Placeholder client for builds without HTTP providers
+#[cfg(not(feature = "http"))]
+#[derive(Debug, Clone, Default)]
+pub struct HttpClient;
+
+/// Build an HTTP client from configuration
+#[cfg(not(feature = "http"))]
+pub fn build_client(_config: &HttpConfig) -> Result<HttpClient> {
+    Ok(HttpClient)
+}
+
+/// Build an HTTP client from configuration
+///
+/// Request timeouts are provider-specific and set per request, so the
+/// client itself only bounds connection setup.
+#[cfg(feature = "http")]
+pub fn build_client(config: &HttpConfig) -> Result<HttpClient> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(&config.user_agent)
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs));
+
+    // Without an explicit proxy, reqwest honors HTTP(S)_PROXY and NO_PROXY
+    if let Some(proxy) = &config.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| A3SError::Config(format!("Invalid proxy {}: {}", proxy, e)))?;
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &config.ca_certs {
+        let pem = std::fs::read(path)?;
+        let cert = reqwest::Certificate::from_pem(&pem).map_err(|e| {
+            A3SError::Config(format!("Invalid CA certificate {}: {}", path.display(), e))
+        })?;
+        builder = builder.add_root_certificate(cert);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Client used by providers constructed without an injected one
+#[cfg(feature = "http")]
+pub(crate) fn default_client() -> HttpClient {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| build_client(&HttpConfig::default()).unwrap_or_default())
+        .clone()
+}
+
+/// Retries and limits for requests to one provider endpoint
+#[cfg(feature = "http")]
+pub struct RequestLimiter {
+    policy: RequestPolicyConfig,
+    /// Permits for requests in flight, when their number is limited
+    permits: Option<tokio::sync::Semaphore>,
+    /// When the next request may start, when requests are throttled
+    next_start: parking_lot::Mutex<Instant>,
+}
+
+#[cfg(feature = "http")]
+impl RequestLimiter {
+    pub fn new(policy: &RequestPolicyConfig) -> Self {
+        Self {
+            policy: policy.clone(),
+            permits: (policy.max_concurrency > 0)
+                .then(|| tokio::sync::Semaphore::new(policy.max_concurrency)),
+            next_start: parking_lot::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Send the request `build` makes, building it again for each retry
+    ///
+    /// Returns the last response, successful or not, once it is not worth
+    /// retrying or the retries run out.
+    pub async fn send<F>(&self, build: F) -> Result<reqwest::Response>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_start().await;
+            let result = {
+                let _permit = match &self.permits {
+                    Some(permits) => Some(permits.acquire().await.expect("never closed")),
+                    None => None,
+                };
+                build().send().await
+            };
+
+            let retry = match &result {
+                Ok(response) if is_transient(response.status()) => {
+                    Some(retry_after(response).unwrap_or_else(|| self.backoff(attempt)))
+                }
+                Err(e) if e.is_timeout() || e.is_connect() => Some(self.backoff(attempt)),
+                _ => None,
+            };
+            let Some(delay) = retry.filter(|_| attempt < self.policy.max_retries) else {
+                return Ok(result?);
+            };
+            let delay = delay.min(Duration::from_millis(self.policy.max_backoff_ms));
+            tracing::warn!(
+                attempt = attempt + 1,
+                delay_ms = delay.as_millis() as u64,
+                status = result.as_ref().ok().map(|r| r.status().as_u16()),
+                "retrying provider request"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Wait for this request's turn under `requests_per_minute`
+    async fn wait_for_start(&self) {
+        if self.policy.requests_per_minute == 0 {
+            return;
+        }
+        let interval = Duration::from_secs(60) / self.policy.requests_per_minute;
+        let wait = {
+            let mut next_start = self.next_start.lock();
+            let now = Instant::now();
+            let start = (*next_start).max(now);
+            *next_start = start + interval;
+            start - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Wait before retry `attempt`: the doubled initial backoff, less up to
+    /// half of it so that clients failing together do not retry together
+    fn backoff(&self, attempt: u32) -> Duration {
+        let full = self
+            .policy
+            .initial_backoff_ms
+            .saturating_mul(1u64 << attempt.min(32))
+            .min(self.policy.max_backoff_ms);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let jitter = full / 2 * u64::from(nanos % 1000) / 1000;
+        Duration::from_millis(full - jitter)
+    }
+}
+
+/// Limiter shared by every provider sending to `endpoint` with `policy`
+#[cfg(feature = "http")]
+pub fn shared_limiter(endpoint: &str, policy: &RequestPolicyConfig) -> Arc<RequestLimiter> {
+    static LIMITERS: OnceLock<dashmap::DashMap<String, Arc<RequestLimiter>>> = OnceLock::new();
+    let limiters = LIMITERS.get_or_init(dashmap::DashMap::new);
+    let mut entry = limiters
+        .entry(endpoint.to_string())
+        .or_insert_with(|| Arc::new(RequestLimiter::new(policy)));
+    if entry.policy != *policy {
+        *entry = Arc::new(RequestLimiter::new(policy));
+    }
+    entry.clone()
+}
+
+/// Responses worth retrying: timeouts, throttling, and server failures
+#[cfg(feature = "http")]
+fn is_transient(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// Wait a response's `Retry-After` header asks for, in seconds
+#[cfg(feature = "http")]
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds: f64 = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client_default() {
+        assert!(build_client(&HttpConfig::default()).is_ok());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_build_client_invalid_proxy() {
+        let config = HttpConfig {
+            proxy: Some("http://[invalid".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(build_client(&config), Err(A3SError::Config(_))));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_backoff_doubles_within_bounds() {
+        let limiter = RequestLimiter::new(&RequestPolicyConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            ..Default::default()
+        });
+        for (attempt, full) in [(0, 100), (1, 200), (2, 400), (5, 1000), (40, 1000)] {
+            let delay = limiter.backoff(attempt).as_millis() as u64;
+            assert!(delay > full / 2 && delay <= full, "{} {}", attempt, delay);
+        }
+    }
+
+    /// Server answering each connection with the next canned status
+    #[cfg(feature = "http")]
+    async fn serve(statuses: Vec<u16>) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let count = served.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                count.fetch_add(1, Ordering::SeqCst);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nretry-after: 0\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    status
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, served)
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_send_retries_transient_failures() {
+        use std::sync::atomic::Ordering;
+
+        let http = default_client();
+        let policy = RequestPolicyConfig {
+            max_retries: 2,
+            initial_backoff_ms: 1,
+            ..Default::default()
+        };
+
+        let (url, served) = serve(vec![429, 503, 200]).await;
+        let response = RequestLimiter::new(&policy)
+            .send(|| http.get(&url))
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+
+        // Out of retries, the last failure is returned
+        let (url, served) = serve(vec![500, 502, 504]).await;
+        let response = RequestLimiter::new(&policy)
+            .send(|| http.get(&url))
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 504);
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+
+        // Client errors are not retried
+        let (url, served) = serve(vec![401]).await;
+        let response = RequestLimiter::new(&policy)
+            .send(|| http.get(&url))
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,52 +1,291 @@
 //! Content ingestion and processing
+//!
+//! Sources are read into nodes, which then pass through the stages
+//! described in [`crate::pipeline`].
 
+use serde::Serialize;
+use std::collections::VecDeque;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use uuid::Uuid;
 use walkdir::WalkDir;
 
-use crate::config::Config;
-use crate::core::{Node, NodeKind};
+use crate::bulk::{apply_all, MetadataOp};
+use crate::chat::ChatFormat;
+use crate::chunk::{self, Chunk, ChunkInfo, RechunkResult};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{ChunkUnit, Config};
+use crate::connector::SourceDocument;
+use crate::core::{Node, NodeKind, RelationKind, SourceInfo, VectorName, QUESTIONS_KEY};
 use crate::digest::DigestGenerator;
+use crate::drift;
+use crate::email;
 use crate::embedding::Embedder;
-use crate::error::Result;
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+use crate::language;
 use crate::pathway::Pathway;
+use crate::pipeline::{IngestItem, Stage, StagePosition, StageRegistry};
+use crate::policy::PolicyChain;
+use crate::provenance::ProvenanceSigner;
+use crate::relate;
 use crate::storage::StorageBackend;
-use crate::IngestResult;
+use crate::syntax;
+use crate::tokenizer::{CharTokenizer, HeuristicTokenizer, Tokenizer};
+use crate::translate;
+use crate::web;
+use crate::{IngestEntry, IngestOutcome, IngestResult};
+
+/// Progress of an ingest, sent as it happens
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum IngestEvent {
+    /// Number of files, pages, threads, or documents the ingest will process
+    Planned { items: usize },
+    /// Processing of an item began
+    Started { source: String, pathway: Pathway },
+    /// A node's content was embedded
+    Embedded { pathway: Pathway },
+    /// A node was written to storage
+    Stored { pathway: Pathway },
+    /// An item was left unchanged or skipped
+    Skipped { source: String, reason: String },
+    /// An item failed
+    Failed { source: String, error: String },
+}
+
+/// An item through the stages before `embed`, waiting for its embeddings
+struct Pending {
+    item: IngestItem,
+    /// Source label and content the item is reported with
+    label: String,
+    content: String,
+    start: Instant,
+    /// Configured views embedded after the content, in order
+    names: Vec<VectorName>,
+    /// Chunk nodes to store, and the positions of those to embed
+    chunks: Vec<Node>,
+    rechunked: Vec<usize>,
+    /// Content, views, then chunks to embed, and the embeddings so far
+    texts: Vec<String>,
+    embeddings: Vec<Vec<f32>>,
+    /// Why embedding failed, if it did
+    failed: Option<String>,
+}
+
+/// Ingested items sharing embedding requests
+///
+/// Texts go out `embedding.batch_size` at a time whichever items they come
+/// from, and an item is stored once all of its embeddings are back, so a
+/// directory of small files costs one request per full batch rather than
+/// one per file. [`Processor::flush`] stores what is left.
+#[derive(Default)]
+pub struct EmbedQueue {
+    items: VecDeque<Pending>,
+}
 
 /// Content processor for ingesting files and directories
 pub struct Processor {
     storage: Arc<dyn StorageBackend>,
     embedder: Arc<dyn Embedder>,
     digest_generator: DigestGenerator,
+    policies: Arc<PolicyChain>,
+    stages: Arc<StageRegistry>,
+    signer: Option<Arc<dyn ProvenanceSigner>>,
+    cancel: Option<CancellationToken>,
+    progress: Option<UnboundedSender<IngestEvent>>,
+    tokenizer: Arc<dyn Tokenizer>,
+    clock: Arc<dyn Clock>,
+    /// The store has a record of its embedding model
+    embedding_recorded: Arc<AtomicBool>,
+    http: HttpClient,
     config: Config,
 }
 
+/// Digest generator for the configured LLM, if any
+#[cfg(feature = "llm-digest")]
+fn digest_generator(config: &Config, http: &HttpClient) -> DigestGenerator {
+    let llm_client = if config.llm.auto_digest && config.llm.api_base.is_some() {
+        Some(
+            crate::digest::LLMClient::new(
+                config.llm.api_base.clone().unwrap(),
+                config.llm.api_key.clone().unwrap_or_default(),
+                config.llm.model.clone().unwrap_or_default(),
+            )
+            .with_request_policy(&config.llm.requests)
+            .with_timeout(config.llm.timeout_secs)
+            .with_http_client(http.clone()),
+        )
+    } else {
+        None
+    };
+
+    DigestGenerator::new(llm_client)
+}
+
+/// Without LLM support, digests are always extracted from content
+#[cfg(not(feature = "llm-digest"))]
+fn digest_generator(_config: &Config, _http: &HttpClient) -> DigestGenerator {
+    DigestGenerator::simple()
+}
+
 impl Processor {
     pub fn new(
         storage: Arc<dyn StorageBackend>,
         embedder: Arc<dyn Embedder>,
         config: &Config,
+        http: &HttpClient,
     ) -> Self {
-        let llm_client = if config.llm.auto_digest && config.llm.api_base.is_some() {
-            Some(crate::digest::LLMClient::new(
-                config.llm.api_base.clone().unwrap(),
-                config.llm.api_key.clone().unwrap_or_default(),
-                config.llm.model.clone().unwrap_or_default(),
-            ))
-        } else {
-            None
-        };
-
         Self {
             storage,
             embedder,
-            digest_generator: DigestGenerator::new(llm_client),
+            digest_generator: digest_generator(config, http),
+            policies: Arc::new(PolicyChain::new()),
+            stages: Arc::new(StageRegistry::new()),
+            signer: None,
+            cancel: None,
+            progress: None,
+            tokenizer: Arc::new(HeuristicTokenizer),
+            clock: Arc::new(SystemClock),
+            embedding_recorded: Arc::new(AtomicBool::new(false)),
+            http: http.clone(),
             config: config.clone(),
         }
     }
 
+    /// Apply the given write policies to every node before it is stored
+    pub fn with_policies(mut self, policies: Arc<PolicyChain>) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Stamp written nodes and their provenance with `clock`'s time
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run the given custom stages on every ingested node
+    pub fn with_stages(mut self, stages: Arc<StageRegistry>) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Sign the provenance record of every ingested node
+    pub fn with_signer(mut self, signer: Arc<dyn ProvenanceSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Stop processing between files once the token is cancelled
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Send an [`IngestEvent`] to `progress` as each item is processed
+    pub fn with_progress(mut self, progress: UnboundedSender<IngestEvent>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Count tokens, digest lengths, and chunk sizes with
+    /// `ingest.chunk_unit: tokens`, with `tokenizer`
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.digest_generator = self.digest_generator.with_tokenizer(tokenizer.clone());
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Share whether the store's embedding model is recorded with other
+    /// writers, so the record is written once before the first node
+    pub fn with_embedding_record(mut self, recorded: Arc<AtomicBool>) -> Self {
+        self.embedding_recorded = recorded;
+        self
+    }
+
+    /// Tokenizer counting the tokens of ingested content
+    pub fn tokenizer(&self) -> &dyn Tokenizer {
+        &*self.tokenizer
+    }
+
+    /// Measure of chunk sizes for `ingest.chunk_unit`
+    fn chunk_measure(&self) -> &dyn Tokenizer {
+        match self.config.ingest.chunk_unit {
+            ChunkUnit::Characters => &CharTokenizer,
+            ChunkUnit::Tokens => &*self.tokenizer,
+        }
+    }
+
+    /// Send a progress event, if anyone is listening
+    fn emit(&self, event: IngestEvent) {
+        if let Some(progress) = &self.progress {
+            // A dropped receiver only means nobody is watching any more
+            let _ = progress.send(event);
+        }
+    }
+
+    /// Report an item's entry as progress and add it to `result`
+    fn record(&self, result: &mut IngestResult, entry: IngestEntry) {
+        let reason = entry.reason.clone().unwrap_or_default();
+        match entry.outcome {
+            IngestOutcome::Unchanged | IngestOutcome::Skipped => self.emit(IngestEvent::Skipped {
+                source: entry.source.clone(),
+                reason,
+            }),
+            IngestOutcome::Failed => self.emit(IngestEvent::Failed {
+                source: entry.source.clone(),
+                error: reason,
+            }),
+            IngestOutcome::Created | IngestOutcome::Updated => {}
+        }
+        result.record(entry);
+    }
+
+    /// Announce that processing of an item began
+    fn start(&self, source: &str, pathway: &Pathway) {
+        self.emit(IngestEvent::Started {
+            source: source.to_string(),
+            pathway: pathway.clone(),
+        });
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancel {
+            Some(cancel) if cancel.is_cancelled() => Err(A3SError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
     /// Process a source path and ingest into target pathway
     pub async fn process(&self, source: &str, target: &Pathway) -> Result<IngestResult> {
+        let span = tracing::info_span!(
+            "ingest",
+            ingest_id = %Uuid::new_v4(),
+            source = %source,
+            target = %target,
+        );
+
+        self.process_inner(source, target).instrument(span).await
+    }
+
+    async fn process_inner(&self, source: &str, target: &Pathway) -> Result<IngestResult> {
+        if let Some((format, path)) = ChatFormat::parse_source(source) {
+            return self.process_chat(format, Path::new(path), target).await;
+        }
+        if let Some(sitemap) = web::parse_source(source) {
+            return self.process_sitemap(sitemap, target).await;
+        }
+        if let Some(url) = web::page_url(source) {
+            return self.process_url(url, target).await;
+        }
+
+        let start = Instant::now();
         let path = Path::new(source);
 
         if !path.exists() {
@@ -56,36 +295,54 @@ impl Processor {
             )));
         }
 
-        let mut nodes_created = 0;
-        let mut nodes_updated = 0;
-        let mut errors = Vec::new();
+        let mut result = IngestResult::new(target.clone());
+        let mut queue = EmbedQueue::default();
 
         if path.is_file() {
-            match self.process_file(path, target).await {
-                Ok(created) => {
-                    if created {
-                        nodes_created += 1;
-                    } else {
-                        nodes_updated += 1;
-                    }
-                }
-                Err(e) => errors.push(format!("{}: {}", source, e)),
-            }
+            self.check_cancelled()?;
+            self.emit(IngestEvent::Planned { items: 1 });
+            self.record_file(path, target, source, &mut queue, &mut result)
+                .await;
         } else if path.is_dir() {
-            for entry in WalkDir::new(path)
+            let entries: Vec<_> = WalkDir::new(path)
                 .follow_links(false)
                 .into_iter()
                 .filter_entry(|e| !self.should_ignore(e.path()))
-            {
+                .collect();
+            self.emit(IngestEvent::Planned {
+                items: entries
+                    .iter()
+                    .filter(|e| e.as_ref().is_ok_and(|e| e.file_type().is_file()))
+                    .count(),
+            });
+            for entry in entries {
                 let entry = match entry {
                     Ok(e) => e,
                     Err(e) => {
-                        errors.push(format!("Walk error: {}", e));
+                        let walked = e.path().map(|p| p.to_string_lossy().to_string());
+                        self.record(
+                            &mut result,
+                            IngestEntry::not_written(
+                                walked.as_deref().unwrap_or(source),
+                                None,
+                                IngestOutcome::Failed,
+                                format!("Walk error: {}", e),
+                            ),
+                        );
                         continue;
                     }
                 };
 
                 if entry.file_type().is_file() {
+                    if self.check_cancelled().is_err() {
+                        tracing::info!(
+                            created = result.nodes_created,
+                            updated = result.nodes_updated,
+                            "ingest cancelled"
+                        );
+                        return Err(A3SError::Cancelled);
+                    }
+
                     let rel_path = entry
                         .path()
                         .strip_prefix(path)
@@ -94,73 +351,1033 @@ impl Processor {
                         .to_string();
 
                     let file_pathway = target.join(&rel_path);
-
-                    match self.process_file(entry.path(), &file_pathway).await {
-                        Ok(created) => {
-                            if created {
-                                nodes_created += 1;
-                            } else {
-                                nodes_updated += 1;
-                            }
-                        }
-                        Err(e) => errors.push(format!("{}: {}", rel_path, e)),
-                    }
+                    self.record_file(
+                        entry.path(),
+                        &file_pathway,
+                        &rel_path,
+                        &mut queue,
+                        &mut result,
+                    )
+                    .await;
                 }
             }
         }
+        for entry in self.flush(&mut queue).await {
+            self.record(&mut result, entry);
+        }
 
-        Ok(IngestResult {
-            pathway: target.clone(),
-            nodes_created,
-            nodes_updated,
-            errors,
-        })
+        tracing::info!(
+            created = result.nodes_created,
+            updated = result.nodes_updated,
+            unchanged = result.nodes_unchanged,
+            skipped = result.count(IngestOutcome::Skipped),
+            errors = result.errors.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "ingest complete"
+        );
+
+        Ok(result)
+    }
+
+    /// Ingest one file labelled `label` in the report, recording the
+    /// entries it completes or its failure
+    async fn record_file(
+        &self,
+        path: &Path,
+        pathway: &Pathway,
+        label: &str,
+        queue: &mut EmbedQueue,
+        result: &mut IngestResult,
+    ) {
+        self.start(label, pathway);
+        match self.process_file(path, pathway, label, queue).await {
+            Ok(entries) => entries.into_iter().for_each(|e| self.record(result, e)),
+            Err(e) => self.record(
+                result,
+                IngestEntry::not_written(label, Some(pathway.clone()), IngestOutcome::Failed, e),
+            ),
+        }
     }
 
-    async fn process_file(&self, path: &Path, pathway: &Pathway) -> Result<bool> {
-        // Check file size
+    /// Queue one file, returning an entry per node it leaves alone and per
+    /// queued node written
+    async fn process_file(
+        &self,
+        path: &Path,
+        pathway: &Pathway,
+        label: &str,
+        queue: &mut EmbedQueue,
+    ) -> Result<Vec<IngestEntry>> {
+        let start = Instant::now();
+
+        // Skip files over the size limit
         let metadata = std::fs::metadata(path)?;
         if metadata.len() > self.config.ingest.max_file_size {
-            return Err(crate::A3SError::Ingest(format!(
-                "File too large: {} bytes",
-                metadata.len()
-            )));
+            let mut entry = IngestEntry::not_written(
+                label,
+                Some(pathway.clone()),
+                IngestOutcome::Skipped,
+                format!("File too large: {} bytes", metadata.len()),
+            );
+            entry.bytes = metadata.len();
+            return Ok(vec![entry]);
         }
 
-        // Read content
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        if email::is_email(extension) {
+            return self.process_email(path, pathway, label, start, queue).await;
+        }
+
+        // Read content, leaving files ingested with the same content alone
         let content = std::fs::read_to_string(path)?;
+        if self.config.ingest.skip_unchanged
+            && self
+                .is_unchanged(pathway, &crate::provenance::sha256_hex(content.as_bytes()))
+                .await?
+        {
+            return Ok(vec![IngestEntry::not_written(
+                label,
+                Some(pathway.clone()),
+                IngestOutcome::Unchanged,
+                "Content hash matches the stored node",
+            )]);
+        }
+        let source = self.source_info(path, &content)?;
 
         // Determine node kind
         let kind = self.detect_kind(path);
+        let metadata: Vec<MetadataOp> = language::code_language(extension)
+            .map(|code| {
+                MetadataOp::SetCustom(
+                    language::CODE_LANGUAGE_KEY.to_string(),
+                    serde_json::json!(code),
+                )
+            })
+            .into_iter()
+            .collect();
 
-        // Check if node exists
-        let exists = self.storage.exists(pathway).await?;
+        let pending = self
+            .prepare(pathway, kind, content, source, &metadata, start)
+            .await?;
+        Ok(self.enqueue(queue, label, pending).await)
+    }
+
+    /// Whether the node at `pathway` is embedded and was ingested from
+    /// content with `hash`
+    async fn is_unchanged(&self, pathway: &Pathway, hash: &str) -> Result<bool> {
+        match self.storage.get(pathway).await {
+            Ok(node) => Ok(node.is_embedded()
+                && node
+                    .metadata
+                    .source
+                    .as_ref()
+                    .is_some_and(|source| source.hash == hash)),
+            Err(A3SError::NodeNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Ingest an `.eml` file as one node, or each message of an `.mbox` as
+    /// `<pathway>/<n>`, counting from 1
+    ///
+    /// A message that fails is reported and the rest are still ingested.
+    async fn process_email(
+        &self,
+        path: &Path,
+        pathway: &Pathway,
+        label: &str,
+        start: Instant,
+        queue: &mut EmbedQueue,
+    ) -> Result<Vec<IngestEntry>> {
+        let raw = std::fs::read(path)?;
+        let messages = if path.extension().is_some_and(|ext| ext == "mbox") {
+            email::parse_mbox(&raw)
+                .into_iter()
+                .enumerate()
+                .map(|(i, message)| (pathway.join(&(i + 1).to_string()), message))
+                .collect()
+        } else {
+            vec![(pathway.clone(), email::parse_message(&raw))]
+        };
+        let origin = path
+            .canonicalize()
+            .unwrap_or_else(|_| path.to_path_buf())
+            .to_string_lossy()
+            .to_string();
+
+        let mut entries = Vec::new();
+        for (pathway, message) in messages {
+            self.check_cancelled()?;
+            match self.prepare_email(&pathway, message, &origin, start).await {
+                Ok(pending) => entries.extend(self.enqueue(queue, label, pending).await),
+                Err(e) => entries.push(IngestEntry::not_written(
+                    label,
+                    Some(pathway),
+                    IngestOutcome::Failed,
+                    e,
+                )),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Prepare one parsed email for its embeddings
+    async fn prepare_email(
+        &self,
+        pathway: &Pathway,
+        message: Result<email::EmailMessage>,
+        origin: &str,
+        start: Instant,
+    ) -> Result<Pending> {
+        let message = message?;
+        let content = message.content();
+        let origin = match &message.message_id {
+            Some(id) => format!("{}#{}", origin, id),
+            None => origin.to_string(),
+        };
+        let source = self.provenance(origin, Some("message/rfc822"), &content)?;
+        self.prepare(
+            pathway,
+            NodeKind::Document,
+            content,
+            source,
+            &message.metadata(),
+            start,
+        )
+        .await
+    }
+
+    /// Ingest a document fetched by a connector, returning whether it was new
+    pub async fn process_document(
+        &self,
+        document: &SourceDocument,
+        pathway: &Pathway,
+    ) -> Result<bool> {
+        let mut pending = self.prepare_document(document, pathway).await?;
+        pending.embeddings = self.embed_texts(&pending.texts).await?;
+        self.finish(pending).await
+    }
+
+    /// Queue a document fetched by a connector, reported by its path;
+    /// returns the entries of the items this completed
+    pub async fn queue_document(
+        &self,
+        queue: &mut EmbedQueue,
+        document: &SourceDocument,
+        pathway: &Pathway,
+    ) -> Vec<IngestEntry> {
+        match self.prepare_document(document, pathway).await {
+            Ok(pending) => self.enqueue(queue, &document.path, pending).await,
+            Err(e) => vec![IngestEntry::not_written(
+                &document.path,
+                Some(pathway.clone()),
+                IngestOutcome::Failed,
+                e,
+            )],
+        }
+    }
+
+    async fn prepare_document(
+        &self,
+        document: &SourceDocument,
+        pathway: &Pathway,
+    ) -> Result<Pending> {
+        let start = Instant::now();
+        self.check_cancelled()?;
+
+        let origin = document.url.clone().unwrap_or_else(|| document.id.clone());
+        let source = self.provenance(origin, Some("text/markdown"), &document.content)?;
+        let tags: Vec<MetadataOp> = document
+            .tags
+            .iter()
+            .map(|tag| MetadataOp::AddTag(tag.clone()))
+            .collect();
+
+        self.prepare(
+            pathway,
+            document.kind,
+            document.content.clone(),
+            source,
+            &tags,
+            start,
+        )
+        .await
+    }
+
+    /// Write content directly to a pathway, through the same stages as
+    /// ingested files, returning whether the node is new
+    ///
+    /// A new node's kind follows the extension of the pathway's name, as
+    /// for files; an existing node keeps its kind.
+    pub async fn process_content(&self, pathway: &Pathway, content: String) -> Result<bool> {
+        let start = Instant::now();
+        let kind = self.detect_kind(Path::new(pathway.name().unwrap_or_default()));
+        let source = self.provenance(pathway.to_string(), None, &content)?;
+        self.write(pathway, kind, content, source, &[], start).await
+    }
+
+    /// Ingest each thread of a chat export as `<target>/<channel>/<thread>`
+    async fn process_chat(
+        &self,
+        format: ChatFormat,
+        path: &Path,
+        target: &Pathway,
+    ) -> Result<IngestResult> {
+        let start = Instant::now();
+        let threads = format.read(path)?;
+        let export = path
+            .canonicalize()
+            .unwrap_or_else(|_| path.to_path_buf())
+            .to_string_lossy()
+            .to_string();
+
+        let mut result = IngestResult::new(target.clone());
+        let mut queue = EmbedQueue::default();
+        self.emit(IngestEvent::Planned {
+            items: threads.len(),
+        });
+        for thread in threads {
+            self.check_cancelled()?;
+            let pathway = target.join(&thread.channel).join(&thread.id);
+            let label = format!("{}/{}", thread.channel, thread.id);
+            self.start(&label, &pathway);
+            let content = thread.content();
+            let origin = format!(
+                "{}:{}#{}/{}",
+                format.as_str(),
+                export,
+                thread.channel,
+                thread.id
+            );
+            let metadata = [
+                MetadataOp::SetCustom(
+                    "participants".to_string(),
+                    serde_json::json!(thread.participants()),
+                ),
+                MetadataOp::SetCustom("channel".to_string(), serde_json::json!(thread.channel)),
+                MetadataOp::SetCustom(
+                    "started_at".to_string(),
+                    serde_json::json!(thread.started_at()),
+                ),
+            ];
+
+            let prepared = match self.provenance(origin, Some("text/plain"), &content) {
+                Ok(source) => {
+                    self.prepare(
+                        &pathway,
+                        NodeKind::Message,
+                        content,
+                        source,
+                        &metadata,
+                        start,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            let entries = match prepared {
+                Ok(pending) => self.enqueue(&mut queue, &label, pending).await,
+                Err(e) => vec![IngestEntry::not_written(
+                    &label,
+                    Some(pathway),
+                    IngestOutcome::Failed,
+                    e,
+                )],
+            };
+            for entry in entries {
+                self.record(&mut result, entry);
+            }
+        }
+        for entry in self.flush(&mut queue).await {
+            self.record(&mut result, entry);
+        }
+
+        tracing::info!(
+            format = format.as_str(),
+            created = result.nodes_created,
+            updated = result.nodes_updated,
+            errors = result.errors.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "chat export ingested"
+        );
+
+        Ok(result)
+    }
+
+    /// Ingest each page listed by a sitemap as `<target>/<url path>`
+    ///
+    /// Pages whose stored ETag still matches are left untouched.
+    #[cfg(feature = "web")]
+    async fn process_sitemap(&self, sitemap: &str, target: &Pathway) -> Result<IngestResult> {
+        use futures::StreamExt;
+
+        let start = Instant::now();
+        let fetcher = web::Fetcher::new(self.http.clone(), &self.config.ingest.web);
+        let urls = fetcher.sitemap_urls(sitemap).await?;
+        self.emit(IngestEvent::Planned { items: urls.len() });
+
+        let fetcher = &fetcher;
+        let outcomes: Vec<(String, Option<Pathway>, Result<IngestEntry>)> =
+            futures::stream::iter(urls)
+                .map(|url| async move {
+                    match web::page_path(&url) {
+                        Ok(path) => {
+                            let pathway = target.join(&path);
+                            self.start(&url, &pathway);
+                            let outcome = self.process_page(fetcher, &url, &pathway, start).await;
+                            (url, Some(pathway), outcome)
+                        }
+                        Err(e) => (url, None, Err(e)),
+                    }
+                })
+                .buffer_unordered(self.config.ingest.web.concurrency.max(1))
+                .collect()
+                .await;
 
-        // Create or update node
+        let mut result = IngestResult::new(target.clone());
+        for (url, pathway, outcome) in outcomes {
+            let entry = match outcome {
+                Ok(entry) => entry,
+                Err(A3SError::Cancelled) => return Err(A3SError::Cancelled),
+                Err(e) => IngestEntry::not_written(&url, pathway, IngestOutcome::Failed, e),
+            };
+            self.record(&mut result, entry);
+        }
+
+        tracing::info!(
+            sitemap,
+            created = result.nodes_created,
+            updated = result.nodes_updated,
+            unchanged = result.nodes_unchanged,
+            errors = result.errors.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "sitemap ingested"
+        );
+
+        Ok(result)
+    }
+
+    #[cfg(not(feature = "web"))]
+    async fn process_sitemap(&self, _sitemap: &str, _target: &Pathway) -> Result<IngestResult> {
+        let _ = &self.http;
+        Err(A3SError::Config(
+            "Sitemap ingestion requires the `web` feature".to_string(),
+        ))
+    }
+
+    /// Ingest a single web page as `target`, stripped to its readable text
+    ///
+    /// A page whose stored ETag still matches is left untouched.
+    #[cfg(feature = "web")]
+    async fn process_url(&self, url: &str, target: &Pathway) -> Result<IngestResult> {
+        let start = Instant::now();
+        let fetcher = web::Fetcher::new(self.http.clone(), &self.config.ingest.web);
+
+        let mut result = IngestResult::new(target.clone());
+        self.emit(IngestEvent::Planned { items: 1 });
+        self.start(url, target);
+        let entry = match self.process_page(&fetcher, url, target, start).await {
+            Ok(entry) => entry,
+            Err(A3SError::Cancelled) => return Err(A3SError::Cancelled),
+            Err(e) => IngestEntry::not_written(url, Some(target.clone()), IngestOutcome::Failed, e),
+        };
+        self.record(&mut result, entry);
+
+        tracing::info!(
+            url,
+            created = result.nodes_created,
+            updated = result.nodes_updated,
+            errors = result.errors.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "page ingested"
+        );
+
+        Ok(result)
+    }
+
+    #[cfg(not(feature = "web"))]
+    async fn process_url(&self, _url: &str, _target: &Pathway) -> Result<IngestResult> {
+        Err(A3SError::Config(
+            "URL ingestion requires the `web` feature".to_string(),
+        ))
+    }
+
+    /// Fetch and write one page as `pathway`, leaving it unchanged when the
+    /// server reports it has not been modified
+    #[cfg(feature = "web")]
+    async fn process_page(
+        &self,
+        fetcher: &web::Fetcher,
+        url: &str,
+        pathway: &Pathway,
+        start: Instant,
+    ) -> Result<IngestEntry> {
+        self.check_cancelled()?;
+        let etag = match self.storage.get(pathway).await {
+            Ok(node) => node
+                .metadata
+                .custom
+                .get(web::ETAG_KEY)
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            Err(A3SError::NodeNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        let (body, etag) = match fetcher.get(url, etag.as_deref()).await? {
+            web::Fetched::NotModified => {
+                return Ok(IngestEntry::not_written(
+                    url,
+                    Some(pathway.clone()),
+                    IngestOutcome::Unchanged,
+                    "Not modified since the last fetch",
+                ))
+            }
+            web::Fetched::Page { body, etag } => (body, etag),
+        };
+        let content = web::page_content(&body);
+        let source = self.provenance(url.to_string(), Some("text/html"), &content)?;
+        let metadata = [match etag {
+            Some(etag) => MetadataOp::SetCustom(web::ETAG_KEY.to_string(), serde_json::json!(etag)),
+            None => MetadataOp::RemoveCustom(web::ETAG_KEY.to_string()),
+        }];
+
+        let created = self
+            .write(
+                pathway,
+                NodeKind::Markdown,
+                content.clone(),
+                source,
+                &metadata,
+                start,
+            )
+            .await?;
+        Ok(IngestEntry::written(
+            url,
+            pathway.clone(),
+            created,
+            &content,
+            self.tokenizer(),
+        ))
+    }
+
+    /// Run content through the pipeline stages, returning whether the node
+    /// was new
+    async fn write(
+        &self,
+        pathway: &Pathway,
+        kind: NodeKind,
+        content: String,
+        source: SourceInfo,
+        metadata: &[MetadataOp],
+        start: Instant,
+    ) -> Result<bool> {
+        let mut pending = self
+            .prepare(pathway, kind, content, source, metadata, start)
+            .await?;
+        pending.embeddings = self.embed_texts(&pending.texts).await?;
+        self.finish(pending).await
+    }
+
+    /// Run content through the stages before `embed`, and gather the texts
+    /// `embed` needs: the content or its translation, the other configured
+    /// views, and each changed chunk
+    async fn prepare(
+        &self,
+        pathway: &Pathway,
+        kind: NodeKind,
+        content: String,
+        source: SourceInfo,
+        metadata: &[MetadataOp],
+        start: Instant,
+    ) -> Result<Pending> {
+        let mut item = self
+            .extract(pathway, kind, content.clone(), source, metadata)
+            .await?;
+        self.stages
+            .run(StagePosition::After(Stage::Extract), &mut item)
+            .await?;
+        for stage in [Stage::Transform, Stage::Chunk, Stage::Digest] {
+            self.stages
+                .run(StagePosition::Before(stage), &mut item)
+                .await?;
+            match stage {
+                Stage::Transform => self.transform(&mut item).await?,
+                Stage::Chunk => self.chunk(&mut item),
+                _ => self.digest(&mut item).await?,
+            }
+            self.stages
+                .run(StagePosition::After(stage), &mut item)
+                .await?;
+        }
+        self.stages
+            .run(StagePosition::Before(Stage::Embed), &mut item)
+            .await?;
+
+        let node = &item.node;
+        let mut texts = vec![translate::embedding_text(node).to_string()];
+        let mut names = Vec::new();
+        for name in &self.config.embedding.vectors {
+            if let Some(text) = vector_text(node, *name) {
+                names.push(*name);
+                texts.push(text);
+            }
+        }
+        let (chunks, rechunked, chunk_texts) = self
+            .build_chunks(node, &item.chunks, &mut RechunkResult::default())
+            .await?;
+        texts.extend(chunk_texts);
+
+        Ok(Pending {
+            item,
+            label: String::new(),
+            content,
+            start,
+            names,
+            chunks,
+            rechunked,
+            texts,
+            embeddings: Vec::new(),
+            failed: None,
+        })
+    }
+
+    /// Give a prepared item its embeddings and run it through the rest of
+    /// the stages, returning whether the node was new
+    async fn finish(&self, pending: Pending) -> Result<bool> {
+        let Pending {
+            mut item,
+            start,
+            names,
+            mut chunks,
+            rechunked,
+            embeddings,
+            ..
+        } = pending;
+        let mut embeddings = embeddings.into_iter();
+        item.node.embedding = embeddings.next().unwrap_or_default();
+        item.node.vectors = names.into_iter().zip(embeddings.by_ref()).collect();
+        for (i, embedding) in rechunked.into_iter().zip(embeddings) {
+            chunks[i].embedding = embedding;
+        }
+        self.emit(IngestEvent::Embedded {
+            pathway: item.node.pathway.clone(),
+        });
+        self.stages
+            .run(StagePosition::After(Stage::Embed), &mut item)
+            .await?;
+
+        self.stages
+            .run(StagePosition::Before(Stage::Store), &mut item)
+            .await?;
+        self.store(&item, &chunks).await?;
+        self.emit(IngestEvent::Stored {
+            pathway: item.node.pathway.clone(),
+        });
+        self.stages
+            .run(StagePosition::After(Stage::Store), &mut item)
+            .await?;
+
+        tracing::debug!(
+            pathway = %item.node.pathway,
+            bytes = item.node.size(),
+            created = item.created,
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "node ingested"
+        );
+
+        Ok(item.created)
+    }
+
+    /// Queue a prepared item reported as `label`, sending every full batch
+    /// of texts; returns the entries of the items this completed
+    async fn enqueue(
+        &self,
+        queue: &mut EmbedQueue,
+        label: &str,
+        mut pending: Pending,
+    ) -> Vec<IngestEntry> {
+        pending.label = label.to_string();
+        queue.items.push_back(pending);
+        self.send(queue, false).await
+    }
+
+    /// Embed and store every item still queued, returning their entries
+    pub async fn flush(&self, queue: &mut EmbedQueue) -> Vec<IngestEntry> {
+        self.send(queue, true).await
+    }
+
+    /// Send queued texts `embedding.batch_size` at a time, and a last
+    /// partial batch if `all`, then finish the items at the front of the
+    /// queue that have every embedding
+    async fn send(&self, queue: &mut EmbedQueue, all: bool) -> Vec<IngestEntry> {
+        let size = self.config.embedding.batch_size.max(1);
+        loop {
+            // Next unsent texts, with the item each belongs to
+            let (owners, texts): (Vec<usize>, Vec<String>) = queue
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.failed.is_none())
+                .flat_map(|(i, p)| p.texts[p.embeddings.len()..].iter().map(move |t| (i, t)))
+                .take(size)
+                .map(|(i, t)| (i, t.clone()))
+                .unzip();
+            if texts.is_empty() || (texts.len() < size && !all) {
+                break;
+            }
+            match self.embed_texts(&texts).await {
+                Ok(embeddings) => {
+                    for (i, embedding) in owners.into_iter().zip(embeddings) {
+                        queue.items[i].embeddings.push(embedding);
+                    }
+                }
+                Err(e) => {
+                    for i in owners {
+                        queue.items[i].failed = Some(e.to_string());
+                    }
+                }
+            }
+        }
+
+        let mut entries = Vec::new();
+        while queue
+            .items
+            .front()
+            .is_some_and(|p| p.failed.is_some() || p.embeddings.len() == p.texts.len())
+        {
+            let mut pending = queue.items.pop_front().expect("front item");
+            let label = std::mem::take(&mut pending.label);
+            let content = std::mem::take(&mut pending.content);
+            let pathway = pending.item.node.pathway.clone();
+            let written = match pending.failed.take() {
+                Some(error) => Err(error),
+                None => self.finish(pending).await.map_err(|e| e.to_string()),
+            };
+            entries.push(match written {
+                Ok(created) => {
+                    IngestEntry::written(&label, pathway, created, &content, self.tokenizer())
+                }
+                Err(e) => IngestEntry::not_written(&label, Some(pathway), IngestOutcome::Failed, e),
+            });
+        }
+        entries
+    }
+
+    /// Create the node, or update the stored one, with the source's content
+    async fn extract(
+        &self,
+        pathway: &Pathway,
+        kind: NodeKind,
+        content: String,
+        source: SourceInfo,
+        metadata: &[MetadataOp],
+    ) -> Result<IngestItem> {
+        let exists = self.storage.exists(pathway).await?;
         let mut node = if exists {
             let mut existing = self.storage.get(pathway).await?;
             existing.update_content(content);
             existing
         } else {
-            Node::new(pathway.clone(), kind, content)
+            let mut node = Node::new(pathway.clone(), kind, content);
+            node.created_at = self.clock.now();
+            node
         };
+        node.updated_at = self.clock.now();
+        node.metadata.source = Some(source);
+        node.metadata.custom.remove(translate::TRANSLATION_KEY);
+        apply_all(metadata, &mut node.metadata);
+        Ok(IngestItem::new(node, !exists))
+    }
 
-        // Generate digest
-        if self.config.llm.auto_digest {
-            node.digest = self
-                .digest_generator
-                .generate(&node.content, node.kind)
-                .await?;
+    /// Enforce write policies, before any expensive processing, and detect
+    /// the content's language
+    async fn transform(&self, item: &mut IngestItem) -> Result<()> {
+        let mut node = self.policies.apply(item.node.clone()).await?;
+
+        if node.kind != NodeKind::Code {
+            match language::detect(&node.content) {
+                Some(code) => {
+                    node.metadata
+                        .custom
+                        .insert(language::LANGUAGE_KEY.to_string(), serde_json::json!(code));
+                }
+                None => {
+                    node.metadata.custom.remove(language::LANGUAGE_KEY);
+                }
+            }
+        }
+        item.node = node;
+        Ok(())
+    }
+
+    /// Split long content when chunking is on
+    fn chunk(&self, item: &mut IngestItem) {
+        item.previous_chunks = chunk::chunk_count(&item.node);
+        item.chunks = if self.config.ingest.chunking {
+            chunk::split_node(
+                &item.node,
+                self.config.ingest.chunk_strategy,
+                self.config.ingest.chunk_size,
+                self.config.ingest.chunk_overlap,
+                self.chunk_measure(),
+            )
+        } else {
+            Vec::new()
+        };
+        set_chunk_count(&mut item.node, item.chunks.len());
+    }
+
+    /// Digest the content, or outline the chunks of a chunked node
+    async fn digest(&self, item: &mut IngestItem) -> Result<()> {
+        if !self.config.llm.auto_digest {
+            return Ok(());
+        }
+        item.node.digest = if item.chunks.is_empty() {
+            self.digest_generator
+                .generate(&item.node.content, item.node.kind)
+                .await?
+        } else {
+            let chunks: Vec<&str> = item.chunks.iter().map(|c| c.text.as_str()).collect();
+            self.digest_generator
+                .generate_from_chunks(&chunks, item.node.kind)
+                .await?
+        };
+        Ok(())
+    }
+
+    /// Embed `texts` in order, one request per `embedding.batch_size` texts
+    async fn embed_texts(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.config.embedding.batch_size.max(1)) {
+            let batch_embeddings = self.embedder.embed_batch(batch).await?;
+            if batch_embeddings.len() != batch.len() {
+                return Err(A3SError::Embedding(format!(
+                    "Expected {} embeddings, got {}",
+                    batch.len(),
+                    batch_embeddings.len()
+                )));
+            }
+            embeddings.extend(batch_embeddings);
+        }
+        Ok(embeddings)
+    }
+
+    async fn store(&self, item: &IngestItem, chunks: &[Node]) -> Result<()> {
+        drift::record_once(
+            self.storage.as_ref(),
+            &self.config.embedding,
+            &self.embedding_recorded,
+        )
+        .await?;
+        let relate = &self.config.ingest.relate;
+        if relate.auto {
+            let mut node = item.node.clone();
+            let suggestions = relate::suggest(self.storage.as_ref(), &node, relate).await?;
+            relate::apply(&mut node, &suggestions);
+            let node = self.policies.apply(node).await?;
+            self.storage.put(&node).await?;
+        } else {
+            self.storage.put(&item.node).await?;
+        }
+        self.write_chunks(
+            &item.node.pathway,
+            chunks,
+            item.previous_chunks,
+            &mut RechunkResult::default(),
+        )
+        .await
+    }
+
+    /// Chunk nodes for `chunks` of `parent`, through the write policies,
+    /// with the positions and texts of the chunks to embed
+    ///
+    /// Chunks whose text is unchanged keep their embedding and relations.
+    async fn build_chunks(
+        &self,
+        parent: &Node,
+        chunks: &[Chunk],
+        result: &mut RechunkResult,
+    ) -> Result<(Vec<Node>, Vec<usize>, Vec<String>)> {
+        let digests = DigestGenerator::simple().with_tokenizer(self.tokenizer.clone());
+        let name = chunk_source_name(parent);
+        let markdown = parent.kind == NodeKind::Markdown;
+        let mut nodes = Vec::with_capacity(chunks.len());
+        // Texts to embed, and the chunks they belong to
+        let mut texts = Vec::new();
+        let mut embedded = Vec::new();
+        for chunk in chunks {
+            let pathway = chunk::chunk_pathway(&parent.pathway, chunk.index);
+            let header = self
+                .config
+                .ingest
+                .chunk_headers
+                .then(|| chunk::context_header(&name, &parent.content, chunk, markdown));
+            let info = ChunkInfo {
+                parent: parent.pathway.clone(),
+                index: chunk.index,
+                start: chunk.start,
+                end: chunk.end,
+                header,
+            };
+
+            let mut node = match self.storage.get(&pathway).await {
+                Ok(existing) => existing,
+                Err(A3SError::NodeNotFound(_)) => {
+                    let mut node = Node::new(pathway.clone(), parent.kind, String::new());
+                    node.created_at = self.clock.now();
+                    node.add_relation(
+                        parent.pathway.clone(),
+                        RelationKind::DerivedFrom,
+                        "chunk".to_string(),
+                    );
+                    node
+                }
+                Err(e) => return Err(e),
+            };
+            let previous_content = std::mem::take(&mut node.content);
+            let previous_header = ChunkInfo::of(&node).and_then(|old| old.header);
+            let was_embedded = node.is_embedded();
+            let previous_updated = node.updated_at;
+            let previous_digest = std::mem::take(&mut node.digest);
+            node.content = chunk.text.clone();
+            node.metadata.tags = parent.metadata.tags.clone();
+            node.metadata.expires_at = parent.metadata.expires_at;
+            node.metadata.source = parent.metadata.source.clone();
+            for key in [language::LANGUAGE_KEY, language::CODE_LANGUAGE_KEY] {
+                match parent.metadata.custom.get(key) {
+                    Some(value) => node.metadata.custom.insert(key.to_string(), value.clone()),
+                    None => node.metadata.custom.remove(key),
+                };
+            }
+            node.metadata
+                .custom
+                .insert(chunk::CHUNK_KEY.to_string(), serde_json::to_value(&info)?);
+            if chunk.symbols.is_empty() {
+                node.metadata.custom.remove(syntax::SYMBOLS_KEY);
+            } else {
+                node.metadata.custom.insert(
+                    syntax::SYMBOLS_KEY.to_string(),
+                    serde_json::to_value(&chunk.symbols)?,
+                );
+            }
+            // Chunks pass the write policies like their parent did, and are
+            // compared and embedded as the policies left them
+            let mut node = self.policies.apply(node).await?;
+
+            let changed =
+                node.content != previous_content || !was_embedded || previous_header != info.header;
+            if changed {
+                node.updated_at = self.clock.now();
+                node.digest = digests.generate(&node.content, node.kind).await?;
+                texts.push(match &info.header {
+                    Some(header) => format!("{}{}", header, node.content),
+                    None => node.content.clone(),
+                });
+                embedded.push(nodes.len());
+                result.embedded += 1;
+            } else {
+                node.updated_at = previous_updated;
+                node.digest = previous_digest;
+                result.unchanged += 1;
+            }
+            nodes.push(node);
+        }
+        Ok((nodes, embedded, texts))
+    }
+
+    /// Store chunk nodes under `parent` and remove chunks left over from an
+    /// earlier, longer split
+    async fn write_chunks(
+        &self,
+        parent: &Pathway,
+        nodes: &[Node],
+        previous: usize,
+        result: &mut RechunkResult,
+    ) -> Result<()> {
+        self.storage.put_batch(nodes).await?;
+
+        for index in nodes.len()..previous {
+            let pathway = chunk::chunk_pathway(parent, index);
+            match self.storage.remove(&pathway, false).await {
+                Ok(()) => result.removed += 1,
+                Err(A3SError::NodeNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
         }
+        Ok(())
+    }
+
+    /// Re-split a stored node's content into chunks of `size` characters
+    /// overlapping by `overlap`, without reading its source again
+    ///
+    /// Only chunks whose text or header changed are re-embedded.
+    pub async fn rechunk(
+        &self,
+        node: &Node,
+        size: usize,
+        overlap: usize,
+        result: &mut RechunkResult,
+    ) -> Result<()> {
+        self.check_cancelled()?;
+        let previous = chunk::chunk_count(node);
+        let chunks = chunk::split_node(
+            node,
+            self.config.ingest.chunk_strategy,
+            size,
+            overlap,
+            self.chunk_measure(),
+        );
+
+        let mut parent = node.clone();
+        set_chunk_count(&mut parent, chunks.len());
+        let (mut nodes, rechunked, texts) = self.build_chunks(&parent, &chunks, result).await?;
+        let embeddings = self.embed_texts(&texts).await?;
+        for (i, embedding) in rechunked.into_iter().zip(embeddings) {
+            nodes[i].embedding = embedding;
+        }
+        if chunks.len() != previous {
+            let parent = self.policies.apply(parent.clone()).await?;
+            self.storage.put(&parent).await?;
+        }
+        self.write_chunks(&parent.pathway, &nodes, previous, result)
+            .await?;
+        result.nodes += 1;
+        Ok(())
+    }
+
+    /// Build the provenance record for a file's content
+    fn source_info(&self, path: &Path, content: &str) -> Result<SourceInfo> {
+        let origin = path
+            .canonicalize()
+            .unwrap_or_else(|_| path.to_path_buf())
+            .to_string_lossy()
+            .to_string();
+
+        self.provenance(origin, content_type_for(path), content)
+    }
 
-        // Generate embedding
-        let embedding = self.embedder.embed(&node.content).await?;
-        node.embedding = embedding;
+    /// Provenance record for content from `origin`, signed if a signer is set
+    fn provenance(
+        &self,
+        origin: String,
+        content_type: Option<&str>,
+        content: &str,
+    ) -> Result<SourceInfo> {
+        let mut source = SourceInfo {
+            origin,
+            content_type: content_type.map(|s| s.to_string()),
+            size: content.len() as u64,
+            hash: crate::provenance::sha256_hex(content.as_bytes()),
+            retrieved_at: Some(self.clock.now()),
+            signature: None,
+        };
 
-        // Store node
-        self.storage.put(&node).await?;
+        if let Some(signer) = &self.signer {
+            source.signature = Some(signer.sign(&source)?);
+        }
 
-        Ok(!exists)
+        Ok(source)
     }
 
     fn detect_kind(&self, path: &Path) -> NodeKind {
@@ -185,3 +1402,78 @@ impl Processor {
         false
     }
 }
+
+/// Name chunk headers use for a node: its source file name, or its own
+fn chunk_source_name(node: &Node) -> String {
+    node.metadata
+        .source
+        .as_ref()
+        .and_then(|source| Path::new(&source.origin).file_name())
+        .and_then(|name| name.to_str())
+        .or(node.pathway.name())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Text embedded as a node's named vector, if the node has that view
+fn vector_text(node: &Node, name: VectorName) -> Option<String> {
+    let text = match name {
+        // Content is embedded as `Node::embedding`
+        VectorName::Content => return None,
+        VectorName::Summary => node.digest.summary.clone(),
+        VectorName::Title => title(node),
+        VectorName::Questions => node
+            .metadata
+            .custom
+            .get(QUESTIONS_KEY)?
+            .as_array()?
+            .iter()
+            .filter_map(|question| question.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// First Markdown heading of a node's content, or its file name
+fn title(node: &Node) -> String {
+    let heading = match node.kind {
+        NodeKind::Code => None,
+        _ => node.content.lines().find_map(|line| {
+            let line = line.trim_start();
+            let text = line.trim_start_matches('#');
+            (text.len() < line.len() && text.starts_with(' ')).then(|| text.trim().to_string())
+        }),
+    };
+    heading.unwrap_or_else(|| chunk_source_name(node))
+}
+
+/// Record how many chunks a node was split into
+fn set_chunk_count(node: &mut Node, count: usize) {
+    if count == 0 {
+        node.metadata.custom.remove(chunk::CHUNK_COUNT_KEY);
+    } else {
+        node.metadata
+            .custom
+            .insert(chunk::CHUNK_COUNT_KEY.to_string(), serde_json::json!(count));
+    }
+}
+
+/// Guess a MIME type from a file extension
+fn content_type_for(path: &Path) -> Option<&'static str> {
+    let ext = path.extension().and_then(|s| s.to_str())?;
+
+    let content_type = match ext {
+        "md" => "text/markdown",
+        "txt" => "text/plain",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "eml" => "message/rfc822",
+        "mbox" => "application/mbox",
+        "rs" | "py" | "js" | "ts" | "go" | "java" | "c" | "cpp" | "h" => "text/x-source",
+        _ => return None,
+    };
+
+    Some(content_type)
+}
@@ -0,0 +1,110 @@
+//! Natural and programming language detection
+//!
+//! Ingest records the natural language of prose as an ISO 639-3 code (e.g.
+//! `eng`, `deu`, `cmn`) under [`LANGUAGE_KEY`], and the programming
+//! language of source files under [`CODE_LANGUAGE_KEY`].
+
+use whatlang::Lang;
+
+use crate::core::Node;
+
+/// Metadata key holding the detected natural language
+pub const LANGUAGE_KEY: &str = "language";
+
+/// Metadata key holding the programming language of code
+pub const CODE_LANGUAGE_KEY: &str = "code_language";
+
+/// Text sampled for detection
+const SAMPLE_CHARS: usize = 2000;
+
+/// ISO 639-3 code of the natural language of `text`, when detection is
+/// reliable
+pub fn detect(text: &str) -> Option<&'static str> {
+    let end = text
+        .char_indices()
+        .nth(SAMPLE_CHARS)
+        .map_or(text.len(), |(offset, _)| offset);
+    let info = whatlang::detect(&text[..end])?;
+    info.is_reliable().then(|| info.lang().code())
+}
+
+/// Programming language of a source file extension
+pub fn code_language(extension: &str) -> Option<&'static str> {
+    let language = match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "c" | "h" => "c",
+        "cpp" => "cpp",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// English name of a language code, e.g. `German` for `deu`
+pub fn name(code: &str) -> Option<&'static str> {
+    Lang::from_code(code).map(|lang| lang.eng_name())
+}
+
+/// Whether a node's natural or programming language is `language`, given
+/// as an ISO 639-3 code, an English name, or a programming language
+pub fn matches(node: &Node, language: &str) -> bool {
+    let wanted = normalize(language);
+    [LANGUAGE_KEY, CODE_LANGUAGE_KEY].iter().any(|key| {
+        node.metadata
+            .custom
+            .get(*key)
+            .and_then(|v| v.as_str())
+            .is_some_and(|found| found.eq_ignore_ascii_case(&wanted))
+    })
+}
+
+/// Language code for an English language name; other input unchanged
+fn normalize(language: &str) -> String {
+    let language = language.trim();
+    Lang::all()
+        .iter()
+        .find(|lang| lang.eng_name().eq_ignore_ascii_case(language))
+        .map_or_else(|| language.to_lowercase(), |lang| lang.code().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+    use crate::pathway::Pathway;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect("The deployment pipeline builds, tests, and ships every merged change to production."),
+            Some("eng")
+        );
+        assert_eq!(
+            detect("Die Bereitstellung erfolgt automatisch, sobald alle Tests erfolgreich durchgelaufen sind."),
+            Some("deu")
+        );
+        assert_eq!(detect(""), None);
+    }
+
+    #[test]
+    fn test_matches_codes_and_names() {
+        let mut node = Node::new(
+            Pathway::parse("a3s://knowledge/docs/guide").unwrap(),
+            NodeKind::Document,
+            String::new(),
+        );
+        node.metadata
+            .custom
+            .insert(LANGUAGE_KEY.to_string(), serde_json::json!("deu"));
+
+        assert!(matches(&node, "deu"));
+        assert!(matches(&node, "German"));
+        assert!(!matches(&node, "eng"));
+        assert_eq!(name("deu"), Some("German"));
+        assert_eq!(code_language("rs"), Some("rust"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Exclusive, expiring write leases on subtrees
+//!
+//! While a lease is live, writes to its subtree must run inside
+//! [`scope`] with that lease; other writers get `A3SError::Leased`.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::clock::{Clock, SystemClock};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+
+tokio::task_local! {
+    /// Tokens of the leases the current task writes under
+    static HELD: Vec<String>;
+}
+
+/// Exclusive write access to a subtree until `expires_at`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lease {
+    pub token: String,
+    pub pathway: Pathway,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Lease {
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Utc::now())
+    }
+
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    fn overlaps(&self, pathway: &Pathway) -> bool {
+        self.pathway.is_prefix_of(pathway) || pathway.is_prefix_of(&self.pathway)
+    }
+}
+
+/// Run `f` with writes permitted under `lease`
+pub async fn scope<F: Future>(lease: &Lease, f: F) -> F::Output {
+    let mut tokens = HELD.try_with(Clone::clone).unwrap_or_default();
+    tokens.push(lease.token.clone());
+    HELD.scope(tokens, f).await
+}
+
+/// Live leases held against one store, expiring by a [`Clock`]
+pub struct LeaseTable {
+    leases: Mutex<Vec<Lease>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for LeaseTable {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemClock))
+    }
+}
+
+impl LeaseTable {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            leases: Mutex::new(Vec::new()),
+            clock,
+        }
+    }
+
+    /// Lease a subtree, failing if a live lease overlaps it
+    pub fn acquire(&self, pathway: Pathway, ttl: Duration) -> Result<Lease> {
+        let now = self.clock.now();
+        let mut leases = self.leases.lock();
+        leases.retain(|lease| !lease.is_expired_at(now));
+        if let Some(held) = leases.iter().find(|lease| lease.overlaps(&pathway)) {
+            return Err(A3SError::Leased(format!(
+                "{} overlaps the lease on {} until {}",
+                pathway,
+                held.pathway,
+                held.expires_at.to_rfc3339()
+            )));
+        }
+
+        let lease = Lease {
+            token: Uuid::new_v4().to_string(),
+            pathway,
+            expires_at: expiry(now, ttl)?,
+        };
+        leases.push(lease.clone());
+        Ok(lease)
+    }
+
+    /// Extend a live lease to `ttl` from now
+    pub fn renew(&self, lease: &Lease, ttl: Duration) -> Result<Lease> {
+        let now = self.clock.now();
+        let mut leases = self.leases.lock();
+        let held = leases
+            .iter_mut()
+            .find(|held| held.token == lease.token && !held.is_expired_at(now))
+            .ok_or_else(|| A3SError::Leased(format!("lease on {} has expired", lease.pathway)))?;
+        held.expires_at = expiry(now, ttl)?;
+        Ok(held.clone())
+    }
+
+    /// Give up a lease, returning whether it was still held
+    pub fn release(&self, lease: &Lease) -> bool {
+        let mut leases = self.leases.lock();
+        let before = leases.len();
+        leases.retain(|held| held.token != lease.token);
+        leases.len() != before
+    }
+
+    /// Fail if a live lease the current task does not hold covers `pathway`,
+    /// or, when `recursive`, anything beneath it
+    pub fn check(&self, pathway: &Pathway, recursive: bool) -> Result<()> {
+        let held = HELD.try_with(Clone::clone).unwrap_or_default();
+        let now = self.clock.now();
+        let leases = self.leases.lock();
+        let blocking = leases.iter().find(|lease| {
+            !lease.is_expired_at(now)
+                && !held.contains(&lease.token)
+                && (lease.pathway.is_prefix_of(pathway)
+                    || (recursive && pathway.is_prefix_of(&lease.pathway)))
+        });
+
+        match blocking {
+            Some(lease) => Err(A3SError::Leased(format!(
+                "{} is leased at {} until {}",
+                pathway,
+                lease.pathway,
+                lease.expires_at.to_rfc3339()
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn expiry(now: DateTime<Utc>, ttl: Duration) -> Result<DateTime<Utc>> {
+    let ttl = chrono::Duration::from_std(ttl)
+        .map_err(|_| A3SError::Leased(format!("lease TTL too long: {:?}", ttl)))?;
+    Ok(now + ttl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lease_blocks_other_writers() {
+        let table = LeaseTable::default();
+        let plans = Pathway::parse("a3s://knowledge/plans").unwrap();
+        let plan = Pathway::parse("a3s://knowledge/plans/q3").unwrap();
+
+        let lease = table
+            .acquire(plans.clone(), Duration::from_secs(60))
+            .unwrap();
+        assert!(table
+            .acquire(plan.clone(), Duration::from_secs(60))
+            .is_err());
+
+        assert!(matches!(
+            table.check(&plan, false),
+            Err(A3SError::Leased(_))
+        ));
+        scope(&lease, async { table.check(&plan, false) })
+            .await
+            .unwrap();
+
+        assert!(table.release(&lease));
+        table.check(&plan, false).unwrap();
+    }
+
+    #[test]
+    fn test_expired_lease_is_ignored() {
+        let table = LeaseTable::default();
+        let plans = Pathway::parse("a3s://knowledge/plans").unwrap();
+
+        let lease = table.acquire(plans.clone(), Duration::ZERO).unwrap();
+        table.check(&plans, true).unwrap();
+        assert!(table.renew(&lease, Duration::from_secs(60)).is_err());
+        table.acquire(plans, Duration::from_secs(60)).unwrap();
+    }
+
+    #[test]
+    fn test_lease_expires_by_clock() {
+        let clock = Arc::new(crate::testing::TestClock::default());
+        let table = LeaseTable::new(clock.clone());
+        let plans = Pathway::parse("a3s://knowledge/plans").unwrap();
+
+        table
+            .acquire(plans.clone(), Duration::from_secs(60))
+            .unwrap();
+        clock.advance(chrono::Duration::seconds(59));
+        assert!(table.check(&plans, false).is_err());
+        clock.advance(chrono::Duration::seconds(1));
+        table.check(&plans, false).unwrap();
+    }
+}
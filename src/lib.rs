@@ -31,124 +31,977 @@
 //! }
 //! ```
 
+pub mod analytics;
+pub mod anomaly;
+pub mod answer;
+pub mod archive;
+pub mod assembly;
+pub mod batch;
+pub mod bulk;
+pub mod capability;
+pub mod chat;
+pub mod chunk;
+pub mod clock;
+pub mod cluster;
+pub mod compare;
+pub mod condense;
 pub mod config;
+pub mod connector;
 pub mod core;
+pub mod dedup;
+pub mod diff;
 pub mod digest;
+pub mod drift;
+pub mod email;
 pub mod embedding;
 pub mod error;
+pub mod grep;
+pub mod http;
 pub mod ingest;
+pub mod language;
+pub mod lease;
+pub mod listing;
+pub mod pack;
 pub mod pathway;
+pub mod pipeline;
+pub mod policy;
+pub mod provenance;
+pub mod relate;
+pub mod relocate;
+pub mod render;
 pub mod rerank;
 pub mod retrieval;
+pub mod routing;
+pub mod saved;
+pub mod schedule;
+pub mod schema;
+pub mod screening;
 pub mod session;
+pub mod snapshot;
 pub mod storage;
+pub mod syntax;
+pub mod testing;
+pub mod text;
+pub mod tokenizer;
+pub mod translate;
+pub mod tree;
+pub mod usage;
+pub mod view;
+pub mod web;
+pub mod window;
 
 pub use crate::config::Config;
-pub use crate::core::{Namespace, Node, NodeKind};
+pub use crate::core::{Namespace, Node, NodeKind, VectorName};
 pub use crate::error::{A3SError, Result};
 pub use crate::pathway::Pathway;
+pub use tokio_util::sync::CancellationToken;
 
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
 
 /// Main client for interacting with A3S Context
 pub struct A3SClient {
     config: Config,
     storage: Arc<dyn storage::StorageBackend>,
+    /// Same store as `storage`, for managing mounts
+    overlay: Arc<storage::OverlayStorage>,
     embedder: Arc<dyn embedding::Embedder>,
-    state: Arc<RwLock<ClientState>>,
+    http: http::HttpClient,
+    policies: Arc<policy::PolicyChain>,
+    stages: Arc<pipeline::StageRegistry>,
+    signer: parking_lot::RwLock<Option<Arc<dyn provenance::ProvenanceSigner>>>,
+    tokenizer: parking_lot::RwLock<Arc<dyn tokenizer::Tokenizer>>,
+    clock: Arc<dyn clock::Clock>,
+    /// Local query log, when analytics are enabled
+    analytics: Option<Arc<analytics::QueryLog>>,
+    /// Mismatch between the configured embedding model and the store's,
+    /// found at startup
+    embedding_drift: parking_lot::RwLock<Option<drift::EmbeddingDrift>>,
+    /// Task taking scheduled backups, when `storage.backup` asks for them
+    backup_task: Option<tokio::task::JoinHandle<()>>,
+    state: ClientState,
 }
 
+/// Mutable client state; every field synchronizes itself, so no outer lock is needed
 struct ClientState {
-    initialized: bool,
-    active_sessions: dashmap::DashMap<String, session::Session>,
+    initialized: AtomicBool,
+    /// Registered session IDs and when each was last touched
+    active_sessions: dashmap::DashMap<String, chrono::DateTime<chrono::Utc>>,
+    /// Live write leases on subtrees
+    leases: Arc<lease::LeaseTable>,
+    /// Slots for queries and bulk work, interactive requests first
+    scheduler: schedule::Scheduler,
+    /// The store has a record of its embedding model
+    embedding_recorded: Arc<AtomicBool>,
 }
 
 impl A3SClient {
     /// Create a new A3S client with the given configuration
     pub async fn new(config: Config) -> Result<Self> {
-        let storage = storage::create_backend(&config.storage).await?;
-        let embedder = embedding::create_embedder(&config.embedding).await?;
+        let http = http::build_client(&config.http)?;
+        let mut base = storage::create_backend(&config.storage, &http).await?;
+        if config.storage.consistency == config::Consistency::Eventual {
+            base = Arc::new(storage::WriteBehindStorage::new(base));
+        }
+        let overlay = Arc::new(storage::OverlayStorage::new(base));
+        let embedder = embedding::create_embedder(&config.embedding, &http).await?;
 
-        let state = Arc::new(RwLock::new(ClientState {
-            initialized: false,
+        let analytics = config.analytics.enabled.then(|| {
+            let path = config
+                .analytics
+                .path
+                .clone()
+                .unwrap_or_else(|| config.storage.path.join("analytics.jsonl"));
+            Arc::new(analytics::QueryLog::new(path))
+        });
+
+        let state = ClientState {
+            initialized: AtomicBool::new(false),
             active_sessions: dashmap::DashMap::new(),
-        }));
+            leases: Arc::new(lease::LeaseTable::default()),
+            scheduler: schedule::Scheduler::new(&config.scheduler),
+            embedding_recorded: Arc::new(AtomicBool::new(false)),
+        };
 
-        let client = Self {
+        let mut client = Self {
             config,
-            storage,
+            storage: overlay.clone(),
+            overlay,
             embedder,
+            http,
+            policies: Arc::new(policy::PolicyChain::new()),
+            stages: Arc::new(pipeline::StageRegistry::new()),
+            signer: parking_lot::RwLock::new(None),
+            tokenizer: parking_lot::RwLock::new(Arc::new(tokenizer::HeuristicTokenizer)),
+            clock: Arc::new(clock::SystemClock),
+            analytics,
+            embedding_drift: parking_lot::RwLock::new(None),
+            backup_task: None,
             state,
         };
 
         client.initialize().await?;
+        client.check_embedding_model().await?;
+        client.register_builtin_stages()?;
+        for mount in &client.config.storage.mounts {
+            client.mount_configured(mount).await?;
+        }
+        for proxy in &client.config.storage.proxies {
+            client.proxy(proxy).await?;
+        }
+        client.backup_task = client.schedule_backups()?;
 
         Ok(client)
     }
 
+    /// Start taking a backup every `storage.backup.interval_secs`, if set
+    fn schedule_backups(&self) -> Result<Option<tokio::task::JoinHandle<()>>> {
+        let backup = &self.config.storage.backup;
+        let Some(target) = backup.target.as_deref() else {
+            return Ok(None);
+        };
+        if backup.interval_secs == 0 {
+            return Ok(None);
+        }
+        let target = storage::BackupTarget::parse(target, &self.http)?;
+        let overlay = self.overlay.clone();
+        let embedding = self.config.embedding.clone();
+        let keep = backup.keep;
+        let period = Duration::from_secs(backup.interval_secs);
+
+        Ok(Some(tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                let result = async {
+                    let _barrier = overlay.exclusive().await;
+                    storage::StorageBackend::settle(overlay.as_ref()).await?;
+                    storage::backup(overlay.base().as_ref(), &target, &embedding, keep).await
+                };
+                match result.await {
+                    Ok(manifest) => tracing::info!(
+                        name = %manifest.name,
+                        nodes = manifest.node_count,
+                        "Scheduled backup written"
+                    ),
+                    Err(e) => tracing::warn!("Scheduled backup failed: {}", e),
+                }
+            }
+        })))
+    }
+
     /// Initialize the storage backend
     async fn initialize(&self) -> Result<()> {
         self.storage.initialize().await?;
 
-        let mut state = self.state.write().await;
-        state.initialized = true;
+        self.state.initialized.store(true, Ordering::Release);
 
         tracing::info!("A3S Context initialized successfully");
         Ok(())
     }
 
-    /// Ingest content from a source path into the specified pathway
+    /// Compare the configured embedding model with the one the store was
+    /// embedded with, warning on a mismatch
+    async fn check_embedding_model(&self) -> Result<()> {
+        let drift = match drift::stored(self.storage.as_ref()).await? {
+            Some(stored) => {
+                self.state.embedding_recorded.store(true, Ordering::Release);
+                drift::compare(stored, &self.config.embedding)
+            }
+            None => None,
+        };
+        if let Some(drift) = &drift {
+            tracing::warn!(
+                stored_model = %drift.stored.model,
+                stored_dimension = drift.stored.dimension,
+                configured_model = %drift.configured.model,
+                configured_dimension = drift.configured.dimension,
+                strict = self.config.embedding.strict_check,
+                "Embedding model differs from the one the store was embedded with; \
+                 similarities will be meaningless until content is re-ingested"
+            );
+        }
+        *self.embedding_drift.write() = drift;
+        Ok(())
+    }
+
+    /// Mismatch between the configured embedding model and the one the
+    /// store was embedded with, if any
+    pub fn embedding_drift(&self) -> Option<drift::EmbeddingDrift> {
+        self.embedding_drift.read().clone()
+    }
+
+    /// Record the configured embedding model as the store's, e.g. after
+    /// re-ingesting everything with it, clearing any drift
+    pub async fn record_embedding_model(&self) -> Result<()> {
+        drift::record(self.storage.as_ref(), &self.config.embedding).await?;
+        self.state.embedding_recorded.store(true, Ordering::Release);
+        *self.embedding_drift.write() = None;
+        Ok(())
+    }
+
+    /// With `embedding.strict_check`, fail instead of searching a store
+    /// embedded with another model
+    fn ensure_embedding_compatible(&self) -> Result<()> {
+        match self.embedding_drift() {
+            Some(drift) if self.config.embedding.strict_check => {
+                Err(A3SError::EmbeddingMismatch(drift.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record the configured embedding model before the first embeddings
+    /// are written to a store that has no record yet
+    async fn record_embedding_model_once(&self) -> Result<()> {
+        drift::record_once(
+            self.storage.as_ref(),
+            &self.config.embedding,
+            &self.state.embedding_recorded,
+        )
+        .await
+    }
+
+    /// Ingest content from a source path or web page URL into the specified pathway
     pub async fn ingest<P: AsRef<str>, T: AsRef<str>>(
         &self,
         source: P,
         target: T,
     ) -> Result<IngestResult> {
         let pathway = Pathway::parse(target.as_ref())?;
-        let processor =
-            ingest::Processor::new(self.storage.clone(), self.embedder.clone(), &self.config);
+        self.ensure_writable(&pathway, true).await?;
+        let _slot = self.background_slot().await;
+        self.processor().process(source.as_ref(), &pathway).await
+    }
 
-        processor.process(source.as_ref(), &pathway).await
+    /// Ingest content, stopping between files once `cancel` is triggered
+    ///
+    /// Nodes written before cancellation are kept.
+    pub async fn ingest_with_cancel<P: AsRef<str>, T: AsRef<str>>(
+        &self,
+        source: P,
+        target: T,
+        cancel: CancellationToken,
+    ) -> Result<IngestResult> {
+        let pathway = Pathway::parse(target.as_ref())?;
+        self.ensure_writable(&pathway, true).await?;
+        let _slot = self.background_slot().await;
+        self.processor()
+            .with_cancellation(cancel)
+            .process(source.as_ref(), &pathway)
+            .await
     }
 
-    /// Query the context store with natural language
-    pub async fn query(&self, query: &str) -> Result<QueryResult> {
+    /// Ingest content, sending an [`ingest::IngestEvent`] to `progress` as
+    /// each file, page, or thread is started, embedded, stored, skipped, or
+    /// fails
+    ///
+    /// Events stop when the ingest returns; a dropped receiver does not
+    /// interrupt it.
+    pub async fn ingest_with_progress<P: AsRef<str>, T: AsRef<str>>(
+        &self,
+        source: P,
+        target: T,
+        progress: tokio::sync::mpsc::UnboundedSender<ingest::IngestEvent>,
+    ) -> Result<IngestResult> {
+        let pathway = Pathway::parse(target.as_ref())?;
+        self.ensure_writable(&pathway, true).await?;
+        let _slot = self.background_slot().await;
+        self.processor()
+            .with_progress(progress)
+            .process(source.as_ref(), &pathway)
+            .await
+    }
+
+    /// Sync a configured connector (`notion`, `confluence`, `github`) into `target`
+    pub async fn sync<T: AsRef<str>>(&self, connector: &str, target: T) -> Result<IngestResult> {
+        let connector =
+            connector::create_connector(connector, &self.config.connectors, &self.http)?;
+        self.sync_with(connector.as_ref(), target).await
+    }
+
+    /// Ingest documents changed since the connector's last sync into `target`
+    ///
+    /// The connector's cursor is stored on the target directory and only
+    /// advanced when every document was ingested, so failures are retried
+    /// on the next sync.
+    pub async fn sync_with<T: AsRef<str>>(
+        &self,
+        connector: &dyn connector::Connector,
+        target: T,
+    ) -> Result<IngestResult> {
+        let target = Pathway::parse(target.as_ref())?;
+        self.ensure_writable(&target, true).await?;
+        let _slot = self.background_slot().await;
+        let processor = self.processor();
+
+        let stored = match self.storage.get(&target).await {
+            Ok(node) => connector::cursor(&node, connector.name()),
+            Err(A3SError::NodeNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut result = IngestResult::new(target.clone());
+        let mut queue = ingest::EmbedQueue::default();
+        let mut cursor = stored;
+        loop {
+            let batch = connector.fetch(cursor.as_deref()).await?;
+            for document in &batch.documents {
+                let pathway = target.join(&document.path);
+                for entry in processor
+                    .queue_document(&mut queue, document, &pathway)
+                    .await
+                {
+                    result.record(entry);
+                }
+            }
+            if batch.cursor.is_some() {
+                cursor = batch.cursor;
+            }
+            if !batch.has_more {
+                break;
+            }
+        }
+        for entry in processor.flush(&mut queue).await {
+            result.record(entry);
+        }
+
+        if let (Some(cursor), true) = (&cursor, result.errors.is_empty()) {
+            let mut root = match self.storage.get(&target).await {
+                Ok(node) => node,
+                Err(A3SError::NodeNotFound(_)) => Node::directory(target.clone()),
+                Err(e) => return Err(e),
+            };
+            connector::set_cursor(&mut root, connector.name(), cursor);
+            let root = self.policies.apply(root).await?;
+            self.storage.put(&root).await?;
+        }
+
+        tracing::info!(
+            connector = connector.name(),
+            created = result.nodes_created,
+            updated = result.nodes_updated,
+            errors = result.errors.len(),
+            "sync complete"
+        );
+        Ok(result)
+    }
+
+    /// Query router, if routing is enabled
+    fn router(&self, config: &config::RetrievalConfig) -> Option<routing::Router> {
+        let routing = &config.routing;
+        if !routing.enabled {
+            return None;
+        }
+        let router = routing::Router::new(routing);
+
+        #[cfg(feature = "llm-digest")]
+        if routing.classifier == config::RoutingClassifier::Llm {
+            if let Some(llm) = self.llm_client() {
+                return Some(router.with_llm(llm));
+            }
+        }
+
+        Some(router)
+    }
+
+    /// Client for the configured LLM, if one is configured
+    #[cfg(feature = "llm-digest")]
+    fn llm_client(&self) -> Option<digest::LLMClient> {
+        let llm = &self.config.llm;
+        Some(
+            digest::LLMClient::new(
+                llm.api_base.clone()?,
+                llm.api_key.clone().unwrap_or_default(),
+                llm.model.clone().unwrap_or_default(),
+            )
+            .with_request_policy(&llm.requests)
+            .with_timeout(llm.timeout_secs)
+            .with_http_client(self.http.clone()),
+        )
+    }
+
+    fn retriever(&self) -> retrieval::Retriever {
+        let retriever = self.retriever_for(&self.config.retrieval);
+        match &self.analytics {
+            Some(log) => retriever.with_analytics(log.clone()),
+            None => retriever,
+        }
+    }
+
+    /// Retriever for a retrieval configuration, ranking by feedback but not
+    /// logging queries
+    fn retriever_for(&self, config: &config::RetrievalConfig) -> retrieval::Retriever {
         let retriever = retrieval::Retriever::new(
             self.storage.clone(),
             self.embedder.clone(),
-            &self.config.retrieval,
-        );
+            config,
+            &self.http,
+        )
+        .with_clock(self.clock.clone());
+        let retriever = match self.router(config) {
+            Some(router) => retriever.with_router(router),
+            None => retriever,
+        };
+        match &self.analytics {
+            Some(log) => retriever.with_feedback(log.clone()),
+            None => retriever,
+        }
+    }
+
+    fn processor(&self) -> ingest::Processor {
+        let processor = ingest::Processor::new(
+            self.storage.clone(),
+            self.embedder.clone(),
+            &self.config,
+            &self.http,
+        )
+        .with_policies(self.policies.clone())
+        .with_stages(self.stages.clone())
+        .with_tokenizer(self.tokenizer())
+        .with_clock(self.clock.clone())
+        .with_embedding_record(self.state.embedding_recorded.clone());
+
+        match self.signer.read().clone() {
+            Some(signer) => processor.with_signer(signer),
+            None => processor,
+        }
+    }
+
+    /// Register a policy that every node must pass before it is written
+    pub fn add_write_policy(&self, policy: Arc<dyn policy::WritePolicy>) {
+        self.policies.push(policy);
+    }
+
+    /// Register a JSON Schema that the custom metadata of every node in
+    /// `scope` must satisfy before it is written
+    pub fn register_metadata_schema(
+        &self,
+        scope: schema::SchemaScope,
+        schema: &serde_json::Value,
+    ) -> Result<()> {
+        self.policies.schemas().register(scope, schema)
+    }
+
+    /// Register the stages enabled in the configuration
+    fn register_builtin_stages(&self) -> Result<()> {
+        let screening = &self.config.ingest.screening;
+        if screening.enabled {
+            self.stages.register(
+                pipeline::StagePosition::Before(pipeline::Stage::Chunk),
+                Arc::new(screening::ScreeningStage::new(screening.action)),
+            )?;
+        }
+        self.register_translation_stage()
+    }
+
+    #[cfg(feature = "llm-digest")]
+    fn register_translation_stage(&self) -> Result<()> {
+        let translation = &self.config.ingest.translation;
+        if translation.enabled {
+            let llm = self.llm_client().ok_or_else(|| {
+                A3SError::Config("ingest.translation requires llm.api_base".to_string())
+            })?;
+            self.stages.register(
+                pipeline::StagePosition::Before(pipeline::Stage::Embed),
+                Arc::new(translate::TranslationStage::new(
+                    llm,
+                    translation.target_language.clone(),
+                )),
+            )?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "llm-digest"))]
+    fn register_translation_stage(&self) -> Result<()> {
+        if self.config.ingest.translation.enabled {
+            return Err(A3SError::Config(
+                "ingest.translation requires the `llm-digest` feature".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run a custom stage on every ingested node, before or after a built-in
+    /// stage
+    pub fn add_pipeline_stage(
+        &self,
+        position: pipeline::StagePosition,
+        stage: Arc<dyn pipeline::PipelineStage>,
+    ) -> Result<()> {
+        self.stages.register(position, stage)
+    }
+
+    /// Sign the provenance record of every node ingested from now on
+    pub fn set_provenance_signer(&self, signer: Arc<dyn provenance::ProvenanceSigner>) {
+        *self.signer.write() = Some(signer);
+    }
+
+    /// Count tokens for context assembly, digests, ingest reports, and
+    /// token-sized chunks with `tokenizer` instead of estimating them from
+    /// characters
+    pub fn set_tokenizer(&self, tokenizer: Arc<dyn tokenizer::Tokenizer>) {
+        *self.tokenizer.write() = tokenizer;
+    }
 
-        retriever.search(query, None).await
+    /// Tokenizer used to count and budget tokens
+    pub fn tokenizer(&self) -> Arc<dyn tokenizer::Tokenizer> {
+        self.tokenizer.read().clone()
+    }
+
+    /// Read the time from `clock` instead of the system clock: for the
+    /// timestamps of nodes the client writes, expiry, match age and
+    /// staleness, view refreshes, lease expiry, and session timestamps and
+    /// idle eviction
+    ///
+    /// Leases taken before the clock is set are dropped.
+    pub fn with_clock(mut self, clock: Arc<dyn clock::Clock>) -> Self {
+        self.state.leases = Arc::new(lease::LeaseTable::new(clock.clone()));
+        self.clock = clock;
+        self
+    }
+
+    /// Current time by the client's clock
+    pub fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.clock.now()
+    }
+
+    /// Get the provenance record of a node, if it was ingested from a source
+    pub async fn provenance<P: AsRef<str>>(
+        &self,
+        pathway: P,
+    ) -> Result<Option<crate::core::SourceInfo>> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        let node = self.storage.get(&pathway).await?;
+        Ok(node.metadata.source)
+    }
+
+    /// Query the context store with natural language
+    pub async fn query(&self, query: &str) -> Result<QueryResult> {
+        self.ensure_embedding_compatible()?;
+        let _slot = self
+            .state
+            .scheduler
+            .acquire(schedule::Priority::Interactive)
+            .await;
+        self.retriever().search(query, None).await
     }
 
     /// Query with additional options
+    ///
+    /// Background-priority queries wait while interactive ones are queued.
     pub async fn query_with_options(
         &self,
         query: &str,
         options: QueryOptions,
     ) -> Result<QueryResult> {
-        let retriever = retrieval::Retriever::new(
-            self.storage.clone(),
-            self.embedder.clone(),
-            &self.config.retrieval,
+        self.ensure_embedding_compatible()?;
+        if options.consistent_read {
+            self.storage.settle().await?;
+        }
+        let _slot = self.state.scheduler.acquire(options.priority).await;
+        let snapshot_session = options.snapshot_session.clone();
+        let result = self.retriever().search(query, Some(options)).await?;
+        if let Some(session_id) = snapshot_session {
+            snapshot::record(self.storage.as_ref(), &session_id, query, &result).await?;
+        }
+        Ok(result)
+    }
+
+    /// Read the snapshot of a query recorded under a session
+    pub async fn retrieval_snapshot(
+        &self,
+        session_id: &str,
+        query_id: &str,
+    ) -> Result<snapshot::RetrievalSnapshot> {
+        let pathway = snapshot::snapshot_pathway(session_id, query_id)?;
+        snapshot::from_node(&self.storage.get(&pathway).await?)
+    }
+
+    /// Every retrieval snapshot recorded under a session, oldest first
+    pub async fn retrieval_snapshots(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<snapshot::RetrievalSnapshot>> {
+        let root = snapshot::retrievals_root(session_id)?;
+        let mut snapshots = self
+            .storage
+            .get_children(&root, 1)
+            .await?
+            .iter()
+            .map(snapshot::from_node)
+            .collect::<Result<Vec<_>>>()?;
+        snapshots.sort_by_key(|s| s.taken_at);
+        Ok(snapshots)
+    }
+
+    /// Replay a session's committed messages and retrieval snapshots in the
+    /// order they happened, passing each to `sink`
+    ///
+    /// Replay stops at the first error `sink` returns. Returns the number of
+    /// events replayed.
+    pub async fn replay_session<F>(&self, session_id: &str, mut sink: F) -> Result<usize>
+    where
+        F: FnMut(session::ReplayEvent) -> Result<()>,
+    {
+        let mut events: Vec<session::ReplayEvent> =
+            session::load_messages(self.storage.as_ref(), session_id)
+                .await?
+                .into_iter()
+                .map(session::ReplayEvent::Message)
+                .collect();
+        events.extend(
+            self.retrieval_snapshots(session_id)
+                .await?
+                .into_iter()
+                .map(|s| session::ReplayEvent::Retrieval(Box::new(s))),
+        );
+        // Stable, so a message stays ahead of a retrieval taken the same instant
+        events.sort_by_key(|e| e.timestamp());
+
+        let count = events.len();
+        for event in events {
+            sink(event)?;
+        }
+        Ok(count)
+    }
+
+    /// Wait for a slot for bulk work such as ingest or rechunking
+    async fn background_slot(&self) -> schedule::Slot<'_> {
+        self.state
+            .scheduler
+            .acquire(schedule::Priority::Background)
+            .await
+    }
+
+    /// Record whether a query's match was useful, for local analytics
+    ///
+    /// Does nothing unless `analytics.enabled` is set.
+    pub async fn record_feedback<P: AsRef<str>>(
+        &self,
+        query_id: &str,
+        pathway: P,
+        useful: bool,
+    ) -> Result<()> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        let Some(log) = &self.analytics else {
+            return Ok(());
+        };
+        log.record(&analytics::AnalyticsEvent::Feedback {
+            query_id: query_id.to_string(),
+            pathway,
+            useful,
+            at: self.now(),
+        })
+        .await
+    }
+
+    /// Forget the feedback behind ranking adjustments for nodes under
+    /// `pathway`, or for every node
+    pub async fn reset_feedback(&self, pathway: Option<&str>) -> Result<()> {
+        let pathway = pathway.map(Pathway::parse).transpose()?;
+        let log = self.analytics.as_ref().ok_or_else(|| {
+            A3SError::Config("analytics are disabled (set analytics.enabled)".to_string())
+        })?;
+        log.record(&analytics::AnalyticsEvent::Reset {
+            pathway,
+            at: self.now(),
+        })
+        .await
+    }
+
+    /// Summarize logged queries and feedback, listing at most `limit`
+    /// entries per section
+    pub async fn analytics_report(&self, limit: usize) -> Result<analytics::AnalyticsReport> {
+        let log = self.analytics.as_ref().ok_or_else(|| {
+            A3SError::Config("analytics are disabled (set analytics.enabled)".to_string())
+        })?;
+        log.report(limit).await
+    }
+
+    /// Run a query under two named retrieval profiles at once and measure
+    /// how their results overlap
+    ///
+    /// `default` names the `retrieval` section; other names come from
+    /// `retrieval_profiles`. Comparison queries are not logged to analytics.
+    pub async fn query_compare(
+        &self,
+        query: &str,
+        profile_a: &str,
+        profile_b: &str,
+    ) -> Result<compare::QueryComparison> {
+        let a = self.retriever_for(self.retrieval_profile(profile_a)?);
+        let b = self.retriever_for(self.retrieval_profile(profile_b)?);
+        let _slot = self
+            .state
+            .scheduler
+            .acquire(schedule::Priority::Interactive)
+            .await;
+        let (result_a, result_b) = tokio::try_join!(a.search(query, None), b.search(query, None))?;
+
+        Ok(compare::QueryComparison {
+            query: query.to_string(),
+            overlap: compare::Overlap::between(&result_a.matches, &result_b.matches),
+            a: compare::ProfileResult {
+                profile: profile_a.to_string(),
+                result: result_a,
+            },
+            b: compare::ProfileResult {
+                profile: profile_b.to_string(),
+                result: result_b,
+            },
+        })
+    }
+
+    fn retrieval_profile(&self, name: &str) -> Result<&config::RetrievalConfig> {
+        if name == compare::DEFAULT_PROFILE {
+            return Ok(&self.config.retrieval);
+        }
+        self.config
+            .retrieval_profiles
+            .get(name)
+            .ok_or_else(|| A3SError::Config(format!("Unknown retrieval profile: {}", name)))
+    }
+
+    /// Retrieve matches and assemble them into cited context within a token budget
+    pub async fn assemble_context(
+        &self,
+        query: &str,
+        mut options: QueryOptions,
+        max_tokens: usize,
+    ) -> Result<assembly::AssembledContext> {
+        options.include_content = true;
+        let result = self.query_with_options(query, options).await?;
+        Ok(assembly::assemble(
+            &result.matches,
+            max_tokens,
+            &*self.tokenizer(),
+        ))
+    }
+
+    /// Retrieve matches and fill a token budget with them, reading each at
+    /// the digest level its share of the budget allows
+    pub async fn build_context(
+        &self,
+        query: &str,
+        options: QueryOptions,
+        max_tokens: usize,
+    ) -> Result<window::ContextWindow> {
+        let result = self.query_with_options(query, options).await?;
+        window::ContextBuilder::new(self.storage.clone(), self.tokenizer(), max_tokens)
+            .build(&result.matches)
+            .await
+    }
+
+    /// Answer a question from retrieved context using the configured LLM
+    #[cfg(feature = "llm-digest")]
+    pub async fn ask(&self, question: &str, options: answer::AskOptions) -> Result<answer::Answer> {
+        let llm = self
+            .llm_client()
+            .ok_or_else(|| A3SError::Config("ask requires llm.api_base".to_string()))?;
+
+        let mut query = options.query;
+        query.include_content = true;
+        let result = self.query_with_options(question, query).await?;
+
+        let context = assembly::assemble(
+            &result.matches,
+            options.max_context_tokens,
+            &*self.tokenizer(),
         );
+        let text = llm
+            .complete(&answer::build_prompt(question, &context))
+            .await?;
 
-        retriever.search(query, Some(options)).await
+        Ok(answer::Answer {
+            citations: answer::cited(&text, context.citations),
+            answer: text,
+            query_id: result.query_id,
+        })
     }
 
     /// List nodes at a pathway
     pub async fn list<P: AsRef<str>>(&self, pathway: P) -> Result<Vec<NodeInfo>> {
+        self.list_with_options(pathway, &listing::ListOptions::default())
+            .await
+    }
+
+    /// List nodes under a pathway, recursively and filtered, sorted, and
+    /// paged as the options ask
+    pub async fn list_with_options<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        options: &listing::ListOptions,
+    ) -> Result<Vec<NodeInfo>> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        self.refresh_stale_view(&pathway).await?;
+        listing::list(self.storage.as_ref(), &pathway, options).await
+    }
+
+    /// Search node contents under a pathway line by line
+    pub async fn grep<P: AsRef<str>>(
+        &self,
+        pattern: &str,
+        pathway: P,
+        options: grep::GrepOptions,
+    ) -> Result<Vec<grep::GrepMatch>> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        grep::grep(self.storage.as_ref(), pattern, &pathway, &options).await
+    }
+
+    /// Get all nodes below a pathway, up to `max_depth` levels deep
+    pub async fn children<P: AsRef<str>>(&self, pathway: P, max_depth: usize) -> Result<Vec<Node>> {
         let pathway = Pathway::parse(pathway.as_ref())?;
-        self.storage.list(&pathway).await
+        self.storage.get_children(&pathway, max_depth).await
     }
 
     /// Read a node's content
     pub async fn read<P: AsRef<str>>(&self, pathway: P) -> Result<Node> {
         let pathway = Pathway::parse(pathway.as_ref())?;
+        if let Some(parent) = pathway.parent() {
+            self.refresh_stale_view(&parent).await?;
+        }
         self.storage.get(&pathway).await
     }
 
+    /// Read a node's content rendered for display according to its content type
+    pub async fn read_rendered<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        format: render::RenderFormat,
+    ) -> Result<String> {
+        let node = self.read(pathway).await?;
+        Ok(render::render(&node, format))
+    }
+
+    /// Read the node a chunk was split from
+    pub async fn parent_of<P: AsRef<str>>(&self, chunk: P) -> Result<Node> {
+        let info = self.chunk_info(chunk.as_ref()).await?;
+        self.storage.get(&info.parent).await
+    }
+
+    /// Read a node's chunks in order; empty when it was not chunked
+    pub async fn chunks_of<P: AsRef<str>>(&self, document: P) -> Result<Vec<Node>> {
+        let pathway = Pathway::parse(document.as_ref())?;
+        let node = self.storage.get(&pathway).await?;
+        self.read_chunks(&pathway, 0..chunk::chunk_count(&node))
+            .await
+    }
+
+    /// Read a chunk and up to `window` chunks either side of it, in order
+    pub async fn neighbors<P: AsRef<str>>(&self, chunk: P, window: usize) -> Result<Vec<Node>> {
+        let info = self.chunk_info(chunk.as_ref()).await?;
+        let parent = self.storage.get(&info.parent).await?;
+        let end = (info.index + window + 1).min(chunk::chunk_count(&parent));
+        self.read_chunks(&info.parent, info.index.saturating_sub(window)..end)
+            .await
+    }
+
+    /// Re-derive the chunks of every node at or below a pathway from stored
+    /// content, splitting into `chunk_size` characters overlapping by `overlap`
+    pub async fn rechunk<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        chunk_size: usize,
+        overlap: usize,
+    ) -> Result<chunk::RechunkResult> {
+        if overlap >= chunk_size {
+            return Err(A3SError::Config(
+                "chunk overlap must be smaller than the chunk size".to_string(),
+            ));
+        }
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        self.ensure_writable(&pathway, true).await?;
+
+        let mut nodes = match self.storage.get(&pathway).await {
+            Ok(node) => vec![node],
+            Err(A3SError::NodeNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        nodes.extend(self.storage.get_children(&pathway, usize::MAX).await?);
+
+        let _slot = self.background_slot().await;
+        let processor = self.processor();
+        let mut result = chunk::RechunkResult::default();
+        for node in nodes {
+            if node.is_directory || chunk::ChunkInfo::of(&node).is_some() {
+                continue;
+            }
+            processor
+                .rechunk(&node, chunk_size, overlap, &mut result)
+                .await?;
+        }
+        Ok(result)
+    }
+
+    async fn chunk_info(&self, chunk: &str) -> Result<chunk::ChunkInfo> {
+        let pathway = Pathway::parse(chunk)?;
+        let node = self.storage.get(&pathway).await?;
+        chunk::ChunkInfo::of(&node)
+            .ok_or_else(|| A3SError::InvalidPathway(format!("{} is not a chunk", pathway)))
+    }
+
+    async fn read_chunks(
+        &self,
+        parent: &Pathway,
+        indices: std::ops::Range<usize>,
+    ) -> Result<Vec<Node>> {
+        let mut chunks = Vec::with_capacity(indices.len());
+        for index in indices {
+            chunks.push(
+                self.storage
+                    .get(&chunk::chunk_pathway(parent, index))
+                    .await?,
+            );
+        }
+        Ok(chunks)
+    }
+
     /// Read a node's brief digest (smallest summary)
     pub async fn brief<P: AsRef<str>>(&self, pathway: P) -> Result<String> {
         let pathway = Pathway::parse(pathway.as_ref())?;
@@ -163,12 +1016,521 @@ impl A3SClient {
         Ok(node.digest.summary)
     }
 
+    /// Read a node within `max_tokens`: its content if it fits, otherwise
+    /// its summary, an LLM compression of the sections most relevant to
+    /// `focus`, its brief, or an excerpt of those sections
+    ///
+    /// A summary is preferred to a compression only without a focus, since
+    /// it was not written with the focus in mind.
+    pub async fn read_within<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        max_tokens: usize,
+        focus: Option<&str>,
+    ) -> Result<condense::BoundedRead> {
+        let node = self.read(pathway).await?;
+        let tokenizer = self.tokenizer();
+        let fits = |text: &str| !text.is_empty() && tokenizer.count(text) <= max_tokens;
+        let bounded = |source, text: String| condense::BoundedRead {
+            pathway: node.pathway.clone(),
+            source,
+            tokens: tokenizer.count(&text),
+            text,
+        };
+
+        if tokenizer.count(&node.content) <= max_tokens {
+            return Ok(bounded(condense::ReadSource::Content, node.content.clone()));
+        }
+        if focus.is_none() && fits(&node.digest.summary) {
+            return Ok(bounded(
+                condense::ReadSource::Summary,
+                node.digest.summary.clone(),
+            ));
+        }
+
+        let sections = condense::sections(&node.content, tokenizer.as_ref());
+        let scores = match focus {
+            Some(focus) => Some(self.section_scores(focus, &sections).await?),
+            None => None,
+        };
+
+        #[cfg(feature = "llm-digest")]
+        if let Some(llm) = self.llm_client() {
+            let source = condense::join(&condense::select(
+                &sections,
+                scores.as_deref(),
+                condense::MAX_COMPRESSION_INPUT_TOKENS,
+                tokenizer.as_ref(),
+            ));
+            let prompt = condense::compression_prompt(&source, max_tokens, focus);
+            match llm.complete(&prompt).await {
+                Ok(text) => {
+                    let text = tokenizer.truncate(text.trim(), max_tokens).to_string();
+                    return Ok(bounded(condense::ReadSource::Compressed, text));
+                }
+                Err(e) => tracing::warn!("Compressing {} failed: {}", node.pathway, e),
+            }
+        }
+
+        if fits(&node.digest.summary) {
+            return Ok(bounded(
+                condense::ReadSource::Summary,
+                node.digest.summary.clone(),
+            ));
+        }
+        if fits(&node.digest.brief) {
+            return Ok(bounded(
+                condense::ReadSource::Brief,
+                node.digest.brief.clone(),
+            ));
+        }
+        let excerpt = condense::join(&condense::select(
+            &sections,
+            scores.as_deref(),
+            max_tokens,
+            tokenizer.as_ref(),
+        ));
+        let text = tokenizer.truncate(&excerpt, max_tokens).to_string();
+        Ok(bounded(condense::ReadSource::Excerpt, text))
+    }
+
+    /// Passages of a node most relevant to `question`, within `max_tokens`
+    ///
+    /// A chunked node is searched by its stored chunk embeddings; other
+    /// content is split into sections that are embedded for the search.
+    pub async fn extract<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        question: &str,
+        max_tokens: usize,
+    ) -> Result<condense::Extraction> {
+        let node = self.read(pathway).await?;
+        let tokenizer = self.tokenizer();
+
+        let count = chunk::chunk_count(&node);
+        let (sections, scores) = if count == 0 {
+            let sections = condense::sections(&node.content, tokenizer.as_ref());
+            let scores = self.section_scores(question, &sections).await?;
+            (sections, scores)
+        } else {
+            let query = self.embedder.embed(question).await?;
+            let mut sections = Vec::with_capacity(count);
+            let mut scores = Vec::with_capacity(count);
+            for stored in self.read_chunks(&node.pathway, 0..count).await? {
+                let Some(info) = chunk::ChunkInfo::of(&stored) else {
+                    continue;
+                };
+                let embedding = if stored.is_embedded() {
+                    stored.embedding
+                } else {
+                    self.embedder.embed(&stored.content).await?
+                };
+                scores.push(retrieval::cosine_similarity(&query, &embedding));
+                sections.push(chunk::Chunk {
+                    index: sections.len(),
+                    start: info.start,
+                    end: info.end,
+                    text: stored.content,
+                    symbols: Vec::new(),
+                });
+            }
+            (sections, scores)
+        };
+
+        let passages: Vec<condense::Passage> =
+            condense::select(&sections, Some(&scores), max_tokens, tokenizer.as_ref())
+                .into_iter()
+                .map(|section| condense::Passage {
+                    start: section.start,
+                    end: section.end,
+                    text: section.text.clone(),
+                    score: scores[section.index],
+                })
+                .collect();
+        Ok(condense::Extraction {
+            pathway: node.pathway,
+            tokens: passages.iter().map(|p| tokenizer.count(&p.text)).sum(),
+            passages,
+        })
+    }
+
+    /// Similarity of each section to a focus query
+    async fn section_scores(&self, focus: &str, sections: &[chunk::Chunk]) -> Result<Vec<f32>> {
+        let query = self.embedder.embed(focus).await?;
+        let texts: Vec<String> = sections.iter().map(|s| s.text.clone()).collect();
+        Ok(self
+            .embedder
+            .embed_batch(&texts)
+            .await?
+            .iter()
+            .map(|embedding| retrieval::cosine_similarity(&query, embedding))
+            .collect())
+    }
+
+    /// Create the node at `pathway`, or replace its content, regenerating
+    /// its digest, chunks, and embedding; returns whether the node is new
+    pub async fn write<P: AsRef<str>>(&self, pathway: P, content: &str) -> Result<bool> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        self.ensure_writable(&pathway, false).await?;
+        self.processor()
+            .process_content(&pathway, content.to_string())
+            .await
+    }
+
+    /// Apply metadata operations to one node, returning whether anything
+    /// changed
+    ///
+    /// A changed node passes the write policies again and its `updated_at`
+    /// is bumped; its content, digest, and embedding are kept.
+    pub async fn update_metadata<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        ops: &[bulk::MetadataOp],
+    ) -> Result<bool> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        self.ensure_writable(&pathway, false).await?;
+        let mut node = self.storage.get(&pathway).await?;
+        if !bulk::apply_all(ops, &mut node.metadata) {
+            return Ok(false);
+        }
+        node.updated_at = self.now();
+
+        let node = self.policies.apply(node).await?;
+        self.storage.put(&node).await?;
+        Ok(true)
+    }
+
+    /// Add tags to a node's metadata, skipping tags it already has
+    pub async fn add_tags<P: AsRef<str>>(&self, pathway: P, tags: &[String]) -> Result<()> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        self.ensure_writable(&pathway, false).await?;
+        let mut node = self.storage.get(&pathway).await?;
+
+        let mut added = false;
+        for tag in tags {
+            if !node.metadata.tags.contains(tag) {
+                node.metadata.tags.push(tag.clone());
+                added = true;
+            }
+        }
+        if added {
+            node.updated_at = self.now();
+        }
+
+        let node = self.policies.apply(node).await?;
+        self.storage.put(&node).await
+    }
+
+    /// Apply metadata operations to every node a filter selects
+    ///
+    /// Runs inside the storage backend unless write policies or metadata
+    /// schemas are registered; then each changed node passes them first,
+    /// and none is written if any fails. Changed nodes are marked updated
+    /// by the client clock. Fails if a selected subtree contains an
+    /// installed pack. Returns the number of nodes changed.
+    pub async fn bulk_update(
+        &self,
+        filter: &bulk::NodeFilter,
+        ops: &[bulk::MetadataOp],
+    ) -> Result<usize> {
+        for root in filter.roots() {
+            self.ensure_writable(&root, true).await?;
+        }
+        if self.policies.is_empty() && self.policies.schemas().is_empty() {
+            return self.storage.update_metadata(filter, ops, self.now()).await;
+        }
+
+        let now = self.now();
+        let mut changed = Vec::new();
+        for root in filter.roots() {
+            let mut nodes = self.storage.get_children(&root, usize::MAX).await?;
+            if let Ok(node) = self.storage.get(&root).await {
+                nodes.push(node);
+            }
+            for mut node in nodes {
+                if filter.matches(&node) && bulk::apply_all(ops, &mut node.metadata) {
+                    node.updated_at = now;
+                    changed.push(self.policies.apply(node).await?);
+                }
+            }
+        }
+        self.storage.put_batch(&changed).await?;
+        Ok(changed.len())
+    }
+
+    /// Take exclusive write access to a subtree for `ttl`
+    ///
+    /// Until the lease expires or is released, writes to the subtree fail
+    /// with `A3SError::Leased` unless run inside [`A3SClient::with_lease`].
+    pub fn lease<P: AsRef<str>>(&self, pathway: P, ttl: Duration) -> Result<lease::Lease> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        self.state.leases.acquire(pathway, ttl)
+    }
+
+    /// Extend a live lease to `ttl` from now
+    pub fn renew_lease(&self, lease: &lease::Lease, ttl: Duration) -> Result<lease::Lease> {
+        self.state.leases.renew(lease, ttl)
+    }
+
+    /// Release a lease, returning whether it was still held
+    pub fn release_lease(&self, lease: &lease::Lease) -> bool {
+        self.state.leases.release(lease)
+    }
+
+    /// Run `f` with writes permitted under `lease`
+    pub async fn with_lease<F: std::future::Future>(
+        &self,
+        lease: &lease::Lease,
+        f: F,
+    ) -> F::Output {
+        lease::scope(lease, f).await
+    }
+
     /// Remove a node or directory
     pub async fn remove<P: AsRef<str>>(&self, pathway: P, recursive: bool) -> Result<()> {
         let pathway = Pathway::parse(pathway.as_ref())?;
+        self.ensure_writable(&pathway, recursive).await?;
         self.storage.remove(&pathway, recursive).await
     }
 
+    /// New memory node holding a fact, under a fresh pathway for the user
+    fn memory_node(&self, fact: &str, user: &str, tags: &[String]) -> Result<Node> {
+        let user_root = Pathway::memory(user)?;
+        if user_root.depth() != 1 {
+            return Err(A3SError::InvalidPathway(format!(
+                "Invalid memory user: {}",
+                user
+            )));
+        }
+
+        let pathway = user_root.join(&uuid::Uuid::new_v4().to_string());
+        let mut node = Node::new(pathway, NodeKind::Memory, fact.to_string());
+        node.created_at = self.now();
+        node.updated_at = node.created_at;
+        node.metadata.tags = tags.to_vec();
+        // Facts are short enough to serve as their own digest
+        node.digest = digest::Digest::with_content(fact.to_string(), fact.to_string());
+        Ok(node)
+    }
+
+    /// Start a batch of writes committed together, with one embedding call
+    /// and one index pass
+    pub fn batch(&self) -> batch::WriteBatch<'_> {
+        batch::WriteBatch::new(self)
+    }
+
+    /// Move a node and everything below it to another pathway, redirecting
+    /// relations that pointed into the moved subtree
+    pub async fn move_node<F: AsRef<str>, T: AsRef<str>>(
+        &self,
+        from: F,
+        to: T,
+    ) -> Result<relocate::RelocateResult> {
+        let from = Pathway::parse(from.as_ref())?;
+        let to = Pathway::parse(to.as_ref())?;
+        self.ensure_writable(&from, true).await?;
+        self.ensure_writable(&to, true).await?;
+        relocate::move_node(self.storage.as_ref(), &self.policies, &from, &to).await
+    }
+
+    /// Copy a node, and everything below it if `recursive`, to another
+    /// pathway
+    pub async fn copy_node<F: AsRef<str>, T: AsRef<str>>(
+        &self,
+        from: F,
+        to: T,
+        recursive: bool,
+    ) -> Result<relocate::RelocateResult> {
+        let from = Pathway::parse(from.as_ref())?;
+        let to = Pathway::parse(to.as_ref())?;
+        self.ensure_writable(&to, true).await?;
+        relocate::copy_node(
+            self.storage.as_ref(),
+            &self.policies,
+            &from,
+            &to,
+            recursive,
+            self.now(),
+        )
+        .await
+    }
+
+    /// Relate every node at and below `pathway` to its most similar nodes
+    /// by embedding, recording the relations unless `dry_run`
+    pub async fn relate_similar<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        config: &config::RelateConfig,
+        dry_run: bool,
+    ) -> Result<relate::RelateResult> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        if !dry_run {
+            self.ensure_writable(&pathway, true).await?;
+        }
+        let _slot = self.background_slot().await;
+        relate::relate_subtree(
+            self.storage.as_ref(),
+            &self.policies,
+            &pathway,
+            config,
+            dry_run,
+        )
+        .await
+    }
+
+    /// Cluster the nodes of `namespace` by embedding into at most `k`
+    /// topics, each labelled by the LLM when one is configured and by its
+    /// most central brief otherwise
+    pub async fn cluster(&self, namespace: Namespace, k: usize) -> Result<cluster::TopicMap> {
+        let _slot = self.background_slot().await;
+        let map = cluster::cluster(self.storage.as_ref(), namespace, k).await?;
+
+        #[cfg(feature = "llm-digest")]
+        let map = {
+            let mut map = map;
+            if let Some(llm) = self.llm_client() {
+                for topic in &mut map.topics {
+                    match llm.complete(&cluster::label_prompt(topic)).await {
+                        Ok(label) if !label.trim().is_empty() => {
+                            topic.label = label.trim().trim_matches('"').to_string();
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            tracing::warn!("LLM topic label failed, using brief: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+            map
+        };
+        Ok(map)
+    }
+
+    /// Store a fact in a user's memory, returning the new node's pathway
+    pub async fn remember(&self, fact: &str, user: &str, tags: &[String]) -> Result<Pathway> {
+        let node = self.memory_node(fact, user, tags)?;
+        let pathway = node.pathway.clone();
+        self.state.leases.check(&pathway, false)?;
+
+        let mut node = self.policies.apply(node).await?;
+        self.record_embedding_model_once().await?;
+        node.embedding = self.embedder.embed(&node.content).await?;
+        self.storage.put(&node).await?;
+
+        Ok(pathway)
+    }
+
+    /// Search a user's memories
+    pub async fn recall(&self, query: &str, user: &str, limit: usize) -> Result<QueryResult> {
+        let user_root = Pathway::memory(user)?;
+        self.query_with_options(
+            query,
+            QueryOptions {
+                namespace: Some(Namespace::Memory),
+                limit: Some(limit),
+                pathway_filter: Some(format!("{}/", user_root)),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Store a capability definition at `pathway` with its version and
+    /// compatibility, replacing any definition stored there
+    pub async fn register_capability<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        definition: &str,
+        spec: capability::CapabilitySpec,
+    ) -> Result<Pathway> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        if pathway.namespace() != Namespace::Capability {
+            return Err(A3SError::InvalidPathway(format!(
+                "Capabilities must be stored under a3s://capability: {}",
+                pathway
+            )));
+        }
+        self.ensure_writable(&pathway, false).await?;
+
+        let now = self.now();
+        let mut node = match self.storage.get(&pathway).await {
+            Ok(mut existing) => {
+                existing.update_content(definition.to_string());
+                existing
+            }
+            Err(A3SError::NodeNotFound(_)) => {
+                let mut node = Node::new(
+                    pathway.clone(),
+                    NodeKind::Capability,
+                    definition.to_string(),
+                );
+                node.created_at = now;
+                node
+            }
+            Err(e) => return Err(e),
+        };
+        node.updated_at = now;
+        node.metadata.custom.insert(
+            capability::CAPABILITY_KEY.to_string(),
+            serde_json::to_value(&spec)?,
+        );
+        node.digest = digest::DigestGenerator::simple()
+            .with_tokenizer(self.tokenizer())
+            .generate(&node.content, node.kind)
+            .await?;
+
+        let mut node = self.policies.apply(node).await?;
+        self.record_embedding_model_once().await?;
+        node.embedding = self.embedder.embed(&node.content).await?;
+        self.storage.put(&node).await?;
+
+        Ok(pathway)
+    }
+
+    /// Mark a capability as deprecated, optionally naming its replacement
+    pub async fn deprecate_capability<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        replaced_by: Option<&str>,
+    ) -> Result<()> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        let replaced_by = replaced_by.map(Pathway::parse).transpose()?;
+        self.ensure_writable(&pathway, false).await?;
+        let mut node = self.storage.get(&pathway).await?;
+        let mut spec = capability::CapabilitySpec::of(&node).ok_or_else(|| {
+            A3SError::InvalidPathway(format!("Not a versioned capability: {}", pathway))
+        })?;
+
+        spec.deprecated = true;
+        spec.replaced_by = replaced_by;
+        node.metadata.custom.insert(
+            capability::CAPABILITY_KEY.to_string(),
+            serde_json::to_value(&spec)?,
+        );
+        let node = self.policies.apply(node).await?;
+        self.storage.put(&node).await
+    }
+
+    /// Search capabilities, returning only definitions that pass `filter`
+    pub async fn find_capabilities(
+        &self,
+        query: &str,
+        filter: capability::CapabilityFilter,
+        limit: usize,
+    ) -> Result<QueryResult> {
+        self.query_with_options(
+            query,
+            QueryOptions {
+                namespace: Some(Namespace::Capability),
+                limit: Some(limit),
+                capability: Some(filter),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
     /// Create a new session for conversation tracking
     pub async fn session(&self, id: Option<&str>) -> Result<session::Session> {
         let session = session::Session::new(
@@ -176,15 +1538,694 @@ impl A3SClient {
             self.storage.clone(),
             self.embedder.clone(),
             &self.config,
+            &self.http,
         )
         .await?;
+        Ok(self.register_session(session))
+    }
 
-        let state = self.state.read().await;
-        state
+    /// Reload a committed session and its messages, e.g. after a restart
+    pub async fn resume_session(&self, id: &str) -> Result<session::Session> {
+        let session = session::Session::resume(
+            id,
+            self.storage.clone(),
+            self.embedder.clone(),
+            &self.config,
+            &self.http,
+        )
+        .await?;
+        Ok(self.register_session(session))
+    }
+
+    /// Give a session the client's retriever, policies, leases, clock, and
+    /// LLM, and mark it active
+    fn register_session(&self, session: session::Session) -> session::Session {
+        let session = session
+            .with_retriever(self.retriever())
+            .with_tokenizer(self.tokenizer())
+            .with_policies(self.policies.clone())
+            .with_leases(self.state.leases.clone())
+            .with_clock(self.clock.clone());
+
+        #[cfg(feature = "llm-digest")]
+        let session = match self.llm_client() {
+            Some(llm) if self.config.session.llm_rewrite => session.with_llm(llm),
+            _ => session,
+        };
+
+        self.evict_idle_sessions();
+        self.state
+            .active_sessions
+            .insert(session.id().to_string(), self.now());
+
+        session
+    }
+
+    /// Mark a session as active, postponing its idle eviction
+    ///
+    /// Returns false if the session is not registered.
+    pub fn touch_session(&self, id: &str) -> bool {
+        match self.state.active_sessions.get_mut(id) {
+            Some(mut last_active) => {
+                *last_active = self.now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Unregister a session, returning whether it was registered
+    pub fn close_session(&self, id: &str) -> bool {
+        self.state.active_sessions.remove(id).is_some()
+    }
+
+    /// Unregister sessions idle for longer than `session.idle_timeout_secs`
+    ///
+    /// Returns the number of sessions evicted.
+    pub fn evict_idle_sessions(&self) -> usize {
+        let timeout = Duration::from_secs(self.config.session.idle_timeout_secs);
+        let now = self.now();
+        let before = self.state.active_sessions.len();
+        self.state
             .active_sessions
-            .insert(session.id().to_string(), session.clone());
+            .retain(|_, last_active| (now - *last_active).to_std().unwrap_or_default() < timeout);
+        before.saturating_sub(self.state.active_sessions.len())
+    }
+
+    /// Number of currently registered sessions
+    pub fn active_session_count(&self) -> usize {
+        self.state.active_sessions.len()
+    }
+
+    /// Every committed session, most recently active first
+    pub async fn sessions(&self) -> Result<Vec<session::StoredSession>> {
+        session::stored_sessions(self.storage.as_ref()).await
+    }
+
+    /// A committed session's messages and the retrievals recorded under it
+    pub async fn export_session(&self, id: &str) -> Result<session::SessionExport> {
+        let info = session::load_info(self.storage.as_ref(), id).await?;
+        Ok(session::SessionExport {
+            id: id.to_string(),
+            user: info.user,
+            created_at: info.created_at,
+            messages: session::load_messages(self.storage.as_ref(), id).await?,
+            retrievals: self.retrieval_snapshots(id).await?,
+        })
+    }
+
+    /// Delete a session's messages and retrieval snapshots, and unregister
+    /// it
+    pub async fn delete_session(&self, id: &str) -> Result<()> {
+        let root = snapshot::session_root(id)?;
+        self.storage.warm(&root).await?;
+        let stored = self.storage.get(&root).await.is_ok()
+            || !self.storage.get_children(&root, 1).await?.is_empty();
+        if !stored {
+            return Err(A3SError::Session(format!("No session stored: {}", id)));
+        }
+        self.ensure_writable(&root, true).await?;
+        self.storage.remove(&root, true).await?;
+        self.close_session(id);
+        Ok(())
+    }
+
+    /// Fold all but the last `keep` messages of a committed session into a
+    /// summary, returning how many were folded
+    pub async fn compact_session(&self, id: &str, keep: usize) -> Result<usize> {
+        let mut session = self.resume_session(id).await?;
+        session.compact(keep).await
+    }
+
+    /// Get recursive usage of a subtree, with the `top_n` largest nodes
+    pub async fn usage<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        top_n: usize,
+    ) -> Result<usage::UsageReport> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        usage::usage(self.storage.as_ref(), &pathway, top_n).await
+    }
+
+    /// Get the subtree under a pathway, `depth` levels deep, with each
+    /// level's brief and number of nodes below it
+    pub async fn tree<P: AsRef<str>>(&self, pathway: P, depth: usize) -> Result<tree::TreeNode> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        tree::tree(self.storage.as_ref(), &pathway, depth).await
+    }
+
+    /// List nodes under a pathway that have no embedding
+    pub async fn unembedded<P: AsRef<str>>(&self, pathway: P) -> Result<Vec<NodeInfo>> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        usage::unembedded(self.storage.as_ref(), &pathway).await
+    }
+
+    /// List nodes under a pathway whose digest was never generated
+    pub async fn undigested<P: AsRef<str>>(&self, pathway: P) -> Result<Vec<NodeInfo>> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        usage::undigested(self.storage.as_ref(), &pathway).await
+    }
 
-        Ok(session)
+    /// Save a query under `name`, replacing any query saved with that name
+    ///
+    /// `query` and `options.pathway_filter` may use `{{variable}}` templates.
+    pub async fn save_query(
+        &self,
+        name: &str,
+        query: &str,
+        options: &QueryOptions,
+    ) -> Result<saved::SavedQuery> {
+        let pathway = saved::pathway(name)?;
+        self.ensure_writable(&pathway, false).await?;
+        let query = saved::SavedQuery::new(query, options);
+        let node = self
+            .policies
+            .apply(saved::to_node(pathway, &query)?)
+            .await?;
+        self.storage.put(&node).await?;
+        Ok(query)
+    }
+
+    /// Run a saved query with its template variables filled in from `params`
+    pub async fn run_saved(
+        &self,
+        name: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<QueryResult> {
+        let pathway = saved::pathway(name)?;
+        let node = self.storage.get(&pathway).await?;
+        let query = saved::from_node(&node)
+            .ok_or_else(|| A3SError::InvalidPathway(format!("{} is not a saved query", name)))?;
+        let (text, options) = query.render(params)?;
+        self.query_with_options(&text, options).await
+    }
+
+    /// List saved queries by name
+    pub async fn saved_queries(&self) -> Result<Vec<(String, saved::SavedQuery)>> {
+        let root = Pathway::parse(saved::SAVED_QUERIES_ROOT)?;
+        let mut queries: Vec<(String, saved::SavedQuery)> = self
+            .storage
+            .get_children(&root, 1)
+            .await?
+            .iter()
+            .filter_map(|node| Some((node.pathway.name()?.to_string(), saved::from_node(node)?)))
+            .collect();
+        queries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(queries)
+    }
+
+    /// Delete a saved query
+    pub async fn delete_saved_query(&self, name: &str) -> Result<()> {
+        let pathway = saved::pathway(name)?;
+        self.ensure_writable(&pathway, false).await?;
+        self.storage.remove(&pathway, false).await
+    }
+
+    /// Define a view at `at` whose children are the results of a saved query
+    ///
+    /// The view is materialized on first access.
+    pub async fn create_view<P: AsRef<str>>(
+        &self,
+        at: P,
+        definition: view::ViewDefinition,
+    ) -> Result<()> {
+        let at = Pathway::parse(at.as_ref())?;
+        if self.storage.exists(&at).await? {
+            return Err(A3SError::AlreadyExists(at.to_string()));
+        }
+        self.ensure_writable(&at, true).await?;
+        let node = self
+            .policies
+            .apply(view::view_node(at, &definition))
+            .await?;
+        self.storage.put(&node).await
+    }
+
+    /// Rebuild a view from its query, returning the number of children
+    pub async fn refresh_view<P: AsRef<str>>(&self, at: P) -> Result<usize> {
+        let at = Pathway::parse(at.as_ref())?;
+        let node = self.storage.get(&at).await?;
+        self.materialize_view(node, schedule::Priority::Interactive)
+            .await
+    }
+
+    /// Rebuild every view at background priority, returning how many were
+    /// refreshed
+    pub async fn refresh_views(&self) -> Result<usize> {
+        let mut refreshed = 0;
+        for namespace in Namespace::ALL {
+            for node in self
+                .storage
+                .get_children(&Pathway::root(namespace), usize::MAX)
+                .await?
+            {
+                if view::definition(&node).is_some() {
+                    self.materialize_view(node, schedule::Priority::Background)
+                        .await?;
+                    refreshed += 1;
+                }
+            }
+        }
+        Ok(refreshed)
+    }
+
+    /// Materialize the view at `pathway`, if it is one and is out of date
+    async fn refresh_stale_view(&self, pathway: &Pathway) -> Result<()> {
+        let Ok(node) = self.storage.get(pathway).await else {
+            return Ok(());
+        };
+        match view::definition(&node) {
+            Some(definition) if view::needs_refresh(&node, &definition, self.now()) => self
+                .materialize_view(node, schedule::Priority::Interactive)
+                .await
+                .map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn materialize_view(
+        &self,
+        mut node: Node,
+        priority: schedule::Priority,
+    ) -> Result<usize> {
+        let definition = view::definition(&node)
+            .ok_or_else(|| A3SError::InvalidPathway(format!("{} is not a view", node.pathway)))?;
+        self.ensure_writable(&node.pathway, true).await?;
+
+        let options = QueryOptions {
+            priority,
+            ..definition.query_options()
+        };
+        let result = self.query_with_options(&definition.query, options).await?;
+        view::mark_materialized(&mut node, self.now());
+        let node = self.policies.apply(node).await?;
+        let mut children = Vec::new();
+        for child in view::materialize(&node.pathway, &result.matches) {
+            children.push(self.policies.apply(child).await?);
+        }
+
+        // Every node passed the policies before the old children go
+        self.storage.remove(&node.pathway, true).await?;
+        self.storage.put(&node).await?;
+        self.storage.put_batch(&children).await?;
+        Ok(children.len())
+    }
+
+    /// Find groups of near-duplicate nodes in a namespace
+    pub async fn find_duplicates(
+        &self,
+        namespace: Namespace,
+        similarity_threshold: f32,
+    ) -> Result<dedup::DuplicateReport> {
+        dedup::find_duplicates(self.storage.as_ref(), namespace, similarity_threshold).await
+    }
+
+    /// Flag nodes at and below `pathway` whose embeddings are far from
+    /// every cluster or whose content fails sanity checks, for review
+    pub async fn find_anomalies<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        options: &anomaly::AnomalyOptions,
+    ) -> Result<anomaly::AnomalyReport> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        let _slot = self.background_slot().await;
+        anomaly::scan(self.storage.as_ref(), &pathway, options).await
+    }
+
+    /// Merge a duplicate group into its canonical node, returning the number removed
+    pub async fn merge_duplicates(&self, group: &dedup::DuplicateGroup) -> Result<usize> {
+        self.ensure_writable(&group.canonical, false).await?;
+        for pathway in &group.duplicates {
+            self.ensure_writable(pathway, false).await?;
+        }
+        dedup::merge(
+            self.storage.as_ref(),
+            &self.policies,
+            &self.state.leases,
+            group,
+        )
+        .await
+    }
+
+    /// Diff the content of two nodes line by line, with the similarity of
+    /// their embeddings; content not yet embedded is embedded for the
+    /// comparison
+    pub async fn diff_content<P: AsRef<str>, Q: AsRef<str>>(
+        &self,
+        from: P,
+        to: Q,
+    ) -> Result<diff::ContentDiff> {
+        let from = self.storage.get(&Pathway::parse(from.as_ref())?).await?;
+        let to = self.storage.get(&Pathway::parse(to.as_ref())?).await?;
+        let similarity = retrieval::cosine_similarity(
+            &self.content_embedding(&from).await?,
+            &self.content_embedding(&to).await?,
+        );
+        Ok(diff::diff(&from, &to, similarity))
+    }
+
+    /// A node's stored embedding, or one of its content if it has none
+    async fn content_embedding(&self, node: &Node) -> Result<Vec<f32>> {
+        if node.is_embedded() {
+            return Ok(node.embedding.clone());
+        }
+        self.embedder.embed(&node.content).await
+    }
+
+    /// Export a subtree as a context pack file
+    pub async fn pack<P: AsRef<str>>(
+        &self,
+        pathway: P,
+        output: &std::path::Path,
+    ) -> Result<pack::PackManifest> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        let pack =
+            pack::Pack::export(self.storage.as_ref(), &pathway, &self.config.embedding).await?;
+        pack.write_to(output)?;
+        Ok(pack.manifest)
+    }
+
+    /// Install a context pack read-only at `target`, or where it was exported from
+    pub async fn install_pack(
+        &self,
+        path: &std::path::Path,
+        target: Option<&str>,
+    ) -> Result<pack::PackManifest> {
+        let pack = pack::Pack::read_from(path)?;
+        pack.check_compatible(&self.config.embedding)?;
+
+        let target = match target {
+            Some(target) => Pathway::parse(target)?,
+            None => pack.manifest.root.clone(),
+        };
+        if self.storage.exists(&target).await?
+            || !self.storage.get_children(&target, 1).await?.is_empty()
+        {
+            return Err(A3SError::AlreadyExists(target.to_string()));
+        }
+        self.ensure_writable(&target, false).await?;
+
+        let manifest = pack.manifest.clone();
+        let mut nodes = Vec::new();
+        for node in pack.rebase(&target)? {
+            nodes.push(self.policies.apply(node).await?);
+        }
+        self.storage.put_batch(&nodes).await?;
+
+        Ok(manifest)
+    }
+
+    /// Export every node at and below a pathway as an archive, with
+    /// digests, embeddings, and metadata
+    pub async fn export<P: AsRef<str>, W: std::io::Write>(
+        &self,
+        pathway: P,
+        writer: W,
+    ) -> Result<archive::ArchiveHeader> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        archive::export(
+            self.storage.as_ref(),
+            &pathway,
+            &self.config.embedding,
+            writer,
+        )
+        .await
+    }
+
+    /// Import an archive as writable content at `target`, or where it was
+    /// exported from, replacing nodes already at its pathways
+    pub async fn import<R: std::io::BufRead>(
+        &self,
+        reader: R,
+        target: Option<&str>,
+    ) -> Result<archive::ImportResult> {
+        let (header, nodes) = archive::read(reader)?;
+        if let Some(drift) = drift::compare(header.embedding.clone(), &self.config.embedding) {
+            return Err(A3SError::EmbeddingMismatch(drift.to_string()));
+        }
+        let root = match target {
+            Some(target) => Pathway::parse(target)?,
+            None => header.root.clone(),
+        };
+        self.ensure_writable(&root, true).await?;
+
+        let mut nodes_replaced = 0;
+        let mut imported = Vec::with_capacity(nodes.len());
+        for node in archive::rebase(nodes, &header.root, &root) {
+            if self.storage.exists(&node.pathway).await? {
+                nodes_replaced += 1;
+            }
+            imported.push(self.policies.apply(node).await?);
+        }
+        self.record_embedding_model_once().await?;
+        self.storage.put_batch(&imported).await?;
+
+        Ok(archive::ImportResult {
+            nodes_created: imported.len() - nodes_replaced,
+            nodes_replaced,
+            header,
+            root,
+        })
+    }
+
+    /// Snapshot the whole store to `target`, or to `storage.backup.target`,
+    /// keeping the newest `storage.backup.keep` backups there
+    ///
+    /// Writes wait until the snapshot is taken. Mounted overlays are not
+    /// part of the store and are left out.
+    pub async fn backup(&self, target: Option<&str>) -> Result<storage::BackupManifest> {
+        let target = self.backup_target(target)?;
+        let _barrier = self.overlay.exclusive().await;
+        self.storage.settle().await?;
+        storage::backup(
+            self.overlay.base().as_ref(),
+            &target,
+            &self.config.embedding,
+            self.config.storage.backup.keep,
+        )
+        .await
+    }
+
+    /// Names of the backups at `target`, or at `storage.backup.target`,
+    /// oldest first
+    pub async fn backups(&self, target: Option<&str>) -> Result<Vec<String>> {
+        self.backup_target(target)?.list().await
+    }
+
+    /// Replace the store's contents with the backup `name`, or the newest,
+    /// at `target`, or at `storage.backup.target`
+    ///
+    /// Fails if any part of the store is leased or holds an installed pack.
+    pub async fn restore(
+        &self,
+        target: Option<&str>,
+        name: Option<&str>,
+    ) -> Result<storage::BackupManifest> {
+        let target = self.backup_target(target)?;
+        let barrier = self.overlay.exclusive().await;
+        self.storage.settle().await?;
+        for namespace in core::Namespace::ALL {
+            self.ensure_writable(&Pathway::root(namespace), true)
+                .await?;
+        }
+        let manifest = storage::restore(self.overlay.base().as_ref(), &target, name).await?;
+        drop(barrier);
+
+        // The restored store may record another embedding model, or none
+        self.state
+            .embedding_recorded
+            .store(false, Ordering::Release);
+        self.check_embedding_model().await?;
+        Ok(manifest)
+    }
+
+    fn backup_target(&self, target: Option<&str>) -> Result<storage::BackupTarget> {
+        let target = target
+            .or(self.config.storage.backup.target.as_deref())
+            .ok_or_else(|| {
+                A3SError::Config(
+                    "No backup target given and storage.backup.target is not set".to_string(),
+                )
+            })?;
+        storage::BackupTarget::parse(target, &self.http)
+    }
+
+    /// Remove the pack installed at a pathway
+    pub async fn uninstall_pack<P: AsRef<str>>(&self, pathway: P) -> Result<pack::PackManifest> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        let node = self.storage.get(&pathway).await?;
+        let manifest = pack::installed_manifest(&node)
+            .ok_or_else(|| A3SError::Pack(format!("No pack installed at {}", pathway)))?;
+        self.state.leases.check(&pathway, true)?;
+        self.storage.remove(&pathway, true).await?;
+        Ok(manifest)
+    }
+
+    /// Fail if `pathway` is leased by another writer or inside an installed
+    /// pack or, when `recursive`, contains either
+    async fn ensure_writable(&self, pathway: &Pathway, recursive: bool) -> Result<()> {
+        self.state.leases.check(pathway, recursive)?;
+
+        let mut current = Some(pathway.clone());
+        while let Some(candidate) = current {
+            if let Ok(node) = self.storage.get(&candidate).await {
+                if pack::is_pack_root(&node) {
+                    return Err(A3SError::ReadOnly(format!(
+                        "{} is inside the pack installed at {}",
+                        pathway, candidate
+                    )));
+                }
+            }
+            current = candidate.parent();
+        }
+
+        if recursive {
+            let children = self.storage.get_children(pathway, usize::MAX).await?;
+            if let Some(root) = children.iter().find(|node| pack::is_pack_root(node)) {
+                return Err(A3SError::ReadOnly(format!(
+                    "{} contains the pack installed at {}",
+                    pathway, root.pathway
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expose `root` of another store read-only at `at` (defaults to `at`)
+    pub fn mount(
+        &self,
+        at: &str,
+        store: Arc<dyn storage::StorageBackend>,
+        root: Option<&str>,
+    ) -> Result<()> {
+        let at = Pathway::parse(at)?;
+        let root = match root {
+            Some(root) => Pathway::parse(root)?,
+            None => at.clone(),
+        };
+        self.overlay.mount(at, store, root)
+    }
+
+    /// Mount a context pack read-only without copying it into the store
+    pub async fn mount_pack(
+        &self,
+        path: &std::path::Path,
+        at: Option<&str>,
+    ) -> Result<pack::PackManifest> {
+        let pack = pack::Pack::read_from(path)?;
+        pack.check_compatible(&self.config.embedding)?;
+
+        let store: Arc<dyn storage::StorageBackend> = Arc::new(storage::MemoryStorage::new(
+            &self.config.storage.vector_index,
+        ));
+        store.put_batch(&pack.nodes).await?;
+
+        let root = pack.manifest.root.clone();
+        let at = match at {
+            Some(at) => Pathway::parse(at)?,
+            None => root.clone(),
+        };
+        self.overlay.mount(at, store, root)?;
+
+        Ok(pack.manifest)
+    }
+
+    /// Mount a local store directory read-only
+    #[cfg(feature = "local-storage")]
+    pub async fn mount_store(
+        &self,
+        path: &std::path::Path,
+        at: &str,
+        root: Option<&str>,
+    ) -> Result<()> {
+        let store: Arc<dyn storage::StorageBackend> =
+            Arc::new(storage::LocalStorage::new(path, &self.config.storage.vector_index).await?);
+        store.initialize().await?;
+        self.mount(at, store, root)
+    }
+
+    /// Mount a local store directory read-only
+    #[cfg(not(feature = "local-storage"))]
+    pub async fn mount_store(
+        &self,
+        path: &std::path::Path,
+        _at: &str,
+        _root: Option<&str>,
+    ) -> Result<()> {
+        Err(A3SError::Config(format!(
+            "Mounting {} requires the `local-storage` feature",
+            path.display()
+        )))
+    }
+
+    async fn mount_configured(&self, mount: &config::MountConfig) -> Result<()> {
+        if mount.source.is_file() {
+            self.mount_pack(&mount.source, Some(&mount.at)).await?;
+            return Ok(());
+        }
+
+        self.mount_store(&mount.source, &mount.at, mount.root.as_deref())
+            .await
+    }
+
+    /// Forward reads and searches under a pathway prefix to an external
+    /// endpoint, merging its results with the local store's
+    #[cfg(feature = "http")]
+    pub async fn proxy(&self, proxy: &config::ProxyConfig) -> Result<()> {
+        let store: Arc<dyn storage::StorageBackend> = match proxy.kind {
+            config::ProxyKind::A3s => {
+                let remote = config::StorageConfig {
+                    backend: config::StorageBackend::Remote,
+                    url: Some(proxy.url.clone()),
+                    api_key: proxy.api_key.clone(),
+                    timeout_secs: proxy.timeout_secs,
+                    ..Default::default()
+                };
+                Arc::new(storage::RemoteStorage::new(&remote, self.http.clone())?)
+            }
+            config::ProxyKind::Qdrant => {
+                Arc::new(storage::QdrantStorage::new(proxy, self.http.clone())?)
+            }
+        };
+        store.initialize().await?;
+        let root = match proxy.kind {
+            config::ProxyKind::A3s => proxy.root.as_deref(),
+            config::ProxyKind::Qdrant => None,
+        };
+        self.mount(&proxy.at, store, root)
+    }
+
+    /// Forward reads and searches under a pathway prefix to an external
+    /// endpoint
+    #[cfg(not(feature = "http"))]
+    pub async fn proxy(&self, proxy: &config::ProxyConfig) -> Result<()> {
+        Err(A3SError::Config(format!(
+            "Proxying {} requires the `http` feature",
+            proxy.at
+        )))
+    }
+
+    /// Remove the overlay mounted at `at`, returning whether one existed
+    pub fn unmount(&self, at: &str) -> Result<bool> {
+        Ok(self.overlay.unmount(&Pathway::parse(at)?))
+    }
+
+    /// Currently mounted overlays
+    pub fn mounts(&self) -> Vec<storage::MountInfo> {
+        self.overlay.mounts()
+    }
+
+    /// Preload the nodes at and below a pathway, and their vectors, ahead of
+    /// an expected workload, returning how many nodes were warmed
+    ///
+    /// Call at session start on the subtree an agent will query so the
+    /// first queries don't pay for loading nodes from disk.
+    pub async fn warm<P: AsRef<str>>(&self, pathway: P) -> Result<usize> {
+        let pathway = Pathway::parse(pathway.as_ref())?;
+        let warmed = self.storage.warm(&pathway).await?;
+        tracing::debug!(pathway = %pathway, nodes = warmed, "warmed subtree");
+        Ok(warmed)
     }
 
     /// Get storage statistics
@@ -192,21 +2233,148 @@ impl A3SClient {
         self.storage.stats().await
     }
 
+    /// Wait until every write accepted so far is searchable
+    pub async fn settle(&self) -> Result<()> {
+        self.storage.settle().await
+    }
+
     /// Shutdown the client gracefully
     pub async fn shutdown(&self) -> Result<()> {
         tracing::info!("Shutting down A3S Context");
+        if let Some(task) = &self.backup_task {
+            task.abort();
+        }
         self.storage.flush().await?;
         Ok(())
     }
 }
 
+impl Drop for A3SClient {
+    fn drop(&mut self) {
+        if let Some(task) = &self.backup_task {
+            task.abort();
+        }
+    }
+}
+
 /// Result of an ingest operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct IngestResult {
     pub pathway: Pathway,
     pub nodes_created: usize,
     pub nodes_updated: usize,
+    /// Items left as stored because they had not changed
+    pub nodes_unchanged: usize,
     pub errors: Vec<String>,
+    /// What happened to each file, page, or document, in processing order
+    pub entries: Vec<IngestEntry>,
+}
+
+impl IngestResult {
+    /// Empty result for an ingest into `pathway`
+    pub fn new(pathway: Pathway) -> Self {
+        Self {
+            pathway,
+            nodes_created: 0,
+            nodes_updated: 0,
+            nodes_unchanged: 0,
+            errors: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add an item's entry, counting it and listing it in `errors` if it failed
+    pub fn record(&mut self, entry: IngestEntry) {
+        match entry.outcome {
+            IngestOutcome::Created => self.nodes_created += 1,
+            IngestOutcome::Updated => self.nodes_updated += 1,
+            IngestOutcome::Failed => self.errors.push(format!(
+                "{}: {}",
+                entry.source,
+                entry.reason.as_deref().unwrap_or_default()
+            )),
+            IngestOutcome::Unchanged => self.nodes_unchanged += 1,
+            IngestOutcome::Skipped => {}
+        }
+        self.entries.push(entry);
+    }
+
+    /// Number of items with an outcome
+    pub fn count(&self, outcome: IngestOutcome) -> usize {
+        self.entries.iter().filter(|e| e.outcome == outcome).count()
+    }
+}
+
+/// What ingest did with one item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestOutcome {
+    Created,
+    Updated,
+    /// Left as stored, e.g. a file whose content hash or a web page whose
+    /// ETag still matches
+    Unchanged,
+    /// Not ingested by design, e.g. a file over `ingest.max_file_size`
+    Skipped,
+    Failed,
+}
+
+/// One file, page, or document of an ingest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestEntry {
+    /// File path relative to the ingested directory, URL, or document path
+    pub source: String,
+    /// Node written or left in place, if the item got as far as one
+    pub pathway: Option<Pathway>,
+    pub outcome: IngestOutcome,
+    /// Why the item was left unchanged, skipped, or failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Size of the content written, or of the skipped file
+    pub bytes: u64,
+    /// Estimated tokens of the content written
+    pub tokens: usize,
+}
+
+impl IngestEntry {
+    /// Entry for content written to a new or existing node
+    pub fn written(
+        source: &str,
+        pathway: Pathway,
+        created: bool,
+        content: &str,
+        tokenizer: &dyn tokenizer::Tokenizer,
+    ) -> Self {
+        Self {
+            source: source.to_string(),
+            pathway: Some(pathway),
+            outcome: if created {
+                IngestOutcome::Created
+            } else {
+                IngestOutcome::Updated
+            },
+            reason: None,
+            bytes: content.len() as u64,
+            tokens: tokenizer.count(content),
+        }
+    }
+
+    /// Entry for an item that was not written, with why
+    pub fn not_written(
+        source: &str,
+        pathway: Option<Pathway>,
+        outcome: IngestOutcome,
+        reason: impl ToString,
+    ) -> Self {
+        Self {
+            source: source.to_string(),
+            pathway,
+            outcome,
+            reason: Some(reason.to_string()),
+            bytes: 0,
+            tokens: 0,
+        }
+    }
 }
 
 /// Options for query operations
@@ -217,19 +2385,87 @@ pub struct QueryOptions {
     pub threshold: Option<f32>,
     pub include_content: bool,
     pub pathway_filter: Option<String>,
+    /// Only match nodes carrying all of these tags
+    pub tags: Vec<String>,
+    /// Override `RetrievalConfig.rerank` for this query
+    pub rerank: Option<bool>,
+    /// Attach a score breakdown to each match
+    pub explain: bool,
+    /// Abort the query with `A3SError::Cancelled` when triggered
+    pub cancel: Option<CancellationToken>,
+    /// Overall deadline for the query, including embedding and reranking
+    pub timeout_ms: Option<u64>,
+    /// Among near-duplicate matches, keep only the most recently updated
+    pub prefer_fresh: bool,
+    /// Only match nodes in this natural language (ISO 639-3 code or English
+    /// name) or programming language
+    pub language: Option<String>,
+    /// Override `RetrievalConfig.vectors` for this query
+    pub vectors: Option<Vec<VectorName>>,
+    /// Exemplars whose embeddings are added to the query vector
+    pub positive_examples: Vec<QueryExample>,
+    /// Exemplars whose embeddings are subtracted from the query vector
+    pub negative_examples: Vec<QueryExample>,
+    /// Only match capabilities with a matching version and environment,
+    /// and skip deprecated ones unless the filter includes them
+    pub capability: Option<capability::CapabilityFilter>,
+    /// Whether the query waits behind interactive ones
+    pub priority: schedule::Priority,
+    /// Store the full result as a retrieval snapshot under this session
+    pub snapshot_session: Option<String>,
+    /// Fail the query when reranking, feedback, or loading a match fails,
+    /// instead of returning what succeeded with warnings
+    pub strict: bool,
+    /// Also match nodes that ingest screening quarantined
+    pub include_quarantined: bool,
+    /// Wait until every write accepted so far is searchable before
+    /// searching; only matters with `storage.consistency: eventual`
+    pub consistent_read: bool,
+}
+
+/// Text or stored node that steers a query toward or away from itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryExample {
+    /// Embedded with the configured embedder
+    Text(String),
+    /// Steers by the node's content embedding
+    Pathway(Pathway),
+}
+
+impl QueryExample {
+    /// A pathway if `s` parses as one, otherwise text
+    pub fn parse(s: &str) -> Self {
+        match Pathway::parse(s) {
+            Ok(pathway) if s.starts_with(Pathway::PROTOCOL) => QueryExample::Pathway(pathway),
+            _ => QueryExample::Text(s.to_string()),
+        }
+    }
 }
 
 /// Result of a query operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryResult {
+    /// Identifier attached to this query's log events
+    #[serde(default)]
+    pub query_id: String,
+    /// Intent the query was routed by, when routing is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<routing::QueryIntent>,
     pub matches: Vec<MatchedNode>,
     pub total_searched: usize,
     pub query_embedding_time_ms: u64,
     pub search_time_ms: u64,
+    /// Time spent reranking, when the query was reranked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_time_ms: Option<u64>,
+    /// Steps that failed without failing the query, e.g. reranking
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 /// A matched node from a query
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchedNode {
     pub pathway: Pathway,
     pub node_kind: NodeKind,
@@ -238,10 +2474,35 @@ pub struct MatchedNode {
     pub summary: Option<String>,
     pub content: Option<String>,
     pub highlights: Vec<String>,
+    /// Seconds since the node was last updated
+    #[serde(default)]
+    pub age: u64,
+    /// Older than `RetrievalConfig.staleness_horizon_days`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+    /// Definitions in a matched code chunk, e.g. `parse` or `Parser`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<MatchExplanation>,
+}
+
+/// How a match was found and scored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchExplanation {
+    /// Cosine similarity between the query and node embeddings
+    pub vector_score: f32,
+    /// Score assigned by the reranker, if reranking ran
+    pub rerank_score: Option<f32>,
+    /// Found by exploring the directory of another match
+    pub via_directory: bool,
+    /// Multiplier learned from result feedback, if the node has any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback_boost: Option<f32>,
 }
 
 /// Basic node information for listing
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeInfo {
     pub pathway: Pathway,
     pub kind: NodeKind,
@@ -252,7 +2513,7 @@ pub struct NodeInfo {
 }
 
 /// Storage statistics
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StorageStats {
     pub total_nodes: u64,
     pub total_directories: u64,
@@ -260,8 +2521,39 @@ pub struct StorageStats {
     pub namespaces: Vec<NamespaceStats>,
 }
 
+impl StorageStats {
+    /// Count a node towards the directory, size, and per-namespace totals
+    pub(crate) fn record(&mut self, node: &Node) {
+        if node.is_directory {
+            self.total_directories += 1;
+        }
+        self.total_size_bytes += node.size();
+
+        let namespace = node.namespace();
+        let index = match self
+            .namespaces
+            .binary_search_by(|ns| ns.namespace.cmp(&namespace))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.namespaces.insert(
+                    index,
+                    NamespaceStats {
+                        namespace,
+                        node_count: 0,
+                        size_bytes: 0,
+                    },
+                );
+                index
+            }
+        };
+        self.namespaces[index].node_count += 1;
+        self.namespaces[index].size_bytes += node.size();
+    }
+}
+
 /// Statistics for a single namespace
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NamespaceStats {
     pub namespace: Namespace,
     pub node_count: u64,
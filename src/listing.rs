@@ -0,0 +1,210 @@
+//! Listing a directory or subtree with filters, ordering, and paging
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Node, NodeKind};
+use crate::error::Result;
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+use crate::NodeInfo;
+
+/// Order of listed nodes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSort {
+    /// By pathway, A to Z
+    #[default]
+    Name,
+    /// Most recently updated first
+    UpdatedAt,
+    /// Largest first
+    Size,
+}
+
+/// Options for list operations
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    /// Levels below the pathway to include; 1 lists direct children
+    pub depth: usize,
+    pub sort: ListSort,
+    /// Reverse the sort order
+    pub reverse: bool,
+    /// Only list nodes of this kind
+    pub kind: Option<NodeKind>,
+    /// Only list nodes carrying all of these tags
+    pub tags: Vec<String>,
+    /// Nodes to skip, after sorting
+    pub offset: usize,
+    /// Maximum number of nodes to return
+    pub limit: Option<usize>,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            sort: ListSort::default(),
+            reverse: false,
+            kind: None,
+            tags: Vec::new(),
+            offset: 0,
+            limit: None,
+        }
+    }
+}
+
+/// List nodes under a pathway
+pub async fn list(
+    storage: &dyn StorageBackend,
+    pathway: &Pathway,
+    options: &ListOptions,
+) -> Result<Vec<NodeInfo>> {
+    let mut infos = if options.depth <= 1 && options.tags.is_empty() {
+        storage.list(pathway).await?
+    } else {
+        // Lazily loading backends may not have read the subtree yet
+        storage.warm(pathway).await?;
+        storage
+            .get_children(pathway, options.depth.max(1))
+            .await?
+            .iter()
+            .filter(|node| has_tags(node, &options.tags))
+            .map(info)
+            .collect()
+    };
+    infos.retain(|info| options.kind.is_none_or(|kind| info.kind == kind));
+
+    infos.sort_by(|a, b| {
+        let order = compare(a, b, options.sort);
+        if options.reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+    Ok(infos
+        .into_iter()
+        .skip(options.offset)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+fn compare(a: &NodeInfo, b: &NodeInfo, sort: ListSort) -> Ordering {
+    let by_name = || a.pathway.to_string().cmp(&b.pathway.to_string());
+    match sort {
+        ListSort::Name => by_name(),
+        ListSort::UpdatedAt => b.updated_at.cmp(&a.updated_at).then_with(by_name),
+        ListSort::Size => b.size.cmp(&a.size).then_with(by_name),
+    }
+}
+
+fn has_tags(node: &Node, tags: &[String]) -> bool {
+    tags.iter().all(|tag| node.metadata.tags.contains(tag))
+}
+
+fn info(node: &Node) -> NodeInfo {
+    NodeInfo {
+        pathway: node.pathway.clone(),
+        kind: node.kind,
+        is_directory: node.is_directory,
+        size: node.size(),
+        created_at: node.created_at,
+        updated_at: node.updated_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::storage::MemoryStorage;
+    use chrono::{Duration, Utc};
+
+    async fn storage() -> MemoryStorage {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let nodes = [
+            (
+                "a3s://knowledge/docs/b.md",
+                NodeKind::Markdown,
+                30,
+                2,
+                "api",
+            ),
+            ("a3s://knowledge/docs/a.txt", NodeKind::Document, 10, 1, ""),
+            (
+                "a3s://knowledge/docs/api/c.md",
+                NodeKind::Markdown,
+                20,
+                3,
+                "api",
+            ),
+        ];
+        for (pathway, kind, size, days_ago, tag) in nodes {
+            let mut node = Node::new(Pathway::parse(pathway).unwrap(), kind, "x".repeat(size));
+            node.updated_at = Utc::now() - Duration::days(days_ago);
+            if !tag.is_empty() {
+                node.metadata.tags.push(tag.to_string());
+            }
+            storage.put(&node).await.unwrap();
+        }
+        storage
+    }
+
+    fn names(infos: &[NodeInfo]) -> Vec<&str> {
+        infos.iter().map(|i| i.pathway.name().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_sorted_and_paged() {
+        let storage = storage().await;
+        let docs = Pathway::parse("a3s://knowledge/docs").unwrap();
+
+        let listed = list(&storage, &docs, &ListOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(names(&listed), vec!["a.txt", "b.md"]);
+
+        let options = ListOptions {
+            depth: 2,
+            sort: ListSort::UpdatedAt,
+            ..Default::default()
+        };
+        let listed = list(&storage, &docs, &options).await.unwrap();
+        assert_eq!(names(&listed), vec!["a.txt", "b.md", "c.md"]);
+
+        let options = ListOptions {
+            depth: 2,
+            sort: ListSort::Size,
+            reverse: true,
+            offset: 1,
+            limit: Some(1),
+            ..Default::default()
+        };
+        let listed = list(&storage, &docs, &options).await.unwrap();
+        assert_eq!(names(&listed), vec!["c.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_kind_and_tags() {
+        let storage = storage().await;
+        let docs = Pathway::parse("a3s://knowledge/docs").unwrap();
+
+        let options = ListOptions {
+            depth: 2,
+            kind: Some(NodeKind::Markdown),
+            ..Default::default()
+        };
+        let listed = list(&storage, &docs, &options).await.unwrap();
+        // Sorted by pathway, so `api/c.md` precedes `b.md`
+        assert_eq!(names(&listed), vec!["c.md", "b.md"]);
+
+        let options = ListOptions {
+            tags: vec!["api".to_string()],
+            ..Default::default()
+        };
+        let listed = list(&storage, &docs, &options).await.unwrap();
+        assert_eq!(names(&listed), vec!["b.md"]);
+    }
+}
@@ -1,5 +1,21 @@
-use a3s_context::{A3SClient, Config};
-use clap::{Parser, Subcommand};
+use a3s_context::config::{ConfigIssue, IssueSeverity};
+use a3s_context::ingest::IngestEvent;
+use a3s_context::listing::{ListOptions, ListSort};
+use a3s_context::render::RenderFormat;
+use a3s_context::session::ReplayEvent;
+use a3s_context::{
+    A3SClient, Config, IngestOutcome, IngestResult, Namespace, NodeInfo, NodeKind, Pathway,
+    QueryExample, VectorName,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+#[cfg(feature = "tui")]
+mod browse;
+#[cfg(feature = "repl")]
+mod repl;
 
 #[derive(Parser)]
 #[command(name = "a3s-ctx")]
@@ -15,18 +31,280 @@ struct Cli {
     /// Log level
     #[arg(short, long, default_value = "info")]
     log_level: String,
+
+    /// Log format
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Write an annotated configuration file
+    Init {
+        /// Destination file
+        #[arg(default_value = "a3s.yaml")]
+        path: PathBuf,
+
+        /// Overwrite an existing file
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Validate the configuration and print effective settings
+    Check {
+        /// Skip endpoint reachability checks
+        #[arg(long)]
+        offline: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ViewAction {
+    /// Define a view backed by a saved query
+    Create {
+        /// Pathway the view appears at
+        at: String,
+
+        /// Query whose results become the view's children
+        query: String,
+
+        /// Restrict to a namespace (knowledge, memory, capability, session)
+        #[arg(short, long, value_parser = parse_namespace)]
+        namespace: Option<Namespace>,
+
+        /// Only include pathways with this prefix
+        #[arg(short, long)]
+        pathway: Option<String>,
+
+        /// Maximum number of children
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+
+        /// Rebuild on access when older than this many seconds
+        #[arg(long)]
+        refresh_secs: Option<u64>,
+    },
+
+    /// Rebuild one view, or every view when none is given
+    Refresh {
+        /// Pathway of the view
+        at: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SavedAction {
+    /// Save a query; `{{name}}` placeholders become parameters
+    Save {
+        /// Name to save the query under
+        name: String,
+
+        /// Query text template
+        query: String,
+
+        /// Restrict to a namespace (knowledge, memory, capability, session)
+        #[arg(short, long, value_parser = parse_namespace)]
+        namespace: Option<Namespace>,
+
+        /// Only match pathways with this prefix (may use placeholders)
+        #[arg(short, long)]
+        pathway: Option<String>,
+
+        /// Maximum number of results
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Only match nodes carrying this tag (repeatable)
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+    },
+
+    /// Run a saved query
+    Run {
+        name: String,
+
+        /// Template parameter as name=value (repeatable)
+        #[arg(short = 'P', long = "param", value_parser = parse_param)]
+        params: Vec<(String, String)>,
+    },
+
+    /// List saved queries
+    List,
+
+    /// Delete a saved query
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+enum SessionAction {
+    /// List committed sessions, most recently active first
+    List,
+
+    /// Show a session's messages and the retrievals recorded under it
+    Show { id: String },
+
+    /// Write a session's messages and retrievals as JSON
+    Export {
+        id: String,
+
+        /// File to write; standard output when omitted
+        #[arg(short = 'o', long = "file")]
+        file: Option<PathBuf>,
+    },
+
+    /// Delete a session with its messages and retrieval snapshots
+    Delete { id: String },
+
+    /// Fold a session's older messages into a summary
+    Compact {
+        id: String,
+
+        /// Most recent messages to keep as they are
+        #[arg(short, long, default_value = "10")]
+        keep: usize,
+    },
+}
+
+/// Format of log lines written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per event, with span fields such as `query_id`
+    Json,
+}
+
+/// Rendering of node content shown by `read`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum RenderArg {
+    /// Content exactly as stored
+    Raw,
+    /// Markdown syntax removed, JSON pretty-printed
+    Plain,
+    /// Styled and syntax-highlighted with ANSI escape codes
+    Ansi,
+}
+
+impl From<RenderArg> for RenderFormat {
+    fn from(arg: RenderArg) -> Self {
+        match arg {
+            RenderArg::Raw => RenderFormat::Raw,
+            RenderArg::Plain => RenderFormat::Plain,
+            RenderArg::Ansi => RenderFormat::Ansi,
+        }
+    }
+}
+
+/// Order of nodes shown by `list`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortArg {
+    /// By pathway
+    Name,
+    /// Most recently updated first
+    Updated,
+    /// Largest first
+    Size,
+}
+
+impl From<SortArg> for ListSort {
+    fn from(arg: SortArg) -> Self {
+        match arg {
+            SortArg::Name => ListSort::Name,
+            SortArg::Updated => ListSort::UpdatedAt,
+            SortArg::Size => ListSort::Size,
+        }
+    }
+}
+
+/// Output format for command results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable text
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
+impl OutputFormat {
+    fn is_structured(self) -> bool {
+        self != OutputFormat::Table
+    }
+
+    /// Print a value in this structured format
+    fn print<T: Serialize>(self, value: &T) -> anyhow::Result<()> {
+        match self {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+            OutputFormat::Table => anyhow::bail!("table output is not structured"),
+        }
+        Ok(())
+    }
+}
+
+/// Structured output of the `backlog` command
+#[derive(Serialize)]
+struct BacklogOutput {
+    unembedded: Vec<NodeInfo>,
+    undigested: Vec<NodeInfo>,
+}
+
+/// Structured output of the `config check` command
+#[derive(Serialize)]
+struct ConfigCheckOutput<'a> {
+    issues: &'a [ConfigIssue],
+    config: Config,
+}
+
+/// Annotated configuration written by `config init`
+const EXAMPLE_CONFIG: &str = include_str!("../a3s.example.yaml");
+
+/// Structured output of the `read` command
+#[derive(Serialize)]
+struct ReadOutput {
+    pathway: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    brief: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Ingest content into A3S
     Ingest {
-        /// Source path (file or directory)
+        /// Source path (file or directory), slack:/discord: export, web page URL, or
+        /// sitemap:<url>
         source: String,
 
         /// Target pathway
         #[arg(short, long)]
         target: String,
+
+        /// List what happened to each file, page, or document
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Sync from an external service (notion, confluence, github, database)
+    Sync {
+        /// Connector configured under `connectors`
+        connector: String,
+
+        /// Target pathway [default: a3s://knowledge/<connector>]
+        #[arg(short, long)]
+        target: Option<String>,
+
+        /// List what happened to each document
+        #[arg(short, long)]
+        verbose: bool,
     },
 
     /// Query the context store
@@ -37,12 +315,135 @@ enum Commands {
         /// Result limit
         #[arg(short, long, default_value = "10")]
         limit: usize,
+
+        /// Restrict to a namespace (knowledge, memory, capability, session)
+        #[arg(short, long, value_parser = parse_namespace)]
+        namespace: Option<Namespace>,
+
+        /// Minimum similarity score
+        #[arg(long)]
+        threshold: Option<f32>,
+
+        /// Only match pathways starting with this prefix
+        #[arg(short, long)]
+        pathway: Option<String>,
+
+        /// Only match nodes carrying all of these tags (comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// Include full node content in results
+        #[arg(long)]
+        include_content: bool,
+
+        /// Force reranking on
+        #[arg(long, conflicts_with = "no_rerank")]
+        rerank: bool,
+
+        /// Force reranking off
+        #[arg(long)]
+        no_rerank: bool,
+
+        /// Show how each match was scored
+        #[arg(long)]
+        explain: bool,
+
+        /// Abort the query after this many milliseconds
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+
+        /// Keep only the newest of near-duplicate matches
+        #[arg(long)]
+        prefer_fresh: bool,
+
+        /// Only match this language: ISO 639-3 code (eng), English name, or
+        /// programming language (rust)
+        #[arg(long)]
+        language: Option<String>,
+
+        /// Vectors to search (content, summary, title, questions;
+        /// comma-separated)
+        #[arg(long, value_delimiter = ',', value_parser = parse_vector_name)]
+        vectors: Vec<VectorName>,
+
+        /// Steer toward this text or pathway (repeatable)
+        #[arg(long)]
+        like: Vec<String>,
+
+        /// Steer away from this text or pathway (repeatable)
+        #[arg(long)]
+        unlike: Vec<String>,
+
+        /// Store the full result under this session for later replay
+        #[arg(long, value_name = "SESSION")]
+        snapshot: Option<String>,
+
+        /// Fail if reranking or loading a match fails, instead of warning
+        #[arg(long)]
+        strict: bool,
+
+        /// Also match nodes quarantined by ingest screening
+        #[arg(long)]
+        include_quarantined: bool,
+
+        /// Wait for queued writes to be indexed before searching
+        #[arg(long)]
+        consistent_read: bool,
+    },
+
+    /// Run a query under two retrieval profiles and compare the results
+    Compare {
+        /// Search query
+        query: String,
+
+        /// First profile (`default` is the retrieval section)
+        profile_a: String,
+
+        /// Second profile, from retrieval_profiles
+        profile_b: String,
+    },
+
+    /// Show retrieval snapshots recorded under a session
+    Replay {
+        /// Session the snapshots were recorded under
+        session: String,
+
+        /// Show only this query's snapshot
+        query_id: Option<String>,
     },
 
     /// List nodes at a pathway
     List {
         /// Pathway to list
         pathway: String,
+
+        /// Levels below the pathway to include
+        #[arg(short, long, default_value = "1")]
+        depth: usize,
+
+        /// Order of the listed nodes
+        #[arg(short, long, value_enum, default_value_t = SortArg::Name)]
+        sort: SortArg,
+
+        /// Reverse the sort order
+        #[arg(short, long)]
+        reverse: bool,
+
+        /// Only list nodes of this kind, e.g. markdown or code
+        #[arg(short, long, value_parser = parse_kind)]
+        kind: Option<NodeKind>,
+
+        /// Only list nodes carrying this tag (repeatable)
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// Nodes to skip, after sorting
+        #[arg(long, default_value = "0")]
+        offset: usize,
+
+        /// Maximum number of nodes to list
+        #[arg(short, long)]
+        limit: Option<usize>,
     },
 
     /// Read a node's content
@@ -57,6 +458,115 @@ enum Commands {
         /// Show only summary
         #[arg(short, long)]
         summary: bool,
+
+        /// How to render the content; styled for terminals by default
+        #[arg(long, value_enum)]
+        render: Option<RenderArg>,
+
+        /// Return at most this many tokens, summarizing or excerpting
+        /// content that does not fit
+        #[arg(long)]
+        max_tokens: Option<usize>,
+
+        /// What to keep when content must be shortened (with --max-tokens)
+        #[arg(long, requires = "max_tokens")]
+        focus: Option<String>,
+    },
+
+    /// Print the passages of a node most relevant to a question
+    Extract {
+        /// Pathway of the node to read
+        pathway: String,
+
+        /// Question the passages should answer
+        question: String,
+
+        /// Token budget for the passages
+        #[arg(long, default_value = "500")]
+        max_tokens: usize,
+    },
+
+    /// Search node contents line by line
+    Grep {
+        /// Pattern to search for
+        pattern: String,
+
+        /// Pathway to search under
+        #[arg(default_value = "a3s://knowledge")]
+        pathway: String,
+
+        /// Treat the pattern as a regular expression
+        #[arg(short = 'E', long)]
+        regex: bool,
+
+        /// Match case-insensitively
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Maximum number of matching nodes
+        #[arg(short, long)]
+        max_results: Option<usize>,
+    },
+
+    /// Show how the content of one node differs from another
+    Diff {
+        /// Pathway to diff from
+        from: String,
+
+        /// Pathway to diff to
+        to: String,
+    },
+
+    /// Store a fact in a user's memory
+    Remember {
+        /// Fact to remember
+        fact: String,
+
+        /// User the memory belongs to
+        #[arg(short, long, default_value = "default")]
+        user: String,
+
+        /// Tags to attach (comma-separated)
+        #[arg(short, long, value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+
+    /// Search a user's memories
+    Recall {
+        /// Query text
+        query: String,
+
+        /// User whose memories to search
+        #[arg(short, long, default_value = "default")]
+        user: String,
+
+        /// Result limit
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+    },
+
+    /// Answer a question from the store with citations
+    #[cfg(feature = "llm-digest")]
+    Ask {
+        /// Question to answer
+        question: String,
+
+        /// Maximum matches to draw context from
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+
+        /// Token budget for context in the prompt
+        #[arg(long, default_value = "3000")]
+        max_tokens: usize,
+    },
+
+    /// Create a node, or replace its content, without ingesting a file
+    Write {
+        /// Pathway to write
+        pathway: String,
+
+        /// Content to store; read from stdin when omitted
+        content: Option<String>,
     },
 
     /// Remove a node
@@ -69,126 +579,1459 @@ enum Commands {
         recursive: bool,
     },
 
-    /// Show storage statistics
-    Stats,
+    /// Move a node and everything below it, redirecting relations to it
+    Move {
+        /// Pathway to move
+        from: String,
 
-    /// Initialize storage
-    Init,
-}
+        /// New pathway
+        to: String,
+    },
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    /// Copy a node to another pathway
+    Copy {
+        /// Pathway to copy
+        from: String,
 
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(cli.log_level)
-        .init();
+        /// Pathway of the copy
+        to: String,
 
-    // Load configuration
-    let config = if let Some(config_path) = cli.config {
-        Config::from_file(&config_path)?
-    } else {
-        Config::from_env()
-    };
+        /// Copy everything below the node too
+        #[arg(short, long)]
+        recursive: bool,
+    },
 
-    // Create client
-    let client = A3SClient::new(config).await?;
+    /// Show recursive usage of a subtree
+    Du {
+        /// Pathway to summarize
+        pathway: String,
 
-    match cli.command {
-        Commands::Ingest { source, target } => {
-            println!("Ingesting {} into {}...", source, target);
-            let result = client.ingest(&source, &target).await?;
-            println!(
-                "✓ Created: {}, Updated: {}, Errors: {}",
-                result.nodes_created,
-                result.nodes_updated,
-                result.errors.len()
-            );
-            if !result.errors.is_empty() {
-                println!("\nErrors:");
-                for err in result.errors {
-                    println!("  - {}", err);
-                }
-            }
-        }
+        /// Number of largest nodes to show
+        #[arg(short = 'n', long, default_value = "10")]
+        top: usize,
+    },
 
-        Commands::Query { query, limit } => {
-            println!("Searching for: {}", query);
-            let result = client
-                .query_with_options(
-                    &query,
-                    a3s_context::QueryOptions {
-                        limit: Some(limit),
-                        ..Default::default()
-                    },
-                )
-                .await?;
+    /// Show a subtree as a tree with each node's brief
+    Tree {
+        /// Pathway to show
+        pathway: String,
 
-            println!(
-                "\nFound {} results (searched {} nodes in {}ms):\n",
-                result.matches.len(),
-                result.total_searched,
-                result.search_time_ms
-            );
+        /// Levels below the pathway to show
+        #[arg(short, long, default_value = "2")]
+        depth: usize,
+    },
 
-            for (i, m) in result.matches.iter().enumerate() {
-                println!("{}. {} (score: {:.3})", i + 1, m.pathway, m.score);
-                println!("   {}", m.brief);
-                println!();
-            }
-        }
+    /// List nodes still missing an embedding or digest
+    Backlog {
+        /// Pathway to inspect
+        pathway: String,
+    },
 
-        Commands::List { pathway } => {
-            let nodes = client.list(&pathway).await?;
-            println!("Nodes at {}:\n", pathway);
-            for node in nodes {
-                let kind_str = format!("{:?}", node.kind);
-                println!(
-                    "  {} {} ({})",
-                    if node.is_directory { "📁" } else { "📄" },
-                    node.pathway.name().unwrap_or(""),
-                    kind_str
-                );
-            }
-        }
+    /// Re-derive chunk nodes from stored content with a new window
+    Rechunk {
+        /// Pathway whose nodes are re-chunked, recursively
+        pathway: String,
 
-        Commands::Read {
-            pathway,
-            brief,
-            summary,
-        } => {
-            if brief {
-                let content = client.brief(&pathway).await?;
-                println!("{}", content);
-            } else if summary {
-                let content = client.summary(&pathway).await?;
-                println!("{}", content);
-            } else {
-                let node = client.read(&pathway).await?;
-                println!("{}", node.content);
-            }
-        }
+        /// Characters per chunk [default: ingest.chunk_size]
+        #[arg(long)]
+        chunk_size: Option<usize>,
 
-        Commands::Remove { pathway, recursive } => {
-            client.remove(&pathway, recursive).await?;
-            println!("✓ Removed {}", pathway);
-        }
+        /// Characters shared by consecutive chunks [default: ingest.chunk_overlap]
+        #[arg(long)]
+        overlap: Option<usize>,
+    },
 
-        Commands::Stats => {
-            let stats = client.stats().await?;
-            println!("Storage Statistics:");
-            println!("  Total nodes: {}", stats.total_nodes);
-            println!("  Total directories: {}", stats.total_directories);
-            println!("  Total size: {} bytes", stats.total_size_bytes);
-        }
+    /// Record whether a query's match was useful (needs analytics.enabled)
+    Feedback {
+        /// ID printed with the query's results
+        query_id: String,
 
-        Commands::Init => {
-            println!("✓ Storage initialized");
-        }
-    }
+        /// Pathway of the match
+        pathway: String,
 
-    client.shutdown().await?;
+        /// Mark the match as not useful
+        #[arg(long)]
+        not_useful: bool,
+    },
+
+    /// Report logged queries, zero-result queries, and feedback
+    Analytics {
+        /// Entries per section
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+    },
+
+    /// Forget the feedback behind ranking adjustments
+    ResetFeedback {
+        /// Only reset nodes under this pathway
+        pathway: Option<String>,
+    },
+
+    /// Inspect and manage stored sessions
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+
+    /// Save, run, and list saved queries
+    Saved {
+        #[command(subcommand)]
+        action: SavedAction,
+    },
+
+    /// Manage views: directories backed by saved queries
+    View {
+        #[command(subcommand)]
+        action: ViewAction,
+    },
+
+    /// Report near-duplicate nodes in a namespace
+    Duplicates {
+        /// Namespace to scan (knowledge, memory, capability, session)
+        #[arg(value_parser = parse_namespace, default_value = "knowledge")]
+        namespace: Namespace,
+
+        /// Embedding similarity at which nodes count as duplicates
+        #[arg(short, long, default_value = "0.95")]
+        threshold: f32,
+
+        /// Merge each group into its canonical node
+        #[arg(long)]
+        merge: bool,
+    },
+
+    /// Flag memories with outlying embeddings or suspicious content
+    Anomalies {
+        /// Pathway whose subtree to check
+        #[arg(default_value = "a3s://memory")]
+        pathway: String,
+
+        /// Nodes less similar than this to every cluster are flagged
+        #[arg(short, long, default_value = "0.35")]
+        min_similarity: f32,
+
+        /// Clusters to compare against (0 picks one from the node count)
+        #[arg(short, long, default_value = "0")]
+        clusters: usize,
+
+        /// Tag flagged nodes `needs-review`
+        #[arg(long)]
+        tag: bool,
+    },
+
+    /// Relate nodes to their most similar nodes by embedding
+    Relate {
+        /// Pathway whose subtree to relate
+        #[arg(default_value = "a3s://knowledge")]
+        pathway: String,
+
+        /// Most relations per node (defaults to ingest.relate.top_k)
+        #[arg(short = 'k', long)]
+        top_k: Option<usize>,
+
+        /// Lowest similarity related (defaults to ingest.relate.threshold)
+        #[arg(short, long)]
+        threshold: Option<f32>,
+
+        /// Show the relations without recording them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Cluster a namespace by embedding into labelled topics
+    Topics {
+        /// Namespace to cluster (knowledge, memory, capability, session)
+        #[arg(value_parser = parse_namespace, default_value = "knowledge")]
+        namespace: Namespace,
+
+        /// Most topics to form
+        #[arg(short, default_value = "8")]
+        k: usize,
+    },
+
+    /// Bundle a subtree into a context pack
+    Pack {
+        /// Pathway to export
+        pathway: String,
+
+        /// Pack file to write
+        #[arg(short = 'o', long = "file", default_value = "pack.a3s")]
+        file: PathBuf,
+    },
+
+    /// Install a context pack read-only
+    Install {
+        /// Pack file to install
+        file: PathBuf,
+
+        /// Pathway to mount the pack at (defaults to where it was exported from)
+        #[arg(long)]
+        at: Option<String>,
+    },
+
+    /// Export a subtree as an archive to move it to another store
+    Export {
+        /// Pathway to export
+        pathway: String,
+
+        /// Archive file to write; standard output when omitted
+        #[arg(short = 'o', long = "file")]
+        file: Option<PathBuf>,
+    },
+
+    /// Import an archive as writable content
+    Import {
+        /// Archive file to read; standard input when omitted
+        file: Option<PathBuf>,
+
+        /// Pathway to import at (defaults to where it was exported from)
+        #[arg(long)]
+        at: Option<String>,
+    },
+
+    /// Snapshot the whole store to a directory or s3:// URL
+    Backup {
+        /// Backup target (defaults to storage.backup.target)
+        target: Option<String>,
+
+        /// List the backups at the target instead of taking one
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Replace the store's contents with a backup
+    Restore {
+        /// Backup target (defaults to storage.backup.target)
+        target: Option<String>,
+
+        /// Backup to restore (defaults to the newest)
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Remove an installed context pack
+    Uninstall {
+        /// Pathway the pack is mounted at
+        pathway: String,
+    },
+
+    /// Show storage statistics
+    Stats,
+
+    /// Initialize storage
+    Init,
+
+    /// Create or validate configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Interactively browse the context store
+    #[cfg(feature = "tui")]
+    Browse,
+
+    /// Start an interactive query REPL
+    #[cfg(feature = "repl")]
+    Repl {
+        /// Record queries and results into a session
+        #[arg(long)]
+        record: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    // Initialize logging
+    let logger = tracing_subscriber::fmt()
+        .with_env_filter(cli.log_level.as_str())
+        .with_writer(std::io::stderr);
+    match cli.log_format {
+        LogFormat::Text => logger.init(),
+        LogFormat::Json => logger.json().with_current_span(true).init(),
+    }
+
+    // Config commands run without a client so broken configurations can be diagnosed
+    if let Commands::Config { action } = &cli.command {
+        return config_command(action, cli.config.as_deref(), cli.output).await;
+    }
+
+    // Load configuration
+    let config = load_config(cli.config.as_deref())?;
+    let ingest_config = config.ingest.clone();
+
+    // Create client
+    let client = A3SClient::new(config).await?;
+
+    match cli.command {
+        Commands::Ingest {
+            source,
+            target,
+            verbose,
+        } => {
+            if !cli.output.is_structured() {
+                println!("Ingesting {} into {}...", source, target);
+            }
+            let result = if !cli.output.is_structured() && std::io::stderr().is_terminal() {
+                let (progress, events) = tokio::sync::mpsc::unbounded_channel();
+                let printer = tokio::spawn(print_progress(events));
+                let result = client
+                    .ingest_with_progress(&source, &target, progress)
+                    .await;
+                printer.await?;
+                result?
+            } else {
+                client.ingest(&source, &target).await?
+            };
+            print_ingest_result(&result, verbose, cli.output)?;
+        }
+
+        Commands::Sync {
+            connector,
+            target,
+            verbose,
+        } => {
+            let target = target.unwrap_or_else(|| format!("a3s://knowledge/{}", connector));
+            if !cli.output.is_structured() {
+                println!("Syncing {} into {}...", connector, target);
+            }
+            let result = client.sync(&connector, &target).await?;
+            print_ingest_result(&result, verbose, cli.output)?;
+        }
+
+        Commands::Query {
+            query,
+            limit,
+            namespace,
+            threshold,
+            pathway,
+            tags,
+            include_content,
+            rerank,
+            no_rerank,
+            explain,
+            timeout_ms,
+            prefer_fresh,
+            language,
+            vectors,
+            like,
+            unlike,
+            snapshot,
+            strict,
+            include_quarantined,
+            consistent_read,
+        } => {
+            if !cli.output.is_structured() {
+                println!("Searching for: {}", query);
+            }
+            let result = client
+                .query_with_options(
+                    &query,
+                    a3s_context::QueryOptions {
+                        namespace,
+                        limit: Some(limit),
+                        threshold,
+                        include_content,
+                        pathway_filter: pathway,
+                        tags,
+                        rerank: (rerank || no_rerank).then_some(rerank),
+                        explain,
+                        timeout_ms,
+                        prefer_fresh,
+                        language,
+                        vectors: (!vectors.is_empty()).then_some(vectors),
+                        positive_examples: like.iter().map(|s| QueryExample::parse(s)).collect(),
+                        negative_examples: unlike.iter().map(|s| QueryExample::parse(s)).collect(),
+                        snapshot_session: snapshot,
+                        strict,
+                        include_quarantined,
+                        consistent_read,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+
+            if cli.output.is_structured() {
+                cli.output.print(&result)?;
+            } else {
+                println!(
+                    "\nFound {} results (searched {} nodes in {}ms)",
+                    result.matches.len(),
+                    result.total_searched,
+                    result.search_time_ms
+                );
+                if let Some(ms) = result.rerank_time_ms {
+                    println!("Reranked in {}ms", ms);
+                }
+                for warning in &result.warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                println!("Query ID: {}\n", result.query_id);
+
+                for (i, m) in result.matches.iter().enumerate() {
+                    println!("{}. {} (score: {:.3})", i + 1, m.pathway, m.score);
+                    println!("   {}", m.brief);
+                    if !m.symbols.is_empty() {
+                        println!("   defines: {}", m.symbols.join(", "));
+                    }
+                    if m.stale {
+                        println!("   ⚠ stale: last updated {} days ago", m.age / 86_400);
+                    }
+                    if let Some(explanation) = &m.explanation {
+                        println!(
+                            "   vector: {:.3}, rerank: {}{}{}",
+                            explanation.vector_score,
+                            explanation
+                                .rerank_score
+                                .map(|s| format!("{:.3}", s))
+                                .unwrap_or_else(|| "-".to_string()),
+                            explanation
+                                .feedback_boost
+                                .map(|b| format!(", feedback: ×{:.3}", b))
+                                .unwrap_or_default(),
+                            if explanation.via_directory {
+                                ", via directory"
+                            } else {
+                                ""
+                            }
+                        );
+                    }
+                    if let Some(content) = &m.content {
+                        println!("\n{}", content);
+                    }
+                    println!();
+                }
+            }
+        }
+
+        Commands::Compare {
+            query,
+            profile_a,
+            profile_b,
+        } => {
+            let comparison = client.query_compare(&query, &profile_a, &profile_b).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&comparison)?;
+            } else {
+                let overlap = &comparison.overlap;
+                println!(
+                    "Shared {} results (Jaccard {:.2}, rank-biased overlap {:.2}, same top: {})",
+                    overlap.shared,
+                    overlap.jaccard,
+                    overlap.rank_biased_overlap,
+                    if overlap.same_top { "yes" } else { "no" }
+                );
+                for side in [&comparison.a, &comparison.b] {
+                    println!("\n{}:", side.profile);
+                    for (i, m) in side.result.matches.iter().enumerate() {
+                        println!("{:>3}. {} (score: {:.3})", i + 1, m.pathway, m.score);
+                    }
+                }
+            }
+        }
+
+        Commands::Replay { session, query_id } => {
+            let snapshots = match query_id {
+                Some(query_id) => vec![client.retrieval_snapshot(&session, &query_id).await?],
+                None => client.retrieval_snapshots(&session).await?,
+            };
+            if cli.output.is_structured() {
+                cli.output.print(&snapshots)?;
+            } else {
+                for snapshot in snapshots {
+                    println!(
+                        "[{}] {} \"{}\" ({} nodes in store)",
+                        snapshot.taken_at.to_rfc3339(),
+                        snapshot.result.query_id,
+                        snapshot.query,
+                        snapshot.store_nodes
+                    );
+                    for (i, m) in snapshot.result.matches.iter().enumerate() {
+                        println!("{:>3}. {} (score: {:.3})", i + 1, m.pathway, m.score);
+                    }
+                    println!();
+                }
+            }
+        }
+
+        Commands::List {
+            pathway,
+            depth,
+            sort,
+            reverse,
+            kind,
+            tags,
+            offset,
+            limit,
+        } => {
+            let options = ListOptions {
+                depth,
+                sort: sort.into(),
+                reverse,
+                kind,
+                tags,
+                offset,
+                limit,
+            };
+            let nodes = client.list_with_options(&pathway, &options).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&nodes)?;
+            } else {
+                println!("Nodes at {}:\n", pathway);
+                let root_depth = Pathway::parse(&pathway)?.depth();
+                for node in nodes {
+                    let kind_str = format!("{:?}", node.kind);
+                    println!(
+                        "  {} {} ({})",
+                        if node.is_directory { "📁" } else { "📄" },
+                        node.pathway.segments()[root_depth..].join("/"),
+                        kind_str
+                    );
+                }
+            }
+        }
+
+        Commands::Read {
+            pathway,
+            brief,
+            summary,
+            render: _,
+            max_tokens: Some(max_tokens),
+            focus,
+        } if !brief && !summary => {
+            let read = client
+                .read_within(&pathway, max_tokens, focus.as_deref())
+                .await?;
+            if cli.output.is_structured() {
+                cli.output.print(&read)?;
+            } else {
+                if read.source != a3s_context::condense::ReadSource::Content {
+                    let source = format!("{:?}", read.source).to_lowercase();
+                    eprintln!("({}, {} tokens)", source, read.tokens);
+                }
+                println!("{}", read.text);
+            }
+        }
+
+        Commands::Read {
+            pathway,
+            brief,
+            summary,
+            render,
+            ..
+        } => {
+            if cli.output.is_structured() {
+                let node = client.read(&pathway).await?;
+                let full = !brief && !summary;
+                cli.output.print(&ReadOutput {
+                    pathway: node.pathway.to_string(),
+                    brief: (brief || full).then_some(node.digest.brief),
+                    summary: (summary || full).then_some(node.digest.summary),
+                    content: full.then_some(node.content),
+                })?;
+            } else if brief {
+                let content = client.brief(&pathway).await?;
+                println!("{}", content);
+            } else if summary {
+                let content = client.summary(&pathway).await?;
+                println!("{}", content);
+            } else {
+                let format = render.map(RenderFormat::from).unwrap_or_else(|| {
+                    if std::io::stdout().is_terminal() {
+                        RenderFormat::Ansi
+                    } else {
+                        RenderFormat::Plain
+                    }
+                });
+                println!("{}", client.read_rendered(&pathway, format).await?);
+            }
+        }
+
+        Commands::Extract {
+            pathway,
+            question,
+            max_tokens,
+        } => {
+            let extraction = client.extract(&pathway, &question, max_tokens).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&extraction)?;
+            } else {
+                for passage in &extraction.passages {
+                    println!(
+                        "[{}..{}] (score: {:.3})\n{}\n",
+                        passage.start,
+                        passage.end,
+                        passage.score,
+                        passage.text.trim_end()
+                    );
+                }
+            }
+        }
+
+        Commands::Grep {
+            pattern,
+            pathway,
+            regex,
+            ignore_case,
+            max_results,
+        } => {
+            let matches = client
+                .grep(
+                    &pattern,
+                    &pathway,
+                    a3s_context::grep::GrepOptions {
+                        regex,
+                        case_insensitive: ignore_case,
+                        max_results,
+                    },
+                )
+                .await?;
+
+            if cli.output.is_structured() {
+                cli.output.print(&matches)?;
+            } else {
+                for m in matches {
+                    for line in m.lines {
+                        println!("{}:{}: {}", m.pathway, line.line_number, line.text);
+                    }
+                }
+            }
+        }
+
+        Commands::Diff { from, to } => {
+            let diff = client.diff_content(&from, &to).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&diff)?;
+            } else {
+                print!("{}", diff.unified);
+                println!(
+                    "{} insertions(+), {} deletions(-), similarity {:.3}",
+                    diff.insertions, diff.deletions, diff.similarity
+                );
+            }
+        }
+
+        Commands::Remember { fact, user, tags } => {
+            let pathway = client.remember(&fact, &user, &tags).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&pathway)?;
+            } else {
+                println!("✓ Remembered {}", pathway);
+            }
+        }
+
+        Commands::Recall { query, user, limit } => {
+            let result = client.recall(&query, &user, limit).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&result)?;
+            } else if result.matches.is_empty() {
+                println!("No memories found for {}", user);
+            } else {
+                for (i, m) in result.matches.iter().enumerate() {
+                    println!("{}. {} (score: {:.3})", i + 1, m.brief, m.score);
+                }
+            }
+        }
+
+        #[cfg(feature = "llm-digest")]
+        Commands::Ask {
+            question,
+            limit,
+            max_tokens,
+        } => {
+            let options = a3s_context::answer::AskOptions {
+                query: a3s_context::QueryOptions {
+                    limit: Some(limit),
+                    ..Default::default()
+                },
+                max_context_tokens: max_tokens,
+            };
+            let answer = client.ask(&question, options).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&answer)?;
+            } else {
+                println!("{}", answer.answer);
+                if !answer.citations.is_empty() {
+                    println!("\nSources:");
+                    for citation in &answer.citations {
+                        match &citation.span {
+                            Some(span) => println!(
+                                "  - {} chars {}..{} ({:.3})",
+                                citation.pathway, span.start, span.end, citation.score
+                            ),
+                            None => println!("  - {} ({:.3})", citation.pathway, citation.score),
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Write { pathway, content } => {
+            let content = match content {
+                Some(content) => content,
+                None => std::io::read_to_string(std::io::stdin())?,
+            };
+            if client.write(&pathway, &content).await? {
+                println!("✓ Created {}", pathway);
+            } else {
+                println!("✓ Updated {}", pathway);
+            }
+        }
+
+        Commands::Remove { pathway, recursive } => {
+            client.remove(&pathway, recursive).await?;
+            println!("✓ Removed {}", pathway);
+        }
+
+        Commands::Move { from, to } => {
+            let result = client.move_node(&from, &to).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&result)?;
+            } else {
+                println!(
+                    "✓ Moved {} to {} ({} nodes, {} relations updated)",
+                    from, to, result.nodes, result.relations_updated
+                );
+            }
+        }
+
+        Commands::Copy {
+            from,
+            to,
+            recursive,
+        } => {
+            let result = client.copy_node(&from, &to, recursive).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&result)?;
+            } else {
+                println!("✓ Copied {} to {} ({} nodes)", from, to, result.nodes);
+            }
+        }
+
+        Commands::Du { pathway, top } => {
+            let report = client.usage(&pathway, top).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&report)?;
+            } else {
+                println!(
+                    "{:>10}  {:>6}  {:>8}  {:>7}  PATHWAY",
+                    "BYTES", "NODES", "EMBEDDED", "DIGESTS"
+                );
+                for usage in report.children.iter().chain(Some(&report.total)) {
+                    println!(
+                        "{:>10}  {:>6}  {:>8}  {:>6.0}%  {}",
+                        usage.size_bytes,
+                        usage.node_count,
+                        usage.embedded_count,
+                        usage.digest_coverage() * 100.0,
+                        usage.pathway
+                    );
+                }
+
+                if !report.largest.is_empty() {
+                    println!("\nLargest nodes:");
+                    for node in &report.largest {
+                        println!("{:>10}  {}", node.size_bytes, node.pathway);
+                    }
+                }
+            }
+        }
+
+        Commands::Tree { pathway, depth } => {
+            let tree = client.tree(&pathway, depth).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&tree)?;
+            } else {
+                print!("{}", a3s_context::tree::render(&tree));
+            }
+        }
+
+        Commands::Backlog { pathway } => {
+            let output = BacklogOutput {
+                unembedded: client.unembedded(&pathway).await?,
+                undigested: client.undigested(&pathway).await?,
+            };
+            if cli.output.is_structured() {
+                cli.output.print(&output)?;
+            } else {
+                for (label, nodes) in [
+                    ("Missing embeddings", &output.unembedded),
+                    ("Missing digests", &output.undigested),
+                ] {
+                    println!("{} ({}):", label, nodes.len());
+                    for node in nodes {
+                        println!("  {}", node.pathway);
+                    }
+                }
+            }
+        }
+
+        Commands::Rechunk {
+            pathway,
+            chunk_size,
+            overlap,
+        } => {
+            let chunk_size = chunk_size.unwrap_or(ingest_config.chunk_size);
+            let overlap = overlap.unwrap_or(ingest_config.chunk_overlap);
+            let result = client.rechunk(&pathway, chunk_size, overlap).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&result)?;
+            } else {
+                println!(
+                    "✓ Re-chunked {} nodes: {} chunks embedded, {} unchanged, {} removed",
+                    result.nodes, result.embedded, result.unchanged, result.removed
+                );
+            }
+        }
+
+        Commands::Feedback {
+            query_id,
+            pathway,
+            not_useful,
+        } => {
+            client
+                .record_feedback(&query_id, &pathway, !not_useful)
+                .await?;
+            println!("✓ Recorded feedback for {}", pathway);
+        }
+
+        Commands::Analytics { limit } => {
+            let report = client.analytics_report(limit).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&report)?;
+            } else {
+                println!(
+                    "{} queries, {} with no results, {} feedback entries",
+                    report.queries, report.zero_result_queries, report.feedback
+                );
+                for (label, queries) in [
+                    ("Top queries", &report.top_queries),
+                    ("Zero-result queries", &report.zero_results),
+                    ("Queries with no useful result", &report.unhelpful),
+                ] {
+                    println!("\n{}:", label);
+                    for query in queries {
+                        println!("{:>6}  {}", query.count, query.query);
+                    }
+                }
+                println!("\nFeedback by node (useful / not useful):");
+                for node in &report.nodes {
+                    println!(
+                        "{:>6} / {:<6}  {}",
+                        node.useful, node.not_useful, node.pathway
+                    );
+                }
+            }
+        }
+
+        Commands::ResetFeedback { pathway } => {
+            client.reset_feedback(pathway.as_deref()).await?;
+            println!(
+                "✓ Reset feedback for {}",
+                pathway.as_deref().unwrap_or("every node")
+            );
+        }
+
+        Commands::Session { action } => match action {
+            SessionAction::List => {
+                let sessions = client.sessions().await?;
+                if cli.output.is_structured() {
+                    cli.output.print(&sessions)?;
+                } else {
+                    for session in &sessions {
+                        let last = session.last_message_at.unwrap_or(session.created_at);
+                        println!(
+                            "{:<36}  {:<12}  {:>4} messages  {}",
+                            session.id,
+                            session.user,
+                            session.messages,
+                            last.to_rfc3339()
+                        );
+                    }
+                }
+            }
+            SessionAction::Show { id } => {
+                let export = client.export_session(&id).await?;
+                if cli.output.is_structured() {
+                    cli.output.print(&export)?;
+                } else {
+                    println!(
+                        "{} (user: {}, started {})",
+                        export.id,
+                        export.user,
+                        export.created_at.to_rfc3339()
+                    );
+                    client
+                        .replay_session(&id, |event| {
+                            match event {
+                                ReplayEvent::Message(m) => {
+                                    println!("\n[{}] {:?}:", m.timestamp.to_rfc3339(), m.role);
+                                    println!("{}", m.content);
+                                }
+                                ReplayEvent::Retrieval(s) => println!(
+                                    "\n[{}] retrieval {} \"{}\" ({} matches)",
+                                    s.taken_at.to_rfc3339(),
+                                    s.result.query_id,
+                                    s.query,
+                                    s.result.matches.len()
+                                ),
+                            }
+                            Ok(())
+                        })
+                        .await?;
+                }
+            }
+            SessionAction::Export { id, file } => {
+                let export = client.export_session(&id).await?;
+                match file {
+                    Some(file) => {
+                        let writer = std::io::BufWriter::new(std::fs::File::create(&file)?);
+                        serde_json::to_writer_pretty(writer, &export)?;
+                        println!(
+                            "✓ Exported {} messages and {} retrievals from {} into {}",
+                            export.messages.len(),
+                            export.retrievals.len(),
+                            id,
+                            file.display()
+                        );
+                    }
+                    None => {
+                        serde_json::to_writer_pretty(std::io::stdout().lock(), &export)?;
+                        println!();
+                    }
+                }
+            }
+            SessionAction::Delete { id } => {
+                client.delete_session(&id).await?;
+                println!("✓ Deleted {}", id);
+            }
+            SessionAction::Compact { id, keep } => {
+                let folded = client.compact_session(&id, keep).await?;
+                if folded == 0 {
+                    println!("✓ {} has nothing to compact", id);
+                } else {
+                    println!("✓ Folded {} messages of {} into a summary", folded, id);
+                }
+            }
+        },
+
+        Commands::Saved { action } => match action {
+            SavedAction::Save {
+                name,
+                query,
+                namespace,
+                pathway,
+                limit,
+                tags,
+            } => {
+                let options = a3s_context::QueryOptions {
+                    namespace,
+                    limit,
+                    pathway_filter: pathway,
+                    tags,
+                    ..Default::default()
+                };
+                let saved = client.save_query(&name, &query, &options).await?;
+                let variables = saved.variables();
+                if variables.is_empty() {
+                    println!("✓ Saved {}", name);
+                } else {
+                    println!("✓ Saved {} (parameters: {})", name, variables.join(", "));
+                }
+            }
+            SavedAction::Run { name, params } => {
+                let params = params.into_iter().collect();
+                let result = client.run_saved(&name, &params).await?;
+                if cli.output.is_structured() {
+                    cli.output.print(&result)?;
+                } else {
+                    for (i, m) in result.matches.iter().enumerate() {
+                        println!("{}. {} (score: {:.3})", i + 1, m.pathway, m.score);
+                        println!("   {}", m.brief);
+                    }
+                }
+            }
+            SavedAction::List => {
+                let queries = client.saved_queries().await?;
+                if cli.output.is_structured() {
+                    let queries: std::collections::BTreeMap<_, _> = queries.into_iter().collect();
+                    cli.output.print(&queries)?;
+                } else {
+                    for (name, saved) in &queries {
+                        println!("{:<20}  {}", name, saved.query);
+                    }
+                }
+            }
+            SavedAction::Delete { name } => {
+                client.delete_saved_query(&name).await?;
+                println!("✓ Deleted {}", name);
+            }
+        },
+
+        Commands::View { action } => match action {
+            ViewAction::Create {
+                at,
+                query,
+                namespace,
+                pathway,
+                limit,
+                refresh_secs,
+            } => {
+                let mut definition = a3s_context::view::ViewDefinition::new(query);
+                definition.namespace = namespace;
+                definition.pathway_filter = pathway;
+                definition.limit = limit;
+                definition.refresh_secs = refresh_secs;
+                client.create_view(&at, definition).await?;
+                println!("✓ Created view {}", at);
+            }
+            ViewAction::Refresh { at: Some(at) } => {
+                let count = client.refresh_view(&at).await?;
+                println!("✓ Refreshed {} ({} nodes)", at, count);
+            }
+            ViewAction::Refresh { at: None } => {
+                let count = client.refresh_views().await?;
+                println!("✓ Refreshed {} views", count);
+            }
+        },
+
+        Commands::Anomalies {
+            pathway,
+            min_similarity,
+            clusters,
+            tag,
+        } => {
+            let options = a3s_context::anomaly::AnomalyOptions {
+                clusters,
+                min_similarity,
+            };
+            let report = client.find_anomalies(&pathway, &options).await?;
+            if tag {
+                let tags = [a3s_context::anomaly::REVIEW_TAG.to_string()];
+                for anomaly in &report.anomalies {
+                    client.add_tags(anomaly.pathway.to_string(), &tags).await?;
+                }
+            }
+            if cli.output.is_structured() {
+                cli.output.print(&report)?;
+            } else {
+                for anomaly in &report.anomalies {
+                    let reasons: Vec<String> =
+                        anomaly.reasons.iter().map(|r| r.to_string()).collect();
+                    println!("{}  {}", anomaly.pathway, reasons.join(", "));
+                }
+                let verb = if tag { "✓ Tagged" } else { "Flagged" };
+                println!(
+                    "{} {} of {} nodes",
+                    verb,
+                    report.anomalies.len(),
+                    report.nodes_scanned
+                );
+            }
+        }
+
+        Commands::Relate {
+            pathway,
+            top_k,
+            threshold,
+            dry_run,
+        } => {
+            let mut config = ingest_config.relate.clone();
+            config.top_k = top_k.unwrap_or(config.top_k);
+            config.threshold = threshold.unwrap_or(config.threshold);
+            let result = client.relate_similar(&pathway, &config, dry_run).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&result)?;
+            } else {
+                for suggestion in &result.suggestions {
+                    println!(
+                        "{} -> {} ({:.3})",
+                        suggestion.source, suggestion.target, suggestion.similarity
+                    );
+                }
+                let verb = if dry_run { "Found" } else { "✓ Recorded" };
+                println!(
+                    "{} {} relations across {} nodes",
+                    verb,
+                    result.suggestions.len(),
+                    result.nodes_scanned
+                );
+            }
+        }
+
+        Commands::Topics { namespace, k } => {
+            let map = client.cluster(namespace, k).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&map)?;
+            } else {
+                for topic in &map.topics {
+                    println!(
+                        "{} ({} nodes, cohesion {:.3})",
+                        topic.label,
+                        topic.members.len(),
+                        topic.cohesion
+                    );
+                    for member in &topic.representatives {
+                        println!("  {}  {}", member.pathway, member.brief);
+                    }
+                }
+                println!(
+                    "{} topics across {} nodes",
+                    map.topics.len(),
+                    map.nodes_clustered
+                );
+            }
+        }
+
+        Commands::Duplicates {
+            namespace,
+            threshold,
+            merge,
+        } => {
+            let report = client.find_duplicates(namespace, threshold).await?;
+            let mut merged = 0;
+            if merge {
+                for group in &report.groups {
+                    merged += client.merge_duplicates(group).await?;
+                }
+            }
+
+            if cli.output.is_structured() {
+                cli.output.print(&report)?;
+            } else {
+                println!(
+                    "Scanned {} nodes, found {} duplicate groups",
+                    report.nodes_scanned,
+                    report.groups.len()
+                );
+                for group in &report.groups {
+                    let kind = if group.exact { "exact" } else { "near" };
+                    println!(
+                        "\n{} ({}, similarity >= {:.3})",
+                        group.canonical, kind, group.min_similarity
+                    );
+                    for duplicate in &group.duplicates {
+                        println!("  - {}", duplicate);
+                    }
+                }
+                if merge {
+                    println!("\n✓ Merged {} duplicates", merged);
+                }
+            }
+        }
+
+        Commands::Pack { pathway, file } => {
+            let manifest = client.pack(&pathway, &file).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&manifest)?;
+            } else {
+                println!(
+                    "✓ Packed {} nodes from {} into {}",
+                    manifest.node_count,
+                    pathway,
+                    file.display()
+                );
+            }
+        }
+
+        Commands::Install { file, at } => {
+            let manifest = client.install_pack(&file, at.as_deref()).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&manifest)?;
+            } else {
+                let target = at.unwrap_or_else(|| manifest.root.to_string());
+                println!(
+                    "✓ Installed {} nodes at {} (read-only)",
+                    manifest.node_count, target
+                );
+            }
+        }
+
+        Commands::Export { pathway, file } => {
+            let header = match &file {
+                Some(file) => {
+                    let writer = std::io::BufWriter::new(std::fs::File::create(file)?);
+                    client.export(&pathway, writer).await?
+                }
+                None => client.export(&pathway, std::io::stdout().lock()).await?,
+            };
+            // Standard output carries the archive itself
+            if let Some(file) = file {
+                if cli.output.is_structured() {
+                    cli.output.print(&header)?;
+                } else {
+                    println!(
+                        "✓ Exported {} nodes from {} into {}",
+                        header.node_count,
+                        pathway,
+                        file.display()
+                    );
+                }
+            }
+        }
+
+        Commands::Import { file, at } => {
+            let result = match file {
+                Some(file) => {
+                    let reader = std::io::BufReader::new(std::fs::File::open(file)?);
+                    client.import(reader, at.as_deref()).await?
+                }
+                None => {
+                    client
+                        .import(std::io::stdin().lock(), at.as_deref())
+                        .await?
+                }
+            };
+            if cli.output.is_structured() {
+                cli.output.print(&result)?;
+            } else {
+                println!(
+                    "✓ Imported {} nodes at {} ({} created, {} replaced)",
+                    result.header.node_count,
+                    result.root,
+                    result.nodes_created,
+                    result.nodes_replaced
+                );
+            }
+        }
+
+        Commands::Backup { target, list } => {
+            if list {
+                let names = client.backups(target.as_deref()).await?;
+                if cli.output.is_structured() {
+                    cli.output.print(&names)?;
+                } else {
+                    for name in names {
+                        println!("{}", name);
+                    }
+                }
+            } else {
+                let manifest = client.backup(target.as_deref()).await?;
+                if cli.output.is_structured() {
+                    cli.output.print(&manifest)?;
+                } else {
+                    println!(
+                        "✓ Backed up {} nodes to {}",
+                        manifest.node_count, manifest.name
+                    );
+                }
+            }
+        }
+
+        Commands::Restore { target, name } => {
+            let manifest = client.restore(target.as_deref(), name.as_deref()).await?;
+            if cli.output.is_structured() {
+                cli.output.print(&manifest)?;
+            } else {
+                println!(
+                    "✓ Restored {} nodes from {}",
+                    manifest.node_count, manifest.name
+                );
+            }
+        }
+
+        Commands::Uninstall { pathway } => {
+            let manifest = client.uninstall_pack(&pathway).await?;
+            println!(
+                "✓ Uninstalled pack of {} nodes from {}",
+                manifest.node_count, pathway
+            );
+        }
+
+        Commands::Stats => {
+            let stats = client.stats().await?;
+            if cli.output.is_structured() {
+                cli.output.print(&stats)?;
+            } else {
+                println!("Storage Statistics:");
+                println!("  Total nodes: {}", stats.total_nodes);
+                println!("  Total directories: {}", stats.total_directories);
+                println!("  Total size: {} bytes", stats.total_size_bytes);
+                for ns in &stats.namespaces {
+                    println!(
+                        "  {}: {} nodes, {} bytes",
+                        ns.namespace.as_str(),
+                        ns.node_count,
+                        ns.size_bytes
+                    );
+                }
+            }
+        }
+
+        Commands::Init => {
+            println!("✓ Storage initialized");
+        }
+
+        Commands::Config { .. } => unreachable!("handled before the client is created"),
+
+        #[cfg(feature = "tui")]
+        Commands::Browse => {
+            browse::run(&client).await?;
+        }
+
+        #[cfg(feature = "repl")]
+        Commands::Repl { record } => {
+            repl::run(&client, record).await?;
+        }
+    }
+
+    client.shutdown().await?;
+
+    Ok(())
+}
+
+/// Show which item an ingest is on, rewriting one line of stderr
+async fn print_progress(mut events: tokio::sync::mpsc::UnboundedReceiver<IngestEvent>) {
+    let (mut total, mut started) = (0, 0);
+    while let Some(event) = events.recv().await {
+        match event {
+            IngestEvent::Planned { items } => total += items,
+            IngestEvent::Started { source, .. } => {
+                started += 1;
+                eprint!("\r\x1b[K[{}/{}] {}", started, total, source);
+            }
+            _ => {}
+        }
+    }
+    if started > 0 {
+        eprint!("\r\x1b[K");
+    }
+}
+
+/// Print an ingest or sync summary, or every entry when `verbose`
+fn print_ingest_result(
+    result: &IngestResult,
+    verbose: bool,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    if output.is_structured() {
+        return output.print(result);
+    }
+
+    println!(
+        "✓ Created: {}, Updated: {}, Unchanged: {}, Skipped: {}, Errors: {}",
+        result.nodes_created,
+        result.nodes_updated,
+        result.nodes_unchanged,
+        result.count(IngestOutcome::Skipped),
+        result.errors.len()
+    );
+    if verbose {
+        println!();
+        for entry in &result.entries {
+            let outcome = format!("{:?}", entry.outcome).to_lowercase();
+            match (&entry.outcome, &entry.reason) {
+                (IngestOutcome::Created | IngestOutcome::Updated, _) => println!(
+                    "  {:<9} {} ({} bytes, ~{} tokens)",
+                    outcome, entry.source, entry.bytes, entry.tokens
+                ),
+                (_, Some(reason)) => println!("  {:<9} {}: {}", outcome, entry.source, reason),
+                (_, None) => println!("  {:<9} {}", outcome, entry.source),
+            }
+        }
+    } else if !result.errors.is_empty() {
+        println!("\nErrors:");
+        for err in &result.errors {
+            println!("  - {}", err);
+        }
+    }
+    Ok(())
+}
+
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=value, got {}", s))?;
+    Ok((name.trim().to_string(), value.to_string()))
+}
+
+fn parse_namespace(s: &str) -> Result<Namespace, String> {
+    Namespace::parse(s).ok_or_else(|| format!("unknown namespace: {}", s))
+}
+
+fn parse_kind(s: &str) -> Result<NodeKind, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_lowercase()))
+        .map_err(|_| format!("unknown node kind: {}", s))
+}
+
+fn parse_vector_name(s: &str) -> Result<VectorName, String> {
+    VectorName::parse(s).ok_or_else(|| format!("unknown vector: {}", s))
+}
+
+/// Load configuration from a file, or from the environment if none is given
+fn load_config(path: Option<&str>) -> a3s_context::Result<Config> {
+    match path {
+        Some(path) => Config::from_file(path),
+        None => Ok(Config::from_env()),
+    }
+}
+
+async fn config_command(
+    action: &ConfigAction,
+    config_path: Option<&str>,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    match action {
+        ConfigAction::Init { path, force } => {
+            if path.exists() && !force {
+                anyhow::bail!(
+                    "{} already exists (use --force to overwrite)",
+                    path.display()
+                );
+            }
+            std::fs::write(path, EXAMPLE_CONFIG)?;
+            println!("✓ Wrote {}", path.display());
+        }
+
+        ConfigAction::Check { offline } => {
+            let config = load_config(config_path)?;
+            #[cfg(feature = "http")]
+            let unreachable = if *offline {
+                Vec::new()
+            } else {
+                config.check_endpoints().await
+            };
+            #[cfg(not(feature = "http"))]
+            let unreachable = {
+                let _ = offline;
+                Vec::new()
+            };
+            let mut issues = config.validate();
+            issues.extend(unreachable);
+
+            if output.is_structured() {
+                output.print(&ConfigCheckOutput {
+                    issues: &issues,
+                    config: config.redacted(),
+                })?;
+            } else {
+                if issues.is_empty() {
+                    println!("✓ Configuration is valid");
+                }
+                for issue in &issues {
+                    let marker = match issue.severity {
+                        IssueSeverity::Error => "✗",
+                        IssueSeverity::Warning => "!",
+                    };
+                    println!("{} {}: {}", marker, issue.field, issue.message);
+                }
+                println!(
+                    "\nEffective settings:\n{}",
+                    serde_yaml::to_string(&config.redacted())?
+                );
+            }
+
+            let errors = issues
+                .iter()
+                .filter(|i| i.severity == IssueSeverity::Error)
+                .count();
+            if errors > 0 {
+                anyhow::bail!("configuration has {} error(s)", errors);
+            }
+        }
+    }
 
     Ok(())
 }
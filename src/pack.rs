@@ -0,0 +1,279 @@
+//! Context packs: portable, versioned bundles of a subtree
+//!
+//! A pack is a gzip-compressed JSON-lines file. The first line is the
+//! [`PackManifest`]; each following line is one node, including its
+//! digest and embedding, so installing a pack needs no provider calls.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::config::EmbeddingConfig;
+use crate::core::Node;
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+
+/// Newest pack format this build reads and the one it writes
+pub const PACK_FORMAT_VERSION: u32 = 1;
+
+/// Metadata key holding the manifest on an installed pack's root node
+pub const PACK_METADATA_KEY: &str = "pack";
+
+/// Description of a pack's contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackManifest {
+    pub format_version: u32,
+    /// Pathway the pack was exported from
+    pub root: Pathway,
+    pub node_count: u64,
+    pub embedding_provider: String,
+    pub embedding_model: String,
+    pub embedding_dimension: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A pack loaded in memory
+#[derive(Debug, Clone)]
+pub struct Pack {
+    pub manifest: PackManifest,
+    pub nodes: Vec<Node>,
+}
+
+impl Pack {
+    /// Collect a subtree, including its root node if one exists
+    pub async fn export(
+        storage: &dyn StorageBackend,
+        root: &Pathway,
+        embedding: &EmbeddingConfig,
+    ) -> Result<Self> {
+        let mut nodes = Vec::new();
+        if let Ok(node) = storage.get(root).await {
+            nodes.push(node);
+        }
+        nodes.extend(storage.get_children(root, usize::MAX).await?);
+        nodes.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+
+        if nodes.is_empty() {
+            return Err(A3SError::NodeNotFound(root.to_string()));
+        }
+
+        Ok(Self {
+            manifest: PackManifest {
+                format_version: PACK_FORMAT_VERSION,
+                root: root.clone(),
+                node_count: nodes.len() as u64,
+                embedding_provider: embedding.provider.clone(),
+                embedding_model: embedding.model.clone(),
+                embedding_dimension: embedding.dimension,
+                created_at: Utc::now(),
+            },
+            nodes,
+        })
+    }
+
+    /// Write the pack to a file
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(GzEncoder::new(file, Compression::default()));
+
+        serde_json::to_writer(&mut writer, &self.manifest)?;
+        writer.write_all(b"\n")?;
+        for node in &self.nodes {
+            serde_json::to_writer(&mut writer, node)?;
+            writer.write_all(b"\n")?;
+        }
+
+        let encoder = writer.into_inner().map_err(|e| e.into_error())?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Read a pack from a file
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        let mut lines = reader.lines();
+
+        let header = lines
+            .next()
+            .ok_or_else(|| A3SError::Pack(format!("{} is empty", path.display())))??;
+        let manifest: PackManifest = serde_json::from_str(&header)
+            .map_err(|e| A3SError::Pack(format!("Invalid manifest: {}", e)))?;
+        if manifest.format_version > PACK_FORMAT_VERSION {
+            return Err(A3SError::Pack(format!(
+                "Pack format {} is newer than supported format {}",
+                manifest.format_version, PACK_FORMAT_VERSION
+            )));
+        }
+
+        let mut nodes = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let node: Node = serde_json::from_str(&line)
+                .map_err(|e| A3SError::Pack(format!("Invalid node: {}", e)))?;
+            nodes.push(node);
+        }
+
+        if nodes.len() as u64 != manifest.node_count {
+            return Err(A3SError::Pack(format!(
+                "Manifest lists {} nodes, pack contains {}",
+                manifest.node_count,
+                nodes.len()
+            )));
+        }
+
+        Ok(Self { manifest, nodes })
+    }
+
+    /// Check that the pack's embeddings are searchable with `embedding`
+    pub fn check_compatible(&self, embedding: &EmbeddingConfig) -> Result<()> {
+        let manifest = &self.manifest;
+        if manifest.embedding_dimension != embedding.dimension
+            || manifest.embedding_model != embedding.model
+        {
+            return Err(A3SError::Pack(format!(
+                "Pack was embedded with {} ({} dimensions), store uses {} ({} dimensions)",
+                manifest.embedding_model,
+                manifest.embedding_dimension,
+                embedding.model,
+                embedding.dimension
+            )));
+        }
+        Ok(())
+    }
+
+    /// Move the nodes under `target`, marking its root node with the manifest
+    pub fn rebase(self, target: &Pathway) -> Result<Vec<Node>> {
+        let depth = self.manifest.root.depth();
+        let manifest = serde_json::to_value(&self.manifest)?;
+
+        let mut nodes: Vec<Node> = self
+            .nodes
+            .into_iter()
+            .map(|mut node| {
+                let mut segments = target.segments().to_vec();
+                segments.extend_from_slice(&node.pathway.segments()[depth..]);
+                node.pathway = Pathway::new(target.namespace(), segments);
+                node.id = uuid::Uuid::new_v4();
+                node
+            })
+            .collect();
+
+        match nodes.iter_mut().find(|n| n.pathway == *target) {
+            Some(root) => {
+                root.metadata
+                    .custom
+                    .insert(PACK_METADATA_KEY.to_string(), manifest);
+            }
+            None => {
+                let mut root = Node::directory(target.clone());
+                root.metadata
+                    .custom
+                    .insert(PACK_METADATA_KEY.to_string(), manifest);
+                nodes.insert(0, root);
+            }
+        }
+
+        Ok(nodes)
+    }
+}
+
+/// Check if a node is the root of an installed pack
+pub fn is_pack_root(node: &Node) -> bool {
+    node.metadata.custom.contains_key(PACK_METADATA_KEY)
+}
+
+/// Manifest of the pack installed at a node, if any
+pub fn installed_manifest(node: &Node) -> Option<PackManifest> {
+    node.metadata
+        .custom
+        .get(PACK_METADATA_KEY)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_pack_round_trip_and_rebase() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let mut node = Node::new(
+            Pathway::parse("a3s://knowledge/docs/auth/tokens").unwrap(),
+            NodeKind::Document,
+            "Tokens expire after one hour".to_string(),
+        );
+        node.embedding = vec![1.0, 0.0];
+        storage.put(&node).await.unwrap();
+
+        let embedding = EmbeddingConfig {
+            provider: "mock".to_string(),
+            model: "mock".to_string(),
+            dimension: 2,
+            ..Default::default()
+        };
+        let root = Pathway::parse("a3s://knowledge/docs").unwrap();
+        let pack = Pack::export(&storage, &root, &embedding).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("docs.a3s");
+        pack.write_to(&path).unwrap();
+
+        let pack = Pack::read_from(&path).unwrap();
+        assert_eq!(pack.manifest.node_count, 1);
+        assert_eq!(pack.nodes[0].embedding, vec![1.0, 0.0]);
+        pack.check_compatible(&embedding).unwrap();
+
+        let other = EmbeddingConfig {
+            dimension: 3,
+            ..embedding.clone()
+        };
+        assert!(matches!(
+            pack.check_compatible(&other),
+            Err(A3SError::Pack(_))
+        ));
+
+        let target = Pathway::parse("a3s://knowledge/shared/docs").unwrap();
+        let nodes = pack.rebase(&target).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert!(installed_manifest(&nodes[0]).is_some());
+        assert_eq!(
+            nodes[1].pathway.to_string(),
+            "a3s://knowledge/shared/docs/auth/tokens"
+        );
+    }
+
+    #[test]
+    fn test_read_rejects_newer_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("future.a3s");
+        let pack = Pack {
+            manifest: PackManifest {
+                format_version: PACK_FORMAT_VERSION + 1,
+                root: Pathway::parse("a3s://knowledge").unwrap(),
+                node_count: 0,
+                embedding_provider: "mock".to_string(),
+                embedding_model: "mock".to_string(),
+                embedding_dimension: 2,
+                created_at: Utc::now(),
+            },
+            nodes: Vec::new(),
+        };
+        pack.write_to(&path).unwrap();
+
+        let err = Pack::read_from(&path).unwrap_err();
+        assert!(err.to_string().contains("newer than supported"));
+    }
+}
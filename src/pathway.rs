@@ -7,14 +7,16 @@
 //! - `a3s://memory/user/preferences`
 //! - `a3s://capability/tools/search`
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 
 use crate::core::Namespace;
 use crate::error::{A3SError, Result};
 
 /// A pathway represents a unique address to a node in A3S
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord)]
+///
+/// Serializes as its `a3s://` URI string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Pathway {
     namespace: Namespace,
     segments: Vec<String>,
@@ -129,6 +131,20 @@ impl Pathway {
             .all(|(a, b)| a == b)
     }
 
+    /// The pathway with prefix `from` replaced by `to`, if `from` is a
+    /// prefix of it
+    pub fn rebase(&self, from: &Self, to: &Self) -> Option<Self> {
+        if !from.is_prefix_of(self) {
+            return None;
+        }
+        let mut segments = to.segments.clone();
+        segments.extend_from_slice(&self.segments[from.segments.len()..]);
+        Some(Self {
+            namespace: to.namespace,
+            segments,
+        })
+    }
+
     /// Check if this is a root namespace pathway
     pub fn is_root(&self) -> bool {
         self.segments.is_empty()
@@ -178,6 +194,35 @@ impl fmt::Display for Pathway {
     }
 }
 
+impl Serialize for Pathway {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pathway {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        // Stores written before pathways serialized as URIs use the struct form
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Uri(String),
+            Parts {
+                namespace: Namespace,
+                segments: Vec<String>,
+            },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Uri(s) => Self::parse(&s).map_err(serde::de::Error::custom),
+            Repr::Parts {
+                namespace,
+                segments,
+            } => Ok(Self::new(namespace, segments)),
+        }
+    }
+}
+
 impl TryFrom<&str> for Pathway {
     type Error = A3SError;
 
@@ -286,6 +331,23 @@ mod tests {
         assert_eq!(c.namespace(), Namespace::Capability);
     }
 
+    #[test]
+    fn test_pathway_serde_uri() {
+        let p = Pathway::parse("a3s://knowledge/docs/api").unwrap();
+        let json = serde_json::to_string(&p).unwrap();
+        assert_eq!(json, "\"a3s://knowledge/docs/api\"");
+
+        let back: Pathway = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, p);
+    }
+
+    #[test]
+    fn test_pathway_deserialize_struct_form() {
+        let json = r#"{"namespace":"memory","segments":["user","prefs"]}"#;
+        let p: Pathway = serde_json::from_str(json).unwrap();
+        assert_eq!(p.to_string(), "a3s://memory/user/prefs");
+    }
+
     #[test]
     fn test_pathway_root_constructor() {
         let root = Pathway::root(Namespace::Knowledge);
@@ -293,4 +355,17 @@ mod tests {
         assert!(root.is_root());
         assert_eq!(root.depth(), 0);
     }
+
+    #[test]
+    fn test_pathway_rebase() {
+        let from = Pathway::parse("a3s://knowledge/docs").unwrap();
+        let to = Pathway::parse("a3s://memory/archive/docs").unwrap();
+        let p = Pathway::parse("a3s://knowledge/docs/api/auth.md").unwrap();
+        assert_eq!(
+            p.rebase(&from, &to).unwrap().to_string(),
+            "a3s://memory/archive/docs/api/auth.md"
+        );
+        assert_eq!(from.rebase(&from, &to), Some(to.clone()));
+        assert!(to.rebase(&from, &to).is_none());
+    }
 }
@@ -0,0 +1,210 @@
+//! Ingest pipeline stages
+//!
+//! Every ingested node passes through the same stages in order:
+//!
+//! ```text
+//! extract → transform → chunk → digest → embed → store
+//! ```
+//!
+//! `extract` reads the source into a node, `transform` applies write
+//! policies and detects the language, `chunk` splits long content, `digest`
+//! and `embed` derive summaries and vectors (digest first, since summaries
+//! are embedded too), and `store` writes the node and its chunks. `embed`
+//! covers the chunks as they stand when it starts, and shares embedding
+//! requests with the items ingested alongside, so an item may wait for
+//! later ones before it reaches `store`. Custom
+//! stages registered before or after a built-in stage run in between, so a
+//! host can add e.g. redaction before `embed` without forking the
+//! processor.
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+use crate::chunk::Chunk;
+use crate::core::Node;
+use crate::error::{A3SError, Result};
+
+/// Built-in ingest stages, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Extract,
+    Transform,
+    Chunk,
+    Digest,
+    Embed,
+    Store,
+}
+
+impl Stage {
+    /// Every built-in stage, in order
+    pub const ALL: [Stage; 6] = [
+        Stage::Extract,
+        Stage::Transform,
+        Stage::Chunk,
+        Stage::Digest,
+        Stage::Embed,
+        Stage::Store,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Extract => "extract",
+            Stage::Transform => "transform",
+            Stage::Chunk => "chunk",
+            Stage::Digest => "digest",
+            Stage::Embed => "embed",
+            Stage::Store => "store",
+        }
+    }
+}
+
+/// Where a custom stage runs relative to a built-in one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StagePosition {
+    Before(Stage),
+    After(Stage),
+}
+
+/// A node on its way through the pipeline
+#[derive(Debug, Clone)]
+pub struct IngestItem {
+    pub node: Node,
+    /// Chunks the node is split into, filled in by `chunk`
+    pub chunks: Vec<Chunk>,
+    /// Whether the node is new to the store
+    pub created: bool,
+    /// Chunks stored for the node before this ingest
+    pub(crate) previous_chunks: usize,
+}
+
+impl IngestItem {
+    pub(crate) fn new(node: Node, created: bool) -> Self {
+        Self {
+            node,
+            chunks: Vec::new(),
+            created,
+            previous_chunks: 0,
+        }
+    }
+}
+
+/// Custom step run on every ingested node
+#[async_trait]
+pub trait PipelineStage: Send + Sync {
+    /// Name shown in logs
+    fn name(&self) -> &str;
+
+    /// Inspect or change the node; an error aborts its ingest
+    async fn process(&self, item: &mut IngestItem) -> Result<()>;
+}
+
+/// Custom stages and where each runs
+///
+/// Stages at the same position run in registration order.
+#[derive(Default)]
+pub struct StageRegistry {
+    stages: RwLock<Vec<(StagePosition, Arc<dyn PipelineStage>)>>,
+}
+
+impl StageRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a stage at a position
+    ///
+    /// Nothing runs before `extract`, since no node exists yet.
+    pub fn register(&self, position: StagePosition, stage: Arc<dyn PipelineStage>) -> Result<()> {
+        if position == StagePosition::Before(Stage::Extract) {
+            return Err(A3SError::Config(format!(
+                "Stage {} cannot run before extract",
+                stage.name()
+            )));
+        }
+        self.stages.write().push((position, stage));
+        Ok(())
+    }
+
+    /// Number of registered stages
+    pub fn len(&self) -> usize {
+        self.stages.read().len()
+    }
+
+    /// Check if no stages are registered
+    pub fn is_empty(&self) -> bool {
+        self.stages.read().is_empty()
+    }
+
+    /// Run the stages registered at a position
+    pub async fn run(&self, position: StagePosition, item: &mut IngestItem) -> Result<()> {
+        let stages: Vec<_> = self
+            .stages
+            .read()
+            .iter()
+            .filter(|(at, _)| *at == position)
+            .map(|(_, stage)| stage.clone())
+            .collect();
+
+        for stage in stages {
+            stage.process(item).await?;
+            tracing::trace!(stage = stage.name(), pathway = %item.node.pathway, "custom stage ran");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+    use crate::pathway::Pathway;
+
+    struct Append(&'static str);
+
+    #[async_trait]
+    impl PipelineStage for Append {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn process(&self, item: &mut IngestItem) -> Result<()> {
+            item.node.content.push_str(self.0);
+            Ok(())
+        }
+    }
+
+    fn item() -> IngestItem {
+        let pathway = Pathway::parse("a3s://knowledge/test").unwrap();
+        IngestItem::new(Node::new(pathway, NodeKind::Document, String::new()), true)
+    }
+
+    #[tokio::test]
+    async fn test_stages_run_at_their_position_in_order() {
+        let registry = StageRegistry::new();
+        let before_embed = StagePosition::Before(Stage::Embed);
+        registry
+            .register(before_embed, Arc::new(Append("a")))
+            .unwrap();
+        registry
+            .register(StagePosition::After(Stage::Store), Arc::new(Append("x")))
+            .unwrap();
+        registry
+            .register(before_embed, Arc::new(Append("b")))
+            .unwrap();
+        assert_eq!(registry.len(), 3);
+
+        let mut item = item();
+        registry.run(before_embed, &mut item).await.unwrap();
+        assert_eq!(item.node.content, "ab");
+    }
+
+    #[test]
+    fn test_nothing_runs_before_extract() {
+        let registry = StageRegistry::new();
+        let result =
+            registry.register(StagePosition::Before(Stage::Extract), Arc::new(Append("a")));
+        assert!(matches!(result, Err(A3SError::Config(_))));
+        assert!(registry.is_empty());
+    }
+}
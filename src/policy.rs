@@ -0,0 +1,255 @@
+//! Content policy hooks applied to nodes before they are written
+//!
+//! Policies let the host application enforce organizational rules (size limits,
+//! forbidden content, redaction) on everything agents try to store: writes,
+//! ingested documents and each of their chunks, metadata updates, moves and
+//! copies, suggested relations, duplicate merges, saved queries, views and
+//! their results, sync cursors, and committed sessions. After the last
+//! policy, the node's metadata is checked against the registered
+//! [`MetadataSchemas`].
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use regex::Regex;
+use std::sync::Arc;
+
+use crate::core::Node;
+use crate::error::{A3SError, Result};
+use crate::schema::MetadataSchemas;
+
+/// Outcome of a policy check
+#[derive(Debug, Clone)]
+pub enum PolicyDecision {
+    /// Store the node unchanged
+    Allow,
+    /// Reject the write with a reason
+    Deny(String),
+    /// Store the given node instead of the original; it must keep the
+    /// original's pathway
+    Transform(Box<Node>),
+}
+
+/// Policy evaluated on every node before it is written
+#[async_trait]
+pub trait WritePolicy: Send + Sync {
+    /// Inspect a node and decide whether it may be stored
+    async fn check(&self, node: &Node) -> Result<PolicyDecision>;
+}
+
+/// Ordered set of write policies
+///
+/// Policies run in registration order; a transform is visible to later
+/// policies, and the first denial aborts the write. The node that comes out
+/// of the chain must satisfy the metadata schemas that apply to it.
+#[derive(Default)]
+pub struct PolicyChain {
+    policies: RwLock<Vec<Arc<dyn WritePolicy>>>,
+    schemas: MetadataSchemas,
+}
+
+impl PolicyChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a policy at the end of the chain
+    pub fn push(&self, policy: Arc<dyn WritePolicy>) {
+        self.policies.write().push(policy);
+    }
+
+    /// Metadata schemas checked after the policies
+    pub fn schemas(&self) -> &MetadataSchemas {
+        &self.schemas
+    }
+
+    /// Number of registered policies
+    pub fn len(&self) -> usize {
+        self.policies.read().len()
+    }
+
+    /// Check if no policies are registered
+    pub fn is_empty(&self) -> bool {
+        self.policies.read().is_empty()
+    }
+
+    /// Run all policies against a node, returning the node to store
+    pub async fn apply(&self, node: Node) -> Result<Node> {
+        let policies = self.policies.read().clone();
+
+        let mut node = node;
+        for policy in policies {
+            match policy.check(&node).await? {
+                PolicyDecision::Allow => {}
+                PolicyDecision::Deny(reason) => {
+                    return Err(A3SError::PolicyViolation(format!(
+                        "{}: {}",
+                        node.pathway, reason
+                    )));
+                }
+                PolicyDecision::Transform(transformed) => {
+                    if transformed.pathway != node.pathway {
+                        return Err(A3SError::PolicyViolation(format!(
+                            "{}: a policy may not move the node to {}",
+                            node.pathway, transformed.pathway
+                        )));
+                    }
+                    node = *transformed;
+                }
+            }
+        }
+
+        self.schemas.validate(&node)?;
+        Ok(node)
+    }
+}
+
+/// Rejects nodes whose content exceeds a byte limit
+pub struct MaxSizePolicy {
+    max_bytes: u64,
+}
+
+impl MaxSizePolicy {
+    pub fn new(max_bytes: u64) -> Self {
+        Self { max_bytes }
+    }
+}
+
+#[async_trait]
+impl WritePolicy for MaxSizePolicy {
+    async fn check(&self, node: &Node) -> Result<PolicyDecision> {
+        if node.size() > self.max_bytes {
+            return Ok(PolicyDecision::Deny(format!(
+                "content is {} bytes, limit is {}",
+                node.size(),
+                self.max_bytes
+            )));
+        }
+        Ok(PolicyDecision::Allow)
+    }
+}
+
+/// Rejects nodes whose content matches any forbidden pattern
+pub struct ForbiddenContentPolicy {
+    patterns: Vec<Regex>,
+}
+
+impl ForbiddenContentPolicy {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p.as_ref())
+                    .map_err(|e| A3SError::Config(format!("Invalid policy pattern: {}", e)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { patterns })
+    }
+}
+
+#[async_trait]
+impl WritePolicy for ForbiddenContentPolicy {
+    async fn check(&self, node: &Node) -> Result<PolicyDecision> {
+        for pattern in &self.patterns {
+            if pattern.is_match(&node.content) {
+                return Ok(PolicyDecision::Deny(format!(
+                    "content matches forbidden pattern `{}`",
+                    pattern.as_str()
+                )));
+            }
+        }
+        Ok(PolicyDecision::Allow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+    use crate::pathway::Pathway;
+
+    struct UppercasePolicy;
+
+    #[async_trait]
+    impl WritePolicy for UppercasePolicy {
+        async fn check(&self, node: &Node) -> Result<PolicyDecision> {
+            let mut node = node.clone();
+            node.content = node.content.to_uppercase();
+            Ok(PolicyDecision::Transform(Box::new(node)))
+        }
+    }
+
+    struct RedirectPolicy;
+
+    #[async_trait]
+    impl WritePolicy for RedirectPolicy {
+        async fn check(&self, node: &Node) -> Result<PolicyDecision> {
+            let mut node = node.clone();
+            node.pathway = Pathway::parse("a3s://capability/planted").unwrap();
+            Ok(PolicyDecision::Transform(Box::new(node)))
+        }
+    }
+
+    fn test_node(content: &str) -> Node {
+        let pathway = Pathway::parse("a3s://knowledge/test").unwrap();
+        Node::new(pathway, NodeKind::Document, content.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_empty_chain_allows() {
+        let chain = PolicyChain::new();
+        assert!(chain.is_empty());
+
+        let node = chain.apply(test_node("hello")).await.unwrap();
+        assert_eq!(node.content, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_max_size_policy_denies() {
+        let chain = PolicyChain::new();
+        chain.push(Arc::new(MaxSizePolicy::new(4)));
+
+        assert!(chain.apply(test_node("tiny")).await.is_ok());
+
+        let err = chain.apply(test_node("too large")).await.unwrap_err();
+        assert!(matches!(err, A3SError::PolicyViolation(_)));
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_content_policy() {
+        let chain = PolicyChain::new();
+        chain.push(Arc::new(
+            ForbiddenContentPolicy::new(&["(?i)api[_-]?key"]).unwrap(),
+        ));
+
+        assert!(chain.apply(test_node("public notes")).await.is_ok());
+        assert!(chain.apply(test_node("my API_KEY is 123")).await.is_err());
+    }
+
+    #[test]
+    fn test_forbidden_content_policy_invalid_pattern() {
+        assert!(ForbiddenContentPolicy::new(&["("]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transform_visible_to_later_policies() {
+        let chain = PolicyChain::new();
+        chain.push(Arc::new(UppercasePolicy));
+        chain.push(Arc::new(ForbiddenContentPolicy::new(&["SECRET"]).unwrap()));
+        assert_eq!(chain.len(), 2);
+
+        let node = chain.apply(test_node("public")).await.unwrap();
+        assert_eq!(node.content, "PUBLIC");
+
+        assert!(chain.apply(test_node("secret")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transform_cannot_change_pathway() {
+        let chain = PolicyChain::new();
+        chain.push(Arc::new(RedirectPolicy));
+
+        let err = chain.apply(test_node("hello")).await.unwrap_err();
+        assert!(matches!(err, A3SError::PolicyViolation(_)));
+    }
+}
@@ -0,0 +1,227 @@
+//! Relations suggested by embedding similarity
+//!
+//! A node is compared by embedding with the rest of its namespace, and its
+//! most similar nodes at or above a threshold are recorded as
+//! [`RelationKind::RelatedTo`] relations whose reason carries the
+//! similarity. This builds a graph between related content without an
+//! LLM, either for each node as it is written (`ingest.relate.auto`) or
+//! over a subtree on demand. Chunks are left out on both sides, since
+//! their documents stand for them, and a node is never related twice to
+//! the same target.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::ChunkInfo;
+use crate::config::RelateConfig;
+use crate::core::{Node, RelationKind};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::policy::PolicyChain;
+use crate::storage::StorageBackend;
+
+/// Start of the reason on relations recorded from similarity
+pub const SIMILARITY_REASON: &str = "embedding similarity";
+
+/// Candidates fetched per relation wanted, since some are chunks or
+/// already related
+const OVERSAMPLE: usize = 4;
+
+/// A relation to record from `source` to `target`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestedRelation {
+    pub source: Pathway,
+    pub target: Pathway,
+    pub similarity: f32,
+}
+
+/// Outcome of relating the nodes of a subtree
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelateResult {
+    /// Embedded, non-chunk nodes compared with their namespace
+    pub nodes_scanned: usize,
+    /// Relations found, recorded unless this was a dry run
+    pub suggestions: Vec<SuggestedRelation>,
+}
+
+/// Most similar nodes in `node`'s namespace it is not yet related to,
+/// most similar first
+pub async fn suggest(
+    storage: &dyn StorageBackend,
+    node: &Node,
+    config: &RelateConfig,
+) -> Result<Vec<SuggestedRelation>> {
+    if !relatable(node) || config.top_k == 0 {
+        return Ok(Vec::new());
+    }
+    let mut seen: HashSet<&Pathway> = node.relations.iter().map(|r| &r.target).collect();
+    seen.insert(&node.pathway);
+
+    let limit = config.top_k * OVERSAMPLE + seen.len();
+    let hits = storage
+        .search_vector(
+            &node.embedding,
+            Some(node.pathway.namespace()),
+            limit,
+            config.threshold,
+        )
+        .await?;
+
+    let mut suggestions = Vec::new();
+    // Named vectors can match a node more than once
+    let mut taken = HashSet::new();
+    for (pathway, similarity) in hits {
+        if suggestions.len() == config.top_k {
+            break;
+        }
+        if seen.contains(&pathway) || taken.contains(&pathway) {
+            continue;
+        }
+        match storage.get(&pathway).await {
+            Ok(target) if relatable(&target) => {}
+            Ok(_) | Err(A3SError::NodeNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        }
+        taken.insert(pathway.clone());
+        suggestions.push(SuggestedRelation {
+            source: node.pathway.clone(),
+            target: pathway,
+            similarity,
+        });
+    }
+    Ok(suggestions)
+}
+
+/// Record `suggestions` as relations on `node`
+pub fn apply(node: &mut Node, suggestions: &[SuggestedRelation]) {
+    for suggestion in suggestions {
+        node.add_relation(
+            suggestion.target.clone(),
+            RelationKind::RelatedTo,
+            format!("{} {:.3}", SIMILARITY_REASON, suggestion.similarity),
+        );
+    }
+}
+
+/// Relate every node at and below `root` to its most similar nodes,
+/// recording the relations unless `dry_run`
+///
+/// Nodes in read-only mounts are compared but left unchanged, and each
+/// node written passes the write policies first.
+pub async fn relate_subtree(
+    storage: &dyn StorageBackend,
+    policies: &PolicyChain,
+    root: &Pathway,
+    config: &RelateConfig,
+    dry_run: bool,
+) -> Result<RelateResult> {
+    storage.warm(root).await?;
+    let mut nodes = Vec::new();
+    match storage.get(root).await {
+        Ok(node) => nodes.push(node),
+        Err(A3SError::NodeNotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    nodes.extend(storage.get_children(root, usize::MAX).await?);
+    nodes.retain(relatable);
+    nodes.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+
+    let mut result = RelateResult {
+        nodes_scanned: nodes.len(),
+        suggestions: Vec::new(),
+    };
+    for mut node in nodes {
+        let suggestions = suggest(storage, &node, config).await?;
+        if suggestions.is_empty() {
+            continue;
+        }
+        if !dry_run {
+            apply(&mut node, &suggestions);
+            let node = policies.apply(node).await?;
+            match storage.put(&node).await {
+                Err(A3SError::ReadOnly(_)) => continue,
+                result => result?,
+            }
+        }
+        result.suggestions.extend(suggestions);
+    }
+    Ok(result)
+}
+
+/// Embedded content other than a chunk
+fn relatable(node: &Node) -> bool {
+    !node.is_directory && !node.embedding.is_empty() && ChunkInfo::of(node).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::storage::MemoryStorage;
+
+    async fn put(storage: &MemoryStorage, pathway: &str, embedding: Vec<f32>) {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            format!("About {}", pathway),
+        );
+        node.embedding = embedding;
+        storage.put(&node).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_relate_subtree() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        put(
+            &storage,
+            "a3s://knowledge/docs/deploy.md",
+            vec![1.0, 0.0, 0.0],
+        )
+        .await;
+        put(
+            &storage,
+            "a3s://knowledge/docs/release.md",
+            vec![0.9, 0.1, 0.0],
+        )
+        .await;
+        put(
+            &storage,
+            "a3s://knowledge/docs/billing.md",
+            vec![0.0, 0.0, 1.0],
+        )
+        .await;
+        // Same direction, other namespace
+        put(&storage, "a3s://memory/alice/deploys", vec![1.0, 0.0, 0.0]).await;
+
+        let config = RelateConfig::default();
+        let policies = PolicyChain::new();
+        let root = Pathway::parse("a3s://knowledge/docs").unwrap();
+        let dry = relate_subtree(&storage, &policies, &root, &config, true)
+            .await
+            .unwrap();
+        assert_eq!(dry.nodes_scanned, 3);
+        assert_eq!(dry.suggestions.len(), 2);
+        let deploy = Pathway::parse("a3s://knowledge/docs/deploy.md").unwrap();
+        assert!(storage.get(&deploy).await.unwrap().relations.is_empty());
+
+        relate_subtree(&storage, &policies, &root, &config, false)
+            .await
+            .unwrap();
+        let node = storage.get(&deploy).await.unwrap();
+        assert_eq!(node.relations.len(), 1);
+        assert_eq!(node.relations[0].kind, RelationKind::RelatedTo);
+        assert_eq!(
+            node.relations[0].target.to_string(),
+            "a3s://knowledge/docs/release.md"
+        );
+        assert!(node.relations[0].reason.starts_with(SIMILARITY_REASON));
+
+        // Existing relations are not recorded again
+        let again = relate_subtree(&storage, &policies, &root, &config, false)
+            .await
+            .unwrap();
+        assert!(again.suggestions.is_empty());
+    }
+}
@@ -0,0 +1,345 @@
+//! Moving and copying nodes to another pathway
+//!
+//! Relocated nodes are written under their new pathway, which also files
+//! their embeddings under it in the vector index, and chunks go with their
+//! document. Moving redirects relations anywhere in the store that pointed
+//! into the moved subtree, so reorganizing a tree keeps its links; a copy
+//! only redirects relations between the copied nodes themselves. Every
+//! node written, including those whose relations were redirected, passes
+//! the write policies first.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::{ChunkInfo, CHUNK_KEY};
+use crate::core::{Namespace, Node};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::policy::PolicyChain;
+use crate::storage::StorageBackend;
+
+/// Outcome of a move or copy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RelocateResult {
+    /// Nodes written under the new pathway
+    pub nodes: usize,
+    /// Relations redirected to a new pathway
+    pub relations_updated: usize,
+}
+
+/// Move the node at `from` and everything below it to `to`
+pub async fn move_node(
+    storage: &dyn StorageBackend,
+    policies: &PolicyChain,
+    from: &Pathway,
+    to: &Pathway,
+) -> Result<RelocateResult> {
+    check_target(storage, from, to).await?;
+    let nodes = sources(storage, from, true).await?;
+    let moved: HashSet<Pathway> = nodes.iter().map(|node| node.pathway.clone()).collect();
+
+    let mut result = RelocateResult::default();
+    let mut relocated = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        result.relations_updated += retarget(&mut node, &moved, from, to);
+        relocated.push(policies.apply(rebase(node, from, to)).await?);
+    }
+
+    // Find incoming relations in every namespace, and check the nodes
+    // holding them, before anything is written
+    let mut incoming = Vec::new();
+    for namespace in Namespace::ALL {
+        for mut node in storage
+            .get_children(&Pathway::root(namespace), usize::MAX)
+            .await?
+        {
+            if moved.contains(&node.pathway) {
+                continue;
+            }
+            let changed = retarget(&mut node, &moved, from, to);
+            if changed > 0 {
+                incoming.push((policies.apply(node).await?, changed));
+            }
+        }
+    }
+
+    result.nodes = relocated.len();
+    storage.put_batch(&relocated).await?;
+    storage.remove(from, true).await?;
+    for (node, changed) in incoming {
+        // Mounted stores cannot be rewritten and keep their links
+        match storage.put(&node).await {
+            Err(A3SError::ReadOnly(_)) => {}
+            result => result?,
+        }
+        result.relations_updated += changed;
+    }
+    Ok(result)
+}
+
+/// Copy the node at `from` to `to`, with everything below it if `recursive`
+///
+/// A document's chunks are copied with it either way. Copies are created
+/// and updated at `now`.
+pub async fn copy_node(
+    storage: &dyn StorageBackend,
+    policies: &PolicyChain,
+    from: &Pathway,
+    to: &Pathway,
+    recursive: bool,
+    now: DateTime<Utc>,
+) -> Result<RelocateResult> {
+    check_target(storage, from, to).await?;
+    let nodes = sources(storage, from, recursive).await?;
+    let copied: HashSet<Pathway> = nodes.iter().map(|node| node.pathway.clone()).collect();
+
+    let mut result = RelocateResult::default();
+    let mut copies = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        result.relations_updated += retarget(&mut node, &copied, from, to);
+        let mut copy = rebase(node, from, to);
+        copy.id = uuid::Uuid::new_v4();
+        copy.created_at = now;
+        copy.updated_at = now;
+        copies.push(policies.apply(copy).await?);
+    }
+    result.nodes = copies.len();
+    storage.put_batch(&copies).await?;
+    Ok(result)
+}
+
+/// Fail unless `to` is free and outside `from`
+async fn check_target(storage: &dyn StorageBackend, from: &Pathway, to: &Pathway) -> Result<()> {
+    if from.is_root() || to.is_root() {
+        return Err(A3SError::InvalidPathway(format!(
+            "Cannot move or copy a namespace root: {} to {}",
+            from, to
+        )));
+    }
+    if from.is_prefix_of(to) {
+        return Err(A3SError::InvalidPathway(format!(
+            "Cannot move or copy {} into itself: {}",
+            from, to
+        )));
+    }
+    // Lazily loading backends may not have read the target yet
+    storage.warm(to).await?;
+    if storage.exists(to).await? || !storage.get_children(to, 1).await?.is_empty() {
+        return Err(A3SError::AlreadyExists(to.to_string()));
+    }
+    Ok(())
+}
+
+/// The node at `from`, and the nodes below it or only its chunks
+async fn sources(
+    storage: &dyn StorageBackend,
+    from: &Pathway,
+    recursive: bool,
+) -> Result<Vec<Node>> {
+    storage.warm(from).await?;
+    let mut nodes = Vec::new();
+    match storage.get(from).await {
+        Ok(node) => nodes.push(node),
+        Err(A3SError::NodeNotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    let below = storage.get_children(from, usize::MAX).await?;
+    if recursive {
+        nodes.extend(below);
+    } else if !nodes.is_empty() {
+        nodes.extend(
+            below
+                .into_iter()
+                .filter(|node| ChunkInfo::of(node).is_some_and(|info| info.parent == *from)),
+        );
+    }
+
+    if nodes.is_empty() {
+        return Err(A3SError::NodeNotFound(from.to_string()));
+    }
+    Ok(nodes)
+}
+
+/// Place a node under `to`, along with the parent recorded on a chunk
+pub(crate) fn rebase(mut node: Node, from: &Pathway, to: &Pathway) -> Node {
+    if let Some(pathway) = node.pathway.rebase(from, to) {
+        node.pathway = pathway;
+    }
+    if let Some(mut info) = ChunkInfo::of(&node) {
+        if let Some(parent) = info.parent.rebase(from, to) {
+            info.parent = parent;
+            if let Ok(value) = serde_json::to_value(info) {
+                node.metadata.custom.insert(CHUNK_KEY.to_string(), value);
+            }
+        }
+    }
+    node
+}
+
+/// Point a node's relations to `relocated` nodes at their new pathways,
+/// returning how many changed
+fn retarget(node: &mut Node, relocated: &HashSet<Pathway>, from: &Pathway, to: &Pathway) -> usize {
+    let mut changed = 0;
+    for relation in &mut node.relations {
+        if !relocated.contains(&relation.target) {
+            continue;
+        }
+        if let Some(target) = relation.target.rebase(from, to) {
+            relation.target = target;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::{NodeKind, RelationKind};
+    use crate::storage::MemoryStorage;
+
+    fn pathway(s: &str) -> Pathway {
+        Pathway::parse(s).unwrap()
+    }
+
+    fn node(s: &str, embedding: Vec<f32>) -> Node {
+        let mut node = Node::new(pathway(s), NodeKind::Document, format!("About {}", s));
+        node.embedding = embedding;
+        node
+    }
+
+    async fn storage() -> MemoryStorage {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        let mut guide = node("a3s://knowledge/docs/guide.md", vec![1.0, 0.0, 0.0]);
+        guide.add_relation(
+            pathway("a3s://knowledge/docs/api/auth.md"),
+            RelationKind::References,
+            "Links to auth".to_string(),
+        );
+        let mut auth = node("a3s://knowledge/docs/api/auth.md", vec![0.0, 1.0, 0.0]);
+        auth.add_relation(
+            pathway("a3s://knowledge/docs/guide.md"),
+            RelationKind::References,
+            "Links back".to_string(),
+        );
+        let mut chunk = Node::new(
+            crate::chunk::chunk_pathway(&auth.pathway, 0),
+            NodeKind::Document,
+            "Tokens".to_string(),
+        );
+        chunk.metadata.custom.insert(
+            CHUNK_KEY.to_string(),
+            serde_json::to_value(ChunkInfo {
+                parent: auth.pathway.clone(),
+                index: 0,
+                start: 0,
+                end: 6,
+                header: None,
+            })
+            .unwrap(),
+        );
+        for node in [guide, auth, chunk] {
+            storage.put(&node).await.unwrap();
+        }
+        storage
+    }
+
+    #[tokio::test]
+    async fn test_move_redirects_relations_and_index() {
+        let storage = storage().await;
+        let from = pathway("a3s://knowledge/docs/api");
+        let to = pathway("a3s://knowledge/reference/api");
+
+        let result = move_node(&storage, &PolicyChain::new(), &from, &to)
+            .await
+            .unwrap();
+        assert_eq!(result.nodes, 2);
+        assert_eq!(result.relations_updated, 1);
+
+        let auth = pathway("a3s://knowledge/reference/api/auth.md");
+        assert!(!storage
+            .exists(&pathway("a3s://knowledge/docs/api/auth.md"))
+            .await
+            .unwrap());
+        let guide = storage
+            .get(&pathway("a3s://knowledge/docs/guide.md"))
+            .await
+            .unwrap();
+        assert_eq!(guide.relations[0].target, auth);
+
+        let chunk = storage
+            .get(&crate::chunk::chunk_pathway(&auth, 0))
+            .await
+            .unwrap();
+        assert_eq!(ChunkInfo::of(&chunk).unwrap().parent, auth);
+
+        let hits = storage
+            .search_vector(&[0.0, 1.0, 0.0], None, 1, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(hits[0].0, auth);
+    }
+
+    #[tokio::test]
+    async fn test_copy_keeps_originals() {
+        let storage = storage().await;
+        let from = pathway("a3s://knowledge/docs/api/auth.md");
+        let to = pathway("a3s://knowledge/archive/auth.md");
+
+        // The document's chunk comes along without `recursive`
+        let policies = PolicyChain::new();
+        let now = crate::testing::TestClock::epoch();
+        let result = copy_node(&storage, &policies, &from, &to, false, now)
+            .await
+            .unwrap();
+        assert_eq!(result.nodes, 2);
+        assert!(storage.exists(&from).await.unwrap());
+        let copy = storage.get(&to).await.unwrap();
+        assert_eq!(copy.created_at, now);
+        assert_eq!(copy.updated_at, now);
+        assert_eq!(
+            copy.relations[0].target,
+            pathway("a3s://knowledge/docs/guide.md")
+        );
+
+        let guide = storage
+            .get(&pathway("a3s://knowledge/docs/guide.md"))
+            .await
+            .unwrap();
+        assert_eq!(guide.relations[0].target, from);
+
+        assert!(matches!(
+            copy_node(&storage, &policies, &from, &to, false, now).await,
+            Err(A3SError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            move_node(&storage, &policies, &pathway("a3s://knowledge/docs"), &from).await,
+            Err(A3SError::InvalidPathway(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_denied_move_writes_nothing() {
+        let storage = storage().await;
+        let policies = PolicyChain::new();
+        policies.push(std::sync::Arc::new(
+            crate::policy::ForbiddenContentPolicy::new(&["Tokens"]).unwrap(),
+        ));
+        let from = pathway("a3s://knowledge/docs/api");
+        let to = pathway("a3s://knowledge/reference/api");
+
+        assert!(matches!(
+            move_node(&storage, &policies, &from, &to).await,
+            Err(A3SError::PolicyViolation(_))
+        ));
+        assert!(storage
+            .exists(&pathway("a3s://knowledge/docs/api/auth.md"))
+            .await
+            .unwrap());
+        assert!(!storage.exists(&to).await.unwrap());
+        assert!(storage.get_children(&to, 1).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,455 @@
+//! Content-type aware rendering of nodes for display
+//!
+//! Markdown renders to plain text or to ANSI-styled terminal text, JSON is
+//! pretty-printed, and code is syntax-highlighted for terminals. Other
+//! content is shown as stored.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Node, NodeKind};
+use crate::language::CODE_LANGUAGE_KEY;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+
+/// How to render a node's content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderFormat {
+    /// Content exactly as stored
+    Raw,
+    /// Markdown syntax removed, JSON pretty-printed
+    #[default]
+    Plain,
+    /// Styled for a terminal with ANSI escape codes
+    Ansi,
+}
+
+/// Render a node's content in the given format
+pub fn render(node: &Node, format: RenderFormat) -> String {
+    let ansi = match format {
+        RenderFormat::Raw => return node.content.clone(),
+        RenderFormat::Plain => false,
+        RenderFormat::Ansi => true,
+    };
+
+    if is_json(node) {
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&node.content) {
+            let pretty = serde_json::to_string_pretty(&value).unwrap_or_default();
+            return if ansi { color_json(&pretty) } else { pretty };
+        }
+    }
+    match node.kind {
+        NodeKind::Markdown => markdown(&node.content, ansi),
+        NodeKind::Code if ansi => {
+            let language = node
+                .metadata
+                .custom
+                .get(CODE_LANGUAGE_KEY)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            node.content
+                .lines()
+                .map(|line| highlight(line, language))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        _ => node.content.clone(),
+    }
+}
+
+fn is_json(node: &Node) -> bool {
+    let declared = node
+        .metadata
+        .source
+        .as_ref()
+        .and_then(|s| s.content_type.as_deref())
+        .is_some_and(|t| t.contains("json"))
+        || node.pathway.name().is_some_and(|n| n.ends_with(".json"));
+    let trimmed = node.content.trim_start();
+    (declared || node.kind == NodeKind::Data)
+        && (trimmed.starts_with('{') || trimmed.starts_with('['))
+}
+
+fn markdown(content: &str, ansi: bool) -> String {
+    let mut out = Vec::new();
+    let mut fence: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("```") {
+            fence = match fence {
+                Some(_) => None,
+                None => Some(rest.trim().to_lowercase()),
+            };
+            continue;
+        }
+        if let Some(language) = &fence {
+            let code = if ansi {
+                highlight(line, language)
+            } else {
+                line.to_string()
+            };
+            out.push(format!("    {}", code));
+            continue;
+        }
+
+        let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            let text = inline(trimmed[hashes..].trim(), false);
+            out.push(match (ansi, hashes) {
+                (true, 1) => format!("{}{}{}{}", BOLD, UNDERLINE, text, RESET),
+                (true, _) => format!("{}{}{}", BOLD, text, RESET),
+                (false, _) => text,
+            });
+            continue;
+        }
+
+        let indent = &line[..line.len() - trimmed.len()];
+        if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            let bullet = if ansi { "• " } else { "- " };
+            out.push(format!("{}{}{}", indent, bullet, inline(item, ansi)));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            let quote = inline(quote.trim_start(), ansi);
+            out.push(if ansi {
+                format!("{}│ {}{}", DIM, quote, RESET)
+            } else {
+                format!("{}{}", indent, quote)
+            });
+        } else {
+            out.push(format!("{}{}", indent, inline(trimmed, ansi)));
+        }
+    }
+    out.join("\n")
+}
+
+/// Render inline emphasis, code spans, and links
+fn inline(text: &str, ansi: bool) -> String {
+    let style = |code: &str, inner: &str| {
+        if ansi {
+            format!("{}{}{}", code, inner, RESET)
+        } else {
+            inner.to_string()
+        }
+    };
+
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let closed = |open: &str, close: &str| {
+            let body = rest.strip_prefix(open)?;
+            let end = body.find(close)?;
+            (end > 0).then(|| (&body[..end], open.len() + end + close.len()))
+        };
+        let link = || {
+            let image = rest.starts_with('!');
+            let body = rest.strip_prefix('!').unwrap_or(rest).strip_prefix('[')?;
+            let text_end = body.find("](")?;
+            let url_end = body[text_end + 2..].find(')')?;
+            let consumed = usize::from(image) + 1 + text_end + 2 + url_end + 1;
+            Some((
+                image,
+                &body[..text_end],
+                &body[text_end + 2..][..url_end],
+                consumed,
+            ))
+        };
+
+        if let Some((code, len)) = closed("`", "`") {
+            out.push_str(&style(CYAN, code));
+            rest = &rest[len..];
+        } else if let Some((bold, len)) = closed("**", "**").or_else(|| closed("__", "__")) {
+            out.push_str(&style(BOLD, &inline(bold, false)));
+            rest = &rest[len..];
+        } else if let Some((em, len)) = closed("*", "*") {
+            out.push_str(&style(ITALIC, &inline(em, false)));
+            rest = &rest[len..];
+        } else if let Some((image, label, url, len)) = (c == '[' || c == '!').then(link).flatten() {
+            if image {
+                out.push_str(label);
+            } else if ansi {
+                out.push_str(&format!(
+                    "{}{}{} {}({}){}",
+                    UNDERLINE, label, RESET, DIM, url, RESET
+                ));
+            } else {
+                out.push_str(&format!("{} ({})", label, url));
+            }
+            rest = &rest[len..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// Keywords and line comment marker of a programming language
+fn syntax(language: &str) -> (&'static [&'static str], &'static str) {
+    match language {
+        "rust" | "rs" => (
+            &[
+                "as", "async", "await", "break", "const", "continue", "crate", "else", "enum",
+                "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+                "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+                "trait", "true", "type", "unsafe", "use", "where", "while",
+            ],
+            "//",
+        ),
+        "python" | "py" => (
+            &[
+                "and", "as", "async", "await", "class", "def", "elif", "else", "except", "False",
+                "finally", "for", "from", "if", "import", "in", "is", "lambda", "None", "not",
+                "or", "pass", "raise", "return", "True", "try", "while", "with", "yield",
+            ],
+            "#",
+        ),
+        "javascript" | "js" | "typescript" | "ts" => (
+            &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "class",
+                "const",
+                "continue",
+                "default",
+                "else",
+                "export",
+                "extends",
+                "false",
+                "for",
+                "function",
+                "if",
+                "import",
+                "interface",
+                "let",
+                "new",
+                "null",
+                "return",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "type",
+                "undefined",
+                "var",
+                "while",
+            ],
+            "//",
+        ),
+        "go" => (
+            &[
+                "break",
+                "case",
+                "chan",
+                "const",
+                "continue",
+                "default",
+                "defer",
+                "else",
+                "false",
+                "for",
+                "func",
+                "go",
+                "if",
+                "import",
+                "interface",
+                "map",
+                "nil",
+                "package",
+                "range",
+                "return",
+                "select",
+                "struct",
+                "switch",
+                "true",
+                "type",
+                "var",
+            ],
+            "//",
+        ),
+        "java" | "c" | "cpp" | "h" => (
+            &[
+                "break", "case", "char", "class", "const", "continue", "default", "double", "else",
+                "enum", "false", "final", "float", "for", "if", "import", "int", "long", "new",
+                "null", "private", "public", "return", "static", "struct", "switch", "this",
+                "true", "void", "while",
+            ],
+            "//",
+        ),
+        "sh" | "bash" | "shell" | "yaml" | "yml" | "toml" => (
+            &[
+                "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "if", "in",
+                "then", "while",
+            ],
+            "#",
+        ),
+        _ => (&[], ""),
+    }
+}
+
+/// Color keywords, strings, and line comments in one line of code
+fn highlight(line: &str, language: &str) -> String {
+    let (keywords, comment) = syntax(language);
+    if keywords.is_empty() {
+        return line.to_string();
+    }
+
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(comment) {
+            out.push_str(&format!("{}{}{}", DIM, rest, RESET));
+            break;
+        }
+        if c == '"' || (c == '\'' && language != "rust" && language != "rs") {
+            let end = string_end(rest, c);
+            out.push_str(&format!("{}{}{}", GREEN, &rest[..end], RESET));
+            rest = &rest[end..];
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            if keywords.contains(&word) {
+                out.push_str(&format!("{}{}{}{}", BOLD, MAGENTA, word, RESET));
+            } else {
+                out.push_str(word);
+            }
+            rest = &rest[end..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// Length of the string literal at the start of `s`, through its closing
+/// quote or the end of the line
+fn string_end(s: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// Color keys, strings, and literals in pretty-printed JSON
+fn color_json(pretty: &str) -> String {
+    let mut out = String::new();
+    let mut rest = pretty;
+    while let Some(c) = rest.chars().next() {
+        if c == '"' {
+            let end = string_end(rest, '"');
+            let is_key = rest[end..].starts_with(':');
+            let color = if is_key { BLUE } else { GREEN };
+            out.push_str(&format!("{}{}{}", color, &rest[..end], RESET));
+            rest = &rest[end..];
+        } else if c.is_ascii_digit() || c == '-' || c.is_ascii_alphabetic() {
+            let end = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || matches!(ch, '.' | '-' | '+')))
+                .unwrap_or(rest.len());
+            out.push_str(&format!("{}{}{}", YELLOW, &rest[..end], RESET));
+            rest = &rest[end..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathway::Pathway;
+
+    fn node(pathway: &str, kind: NodeKind, content: &str) -> Node {
+        Node::new(Pathway::parse(pathway).unwrap(), kind, content.to_string())
+    }
+
+    #[test]
+    fn test_markdown_to_plain_text() {
+        let node = node(
+            "a3s://knowledge/docs/auth.md",
+            NodeKind::Markdown,
+            "# Auth\n\nUse **tokens** and `refresh()`, see [docs](https://x.dev).\n\n* one\n```rust\nlet a = 1;\n```",
+        );
+        assert_eq!(
+            render(&node, RenderFormat::Plain),
+            "Auth\n\nUse tokens and refresh(), see docs (https://x.dev).\n\n- one\n    let a = 1;"
+        );
+        assert_eq!(render(&node, RenderFormat::Raw), node.content);
+    }
+
+    #[test]
+    fn test_markdown_to_ansi() {
+        let node = node(
+            "a3s://knowledge/docs/a.md",
+            NodeKind::Markdown,
+            "## Setup\n- `cargo`",
+        );
+        let rendered = render(&node, RenderFormat::Ansi);
+        assert!(rendered.starts_with(&format!("{}Setup{}", BOLD, RESET)));
+        assert!(rendered.contains(&format!("• {}cargo{}", CYAN, RESET)));
+    }
+
+    #[test]
+    fn test_json_pretty_printed() {
+        let node = node(
+            "a3s://knowledge/data/config.json",
+            NodeKind::Document,
+            r#"{"a":[1,true]}"#,
+        );
+        assert_eq!(
+            render(&node, RenderFormat::Plain),
+            "{\n  \"a\": [\n    1,\n    true\n  ]\n}"
+        );
+        let ansi = render(&node, RenderFormat::Ansi);
+        assert!(ansi.contains(&format!("{}\"a\"{}:", BLUE, RESET)));
+        assert!(ansi.contains(&format!("{}true{}", YELLOW, RESET)));
+    }
+
+    #[test]
+    fn test_code_highlighting() {
+        let mut node = node(
+            "a3s://knowledge/src/main.rs",
+            NodeKind::Code,
+            "let s = \"fn\"; // note",
+        );
+        node.metadata
+            .custom
+            .insert(CODE_LANGUAGE_KEY.to_string(), serde_json::json!("rust"));
+        assert_eq!(
+            render(&node, RenderFormat::Ansi),
+            format!(
+                "{b}{m}let{r} s = {g}\"fn\"{r}; {d}// note{r}",
+                b = BOLD,
+                m = MAGENTA,
+                r = RESET,
+                g = GREEN,
+                d = DIM
+            )
+        );
+        assert_eq!(render(&node, RenderFormat::Plain), node.content);
+    }
+}
@@ -0,0 +1,188 @@
+//! Interactive query REPL
+
+use std::path::PathBuf;
+
+use a3s_context::session::{MessageRole, Session};
+use a3s_context::{A3SClient, MatchedNode, Namespace, QueryOptions};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+const HELP: &str = "\
+Commands:
+  <text>            Query the store (same as `query <text>`)
+  query <text>      Query the store
+  use <namespace>   Restrict queries to a namespace (`use all` to clear)
+  limit <n>         Set the result limit
+  open <n>          Print the full content of result <n> from the last query
+  help              Show this help
+  quit | exit       Leave the REPL";
+
+/// Run the REPL until the user exits
+pub async fn run(client: &A3SClient, record: bool) -> anyhow::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing history file is expected on first run
+        let _ = editor.load_history(path);
+    }
+
+    let session = if record {
+        Some(client.session(None).await?)
+    } else {
+        None
+    };
+
+    let mut repl = Repl {
+        client,
+        namespace: None,
+        limit: 10,
+        last: Vec::new(),
+        session,
+    };
+
+    println!("A3S Context REPL — type `help` for commands");
+
+    loop {
+        let prompt = format!(
+            "{}> ",
+            repl.namespace.map(|ns| ns.as_str()).unwrap_or("a3s")
+        );
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        match repl.handle(line).await {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("error: {}", e),
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        editor.save_history(path)?;
+    }
+
+    if let Some(mut session) = repl.session {
+        session.commit().await?;
+        println!("Recorded session {}", session.id());
+    }
+
+    Ok(())
+}
+
+fn history_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "A3S", "a3s-ctx")
+        .map(|dirs| dirs.data_dir().join("repl_history"))
+}
+
+struct Repl<'a> {
+    client: &'a A3SClient,
+    namespace: Option<Namespace>,
+    limit: usize,
+    last: Vec<MatchedNode>,
+    session: Option<Session>,
+}
+
+impl Repl<'_> {
+    /// Handle one input line, returning false when the REPL should exit
+    async fn handle(&mut self, line: &str) -> anyhow::Result<bool> {
+        let (command, arg) = line
+            .split_once(' ')
+            .map(|(command, arg)| (command, arg.trim()))
+            .unwrap_or((line, ""));
+
+        match command {
+            "quit" | "exit" => return Ok(false),
+            "help" => println!("{}", HELP),
+            "use" => {
+                if arg.is_empty() || arg == "all" {
+                    self.namespace = None;
+                } else {
+                    self.namespace = Some(
+                        Namespace::parse(arg)
+                            .ok_or_else(|| anyhow::anyhow!("unknown namespace: {}", arg))?,
+                    );
+                }
+            }
+            "limit" => {
+                self.limit = arg
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("usage: limit <n>"))?;
+            }
+            "open" => self.open(arg).await?,
+            "query" => self.query(arg).await?,
+            _ => self.query(line).await?,
+        }
+
+        Ok(true)
+    }
+
+    async fn query(&mut self, text: &str) -> anyhow::Result<()> {
+        if text.is_empty() {
+            anyhow::bail!("usage: query <text>");
+        }
+
+        let result = self
+            .client
+            .query_with_options(
+                text,
+                QueryOptions {
+                    namespace: self.namespace,
+                    limit: Some(self.limit),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let mut listing = String::new();
+        for (i, m) in result.matches.iter().enumerate() {
+            listing.push_str(&format!(
+                "{}. {} (score: {:.3})\n   {}\n",
+                i + 1,
+                m.pathway,
+                m.score,
+                m.brief
+            ));
+        }
+
+        if result.matches.is_empty() {
+            println!("No results ({}ms)", result.search_time_ms);
+        } else {
+            print!("{}", listing);
+        }
+
+        if let Some(session) = &mut self.session {
+            session.add_message(MessageRole::User, text.to_string());
+            session.add_message(MessageRole::Assistant, listing);
+        }
+
+        self.last = result.matches;
+        Ok(())
+    }
+
+    async fn open(&self, arg: &str) -> anyhow::Result<()> {
+        let index: usize = arg
+            .parse()
+            .map_err(|_| anyhow::anyhow!("usage: open <n>"))?;
+        let matched = index
+            .checked_sub(1)
+            .and_then(|i| self.last.get(i))
+            .ok_or_else(|| anyhow::anyhow!("no result #{} in the last query", index))?;
+
+        let node = self.client.read(matched.pathway.to_string()).await?;
+        println!("── {} ──\n{}", node.pathway, node.content);
+        Ok(())
+    }
+}
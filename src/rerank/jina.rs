@@ -2,6 +2,7 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use super::{RerankDocument, RerankResult, Reranker};
 use crate::config::RerankConfig;
@@ -15,6 +16,8 @@ pub struct JinaReranker {
     api_base: String,
     api_key: String,
     model: String,
+    timeout_secs: u64,
+    http: reqwest::Client,
 }
 
 impl JinaReranker {
@@ -39,8 +42,16 @@ impl JinaReranker {
             api_base,
             api_key,
             model,
+            timeout_secs: config.timeout_secs,
+            http: crate::http::default_client(),
         })
     }
+
+    /// Send requests through the given shared client
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -85,9 +96,10 @@ impl Reranker for JinaReranker {
             top_n,
         };
 
-        let client = reqwest::Client::new();
-        let response = client
+        let response = self
+            .http
             .post(format!("{}/rerank", self.api_base))
+            .timeout(Duration::from_secs(self.timeout_secs))
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(&request)
@@ -136,6 +148,7 @@ mod tests {
             api_key: None,
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let result = JinaReranker::new(&config);
         assert!(result.is_err());
@@ -149,6 +162,7 @@ mod tests {
             api_key: Some("test-key".to_string()),
             model: Some("custom-model".to_string()),
             top_n: Some(5),
+            timeout_secs: 30,
         };
         let reranker = JinaReranker::new(&config).unwrap();
         assert_eq!(reranker.api_base, "https://custom.api");
@@ -165,6 +179,7 @@ mod tests {
             api_key: None,
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let reranker = JinaReranker::new(&config).unwrap();
         assert_eq!(reranker.api_key, "env-test-key");
@@ -180,6 +195,7 @@ mod tests {
             api_key: Some("test-key".to_string()),
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let reranker = JinaReranker::new(&config).unwrap();
         let results = reranker.rerank("query", vec![], 5).await.unwrap();
@@ -195,6 +211,7 @@ mod tests {
             api_key: None, // Uses JINA_API_KEY env var
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let reranker = JinaReranker::new(&config).unwrap();
         let documents = vec![
@@ -3,14 +3,20 @@
 //! This module provides reranking capabilities to reorder search results
 //! using specialized reranking models after initial vector search.
 
+#[cfg(feature = "rerank-providers")]
 mod cohere;
+#[cfg(feature = "rerank-providers")]
 mod jina;
 mod mock;
+#[cfg(feature = "rerank-providers")]
 mod openai;
 
+#[cfg(feature = "rerank-providers")]
 pub use cohere::CohereReranker;
+#[cfg(feature = "rerank-providers")]
 pub use jina::JinaReranker;
 pub use mock::MockReranker;
+#[cfg(feature = "rerank-providers")]
 pub use openai::OpenAIReranker;
 
 use async_trait::async_trait;
@@ -18,6 +24,7 @@ use std::sync::Arc;
 
 use crate::config::RerankConfig;
 use crate::error::Result;
+use crate::http::HttpClient;
 
 /// Document to be reranked
 #[derive(Debug, Clone)]
@@ -59,13 +66,30 @@ pub trait Reranker: Send + Sync {
     ) -> Result<Vec<RerankResult>>;
 }
 
-/// Create a reranker based on configuration
-pub fn create_reranker(config: &RerankConfig) -> Result<Arc<dyn Reranker>> {
+/// Create a reranker based on configuration, sending requests through `http`
+pub fn create_reranker(config: &RerankConfig, http: &HttpClient) -> Result<Arc<dyn Reranker>> {
     match config.provider.as_str() {
         "mock" => Ok(Arc::new(MockReranker::new())),
-        "cohere" => Ok(Arc::new(CohereReranker::new(config)?)),
-        "jina" => Ok(Arc::new(JinaReranker::new(config)?)),
-        "openai" => Ok(Arc::new(OpenAIReranker::new(config)?)),
+        #[cfg(feature = "rerank-providers")]
+        "cohere" => Ok(Arc::new(
+            CohereReranker::new(config)?.with_http_client(http.clone()),
+        )),
+        #[cfg(feature = "rerank-providers")]
+        "jina" => Ok(Arc::new(
+            JinaReranker::new(config)?.with_http_client(http.clone()),
+        )),
+        #[cfg(feature = "rerank-providers")]
+        "openai" => Ok(Arc::new(
+            OpenAIReranker::new(config)?.with_http_client(http.clone()),
+        )),
+        #[cfg(not(feature = "rerank-providers"))]
+        "cohere" | "jina" | "openai" => {
+            let _ = http;
+            Err(crate::A3SError::Config(format!(
+                "Rerank provider '{}' requires the `rerank-providers` feature",
+                config.provider
+            )))
+        }
         _ => Err(crate::A3SError::Config(format!(
             "Unknown rerank provider: {}",
             config.provider
@@ -102,7 +126,10 @@ mod tests {
     #[test]
     fn test_create_mock_reranker() {
         let config = RerankConfig::default();
-        let reranker = create_reranker(&config);
+        let reranker = create_reranker(
+            &config,
+            &crate::http::build_client(&Default::default()).unwrap(),
+        );
         assert!(reranker.is_ok());
     }
 
@@ -112,7 +139,10 @@ mod tests {
             provider: "unknown".to_string(),
             ..Default::default()
         };
-        let result = create_reranker(&config);
+        let result = create_reranker(
+            &config,
+            &crate::http::build_client(&Default::default()).unwrap(),
+        );
         assert!(result.is_err());
     }
 }
@@ -2,6 +2,7 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use super::{RerankDocument, RerankResult, Reranker};
 use crate::config::RerankConfig;
@@ -15,6 +16,8 @@ pub struct OpenAIReranker {
     api_base: String,
     api_key: String,
     model: String,
+    timeout_secs: u64,
+    http: reqwest::Client,
 }
 
 impl OpenAIReranker {
@@ -39,9 +42,17 @@ impl OpenAIReranker {
             api_base,
             api_key,
             model,
+            timeout_secs: config.timeout_secs,
+            http: crate::http::default_client(),
         })
     }
 
+    /// Send requests through the given shared client
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
     async fn score_document(&self, query: &str, document: &str) -> Result<f32> {
         let prompt = format!(
             "Rate the relevance of the following document to the query on a scale of 0 to 10.\n\n\
@@ -61,9 +72,10 @@ impl OpenAIReranker {
             max_tokens: 10,
         };
 
-        let client = reqwest::Client::new();
-        let response = client
+        let response = self
+            .http
             .post(format!("{}/chat/completions", self.api_base))
+            .timeout(Duration::from_secs(self.timeout_secs))
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(&request)
@@ -178,6 +190,7 @@ mod tests {
             api_key: None,
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let result = OpenAIReranker::new(&config);
         assert!(result.is_err());
@@ -191,6 +204,7 @@ mod tests {
             api_key: Some("test-key".to_string()),
             model: Some("gpt-4".to_string()),
             top_n: Some(5),
+            timeout_secs: 30,
         };
         let reranker = OpenAIReranker::new(&config).unwrap();
         assert_eq!(reranker.api_base, "https://custom.api");
@@ -207,6 +221,7 @@ mod tests {
             api_key: None,
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let reranker = OpenAIReranker::new(&config).unwrap();
         assert_eq!(reranker.api_key, "env-test-key");
@@ -222,6 +237,7 @@ mod tests {
             api_key: Some("test-key".to_string()),
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let reranker = OpenAIReranker::new(&config).unwrap();
         let results = reranker.rerank("query", vec![], 5).await.unwrap();
@@ -237,6 +253,7 @@ mod tests {
             api_key: None, // Uses OPENAI_API_KEY env var
             model: None,
             top_n: None,
+            timeout_secs: 30,
         };
         let reranker = OpenAIReranker::new(&config).unwrap();
         let documents = vec![
@@ -1,15 +1,30 @@
 //! Hierarchical retrieval system
 
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::analytics::{AnalyticsEvent, QueryLog};
+use crate::clock::{Clock, SystemClock};
 use crate::config::RetrievalConfig;
+use crate::core::{Node, VectorName};
+use crate::digest::DigestLevel;
 use crate::embedding::Embedder;
-use crate::error::Result;
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
 use crate::pathway::Pathway;
 use crate::rerank::{create_reranker, RerankDocument, Reranker};
+use crate::routing::Router;
 use crate::storage::StorageBackend;
-use crate::{MatchedNode, QueryOptions, QueryResult};
+use crate::{MatchExplanation, MatchedNode, QueryExample, QueryOptions, QueryResult};
+
+/// Cosine similarity at which two matches count as copies of each other
+const DUPLICATE_SIMILARITY: f32 = 0.95;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
 
 /// Hierarchical retriever for semantic search
 pub struct Retriever {
@@ -17,6 +32,13 @@ pub struct Retriever {
     embedder: Arc<dyn Embedder>,
     config: RetrievalConfig,
     reranker: Option<Arc<dyn Reranker>>,
+    router: Option<Router>,
+    analytics: Option<Arc<QueryLog>>,
+    /// Whether queries are appended to `analytics`
+    log_queries: bool,
+    /// Time that expiry, age, and staleness are judged against
+    clock: Arc<dyn Clock>,
+    http: HttpClient,
 }
 
 impl Retriever {
@@ -24,10 +46,11 @@ impl Retriever {
         storage: Arc<dyn StorageBackend>,
         embedder: Arc<dyn Embedder>,
         config: &RetrievalConfig,
+        http: &HttpClient,
     ) -> Self {
         // Create reranker if reranking is enabled
         let reranker = if config.rerank {
-            match create_reranker(&config.rerank_config) {
+            match create_reranker(&config.rerank_config, http) {
                 Ok(r) => Some(r),
                 Err(e) => {
                     tracing::warn!("Failed to create reranker: {}, reranking disabled", e);
@@ -43,16 +66,121 @@ impl Retriever {
             embedder,
             config: config.clone(),
             reranker,
+            router: None,
+            analytics: None,
+            log_queries: false,
+            clock: Arc::new(SystemClock),
+            http: http.clone(),
         }
     }
 
+    /// Log every query to `log` and rank by its feedback
+    pub fn with_analytics(mut self, log: Arc<QueryLog>) -> Self {
+        self.analytics = Some(log);
+        self.log_queries = true;
+        self
+    }
+
+    /// Rank by the feedback in `log` without logging queries
+    pub fn with_feedback(mut self, log: Arc<QueryLog>) -> Self {
+        self.analytics = Some(log);
+        self
+    }
+
+    /// Judge expiry, age, and staleness by `clock` instead of the system
+    /// clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Classify queries with `router` and apply its per-intent adjustments
+    pub fn with_router(mut self, router: Router) -> Self {
+        self.router = Some(router);
+        self
+    }
+
     /// Search for relevant context
     pub async fn search(&self, query: &str, options: Option<QueryOptions>) -> Result<QueryResult> {
         let options = options.unwrap_or_default();
+        let query_id = Uuid::new_v4().to_string();
+        let span = tracing::info_span!(
+            "query",
+            query_id = %query_id,
+            namespace = ?options.namespace,
+            limit = ?options.limit,
+        );
+
+        let namespace = options.namespace;
+        let cancel = options.cancel.clone();
+        let timeout_ms = options.timeout_ms;
+        let search = async {
+            let search = self.search_inner(query, query_id, options);
+            match timeout_ms {
+                Some(ms) => tokio::time::timeout(Duration::from_millis(ms), search)
+                    .await
+                    .map_err(|_| A3SError::Timeout(format!("query exceeded {}ms", ms)))?,
+                None => search.await,
+            }
+        }
+        .instrument(span);
+
+        let result = match cancel {
+            Some(cancel) => tokio::select! {
+                biased;
+                _ = cancel.cancelled() => Err(A3SError::Cancelled),
+                result = search => result,
+            },
+            None => search.await,
+        };
+
+        if let (Ok(result), Some(log), true) = (&result, &self.analytics, self.log_queries) {
+            let event = AnalyticsEvent::Query {
+                query_id: result.query_id.clone(),
+                query: query.to_string(),
+                namespace,
+                matches: result.matches.len(),
+                at: self.clock.now(),
+            };
+            if let Err(e) = log.record(&event).await {
+                tracing::warn!(error = %e, "failed to record query analytics");
+            }
+        }
+        result
+    }
+
+    async fn search_inner(
+        &self,
+        query: &str,
+        query_id: String,
+        mut options: QueryOptions,
+    ) -> Result<QueryResult> {
+        let intent = match &self.router {
+            Some(router) => Some(router.classify(query).await),
+            None => None,
+        };
+        let route = intent
+            .and_then(|intent| self.router.as_ref()?.route(intent))
+            .cloned();
+        if let Some(route) = &route {
+            tracing::debug!(intent = ?intent, "query routed");
+            if route.digest_level == DigestLevel::Full {
+                options.include_content = true;
+            }
+        }
 
         // Generate query embedding
         let embed_start = Instant::now();
-        let query_vector = self.embedder.embed(query).await?;
+        let mut query_vector = self.embedder.embed(query).await?;
+        if !options.positive_examples.is_empty() || !options.negative_examples.is_empty() {
+            let positive = self
+                .example_vectors(&options.positive_examples, query_vector.len())
+                .await?;
+            let negative = self
+                .example_vectors(&options.negative_examples, query_vector.len())
+                .await?;
+            query_vector = steer(&query_vector, &positive, &negative);
+        }
         let embed_time = embed_start.elapsed().as_millis() as u64;
 
         let search_start = Instant::now();
@@ -61,53 +189,239 @@ impl Retriever {
         let limit = options.limit.unwrap_or(self.config.default_limit);
         let threshold = options.threshold.unwrap_or(self.config.score_threshold);
 
+        // Use reranking if enabled, unless overridden per query
+        let rerank = options
+            .rerank
+            .or_else(|| route.as_ref().and_then(|route| route.rerank));
+        let mut warnings = Vec::new();
+        let reranker = match rerank {
+            Some(false) => None,
+            Some(true) => match &self.reranker {
+                Some(reranker) => Some(reranker.clone()),
+                None => match create_reranker(&self.config.rerank_config, &self.http) {
+                    Ok(reranker) => Some(reranker),
+                    Err(e) => {
+                        degrade(&options, &mut warnings, "reranking skipped", e)?;
+                        None
+                    }
+                },
+            },
+            None => self.reranker.clone(),
+        };
+        // The reranker sees every candidate, not just the top `limit`
+        let candidate_limit = limit * 3;
+
+        let vectors = options
+            .vectors
+            .clone()
+            .unwrap_or_else(|| self.config.vectors.clone());
+
         // Perform vector search
-        let candidates = self
+        let mut candidates = self
             .storage
-            .search_vector(&query_vector, options.namespace, limit * 3, threshold)
+            .search_vectors(
+                &query_vector,
+                &vectors,
+                options.namespace,
+                candidate_limit,
+                threshold,
+            )
             .await?;
+        if let Some(filter) = &options.pathway_filter {
+            candidates.retain(|(pathway, _)| pathway.to_string().starts_with(filter.as_str()));
+        }
+        tracing::debug!(
+            candidates = candidates.len(),
+            embed_ms = embed_time,
+            "vector search complete"
+        );
 
         // If hierarchical search is enabled, explore directories
         let mut results = if self.config.hierarchical {
-            self.hierarchical_search(&query_vector, &candidates, limit, threshold)
-                .await?
+            self.hierarchical_search(
+                &query_vector,
+                &vectors,
+                &candidates,
+                threshold,
+                &options,
+                &mut warnings,
+            )
+            .await?
         } else {
-            self.flat_search(&candidates, limit).await?
+            let kept = if reranker.is_some() {
+                candidate_limit
+            } else {
+                limit
+            };
+            self.flat_search(&candidates, kept, &options, &mut warnings)
+                .await?
         };
 
+        // Directory exploration can reach outside the filter
+        if let Some(filter) = &options.pathway_filter {
+            results.retain(|m| m.pathway.to_string().starts_with(filter.as_str()));
+        }
+
+        if options.prefer_fresh {
+            results = self.prefer_fresh_duplicates(results).await?;
+        }
+
+        // Weights only matter when several namespaces compete
+        if let (Some(route), None) = (&route, options.namespace) {
+            for result in &mut results {
+                result.score *= route.weight(result.pathway.namespace());
+            }
+        }
+
         // Sort by score
         results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
 
-        // Apply reranking if enabled
-        if let Some(ref reranker) = self.reranker {
+        // Rerank every candidate, then re-score the final results
+        let mut rerank_time = None;
+        if let Some(ref reranker) = reranker {
             let top_n = self.config.rerank_config.top_n.unwrap_or(limit);
-            results = self
-                .apply_reranking(query, results, reranker, top_n)
-                .await?;
+            let rerank_start = Instant::now();
+            match self.apply_reranking(query, &results, reranker, top_n).await {
+                Ok(reranked) => {
+                    results = reranked;
+                    let elapsed = rerank_start.elapsed().as_millis() as u64;
+                    rerank_time = Some(elapsed);
+                    tracing::debug!(
+                        reranked = results.len(),
+                        rerank_ms = elapsed,
+                        "rerank complete"
+                    );
+                }
+                // Keep the vector ranking
+                Err(e) => degrade(&options, &mut warnings, "reranking failed", e)?,
+            }
+        }
+
+        if let (true, Some(log)) = (self.config.feedback.enabled, &self.analytics) {
+            if let Err(e) = self.apply_feedback(&mut results, log).await {
+                degrade(&options, &mut warnings, "feedback ranking skipped", e)?;
+            }
         }
 
         results.truncate(limit);
 
+        if let Some(days) = self.config.staleness_horizon_days {
+            let horizon = days.saturating_mul(SECS_PER_DAY);
+            for result in &mut results {
+                result.stale = result.age > horizon;
+            }
+            let stale = results.iter().filter(|m| m.stale).count();
+            if stale > 0 {
+                tracing::warn!(stale, horizon_days = days, "query returned stale matches");
+            }
+        }
+
+        if route.is_some_and(|route| route.digest_level == DigestLevel::Brief) {
+            for result in &mut results {
+                result.summary = None;
+            }
+        }
+
         let search_time = search_start.elapsed().as_millis() as u64;
 
+        tracing::info!(
+            matches = results.len(),
+            total_searched = candidates.len(),
+            embed_ms = embed_time,
+            search_ms = search_time,
+            rerank_ms = rerank_time,
+            "query complete"
+        );
+
         Ok(QueryResult {
+            query_id,
+            intent,
             matches: results,
             total_searched: candidates.len(),
             query_embedding_time_ms: embed_time,
             search_time_ms: search_time,
+            rerank_time_ms: rerank_time,
+            warnings,
         })
     }
 
+    /// Scale scores by each node's feedback and restore score order
+    async fn apply_feedback(&self, results: &mut [MatchedNode], log: &QueryLog) -> Result<()> {
+        let counts = log.feedback_counts().await?;
+        if counts.is_empty() {
+            return Ok(());
+        }
+        for result in results.iter_mut() {
+            let Some(count) = counts.get(&result.pathway) else {
+                continue;
+            };
+            let boost = count.boost(&self.config.feedback);
+            // Move negative scores the same way as positive ones
+            result.score += result.score.abs() * (boost - 1.0);
+            if let Some(explanation) = &mut result.explanation {
+                explanation.feedback_boost = Some(boost);
+            }
+        }
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(())
+    }
+
+    /// Embeddings of steering examples, which must match the query's dimension
+    async fn example_vectors(
+        &self,
+        examples: &[QueryExample],
+        dimension: usize,
+    ) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(examples.len());
+        for example in examples {
+            let vector = match example {
+                QueryExample::Text(text) => self.embedder.embed(text).await?,
+                QueryExample::Pathway(pathway) => self.storage.get(pathway).await?.embedding,
+            };
+            if vector.len() != dimension {
+                let example = match example {
+                    QueryExample::Text(text) => text.clone(),
+                    QueryExample::Pathway(pathway) => pathway.to_string(),
+                };
+                return Err(A3SError::Retrieval(format!(
+                    "Example {} has no {}-dimensional embedding to steer by",
+                    example, dimension
+                )));
+            }
+            vectors.push(vector);
+        }
+        Ok(vectors)
+    }
+
+    /// Collapse near-duplicate matches to their most recently updated copy
+    async fn prefer_fresh_duplicates(&self, results: Vec<MatchedNode>) -> Result<Vec<MatchedNode>> {
+        let mut kept: Vec<(MatchedNode, Vec<f32>)> = Vec::with_capacity(results.len());
+
+        for matched in results {
+            let embedding = self.storage.get(&matched.pathway).await?.embedding;
+            let duplicate = kept
+                .iter_mut()
+                .find(|(_, other)| cosine_similarity(other, &embedding) >= DUPLICATE_SIMILARITY);
+            match duplicate {
+                Some(slot) if matched.age < slot.0.age => *slot = (matched, embedding),
+                Some(_) => {}
+                None => kept.push((matched, embedding)),
+            }
+        }
+
+        Ok(kept.into_iter().map(|(matched, _)| matched).collect())
+    }
+
     /// Apply reranking to search results
     async fn apply_reranking(
         &self,
         query: &str,
-        results: Vec<MatchedNode>,
+        results: &[MatchedNode],
         reranker: &Arc<dyn Reranker>,
         top_n: usize,
     ) -> Result<Vec<MatchedNode>> {
         if results.is_empty() {
-            return Ok(results);
+            return Ok(Vec::new());
         }
 
         // Convert results to rerank documents
@@ -123,16 +437,17 @@ impl Retriever {
         let reranked = reranker.rerank(query, documents, top_n).await?;
 
         // Create a map from pathway to original result
-        let result_map: std::collections::HashMap<String, MatchedNode> = results
-            .into_iter()
-            .map(|r| (r.pathway.to_string(), r))
-            .collect();
+        let result_map: std::collections::HashMap<String, &MatchedNode> =
+            results.iter().map(|r| (r.pathway.to_string(), r)).collect();
 
         // Rebuild results in reranked order with updated scores
         let mut reranked_results = Vec::with_capacity(reranked.len());
         for rr in reranked {
-            if let Some(mut matched) = result_map.get(&rr.id).cloned() {
+            if let Some(mut matched) = result_map.get(&rr.id).map(|r| (*r).clone()) {
                 matched.score = rr.score;
+                if let Some(explanation) = &mut matched.explanation {
+                    explanation.rerank_score = Some(rr.score);
+                }
                 reranked_results.push(matched);
             }
         }
@@ -144,21 +459,27 @@ impl Retriever {
         &self,
         candidates: &[(Pathway, f32)],
         limit: usize,
+        options: &QueryOptions,
+        warnings: &mut Vec<String>,
     ) -> Result<Vec<MatchedNode>> {
+        let now = self.clock.now();
         let mut results = Vec::new();
 
-        for (pathway, score) in candidates.iter().take(limit) {
-            let node = self.storage.get(pathway).await?;
-
-            results.push(MatchedNode {
-                pathway: pathway.clone(),
-                node_kind: node.kind,
-                score: *score,
-                brief: node.digest.brief,
-                summary: Some(node.digest.summary),
-                content: None,
-                highlights: Vec::new(),
-            });
+        for (pathway, score) in candidates {
+            if results.len() >= limit {
+                break;
+            }
+
+            let node = match self.storage.get(pathway).await {
+                Ok(node) => node,
+                Err(e) => {
+                    degrade(options, warnings, &format!("skipped {}", pathway), e)?;
+                    continue;
+                }
+            };
+            if is_eligible(&node, options, now) {
+                results.push(to_match(node, *score, options, false, now));
+            }
         }
 
         Ok(results)
@@ -167,10 +488,13 @@ impl Retriever {
     async fn hierarchical_search(
         &self,
         query_vector: &[f32],
+        vectors: &[VectorName],
         initial_candidates: &[(Pathway, f32)],
-        _limit: usize,
         threshold: f32,
+        options: &QueryOptions,
+        warnings: &mut Vec<String>,
     ) -> Result<Vec<MatchedNode>> {
+        let now = self.clock.now();
         let mut results = Vec::new();
         let mut explored_dirs = std::collections::HashSet::new();
 
@@ -180,20 +504,20 @@ impl Retriever {
                 continue;
             }
 
-            let node = self.storage.get(pathway).await?;
+            let node = match self.storage.get(pathway).await {
+                Ok(node) => node,
+                Err(e) => {
+                    degrade(options, warnings, &format!("skipped {}", pathway), e)?;
+                    continue;
+                }
+            };
 
             if node.is_directory {
                 explored_dirs.insert(pathway.clone());
             } else {
-                results.push(MatchedNode {
-                    pathway: pathway.clone(),
-                    node_kind: node.kind,
-                    score: *score,
-                    brief: node.digest.brief.clone(),
-                    summary: Some(node.digest.summary.clone()),
-                    content: None,
-                    highlights: Vec::new(),
-                });
+                if is_eligible(&node, options, now) {
+                    results.push(to_match(node, *score, options, false, now));
+                }
 
                 // Mark parent directory for exploration
                 if let Some(parent) = pathway.parent() {
@@ -204,28 +528,28 @@ impl Retriever {
 
         // Second pass: explore promising directories
         for dir_pathway in explored_dirs.iter().take(self.config.max_depth) {
-            let children = self.storage.get_children(dir_pathway, 2).await?;
+            let children = match self.storage.get_children(dir_pathway, 2).await {
+                Ok(children) => children,
+                Err(e) => {
+                    let context = format!("did not explore {}", dir_pathway);
+                    degrade(options, warnings, &context, e)?;
+                    continue;
+                }
+            };
 
             for child in children {
-                if child.is_directory || child.embedding.is_empty() {
+                if child.is_directory || !is_eligible(&child, options, now) {
                     continue;
                 }
-
-                let score = cosine_similarity(query_vector, &child.embedding);
+                let Some(score) = best_score(query_vector, &child, vectors) else {
+                    continue;
+                };
 
                 if score >= threshold {
                     // Check if already in results
                     let exists = results.iter().any(|r| r.pathway == child.pathway);
                     if !exists {
-                        results.push(MatchedNode {
-                            pathway: child.pathway,
-                            node_kind: child.kind,
-                            score,
-                            brief: child.digest.brief,
-                            summary: Some(child.digest.summary),
-                            content: None,
-                            highlights: Vec::new(),
-                        });
+                        results.push(to_match(child, score, options, true, now));
                     }
                 }
             }
@@ -235,8 +559,107 @@ impl Retriever {
     }
 }
 
+/// Record a failed step as a warning and carry on, or fail the query when
+/// it is strict
+///
+/// Cancellation and timeouts always fail the query.
+fn degrade(
+    options: &QueryOptions,
+    warnings: &mut Vec<String>,
+    context: &str,
+    error: A3SError,
+) -> Result<()> {
+    if options.strict || matches!(error, A3SError::Cancelled | A3SError::Timeout(_)) {
+        return Err(error);
+    }
+    tracing::warn!(error = %error, "{}", context);
+    warnings.push(format!("{}: {}", context, error));
+    Ok(())
+}
+
+/// Check that a node has not expired, carries every requested tag, is in
+/// the requested language, and passes the capability filter
+fn is_eligible(node: &Node, options: &QueryOptions, now: DateTime<Utc>) -> bool {
+    !node.metadata.is_expired_at(now)
+        && (options.include_quarantined
+            || !node
+                .metadata
+                .tags
+                .iter()
+                .any(|tag| tag == crate::screening::QUARANTINE_TAG))
+        && options
+            .tags
+            .iter()
+            .all(|tag| node.metadata.tags.contains(tag))
+        && options
+            .language
+            .iter()
+            .all(|language| crate::language::matches(node, language))
+        && options.capability.iter().all(|filter| filter.matches(node))
+}
+
+/// Move a query vector toward the mean of `positive` and away from the mean
+/// of `negative`, keeping unit length
+fn steer(query: &[f32], positive: &[Vec<f32>], negative: &[Vec<f32>]) -> Vec<f32> {
+    let mut steered = query.to_vec();
+    for (examples, sign) in [(positive, 1.0), (negative, -1.0)] {
+        let weight = sign / examples.len().max(1) as f32;
+        for example in examples {
+            for (value, x) in steered.iter_mut().zip(example) {
+                *value += weight * x;
+            }
+        }
+    }
+
+    let norm = steered.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for value in &mut steered {
+            *value /= norm;
+        }
+    }
+    steered
+}
+
+/// Similarity of a node's best-matching named vector, if it has any of them
+fn best_score(query_vector: &[f32], node: &Node, vectors: &[VectorName]) -> Option<f32> {
+    vectors
+        .iter()
+        .filter_map(|name| node.vector(*name))
+        .map(|vector| cosine_similarity(query_vector, vector))
+        .reduce(f32::max)
+}
+
+/// Build a match from a retrieved node, honoring content and explain options
+fn to_match(
+    node: Node,
+    score: f32,
+    options: &QueryOptions,
+    via_directory: bool,
+    now: DateTime<Utc>,
+) -> MatchedNode {
+    let symbols = crate::syntax::symbols(&node);
+    MatchedNode {
+        pathway: node.pathway,
+        node_kind: node.kind,
+        score,
+        brief: node.digest.brief,
+        summary: Some(node.digest.summary),
+        content: options.include_content.then_some(node.content),
+        highlights: Vec::new(),
+        age: (now - node.updated_at).num_seconds().max(0) as u64,
+        stale: false,
+        symbols,
+        explanation: options.explain.then_some(MatchExplanation {
+            vector_score: score,
+            rerank_score: None,
+            via_directory,
+            feedback_boost: None,
+        }),
+    }
+}
+
 /// Calculate cosine similarity between two vectors
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() || a.is_empty() {
         return 0.0;
     }
@@ -277,6 +700,22 @@ mod tests {
         assert!((cosine_similarity(&a, &b) + 1.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_steer_toward_positive_and_away_from_negative() {
+        let query = vec![1.0, 0.0, 0.0];
+        let positive = vec![vec![0.0, 1.0, 0.0]];
+        let negative = vec![vec![0.0, 0.0, 1.0]];
+        let steered = steer(&query, &positive, &negative);
+
+        assert!(
+            cosine_similarity(&steered, &positive[0]) > cosine_similarity(&query, &positive[0])
+        );
+        assert!(cosine_similarity(&steered, &negative[0]) < 0.0);
+        let norm: f32 = steered.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 0.001);
+        assert_eq!(steer(&query, &[], &[]), query);
+    }
+
     #[test]
     fn test_cosine_similarity_empty() {
         let a: Vec<f32> = vec![];
@@ -0,0 +1,217 @@
+//! Intent-based query routing
+//!
+//! A router classifies each query and looks up the retrieval adjustments
+//! configured for that intent in `RetrievalConfig.routing`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{IntentRoute, RoutingConfig};
+#[cfg(feature = "llm-digest")]
+use crate::digest::LLMClient;
+
+/// What a query is looking for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryIntent {
+    /// A specific fact from the knowledge base
+    FactLookup,
+    /// Code, APIs, or identifiers
+    CodeSearch,
+    /// Something the user or agent said or decided before
+    MemoryRecall,
+    /// No clear intent; retrieval is left unchanged
+    General,
+}
+
+/// Classifies queries and maps intents to retrieval adjustments
+pub struct Router {
+    config: RoutingConfig,
+    #[cfg(feature = "llm-digest")]
+    llm: Option<LLMClient>,
+}
+
+impl Router {
+    /// Create a router that classifies with keyword heuristics
+    pub fn new(config: &RoutingConfig) -> Self {
+        Self {
+            config: config.clone(),
+            #[cfg(feature = "llm-digest")]
+            llm: None,
+        }
+    }
+
+    /// Classify with an LLM call, falling back to heuristics on failure
+    #[cfg(feature = "llm-digest")]
+    pub fn with_llm(mut self, llm: LLMClient) -> Self {
+        self.llm = Some(llm);
+        self
+    }
+
+    /// Classify a query
+    pub async fn classify(&self, query: &str) -> QueryIntent {
+        #[cfg(feature = "llm-digest")]
+        if let Some(llm) = &self.llm {
+            match classify_with_llm(llm, query).await {
+                Ok(intent) => return intent,
+                Err(e) => tracing::warn!("LLM query routing failed: {}, using heuristics", e),
+            }
+        }
+
+        classify_heuristic(query)
+    }
+
+    /// Adjustments for an intent; `General` queries are not adjusted
+    pub fn route(&self, intent: QueryIntent) -> Option<&IntentRoute> {
+        match intent {
+            QueryIntent::FactLookup => Some(&self.config.fact),
+            QueryIntent::CodeSearch => Some(&self.config.code),
+            QueryIntent::MemoryRecall => Some(&self.config.memory),
+            QueryIntent::General => None,
+        }
+    }
+}
+
+/// Phrases suggesting the query is about earlier conversations
+const MEMORY_CUES: &[&str] = &[
+    "remember",
+    "recall",
+    "last time",
+    "earlier",
+    "previously",
+    "did i",
+    "i prefer",
+    "i said",
+    "we discussed",
+    "we decided",
+    "preference",
+    "preferences",
+];
+
+/// Words suggesting the query is about code
+const CODE_WORDS: &[&str] = &[
+    "function",
+    "method",
+    "class",
+    "struct",
+    "impl",
+    "code",
+    "snippet",
+    "implementation",
+    "signature",
+    "compile",
+    "exception",
+    "traceback",
+];
+
+/// Symbols that rarely appear outside code
+const CODE_SYMBOLS: &[&str] = &["::", "()", "`", "->", "=>"];
+
+const FACT_WORDS: &[&str] = &[
+    "what", "who", "when", "where", "which", "why", "how", "is", "are", "does", "define", "explain",
+];
+
+/// Classify a query from keyword cues, without network calls
+pub fn classify_heuristic(query: &str) -> QueryIntent {
+    let query = query.trim().to_lowercase();
+    // Word cues only match whole words: "impl" must not match "simple"
+    let words: String = query
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let words = format!(
+        " {} ",
+        words.split_whitespace().collect::<Vec<_>>().join(" ")
+    );
+    let has_word = |cue: &str| words.contains(&format!(" {} ", cue));
+
+    if MEMORY_CUES.iter().any(|cue| has_word(cue)) {
+        return QueryIntent::MemoryRecall;
+    }
+
+    let has_identifier = query
+        .split_whitespace()
+        .any(|word| is_snake_case(word) || is_camel_case(word) || has_code_extension(word));
+    if has_identifier
+        || CODE_WORDS.iter().any(|cue| has_word(cue))
+        || CODE_SYMBOLS.iter().any(|cue| query.contains(cue))
+    {
+        return QueryIntent::CodeSearch;
+    }
+
+    let first_word = words.split_whitespace().next().unwrap_or_default();
+    if FACT_WORDS.contains(&first_word) || query.ends_with('?') {
+        return QueryIntent::FactLookup;
+    }
+
+    QueryIntent::General
+}
+
+fn is_snake_case(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
+    word.contains('_') && !word.starts_with('_') && !word.ends_with('_')
+}
+
+fn is_camel_case(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(|c| c.is_lowercase()) && chars.any(|c| c.is_uppercase())
+}
+
+fn has_code_extension(word: &str) -> bool {
+    const EXTENSIONS: &[&str] = &[".rs", ".py", ".ts", ".js", ".go", ".java", ".c", ".cpp"];
+    EXTENSIONS.iter().any(|ext| word.ends_with(ext))
+}
+
+#[cfg(feature = "llm-digest")]
+async fn classify_with_llm(llm: &LLMClient, query: &str) -> crate::Result<QueryIntent> {
+    let prompt = format!(
+        "Classify this search query as one of: fact, code, memory, general. \
+         Answer with the single word only.\n\nQuery: {}",
+        query
+    );
+    let answer = llm.complete(&prompt).await?.trim().to_lowercase();
+
+    Ok(match answer.trim_matches(|c: char| !c.is_alphabetic()) {
+        "fact" => QueryIntent::FactLookup,
+        "code" => QueryIntent::CodeSearch,
+        "memory" => QueryIntent::MemoryRecall,
+        _ => QueryIntent::General,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::DigestLevel;
+
+    #[test]
+    fn test_classify_heuristic() {
+        assert_eq!(
+            classify_heuristic("What did I say about dark mode last time?"),
+            QueryIntent::MemoryRecall
+        );
+        assert_eq!(
+            classify_heuristic("where is parse_embedding_response defined"),
+            QueryIntent::CodeSearch
+        );
+        assert_eq!(
+            classify_heuristic("How long do access tokens last?"),
+            QueryIntent::FactLookup
+        );
+        assert_eq!(
+            classify_heuristic("authentication overview"),
+            QueryIntent::General
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_routes() {
+        let router = Router::new(&RoutingConfig::default());
+        let intent = router.classify("getUserById in users.ts").await;
+        assert_eq!(intent, QueryIntent::CodeSearch);
+        assert_eq!(
+            router.route(intent).unwrap().digest_level,
+            DigestLevel::Full
+        );
+        assert!(router.route(QueryIntent::General).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+//! Saved queries with `{{variable}}` templates
+//!
+//! Saved queries are stored as unembedded nodes under
+//! [`SAVED_QUERIES_ROOT`], so every client sharing the store can run them.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Namespace, Node, NodeKind, VectorName};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::{QueryExample, QueryOptions};
+
+/// Pathway under which saved queries are stored
+pub const SAVED_QUERIES_ROOT: &str = "a3s://capability/queries";
+
+/// Metadata key holding a saved query on its node
+const SAVED_QUERY_KEY: &str = "saved_query";
+
+/// A query and the options it runs with
+///
+/// `query` and `pathway_filter` may contain `{{name}}` variables that are
+/// filled in when the query runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<Namespace>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+    #[serde(default)]
+    pub include_content: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pathway_filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub prefer_fresh: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vectors: Option<Vec<VectorName>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub positive_examples: Vec<QueryExample>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub negative_examples: Vec<QueryExample>,
+}
+
+impl SavedQuery {
+    /// Capture a query and the persistable parts of its options
+    pub fn new(query: impl Into<String>, options: &QueryOptions) -> Self {
+        Self {
+            query: query.into(),
+            namespace: options.namespace,
+            limit: options.limit,
+            threshold: options.threshold,
+            include_content: options.include_content,
+            pathway_filter: options.pathway_filter.clone(),
+            tags: options.tags.clone(),
+            rerank: options.rerank,
+            timeout_ms: options.timeout_ms,
+            prefer_fresh: options.prefer_fresh,
+            language: options.language.clone(),
+            vectors: options.vectors.clone(),
+            positive_examples: options.positive_examples.clone(),
+            negative_examples: options.negative_examples.clone(),
+        }
+    }
+
+    /// Variables used by the templates, in order of first use
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for template in std::iter::once(&self.query).chain(&self.pathway_filter) {
+            for name in placeholders(template) {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    /// Fill in the templates, returning the query text and options
+    pub fn render(&self, params: &HashMap<String, String>) -> Result<(String, QueryOptions)> {
+        let query = render(&self.query, params)?;
+        let pathway_filter = self
+            .pathway_filter
+            .as_deref()
+            .map(|filter| render(filter, params))
+            .transpose()?;
+
+        let options = QueryOptions {
+            namespace: self.namespace,
+            limit: self.limit,
+            threshold: self.threshold,
+            include_content: self.include_content,
+            pathway_filter,
+            tags: self.tags.clone(),
+            rerank: self.rerank,
+            timeout_ms: self.timeout_ms,
+            prefer_fresh: self.prefer_fresh,
+            language: self.language.clone(),
+            vectors: self.vectors.clone(),
+            positive_examples: self.positive_examples.clone(),
+            negative_examples: self.negative_examples.clone(),
+            ..Default::default()
+        };
+        Ok((query, options))
+    }
+}
+
+/// Pathway of the saved query with a given name
+pub fn pathway(name: &str) -> Result<Pathway> {
+    if name.is_empty() || name.contains('/') {
+        return Err(A3SError::InvalidPathway(format!(
+            "Invalid saved query name: {:?}",
+            name
+        )));
+    }
+    Ok(Pathway::parse(SAVED_QUERIES_ROOT)?.join(name))
+}
+
+/// Node storing a saved query
+pub fn to_node(pathway: Pathway, saved: &SavedQuery) -> Result<Node> {
+    let mut node = Node::new(pathway, NodeKind::Data, saved.query.clone());
+    node.metadata
+        .custom
+        .insert(SAVED_QUERY_KEY.to_string(), serde_json::to_value(saved)?);
+    Ok(node)
+}
+
+/// Saved query stored on a node, if any
+pub fn from_node(node: &Node) -> Option<SavedQuery> {
+    let value = node.metadata.custom.get(SAVED_QUERY_KEY)?;
+    serde_json::from_value(value.clone()).ok()
+}
+
+/// Replace every `{{name}}` with its parameter
+fn render(template: &str, params: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = params
+            .get(name)
+            .ok_or_else(|| A3SError::Template(format!("missing variable {}", name)))?;
+        out.push_str(&rest[..start]);
+        out.push_str(value);
+        rest = &rest[start + 4 + len..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn placeholders(template: &str) -> impl Iterator<Item = &str> {
+    template.split("{{").skip(1).filter_map(|part| {
+        let end = part.find("}}")?;
+        Some(part[..end].trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let options = QueryOptions {
+            limit: Some(5),
+            pathway_filter: Some("a3s://knowledge/{{service}}/".to_string()),
+            ..Default::default()
+        };
+        let saved = SavedQuery::new("How does {{ service }} handle {{topic}}?", &options);
+        assert_eq!(saved.variables(), vec!["service", "topic"]);
+
+        let (query, options) = saved
+            .render(&params(&[("service", "billing"), ("topic", "retries")]))
+            .unwrap();
+        assert_eq!(query, "How does billing handle retries?");
+        assert_eq!(
+            options.pathway_filter.as_deref(),
+            Some("a3s://knowledge/billing/")
+        );
+        assert_eq!(options.limit, Some(5));
+
+        let err = saved
+            .render(&params(&[("service", "billing")]))
+            .unwrap_err();
+        assert!(matches!(err, A3SError::Template(_)));
+    }
+
+    #[test]
+    fn test_node_round_trip() {
+        let saved = SavedQuery::new("recent incidents", &QueryOptions::default());
+        let node = to_node(pathway("incidents").unwrap(), &saved).unwrap();
+        assert_eq!(
+            node.pathway.to_string(),
+            "a3s://capability/queries/incidents"
+        );
+        assert_eq!(from_node(&node), Some(saved));
+        assert!(pathway("a/b").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+//! Priority scheduling of queries and bulk work
+//!
+//! Every query and bulk operation takes a slot before touching the store.
+//! Interactive work queues for slots in arrival order; background work
+//! (ingest, sync, rechunking, view refreshes, and queries marked
+//! background) only takes a slot when no interactive request is waiting,
+//! and at most `max_background` background operations run at once, so
+//! maintenance jobs can never occupy every slot.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{Notify, Semaphore, SemaphorePermit};
+
+use crate::config::SchedulerConfig;
+
+/// How urgently a request should run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// An agent or user is waiting on the result
+    #[default]
+    Interactive,
+    /// Maintenance work that can wait for interactive requests
+    Background,
+}
+
+/// Hands out execution slots, interactive requests first
+pub struct Scheduler {
+    slots: Semaphore,
+    background: Semaphore,
+    interactive_waiting: AtomicUsize,
+    released: Notify,
+}
+
+/// A held execution slot, returned to the scheduler on drop
+pub struct Slot<'a> {
+    slot: Option<SemaphorePermit<'a>>,
+    background: Option<SemaphorePermit<'a>>,
+    released: &'a Notify,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        // Return the permits before waking background waiters to retry
+        self.slot.take();
+        self.background.take();
+        self.released.notify_waiters();
+    }
+}
+
+/// Counts an interactive request as waiting until it gets a slot or is
+/// dropped
+struct Waiting<'a>(&'a Scheduler);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.interactive_waiting.fetch_sub(1, Ordering::SeqCst);
+        self.0.released.notify_waiters();
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: &SchedulerConfig) -> Self {
+        let max_concurrent = config.max_concurrent.max(1);
+        Self {
+            slots: Semaphore::new(max_concurrent),
+            background: Semaphore::new(config.max_background.clamp(1, max_concurrent)),
+            interactive_waiting: AtomicUsize::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    /// Wait for a slot at the given priority
+    pub async fn acquire(&self, priority: Priority) -> Slot<'_> {
+        match priority {
+            Priority::Interactive => {
+                self.interactive_waiting.fetch_add(1, Ordering::SeqCst);
+                let waiting = Waiting(self);
+                let slot = self.slots.acquire().await.expect("slots are never closed");
+                drop(waiting);
+                Slot {
+                    slot: Some(slot),
+                    background: None,
+                    released: &self.released,
+                }
+            }
+            Priority::Background => {
+                let background = self
+                    .background
+                    .acquire()
+                    .await
+                    .expect("slots are never closed");
+                loop {
+                    // Register for wakeups before checking, so a release
+                    // between the check and the wait is not missed
+                    let released = self.released.notified();
+                    tokio::pin!(released);
+                    released.as_mut().enable();
+
+                    if self.interactive_waiting.load(Ordering::SeqCst) == 0 {
+                        if let Ok(slot) = self.slots.try_acquire() {
+                            return Slot {
+                                slot: Some(slot),
+                                background: Some(background),
+                                released: &self.released,
+                            };
+                        }
+                    }
+                    released.await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn scheduler(max_concurrent: usize, max_background: usize) -> Arc<Scheduler> {
+        Arc::new(Scheduler::new(&SchedulerConfig {
+            max_concurrent,
+            max_background,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_interactive_runs_before_queued_background() {
+        let scheduler = scheduler(1, 1);
+        let held = scheduler.acquire(Priority::Interactive).await;
+
+        let order = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let background = tokio::spawn({
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            async move {
+                let _slot = scheduler.acquire(Priority::Background).await;
+                order.lock().push(Priority::Background);
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let interactive = tokio::spawn({
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            async move {
+                let _slot = scheduler.acquire(Priority::Interactive).await;
+                order.lock().push(Priority::Interactive);
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        drop(held);
+        interactive.await.unwrap();
+        background.await.unwrap();
+        assert_eq!(
+            *order.lock(),
+            vec![Priority::Interactive, Priority::Background]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_background_capped_below_total() {
+        let scheduler = scheduler(3, 1);
+        let _background = scheduler.acquire(Priority::Background).await;
+
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(20),
+            scheduler.acquire(Priority::Background),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // Interactive requests still find free slots
+        let _a = scheduler.acquire(Priority::Interactive).await;
+        let _b = scheduler.acquire(Priority::Interactive).await;
+    }
+
+    #[tokio::test]
+    async fn test_dropped_interactive_waiter_unblocks_background() {
+        let scheduler = scheduler(1, 1);
+        let held = scheduler.acquire(Priority::Interactive).await;
+
+        // An interactive request gives up while waiting
+        let gave_up = tokio::time::timeout(
+            Duration::from_millis(20),
+            scheduler.acquire(Priority::Interactive),
+        )
+        .await;
+        assert!(gave_up.is_err());
+
+        drop(held);
+        let _slot = tokio::time::timeout(
+            Duration::from_secs(1),
+            scheduler.acquire(Priority::Background),
+        )
+        .await
+        .expect("background work should not wait on an abandoned request");
+    }
+}
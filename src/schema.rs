@@ -0,0 +1,203 @@
+//! JSON Schemas that node metadata must satisfy on write
+//!
+//! A schema is registered for a pathway prefix or for a node kind. Every
+//! schema that applies to a node validates its `metadata.custom` object
+//! before the node is written, so structured records such as capabilities
+//! or memories cannot drift in shape.
+
+use parking_lot::RwLock;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::core::{Node, NodeKind};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+
+/// Nodes a metadata schema applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaScope {
+    /// The node at a pathway and everything below it
+    Prefix(Pathway),
+    /// Every node of a kind
+    Kind(NodeKind),
+}
+
+impl SchemaScope {
+    fn applies_to(&self, node: &Node) -> bool {
+        match self {
+            SchemaScope::Prefix(prefix) => prefix.is_prefix_of(&node.pathway),
+            SchemaScope::Kind(kind) => node.kind == *kind,
+        }
+    }
+}
+
+impl fmt::Display for SchemaScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaScope::Prefix(prefix) => write!(f, "{}", prefix),
+            SchemaScope::Kind(kind) => write!(f, "{} nodes", format!("{:?}", kind).to_lowercase()),
+        }
+    }
+}
+
+/// Registered metadata schemas, checked in registration order
+#[derive(Default)]
+pub struct MetadataSchemas {
+    schemas: RwLock<Vec<(SchemaScope, Arc<jsonschema::Validator>)>>,
+}
+
+impl MetadataSchemas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile and register a schema for the nodes in `scope`
+    pub fn register(&self, scope: SchemaScope, schema: &serde_json::Value) -> Result<()> {
+        let validator = jsonschema::validator_for(schema).map_err(|e| {
+            A3SError::Config(format!("Invalid metadata schema for {}: {}", scope, e))
+        })?;
+        self.schemas.write().push((scope, Arc::new(validator)));
+        Ok(())
+    }
+
+    /// Number of registered schemas
+    pub fn len(&self) -> usize {
+        self.schemas.read().len()
+    }
+
+    /// Check if no schemas are registered
+    pub fn is_empty(&self) -> bool {
+        self.schemas.read().is_empty()
+    }
+
+    /// Validate a node's custom metadata against every schema that applies
+    /// to it, reporting each violation with its location
+    pub fn validate(&self, node: &Node) -> Result<()> {
+        let schemas: Vec<_> = self
+            .schemas
+            .read()
+            .iter()
+            .filter(|(scope, _)| scope.applies_to(node))
+            .cloned()
+            .collect();
+        if schemas.is_empty() {
+            return Ok(());
+        }
+
+        let metadata = serde_json::to_value(&node.metadata.custom)?;
+        let mut violations = Vec::new();
+        for (scope, validator) in &schemas {
+            for error in validator.iter_errors(&metadata) {
+                violations.push(format!(
+                    "metadata{}: {} (schema for {})",
+                    error.instance_path.as_str().replace('/', "."),
+                    error,
+                    scope
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(A3SError::SchemaViolation(format!(
+                "{}: {}",
+                node.pathway,
+                violations.join("; ")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn capability(pathway: &str, metadata: serde_json::Value) -> Node {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Capability,
+            "Search the web".to_string(),
+        );
+        for (key, value) in metadata.as_object().unwrap() {
+            node.metadata.custom.insert(key.clone(), value.clone());
+        }
+        node
+    }
+
+    fn tool_schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "required": ["version"],
+            "properties": {
+                "version": {"type": "string"},
+                "timeout_secs": {"type": "integer", "minimum": 1}
+            }
+        })
+    }
+
+    #[test]
+    fn test_prefix_schema() {
+        let schemas = MetadataSchemas::new();
+        schemas
+            .register(
+                SchemaScope::Prefix(Pathway::parse("a3s://capability/tools").unwrap()),
+                &tool_schema(),
+            )
+            .unwrap();
+        assert_eq!(schemas.len(), 1);
+
+        let valid = capability("a3s://capability/tools/search", json!({"version": "1.2"}));
+        assert!(schemas.validate(&valid).is_ok());
+
+        let outside = capability("a3s://capability/skills/plan", json!({}));
+        assert!(schemas.validate(&outside).is_ok());
+
+        let invalid = capability(
+            "a3s://capability/tools/search",
+            json!({"version": 2, "timeout_secs": 0}),
+        );
+        let err = schemas.validate(&invalid).unwrap_err().to_string();
+        assert!(matches!(
+            schemas.validate(&invalid),
+            Err(A3SError::SchemaViolation(_))
+        ));
+        assert!(err.contains("a3s://capability/tools/search"));
+        assert!(err.contains("metadata.version: 2 is not of type \"string\""));
+        assert!(err.contains("metadata.timeout_secs: 0 is less than the minimum of 1"));
+        assert!(err.contains("(schema for a3s://capability/tools)"));
+    }
+
+    #[test]
+    fn test_kind_schema() {
+        let schemas = MetadataSchemas::new();
+        schemas
+            .register(SchemaScope::Kind(NodeKind::Capability), &tool_schema())
+            .unwrap();
+
+        let err = schemas
+            .validate(&capability("a3s://capability/tools/search", json!({})))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("metadata: \"version\" is a required property"));
+        assert!(err.contains("(schema for capability nodes)"));
+
+        let document = Node::new(
+            Pathway::parse("a3s://knowledge/readme").unwrap(),
+            NodeKind::Document,
+            String::new(),
+        );
+        assert!(schemas.validate(&document).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        let schemas = MetadataSchemas::new();
+        let err = schemas
+            .register(SchemaScope::Kind(NodeKind::Memory), &json!({"type": 5}))
+            .unwrap_err();
+        assert!(matches!(err, A3SError::Config(_)));
+        assert!(schemas.is_empty());
+    }
+}
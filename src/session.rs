@@ -5,27 +5,77 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::clock::{Clock, SystemClock};
 use crate::config::Config;
+use crate::core::{Namespace, Node, NodeKind};
+#[cfg(feature = "llm-digest")]
+use crate::digest::LLMClient;
 use crate::embedding::Embedder;
-use crate::error::Result;
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+use crate::lease::LeaseTable;
 use crate::pathway::Pathway;
+use crate::policy::PolicyChain;
+use crate::retrieval::{cosine_similarity, Retriever};
+use crate::snapshot::{self, RetrievalSnapshot};
 use crate::storage::StorageBackend;
+use crate::tokenizer::{HeuristicTokenizer, Tokenizer};
+use crate::window::ContextBuilder;
+use crate::{QueryOptions, QueryResult};
+
+/// Segment under a session that holds its committed messages
+pub const MESSAGES_SEGMENT: &str = "messages";
+
+/// Metadata key on a session's root node holding its [`SessionInfo`]
+pub const SESSION_KEY: &str = "session";
+
+/// Metadata key on a committed message holding its [`MessageInfo`]
+pub const MESSAGE_KEY: &str = "message";
+
+/// Words that refer back to something mentioned earlier in the conversation
+const REFERRING_WORDS: &[&str] = &[
+    "it", "its", "it's", "they", "them", "their", "this", "that", "these", "those", "he", "she",
+    "his", "her", "there",
+];
+
+/// Openings that continue the previous question
+const FOLLOW_UP_OPENINGS: &[&str] = &["what about", "how about", "and ", "also "];
+
+/// Memories retrieved for a turn's context
+const TURN_MEMORIES: usize = 5;
+
+/// Longest line of an extractive summary, in tokens
+const SUMMARY_LINE_TOKENS: usize = 40;
+
+const SUMMARY_HEADING: &str = "Summary of the conversation so far:";
+const MEMORIES_HEADING: &str = "What you remember about the user:";
+const KNOWLEDGE_HEADING: &str = "Relevant knowledge:";
 
 /// A conversation session
 #[derive(Clone)]
 pub struct Session {
     id: String,
-    #[allow(dead_code)]
     user: String,
-    #[allow(dead_code)]
     created_at: DateTime<Utc>,
+    /// The session has been stored, by a commit or before a resume
+    stored: bool,
     messages: Vec<Message>,
-    #[allow(dead_code)]
+    /// Embeddings of the leading messages already stored, in order
+    embeddings: Vec<Vec<f32>>,
     storage: Arc<dyn StorageBackend>,
-    #[allow(dead_code)]
     embedder: Arc<dyn Embedder>,
-    #[allow(dead_code)]
     config: Config,
+    retriever: Arc<Retriever>,
+    tokenizer: Arc<dyn Tokenizer>,
+    policies: Arc<PolicyChain>,
+    leases: Arc<LeaseTable>,
+    clock: Arc<dyn Clock>,
+    /// Running summary of the leading messages that no longer fit a turn
+    summary: Option<String>,
+    /// Leading messages covered by `summary`
+    summarized: usize,
+    #[cfg(feature = "llm-digest")]
+    llm: Option<Arc<LLMClient>>,
 }
 
 impl Session {
@@ -34,31 +84,120 @@ impl Session {
         storage: Arc<dyn StorageBackend>,
         embedder: Arc<dyn Embedder>,
         config: &Config,
+        http: &HttpClient,
     ) -> Result<Self> {
         let id = id
             .map(|s| s.to_string())
             .unwrap_or_else(|| Uuid::new_v4().to_string());
 
+        let retriever = Retriever::new(storage.clone(), embedder.clone(), &config.retrieval, http);
+
         Ok(Self {
             id,
             user: "default".to_string(),
             created_at: Utc::now(),
+            stored: false,
             messages: Vec::new(),
+            embeddings: Vec::new(),
             storage,
             embedder,
             config: config.clone(),
+            retriever: Arc::new(retriever),
+            tokenizer: Arc::new(HeuristicTokenizer),
+            policies: Arc::new(PolicyChain::new()),
+            leases: Arc::default(),
+            clock: Arc::new(SystemClock),
+            summary: None,
+            summarized: 0,
+            #[cfg(feature = "llm-digest")]
+            llm: None,
         })
     }
 
+    /// Reload a committed session and its messages from storage
+    pub async fn resume(
+        id: &str,
+        storage: Arc<dyn StorageBackend>,
+        embedder: Arc<dyn Embedder>,
+        config: &Config,
+        http: &HttpClient,
+    ) -> Result<Self> {
+        let info = load_info(storage.as_ref(), id).await?;
+        let committed = load_committed(storage.as_ref(), id).await?;
+        let mut session = Self::new(Some(id), storage, embedder, config, http).await?;
+        session.user = info.user;
+        session.created_at = info.created_at;
+        session.stored = true;
+        (session.messages, session.embeddings) = committed.into_iter().unzip();
+        Ok(session)
+    }
+
+    /// Retrieve with the given retriever instead of a default one
+    pub fn with_retriever(mut self, retriever: Retriever) -> Self {
+        self.retriever = Arc::new(retriever);
+        self
+    }
+
+    /// Measure turn context with the given tokenizer instead of estimating
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Apply the given write policies to the session and its messages when
+    /// committed
+    pub fn with_policies(mut self, policies: Arc<PolicyChain>) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Refuse to commit or compact while another writer leases the session
+    pub fn with_leases(mut self, leases: Arc<LeaseTable>) -> Self {
+        self.leases = leases;
+        self
+    }
+
+    /// Stamp messages by `clock` instead of the system clock, and the
+    /// session's creation too unless it is already stored
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        if !self.stored {
+            self.created_at = clock.now();
+        }
+        self.clock = clock;
+        self
+    }
+
+    /// Attribute the session to a user, whose memories turn context draws on
+    pub fn with_user(mut self, user: &str) -> Self {
+        self.user = user.to_string();
+        self
+    }
+
+    /// Resolve references in follow-up queries and summarize earlier turns
+    /// with an LLM call
+    #[cfg(feature = "llm-digest")]
+    pub fn with_llm(mut self, llm: LLMClient) -> Self {
+        self.llm = Some(Arc::new(llm));
+        self
+    }
+
     pub fn id(&self) -> &str {
         &self.id
     }
 
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
     pub fn add_message(&mut self, role: MessageRole, content: String) {
         self.messages.push(Message {
             role,
             content,
-            timestamp: Utc::now(),
+            timestamp: self.clock.now(),
             contexts_used: Vec::new(),
         });
     }
@@ -67,14 +206,646 @@ impl Session {
         &self.messages
     }
 
+    /// Running summary of the earlier messages left out of turn context
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    /// Search with the query rewritten against recent conversation history
+    pub async fn retrieve(&self, query: &str) -> Result<QueryResult> {
+        self.retrieve_with_options(query, QueryOptions::default())
+            .await
+    }
+
+    /// Search with options, rewriting the query against recent history
+    ///
+    /// With `session.snapshot_retrievals` set, each result is also stored
+    /// under this session's `retrievals` for replay.
+    pub async fn retrieve_with_options(
+        &self,
+        query: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult> {
+        let query = self.rewrite_query(query).await;
+        let result = self.retriever.search(&query, Some(options)).await?;
+        if self.config.session.snapshot_retrievals {
+            snapshot::record(self.storage.as_ref(), &self.id, &query, &result).await?;
+        }
+        Ok(result)
+    }
+
+    /// Make a follow-up query self-contained using recent messages
+    ///
+    /// Queries without history, or that do not refer back to it, are
+    /// returned unchanged.
+    pub async fn rewrite_query(&self, query: &str) -> String {
+        let turns = self.config.session.history_turns;
+        let start = self.messages.len().saturating_sub(turns);
+        let history = &self.messages[start..];
+        if history.is_empty() || !is_follow_up(query) {
+            return query.to_string();
+        }
+
+        #[cfg(feature = "llm-digest")]
+        if let Some(llm) = &self.llm {
+            match rewrite_with_llm(llm, history, query).await {
+                Ok(rewritten) if !rewritten.is_empty() => return rewritten,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("LLM query rewrite failed: {}, concatenating", e),
+            }
+        }
+
+        concat_history(history, query)
+    }
+
+    /// Messages to send for the next turn, within `token_budget`: a system
+    /// message with a running summary of earlier turns, the user's
+    /// memories, and knowledge relevant to `user_message`, then the most
+    /// recent messages and `user_message` itself
+    ///
+    /// Half the budget left after `user_message` goes to the conversation:
+    /// recent messages, newest first, take up to three quarters of it and
+    /// the summary covers the older ones. Memories take up to a third of
+    /// what remains and knowledge matches fill the rest, each at the digest
+    /// level its share allows. `user_message` is not added to the session,
+    /// nor repeated when it already is the last message.
+    pub async fn context_for_next_turn(
+        &mut self,
+        user_message: &str,
+        token_budget: usize,
+    ) -> Result<TurnContext> {
+        let tokenizer = self.tokenizer.clone();
+        let count = |text: &str| tokenizer.count(text);
+        let mut history_len = self.messages.len();
+        if self
+            .messages
+            .last()
+            .is_some_and(|m| m.role == MessageRole::User && m.content == user_message)
+        {
+            history_len -= 1;
+        }
+        let remaining = token_budget.saturating_sub(count(user_message));
+
+        let history_budget = remaining / 2;
+        let mut start = history_len;
+        let mut recent_tokens = 0;
+        while start > 0 {
+            let tokens = count(&self.messages[start - 1].content);
+            if recent_tokens + tokens > history_budget * 3 / 4 {
+                break;
+            }
+            recent_tokens += tokens;
+            start -= 1;
+        }
+        // Section separators and headings are measured with their sections
+        let system_budget = remaining - recent_tokens;
+        let section_budget =
+            |budget: usize, heading: &str| budget.saturating_sub(count(heading) + 2);
+        let mut sections = Vec::new();
+
+        if start > 0 {
+            self.summarize_until(start).await;
+            let budget = section_budget(history_budget - recent_tokens, SUMMARY_HEADING);
+            let summary = self.summary.as_deref().unwrap_or_default();
+            // The end of the summary covers the most recent of the earlier turns
+            let kept = tokenizer.truncate_start(summary, budget).trim();
+            if !kept.is_empty() {
+                sections.push(format!("{}\n{}", SUMMARY_HEADING, kept));
+            }
+        }
+
+        let query = self.rewrite_query(user_message).await;
+        let mut memories = Vec::new();
+        let used = count(&sections.join("\n\n"));
+        let budget = section_budget(system_budget.saturating_sub(used) / 3, MEMORIES_HEADING);
+        if budget > 0 {
+            let found = self
+                .retriever
+                .search(
+                    &query,
+                    Some(QueryOptions {
+                        namespace: Some(Namespace::Memory),
+                        limit: Some(TURN_MEMORIES),
+                        include_content: true,
+                        pathway_filter: Some(format!("{}/", Pathway::memory(&self.user)?)),
+                        ..Default::default()
+                    }),
+                )
+                .await?;
+            let mut lines = String::new();
+            for m in &found.matches {
+                let fact = m.content.as_deref().unwrap_or(&m.brief).trim();
+                let line = format!("- {}\n", fact);
+                if count(&format!("{}{}", lines, line)) > budget {
+                    continue;
+                }
+                lines.push_str(&line);
+                memories.push(m.pathway.clone());
+            }
+            if !lines.is_empty() {
+                sections.push(format!("{}\n{}", MEMORIES_HEADING, lines.trim_end()));
+            }
+        }
+
+        let mut knowledge = Vec::new();
+        let used = count(&sections.join("\n\n"));
+        let budget = section_budget(system_budget.saturating_sub(used), KNOWLEDGE_HEADING);
+        if budget > 0 {
+            let result = self
+                .retriever
+                .search(
+                    &query,
+                    Some(QueryOptions {
+                        namespace: Some(Namespace::Knowledge),
+                        ..Default::default()
+                    }),
+                )
+                .await?;
+            if self.config.session.snapshot_retrievals {
+                snapshot::record(self.storage.as_ref(), &self.id, &query, &result).await?;
+            }
+            let window = ContextBuilder::new(self.storage.clone(), tokenizer.clone(), budget)
+                .build(&result.matches)
+                .await?;
+            if !window.entries.is_empty() {
+                sections.push(format!("{}\n{}", KNOWLEDGE_HEADING, window.text.trim_end()));
+                knowledge = window.entries.into_iter().map(|e| e.pathway).collect();
+            }
+        }
+
+        let mut messages = Vec::new();
+        if !sections.is_empty() {
+            messages.push(PromptMessage {
+                role: MessageRole::System,
+                content: sections.join("\n\n"),
+            });
+        }
+        messages.extend(
+            self.messages[start..history_len]
+                .iter()
+                .map(|m| PromptMessage {
+                    role: m.role,
+                    content: m.content.clone(),
+                }),
+        );
+        messages.push(PromptMessage {
+            role: MessageRole::User,
+            content: user_message.to_string(),
+        });
+        let tokens = messages.iter().map(|m| count(&m.content)).sum();
+        Ok(TurnContext {
+            messages,
+            summarized: start,
+            memories,
+            knowledge,
+            tokens,
+        })
+    }
+
+    /// Extend the running summary to cover the messages before `end`
+    async fn summarize_until(&mut self, end: usize) {
+        if end <= self.summarized {
+            return;
+        }
+        let new = &self.messages[self.summarized..end];
+
+        #[cfg(feature = "llm-digest")]
+        if let Some(llm) = &self.llm {
+            match summarize_with_llm(llm, self.summary.as_deref(), new).await {
+                Ok(summary) if !summary.is_empty() => {
+                    self.summary = Some(summary);
+                    self.summarized = end;
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("LLM summary failed: {}, summarizing extractively", e),
+            }
+        }
+
+        let lines: Vec<String> = new
+            .iter()
+            .map(|m| {
+                let sentence = first_sentence(&m.content);
+                format!(
+                    "{:?}: {}",
+                    m.role,
+                    self.tokenizer.truncate(sentence, SUMMARY_LINE_TOKENS)
+                )
+            })
+            .collect();
+        let lines = lines.join("\n");
+        self.summary = Some(match self.summary.take() {
+            Some(summary) => format!("{}\n{}", summary, lines),
+            None => lines,
+        });
+        self.summarized = end;
+    }
+
+    /// Store the session at `a3s://session/<id>` and each message added
+    /// since the last commit, embedded, under its `messages`, so it can be
+    /// resumed, replayed, and searched
+    ///
+    /// Every node passes the write policies before any is stored, and
+    /// messages are embedded as the policies left them.
     pub async fn commit(&mut self) -> Result<()> {
-        // Save session to storage
-        let _pathway = Pathway::parse(&format!("a3s://session/{}", self.id))?;
+        let pathway = snapshot::session_root(&self.id)?;
+        self.leases.check(&pathway, true)?;
+        let mut root = Node::new(pathway, NodeKind::Data, String::new());
+        root.created_at = self.created_at;
+        let info = SessionInfo {
+            user: self.user.clone(),
+            created_at: self.created_at,
+        };
+        root.metadata
+            .custom
+            .insert(SESSION_KEY.to_string(), serde_json::to_value(info)?);
+        let root = self.policies.apply(root).await?;
 
-        // TODO: Implement session persistence
+        let start = self.embeddings.len();
+        let mut nodes = Vec::with_capacity(self.messages.len() - start);
+        for (index, message) in self.messages.iter().enumerate().skip(start) {
+            let mut node = Node::new(
+                message_pathway(&self.id, index)?,
+                NodeKind::Message,
+                message.content.clone(),
+            );
+            node.created_at = message.timestamp;
+            let info = MessageInfo {
+                index,
+                role: message.role,
+                timestamp: message.timestamp,
+                contexts_used: message.contexts_used.clone(),
+            };
+            node.metadata
+                .custom
+                .insert(MESSAGE_KEY.to_string(), serde_json::to_value(info)?);
+            nodes.push(self.policies.apply(node).await?);
+        }
 
+        // Embed what the policies let through, e.g. redacted content
+        if !nodes.is_empty() {
+            let contents: Vec<String> = nodes.iter().map(|n| n.content.clone()).collect();
+            let embeddings = self.embedder.embed_batch(&contents).await?;
+            for (node, embedding) in nodes.iter_mut().zip(embeddings) {
+                node.embedding = embedding;
+            }
+        }
+
+        self.storage.put(&root).await?;
+        self.storage.put_batch(&nodes).await?;
+        self.stored = true;
+        self.embeddings
+            .extend(nodes.into_iter().map(|node| node.embedding));
         Ok(())
     }
+
+    /// Fold the messages before the last `keep` into one system message
+    /// holding their summary, and store the shorter session in place of
+    /// the old one
+    ///
+    /// A leading summary from an earlier compaction is extended rather than
+    /// summarized again. Messages added since the last commit are committed
+    /// with the rest. Returns how many messages were folded.
+    pub async fn compact(&mut self, keep: usize) -> Result<usize> {
+        // Check before folding, so a refused compaction leaves the session as it was
+        self.leases
+            .check(&snapshot::session_root(&self.id)?, true)?;
+        let seed = self
+            .messages
+            .first()
+            .filter(|m| m.role == MessageRole::System)
+            .and_then(|m| m.content.strip_prefix(SUMMARY_HEADING))
+            .map(|summary| summary.trim().to_string());
+        let end = self.messages.len().saturating_sub(keep);
+        if end <= usize::from(seed.is_some()) {
+            return Ok(0);
+        }
+
+        self.summarized = usize::from(seed.is_some());
+        self.summary = seed;
+        self.summarize_until(end).await;
+        let summary = self.summary.clone().unwrap_or_default();
+        let message = Message {
+            role: MessageRole::System,
+            content: format!("{}\n{}", SUMMARY_HEADING, summary),
+            timestamp: self.messages[end - 1].timestamp,
+            contexts_used: Vec::new(),
+        };
+        self.messages.splice(..end, [message]);
+        self.summarized = 1;
+
+        // Every message moves to a new index, so all are stored again and
+        // the indices past the new end removed
+        let stored = self.embeddings.len();
+        self.embeddings.clear();
+        self.commit().await?;
+        for index in self.messages.len()..stored {
+            self.storage
+                .remove(&message_pathway(&self.id, index)?, false)
+                .await?;
+        }
+        Ok(end)
+    }
+
+    /// Earlier messages most similar in meaning to `query`, best first
+    ///
+    /// Committed messages are compared by the embeddings stored with them;
+    /// messages added since the last commit are embedded for the search.
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<MessageMatch>> {
+        if self.messages.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let pending: Vec<String> = self.messages[self.embeddings.len()..]
+            .iter()
+            .map(|m| m.content.clone())
+            .collect();
+        let mut embeddings = self.embeddings.clone();
+        if !pending.is_empty() {
+            embeddings.extend(self.embedder.embed_batch(&pending).await?);
+        }
+        let query = self.embedder.embed(query).await?;
+
+        let mut matches: Vec<MessageMatch> = self
+            .messages
+            .iter()
+            .zip(&embeddings)
+            .enumerate()
+            .map(|(index, (message, embedding))| MessageMatch {
+                index,
+                message: message.clone(),
+                score: cosine_similarity(&query, embedding),
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+}
+
+/// A message ready to send to a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptMessage {
+    pub role: MessageRole,
+    pub content: String,
+}
+
+/// Context assembled by [`Session::context_for_next_turn`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnContext {
+    /// A system message when there is context for one, the recent
+    /// messages, then the user's message
+    pub messages: Vec<PromptMessage>,
+    /// Leading messages left out and represented by the running summary
+    pub summarized: usize,
+    /// Memories included in the system message
+    pub memories: Vec<Pathway>,
+    /// Knowledge nodes included in the system message
+    pub knowledge: Vec<Pathway>,
+    /// Tokens across all messages
+    pub tokens: usize,
+}
+
+/// An earlier message found by [`Session::search`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageMatch {
+    /// Position of the message in the session
+    pub index: usize,
+    pub message: Message,
+    pub score: f32,
+}
+
+/// Who a committed session belongs to and when it started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub user: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SessionInfo {
+    /// Info recorded on a session's root node
+    pub fn of(node: &Node) -> Option<Self> {
+        serde_json::from_value(node.metadata.custom.get(SESSION_KEY)?.clone()).ok()
+    }
+}
+
+/// A committed session as listed, without its messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSession {
+    pub id: String,
+    pub user: String,
+    pub created_at: DateTime<Utc>,
+    /// Number of committed messages
+    pub messages: usize,
+    /// When the last committed message was added, if any was
+    pub last_message_at: Option<DateTime<Utc>>,
+}
+
+/// Everything stored for a session: its messages and the retrievals
+/// recorded under it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExport {
+    pub id: String,
+    pub user: String,
+    pub created_at: DateTime<Utc>,
+    pub messages: Vec<Message>,
+    pub retrievals: Vec<RetrievalSnapshot>,
+}
+
+/// Every committed session, most recently active first
+pub async fn stored_sessions(storage: &dyn StorageBackend) -> Result<Vec<StoredSession>> {
+    let root = Pathway::root(Namespace::Session);
+    storage.warm(&root).await?;
+    let mut sessions = Vec::new();
+    for node in storage.get_children(&root, 1).await? {
+        let (Some(id), Some(info)) = (node.pathway.name(), SessionInfo::of(&node)) else {
+            continue;
+        };
+        let messages: Vec<MessageInfo> = storage
+            .get_children(&messages_pathway(id)?, 1)
+            .await?
+            .iter()
+            .filter_map(|node| {
+                serde_json::from_value(node.metadata.custom.get(MESSAGE_KEY)?.clone()).ok()
+            })
+            .collect();
+        sessions.push(StoredSession {
+            id: id.to_string(),
+            user: info.user,
+            created_at: info.created_at,
+            messages: messages.len(),
+            last_message_at: messages.iter().map(|m| m.timestamp).max(),
+        });
+    }
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.last_message_at.unwrap_or(s.created_at)));
+    Ok(sessions)
+}
+
+/// Info of a committed session
+pub(crate) async fn load_info(
+    storage: &dyn StorageBackend,
+    session_id: &str,
+) -> Result<SessionInfo> {
+    match storage.get(&snapshot::session_root(session_id)?).await {
+        Ok(node) => SessionInfo::of(&node),
+        Err(A3SError::NodeNotFound(_)) => None,
+        Err(e) => return Err(e),
+    }
+    .ok_or_else(|| A3SError::Session(format!("Session was never committed: {}", session_id)))
+}
+
+/// A committed message's place in its session, besides its content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageInfo {
+    pub index: usize,
+    pub role: MessageRole,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub contexts_used: Vec<String>,
+}
+
+/// Directory of a session's committed messages
+pub fn messages_pathway(session_id: &str) -> Result<Pathway> {
+    Ok(snapshot::session_root(session_id)?.join(MESSAGES_SEGMENT))
+}
+
+/// Pathway of a session's message at `index`, e.g. `messages/message-0001`
+pub fn message_pathway(session_id: &str, index: usize) -> Result<Pathway> {
+    Ok(messages_pathway(session_id)?.join(&format!("message-{:04}", index + 1)))
+}
+
+/// Messages committed for a session, in order; none if it was never
+/// committed
+pub async fn load_messages(storage: &dyn StorageBackend, session_id: &str) -> Result<Vec<Message>> {
+    let committed = load_committed(storage, session_id).await?;
+    Ok(committed.into_iter().map(|(message, _)| message).collect())
+}
+
+/// Committed messages in order, each with its stored embedding
+async fn load_committed(
+    storage: &dyn StorageBackend,
+    session_id: &str,
+) -> Result<Vec<(Message, Vec<f32>)>> {
+    let root = messages_pathway(session_id)?;
+    // Stores that load lazily may not have read a resumed session yet
+    storage.warm(&root).await?;
+    let mut messages: Vec<(usize, Message, Vec<f32>)> = storage
+        .get_children(&root, 1)
+        .await?
+        .into_iter()
+        .filter_map(|node| {
+            let info: MessageInfo =
+                serde_json::from_value(node.metadata.custom.get(MESSAGE_KEY)?.clone()).ok()?;
+            Some((
+                info.index,
+                Message {
+                    role: info.role,
+                    content: node.content,
+                    timestamp: info.timestamp,
+                    contexts_used: info.contexts_used,
+                },
+                node.embedding,
+            ))
+        })
+        .collect();
+    messages.sort_by_key(|(index, _, _)| *index);
+    Ok(messages
+        .into_iter()
+        .map(|(_, message, embedding)| (message, embedding))
+        .collect())
+}
+
+/// One step of a recorded session
+#[derive(Debug, Clone)]
+pub enum ReplayEvent {
+    Message(Message),
+    Retrieval(Box<RetrievalSnapshot>),
+}
+
+impl ReplayEvent {
+    /// When the step happened
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            ReplayEvent::Message(message) => message.timestamp,
+            ReplayEvent::Retrieval(snapshot) => snapshot.taken_at,
+        }
+    }
+}
+
+/// Whether a query leans on earlier turns to be understood
+fn is_follow_up(query: &str) -> bool {
+    let query = query.trim().to_lowercase();
+    if FOLLOW_UP_OPENINGS
+        .iter()
+        .any(|opening| query.starts_with(opening))
+    {
+        return true;
+    }
+    query
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .any(|word| REFERRING_WORDS.contains(&word))
+}
+
+/// Text up to the end of the first sentence or line
+fn first_sentence(text: &str) -> &str {
+    let text = text.trim();
+    let end = text
+        .char_indices()
+        .find(|&(i, c)| {
+            c == '\n'
+                || (matches!(c, '.' | '?' | '!') && text[i + 1..].starts_with(char::is_whitespace))
+        })
+        .map(|(i, c)| if c == '\n' { i } else { i + 1 })
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Prefix the query with the user's recent questions
+fn concat_history(history: &[Message], query: &str) -> String {
+    let mut parts: Vec<&str> = history
+        .iter()
+        .filter(|m| m.role == MessageRole::User)
+        .map(|m| m.content.trim())
+        .filter(|content| *content != query.trim())
+        .collect();
+    parts.push(query.trim());
+    parts.join(" ")
+}
+
+#[cfg(feature = "llm-digest")]
+async fn rewrite_with_llm(llm: &LLMClient, history: &[Message], query: &str) -> Result<String> {
+    let transcript: String = history
+        .iter()
+        .map(|m| format!("{:?}: {}\n", m.role, m.content))
+        .collect();
+    let prompt = format!(
+        "Rewrite the final question so it can be understood without the \
+         conversation, replacing pronouns and references with what they \
+         refer to. Answer with the rewritten question only.\n\n\
+         Conversation:\n{}\nQuestion: {}",
+        transcript, query
+    );
+    Ok(llm.complete(&prompt).await?.trim().to_string())
+}
+
+#[cfg(feature = "llm-digest")]
+async fn summarize_with_llm(
+    llm: &LLMClient,
+    summary: Option<&str>,
+    messages: &[Message],
+) -> Result<String> {
+    let transcript: String = messages
+        .iter()
+        .map(|m| format!("{:?}: {}\n", m.role, m.content))
+        .collect();
+    let prompt = format!(
+        "Update the summary of a conversation with its newer messages. \
+         Keep facts, decisions, and open questions, in a few sentences. \
+         Answer with the summary only.\n\n\
+         Summary so far:\n{}\n\nNewer messages:\n{}",
+        summary.unwrap_or("(none)"),
+        transcript
+    );
+    Ok(llm.complete(&prompt).await?.trim().to_string())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,11 +876,17 @@ mod tests {
         Arc::new(MockEmbedder::new(128))
     }
 
+    fn create_test_http() -> HttpClient {
+        crate::http::build_client(&Default::default()).unwrap()
+    }
+
     fn create_test_storage() -> Arc<dyn StorageBackend> {
         let config = VectorIndexConfig {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         Arc::new(MemoryStorage::new(&config))
     }
@@ -120,9 +897,15 @@ mod tests {
         let embedder = create_test_embedder();
         let config = Config::default();
 
-        let session = Session::new(Some("test-session-id"), storage, embedder, &config)
-            .await
-            .unwrap();
+        let session = Session::new(
+            Some("test-session-id"),
+            storage,
+            embedder,
+            &config,
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(session.id(), "test-session-id");
         assert_eq!(session.messages().len(), 0);
@@ -134,7 +917,7 @@ mod tests {
         let embedder = create_test_embedder();
         let config = Config::default();
 
-        let session = Session::new(None, storage, embedder, &config)
+        let session = Session::new(None, storage, embedder, &config, &create_test_http())
             .await
             .unwrap();
 
@@ -148,7 +931,7 @@ mod tests {
         let embedder = create_test_embedder();
         let config = Config::default();
 
-        let mut session = Session::new(None, storage, embedder, &config)
+        let mut session = Session::new(None, storage, embedder, &config, &create_test_http())
             .await
             .unwrap();
 
@@ -168,7 +951,7 @@ mod tests {
         let embedder = create_test_embedder();
         let config = Config::default();
 
-        let mut session = Session::new(None, storage, embedder, &config)
+        let mut session = Session::new(None, storage, embedder, &config, &create_test_http())
             .await
             .unwrap();
 
@@ -177,6 +960,255 @@ mod tests {
         // Commit should not fail
         let result = session.commit().await;
         assert!(result.is_ok());
+
+        let stored = load_messages(session.storage.as_ref(), session.id())
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].content, "Test message");
+
+        // Later commits add only the new messages, after the earlier ones
+        session.add_message(MessageRole::Assistant, "Reply".to_string());
+        session.commit().await.unwrap();
+        let stored = load_messages(session.storage.as_ref(), session.id())
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1].role, MessageRole::Assistant);
+        assert!(load_messages(session.storage.as_ref(), "never-committed")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_context_for_next_turn_summarizes_earlier_messages() {
+        let mut session = Session::new(
+            None,
+            create_test_storage(),
+            create_test_embedder(),
+            &Config::default(),
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
+        for i in 0..10 {
+            session.add_message(
+                MessageRole::User,
+                format!("Question {} about deploys. {}", i, "Details ".repeat(20)),
+            );
+            session.add_message(MessageRole::Assistant, format!("Answer {}.", i));
+        }
+        session.add_message(MessageRole::User, "What changed?".to_string());
+
+        let context = session
+            .context_for_next_turn("What changed?", 200)
+            .await
+            .unwrap();
+        assert!(context.summarized > 0);
+        assert!(context.tokens <= 200);
+        let system = &context.messages[0];
+        assert_eq!(system.role, MessageRole::System);
+        assert!(system.content.starts_with(SUMMARY_HEADING));
+        assert!(session
+            .summary()
+            .unwrap()
+            .starts_with("User: Question 0 about deploys."));
+
+        // Recent messages follow in order, ending with the user's message once
+        let last = context.messages.last().unwrap();
+        assert_eq!(last.content, "What changed?");
+        assert_eq!(
+            context.messages[context.messages.len() - 2].content,
+            "Answer 9."
+        );
+        assert_eq!(context.messages.len(), 1 + (20 - context.summarized) + 1,);
+    }
+
+    #[test]
+    fn test_first_sentence() {
+        assert_eq!(
+            first_sentence(" Deploys run nightly. Ask ops."),
+            "Deploys run nightly."
+        );
+        assert_eq!(first_sentence("v1.2 ships\nlater"), "v1.2 ships");
+        assert_eq!(first_sentence("No end"), "No end");
+    }
+
+    #[tokio::test]
+    async fn test_session_resume() {
+        let storage = create_test_storage();
+        let config = Config::default();
+        let mut session = Session::new(
+            Some("support-42"),
+            storage.clone(),
+            create_test_embedder(),
+            &config,
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
+        session.add_message(MessageRole::User, "Where is the deploy guide?".to_string());
+        session.commit().await.unwrap();
+
+        let resumed = Session::resume(
+            "support-42",
+            storage.clone(),
+            create_test_embedder(),
+            &config,
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resumed.messages().len(), 1);
+        assert_eq!(resumed.messages()[0].content, "Where is the deploy guide?");
+        assert_eq!(resumed.created_at(), session.created_at());
+        assert_eq!(resumed.user(), "default");
+
+        let err = Session::resume(
+            "never-committed",
+            storage,
+            create_test_embedder(),
+            &config,
+            &create_test_http(),
+        )
+        .await;
+        assert!(matches!(err, Err(A3SError::Session(_))));
+    }
+
+    #[tokio::test]
+    async fn test_compact_folds_older_messages() {
+        let storage = create_test_storage();
+        let config = Config::default();
+        let mut session = Session::new(
+            Some("long-run"),
+            storage.clone(),
+            create_test_embedder(),
+            &config,
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
+        for i in 0..6 {
+            session.add_message(MessageRole::User, format!("Question {}. More detail.", i));
+        }
+        session.commit().await.unwrap();
+
+        assert_eq!(session.compact(2).await.unwrap(), 4);
+        let stored = load_messages(storage.as_ref(), "long-run").await.unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored[0].role, MessageRole::System);
+        assert!(stored[0].content.starts_with(SUMMARY_HEADING));
+        assert!(stored[0].content.contains("Question 3."));
+        assert!(!stored[0].content.contains("More detail"));
+        assert_eq!(stored[2].content, "Question 5. More detail.");
+
+        // A second compaction extends the summary instead of summarizing it
+        session.add_message(MessageRole::User, "Question 6.".to_string());
+        assert_eq!(session.compact(1).await.unwrap(), 3);
+        let stored = load_messages(storage.as_ref(), "long-run").await.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored[0].content.contains("Question 0."));
+        assert!(stored[0].content.contains("Question 5."));
+        assert_eq!(session.compact(1).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_search() {
+        let storage = create_test_storage();
+        let config = Config::default();
+        let mut session = Session::new(
+            Some("long-chat"),
+            storage.clone(),
+            create_test_embedder(),
+            &config,
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
+        assert!(session.search("anything", 3).await.unwrap().is_empty());
+
+        session.add_message(
+            MessageRole::User,
+            "Our staging cluster is in Frankfurt".to_string(),
+        );
+        session.add_message(
+            MessageRole::Assistant,
+            "Noted, staging is in Frankfurt".to_string(),
+        );
+        session.add_message(MessageRole::User, "Deploys run every Tuesday".to_string());
+        session.commit().await.unwrap();
+        session.add_message(
+            MessageRole::User,
+            "The billing API rate limit is 100/s".to_string(),
+        );
+
+        let matches = session
+            .search("Deploys run every Tuesday", 2)
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].index, 2);
+        assert!(matches[0].score > matches[1].score);
+
+        // Messages added since the last commit are searched too
+        let matches = session
+            .search("The billing API rate limit is 100/s", 1)
+            .await
+            .unwrap();
+        assert_eq!(matches[0].index, 3);
+
+        // A resumed session searches the embeddings stored with its messages
+        let resumed = Session::resume(
+            "long-chat",
+            storage,
+            create_test_embedder(),
+            &config,
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
+        let matches = resumed
+            .search("Our staging cluster is in Frankfurt", 1)
+            .await
+            .unwrap();
+        assert_eq!(matches[0].message.role, MessageRole::User);
+        assert_eq!(matches[0].index, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_query_resolves_follow_ups() {
+        let config = Config::default();
+        let mut session = Session::new(
+            None,
+            create_test_storage(),
+            create_test_embedder(),
+            &config,
+            &create_test_http(),
+        )
+        .await
+        .unwrap();
+
+        // Without history there is nothing to resolve against
+        assert_eq!(
+            session.rewrite_query("what about its rate limits?").await,
+            "what about its rate limits?"
+        );
+
+        session.add_message(
+            MessageRole::User,
+            "How does the billing API work?".to_string(),
+        );
+        session.add_message(MessageRole::Assistant, "It uses REST.".to_string());
+
+        assert_eq!(
+            session.rewrite_query("what about its rate limits?").await,
+            "How does the billing API work? what about its rate limits?"
+        );
+        assert_eq!(
+            session.rewrite_query("postgres connection pooling").await,
+            "postgres connection pooling"
+        );
     }
 
     #[test]
@@ -0,0 +1,122 @@
+//! Query result snapshots for replaying agent runs
+//!
+//! A snapshot stores a query's full result, with when it ran and how large
+//! the store was, as a data node at
+//! `a3s://session/<session>/retrievals/<query_id>`. Snapshot nodes carry no
+//! embedding, so they never show up in search results themselves.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Node, NodeKind};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+use crate::QueryResult;
+
+/// Segment under a session that holds its retrieval snapshots
+pub const RETRIEVALS_SEGMENT: &str = "retrievals";
+
+/// A query's result as an agent saw it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalSnapshot {
+    /// Query text as searched, after any session rewriting
+    pub query: String,
+    pub result: QueryResult,
+    pub taken_at: DateTime<Utc>,
+    /// Nodes in the store when the query ran
+    pub store_nodes: u64,
+}
+
+/// Directory holding everything recorded for a session
+pub fn session_root(session_id: &str) -> Result<Pathway> {
+    let session = Pathway::parse(&format!("a3s://session/{}", session_id))?;
+    if session.depth() != 1 {
+        return Err(A3SError::InvalidPathway(format!(
+            "Invalid session id: {}",
+            session_id
+        )));
+    }
+    Ok(session)
+}
+
+/// Directory holding a session's snapshots
+pub fn retrievals_root(session_id: &str) -> Result<Pathway> {
+    Ok(session_root(session_id)?.join(RETRIEVALS_SEGMENT))
+}
+
+/// Pathway of one query's snapshot
+pub fn snapshot_pathway(session_id: &str, query_id: &str) -> Result<Pathway> {
+    Ok(retrievals_root(session_id)?.join(query_id))
+}
+
+/// Store a query's result under its session
+pub async fn record(
+    storage: &dyn StorageBackend,
+    session_id: &str,
+    query: &str,
+    result: &QueryResult,
+) -> Result<Pathway> {
+    let pathway = snapshot_pathway(session_id, &result.query_id)?;
+    let snapshot = RetrievalSnapshot {
+        query: query.to_string(),
+        result: result.clone(),
+        taken_at: Utc::now(),
+        store_nodes: storage.stats().await?.total_nodes,
+    };
+    let node = Node::new(
+        pathway.clone(),
+        NodeKind::Data,
+        serde_json::to_string_pretty(&snapshot)?,
+    );
+    storage.put(&node).await?;
+    Ok(pathway)
+}
+
+/// Parse a snapshot node
+pub fn from_node(node: &Node) -> Result<RetrievalSnapshot> {
+    serde_json::from_str(&node.content).map_err(|e| {
+        A3SError::InvalidPathway(format!(
+            "{} is not a retrieval snapshot: {}",
+            node.pathway, e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_pathway() {
+        assert_eq!(
+            snapshot_pathway("run-1", "q-42").unwrap().to_string(),
+            "a3s://session/run-1/retrievals/q-42"
+        );
+        assert!(matches!(
+            retrievals_root("run-1/nested"),
+            Err(A3SError::InvalidPathway(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_record_round_trip() {
+        let storage = crate::storage::MemoryStorage::new(&Default::default());
+        let result = QueryResult {
+            query_id: "q-1".to_string(),
+            intent: None,
+            matches: Vec::new(),
+            total_searched: 3,
+            query_embedding_time_ms: 1,
+            search_time_ms: 2,
+            rerank_time_ms: None,
+            warnings: Vec::new(),
+        };
+
+        let pathway = record(&storage, "run-1", "deploys", &result).await.unwrap();
+        let snapshot = from_node(&storage.get(&pathway).await.unwrap()).unwrap();
+        assert_eq!(snapshot.query, "deploys");
+        assert_eq!(snapshot.result.query_id, "q-1");
+        assert_eq!(snapshot.result.total_searched, 3);
+    }
+}
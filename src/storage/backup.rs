@@ -0,0 +1,420 @@
+//! Backups: snapshots of a whole store, and restoring from them
+//!
+//! A backup is one gzip-compressed JSON-lines file: a [`BackupManifest`]
+//! line, then every node with its digest, metadata, and vectors. The
+//! vector index is rebuilt from those vectors on restore, so a snapshot
+//! needs no backend-specific index files. Backups are written to a
+//! directory, through a temporary file renamed into place, or to an S3
+//! location with a single upload, so a target never holds a partial
+//! backup. The client holds off its writes while a backup or restore runs,
+//! so a backup is a consistent snapshot. A restore writes the backup over
+//! the store before removing what it lacks, and puts the previous contents
+//! back if either step fails, so the store is never left empty.
+
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::config::EmbeddingConfig;
+use crate::core::{Namespace, Node};
+use crate::drift::EmbeddingModel;
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+use crate::pathway::Pathway;
+
+use super::StorageBackend;
+
+/// Newest backup format this build reads and the one it writes
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+const BACKUP_PREFIX: &str = "a3s-backup-";
+const BACKUP_SUFFIX: &str = ".jsonl.gz";
+
+/// Description of a backup's contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    /// File name of the backup at its target
+    pub name: String,
+    pub node_count: u64,
+    /// Model the backed-up embeddings were made with
+    pub embedding: EmbeddingModel,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where backups are kept
+pub enum BackupTarget {
+    Directory(PathBuf),
+    #[cfg(feature = "http")]
+    S3(super::s3::S3Client),
+}
+
+impl BackupTarget {
+    /// A directory path, or an `s3://bucket/prefix` URL signed with
+    /// credentials from the environment
+    pub fn parse(target: &str, http: &HttpClient) -> Result<Self> {
+        if target.starts_with("s3://") {
+            #[cfg(feature = "http")]
+            {
+                let location = super::s3::S3Location::parse(target)?;
+                return Ok(Self::S3(super::s3::S3Client::from_env(
+                    location,
+                    http.clone(),
+                )?));
+            }
+            #[cfg(not(feature = "http"))]
+            {
+                let _ = http;
+                return Err(A3SError::Config(
+                    "S3 backup targets need the `http` feature".to_string(),
+                ));
+            }
+        }
+        let _ = http;
+        Ok(Self::Directory(PathBuf::from(target)))
+    }
+
+    /// Names of the backups at the target, oldest first
+    pub async fn list(&self) -> Result<Vec<String>> {
+        let mut names = match self {
+            Self::Directory(dir) => {
+                let mut names = Vec::new();
+                if dir.exists() {
+                    let mut entries = fs::read_dir(dir).await?;
+                    while let Some(entry) = entries.next_entry().await? {
+                        names.push(entry.file_name().to_string_lossy().into_owned());
+                    }
+                }
+                names
+            }
+            #[cfg(feature = "http")]
+            Self::S3(client) => client.list().await?,
+        };
+        names.retain(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX));
+        // Names embed their creation time, so they sort by age
+        names.sort();
+        Ok(names)
+    }
+
+    async fn write(&self, name: &str, bytes: Vec<u8>) -> Result<()> {
+        match self {
+            Self::Directory(dir) => {
+                fs::create_dir_all(dir).await?;
+                let partial = dir.join(format!(".{}.partial", name));
+                fs::write(&partial, bytes).await?;
+                fs::rename(&partial, dir.join(name)).await?;
+                Ok(())
+            }
+            #[cfg(feature = "http")]
+            Self::S3(client) => client.put(name, bytes).await,
+        }
+    }
+
+    async fn read(&self, name: &str) -> Result<Vec<u8>> {
+        check_name(name)?;
+        match self {
+            Self::Directory(dir) => {
+                let path = dir.join(name);
+                if !path.exists() {
+                    return Err(A3SError::Backup(format!("{} not found", path.display())));
+                }
+                Ok(fs::read(path).await?)
+            }
+            #[cfg(feature = "http")]
+            Self::S3(client) => client.get(name).await,
+        }
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        match self {
+            Self::Directory(dir) => Ok(fs::remove_file(dir.join(name)).await?),
+            #[cfg(feature = "http")]
+            Self::S3(client) => client.delete(name).await,
+        }
+    }
+}
+
+/// Reject names that are not backup file names, so none escapes the target
+fn check_name(name: &str) -> Result<()> {
+    let valid = name.len() > BACKUP_PREFIX.len() + BACKUP_SUFFIX.len()
+        && name.starts_with(BACKUP_PREFIX)
+        && name.ends_with(BACKUP_SUFFIX)
+        && !name.contains(['/', '\\'])
+        && !name.contains("..");
+    if valid {
+        Ok(())
+    } else {
+        Err(A3SError::Backup(format!("Invalid backup name: {}", name)))
+    }
+}
+
+/// Every node in the store, by pathway
+async fn snapshot(storage: &dyn StorageBackend) -> Result<Vec<Node>> {
+    let mut nodes = Vec::new();
+    for namespace in Namespace::ALL {
+        let root = Pathway::root(namespace);
+        // Lazily loading backends may not have read the namespace yet
+        storage.warm(&root).await?;
+        nodes.extend(storage.get_children(&root, usize::MAX).await?);
+    }
+    nodes.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+    Ok(nodes)
+}
+
+/// Snapshot every node in the store to `target`, then delete all but the
+/// newest `keep` backups there (0 keeps all)
+pub async fn backup(
+    storage: &dyn StorageBackend,
+    target: &BackupTarget,
+    embedding: &EmbeddingConfig,
+    keep: usize,
+) -> Result<BackupManifest> {
+    storage.flush().await?;
+    let nodes = snapshot(storage).await?;
+
+    let created_at = Utc::now();
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        name: format!(
+            "{}{}{}",
+            BACKUP_PREFIX,
+            created_at.format("%Y%m%dT%H%M%S%.3fZ"),
+            BACKUP_SUFFIX
+        ),
+        node_count: nodes.len() as u64,
+        embedding: EmbeddingModel::of(embedding),
+        created_at,
+    };
+    target
+        .write(&manifest.name, encode(&manifest, &nodes)?)
+        .await?;
+
+    if keep > 0 {
+        let names = target.list().await?;
+        for name in &names[..names.len().saturating_sub(keep)] {
+            target.delete(name).await?;
+        }
+    }
+    Ok(manifest)
+}
+
+/// Replace the store's contents with a backup, the newest at `target`
+/// unless `name` is given
+pub async fn restore(
+    storage: &dyn StorageBackend,
+    target: &BackupTarget,
+    name: Option<&str>,
+) -> Result<BackupManifest> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => target
+            .list()
+            .await?
+            .pop()
+            .ok_or_else(|| A3SError::Backup("No backups found at the target".to_string()))?,
+    };
+    // Decode everything before touching the store
+    let (manifest, nodes) = decode(&target.read(&name).await?)?;
+    let previous = snapshot(storage).await?;
+
+    if let Err(e) = swap(storage, &previous, &nodes).await {
+        if let Err(undo) = swap(storage, &nodes, &previous).await {
+            tracing::error!("Could not undo a failed restore: {}", undo);
+        }
+        return Err(e);
+    }
+    storage.flush().await?;
+    Ok(manifest)
+}
+
+/// Replace a store holding `from` with `to`: write `to` over it, then
+/// remove the nodes `to` lacks, deepest first
+async fn swap(storage: &dyn StorageBackend, from: &[Node], to: &[Node]) -> Result<()> {
+    storage.put_batch(to).await?;
+    let kept: HashSet<&Pathway> = to.iter().map(|node| &node.pathway).collect();
+    let mut stale: Vec<&Pathway> = from
+        .iter()
+        .map(|node| &node.pathway)
+        .filter(|pathway| !kept.contains(pathway))
+        .collect();
+    stale.sort_by_key(|pathway| std::cmp::Reverse(pathway.depth()));
+    for pathway in stale {
+        storage.remove(pathway, false).await?;
+    }
+    Ok(())
+}
+
+fn encode(manifest: &BackupManifest, nodes: &[Node]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, manifest)?;
+    encoder.write_all(b"\n")?;
+    for node in nodes {
+        serde_json::to_writer(&mut encoder, node)?;
+        encoder.write_all(b"\n")?;
+    }
+    Ok(encoder.finish()?)
+}
+
+fn decode(bytes: &[u8]) -> Result<(BackupManifest, Vec<Node>)> {
+    let mut lines = BufReader::new(GzDecoder::new(bytes)).lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| A3SError::Backup("Backup is empty".to_string()))??;
+    let manifest: BackupManifest = serde_json::from_str(&header)
+        .map_err(|e| A3SError::Backup(format!("Invalid manifest: {}", e)))?;
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(A3SError::Backup(format!(
+            "Backup format {} is newer than supported format {}",
+            manifest.format_version, BACKUP_FORMAT_VERSION
+        )));
+    }
+
+    let mut nodes = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let node: Node = serde_json::from_str(&line)
+            .map_err(|e| A3SError::Backup(format!("Invalid node: {}", e)))?;
+        nodes.push(node);
+    }
+    if nodes.len() as u64 != manifest.node_count {
+        return Err(A3SError::Backup(format!(
+            "Manifest lists {} nodes, backup contains {}",
+            manifest.node_count,
+            nodes.len()
+        )));
+    }
+    Ok((manifest, nodes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::storage::MemoryStorage;
+
+    async fn put(storage: &MemoryStorage, pathway: &str, embedding: Vec<f32>) {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            format!("About {}", pathway),
+        );
+        node.embedding = embedding;
+        storage.put(&node).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = BackupTarget::Directory(dir.path().to_path_buf());
+        let embedding = EmbeddingConfig::default();
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        put(&storage, "a3s://knowledge/docs/auth.md", vec![1.0, 0.0]).await;
+        put(&storage, "a3s://memory/alice/prefs", vec![0.0, 1.0]).await;
+
+        let manifest = backup(&storage, &target, &embedding, 0).await.unwrap();
+        assert_eq!(manifest.node_count, 2);
+        assert_eq!(target.list().await.unwrap(), vec![manifest.name.clone()]);
+
+        // Corrupt the store after the backup
+        storage
+            .remove(&Pathway::parse("a3s://knowledge").unwrap(), true)
+            .await
+            .unwrap();
+        put(&storage, "a3s://knowledge/junk", vec![1.0, 0.0]).await;
+
+        let restored = restore(&storage, &target, None).await.unwrap();
+        assert_eq!(restored.name, manifest.name);
+        let auth = Pathway::parse("a3s://knowledge/docs/auth.md").unwrap();
+        assert!(storage.exists(&auth).await.unwrap());
+        assert!(!storage
+            .exists(&Pathway::parse("a3s://knowledge/junk").unwrap())
+            .await
+            .unwrap());
+        let hits = storage
+            .search_vector(&[1.0, 0.0], None, 10, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, auth);
+    }
+
+    #[tokio::test]
+    async fn test_backup_keeps_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = BackupTarget::Directory(dir.path().to_path_buf());
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        put(&storage, "a3s://knowledge/a", vec![1.0, 0.0]).await;
+
+        let mut names = Vec::new();
+        for _ in 0..3 {
+            let manifest = backup(&storage, &target, &EmbeddingConfig::default(), 2)
+                .await
+                .unwrap();
+            names.push(manifest.name);
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(target.list().await.unwrap(), names[1..].to_vec());
+        assert!(
+            restore(&storage, &target, Some("a3s-backup-missing.jsonl.gz"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("a3s-backup-20260101T000000.000Z.jsonl.gz").is_ok());
+        for name in [
+            "a3s-backup-.jsonl.gz",
+            "notes.txt",
+            "a3s-backup-../../etc/x.jsonl.gz",
+            "a3s-backup-a\\b.jsonl.gz",
+        ] {
+            assert!(check_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_keeps_nodes_the_backup_shares() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = BackupTarget::Directory(dir.path().to_path_buf());
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        put(&storage, "a3s://knowledge/docs/a", vec![1.0, 0.0]).await;
+        backup(&storage, &target, &EmbeddingConfig::default(), 0)
+            .await
+            .unwrap();
+        put(&storage, "a3s://knowledge/docs/b", vec![0.0, 1.0]).await;
+
+        restore(&storage, &target, None).await.unwrap();
+        let docs = Pathway::parse("a3s://knowledge/docs").unwrap();
+        let children = storage.get_children(&docs, usize::MAX).await.unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].pathway.to_string(), "a3s://knowledge/docs/a");
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_backup() {
+        let manifest = BackupManifest {
+            format_version: BACKUP_FORMAT_VERSION,
+            name: "a3s-backup-x.jsonl.gz".to_string(),
+            node_count: 1,
+            embedding: EmbeddingModel::of(&EmbeddingConfig::default()),
+            created_at: Utc::now(),
+        };
+        let bytes = encode(&manifest, &[]).unwrap();
+        let err = decode(&bytes).unwrap_err();
+        assert!(err.to_string().contains("lists 1 nodes"));
+    }
+}
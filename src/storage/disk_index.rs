@@ -0,0 +1,672 @@
+//! Flat vector index kept on disk
+//!
+//! With `index_type: disk`, vectors live in `vectors.rows`, one
+//! fixed-size row of little-endian `f32`s per named vector, and only each
+//! row's pathway and vector name stay in memory, so the embedding set can
+//! be far larger than RAM. Searches stream the file in blocks on the
+//! blocking thread pool and score every live row exactly, leaving caching
+//! to the operating system.
+//! Rows are normalized on write, so scores are cosine similarities.
+//!
+//! `vectors.rows.log` records which node each row holds:
+//!
+//! ```text
+//! record = op:u8 row:u32 [name_len:u8 name key_len:u32 key]   op 0 sets, 1 frees
+//! ```
+//!
+//! A row is written before the record that claims it, so a crash leaves
+//! at worst an unclaimed row, which is reused. Freed rows are reused
+//! before the file grows; the file never shrinks.
+
+use ordered_float::OrderedFloat;
+use parking_lot::RwLock;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::hnsw::normalize;
+use super::persist::NodeVectors;
+use super::vector_index::{node_vectors, VectorIndexBackend};
+use crate::core::{Namespace, Node, VectorName};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+
+/// Index type that keeps vectors on disk
+pub const DISK_INDEX: &str = "disk";
+
+/// Leads the rows file, followed by the dimension as a `u32`
+const ROWS_MAGIC: &[u8; 8] = b"A3SROW01";
+const HEADER_LEN: u64 = 12;
+
+const OP_SET: u8 = 0;
+const OP_FREE: u8 = 1;
+
+/// Rows read from disk at a time while searching
+const BLOCK_ROWS: usize = 1024;
+
+/// Log records tolerated before the log is rewritten, unless more rows
+/// are in use
+const COMPACT_AFTER_RECORDS: usize = 10_000;
+
+/// Exact nearest-neighbour index over vectors stored on disk
+pub struct DiskIndex {
+    rows_path: PathBuf,
+    log_path: PathBuf,
+    /// Shared with searches, which scan on the blocking pool
+    state: Arc<RwLock<State>>,
+}
+
+struct Row {
+    key: Arc<str>,
+    name: VectorName,
+    namespace: Namespace,
+}
+
+#[derive(Default)]
+struct State {
+    /// Floats per row; 0 until the first vector is written
+    dimension: usize,
+    /// What each row of the file holds; `None` rows are free
+    rows: Vec<Option<Row>>,
+    free: Vec<usize>,
+    /// Rows of each node, by vector name
+    nodes: HashMap<Arc<str>, Vec<(VectorName, usize)>>,
+    /// Records in the log
+    records: usize,
+}
+
+impl DiskIndex {
+    /// Index in `vectors.rows` and `vectors.rows.log` under `dir`,
+    /// loading what is already there
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let index = Self {
+            rows_path: dir.join("vectors.rows"),
+            log_path: dir.join("vectors.rows.log"),
+            state: Arc::new(RwLock::new(State::default())),
+        };
+        index.restore()?;
+        Ok(index)
+    }
+
+    fn restore(&self) -> Result<()> {
+        let mut state = self.state.write();
+        *state = State::default();
+        let mut file = match File::open(&self.rows_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_exact(&mut header)?;
+        if &header[..8] != ROWS_MAGIC {
+            return Err(A3SError::Storage(format!(
+                "{} is not a vector rows file",
+                self.rows_path.display()
+            )));
+        }
+        state.dimension = u32::from_le_bytes(header[8..].try_into().expect("4 bytes")) as usize;
+        let row_bytes = (state.dimension * 4) as u64;
+        let count = (file.metadata()?.len() - HEADER_LEN)
+            .checked_div(row_bytes)
+            .unwrap_or(0) as usize;
+        state.rows.resize_with(count, || None);
+
+        let log = match fs::read(&self.log_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut cursor = log.as_slice();
+        while let Some((row, claim)) = read_record(&mut cursor) {
+            state.records += 1;
+            if row >= count {
+                continue;
+            }
+            state.release(row);
+            if let Some((name, key)) = claim {
+                state.claim(row, key.into(), name);
+            }
+        }
+        let free: Vec<usize> = (0..count)
+            .rev()
+            .filter(|&r| state.rows[r].is_none())
+            .collect();
+        state.free = free;
+        tracing::debug!(
+            nodes = state.nodes.len(),
+            rows = count,
+            "Loaded disk vector index"
+        );
+        Ok(())
+    }
+
+    /// Store vectors for each node, dropping its other vectors when
+    /// `replace` is set
+    ///
+    /// Every entry is checked before anything changes, and rows freed here
+    /// are only reused once the log records them free.
+    fn write(&self, entries: &[(String, NodeVectors)], replace: bool) -> Result<()> {
+        let namespaces = entries
+            .iter()
+            .map(|(key, _)| Ok(Pathway::parse(key)?.namespace()))
+            .collect::<Result<Vec<Namespace>>>()?;
+        let mut state = self.state.write();
+        let mut dimension = state.dimension;
+        for (key, vectors) in entries {
+            for (_, vector) in vectors {
+                if dimension == 0 {
+                    dimension = vector.len();
+                }
+                if vector.len() != dimension {
+                    return Err(A3SError::Storage(format!(
+                        "Vector for {} has {} dimensions, the disk index holds {}",
+                        key,
+                        vector.len(),
+                        dimension
+                    )));
+                }
+            }
+        }
+        if state.dimension == 0 && dimension > 0 {
+            self.write_header(dimension)?;
+            state.dimension = dimension;
+        }
+
+        let mut file = None;
+        let mut log = Vec::new();
+        let mut freed = Vec::new();
+        for ((key, vectors), namespace) in entries.iter().zip(namespaces) {
+            let key: Arc<str> = key.as_str().into();
+            let mut rows = state.nodes.remove(&key).unwrap_or_default();
+            if replace {
+                for &(name, row) in &rows {
+                    if !vectors.iter().any(|(n, _)| *n == name) {
+                        state.rows[row] = None;
+                        freed.push(row);
+                        write_record(&mut log, row, None);
+                    }
+                }
+                rows.retain(|(name, _)| vectors.iter().any(|(n, _)| n == name));
+            }
+
+            for (name, vector) in vectors {
+                let row = match rows.iter().find(|(n, _)| n == name) {
+                    Some(&(_, row)) => row,
+                    None => {
+                        let row = state.free.pop().unwrap_or(state.rows.len());
+                        rows.push((*name, row));
+                        row
+                    }
+                };
+                let file = match &mut file {
+                    Some(file) => file,
+                    None => file.insert(OpenOptions::new().write(true).open(&self.rows_path)?),
+                };
+                file.seek(SeekFrom::Start(row_offset(row, dimension)))?;
+                let bytes: Vec<u8> = normalize(vector)
+                    .iter()
+                    .flat_map(|x| x.to_le_bytes())
+                    .collect();
+                file.write_all(&bytes)?;
+
+                if row >= state.rows.len() {
+                    state.rows.resize_with(row + 1, || None);
+                }
+                state.rows[row] = Some(Row {
+                    key: key.clone(),
+                    name: *name,
+                    namespace,
+                });
+                write_record(&mut log, row, Some((*name, &key)));
+            }
+            if !rows.is_empty() {
+                state.nodes.insert(key, rows);
+            }
+        }
+        self.append(&mut state, &log)?;
+        state.free.extend(freed);
+        Ok(())
+    }
+
+    fn forget(&self, keys: &[String]) -> Result<()> {
+        let mut state = self.state.write();
+        let mut log = Vec::new();
+        for key in keys {
+            for (_, row) in state.nodes.remove(key.as_str()).unwrap_or_default() {
+                state.rows[row] = None;
+                state.free.push(row);
+                write_record(&mut log, row, None);
+            }
+        }
+        self.append(&mut state, &log)
+    }
+
+    fn write_header(&self, dimension: usize) -> Result<()> {
+        let mut file = File::create(&self.rows_path)?;
+        file.write_all(ROWS_MAGIC)?;
+        file.write_all(&(dimension as u32).to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Append records to the log, rewriting it once it has grown well past
+    /// the rows in use
+    fn append(&self, state: &mut State, log: &[u8]) -> Result<()> {
+        if log.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)?;
+        file.write_all(log)?;
+        state.records += count_records(log);
+
+        let in_use = state.rows.len() - state.free.len();
+        if state.records > COMPACT_AFTER_RECORDS.max(in_use * 2) {
+            let mut compacted = Vec::new();
+            for (row, slot) in state.rows.iter().enumerate() {
+                if let Some(slot) = slot {
+                    write_record(&mut compacted, row, Some((slot.name, &slot.key)));
+                }
+            }
+            let partial = self.log_path.with_extension("log.partial");
+            fs::write(&partial, &compacted)?;
+            fs::rename(&partial, &self.log_path)?;
+            state.records = in_use;
+        }
+        Ok(())
+    }
+}
+
+impl State {
+    fn claim(&mut self, row: usize, key: Arc<str>, name: VectorName) {
+        let Ok(pathway) = Pathway::parse(&key) else {
+            return;
+        };
+        let rows = self.nodes.entry(key.clone()).or_default();
+        if let Some(stale) = rows.iter().position(|(n, _)| *n == name) {
+            let (_, old) = rows.remove(stale);
+            self.rows[old] = None;
+        }
+        rows.push((name, row));
+        self.rows[row] = Some(Row {
+            key,
+            name,
+            namespace: pathway.namespace(),
+        });
+    }
+
+    fn release(&mut self, row: usize) {
+        let Some(slot) = self.rows[row].take() else {
+            return;
+        };
+        if let Some(rows) = self.nodes.get_mut(&slot.key) {
+            rows.retain(|(_, r)| *r != row);
+            if rows.is_empty() {
+                self.nodes.remove(&slot.key);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl VectorIndexBackend for DiskIndex {
+    /// Contents are restored when the index is opened
+    async fn load(&self) -> Result<usize> {
+        Ok(self.size())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let _state = self.state.read();
+        for path in [&self.rows_path, &self.log_path] {
+            if path.exists() {
+                File::open(path)?.sync_all()?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn add(&self, pathway: &Pathway, vector: &[f32]) -> Result<()> {
+        let vectors = vec![(VectorName::Content, vector.to_vec())];
+        self.write(&[(pathway.to_string(), vectors)], false)
+    }
+
+    async fn index(&self, node: &Node) -> Result<()> {
+        self.write(&[(node.pathway.to_string(), node_vectors(node))], true)
+    }
+
+    async fn index_batch(&self, nodes: &[Node]) -> Result<()> {
+        let entries: Vec<(String, NodeVectors)> = nodes
+            .iter()
+            .map(|node| (node.pathway.to_string(), node_vectors(node)))
+            .collect();
+        self.write(&entries, true)
+    }
+
+    async fn remove(&self, pathway: &Pathway) -> Result<()> {
+        self.forget(&[pathway.to_string()])
+    }
+
+    async fn remove_tree(&self, pathway: &Pathway) -> Result<()> {
+        let keys: Vec<String> = self
+            .state
+            .read()
+            .nodes
+            .keys()
+            .filter(|key| Pathway::parse(key).is_ok_and(|p| pathway.is_prefix_of(&p)))
+            .map(|key| key.to_string())
+            .collect();
+        self.forget(&keys)
+    }
+
+    async fn search_vectors(
+        &self,
+        query: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let state = self.state.clone();
+        let rows_path = self.rows_path.clone();
+        let query = query.to_vec();
+        let names = names.to_vec();
+        tokio::task::spawn_blocking(move || {
+            scan(
+                &state, &rows_path, &query, &names, namespace, limit, threshold,
+            )
+        })
+        .await
+        .map_err(|e| A3SError::Storage(format!("Vector scan failed: {}", e)))?
+    }
+
+    fn contains(&self, pathway: &Pathway) -> bool {
+        self.state
+            .read()
+            .nodes
+            .contains_key(pathway.to_string().as_str())
+    }
+
+    fn size(&self) -> usize {
+        self.state.read().nodes.len()
+    }
+}
+
+/// Score every live row of the rows file against `query`
+///
+/// The state lock is taken one block at a time, so writes wait for at
+/// most one block rather than the whole scan.
+fn scan(
+    state: &RwLock<State>,
+    rows_path: &Path,
+    query: &[f32],
+    names: &[VectorName],
+    namespace: Option<Namespace>,
+    limit: usize,
+    threshold: f32,
+) -> Result<Vec<(Pathway, f32)>> {
+    let dimension = state.read().dimension;
+    if dimension == 0 || query.len() != dimension {
+        return Ok(Vec::new());
+    }
+    let query = normalize(query);
+
+    // A node matches at most once per name, so its best row is among
+    // the top `limit * names` rows
+    let keep = limit * names.len();
+    let mut heap = BinaryHeap::new();
+    let mut file = File::open(rows_path)?;
+    let row_bytes = dimension * 4;
+    let mut block = vec![0u8; row_bytes * BLOCK_ROWS];
+    let mut row_vector = vec![0f32; dimension];
+    let mut first = 0;
+    loop {
+        let state = state.read();
+        if first >= state.rows.len() {
+            break;
+        }
+        let count = BLOCK_ROWS.min(state.rows.len() - first);
+        let bytes = &mut block[..count * row_bytes];
+        file.seek(SeekFrom::Start(row_offset(first, dimension)))?;
+        file.read_exact(bytes)?;
+        for (offset, raw) in bytes.chunks_exact(row_bytes).enumerate() {
+            let Some(slot) = &state.rows[first + offset] else {
+                continue;
+            };
+            if !names.contains(&slot.name) || namespace.is_some_and(|ns| ns != slot.namespace) {
+                continue;
+            }
+            for (x, b) in row_vector.iter_mut().zip(raw.chunks_exact(4)) {
+                *x = f32::from_le_bytes(b.try_into().expect("4 bytes"));
+            }
+            let score = dot(&query, &row_vector);
+            if score < threshold {
+                continue;
+            }
+            heap.push(Reverse((OrderedFloat(score), slot.key.clone())));
+            if heap.len() > keep {
+                heap.pop();
+            }
+        }
+        first += count;
+    }
+
+    let mut best: HashMap<Arc<str>, f32> = HashMap::new();
+    for Reverse((score, key)) in heap {
+        let entry = best.entry(key).or_insert(score.0);
+        *entry = entry.max(score.0);
+    }
+    let mut ranked: Vec<(Arc<str>, f32)> = best.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(key, score)| Ok((Pathway::parse(&key)?, score)))
+        .collect()
+}
+
+/// Dot product over eight lanes at a time, which the compiler turns into
+/// SIMD instructions
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut lanes = [0f32; 8];
+    let (a_chunks, b_chunks) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+    for (x, y) in a_chunks.zip(b_chunks) {
+        for i in 0..8 {
+            lanes[i] += x[i] * y[i];
+        }
+    }
+    lanes.iter().sum::<f32>() + tail
+}
+
+fn row_offset(row: usize, dimension: usize) -> u64 {
+    HEADER_LEN + (row * dimension * 4) as u64
+}
+
+fn write_record(out: &mut Vec<u8>, row: usize, claim: Option<(VectorName, &str)>) {
+    match claim {
+        Some((name, key)) => {
+            out.push(OP_SET);
+            out.extend_from_slice(&(row as u32).to_le_bytes());
+            out.push(name.as_str().len() as u8);
+            out.extend_from_slice(name.as_str().as_bytes());
+            out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            out.extend_from_slice(key.as_bytes());
+        }
+        None => {
+            out.push(OP_FREE);
+            out.extend_from_slice(&(row as u32).to_le_bytes());
+        }
+    }
+}
+
+/// Next record, or `None` at the end of the log or a record cut short
+#[allow(clippy::type_complexity)]
+fn read_record(cursor: &mut &[u8]) -> Option<(usize, Option<(VectorName, String)>)> {
+    fn take<'a>(cursor: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+        if cursor.len() < n {
+            return None;
+        }
+        let (head, rest) = cursor.split_at(n);
+        *cursor = rest;
+        Some(head)
+    }
+    let op = take(cursor, 1)?[0];
+    let row = u32::from_le_bytes(take(cursor, 4)?.try_into().ok()?) as usize;
+    if op == OP_FREE {
+        return Some((row, None));
+    }
+    let name_len = take(cursor, 1)?[0] as usize;
+    let name = VectorName::parse(std::str::from_utf8(take(cursor, name_len)?).ok()?)?;
+    let key_len = u32::from_le_bytes(take(cursor, 4)?.try_into().ok()?) as usize;
+    let key = String::from_utf8(take(cursor, key_len)?.to_vec()).ok()?;
+    Some((row, Some((name, key))))
+}
+
+fn count_records(mut log: &[u8]) -> usize {
+    let mut count = 0;
+    while read_record(&mut log).is_some() {
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+
+    fn node(pathway: &str, embedding: Vec<f32>) -> Node {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            String::new(),
+        );
+        node.embedding = embedding;
+        node
+    }
+
+    #[tokio::test]
+    async fn test_disk_index_search_and_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = DiskIndex::open(dir.path()).unwrap();
+        let auth = node("a3s://knowledge/docs/auth.md", vec![1.0, 0.0, 0.0]);
+        let deploy = node("a3s://knowledge/docs/deploy.md", vec![0.0, 2.0, 0.0]);
+        let mut prefs = node("a3s://memory/alice/prefs", vec![0.9, 0.1, 0.0]);
+        prefs
+            .vectors
+            .insert(VectorName::Summary, vec![0.0, 0.0, 1.0]);
+        index
+            .index_batch(&[auth.clone(), deploy.clone(), prefs.clone()])
+            .await
+            .unwrap();
+        assert_eq!(index.size(), 3);
+
+        let hits = index.search(&[1.0, 0.0, 0.0], None, 2, 0.0).await.unwrap();
+        assert_eq!(hits[0].0, auth.pathway);
+        assert_eq!(hits[1].0, prefs.pathway);
+        let memory = index
+            .search(&[1.0, 0.0, 0.0], Some(Namespace::Memory), 5, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(memory.len(), 1);
+        let summary = index
+            .search_vectors(&[0.0, 0.0, 1.0], &[VectorName::Summary], None, 5, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(summary[0].0, prefs.pathway);
+        assert!((summary[0].1 - 1.0).abs() < 1e-6);
+
+        // Freed rows are reused rather than growing the file
+        index.remove(&deploy.pathway).await.unwrap();
+        let rows = fs::metadata(dir.path().join("vectors.rows")).unwrap().len();
+        index
+            .index(&node(
+                "a3s://knowledge/docs/billing.md",
+                vec![0.0, 1.0, 0.0],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::metadata(dir.path().join("vectors.rows")).unwrap().len(),
+            rows
+        );
+        drop(index);
+
+        let index = DiskIndex::open(dir.path()).unwrap();
+        assert_eq!(index.load().await.unwrap(), 3);
+        assert!(!index.contains(&deploy.pathway));
+        let hits = index.search(&[0.0, 1.0, 0.0], None, 1, 0.5).await.unwrap();
+        assert_eq!(hits[0].0.to_string(), "a3s://knowledge/docs/billing.md");
+
+        index
+            .remove_tree(&Pathway::parse("a3s://knowledge").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(index.size(), 1);
+        assert!(index
+            .index(&node("a3s://knowledge/x", vec![1.0, 0.0]))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_checks_entries_and_holds_freed_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let rows_file = dir.path().join("vectors.rows");
+        let index = DiskIndex::open(dir.path()).unwrap();
+        let mut prefs = node("a3s://memory/alice/prefs", vec![1.0, 0.0]);
+        prefs.vectors.insert(VectorName::Summary, vec![0.0, 1.0]);
+        index.index(&prefs).await.unwrap();
+        let len = fs::metadata(&rows_file).unwrap().len();
+
+        // A bad key anywhere in a batch leaves the index untouched
+        let entries = vec![
+            (
+                "a3s://knowledge/new".to_string(),
+                vec![(VectorName::Content, vec![1.0, 1.0])],
+            ),
+            (
+                "not a pathway".to_string(),
+                vec![(VectorName::Content, vec![1.0, 0.0])],
+            ),
+        ];
+        assert!(index.write(&entries, true).is_err());
+        assert_eq!(index.size(), 1);
+        assert_eq!(fs::metadata(&rows_file).unwrap().len(), len);
+
+        // The summary row this batch frees is not handed to the new node
+        prefs.vectors.clear();
+        let new = node("a3s://knowledge/new", vec![0.0, 1.0]);
+        index.index_batch(&[prefs, new.clone()]).await.unwrap();
+        assert_eq!(fs::metadata(&rows_file).unwrap().len(), len + 8);
+        drop(index);
+
+        let index = DiskIndex::open(dir.path()).unwrap();
+        let hits = index.search(&[0.0, 1.0], None, 5, 0.5).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, new.pathway);
+    }
+
+    #[test]
+    fn test_dot_matches_naive() {
+        let a: Vec<f32> = (0..19).map(|i| i as f32 * 0.5).collect();
+        let b: Vec<f32> = (0..19).map(|i| 1.0 - i as f32 * 0.1).collect();
+        let naive: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        assert!((dot(&a, &b) - naive).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,466 @@
+//! Hierarchical navigable small world graph for approximate nearest
+//! neighbor search (Malkov & Yashunin, 2016)
+//!
+//! Vectors are normalized on insert so cosine similarity is a dot product.
+//! Removed points are tombstoned: they keep routing searches but are never
+//! returned, and the graph is rebuilt once they outnumber live points.
+
+use ordered_float::OrderedFloat;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::config::VectorIndexConfig;
+
+/// Index type that skips the graph and always scans every vector
+const FLAT_INDEX: &str = "flat";
+
+/// Graphs this small are searched exactly; a scan is as fast as a walk
+const EXACT_SEARCH_LIMIT: usize = 1024;
+
+/// Tombstones tolerated before a rebuild is worth its cost
+const MIN_COMPACT_POINTS: usize = 64;
+
+/// Highest layer a point can be assigned
+const MAX_LEVEL: usize = 16;
+
+type Scored = (OrderedFloat<f32>, usize);
+
+struct Point {
+    key: String,
+    vector: Vec<f32>,
+    /// Neighbors on each layer the point reaches, layer 0 first
+    links: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// HNSW graph over keyed vectors
+pub(super) struct Hnsw {
+    /// Whether points are linked into a graph; flat indexes only scan
+    linked: bool,
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    level_mult: f64,
+    points: Vec<Point>,
+    ids: HashMap<String, usize>,
+    entry: Option<usize>,
+    deleted: usize,
+    rng: u64,
+}
+
+impl Hnsw {
+    pub(super) fn new(config: &VectorIndexConfig) -> Self {
+        let m = config.hnsw_m.max(2);
+        Self {
+            linked: config.index_type != FLAT_INDEX,
+            m,
+            ef_construction: config.hnsw_ef_construction.max(m),
+            ef_search: config.hnsw_ef_search.max(1),
+            level_mult: 1.0 / (m as f64).ln(),
+            points: Vec::new(),
+            ids: HashMap::new(),
+            entry: None,
+            deleted: 0,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Number of live vectors
+    pub(super) fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Insert a vector, replacing any earlier one under the same key
+    pub(super) fn insert(&mut self, key: &str, vector: &[f32]) {
+        self.tombstone(key);
+
+        let id = self.points.len();
+        let level = if self.linked { self.random_level() } else { 0 };
+        self.points.push(Point {
+            key: key.to_string(),
+            vector: normalize(vector),
+            links: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.ids.insert(key.to_string(), id);
+        if !self.linked {
+            return;
+        }
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            return;
+        };
+        let query = self.points[id].vector.clone();
+        let top = self.points[entry].links.len() - 1;
+
+        let mut entry_points = vec![entry];
+        for layer in (level + 1..=top).rev() {
+            entry_points = vec![self.search_layer(&query, &entry_points, 1, layer)[0].1];
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entry_points, self.ef_construction, layer);
+            let neighbors = self.select_neighbors(&found, self.m);
+            for &neighbor in &neighbors {
+                self.points[neighbor].links[layer].push(id);
+                if self.points[neighbor].links[layer].len() > self.max_links(layer) {
+                    self.shrink(neighbor, layer);
+                }
+            }
+            self.points[id].links[layer] = neighbors;
+            entry_points = found.into_iter().map(|(_, id)| id).collect();
+        }
+
+        if level > top {
+            self.entry = Some(id);
+        }
+    }
+
+    /// A key's vector, normalized
+    pub(super) fn get(&self, key: &str) -> Option<&[f32]> {
+        self.ids
+            .get(key)
+            .map(|&id| self.points[id].vector.as_slice())
+    }
+
+    /// Remove a key's vector
+    pub(super) fn remove(&mut self, key: &str) {
+        self.tombstone(key);
+    }
+
+    /// Best matches among accepted keys, best first
+    ///
+    /// Small and flat indexes are scanned exactly; larger graphs are walked.
+    pub(super) fn search(
+        &self,
+        query: &[f32],
+        limit: usize,
+        accept: impl Fn(&str) -> bool,
+    ) -> Vec<(&str, f32)> {
+        if !self.linked || self.len() <= EXACT_SEARCH_LIMIT {
+            self.exact(query, limit, accept)
+        } else {
+            self.approximate(query, limit, accept)
+        }
+    }
+
+    /// Score every live vector
+    pub(super) fn exact(
+        &self,
+        query: &[f32],
+        limit: usize,
+        accept: impl Fn(&str) -> bool,
+    ) -> Vec<(&str, f32)> {
+        let query = normalize(query);
+        let mut heap = BinaryHeap::new();
+        for (id, point) in self.points.iter().enumerate() {
+            if point.deleted || !accept(&point.key) {
+                continue;
+            }
+            heap.push(Reverse((OrderedFloat(dot(&query, &point.vector)), id)));
+            if heap.len() > limit {
+                heap.pop();
+            }
+        }
+        let mut hits: Vec<Scored> = heap.into_iter().map(|Reverse(hit)| hit).collect();
+        hits.sort_by(|a, b| b.cmp(a));
+        self.keyed(hits)
+    }
+
+    /// Walk the graph, widening the beam until enough accepted keys turn up
+    pub(super) fn approximate(
+        &self,
+        query: &[f32],
+        limit: usize,
+        accept: impl Fn(&str) -> bool,
+    ) -> Vec<(&str, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if limit == 0 {
+            return Vec::new();
+        }
+        let query = normalize(query);
+
+        let mut entry_point = entry;
+        for layer in (1..self.points[entry].links.len()).rev() {
+            entry_point = self.search_layer(&query, &[entry_point], 1, layer)[0].1;
+        }
+
+        let mut ef = self.ef_search.max(limit);
+        loop {
+            let hits: Vec<Scored> = self
+                .search_layer(&query, &[entry_point], ef, 0)
+                .into_iter()
+                .filter(|(_, id)| !self.points[*id].deleted && accept(&self.points[*id].key))
+                .take(limit)
+                .collect();
+            if hits.len() >= limit || ef >= self.points.len() {
+                return self.keyed(hits);
+            }
+            ef *= 2;
+        }
+    }
+
+    fn keyed(&self, hits: Vec<Scored>) -> Vec<(&str, f32)> {
+        hits.into_iter()
+            .map(|(score, id)| (self.points[id].key.as_str(), score.0))
+            .collect()
+    }
+
+    /// The `ef` points closest to `query` reachable on a layer, best first
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entry_points.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut found = BinaryHeap::new();
+        for &id in entry_points {
+            let score = OrderedFloat(dot(query, &self.points[id].vector));
+            candidates.push((score, id));
+            found.push(Reverse((score, id)));
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some((score, id)) = candidates.pop() {
+            if let Some(Reverse((worst, _))) = found.peek() {
+                if found.len() >= ef && score < *worst {
+                    break;
+                }
+            }
+            for &neighbor in &self.points[id].links[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let score = OrderedFloat(dot(query, &self.points[neighbor].vector));
+                let improves = match found.peek() {
+                    Some(Reverse((worst, _))) => found.len() < ef || score > *worst,
+                    None => true,
+                };
+                if improves {
+                    candidates.push((score, neighbor));
+                    found.push(Reverse((score, neighbor)));
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        let mut found: Vec<Scored> = found.into_iter().map(|Reverse(hit)| hit).collect();
+        found.sort_by(|a, b| b.cmp(a));
+        found
+    }
+
+    /// Pick up to `m` neighbors from candidates sorted best first, preferring
+    /// ones closer to the query than to any already picked so links spread
+    /// across clusters, then filling with the closest of the rest
+    fn select_neighbors(&self, candidates: &[Scored], m: usize) -> Vec<usize> {
+        let mut selected: Vec<usize> = Vec::with_capacity(m);
+        let mut pruned = Vec::new();
+        for &(score, id) in candidates {
+            if selected.len() >= m {
+                break;
+            }
+            let vector = &self.points[id].vector;
+            if selected
+                .iter()
+                .all(|&other| dot(vector, &self.points[other].vector) < score.0)
+            {
+                selected.push(id);
+            } else {
+                pruned.push(id);
+            }
+        }
+        let room = m - selected.len();
+        selected.extend(pruned.into_iter().take(room));
+        selected
+    }
+
+    fn shrink(&mut self, id: usize, layer: usize) {
+        let mut candidates: Vec<Scored> = self.points[id].links[layer]
+            .iter()
+            .map(|&n| {
+                let score = dot(&self.points[id].vector, &self.points[n].vector);
+                (OrderedFloat(score), n)
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.cmp(a));
+        let kept = self.select_neighbors(&candidates, self.max_links(layer));
+        self.points[id].links[layer] = kept;
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    fn tombstone(&mut self, key: &str) {
+        let Some(id) = self.ids.remove(key) else {
+            return;
+        };
+        self.points[id].deleted = true;
+        self.deleted += 1;
+        if self.deleted > self.len() && self.points.len() >= MIN_COMPACT_POINTS {
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let live: Vec<Point> = std::mem::take(&mut self.points)
+            .into_iter()
+            .filter(|p| !p.deleted)
+            .collect();
+        self.ids.clear();
+        self.entry = None;
+        self.deleted = 0;
+        for point in live {
+            self.insert(&point.key, &point.vector);
+        }
+    }
+
+    /// Layer drawn from the exponentially decaying distribution HNSW uses
+    fn random_level(&mut self) -> usize {
+        // xorshift64*: deterministic, so rebuilt graphs are reproducible
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+        ((-uniform.ln() * self.level_mult) as usize).min(MAX_LEVEL)
+    }
+}
+
+pub(super) fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+/// Cosine similarity of normalized vectors; 0 when dimensions differ
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(seed: u64, dimension: usize) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..dimension)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state % 2000) as f32 / 1000.0 - 1.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        let a = normalize(&[2.0, 0.0, 0.0]);
+        let b = normalize(&[1.0, 0.0, 0.0]);
+        assert!((dot(&a, &b) - 1.0).abs() < 0.001);
+
+        let c = normalize(&[0.0, 1.0, 0.0]);
+        assert!((dot(&a, &c) - 0.0).abs() < 0.001);
+        assert_eq!(dot(&a, &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_hnsw_recall_matches_exact_search() {
+        let mut graph = Hnsw::new(&VectorIndexConfig::default());
+        for i in 0..2000 {
+            graph.insert(&format!("a3s://knowledge/doc{}", i), &vector(i, 32));
+        }
+        assert_eq!(graph.len(), 2000);
+
+        let mut recalled = 0;
+        for q in 0..20 {
+            let query = vector(10_000 + q, 32);
+            let exact: HashSet<String> = graph
+                .exact(&query, 10, |_| true)
+                .into_iter()
+                .map(|(k, _)| k.to_string())
+                .collect();
+            let approximate = graph.approximate(&query, 10, |_| true);
+            assert_eq!(approximate.len(), 10);
+            recalled += approximate
+                .iter()
+                .filter(|(k, _)| exact.contains(*k))
+                .count();
+        }
+        // Recall@10 over 20 queries
+        assert!(recalled >= 180, "recall too low: {}/200", recalled);
+    }
+
+    #[test]
+    fn test_hnsw_remove_and_rebuild() {
+        let mut graph = Hnsw::new(&VectorIndexConfig::default());
+        for i in 0..200 {
+            graph.insert(&format!("doc{}", i), &vector(i, 8));
+        }
+        // Re-inserting a key replaces its vector
+        graph.insert("doc0", &vector(999, 8));
+        assert_eq!(graph.len(), 200);
+        let hits = graph.approximate(&vector(999, 8), 1, |_| true);
+        assert_eq!(hits[0].0, "doc0");
+
+        for i in 0..150 {
+            graph.remove(&format!("doc{}", i));
+        }
+        assert_eq!(graph.len(), 50);
+        assert!(graph.points.len() < 200, "tombstones were not compacted");
+
+        let hits = graph.approximate(&vector(160, 8), 50, |_| true);
+        assert_eq!(hits.len(), 50);
+        assert_eq!(hits[0].0, "doc160");
+        assert!(hits
+            .iter()
+            .all(|(k, _)| k[3..].parse::<u64>().unwrap() >= 150));
+    }
+
+    #[test]
+    fn test_hnsw_filter_widens_search() {
+        let mut graph = Hnsw::new(&VectorIndexConfig::default());
+        for i in 0..1000 {
+            graph.insert(&format!("doc{}", i), &vector(i, 16));
+        }
+        // Only nine keys are accepted
+        let hits = graph.approximate(&vector(1, 16), 5, |k| k.ends_with("00"));
+        assert_eq!(hits.len(), 5);
+        assert!(hits.iter().all(|(k, _)| k.ends_with("00")));
+    }
+
+    #[test]
+    fn test_flat_index_scans() {
+        let config = VectorIndexConfig {
+            index_type: FLAT_INDEX.to_string(),
+            ..Default::default()
+        };
+        let mut graph = Hnsw::new(&config);
+        graph.insert("a", &[1.0, 0.0]);
+        graph.insert("b", &[0.0, 1.0]);
+        assert!(graph.entry.is_none());
+
+        let hits = graph.search(&[0.9, 0.1], 2, |_| true);
+        assert_eq!(hits[0].0, "a");
+        assert_eq!(hits.len(), 2);
+    }
+}
@@ -1,23 +1,27 @@
 //! Local file-based storage implementation
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs;
 
+use crate::bulk::{self, MetadataOp, NodeFilter};
 use crate::config::VectorIndexConfig;
-use crate::core::{Namespace, Node};
+use crate::core::{Namespace, Node, VectorName};
 use crate::error::Result;
 use crate::pathway::Pathway;
 use crate::{NodeInfo, StorageStats};
 
-use super::{StorageBackend, VectorIndex};
+use super::{
+    search_nodes, StorageBackend, TextMatch, TextSearchOptions, VectorIndex, VectorIndexBackend,
+};
 
 pub struct LocalStorage {
     root_path: PathBuf,
     nodes: Arc<DashMap<String, Node>>,
-    vector_index: Arc<VectorIndex>,
+    vector_index: Arc<dyn VectorIndexBackend>,
 }
 
 impl LocalStorage {
@@ -27,17 +31,29 @@ impl LocalStorage {
         let storage = Self {
             root_path: root_path.to_path_buf(),
             nodes: Arc::new(DashMap::new()),
-            vector_index: Arc::new(VectorIndex::new(config)),
+            vector_index: Arc::new(VectorIndex::persistent(config, root_path)),
         };
 
         Ok(storage)
     }
 
+    /// Keep vectors in `index` instead of the files under the root path
+    pub fn with_vector_index(mut self, index: Arc<dyn VectorIndexBackend>) -> Self {
+        self.vector_index = index;
+        self
+    }
+
     fn node_path(&self, pathway: &Pathway) -> PathBuf {
         let rel_path = pathway.to_relative().replace("://", "/");
         self.root_path.join(rel_path).with_extension("json")
     }
 
+    /// Directory holding the files of the nodes below a pathway
+    fn subtree_dir(&self, pathway: &Pathway) -> PathBuf {
+        self.root_path
+            .join(pathway.to_relative().replace("://", "/"))
+    }
+
     async fn load_node(&self, pathway: &Pathway) -> Result<Node> {
         let path = self.node_path(pathway);
 
@@ -51,6 +67,32 @@ impl LocalStorage {
         Ok(node)
     }
 
+    /// Node files for a pathway and everything stored below it
+    async fn node_files(&self, pathway: &Pathway) -> Result<Vec<PathBuf>> {
+        let own = self.node_path(pathway);
+        let mut dirs = vec![own.with_extension("")];
+        let mut files = Vec::new();
+        if own.exists() {
+            files.push(own);
+        }
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "json") {
+                    files.push(path);
+                }
+            }
+        }
+        Ok(files)
+    }
+
     async fn save_node(&self, node: &Node) -> Result<()> {
         let path = self.node_path(&node.pathway);
 
@@ -72,6 +114,7 @@ impl StorageBackend for LocalStorage {
         // Load existing nodes
         // TODO: Implement node loading from disk
 
+        self.vector_index.load().await?;
         Ok(())
     }
 
@@ -79,12 +122,8 @@ impl StorageBackend for LocalStorage {
         // Save to disk
         self.save_node(node).await?;
 
-        // Add to vector index if embedded
-        if !node.embedding.is_empty() {
-            self.vector_index
-                .add(&node.pathway, &node.embedding)
-                .await?;
-        }
+        // Index whichever vectors the node carries
+        self.vector_index.index(node).await?;
 
         // Cache in memory
         self.nodes.insert(node.pathway.to_string(), node.clone());
@@ -92,6 +131,17 @@ impl StorageBackend for LocalStorage {
         Ok(())
     }
 
+    async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
+        for node in nodes {
+            self.save_node(node).await?;
+        }
+        self.vector_index.index_batch(nodes).await?;
+        for node in nodes {
+            self.nodes.insert(node.pathway.to_string(), node.clone());
+        }
+        Ok(())
+    }
+
     async fn get(&self, pathway: &Pathway) -> Result<Node> {
         let key = pathway.to_string();
 
@@ -121,11 +171,13 @@ impl StorageBackend for LocalStorage {
         let path = self.node_path(pathway);
 
         if recursive {
-            // Remove directory and all children
-            if let Some(parent) = path.parent() {
-                if parent.exists() {
-                    fs::remove_dir_all(parent).await?;
-                }
+            // Remove the node's own file and the directory of nodes below it
+            if path.exists() {
+                fs::remove_file(&path).await?;
+            }
+            let below = self.subtree_dir(pathway);
+            if below.exists() {
+                fs::remove_dir_all(&below).await?;
             }
 
             // Remove from cache
@@ -149,7 +201,11 @@ impl StorageBackend for LocalStorage {
         }
 
         // Remove from vector index
-        self.vector_index.remove(pathway).await?;
+        if recursive {
+            self.vector_index.remove_tree(pathway).await?;
+        } else {
+            self.vector_index.remove(pathway).await?;
+        }
 
         Ok(())
     }
@@ -189,39 +245,26 @@ impl StorageBackend for LocalStorage {
             .await
     }
 
+    async fn search_vectors(
+        &self,
+        vector: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.vector_index
+            .search_vectors(vector, names, namespace, limit, threshold)
+            .await
+    }
+
     async fn search_text(
         &self,
         pattern: &str,
         pathway: &Pathway,
-        case_insensitive: bool,
-    ) -> Result<Vec<Pathway>> {
-        let pattern = if case_insensitive {
-            pattern.to_lowercase()
-        } else {
-            pattern.to_string()
-        };
-
-        let results: Vec<Pathway> = self
-            .nodes
-            .iter()
-            .filter(|entry| {
-                let node = entry.value();
-                if !pathway.is_prefix_of(&node.pathway) {
-                    return false;
-                }
-
-                let content = if case_insensitive {
-                    node.content.to_lowercase()
-                } else {
-                    node.content.clone()
-                };
-
-                content.contains(&pattern)
-            })
-            .map(|entry| entry.value().pathway.clone())
-            .collect();
-
-        Ok(results)
+        options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>> {
+        search_nodes(self.nodes.iter(), pattern, pathway, options)
     }
 
     async fn stats(&self) -> Result<StorageStats> {
@@ -231,19 +274,15 @@ impl StorageBackend for LocalStorage {
         };
 
         for entry in self.nodes.iter() {
-            let node = entry.value();
-            if node.is_directory {
-                stats.total_directories += 1;
-            }
-            stats.total_size_bytes += node.size();
+            stats.record(entry.value());
         }
 
         Ok(stats)
     }
 
     async fn flush(&self) -> Result<()> {
-        // All writes are immediate in this implementation
-        Ok(())
+        // Node writes are immediate; fold logged index changes into the snapshot
+        self.vector_index.flush().await
     }
 
     async fn get_children(&self, pathway: &Pathway, max_depth: usize) -> Result<Vec<Node>> {
@@ -282,4 +321,86 @@ impl StorageBackend for LocalStorage {
         }
         Ok(())
     }
+
+    async fn warm(&self, pathway: &Pathway) -> Result<usize> {
+        let files = self.node_files(pathway).await?;
+        for path in &files {
+            let content = fs::read_to_string(path).await?;
+            let node: Node = match serde_json::from_str(&content) {
+                Ok(node) => node,
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), "Skipping unreadable node file: {}", e);
+                    continue;
+                }
+            };
+            let key = node.pathway.to_string();
+            if self.nodes.contains_key(&key) {
+                continue;
+            }
+            // Nodes written before the index was persisted are indexed now
+            if !self.vector_index.contains(&node.pathway) {
+                self.vector_index.index(&node).await?;
+            }
+            self.nodes.insert(key, node);
+        }
+        Ok(files.len())
+    }
+
+    async fn update_metadata(
+        &self,
+        filter: &NodeFilter,
+        ops: &[MetadataOp],
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut changed = Vec::new();
+        for mut entry in self.nodes.iter_mut() {
+            let node = entry.value_mut();
+            if filter.matches(node) && bulk::apply_all(ops, &mut node.metadata) {
+                node.updated_at = now;
+                changed.push(node.clone());
+            }
+        }
+        // Write files after releasing the map's locks
+        for node in &changed {
+            self.save_node(node).await?;
+        }
+        Ok(changed.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recursive_remove_keeps_siblings() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = VectorIndexConfig::default();
+        let storage = LocalStorage::new(dir.path(), &config).await.unwrap();
+        for pathway in [
+            "a3s://knowledge/docs/api",
+            "a3s://knowledge/docs/api/auth.md",
+            "a3s://knowledge/docs/guide.md",
+        ] {
+            let node = Node::new(
+                Pathway::parse(pathway).unwrap(),
+                crate::core::NodeKind::Document,
+                "text".to_string(),
+            );
+            storage.put(&node).await.unwrap();
+        }
+
+        let api = Pathway::parse("a3s://knowledge/docs/api").unwrap();
+        storage.remove(&api, true).await.unwrap();
+        drop(storage);
+
+        let storage = LocalStorage::new(dir.path(), &config).await.unwrap();
+        let guide = Pathway::parse("a3s://knowledge/docs/guide.md").unwrap();
+        assert!(storage.get(&guide).await.is_ok());
+        assert!(!storage.exists(&api).await.unwrap());
+        assert!(!storage
+            .exists(&Pathway::parse("a3s://knowledge/docs/api/auth.md").unwrap())
+            .await
+            .unwrap());
+    }
 }
@@ -1,16 +1,20 @@
 //! In-memory storage implementation (for testing)
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use std::sync::Arc;
 
+use crate::bulk::{self, MetadataOp, NodeFilter};
 use crate::config::VectorIndexConfig;
-use crate::core::{Namespace, Node};
+use crate::core::{Namespace, Node, VectorName};
 use crate::error::Result;
 use crate::pathway::Pathway;
 use crate::{NodeInfo, StorageStats};
 
-use super::{StorageBackend, VectorIndex};
+use super::{
+    search_nodes, StorageBackend, TextMatch, TextSearchOptions, VectorIndex, VectorIndexBackend,
+};
 
 pub struct MemoryStorage {
     nodes: Arc<DashMap<String, Node>>,
@@ -35,17 +39,21 @@ impl StorageBackend for MemoryStorage {
     async fn put(&self, node: &Node) -> Result<()> {
         let key = node.pathway.to_string();
 
-        // Add to vector index if embedded
-        if !node.embedding.is_empty() {
-            self.vector_index
-                .add(&node.pathway, &node.embedding)
-                .await?;
-        }
+        // Index whichever vectors the node carries
+        self.vector_index.index(node).await?;
 
         self.nodes.insert(key, node.clone());
         Ok(())
     }
 
+    async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
+        self.vector_index.index_batch(nodes).await?;
+        for node in nodes {
+            self.nodes.insert(node.pathway.to_string(), node.clone());
+        }
+        Ok(())
+    }
+
     async fn get(&self, pathway: &Pathway) -> Result<Node> {
         let key = pathway.to_string();
         self.nodes
@@ -81,7 +89,11 @@ impl StorageBackend for MemoryStorage {
         }
 
         // Remove from vector index
-        self.vector_index.remove(pathway).await?;
+        if recursive {
+            self.vector_index.remove_tree(pathway).await?;
+        } else {
+            self.vector_index.remove(pathway).await?;
+        }
 
         Ok(())
     }
@@ -120,39 +132,26 @@ impl StorageBackend for MemoryStorage {
             .await
     }
 
+    async fn search_vectors(
+        &self,
+        vector: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.vector_index
+            .search_vectors(vector, names, namespace, limit, threshold)
+            .await
+    }
+
     async fn search_text(
         &self,
         pattern: &str,
         pathway: &Pathway,
-        case_insensitive: bool,
-    ) -> Result<Vec<Pathway>> {
-        let pattern = if case_insensitive {
-            pattern.to_lowercase()
-        } else {
-            pattern.to_string()
-        };
-
-        let results: Vec<Pathway> = self
-            .nodes
-            .iter()
-            .filter(|entry| {
-                let node = entry.value();
-                if !pathway.is_prefix_of(&node.pathway) {
-                    return false;
-                }
-
-                let content = if case_insensitive {
-                    node.content.to_lowercase()
-                } else {
-                    node.content.clone()
-                };
-
-                content.contains(&pattern)
-            })
-            .map(|entry| entry.value().pathway.clone())
-            .collect();
-
-        Ok(results)
+        options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>> {
+        search_nodes(self.nodes.iter(), pattern, pathway, options)
     }
 
     async fn stats(&self) -> Result<StorageStats> {
@@ -162,11 +161,7 @@ impl StorageBackend for MemoryStorage {
         };
 
         for entry in self.nodes.iter() {
-            let node = entry.value();
-            if node.is_directory {
-                stats.total_directories += 1;
-            }
-            stats.total_size_bytes += node.size();
+            stats.record(entry.value());
         }
 
         Ok(stats)
@@ -210,6 +205,23 @@ impl StorageBackend for MemoryStorage {
         }
         Ok(())
     }
+
+    async fn update_metadata(
+        &self,
+        filter: &NodeFilter,
+        ops: &[MetadataOp],
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut changed = 0;
+        for mut entry in self.nodes.iter_mut() {
+            let node = entry.value_mut();
+            if filter.matches(node) && bulk::apply_all(ops, &mut node.metadata) {
+                node.updated_at = now;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
 }
 
 #[cfg(test)]
@@ -223,6 +235,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let storage = MemoryStorage::new(&config);
 
@@ -246,6 +260,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let storage = MemoryStorage::new(&config);
 
@@ -264,6 +280,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let storage = MemoryStorage::new(&config);
 
@@ -284,6 +302,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let storage = MemoryStorage::new(&config);
 
@@ -307,6 +327,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let storage = MemoryStorage::new(&config);
 
@@ -317,6 +339,9 @@ mod tests {
         let stats = storage.stats().await.unwrap();
         assert_eq!(stats.total_nodes, 1);
         assert!(stats.total_size_bytes > 0);
+        assert_eq!(stats.namespaces.len(), 1);
+        assert_eq!(stats.namespaces[0].namespace, Namespace::Knowledge);
+        assert_eq!(stats.namespaces[0].node_count, 1);
     }
 
     #[tokio::test]
@@ -325,6 +350,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let storage = MemoryStorage::new(&config);
 
@@ -1,39 +1,119 @@
 //! Storage backend abstraction and implementations
 
+mod backup;
+mod disk_index;
+mod hnsw;
+#[cfg(feature = "local-storage")]
 mod local;
 mod memory;
+#[cfg(feature = "object-store")]
+mod object_store;
+mod overlay;
+mod persist;
+#[cfg(feature = "http")]
+mod qdrant;
+#[cfg(feature = "http")]
+mod qdrant_index;
+#[cfg(feature = "http")]
+mod remote;
+#[cfg(feature = "http")]
+mod s3;
+mod text_search;
 mod vector_index;
+mod write_behind;
 
+#[cfg(feature = "object-store")]
+pub use self::object_store::ObjectStoreStorage;
+pub use backup::{backup, restore, BackupManifest, BackupTarget, BACKUP_FORMAT_VERSION};
+pub use disk_index::{DiskIndex, DISK_INDEX};
+#[cfg(feature = "local-storage")]
 pub use local::LocalStorage;
 pub use memory::MemoryStorage;
-pub use vector_index::VectorIndex;
+pub use overlay::{MountInfo, OverlayStorage};
+#[cfg(feature = "http")]
+pub use qdrant::QdrantStorage;
+#[cfg(feature = "http")]
+pub use qdrant_index::QdrantIndex;
+#[cfg(feature = "http")]
+pub use remote::RemoteStorage;
+#[cfg(feature = "http")]
+pub use s3::{S3Client, S3Location};
+pub use text_search::{
+    search_nodes, sort_and_limit, MatchSpan, TextLine, TextMatch, TextMatcher, TextSearchOptions,
+};
+pub use vector_index::{open_index, VectorIndex, VectorIndexBackend, QDRANT_INDEX};
+pub use write_behind::WriteBehindStorage;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 
+use crate::bulk::{self, MetadataOp, NodeFilter};
 use crate::config::{StorageBackend as StorageBackendType, StorageConfig};
-use crate::core::Node;
+use crate::core::{Node, VectorName};
 use crate::error::Result;
+use crate::http::HttpClient;
 use crate::pathway::Pathway;
 use crate::{NodeInfo, StorageStats};
 
 /// Create a storage backend based on configuration
-pub async fn create_backend(config: &StorageConfig) -> Result<Arc<dyn StorageBackend>> {
+///
+/// The remote backend sends its requests through `http`.
+pub async fn create_backend(
+    config: &StorageConfig,
+    http: &HttpClient,
+) -> Result<Arc<dyn StorageBackend>> {
     match config.backend {
+        #[cfg(feature = "local-storage")]
         StorageBackendType::Local => {
-            let storage = LocalStorage::new(&config.path, &config.vector_index).await?;
+            let mut storage = LocalStorage::new(&config.path, &config.vector_index).await?;
+            if let Some(index) = open_index(&config.vector_index, &config.path, http)? {
+                storage = storage.with_vector_index(index);
+            }
             Ok(Arc::new(storage))
         }
+        #[cfg(not(feature = "local-storage"))]
+        StorageBackendType::Local => Err(crate::A3SError::Config(
+            "Local storage requires the `local-storage` feature".to_string(),
+        )),
         StorageBackendType::Memory => {
             let storage = MemoryStorage::new(&config.vector_index);
             Ok(Arc::new(storage))
         }
+        #[cfg(feature = "http")]
         StorageBackendType::Remote => {
-            // TODO: Implement remote storage
+            let storage = RemoteStorage::new(config, http.clone())?;
+            Ok(Arc::new(storage))
+        }
+        #[cfg(not(feature = "http"))]
+        StorageBackendType::Remote => {
+            let _ = http;
             Err(crate::A3SError::Config(
-                "Remote storage not yet implemented".to_string(),
+                "Remote storage requires the `http` feature".to_string(),
             ))
         }
+        #[cfg(feature = "object-store")]
+        StorageBackendType::ObjectStore => {
+            let url = config.url.as_deref().ok_or_else(|| {
+                crate::A3SError::Config(
+                    "storage.url is required for the object_store backend".to_string(),
+                )
+            })?;
+            let mut storage = ObjectStoreStorage::new(
+                url,
+                &config.options,
+                Some(&config.path),
+                &config.vector_index,
+            )?;
+            if let Some(index) = open_index(&config.vector_index, &config.path, http)? {
+                storage = storage.with_vector_index(index);
+            }
+            Ok(Arc::new(storage))
+        }
+        #[cfg(not(feature = "object-store"))]
+        StorageBackendType::ObjectStore => Err(crate::A3SError::Config(
+            "Object store storage requires the `object-store` feature".to_string(),
+        )),
     }
 }
 
@@ -67,13 +147,34 @@ pub trait StorageBackend: Send + Sync {
         threshold: f32,
     ) -> Result<Vec<(Pathway, f32)>>;
 
-    /// Search by text pattern
+    /// Search by similarity to any of the named vectors, scoring each node
+    /// by its best match
+    ///
+    /// Backends that only index content vectors search those.
+    async fn search_vectors(
+        &self,
+        vector: &[f32],
+        _names: &[VectorName],
+        namespace: Option<crate::core::Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.search_vector(vector, namespace, limit, threshold)
+            .await
+    }
+
+    /// Non-directory nodes at or below `pathway` whose content matches
+    /// `pattern`, with their matching lines, in pathway order
+    ///
+    /// Matching happens where the nodes are, so only matches leave the
+    /// backend; an invalid or oversized regex is an
+    /// [`A3SError::InvalidPattern`].
     async fn search_text(
         &self,
         pattern: &str,
         pathway: &Pathway,
-        case_insensitive: bool,
-    ) -> Result<Vec<Pathway>>;
+        options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>>;
 
     /// Get storage statistics
     async fn stats(&self) -> Result<StorageStats>;
@@ -81,6 +182,13 @@ pub trait StorageBackend: Send + Sync {
     /// Flush pending writes
     async fn flush(&self) -> Result<()>;
 
+    /// Wait until every write accepted so far is searchable
+    ///
+    /// Backends that apply writes before returning have nothing to wait for.
+    async fn settle(&self) -> Result<()> {
+        Ok(())
+    }
+
     /// Get all children of a pathway (recursive)
     async fn get_children(&self, pathway: &Pathway, max_depth: usize) -> Result<Vec<Node>>;
 
@@ -90,6 +198,15 @@ pub trait StorageBackend: Send + Sync {
     /// Update node digest
     async fn update_digest(&self, pathway: &Pathway, digest: crate::digest::Digest) -> Result<()>;
 
+    /// Load the node at a pathway and every node below it into memory
+    /// ahead of queries, returning how many there are
+    ///
+    /// Backends that keep every node in memory just count them.
+    async fn warm(&self, pathway: &Pathway) -> Result<usize> {
+        let below = self.get_children(pathway, usize::MAX).await?.len();
+        Ok(below + usize::from(self.exists(pathway).await?))
+    }
+
     /// Batch insert nodes
     async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
         for node in nodes {
@@ -97,4 +214,29 @@ pub trait StorageBackend: Send + Sync {
         }
         Ok(())
     }
+
+    /// Apply metadata operations to every node a filter selects, marking
+    /// each changed node updated at `now`, and return how many changed
+    async fn update_metadata(
+        &self,
+        filter: &NodeFilter,
+        ops: &[MetadataOp],
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut changed = Vec::new();
+        for root in filter.roots() {
+            let mut nodes = self.get_children(&root, usize::MAX).await?;
+            if let Ok(node) = self.get(&root).await {
+                nodes.push(node);
+            }
+            for mut node in nodes {
+                if filter.matches(&node) && bulk::apply_all(ops, &mut node.metadata) {
+                    node.updated_at = now;
+                    changed.push(node);
+                }
+            }
+        }
+        self.put_batch(&changed).await?;
+        Ok(changed.len())
+    }
 }
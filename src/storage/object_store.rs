@@ -0,0 +1,508 @@
+//! Object store storage: nodes in S3, GCS, or Azure Blob Storage
+//!
+//! Each node is one JSON object under the bucket URL's prefix, laid out
+//! like local storage's files. Initializing lists and reads every node and
+//! rebuilds the vector index from their embeddings, so a runner without a
+//! persistent disk starts with working searches. Objects read are kept in
+//! a local cache directory keyed by their ETag, so a runner that does keep
+//! its disk between starts downloads only what changed. Writes go to the
+//! object store before the in-memory view, which makes the bucket the
+//! source of truth for every runner sharing it.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures::TryStreamExt;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectMeta, ObjectStore, PutPayload};
+use tokio::fs;
+
+use crate::bulk::{self, MetadataOp, NodeFilter};
+use crate::config::VectorIndexConfig;
+use crate::core::{Namespace, Node, VectorName};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::provenance::sha256_hex;
+use crate::{NodeInfo, StorageStats};
+
+use super::{
+    search_nodes, StorageBackend, TextMatch, TextSearchOptions, VectorIndex, VectorIndexBackend,
+};
+
+/// Environment variable prefixes read as object store options
+const ENV_PREFIXES: &[&str] = &["AWS_", "GOOGLE_", "AZURE_"];
+
+/// Objects read or written at once
+const CONCURRENCY: usize = 16;
+
+pub struct ObjectStoreStorage {
+    store: Arc<dyn ObjectStore>,
+    prefix: ObjectPath,
+    cache_dir: Option<PathBuf>,
+    nodes: Arc<DashMap<String, Node>>,
+    vector_index: Arc<dyn VectorIndexBackend>,
+}
+
+impl ObjectStoreStorage {
+    /// Open the bucket at `url`, e.g. `s3://bucket/prefix`, configured by
+    /// the cloud's environment variables overridden by `options`, caching
+    /// objects read under `cache_dir`
+    pub fn new(
+        url: &str,
+        options: &BTreeMap<String, String>,
+        cache_dir: Option<&Path>,
+        config: &VectorIndexConfig,
+    ) -> Result<Self> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| A3SError::Config(format!("Invalid bucket URL {}: {}", url, e)))?;
+        let env = std::env::vars()
+            .filter(|(key, _)| ENV_PREFIXES.iter().any(|prefix| key.starts_with(prefix)))
+            .map(|(key, value)| (key.to_lowercase(), value));
+        let options = env.chain(options.iter().map(|(k, v)| (k.to_lowercase(), v.clone())));
+        let (store, prefix) = object_store::parse_url_opts(&parsed, options)
+            .map_err(|e| A3SError::Config(format!("Cannot open {}: {}", url, e)))?;
+        Ok(Self::with_store(
+            Arc::from(store),
+            prefix,
+            cache_dir,
+            config,
+        ))
+    }
+
+    /// Store nodes under `prefix` in an already configured object store
+    pub fn with_store(
+        store: Arc<dyn ObjectStore>,
+        prefix: ObjectPath,
+        cache_dir: Option<&Path>,
+        config: &VectorIndexConfig,
+    ) -> Self {
+        Self {
+            store,
+            prefix,
+            cache_dir: cache_dir.map(|dir| dir.join("object-cache")),
+            nodes: Arc::new(DashMap::new()),
+            vector_index: Arc::new(VectorIndex::new(config)),
+        }
+    }
+
+    /// Keep vectors in `index` instead of in memory
+    pub fn with_vector_index(mut self, index: Arc<dyn VectorIndexBackend>) -> Self {
+        self.vector_index = index;
+        self
+    }
+
+    /// Object path of the nodes below a pathway
+    fn subtree_path(&self, pathway: &Pathway) -> ObjectPath {
+        let relative = pathway.to_relative().replace("://", "/");
+        ObjectPath::from(format!("{}/{}", self.prefix, relative))
+    }
+
+    fn node_path(&self, pathway: &Pathway) -> ObjectPath {
+        let relative = pathway.to_relative().replace("://", "/");
+        ObjectPath::from(format!("{}/{}.json", self.prefix, relative))
+    }
+
+    /// Cache file for an object version; `None` without a cache directory
+    /// or a version to key it by
+    fn cache_path(&self, location: &ObjectPath, version: &str) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        let key = sha256_hex(format!("{}\n{}", location, version).as_bytes());
+        Some(dir.join(format!("{}.json", key)))
+    }
+
+    async fn save_node(&self, node: &Node) -> Result<()> {
+        let location = self.node_path(&node.pathway);
+        let bytes = serde_json::to_vec(node)?;
+        let result = self
+            .store
+            .put(&location, PutPayload::from(bytes.clone()))
+            .await
+            .map_err(storage_error)?;
+        if let Some(path) = result
+            .e_tag
+            .and_then(|e_tag| self.cache_path(&location, &e_tag))
+        {
+            write_cache(&path, &bytes).await;
+        }
+        Ok(())
+    }
+
+    /// Read a listed node object, from the cache when this version is there
+    async fn read_object(&self, meta: &ObjectMeta) -> Result<(Node, Option<PathBuf>)> {
+        let version = meta
+            .e_tag
+            .clone()
+            .unwrap_or_else(|| format!("{}:{}", meta.last_modified.to_rfc3339(), meta.size));
+        let cached = self.cache_path(&meta.location, &version);
+        if let Some(path) = &cached {
+            if let Ok(bytes) = fs::read(path).await {
+                if let Ok(node) = serde_json::from_slice(&bytes) {
+                    return Ok((node, cached));
+                }
+            }
+        }
+
+        let bytes = self
+            .store
+            .get(&meta.location)
+            .await
+            .map_err(storage_error)?
+            .bytes()
+            .await
+            .map_err(storage_error)?;
+        let node = serde_json::from_slice(&bytes)?;
+        if let Some(path) = &cached {
+            write_cache(path, &bytes).await;
+        }
+        Ok((node, cached))
+    }
+
+    /// Node objects at and below a pathway, or in the whole store
+    async fn list_objects(&self, below: &ObjectPath) -> Result<Vec<ObjectMeta>> {
+        let objects: Vec<ObjectMeta> = self
+            .store
+            .list(Some(below))
+            .try_collect()
+            .await
+            .map_err(storage_error)?;
+        Ok(objects
+            .into_iter()
+            .filter(|meta| meta.location.extension() == Some("json"))
+            .collect())
+    }
+
+    /// Delete cache files of object versions no longer in the store
+    async fn prune_cache(&self, live: &HashSet<PathBuf>) -> Result<()> {
+        let Some(dir) = &self.cache_dir else {
+            return Ok(());
+        };
+        let mut entries = match fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if !live.contains(&entry.path()) {
+                fs::remove_file(entry.path()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ObjectStoreStorage {
+    async fn initialize(&self) -> Result<()> {
+        let objects = self.list_objects(&self.prefix).await?;
+        let mut live = HashSet::new();
+        let mut nodes = Vec::with_capacity(objects.len());
+        for batch in objects.chunks(CONCURRENCY) {
+            let loaded =
+                futures::future::try_join_all(batch.iter().map(|meta| self.read_object(meta)))
+                    .await?;
+            for (node, cached) in loaded {
+                live.extend(cached);
+                nodes.push(node);
+            }
+        }
+        // An external index already holds the vectors it had before
+        self.vector_index.load().await?;
+        let unindexed: Vec<Node> = nodes
+            .iter()
+            .filter(|node| !self.vector_index.contains(&node.pathway))
+            .cloned()
+            .collect();
+        self.vector_index.index_batch(&unindexed).await?;
+        for node in nodes {
+            self.nodes.insert(node.pathway.to_string(), node);
+        }
+        self.prune_cache(&live).await
+    }
+
+    async fn put(&self, node: &Node) -> Result<()> {
+        self.save_node(node).await?;
+        self.vector_index.index(node).await?;
+        self.nodes.insert(node.pathway.to_string(), node.clone());
+        Ok(())
+    }
+
+    async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
+        for batch in nodes.chunks(CONCURRENCY) {
+            futures::future::try_join_all(batch.iter().map(|node| self.save_node(node))).await?;
+        }
+        self.vector_index.index_batch(nodes).await?;
+        for node in nodes {
+            self.nodes.insert(node.pathway.to_string(), node.clone());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, pathway: &Pathway) -> Result<Node> {
+        let key = pathway.to_string();
+        if let Some(entry) = self.nodes.get(&key) {
+            return Ok(entry.clone());
+        }
+
+        // Written by another runner since this one initialized
+        let bytes = match self.store.get(&self.node_path(pathway)).await {
+            Ok(result) => result.bytes().await.map_err(storage_error)?,
+            Err(object_store::Error::NotFound { .. }) => {
+                return Err(A3SError::NodeNotFound(key));
+            }
+            Err(e) => return Err(storage_error(e)),
+        };
+        let node: Node = serde_json::from_slice(&bytes)?;
+        self.vector_index.index(&node).await?;
+        self.nodes.insert(key, node.clone());
+        Ok(node)
+    }
+
+    async fn exists(&self, pathway: &Pathway) -> Result<bool> {
+        if self.nodes.contains_key(&pathway.to_string()) {
+            return Ok(true);
+        }
+        match self.store.head(&self.node_path(pathway)).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    async fn remove(&self, pathway: &Pathway, recursive: bool) -> Result<()> {
+        let mut locations = vec![self.node_path(pathway)];
+        if recursive {
+            let below = self.list_objects(&self.subtree_path(pathway)).await?;
+            locations.extend(below.into_iter().map(|meta| meta.location));
+        }
+        for location in &locations {
+            match self.store.delete(location).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => return Err(storage_error(e)),
+            }
+        }
+
+        if recursive {
+            self.nodes
+                .retain(|_, node| !pathway.is_prefix_of(&node.pathway));
+            self.vector_index.remove_tree(pathway).await?;
+        } else {
+            self.nodes.remove(&pathway.to_string());
+            self.vector_index.remove(pathway).await?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+        Ok(self
+            .nodes
+            .iter()
+            .filter(|entry| entry.value().pathway.parent().as_ref() == Some(pathway))
+            .map(|entry| {
+                let node = entry.value();
+                NodeInfo {
+                    pathway: node.pathway.clone(),
+                    kind: node.kind,
+                    is_directory: node.is_directory,
+                    size: node.size(),
+                    created_at: node.created_at,
+                    updated_at: node.updated_at,
+                }
+            })
+            .collect())
+    }
+
+    async fn search_vector(
+        &self,
+        vector: &[f32],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.vector_index
+            .search(vector, namespace, limit, threshold)
+            .await
+    }
+
+    async fn search_vectors(
+        &self,
+        vector: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.vector_index
+            .search_vectors(vector, names, namespace, limit, threshold)
+            .await
+    }
+
+    async fn search_text(
+        &self,
+        pattern: &str,
+        pathway: &Pathway,
+        options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>> {
+        search_nodes(self.nodes.iter(), pattern, pathway, options)
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        let mut stats = StorageStats {
+            total_nodes: self.nodes.len() as u64,
+            ..Default::default()
+        };
+        for entry in self.nodes.iter() {
+            stats.record(entry.value());
+        }
+        Ok(stats)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        // Every write is already in the object store
+        Ok(())
+    }
+
+    async fn get_children(&self, pathway: &Pathway, max_depth: usize) -> Result<Vec<Node>> {
+        Ok(self
+            .nodes
+            .iter()
+            .filter(|entry| {
+                let p = &entry.value().pathway;
+                if !pathway.is_prefix_of(p) {
+                    return false;
+                }
+                let depth = p.depth() - pathway.depth();
+                depth > 0 && depth <= max_depth
+            })
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+
+    async fn update_embedding(&self, pathway: &Pathway, embedding: Vec<f32>) -> Result<()> {
+        let Some(mut node) = self.nodes.get(&pathway.to_string()).map(|e| e.clone()) else {
+            return Ok(());
+        };
+        node.embedding = embedding;
+        self.put(&node).await
+    }
+
+    async fn update_digest(&self, pathway: &Pathway, digest: crate::digest::Digest) -> Result<()> {
+        let Some(mut node) = self.nodes.get(&pathway.to_string()).map(|e| e.clone()) else {
+            return Ok(());
+        };
+        node.digest = digest;
+        self.save_node(&node).await?;
+        self.nodes.insert(pathway.to_string(), node);
+        Ok(())
+    }
+
+    async fn update_metadata(
+        &self,
+        filter: &NodeFilter,
+        ops: &[MetadataOp],
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut changed = Vec::new();
+        for entry in self.nodes.iter() {
+            let mut node = entry.value().clone();
+            if filter.matches(&node) && bulk::apply_all(ops, &mut node.metadata) {
+                node.updated_at = now;
+                changed.push(node);
+            }
+        }
+        // Write objects after releasing the map's locks
+        self.put_batch(&changed).await?;
+        Ok(changed.len())
+    }
+}
+
+fn storage_error(e: object_store::Error) -> A3SError {
+    A3SError::Storage(e.to_string())
+}
+
+/// Best-effort cache write; a failed write only costs a later download
+async fn write_cache(path: &Path, bytes: &[u8]) {
+    let written = match path.parent() {
+        Some(dir) => match fs::create_dir_all(dir).await {
+            Ok(()) => fs::write(path, bytes).await,
+            Err(e) => Err(e),
+        },
+        None => fs::write(path, bytes).await,
+    };
+    if let Err(e) = written {
+        tracing::warn!(path = %path.display(), "Cannot cache object: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+    use object_store::memory::InMemory;
+
+    fn node(pathway: &str, embedding: Vec<f32>) -> Node {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            format!("About {}", pathway),
+        );
+        node.embedding = embedding;
+        node
+    }
+
+    #[tokio::test]
+    async fn test_nodes_survive_a_new_runner() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let prefix = ObjectPath::from("ctx");
+        let config = VectorIndexConfig::default();
+        let cache = tempfile::tempdir().unwrap();
+
+        let storage = ObjectStoreStorage::with_store(store.clone(), prefix.clone(), None, &config);
+        storage
+            .put_batch(&[
+                node("a3s://knowledge/docs/api", vec![1.0, 0.0]),
+                node("a3s://knowledge/docs/api/auth.md", vec![0.0, 1.0]),
+                node("a3s://knowledge/docs/guide.md", vec![0.7, 0.7]),
+            ])
+            .await
+            .unwrap();
+
+        // A fresh runner rebuilds its view and index from the bucket
+        let storage = ObjectStoreStorage::with_store(
+            store.clone(),
+            prefix.clone(),
+            Some(cache.path()),
+            &config,
+        );
+        storage.initialize().await.unwrap();
+        let auth = Pathway::parse("a3s://knowledge/docs/auth.md").unwrap();
+        assert!(!storage.exists(&auth).await.unwrap());
+        let hits = storage
+            .search_vector(&[0.0, 1.0], None, 1, 0.9)
+            .await
+            .unwrap();
+        assert_eq!(hits[0].0.to_string(), "a3s://knowledge/docs/api/auth.md");
+        let cached = std::fs::read_dir(cache.path().join("object-cache"))
+            .unwrap()
+            .count();
+        assert_eq!(cached, 3);
+
+        let api = Pathway::parse("a3s://knowledge/docs/api").unwrap();
+        storage.remove(&api, true).await.unwrap();
+        let storage = ObjectStoreStorage::with_store(store, prefix, Some(cache.path()), &config);
+        storage.initialize().await.unwrap();
+        assert_eq!(storage.stats().await.unwrap().total_nodes, 1);
+        assert!(storage
+            .get(&Pathway::parse("a3s://knowledge/docs/guide.md").unwrap())
+            .await
+            .is_ok());
+        // Removed objects' cache files are pruned
+        let cached = std::fs::read_dir(cache.path().join("object-cache"))
+            .unwrap()
+            .count();
+        assert_eq!(cached, 1);
+    }
+}
@@ -0,0 +1,649 @@
+//! Read-only overlays mounted over a base store
+//!
+//! Each mount exposes a subtree of another store at a pathway prefix.
+//! Reads and searches federate across the base and every mount; writes
+//! always go to the base, and writes under a mount are rejected. Every
+//! write passes a gate that [`OverlayStorage::exclusive`] closes, so a
+//! backup or restore sees no write in flight.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+use super::{sort_and_limit, StorageBackend, TextMatch, TextSearchOptions};
+use crate::bulk::{MetadataOp, NodeFilter};
+use crate::core::{Namespace, Node, VectorName};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::{NodeInfo, StorageStats};
+
+/// A store mounted read-only at a pathway prefix
+#[derive(Clone)]
+struct Mount {
+    /// Pathway the mount appears at
+    at: Pathway,
+    /// Subtree of the mounted store exposed at `at`
+    root: Pathway,
+    store: Arc<dyn StorageBackend>,
+}
+
+impl Mount {
+    /// Translate a pathway under `at` into the mounted store
+    fn inner(&self, pathway: &Pathway) -> Pathway {
+        rebase(pathway, &self.at, &self.root)
+    }
+
+    /// Translate a mounted store pathway back under `at`
+    fn outer(&self, pathway: &Pathway) -> Option<Pathway> {
+        self.root
+            .is_prefix_of(pathway)
+            .then(|| rebase(pathway, &self.root, &self.at))
+    }
+
+    fn outer_node(&self, mut node: Node) -> Option<Node> {
+        node.pathway = self.outer(&node.pathway)?;
+        Some(node)
+    }
+
+    /// Root node and descendants within `max_depth` of the mount root
+    async fn nodes(&self, max_depth: usize) -> Result<Vec<Node>> {
+        let mut nodes = Vec::new();
+        if let Ok(node) = self.store.get(&self.root).await {
+            nodes.push(node);
+        }
+        if max_depth > 0 {
+            nodes.extend(self.store.get_children(&self.root, max_depth).await?);
+        }
+        Ok(nodes
+            .into_iter()
+            .filter_map(|node| self.outer_node(node))
+            .collect())
+    }
+}
+
+fn rebase(pathway: &Pathway, from: &Pathway, to: &Pathway) -> Pathway {
+    let mut segments = to.segments().to_vec();
+    segments.extend_from_slice(&pathway.segments()[from.depth()..]);
+    Pathway::new(to.namespace(), segments)
+}
+
+/// Public description of a mount
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    pub at: Pathway,
+    pub root: Pathway,
+}
+
+/// Base store with read-only mounts layered on top
+pub struct OverlayStorage {
+    base: Arc<dyn StorageBackend>,
+    mounts: RwLock<Vec<Mount>>,
+    /// Held shared by each write, exclusively by [`Self::exclusive`]
+    gate: tokio::sync::RwLock<()>,
+}
+
+impl OverlayStorage {
+    pub fn new(base: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            base,
+            mounts: RwLock::new(Vec::new()),
+            gate: tokio::sync::RwLock::new(()),
+        }
+    }
+
+    /// Wait for writes in flight and hold off new ones until the guard drops
+    pub async fn exclusive(&self) -> tokio::sync::RwLockWriteGuard<'_, ()> {
+        self.gate.write().await
+    }
+
+    /// Expose `root` of `store` read-only at `at`
+    ///
+    /// Mounts may not overlap each other.
+    pub fn mount(&self, at: Pathway, store: Arc<dyn StorageBackend>, root: Pathway) -> Result<()> {
+        let mut mounts = self.mounts.write();
+        if let Some(existing) = mounts
+            .iter()
+            .find(|m| m.at.is_prefix_of(&at) || at.is_prefix_of(&m.at))
+        {
+            return Err(A3SError::AlreadyExists(format!(
+                "{} overlaps the mount at {}",
+                at, existing.at
+            )));
+        }
+        mounts.push(Mount { at, root, store });
+        Ok(())
+    }
+
+    /// Remove the mount at `at`, returning whether one existed
+    pub fn unmount(&self, at: &Pathway) -> bool {
+        let mut mounts = self.mounts.write();
+        let before = mounts.len();
+        mounts.retain(|m| m.at != *at);
+        mounts.len() != before
+    }
+
+    /// Current mounts
+    pub fn mounts(&self) -> Vec<MountInfo> {
+        self.mounts
+            .read()
+            .iter()
+            .map(|m| MountInfo {
+                at: m.at.clone(),
+                root: m.root.clone(),
+            })
+            .collect()
+    }
+
+    /// Mount containing a pathway, if any
+    fn mount_for(&self, pathway: &Pathway) -> Option<Mount> {
+        self.mounts
+            .read()
+            .iter()
+            .find(|m| m.at.is_prefix_of(pathway))
+            .cloned()
+    }
+
+    /// Mounts located at or below a pathway
+    fn mounts_under(&self, pathway: &Pathway) -> Vec<Mount> {
+        self.mounts
+            .read()
+            .iter()
+            .filter(|m| pathway.is_prefix_of(&m.at))
+            .cloned()
+            .collect()
+    }
+
+    fn all_mounts(&self) -> Vec<Mount> {
+        self.mounts.read().clone()
+    }
+
+    /// The writable store under the mounts
+    pub fn base(&self) -> Arc<dyn StorageBackend> {
+        self.base.clone()
+    }
+
+    fn ensure_writable(&self, pathway: &Pathway) -> Result<()> {
+        match self.mount_for(pathway) {
+            Some(mount) => Err(A3SError::ReadOnly(format!(
+                "{} is inside the overlay mounted at {}",
+                pathway, mount.at
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Best `limit` vector hits of `store` that `keep` accepts
+///
+/// Stores search whole namespaces, so hits outside the wanted pathways
+/// may crowd out the rest; the search is repeated for more hits until
+/// enough are kept or the store has no more.
+async fn search_filtered(
+    store: &dyn StorageBackend,
+    vector: &[f32],
+    names: &[VectorName],
+    namespace: Option<Namespace>,
+    limit: usize,
+    threshold: f32,
+    keep: impl Fn(&Pathway) -> bool,
+) -> Result<Vec<(Pathway, f32)>> {
+    let mut fetch = limit;
+    loop {
+        let found = store
+            .search_vectors(vector, names, namespace, fetch, threshold)
+            .await?;
+        let exhausted = found.len() < fetch;
+        let mut kept: Vec<(Pathway, f32)> = found
+            .into_iter()
+            .filter(|(pathway, _)| keep(pathway))
+            .collect();
+        if kept.len() >= limit || exhausted {
+            kept.truncate(limit);
+            return Ok(kept);
+        }
+        fetch = fetch.saturating_mul(4);
+    }
+}
+
+#[async_trait]
+impl StorageBackend for OverlayStorage {
+    async fn initialize(&self) -> Result<()> {
+        self.base.initialize().await
+    }
+
+    async fn put(&self, node: &Node) -> Result<()> {
+        self.ensure_writable(&node.pathway)?;
+        let _gate = self.gate.read().await;
+        self.base.put(node).await
+    }
+
+    async fn get(&self, pathway: &Pathway) -> Result<Node> {
+        match self.mount_for(pathway) {
+            Some(mount) => {
+                let node = mount.store.get(&mount.inner(pathway)).await?;
+                mount
+                    .outer_node(node)
+                    .ok_or_else(|| A3SError::NodeNotFound(pathway.to_string()))
+            }
+            None => self.base.get(pathway).await,
+        }
+    }
+
+    async fn exists(&self, pathway: &Pathway) -> Result<bool> {
+        match self.mount_for(pathway) {
+            Some(mount) => mount.store.exists(&mount.inner(pathway)).await,
+            None => self.base.exists(pathway).await,
+        }
+    }
+
+    async fn remove(&self, pathway: &Pathway, recursive: bool) -> Result<()> {
+        self.ensure_writable(pathway)?;
+        let _gate = self.gate.read().await;
+        self.base.remove(pathway, recursive).await
+    }
+
+    async fn list(&self, pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+        if let Some(mount) = self.mount_for(pathway) {
+            let infos = mount.store.list(&mount.inner(pathway)).await?;
+            return Ok(infos
+                .into_iter()
+                .filter_map(|mut info| {
+                    info.pathway = mount.outer(&info.pathway)?;
+                    Some(info)
+                })
+                .collect());
+        }
+
+        let mut infos = self.base.list(pathway).await?;
+        for mount in self.mounts_under(pathway) {
+            if mount.at.parent().as_ref() != Some(pathway)
+                || infos.iter().any(|info| info.pathway == mount.at)
+            {
+                continue;
+            }
+            let node = match mount.store.get(&mount.root).await {
+                Ok(node) => node,
+                Err(_) => Node::directory(mount.root.clone()),
+            };
+            infos.push(NodeInfo {
+                pathway: mount.at.clone(),
+                kind: node.kind,
+                is_directory: node.is_directory,
+                size: node.size(),
+                created_at: node.created_at,
+                updated_at: node.updated_at,
+            });
+        }
+        Ok(infos)
+    }
+
+    async fn search_vector(
+        &self,
+        vector: &[f32],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.search_vectors(vector, &[VectorName::Content], namespace, limit, threshold)
+            .await
+    }
+
+    async fn search_vectors(
+        &self,
+        vector: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        let mounts: Vec<Mount> = self
+            .all_mounts()
+            .into_iter()
+            .filter(|mount| namespace.is_none_or(|ns| ns == mount.at.namespace()))
+            .collect();
+        // Base nodes under a mount are hidden by it
+        let mut results = search_filtered(
+            self.base.as_ref(),
+            vector,
+            names,
+            namespace,
+            limit,
+            threshold,
+            |pathway| !mounts.iter().any(|mount| mount.at.is_prefix_of(pathway)),
+        )
+        .await?;
+
+        for mount in &mounts {
+            let found = search_filtered(
+                mount.store.as_ref(),
+                vector,
+                names,
+                Some(mount.root.namespace()),
+                limit,
+                threshold,
+                |pathway| mount.root.is_prefix_of(pathway),
+            )
+            .await?;
+            results.extend(
+                found
+                    .into_iter()
+                    .filter_map(|(pathway, score)| Some((mount.outer(&pathway)?, score))),
+            );
+        }
+
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    async fn search_text(
+        &self,
+        pattern: &str,
+        pathway: &Pathway,
+        options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>> {
+        let outer = |mount: &Mount, found: Vec<TextMatch>| -> Vec<TextMatch> {
+            found
+                .into_iter()
+                .filter_map(|m| {
+                    Some(TextMatch {
+                        pathway: mount.outer(&m.pathway)?,
+                        lines: m.lines,
+                    })
+                })
+                .collect()
+        };
+        if let Some(mount) = self.mount_for(pathway) {
+            let found = mount
+                .store
+                .search_text(pattern, &mount.inner(pathway), options)
+                .await?;
+            return Ok(outer(&mount, found));
+        }
+
+        let mounts = self.mounts_under(pathway);
+        // Base nodes under a mount are hidden by it, so the base's first
+        // matches may not be the overlay's
+        let base_options = if mounts.is_empty() {
+            options.clone()
+        } else {
+            TextSearchOptions {
+                max_results: None,
+                ..options.clone()
+            }
+        };
+        let mut results = self
+            .base
+            .search_text(pattern, pathway, &base_options)
+            .await?;
+        results.retain(|m| !mounts.iter().any(|mount| mount.at.is_prefix_of(&m.pathway)));
+        for mount in mounts {
+            let found = mount
+                .store
+                .search_text(pattern, &mount.root, options)
+                .await?;
+            results.extend(outer(&mount, found));
+        }
+        sort_and_limit(&mut results, options);
+        Ok(results)
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        let mut stats = self.base.stats().await?;
+        for mount in self.all_mounts() {
+            for node in mount.nodes(usize::MAX).await? {
+                stats.total_nodes += 1;
+                stats.record(&node);
+            }
+        }
+        Ok(stats)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.base.flush().await
+    }
+
+    async fn settle(&self) -> Result<()> {
+        self.base.settle().await
+    }
+
+    async fn get_children(&self, pathway: &Pathway, max_depth: usize) -> Result<Vec<Node>> {
+        if let Some(mount) = self.mount_for(pathway) {
+            let nodes = mount
+                .store
+                .get_children(&mount.inner(pathway), max_depth)
+                .await?;
+            return Ok(nodes
+                .into_iter()
+                .filter_map(|node| mount.outer_node(node))
+                .collect());
+        }
+
+        let mut nodes = self.base.get_children(pathway, max_depth).await?;
+        for mount in self.mounts_under(pathway) {
+            let offset = mount.at.depth() - pathway.depth();
+            if offset == 0 || offset > max_depth {
+                continue;
+            }
+            nodes.extend(mount.nodes(max_depth - offset).await?);
+        }
+        Ok(nodes)
+    }
+
+    async fn warm(&self, pathway: &Pathway) -> Result<usize> {
+        if let Some(mount) = self.mount_for(pathway) {
+            return mount.store.warm(&mount.inner(pathway)).await;
+        }
+
+        let mut warmed = self.base.warm(pathway).await?;
+        for mount in self.mounts_under(pathway) {
+            warmed += mount.store.warm(&mount.root).await?;
+        }
+        Ok(warmed)
+    }
+
+    async fn update_embedding(&self, pathway: &Pathway, embedding: Vec<f32>) -> Result<()> {
+        self.ensure_writable(pathway)?;
+        let _gate = self.gate.read().await;
+        self.base.update_embedding(pathway, embedding).await
+    }
+
+    async fn update_digest(&self, pathway: &Pathway, digest: crate::digest::Digest) -> Result<()> {
+        self.ensure_writable(pathway)?;
+        let _gate = self.gate.read().await;
+        self.base.update_digest(pathway, digest).await
+    }
+
+    async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
+        for node in nodes {
+            self.ensure_writable(&node.pathway)?;
+        }
+        let _gate = self.gate.read().await;
+        self.base.put_batch(nodes).await
+    }
+
+    /// Mounted nodes are never selected; a filter confined to a mount is rejected
+    async fn update_metadata(
+        &self,
+        filter: &NodeFilter,
+        ops: &[MetadataOp],
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        for root in filter.roots() {
+            self.ensure_writable(&root)?;
+        }
+        let _gate = self.gate.read().await;
+        self.base.update_metadata(filter, ops, now).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::storage::MemoryStorage;
+
+    fn node(pathway: &str, content: &str, embedding: Vec<f32>) -> Node {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            content.to_string(),
+        );
+        node.embedding = embedding;
+        node
+    }
+
+    #[tokio::test]
+    async fn test_overlay_federates_reads_and_rejects_writes() {
+        let base = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        base.put(&node("a3s://memory/agent/note", "private", vec![0.6, 0.8]))
+            .await
+            .unwrap();
+
+        let shared = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        shared
+            .put(&node(
+                "a3s://knowledge/org/policy",
+                "shared",
+                vec![1.0, 0.0],
+            ))
+            .await
+            .unwrap();
+
+        let overlay = OverlayStorage::new(base);
+        overlay
+            .mount(
+                Pathway::parse("a3s://knowledge/shared").unwrap(),
+                shared,
+                Pathway::parse("a3s://knowledge/org").unwrap(),
+            )
+            .unwrap();
+
+        let mounted = Pathway::parse("a3s://knowledge/shared/policy").unwrap();
+        assert_eq!(overlay.get(&mounted).await.unwrap().content, "shared");
+
+        let results = overlay
+            .search_vector(&[1.0, 0.0], None, 10, 0.0)
+            .await
+            .unwrap();
+        assert_eq!(results[0].0, mounted);
+        assert_eq!(results.len(), 2);
+
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+        let listed = overlay.list(&root).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].pathway.to_string(), "a3s://knowledge/shared");
+
+        let err = overlay
+            .put(&node("a3s://knowledge/shared/new", "x", vec![]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, A3SError::ReadOnly(_)));
+        overlay
+            .put(&node("a3s://knowledge/local", "x", vec![]))
+            .await
+            .unwrap();
+
+        assert_eq!(overlay.stats().await.unwrap().total_nodes, 3);
+    }
+
+    #[tokio::test]
+    async fn test_vector_search_finds_small_mounts() {
+        let base = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        base.put(&node("a3s://knowledge/shared/stale", "x", vec![1.0, 0.0]))
+            .await
+            .unwrap();
+        base.put(&node("a3s://knowledge/local", "x", vec![0.0, 1.0]))
+            .await
+            .unwrap();
+
+        // The mounted subtree's node ranks below everything else in its store
+        let shared = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        for i in 0..20 {
+            shared
+                .put(&node(
+                    &format!("a3s://knowledge/other/{}", i),
+                    "x",
+                    vec![1.0, 0.0],
+                ))
+                .await
+                .unwrap();
+        }
+        shared
+            .put(&node("a3s://knowledge/org/policy", "x", vec![0.8, 0.6]))
+            .await
+            .unwrap();
+
+        let overlay = OverlayStorage::new(base);
+        overlay
+            .mount(
+                Pathway::parse("a3s://knowledge/shared").unwrap(),
+                shared,
+                Pathway::parse("a3s://knowledge/org").unwrap(),
+            )
+            .unwrap();
+
+        let results = overlay
+            .search_vector(&[1.0, 0.0], None, 1, 0.0)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.to_string(), "a3s://knowledge/shared/policy");
+    }
+
+    #[tokio::test]
+    async fn test_text_search_hides_shadowed_base_nodes() {
+        let base = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        for pathway in ["a3s://knowledge/a", "a3s://knowledge/shared/stale"] {
+            base.put(&node(pathway, "rotate keys", vec![]))
+                .await
+                .unwrap();
+        }
+        let shared = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        shared
+            .put(&node("a3s://knowledge/org/keys", "Rotate keys", vec![]))
+            .await
+            .unwrap();
+        let overlay = OverlayStorage::new(base);
+        overlay
+            .mount(
+                Pathway::parse("a3s://knowledge/shared").unwrap(),
+                shared,
+                Pathway::parse("a3s://knowledge/org").unwrap(),
+            )
+            .unwrap();
+
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+        let options = TextSearchOptions {
+            regex: true,
+            case_insensitive: true,
+            max_results: Some(2),
+        };
+        let found: Vec<String> = overlay
+            .search_text("^rotate", &root, &options)
+            .await
+            .unwrap()
+            .iter()
+            .map(|m| m.pathway.to_string())
+            .collect();
+        assert_eq!(
+            found,
+            vec!["a3s://knowledge/a", "a3s://knowledge/shared/keys"]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_mounts_rejected() {
+        let base = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        let overlay = OverlayStorage::new(base.clone());
+        let at = Pathway::parse("a3s://knowledge/shared").unwrap();
+        overlay.mount(at.clone(), base.clone(), at.clone()).unwrap();
+
+        let nested = at.join("inner");
+        assert!(overlay.mount(nested, base, at.clone()).is_err());
+        assert!(overlay.unmount(&at));
+        assert!(overlay.mounts().is_empty());
+    }
+}
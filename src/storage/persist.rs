@@ -0,0 +1,343 @@
+//! On-disk persistence for the vector index
+//!
+//! `vectors.bin` is a snapshot of every node's vectors and `vectors.log`
+//! appends each change made since. Loading replays the log over the
+//! snapshot; compaction folds both into a fresh snapshot and empties the
+//! log. Records are little-endian:
+//!
+//! ```text
+//! record = op:u8 key_len:u32 key [count:u8 vector*]   op 0 replaces, 1 removes
+//! vector = name_len:u8 name dim:u32 f32*dim
+//! ```
+//!
+//! A record cut short by a crash ends the log: everything before it loads
+//! and the log is truncated to that point.
+
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::core::VectorName;
+use crate::error::{A3SError, Result};
+
+/// Leads every snapshot file
+const SNAPSHOT_MAGIC: &[u8; 8] = b"A3SVEC01";
+
+const OP_REPLACE: u8 = 0;
+const OP_REMOVE: u8 = 1;
+
+/// Vectors indexed for one node
+pub(super) type NodeVectors = Vec<(VectorName, Vec<f32>)>;
+
+/// A change to the index
+pub(super) enum Record<'a> {
+    /// Replace every vector indexed for a node
+    Replace {
+        key: &'a str,
+        vectors: &'a [(VectorName, Vec<f32>)],
+    },
+    /// Drop a node from the index
+    Remove { key: &'a str },
+}
+
+/// Snapshot and change log backing a vector index
+pub(super) struct IndexFiles {
+    snapshot: PathBuf,
+    log: PathBuf,
+    state: Mutex<LogState>,
+}
+
+#[derive(Default)]
+struct LogState {
+    writer: Option<File>,
+    /// Records appended since the last compaction
+    records: usize,
+}
+
+impl IndexFiles {
+    /// Files `vectors.bin` and `vectors.log` in `dir`
+    pub(super) fn new(dir: &Path) -> Self {
+        Self {
+            snapshot: dir.join("vectors.bin"),
+            log: dir.join("vectors.log"),
+            state: Mutex::new(LogState::default()),
+        }
+    }
+
+    /// Read the snapshot and replay the log over it
+    pub(super) fn load(&self) -> Result<BTreeMap<String, NodeVectors>> {
+        let mut nodes = BTreeMap::new();
+        match fs::read(&self.snapshot) {
+            Ok(bytes) => {
+                let body = bytes.strip_prefix(SNAPSHOT_MAGIC).ok_or_else(|| {
+                    A3SError::Storage(format!(
+                        "{} is not a vector index snapshot",
+                        self.snapshot.display()
+                    ))
+                })?;
+                replay(body, &mut nodes);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        match fs::read(&self.log) {
+            Ok(bytes) => {
+                let (records, valid) = replay(&bytes, &mut nodes);
+                if valid < bytes.len() {
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&self.log)?
+                        .set_len(valid as u64)?;
+                }
+                self.state.lock().records = records;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(nodes)
+    }
+
+    /// Append a change to the log, returning the records logged since the
+    /// last compaction
+    pub(super) fn append(&self, record: &Record) -> Result<usize> {
+        let mut bytes = Vec::new();
+        encode(record, &mut bytes);
+
+        let mut state = self.state.lock();
+        if state.writer.is_none() {
+            if let Some(parent) = self.log.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            state.writer = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.log)?,
+            );
+        }
+        if let Some(writer) = state.writer.as_mut() {
+            writer.write_all(&bytes)?;
+        }
+        state.records += 1;
+        Ok(state.records)
+    }
+
+    /// Records appended since the last compaction
+    pub(super) fn pending(&self) -> usize {
+        self.state.lock().records
+    }
+
+    /// Write every node's vectors to a fresh snapshot and empty the log
+    ///
+    /// The snapshot is written beside the old one and renamed over it, so a
+    /// crash leaves either the old or the new snapshot intact.
+    pub(super) fn compact<'a>(
+        &self,
+        nodes: impl IntoIterator<Item = (&'a str, &'a [(VectorName, Vec<f32>)])>,
+    ) -> Result<()> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        for (key, vectors) in nodes {
+            encode(&Record::Replace { key, vectors }, &mut bytes);
+        }
+
+        let mut state = self.state.lock();
+        if let Some(parent) = self.snapshot.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = self.snapshot.with_extension("bin.tmp");
+        let mut file = File::create(&temp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        fs::rename(&temp, &self.snapshot)?;
+
+        state.writer = Some(File::create(&self.log)?);
+        state.records = 0;
+        Ok(())
+    }
+}
+
+fn encode(record: &Record, out: &mut Vec<u8>) {
+    let (op, key) = match record {
+        Record::Replace { key, .. } => (OP_REPLACE, key),
+        Record::Remove { key } => (OP_REMOVE, key),
+    };
+    out.push(op);
+    out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    out.extend_from_slice(key.as_bytes());
+
+    if let Record::Replace { vectors, .. } = record {
+        out.push(vectors.len() as u8);
+        for (name, vector) in vectors.iter() {
+            let name = name.as_str();
+            out.push(name.len() as u8);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&(vector.len() as u32).to_le_bytes());
+            for value in vector {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Apply records to `nodes`, returning how many were read and the length
+/// of the intact prefix they span
+fn replay(bytes: &[u8], nodes: &mut BTreeMap<String, NodeVectors>) -> (usize, usize) {
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut count = 0;
+    while reader.pos < bytes.len() {
+        let start = reader.pos;
+        match reader.record() {
+            Some((key, Some(vectors))) => {
+                nodes.insert(key, vectors);
+            }
+            Some((key, None)) => {
+                nodes.remove(&key);
+            }
+            None => {
+                tracing::warn!(
+                    offset = start,
+                    "Ignoring truncated or corrupt vector index record"
+                );
+                return (count, start);
+            }
+        }
+        count += 1;
+    }
+    (count, bytes.len())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Next record's key, with its vectors unless it is a removal
+    fn record(&mut self) -> Option<(String, Option<NodeVectors>)> {
+        let op = self.u8()?;
+        let len = self.u32()? as usize;
+        let key = String::from_utf8(self.take(len)?.to_vec()).ok()?;
+        match op {
+            OP_REMOVE => Some((key, None)),
+            OP_REPLACE => {
+                let count = self.u8()?;
+                let mut vectors = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let len = self.u8()? as usize;
+                    let name = std::str::from_utf8(self.take(len)?).ok()?;
+                    let name = VectorName::parse(name)?;
+                    let dimension = self.u32()? as usize;
+                    let vector = self
+                        .take(dimension.checked_mul(4)?)?
+                        .chunks_exact(4)
+                        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                        .collect();
+                    vectors.push((name, vector));
+                }
+                Some((key, Some(vectors)))
+            }
+            _ => None,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let slice = self.bytes.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let b = self.take(4)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_and_log_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = IndexFiles::new(dir.path());
+        assert!(files.load().unwrap().is_empty());
+
+        let a = vec![(VectorName::Content, vec![1.0, 0.0])];
+        let b = vec![
+            (VectorName::Content, vec![0.0, 1.0]),
+            (VectorName::Title, vec![0.5, 0.5]),
+        ];
+        files
+            .compact([("a3s://knowledge/a", a.as_slice())])
+            .unwrap();
+        files
+            .append(&Record::Replace {
+                key: "a3s://knowledge/b",
+                vectors: &b,
+            })
+            .unwrap();
+        assert_eq!(
+            files
+                .append(&Record::Remove {
+                    key: "a3s://knowledge/a"
+                })
+                .unwrap(),
+            2
+        );
+
+        let reopened = IndexFiles::new(dir.path());
+        let nodes = reopened.load().unwrap();
+        assert_eq!(reopened.pending(), 2);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes["a3s://knowledge/b"], b);
+    }
+
+    #[test]
+    fn test_truncated_log_keeps_complete_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = IndexFiles::new(dir.path());
+        let vectors = vec![(VectorName::Content, vec![1.0, 2.0, 3.0])];
+        for key in ["a3s://knowledge/a", "a3s://knowledge/b"] {
+            files
+                .append(&Record::Replace {
+                    key,
+                    vectors: &vectors,
+                })
+                .unwrap();
+        }
+
+        // Cut the second record short, as a crash mid-write would
+        let log = dir.path().join("vectors.log");
+        let bytes = fs::read(&log).unwrap();
+        fs::write(&log, &bytes[..bytes.len() - 3]).unwrap();
+
+        let files = IndexFiles::new(dir.path());
+        let nodes = files.load().unwrap();
+        assert_eq!(nodes.keys().collect::<Vec<_>>(), vec!["a3s://knowledge/a"]);
+
+        // Later appends land after the intact prefix
+        files
+            .append(&Record::Remove {
+                key: "a3s://knowledge/a",
+            })
+            .unwrap();
+        assert!(IndexFiles::new(dir.path()).load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_foreign_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("vectors.bin"), b"not an index").unwrap();
+        assert!(matches!(
+            IndexFiles::new(dir.path()).load(),
+            Err(A3SError::Storage(_))
+        ));
+    }
+}
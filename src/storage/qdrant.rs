@@ -0,0 +1,313 @@
+//! Read-only storage over a Qdrant collection
+//!
+//! Each point appears as a document at `<at>/<point id>`, with the payload's
+//! content field as its text and the remaining payload fields as custom
+//! metadata. A collection is searched, not browsed: listing and child
+//! lookups return nothing, text search is not forwarded, and every write is
+//! rejected.
+
+use async_trait::async_trait;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+use crate::config::ProxyConfig;
+use crate::core::{Namespace, Node, NodeKind};
+use crate::digest::Digest;
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+use crate::pathway::Pathway;
+use crate::{NodeInfo, StorageStats};
+
+use super::{StorageBackend, TextMatch, TextSearchOptions};
+
+/// A Qdrant collection exposed as read-only nodes
+pub struct QdrantStorage {
+    /// Pathway points appear under
+    at: Pathway,
+    collection_url: String,
+    api_key: Option<String>,
+    vector: Option<String>,
+    content_field: String,
+    timeout: Duration,
+    http: HttpClient,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    result: T,
+}
+
+#[derive(Deserialize)]
+struct Point {
+    id: Value,
+    #[serde(default)]
+    payload: Option<Map<String, Value>>,
+}
+
+#[derive(Deserialize)]
+struct ScoredPoint {
+    id: Value,
+    score: f32,
+}
+
+#[derive(Serialize)]
+struct SearchRequest {
+    vector: Value,
+    limit: usize,
+    score_threshold: f32,
+    with_payload: bool,
+}
+
+impl QdrantStorage {
+    pub fn new(config: &ProxyConfig, http: HttpClient) -> Result<Self> {
+        let collection = config.collection.as_deref().ok_or_else(|| {
+            A3SError::Config(format!("Proxy at {} needs a qdrant collection", config.at))
+        })?;
+        url::Url::parse(&config.url)
+            .map_err(|e| A3SError::Config(format!("Invalid proxy URL {}: {}", config.url, e)))?;
+
+        Ok(Self {
+            at: Pathway::parse(&config.at)?,
+            collection_url: format!(
+                "{}/collections/{}",
+                config.url.trim_end_matches('/'),
+                collection
+            ),
+            api_key: config.api_key.clone(),
+            vector: config.vector.clone(),
+            content_field: config.content_field.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+            http,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.collection_url, path))
+            .timeout(self.timeout);
+        match &self.api_key {
+            Some(key) => request.header("api-key", key),
+            None => request,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        pathway: Option<&Pathway>,
+    ) -> Result<T> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| A3SError::Storage(format!("Qdrant request failed: {}", e)))?;
+        let status = response.status();
+        if let (StatusCode::NOT_FOUND, Some(pathway)) = (status, pathway) {
+            return Err(A3SError::NodeNotFound(pathway.to_string()));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(A3SError::Storage(format!(
+                "Qdrant error {}: {}",
+                status, body
+            )));
+        }
+        let body: Response<T> = response
+            .json()
+            .await
+            .map_err(|e| A3SError::Storage(format!("Invalid Qdrant response: {}", e)))?;
+        Ok(body.result)
+    }
+
+    /// Point id addressed by a pathway directly under `at`
+    fn point_id(&self, pathway: &Pathway) -> Option<String> {
+        (pathway.depth() == self.at.depth() + 1 && self.at.is_prefix_of(pathway))
+            .then(|| pathway.segments().last().cloned())
+            .flatten()
+    }
+
+    fn point_pathway(&self, id: &Value) -> Option<Pathway> {
+        match id {
+            Value::Number(n) => Some(self.at.join(&n.to_string())),
+            Value::String(s) => Some(self.at.join(s)),
+            _ => None,
+        }
+    }
+
+    fn to_node(&self, point: Point) -> Option<Node> {
+        let pathway = self.point_pathway(&point.id)?;
+        let mut payload = point.payload.unwrap_or_default();
+        let content = match payload.remove(&self.content_field) {
+            Some(Value::String(content)) => content,
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        let mut node = Node::new(pathway, NodeKind::Document, content);
+        node.metadata.custom = payload.into_iter().collect();
+        Some(node)
+    }
+
+    fn read_only(&self, pathway: &Pathway) -> A3SError {
+        A3SError::ReadOnly(format!(
+            "{} is served by the Qdrant proxy at {}",
+            pathway, self.at
+        ))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for QdrantStorage {
+    async fn initialize(&self) -> Result<()> {
+        self.send::<Value>(self.request(Method::GET, ""), None)
+            .await?;
+        Ok(())
+    }
+
+    async fn put(&self, node: &Node) -> Result<()> {
+        Err(self.read_only(&node.pathway))
+    }
+
+    async fn get(&self, pathway: &Pathway) -> Result<Node> {
+        let id = self
+            .point_id(pathway)
+            .ok_or_else(|| A3SError::NodeNotFound(pathway.to_string()))?;
+        let point: Point = self
+            .send(
+                self.request(Method::GET, &format!("/points/{}", id)),
+                Some(pathway),
+            )
+            .await?;
+        self.to_node(point)
+            .ok_or_else(|| A3SError::NodeNotFound(pathway.to_string()))
+    }
+
+    async fn exists(&self, pathway: &Pathway) -> Result<bool> {
+        match self.get(pathway).await {
+            Ok(_) => Ok(true),
+            Err(A3SError::NodeNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn remove(&self, pathway: &Pathway, _recursive: bool) -> Result<()> {
+        Err(self.read_only(pathway))
+    }
+
+    async fn list(&self, _pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+        Ok(Vec::new())
+    }
+
+    async fn search_vector(
+        &self,
+        vector: &[f32],
+        _namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        let vector = match &self.vector {
+            Some(name) => serde_json::json!({ "name": name, "vector": vector }),
+            None => serde_json::json!(vector),
+        };
+        let request = self
+            .request(Method::POST, "/points/search")
+            .json(&SearchRequest {
+                vector,
+                limit,
+                score_threshold: threshold,
+                with_payload: false,
+            });
+        let points: Vec<ScoredPoint> = self.send(request, None).await?;
+        Ok(points
+            .into_iter()
+            .filter_map(|p| Some((self.point_pathway(&p.id)?, p.score)))
+            .collect())
+    }
+
+    async fn search_text(
+        &self,
+        _pattern: &str,
+        _pathway: &Pathway,
+        _options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>> {
+        Ok(Vec::new())
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        Ok(StorageStats::default())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn get_children(&self, _pathway: &Pathway, _max_depth: usize) -> Result<Vec<Node>> {
+        Ok(Vec::new())
+    }
+
+    async fn update_embedding(&self, pathway: &Pathway, _embedding: Vec<f32>) -> Result<()> {
+        Err(self.read_only(pathway))
+    }
+
+    async fn update_digest(&self, pathway: &Pathway, _digest: Digest) -> Result<()> {
+        Err(self.read_only(pathway))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProxyKind;
+
+    fn storage() -> QdrantStorage {
+        let config = ProxyConfig {
+            at: "a3s://knowledge/legacy".to_string(),
+            kind: ProxyKind::Qdrant,
+            url: "http://qdrant.internal:6333/".to_string(),
+            api_key: None,
+            root: None,
+            collection: Some("docs".to_string()),
+            vector: None,
+            content_field: "text".to_string(),
+            timeout_secs: 30,
+        };
+        QdrantStorage::new(&config, crate::http::default_client()).unwrap()
+    }
+
+    #[test]
+    fn test_point_pathways() {
+        let storage = storage();
+        assert_eq!(
+            storage.collection_url,
+            "http://qdrant.internal:6333/collections/docs"
+        );
+
+        let pathway = storage.point_pathway(&serde_json::json!(42)).unwrap();
+        assert_eq!(pathway.to_string(), "a3s://knowledge/legacy/42");
+        assert_eq!(storage.point_id(&pathway).as_deref(), Some("42"));
+
+        let nested = Pathway::parse("a3s://knowledge/legacy/42/more").unwrap();
+        assert_eq!(storage.point_id(&nested), None);
+        assert_eq!(storage.point_id(&storage.at), None);
+    }
+
+    #[test]
+    fn test_point_to_node() {
+        let storage = storage();
+        let point: Point = serde_json::from_value(serde_json::json!({
+            "id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26",
+            "payload": { "text": "Rotate keys yearly", "team": "security" }
+        }))
+        .unwrap();
+
+        let node = storage.to_node(point).unwrap();
+        assert_eq!(
+            node.pathway.to_string(),
+            "a3s://knowledge/legacy/5c56c793-69f3-4fbf-87e6-c4bf54c28c26"
+        );
+        assert_eq!(node.content, "Rotate keys yearly");
+        assert_eq!(node.metadata.custom["team"], "security");
+    }
+}
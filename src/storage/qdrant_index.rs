@@ -0,0 +1,459 @@
+//! Vector index kept in a Qdrant collection
+//!
+//! Each of a node's named vectors is one point, with a deterministic id
+//! derived from the pathway and vector name so a rewrite replaces it in
+//! place. Points carry the pathway, namespace, and vector name as
+//! payload, which searches filter on. The collection is created with
+//! cosine distance on the first write, sized to that write's vectors.
+//! Which vectors each node has is mirrored in memory, loaded by scrolling
+//! the collection, so removals and `contains` need no round trip.
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config::QdrantIndexConfig;
+use crate::core::{Namespace, Node, VectorName};
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+use crate::pathway::Pathway;
+use crate::provenance::sha256_hex;
+
+use super::persist::NodeVectors;
+use super::vector_index::{node_vectors, VectorIndexBackend};
+
+/// Points sent or fetched per request
+const PAGE_SIZE: usize = 256;
+
+/// Vectors of a store's nodes in a Qdrant collection
+pub struct QdrantIndex {
+    collection_url: String,
+    api_key: Option<String>,
+    timeout: Duration,
+    http: HttpClient,
+    /// Names of the vectors indexed for each node
+    nodes: DashMap<String, Vec<VectorName>>,
+    /// Set once the collection is known to exist
+    ready: AtomicBool,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    result: T,
+}
+
+#[derive(Deserialize)]
+struct ScrollPage {
+    points: Vec<Point>,
+    next_page_offset: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct Point {
+    #[serde(default)]
+    payload: Option<Payload>,
+}
+
+#[derive(Deserialize)]
+struct ScoredPoint {
+    score: f32,
+    #[serde(default)]
+    payload: Option<Payload>,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    pathway: String,
+    vector: String,
+}
+
+impl QdrantIndex {
+    pub fn new(config: &QdrantIndexConfig, http: HttpClient) -> Result<Self> {
+        let url = config.url.as_deref().ok_or_else(|| {
+            A3SError::Config(
+                "storage.vector_index.qdrant.url is required for the qdrant index".to_string(),
+            )
+        })?;
+        url::Url::parse(url)
+            .map_err(|e| A3SError::Config(format!("Invalid Qdrant URL {}: {}", url, e)))?;
+
+        Ok(Self {
+            collection_url: format!(
+                "{}/collections/{}",
+                url.trim_end_matches('/'),
+                config.collection
+            ),
+            api_key: config.api_key.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+            http,
+            nodes: DashMap::new(),
+            ready: AtomicBool::new(false),
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.collection_url, path))
+            .timeout(self.timeout);
+        match &self.api_key {
+            Some(key) => request.header("api-key", key),
+            None => request,
+        }
+    }
+
+    /// Send a request, returning `None` if the collection does not exist
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<Option<T>> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| A3SError::Storage(format!("Qdrant request failed: {}", e)))?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(A3SError::Storage(format!(
+                "Qdrant error {}: {}",
+                status, body
+            )));
+        }
+        let body: Response<T> = response
+            .json()
+            .await
+            .map_err(|e| A3SError::Storage(format!("Invalid Qdrant response: {}", e)))?;
+        Ok(Some(body.result))
+    }
+
+    async fn send_required<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        self.send(request)
+            .await?
+            .ok_or_else(|| A3SError::Storage("Qdrant collection not found".to_string()))
+    }
+
+    /// Whether the collection exists, checking once it has been seen
+    async fn exists(&self) -> Result<bool> {
+        if self.ready.load(Ordering::Acquire) {
+            return Ok(true);
+        }
+        let found = self
+            .send::<Value>(self.request(Method::GET, ""))
+            .await?
+            .is_some();
+        self.ready.store(found, Ordering::Release);
+        Ok(found)
+    }
+
+    /// Create the collection for vectors of `dimension` if it is missing
+    async fn ensure_collection(&self, dimension: usize) -> Result<()> {
+        if self.exists().await? {
+            return Ok(());
+        }
+        let created = self
+            .send::<Value>(self.request(Method::PUT, "").json(&json!({
+                "vectors": { "size": dimension, "distance": "Cosine" },
+            })))
+            .await;
+        // Another writer may have created it first
+        if let Err(e) = created {
+            if !self.exists().await? {
+                return Err(e);
+            }
+        }
+        for field in ["pathway", "namespace", "vector"] {
+            self.send_required::<Value>(self.request(Method::PUT, "/index").json(&json!({
+                "field_name": field,
+                "field_schema": "keyword",
+            })))
+            .await?;
+        }
+        self.ready.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Make `vectors` the only ones indexed for each node
+    async fn replace(&self, entries: &[(&Pathway, NodeVectors)]) -> Result<()> {
+        let mut points = Vec::new();
+        let mut stale = Vec::new();
+        for (pathway, vectors) in entries {
+            let key = pathway.to_string();
+            if let Some(names) = self.nodes.get(&key) {
+                stale.extend(
+                    names
+                        .iter()
+                        .filter(|name| !vectors.iter().any(|(n, _)| n == *name))
+                        .map(|name| point_id(&key, *name)),
+                );
+            }
+            points.extend(
+                vectors
+                    .iter()
+                    .map(|(name, vector)| point(pathway, *name, vector)),
+            );
+        }
+
+        if let Some(dimension) = points.first().and_then(|p| p["vector"].as_array()) {
+            self.ensure_collection(dimension.len()).await?;
+        }
+        for page in points.chunks(PAGE_SIZE) {
+            self.send_required::<Value>(
+                self.request(Method::PUT, "/points?wait=true")
+                    .json(&json!({ "points": page })),
+            )
+            .await?;
+        }
+        self.delete(&stale).await?;
+
+        for (pathway, vectors) in entries {
+            let key = pathway.to_string();
+            if vectors.is_empty() {
+                self.nodes.remove(&key);
+            } else {
+                self.nodes
+                    .insert(key, vectors.iter().map(|(name, _)| *name).collect());
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<()> {
+        for page in ids.chunks(PAGE_SIZE) {
+            self.send::<Value>(
+                self.request(Method::POST, "/points/delete?wait=true")
+                    .json(&json!({ "points": page })),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Forget nodes, deleting their points
+    async fn forget(&self, keys: &[String]) -> Result<()> {
+        let mut ids = Vec::new();
+        for key in keys {
+            if let Some((_, names)) = self.nodes.remove(key) {
+                ids.extend(names.into_iter().map(|name| point_id(key, name)));
+            }
+        }
+        self.delete(&ids).await
+    }
+}
+
+#[async_trait]
+impl VectorIndexBackend for QdrantIndex {
+    /// Mirror which vectors each node has from the collection
+    async fn load(&self) -> Result<usize> {
+        if !self.exists().await? {
+            return Ok(0);
+        }
+        self.nodes.clear();
+        let mut offset = Value::Null;
+        loop {
+            let page: ScrollPage = self
+                .send_required(self.request(Method::POST, "/points/scroll").json(&json!({
+                    "limit": PAGE_SIZE,
+                    "offset": offset,
+                    "with_payload": ["pathway", "vector"],
+                    "with_vector": false,
+                })))
+                .await?;
+            for payload in page.points.into_iter().filter_map(|p| p.payload) {
+                if let Some(name) = VectorName::parse(&payload.vector) {
+                    self.nodes.entry(payload.pathway).or_default().push(name);
+                }
+            }
+            match page.next_page_offset {
+                Some(next) if !next.is_null() => offset = next,
+                _ => break,
+            }
+        }
+        tracing::debug!(nodes = self.nodes.len(), "Loaded Qdrant vector index");
+        Ok(self.nodes.len())
+    }
+
+    /// Writes wait for Qdrant to apply them, so nothing is pending
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn add(&self, pathway: &Pathway, vector: &[f32]) -> Result<()> {
+        let key = pathway.to_string();
+        let point = point(pathway, VectorName::Content, vector);
+        self.ensure_collection(vector.len()).await?;
+        self.send_required::<Value>(
+            self.request(Method::PUT, "/points?wait=true")
+                .json(&json!({ "points": [point] })),
+        )
+        .await?;
+        let mut names = self.nodes.entry(key).or_default();
+        if !names.contains(&VectorName::Content) {
+            names.push(VectorName::Content);
+        }
+        Ok(())
+    }
+
+    async fn index(&self, node: &Node) -> Result<()> {
+        self.replace(&[(&node.pathway, node_vectors(node))]).await
+    }
+
+    async fn index_batch(&self, nodes: &[Node]) -> Result<()> {
+        let entries: Vec<(&Pathway, NodeVectors)> = nodes
+            .iter()
+            .map(|node| (&node.pathway, node_vectors(node)))
+            .collect();
+        self.replace(&entries).await
+    }
+
+    async fn remove(&self, pathway: &Pathway) -> Result<()> {
+        self.forget(&[pathway.to_string()]).await
+    }
+
+    async fn remove_tree(&self, pathway: &Pathway) -> Result<()> {
+        let keys: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|key| Pathway::parse(key).is_ok_and(|p| pathway.is_prefix_of(&p)))
+            .collect();
+        self.forget(&keys).await
+    }
+
+    async fn search_vectors(
+        &self,
+        query: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        if names.is_empty() || limit == 0 || !self.exists().await? {
+            return Ok(Vec::new());
+        }
+        let request = self
+            .request(Method::POST, "/points/search")
+            .json(&search_request(query, names, namespace, limit, threshold));
+        let Some(points) = self.send::<Vec<ScoredPoint>>(request).await? else {
+            return Ok(Vec::new());
+        };
+
+        // A node scores by its best-matching vector
+        let mut best: HashMap<String, f32> = HashMap::new();
+        for point in points {
+            let Some(payload) = point.payload else {
+                continue;
+            };
+            let entry = best.entry(payload.pathway).or_insert(point.score);
+            *entry = entry.max(point.score);
+        }
+        let mut ranked: Vec<(String, f32)> = best.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(key, score)| Ok((Pathway::parse(&key)?, score)))
+            .collect()
+    }
+
+    fn contains(&self, pathway: &Pathway) -> bool {
+        self.nodes.contains_key(&pathway.to_string())
+    }
+
+    fn size(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+/// Id of the point holding one of a node's vectors
+fn point_id(key: &str, name: VectorName) -> String {
+    let digest = sha256_hex(format!("{}#{}", key, name.as_str()).as_bytes());
+    Uuid::parse_str(&digest[..32])
+        .expect("a hex digest is a valid simple UUID")
+        .to_string()
+}
+
+fn point(pathway: &Pathway, name: VectorName, vector: &[f32]) -> Value {
+    let key = pathway.to_string();
+    json!({
+        "id": point_id(&key, name),
+        "vector": vector,
+        "payload": {
+            "pathway": key,
+            "namespace": pathway.namespace().as_str(),
+            "vector": name.as_str(),
+        },
+    })
+}
+
+fn search_request(
+    query: &[f32],
+    names: &[VectorName],
+    namespace: Option<Namespace>,
+    limit: usize,
+    threshold: f32,
+) -> Value {
+    let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+    let mut must = vec![json!({ "key": "vector", "match": { "any": names } })];
+    if let Some(namespace) = namespace {
+        must.push(json!({ "key": "namespace", "match": { "value": namespace.as_str() } }));
+    }
+    json!({
+        "vector": query,
+        // A node can match once per vector name
+        "limit": limit * names.len(),
+        "score_threshold": threshold,
+        "filter": { "must": must },
+        "with_payload": ["pathway"],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_ids_and_requests() {
+        let pathway = Pathway::parse("a3s://knowledge/docs/auth.md").unwrap();
+        let key = pathway.to_string();
+        let id = point_id(&key, VectorName::Content);
+        assert_eq!(id, point_id(&key, VectorName::Content));
+        assert_ne!(id, point_id(&key, VectorName::Summary));
+        assert!(Uuid::parse_str(&id).is_ok());
+
+        let point = point(&pathway, VectorName::Title, &[0.5, 0.5]);
+        assert_eq!(point["payload"]["namespace"], "knowledge");
+        assert_eq!(point["payload"]["vector"], "title");
+
+        let request = search_request(
+            &[1.0, 0.0],
+            &[VectorName::Content, VectorName::Summary],
+            Some(Namespace::Memory),
+            5,
+            0.3,
+        );
+        assert_eq!(request["limit"], 10);
+        assert_eq!(
+            request["filter"]["must"][0]["match"]["any"],
+            json!(["content", "summary"])
+        );
+        assert_eq!(request["filter"]["must"][1]["match"]["value"], "memory");
+
+        let config = QdrantIndexConfig {
+            url: Some("http://qdrant.internal:6333/".to_string()),
+            ..QdrantIndexConfig::default()
+        };
+        let index = QdrantIndex::new(&config, crate::http::default_client()).unwrap();
+        assert_eq!(
+            index.collection_url,
+            "http://qdrant.internal:6333/collections/a3s_context"
+        );
+        assert!(QdrantIndex::new(&QdrantIndexConfig::default(), HttpClient::default()).is_err());
+    }
+}
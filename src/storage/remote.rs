@@ -0,0 +1,328 @@
+//! Remote storage backend speaking JSON over HTTP to a shared A3S server
+//!
+//! Every backend operation maps to one request under `{storage.url}/v1/storage`.
+//! Pathways travel as `a3s://` URIs in the `pathway` query parameter or
+//! request body. The server answers 404 for missing nodes, 409 for a
+//! non-recursive remove of a non-empty directory, and 403 for read-only
+//! pathways.
+
+use async_trait::async_trait;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::StorageConfig;
+use crate::core::{Namespace, Node, VectorName};
+use crate::digest::Digest;
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+use crate::pathway::Pathway;
+use crate::{NodeInfo, StorageStats};
+
+use super::{StorageBackend, TextMatch, TextSearchOptions};
+
+/// Storage backed by a remote A3S server, shared by many clients
+pub struct RemoteStorage {
+    base: String,
+    api_key: Option<String>,
+    timeout: Duration,
+    http: HttpClient,
+}
+
+impl RemoteStorage {
+    pub fn new(config: &StorageConfig, http: HttpClient) -> Result<Self> {
+        let url = config.url.as_deref().ok_or_else(|| {
+            A3SError::Config("storage.url is required for the remote backend".to_string())
+        })?;
+        url::Url::parse(url)
+            .map_err(|e| A3SError::Config(format!("Invalid storage.url {}: {}", url, e)))?;
+
+        Ok(Self {
+            base: format!("{}/v1/storage", url.trim_end_matches('/')),
+            api_key: config.api_key.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+            http,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.base, path))
+            .timeout(self.timeout);
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder, pathway: Option<&Pathway>) -> Result<Response> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| A3SError::Storage(format!("Remote storage request failed: {}", e)))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(status_error(status, &body, pathway))
+    }
+
+    async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        pathway: Option<&Pathway>,
+    ) -> Result<T> {
+        self.send(request, pathway)
+            .await?
+            .json()
+            .await
+            .map_err(|e| A3SError::Storage(format!("Invalid remote storage response: {}", e)))
+    }
+}
+
+/// Map an error status from the server to the matching local error
+fn status_error(status: StatusCode, body: &str, pathway: Option<&Pathway>) -> A3SError {
+    let target = pathway.map(|p| p.to_string()).unwrap_or_default();
+    match status {
+        StatusCode::NOT_FOUND if pathway.is_some() => A3SError::NodeNotFound(target),
+        StatusCode::CONFLICT if pathway.is_some() => A3SError::DirectoryNotEmpty(target),
+        StatusCode::FORBIDDEN if pathway.is_some() => A3SError::ReadOnly(target),
+        _ => A3SError::Storage(format!("Remote storage error {}: {}", status, body)),
+    }
+}
+
+#[derive(Serialize)]
+struct VectorSearchRequest<'a> {
+    vector: &'a [f32],
+    names: &'a [VectorName],
+    namespace: Option<Namespace>,
+    limit: usize,
+    threshold: f32,
+}
+
+#[derive(Serialize)]
+struct TextSearchRequest<'a> {
+    pattern: &'a str,
+    pathway: &'a Pathway,
+    #[serde(flatten)]
+    options: &'a TextSearchOptions,
+}
+
+#[derive(Serialize)]
+struct EmbeddingUpdate<'a> {
+    pathway: &'a Pathway,
+    embedding: Vec<f32>,
+}
+
+#[derive(Serialize)]
+struct DigestUpdate<'a> {
+    pathway: &'a Pathway,
+    digest: Digest,
+}
+
+#[derive(Deserialize)]
+struct ScoredPathway {
+    pathway: Pathway,
+    score: f32,
+}
+
+#[derive(Deserialize)]
+struct Exists {
+    exists: bool,
+}
+
+#[async_trait]
+impl StorageBackend for RemoteStorage {
+    async fn initialize(&self) -> Result<()> {
+        self.send(self.request(Method::GET, "/health"), None)
+            .await?;
+        Ok(())
+    }
+
+    async fn put(&self, node: &Node) -> Result<()> {
+        let request = self.request(Method::PUT, "/nodes").json(node);
+        self.send(request, Some(&node.pathway)).await?;
+        Ok(())
+    }
+
+    async fn get(&self, pathway: &Pathway) -> Result<Node> {
+        let request = self
+            .request(Method::GET, "/nodes")
+            .query(&[("pathway", pathway.to_string())]);
+        self.send_json(request, Some(pathway)).await
+    }
+
+    async fn exists(&self, pathway: &Pathway) -> Result<bool> {
+        let request = self
+            .request(Method::GET, "/exists")
+            .query(&[("pathway", pathway.to_string())]);
+        let exists: Exists = self.send_json(request, None).await?;
+        Ok(exists.exists)
+    }
+
+    async fn remove(&self, pathway: &Pathway, recursive: bool) -> Result<()> {
+        let request = self.request(Method::DELETE, "/nodes").query(&[
+            ("pathway", pathway.to_string()),
+            ("recursive", recursive.to_string()),
+        ]);
+        self.send(request, Some(pathway)).await?;
+        Ok(())
+    }
+
+    async fn list(&self, pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+        let request = self
+            .request(Method::GET, "/list")
+            .query(&[("pathway", pathway.to_string())]);
+        self.send_json(request, Some(pathway)).await
+    }
+
+    async fn search_vector(
+        &self,
+        vector: &[f32],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.search_vectors(vector, &[VectorName::Content], namespace, limit, threshold)
+            .await
+    }
+
+    async fn search_vectors(
+        &self,
+        vector: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        let request = self
+            .request(Method::POST, "/search/vector")
+            .json(&VectorSearchRequest {
+                vector,
+                names,
+                namespace,
+                limit,
+                threshold,
+            });
+        let results: Vec<ScoredPathway> = self.send_json(request, None).await?;
+        Ok(results.into_iter().map(|r| (r.pathway, r.score)).collect())
+    }
+
+    async fn search_text(
+        &self,
+        pattern: &str,
+        pathway: &Pathway,
+        options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>> {
+        let request = self
+            .request(Method::POST, "/search/text")
+            .json(&TextSearchRequest {
+                pattern,
+                pathway,
+                options,
+            });
+        self.send_json(request, None).await
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        self.send_json(self.request(Method::GET, "/stats"), None)
+            .await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.send(self.request(Method::POST, "/flush"), None)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_children(&self, pathway: &Pathway, max_depth: usize) -> Result<Vec<Node>> {
+        let request = self.request(Method::GET, "/children").query(&[
+            ("pathway", pathway.to_string()),
+            ("max_depth", max_depth.to_string()),
+        ]);
+        self.send_json(request, Some(pathway)).await
+    }
+
+    async fn update_embedding(&self, pathway: &Pathway, embedding: Vec<f32>) -> Result<()> {
+        let request = self
+            .request(Method::PUT, "/embedding")
+            .json(&EmbeddingUpdate { pathway, embedding });
+        self.send(request, Some(pathway)).await?;
+        Ok(())
+    }
+
+    async fn update_digest(&self, pathway: &Pathway, digest: Digest) -> Result<()> {
+        let request = self
+            .request(Method::PUT, "/digest")
+            .json(&DigestUpdate { pathway, digest });
+        self.send(request, Some(pathway)).await?;
+        Ok(())
+    }
+
+    async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
+        if nodes.is_empty() {
+            return Ok(());
+        }
+        let request = self.request(Method::POST, "/nodes/batch").json(nodes);
+        self.send(request, None).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(url: &str) -> StorageConfig {
+        StorageConfig {
+            backend: crate::config::StorageBackend::Remote,
+            url: Some(url.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_remote_storage_requires_valid_url() {
+        let http = crate::http::default_client();
+        let storage =
+            RemoteStorage::new(&config("http://ctx.internal:8080/"), http.clone()).unwrap();
+        assert_eq!(storage.base, "http://ctx.internal:8080/v1/storage");
+
+        assert!(matches!(
+            RemoteStorage::new(&StorageConfig::default(), http.clone()),
+            Err(A3SError::Config(_))
+        ));
+        assert!(matches!(
+            RemoteStorage::new(&config("not a url"), http),
+            Err(A3SError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_status_error_mapping() {
+        let pathway = Pathway::parse("a3s://knowledge/docs").unwrap();
+        assert!(matches!(
+            status_error(StatusCode::NOT_FOUND, "", Some(&pathway)),
+            A3SError::NodeNotFound(p) if p == "a3s://knowledge/docs"
+        ));
+        assert!(matches!(
+            status_error(StatusCode::CONFLICT, "", Some(&pathway)),
+            A3SError::DirectoryNotEmpty(_)
+        ));
+        assert!(matches!(
+            status_error(StatusCode::FORBIDDEN, "", Some(&pathway)),
+            A3SError::ReadOnly(_)
+        ));
+        assert!(matches!(
+            status_error(StatusCode::NOT_FOUND, "no route", None),
+            A3SError::Storage(_)
+        ));
+        assert!(matches!(
+            status_error(StatusCode::INTERNAL_SERVER_ERROR, "boom", Some(&pathway)),
+            A3SError::Storage(m) if m.contains("boom")
+        ));
+    }
+}
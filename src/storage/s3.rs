@@ -0,0 +1,392 @@
+//! Minimal S3 client for backups: put, get, list, and delete objects
+//!
+//! Requests are signed with AWS Signature Version 4 using credentials from
+//! `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, and the optional
+//! `AWS_SESSION_TOKEN`. The region comes from `AWS_REGION` or
+//! `AWS_DEFAULT_REGION`, and `AWS_ENDPOINT_URL` points at S3-compatible
+//! services such as MinIO. Buckets are addressed path-style.
+
+use chrono::{DateTime, Utc};
+use reqwest::{Method, StatusCode};
+
+use crate::error::{A3SError, Result};
+use crate::http::HttpClient;
+use crate::provenance::{hmac_sha256, sha256_hex, to_hex};
+
+/// A bucket and key prefix, from an `s3://bucket/prefix` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    /// Key prefix without a trailing slash; may be empty
+    pub prefix: String,
+}
+
+impl S3Location {
+    pub fn parse(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix("s3://")
+            .ok_or_else(|| A3SError::Config(format!("Not an s3:// URL: {}", url)))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(A3SError::Config(format!("Missing bucket in {}", url)));
+        }
+        Ok(Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+
+    /// Key of an object named `name` under the prefix
+    pub fn key(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+}
+
+/// Signing credentials and endpoint
+#[derive(Debug, Clone)]
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    region: String,
+    endpoint: String,
+}
+
+impl Credentials {
+    fn from_env() -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let missing = |name: &str| A3SError::Config(format!("{} is required for S3 backups", name));
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        Ok(Self {
+            access_key: var("AWS_ACCESS_KEY_ID").ok_or_else(|| missing("AWS_ACCESS_KEY_ID"))?,
+            secret_key: var("AWS_SECRET_ACCESS_KEY")
+                .ok_or_else(|| missing("AWS_SECRET_ACCESS_KEY"))?,
+            session_token: var("AWS_SESSION_TOKEN"),
+            endpoint: var("AWS_ENDPOINT_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region)),
+            region,
+        })
+    }
+}
+
+/// Client for one bucket location
+pub struct S3Client {
+    location: S3Location,
+    credentials: Credentials,
+    http: HttpClient,
+}
+
+impl S3Client {
+    /// Client for `location` with credentials from the environment
+    pub fn from_env(location: S3Location, http: HttpClient) -> Result<Self> {
+        Ok(Self {
+            location,
+            credentials: Credentials::from_env()?,
+            http,
+        })
+    }
+
+    pub fn location(&self) -> &S3Location {
+        &self.location
+    }
+
+    pub async fn put(&self, name: &str, body: Vec<u8>) -> Result<()> {
+        self.send(Method::PUT, &self.location.key(name), &[], body)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Vec<u8>> {
+        self.send(Method::GET, &self.location.key(name), &[], Vec::new())
+            .await
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<()> {
+        self.send(Method::DELETE, &self.location.key(name), &[], Vec::new())
+            .await?;
+        Ok(())
+    }
+
+    /// Names of the objects directly under the prefix
+    pub async fn list(&self) -> Result<Vec<String>> {
+        let prefix = self.location.key("");
+        let mut names = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.clone()),
+            ];
+            if let Some(token) = &token {
+                query.push(("continuation-token".to_string(), token.clone()));
+            }
+            let body = self.send(Method::GET, "", &query, Vec::new()).await?;
+            let body = String::from_utf8_lossy(&body);
+            names.extend(
+                xml_values(&body, "Key")
+                    .into_iter()
+                    .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+                    .filter(|name| !name.is_empty() && !name.contains('/')),
+            );
+            token = xml_values(&body, "NextContinuationToken")
+                .into_iter()
+                .next();
+            if token.is_none() {
+                return Ok(names);
+            }
+        }
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(String, String)],
+        body: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let path = if key.is_empty() {
+            format!("/{}", self.location.bucket)
+        } else {
+            format!("/{}/{}", self.location.bucket, uri_encode(key, false))
+        };
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let url = url::Url::parse(&self.credentials.endpoint)
+            .map_err(|e| A3SError::Config(format!("Invalid S3 endpoint: {}", e)))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let signed = sign(
+            &self.credentials,
+            method.as_str(),
+            &host,
+            &path,
+            &query,
+            &sha256_hex(&body),
+            Utc::now(),
+        );
+
+        let mut target = format!("{}{}", self.credentials.endpoint, path);
+        if !query.is_empty() {
+            target = format!("{}?{}", target, query);
+        }
+        let mut request = self.http.request(method, target).body(body);
+        for (name, value) in signed {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| A3SError::Backup(format!("S3 request failed: {}", e)))?;
+        let status = response.status();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| A3SError::Backup(format!("S3 response failed: {}", e)))?;
+        match status {
+            s if s.is_success() => Ok(bytes.to_vec()),
+            StatusCode::NOT_FOUND => Err(A3SError::Backup(format!(
+                "s3://{}/{} not found",
+                self.location.bucket, key
+            ))),
+            s => Err(A3SError::Backup(format!(
+                "S3 returned {}: {}",
+                s,
+                String::from_utf8_lossy(&bytes)
+            ))),
+        }
+    }
+}
+
+/// Headers signing a request with AWS Signature Version 4
+fn sign(
+    credentials: &Credentials,
+    method: &str,
+    host: &str,
+    path: &str,
+    query: &str,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let mut headers = vec![
+        ("host".to_string(), host.to_string()),
+        ("x-amz-content-sha256".to_string(), payload_hash.to_string()),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, path, query, canonical_headers, signed_headers, payload_hash
+    );
+
+    let scope = format!("{}/{}/s3/aws4_request", date, credentials.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+    let key = signing_key(&credentials.secret_key, &date, &credentials.region, "s3");
+    let signature = to_hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    headers.retain(|(name, _)| name != "host");
+    headers.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key, scope, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+/// Percent-encode all but unreserved characters, keeping `/` in paths
+fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// Text of every `<tag>` element in an XML document
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        values.push(
+            rest[..end]
+                .replace("&amp;", "&")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_location() {
+        let location = S3Location::parse("s3://backups/a3s/prod/").unwrap();
+        assert_eq!(location.bucket, "backups");
+        assert_eq!(location.key("b.gz"), "a3s/prod/b.gz");
+        assert_eq!(
+            S3Location::parse("s3://backups").unwrap().key("b.gz"),
+            "b.gz"
+        );
+        assert!(S3Location::parse("s3://").is_err());
+        assert!(S3Location::parse("/var/backups").is_err());
+    }
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            to_hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_sign_request() {
+        let credentials = Credentials {
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+            region: "us-east-1".to_string(),
+            endpoint: "https://s3.us-east-1.amazonaws.com".to_string(),
+        };
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let headers = sign(
+            &credentials,
+            "GET",
+            "s3.us-east-1.amazonaws.com",
+            "/backups/a3s/b.gz",
+            "",
+            &sha256_hex(b""),
+            now,
+        );
+        let auth = &headers
+            .iter()
+            .find(|(n, _)| n == "authorization")
+            .unwrap()
+            .1;
+        assert_eq!(
+            auth,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20261016/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+             Signature=2ad28c2460438a28958577436b5d154e3893132633407c4d2ff4cea67ce3dd82"
+        );
+    }
+
+    #[test]
+    fn test_xml_values() {
+        let xml = "<ListBucketResult><Contents><Key>a3s/a&amp;b.gz</Key></Contents>\
+                   <Contents><Key>a3s/c.gz</Key></Contents></ListBucketResult>";
+        assert_eq!(xml_values(xml, "Key"), vec!["a3s/a&b.gz", "a3s/c.gz"]);
+        assert_eq!(uri_encode("a3s/b c.gz", false), "a3s/b%20c.gz");
+        assert_eq!(uri_encode("a3s/", true), "a3s%2F");
+    }
+}
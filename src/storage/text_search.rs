@@ -0,0 +1,214 @@
+//! Line-oriented text search shared by the storage backends
+//!
+//! Patterns are literals or regular expressions. Literals are compared in
+//! the Unicode search form of [`crate::text`], so accents and full-width
+//! letters match however they are encoded; regexes run on the stored text and
+//! are compiled with size and nesting limits so a hostile pattern cannot
+//! exhaust memory. Each matching line reports where every match starts and
+//! ends, so callers can locate exact spans without re-running the pattern.
+
+use std::ops::Deref;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::core::Node;
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::text;
+
+/// Compiled regexes larger than this are rejected
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Memory each search may use for its lazily built DFA
+const DFA_SIZE_LIMIT: usize = 1 << 20;
+
+/// Deepest nesting of groups and repetitions a pattern may use
+const REGEX_NEST_LIMIT: u32 = 64;
+
+/// How [`super::StorageBackend::search_text`] matches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextSearchOptions {
+    /// Treat the pattern as a regular expression instead of a literal
+    #[serde(default)]
+    pub regex: bool,
+    /// Match case-insensitively
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Maximum number of matching nodes to return, first by pathway
+    #[serde(default)]
+    pub max_results: Option<usize>,
+}
+
+/// A node with matching lines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextMatch {
+    pub pathway: Pathway,
+    pub lines: Vec<TextLine>,
+}
+
+/// A single matching line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextLine {
+    /// 1-based line number
+    pub line_number: usize,
+    /// Byte offset of the line in the node's content
+    pub offset: usize,
+    pub text: String,
+    /// Each match in the line, as byte offsets into `text`
+    pub spans: Vec<MatchSpan>,
+}
+
+/// Where a match starts and ends within a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A compiled search pattern
+pub enum TextMatcher {
+    Literal {
+        /// Pattern in its search form
+        needle: String,
+        pattern: String,
+        case_insensitive: bool,
+    },
+    Regex(Regex),
+}
+
+impl TextMatcher {
+    pub fn new(pattern: &str, options: &TextSearchOptions) -> Result<Self> {
+        if !options.regex {
+            return Ok(Self::Literal {
+                needle: text::search_form(pattern, options.case_insensitive),
+                pattern: pattern.to_string(),
+                case_insensitive: options.case_insensitive,
+            });
+        }
+
+        RegexBuilder::new(pattern)
+            .case_insensitive(options.case_insensitive)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .dfa_size_limit(DFA_SIZE_LIMIT)
+            .nest_limit(REGEX_NEST_LIMIT)
+            .build()
+            .map(Self::Regex)
+            .map_err(|e| A3SError::InvalidPattern(e.to_string()))
+    }
+
+    /// Lines of `content` with at least one match
+    pub fn matching_lines(&self, content: &str) -> Vec<TextLine> {
+        // Most content has no match; skip splitting it into lines
+        if let Self::Literal {
+            needle,
+            case_insensitive,
+            ..
+        } = self
+        {
+            if !text::search_form(content, *case_insensitive).contains(needle.as_str()) {
+                return Vec::new();
+            }
+        }
+
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        for (i, raw) in content.split_inclusive('\n').enumerate() {
+            let line = raw.strip_suffix('\n').unwrap_or(raw);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let spans = self.spans(line);
+            if !spans.is_empty() {
+                lines.push(TextLine {
+                    line_number: i + 1,
+                    offset,
+                    text: line.to_string(),
+                    spans,
+                });
+            }
+            offset += raw.len();
+        }
+        lines
+    }
+
+    fn spans(&self, line: &str) -> Vec<MatchSpan> {
+        match self {
+            Self::Literal {
+                pattern,
+                case_insensitive,
+                ..
+            } => text::find_all(line, pattern, *case_insensitive)
+                .into_iter()
+                .map(|range| MatchSpan {
+                    start: range.start,
+                    end: range.end,
+                })
+                .collect(),
+            Self::Regex(regex) => regex
+                .find_iter(line)
+                .map(|m| MatchSpan {
+                    start: m.start(),
+                    end: m.end(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Search the non-directory nodes at or below `pathway`, for backends that
+/// hold their nodes in memory
+pub fn search_nodes<N: Deref<Target = Node>>(
+    nodes: impl IntoIterator<Item = N>,
+    pattern: &str,
+    pathway: &Pathway,
+    options: &TextSearchOptions,
+) -> Result<Vec<TextMatch>> {
+    let matcher = TextMatcher::new(pattern, options)?;
+    let mut matches: Vec<TextMatch> = nodes
+        .into_iter()
+        .filter(|node| !node.is_directory && pathway.is_prefix_of(&node.pathway))
+        .filter_map(|node| {
+            let lines = matcher.matching_lines(&node.content);
+            (!lines.is_empty()).then(|| TextMatch {
+                pathway: node.pathway.clone(),
+                lines,
+            })
+        })
+        .collect();
+    sort_and_limit(&mut matches, options);
+    Ok(matches)
+}
+
+/// Order matches by pathway and keep the first `max_results`
+pub fn sort_and_limit(matches: &mut Vec<TextMatch>, options: &TextSearchOptions) {
+    matches.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+    if let Some(max) = options.max_results {
+        matches.truncate(max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_lines_spans() {
+        let options = TextSearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let matcher = TextMatcher::new(r"fn \w+", &options).unwrap();
+        let lines = matcher.matching_lines("use x;\r\nfn a() {} fn bc() {}\r\n");
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line_number, 2);
+        assert_eq!(lines[0].offset, 8);
+        assert_eq!(lines[0].text, "fn a() {} fn bc() {}");
+        assert_eq!(
+            lines[0].spans,
+            vec![
+                MatchSpan { start: 0, end: 4 },
+                MatchSpan { start: 10, end: 15 }
+            ]
+        );
+    }
+}
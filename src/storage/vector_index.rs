@@ -1,97 +1,370 @@
-//! Simple vector index implementation
-
+//! Vector index over each node's named vectors
+//!
+//! Storage backends keep their vectors in a [`VectorIndexBackend`]: the
+//! in-process [`VectorIndex`] by default, a flat index on disk for
+//! embedding sets larger than memory, or a dedicated search engine, in
+//! which case node content stays in the backend and only vectors are sent
+//! to the engine. `storage.vector_index.index_type` chooses between them.
+
+use async_trait::async_trait;
 use dashmap::DashMap;
 use ordered_float::OrderedFloat;
-use std::collections::BinaryHeap;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Arc;
 
+use super::hnsw::Hnsw;
+use super::persist::{IndexFiles, NodeVectors, Record};
 use crate::config::VectorIndexConfig;
-use crate::core::Namespace;
+use crate::core::{Namespace, Node, VectorName};
 use crate::error::Result;
+use crate::http::HttpClient;
 use crate::pathway::Pathway;
 
-/// Simple in-memory vector index
+/// Logged changes tolerated before the snapshot is rewritten, unless the
+/// index itself is larger
+const COMPACT_AFTER_RECORDS: usize = 10_000;
+
+type Graphs = BTreeMap<VectorName, Hnsw>;
+
+/// Index type that keeps vectors in a Qdrant collection
+pub const QDRANT_INDEX: &str = "qdrant";
+
+/// Where a storage backend indexes node vectors for similarity search
+#[async_trait]
+pub trait VectorIndexBackend: Send + Sync {
+    /// Restore earlier contents, returning how many nodes were loaded
+    async fn load(&self) -> Result<usize>;
+
+    /// Make pending changes durable
+    async fn flush(&self) -> Result<()>;
+
+    /// Set a node's content vector
+    async fn add(&self, pathway: &Pathway, vector: &[f32]) -> Result<()>;
+
+    /// Replace every vector indexed for a node with the ones it carries
+    async fn index(&self, node: &Node) -> Result<()>;
+
+    /// Index many nodes in one pass
+    async fn index_batch(&self, nodes: &[Node]) -> Result<()>;
+
+    async fn remove(&self, pathway: &Pathway) -> Result<()>;
+
+    /// Remove a pathway and every node below it
+    async fn remove_tree(&self, pathway: &Pathway) -> Result<()>;
+
+    /// Search content vectors
+    async fn search(
+        &self,
+        query: &[f32],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.search_vectors(query, &[VectorName::Content], namespace, limit, threshold)
+            .await
+    }
+
+    /// Search the named vectors, scoring each node by its best match
+    async fn search_vectors(
+        &self,
+        query: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>>;
+
+    /// Whether any vector is indexed for a node
+    fn contains(&self, pathway: &Pathway) -> bool;
+
+    /// Nodes with at least one vector indexed
+    fn size(&self) -> usize;
+}
+
+/// Every vector a node carries, content first
+pub(super) fn node_vectors(node: &Node) -> NodeVectors {
+    std::iter::once(VectorName::Content)
+        .chain(node.vectors.keys().copied())
+        .filter_map(|name| Some((name, node.vector(name)?.to_vec())))
+        .collect()
+}
+
+/// The index `config` selects in place of the in-process graphs, if any
+///
+/// `dir` holds the files of an on-disk index.
+pub fn open_index(
+    config: &VectorIndexConfig,
+    dir: &Path,
+    http: &HttpClient,
+) -> Result<Option<Arc<dyn VectorIndexBackend>>> {
+    match config.index_type.as_str() {
+        super::disk_index::DISK_INDEX => {
+            Ok(Some(Arc::new(super::disk_index::DiskIndex::open(dir)?)))
+        }
+        #[cfg(feature = "http")]
+        QDRANT_INDEX => Ok(Some(Arc::new(super::qdrant_index::QdrantIndex::new(
+            &config.qdrant,
+            http.clone(),
+        )?))),
+        #[cfg(not(feature = "http"))]
+        QDRANT_INDEX => {
+            let _ = http;
+            Err(crate::error::A3SError::Config(
+                "The qdrant vector index requires the `http` feature".to_string(),
+            ))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// In-memory vector index keeping one HNSW graph per vector name
 pub struct VectorIndex {
-    vectors: Arc<DashMap<String, Vec<f32>>>,
-    #[allow(dead_code)]
+    /// Names of the vectors indexed for each node
+    nodes: DashMap<String, Vec<VectorName>>,
+    graphs: RwLock<Graphs>,
     config: VectorIndexConfig,
+    /// Snapshot and change log, for indexes that survive restarts
+    files: Option<IndexFiles>,
 }
 
 impl VectorIndex {
     pub fn new(config: &VectorIndexConfig) -> Self {
         Self {
-            vectors: Arc::new(DashMap::new()),
+            nodes: DashMap::new(),
+            graphs: RwLock::new(BTreeMap::new()),
             config: config.clone(),
+            files: None,
         }
     }
 
-    pub async fn add(&self, pathway: &Pathway, vector: &[f32]) -> Result<()> {
-        self.vectors.insert(pathway.to_string(), vector.to_vec());
-        Ok(())
+    /// Index persisted to `vectors.bin` and `vectors.log` in `dir`
+    ///
+    /// Call [`VectorIndex::load`] before use to restore earlier contents.
+    pub fn persistent(config: &VectorIndexConfig, dir: &Path) -> Self {
+        Self {
+            files: Some(IndexFiles::new(dir)),
+            ..Self::new(config)
+        }
     }
 
-    pub async fn remove(&self, pathway: &Pathway) -> Result<()> {
-        self.vectors.remove(&pathway.to_string());
-        Ok(())
+    fn index_locked(&self, graphs: &mut Graphs, node: &Node) -> Result<()> {
+        let key = node.pathway.to_string();
+        let vectors = node_vectors(node);
+
+        self.replace(graphs, &key, &vectors);
+        if vectors.is_empty() {
+            self.log(graphs, Record::Remove { key: &key })
+        } else {
+            self.log(
+                graphs,
+                Record::Replace {
+                    key: &key,
+                    vectors: &vectors,
+                },
+            )
+        }
     }
 
-    pub async fn search(
-        &self,
-        query: &[f32],
-        namespace: Option<Namespace>,
-        limit: usize,
-        threshold: f32,
-    ) -> Result<Vec<(Pathway, f32)>> {
-        let mut heap = BinaryHeap::new();
-
-        for entry in self.vectors.iter() {
-            let pathway = Pathway::parse(entry.key())?;
+    /// Vectors currently indexed for a node
+    fn stored(&self, graphs: &Graphs, key: &str) -> NodeVectors {
+        let Some(names) = self.nodes.get(key) else {
+            return Vec::new();
+        };
+        names
+            .iter()
+            .filter_map(|name| Some((*name, graphs.get(name)?.get(key)?.to_vec())))
+            .collect()
+    }
 
-            // Filter by namespace if specified
-            if let Some(ns) = namespace {
-                if pathway.namespace() != ns {
-                    continue;
+    /// Make `vectors` the only ones indexed for a node
+    fn replace(&self, graphs: &mut Graphs, key: &str, vectors: &[(VectorName, Vec<f32>)]) {
+        if let Some((_, stale)) = self.nodes.remove(key) {
+            for name in stale {
+                if let Some(graph) = graphs.get_mut(&name) {
+                    graph.remove(key);
                 }
             }
+        }
+        if vectors.is_empty() {
+            return;
+        }
+        for (name, vector) in vectors {
+            graphs
+                .entry(*name)
+                .or_insert_with(|| Hnsw::new(&self.config))
+                .insert(key, vector);
+        }
+        self.nodes.insert(
+            key.to_string(),
+            vectors.iter().map(|(name, _)| *name).collect(),
+        );
+    }
 
-            let score = cosine_similarity(query, entry.value());
+    /// Append a change to the log of a persistent index
+    ///
+    /// Callers hold the graph write lock, so log order matches index order.
+    fn log(&self, graphs: &Graphs, record: Record) -> Result<()> {
+        let Some(files) = &self.files else {
+            return Ok(());
+        };
+        if files.append(&record)? > COMPACT_AFTER_RECORDS.max(self.nodes.len()) {
+            self.compact(graphs, files)?;
+        }
+        Ok(())
+    }
 
-            if score >= threshold {
-                heap.push((OrderedFloat(score), pathway));
+    fn compact(&self, graphs: &Graphs, files: &IndexFiles) -> Result<()> {
+        let stored: Vec<(String, NodeVectors)> = self
+            .nodes
+            .iter()
+            .map(|entry| (entry.key().clone(), self.stored(graphs, entry.key())))
+            .collect();
+        files.compact(
+            stored
+                .iter()
+                .map(|(key, vectors)| (key.as_str(), vectors.as_slice())),
+        )
+    }
+}
+
+#[async_trait]
+impl VectorIndexBackend for VectorIndex {
+    /// Restore persisted vectors, returning how many nodes were loaded
+    ///
+    /// Graphs are rebuilt from the stored vectors, then the change log is
+    /// folded into a fresh snapshot.
+    async fn load(&self) -> Result<usize> {
+        let Some(files) = &self.files else {
+            return Ok(0);
+        };
+        let stored = files.load()?;
+        let loaded = stored.len();
+        {
+            let mut graphs = self.graphs.write();
+            for (key, vectors) in &stored {
+                self.replace(&mut graphs, key, vectors);
             }
         }
+        if files.pending() > 0 {
+            self.compact(&self.graphs.read(), files)?;
+        }
+        tracing::debug!(nodes = loaded, "Loaded vector index");
+        Ok(loaded)
+    }
 
-        let mut results = Vec::new();
-        for _ in 0..limit {
-            if let Some((score, pathway)) = heap.pop() {
-                results.push((pathway, score.0));
-            } else {
-                break;
-            }
+    /// Rewrite the snapshot if changes are waiting in the log
+    async fn flush(&self) -> Result<()> {
+        match &self.files {
+            Some(files) if files.pending() > 0 => self.compact(&self.graphs.read(), files),
+            _ => Ok(()),
         }
+    }
 
-        Ok(results)
+    /// Set a node's content vector
+    async fn add(&self, pathway: &Pathway, vector: &[f32]) -> Result<()> {
+        let key = pathway.to_string();
+        let mut graphs = self.graphs.write();
+        let mut vectors = self.stored(&graphs, &key);
+        vectors.retain(|(name, _)| *name != VectorName::Content);
+        vectors.push((VectorName::Content, vector.to_vec()));
+        self.replace(&mut graphs, &key, &vectors);
+        self.log(
+            &graphs,
+            Record::Replace {
+                key: &key,
+                vectors: &vectors,
+            },
+        )
     }
 
-    pub fn size(&self) -> usize {
-        self.vectors.len()
+    /// Replace every vector indexed for a node with the ones it carries
+    async fn index(&self, node: &Node) -> Result<()> {
+        let mut graphs = self.graphs.write();
+        self.index_locked(&mut graphs, node)
     }
-}
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-    if a.len() != b.len() || a.is_empty() {
-        return 0.0;
+    /// Index many nodes in one pass under a single write lock
+    async fn index_batch(&self, nodes: &[Node]) -> Result<()> {
+        let mut graphs = self.graphs.write();
+        for node in nodes {
+            self.index_locked(&mut graphs, node)?;
+        }
+        Ok(())
     }
 
-    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    async fn remove(&self, pathway: &Pathway) -> Result<()> {
+        let key = pathway.to_string();
+        let mut graphs = self.graphs.write();
+        if self.nodes.contains_key(&key) {
+            self.replace(&mut graphs, &key, &[]);
+            self.log(&graphs, Record::Remove { key: &key })?;
+        }
+        Ok(())
+    }
 
-    if norm_a == 0.0 || norm_b == 0.0 {
-        return 0.0;
+    /// Remove a pathway and every node below it
+    async fn remove_tree(&self, pathway: &Pathway) -> Result<()> {
+        let keys: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|key| Pathway::parse(key).is_ok_and(|p| pathway.is_prefix_of(&p)))
+            .collect();
+        let mut graphs = self.graphs.write();
+        for key in keys {
+            self.replace(&mut graphs, &key, &[]);
+            self.log(&graphs, Record::Remove { key: &key })?;
+        }
+        Ok(())
     }
 
-    dot / (norm_a * norm_b)
+    /// Search the named vectors, scoring each node by its best match
+    async fn search_vectors(
+        &self,
+        query: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        let in_namespace = |key: &str| match namespace {
+            Some(ns) => Pathway::parse(key).is_ok_and(|p| p.namespace() == ns),
+            None => true,
+        };
+
+        let mut best: HashMap<String, f32> = HashMap::new();
+        {
+            let graphs = self.graphs.read();
+            for graph in names.iter().filter_map(|name| graphs.get(name)) {
+                for (key, score) in graph.search(query, limit, in_namespace) {
+                    if score < threshold {
+                        continue;
+                    }
+                    let entry = best.entry(key.to_string()).or_insert(score);
+                    *entry = entry.max(score);
+                }
+            }
+        }
+
+        let mut ranked: Vec<(String, f32)> = best.into_iter().collect();
+        ranked.sort_by_key(|(key, score)| (std::cmp::Reverse(OrderedFloat(*score)), key.clone()));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(key, score)| Ok((Pathway::parse(&key)?, score)))
+            .collect()
+    }
+
+    /// Whether any vector is indexed for a node
+    fn contains(&self, pathway: &Pathway) -> bool {
+        self.nodes.contains_key(&pathway.to_string())
+    }
+
+    fn size(&self) -> usize {
+        self.nodes.len()
+    }
 }
 
 #[cfg(test)]
@@ -104,6 +377,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let index = VectorIndex::new(&config);
 
@@ -131,6 +406,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let index = VectorIndex::new(&config);
 
@@ -150,6 +427,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let index = VectorIndex::new(&config);
 
@@ -178,6 +457,8 @@ mod tests {
             index_type: "hnsw".to_string(),
             hnsw_m: 16,
             hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
         };
         let index = VectorIndex::new(&config);
 
@@ -192,13 +473,100 @@ mod tests {
         assert!(results.is_empty());
     }
 
-    #[test]
-    fn test_cosine_similarity() {
-        let a = vec![1.0, 0.0, 0.0];
-        let b = vec![1.0, 0.0, 0.0];
-        assert!((cosine_similarity(&a, &b) - 1.0).abs() < 0.001);
+    #[tokio::test]
+    async fn test_vector_index_max_score_fusion() {
+        let config = VectorIndexConfig {
+            index_type: "hnsw".to_string(),
+            hnsw_m: 16,
+            hnsw_ef_construction: 200,
+            hnsw_ef_search: 64,
+            qdrant: Default::default(),
+        };
+        let index = VectorIndex::new(&config);
+
+        let pathway = Pathway::parse("a3s://knowledge/doc1").unwrap();
+        let mut node = Node::new(
+            pathway.clone(),
+            crate::core::NodeKind::Document,
+            String::new(),
+        );
+        node.embedding = vec![1.0, 0.0, 0.0];
+        node.vectors.insert(VectorName::Title, vec![0.0, 1.0, 0.0]);
+        index.index(&node).await.unwrap();
+
+        let query = vec![0.0, 1.0, 0.0];
+        assert!(index
+            .search(&query, None, 10, 0.5)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let names = [VectorName::Content, VectorName::Title];
+        let results = index
+            .search_vectors(&query, &names, None, 10, 0.5)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!((results[0].1 - 1.0).abs() < 0.001);
+
+        node.embedding.clear();
+        node.vectors.clear();
+        index.index(&node).await.unwrap();
+        assert_eq!(index.size(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_vector_index_index_batch() {
+        let index = VectorIndex::new(&VectorIndexConfig::default());
+        let nodes: Vec<Node> = (0..3)
+            .map(|i| {
+                let mut vector = vec![0.0; 3];
+                vector[i] = 1.0;
+                let pathway = Pathway::parse(&format!("a3s://memory/alice/{}", i)).unwrap();
+                let mut node = Node::new(pathway, crate::core::NodeKind::Memory, String::new());
+                node.embedding = vector;
+                node
+            })
+            .collect();
+
+        index.index_batch(&nodes).await.unwrap();
+        assert_eq!(index.size(), 3);
+        let results = index.search(&[0.0, 0.0, 1.0], None, 1, 0.5).await.unwrap();
+        assert_eq!(results[0].0, nodes[2].pathway);
+    }
 
-        let c = vec![0.0, 1.0, 0.0];
-        assert!((cosine_similarity(&a, &c) - 0.0).abs() < 0.001);
+    #[tokio::test]
+    async fn test_vector_index_persists_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = VectorIndexConfig::default();
+        let docs = Pathway::parse("a3s://knowledge/docs").unwrap();
+        let p1 = Pathway::parse("a3s://knowledge/docs/doc1").unwrap();
+        let p2 = Pathway::parse("a3s://knowledge/docs/doc2").unwrap();
+        let p3 = Pathway::parse("a3s://memory/mem1").unwrap();
+
+        let index = VectorIndex::persistent(&config, dir.path());
+        index.add(&p1, &[1.0, 0.0, 0.0]).await.unwrap();
+        index.flush().await.unwrap();
+        // Logged after the snapshot
+        index.add(&p2, &[0.0, 1.0, 0.0]).await.unwrap();
+        index.add(&p3, &[0.0, 0.0, 1.0]).await.unwrap();
+        drop(index);
+
+        let index = VectorIndex::persistent(&config, dir.path());
+        assert_eq!(index.load().await.unwrap(), 3);
+        let results = index.search(&[0.0, 1.0, 0.0], None, 1, 0.5).await.unwrap();
+        assert_eq!(results[0].0, p2);
+
+        index.remove_tree(&docs).await.unwrap();
+        assert_eq!(index.size(), 1);
+        drop(index);
+
+        let index = VectorIndex::persistent(&config, dir.path());
+        assert_eq!(index.load().await.unwrap(), 1);
+        assert!(index
+            .search(&[1.0, 0.0, 0.0], None, 10, 0.5)
+            .await
+            .unwrap()
+            .is_empty());
     }
 }
@@ -0,0 +1,491 @@
+//! Eventually consistent writes over another backend
+//!
+//! With `storage.consistency: eventual`, [`WriteBehindStorage`] accepts a
+//! put by queueing the node and returns; one background task hands queued
+//! nodes to the wrapped backend in batches, which persists and indexes
+//! them. Until then `get` and `exists` answer from the queue, so a writer
+//! reads its own writes, while searches and listings see a node once it is
+//! applied. [`StorageBackend::settle`] waits for the queue to drain, and
+//! every other change (removals, embedding and digest updates) settles
+//! first so it cannot overtake a queued put.
+//!
+//! A batch the wrapped backend rejects stays queued, so reads still find
+//! its nodes, and further puts fail until a settle stores it on retry.
+//! Writes still queued when the runtime stops are lost; the background task
+//! keeps applying them after the storage is dropped, but only
+//! `settle`, `flush`, or [`crate::A3SClient::shutdown`] wait for it.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+
+use super::{StorageBackend, TextMatch, TextSearchOptions};
+use crate::bulk::{MetadataOp, NodeFilter};
+use crate::core::{Namespace, Node, VectorName};
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::{NodeInfo, StorageStats};
+
+/// A queued node and its write sequence number
+type Write = (u64, Node);
+
+/// Writes accepted but not yet applied
+struct Queue {
+    /// Newest queued write of each node, by pathway
+    pending: DashMap<String, Write>,
+    /// Sequence number of the last write accepted
+    accepted: AtomicU64,
+    /// Sequence number of the last write applied
+    applied: watch::Sender<u64>,
+    /// Writes the wrapped backend rejected, kept until a retry stores them
+    failed: Mutex<Option<Failure>>,
+}
+
+/// Background writes that failed, and the first error
+struct Failure {
+    error: String,
+    writes: Vec<Write>,
+}
+
+impl Queue {
+    /// Whether `write` is still the newest queued write of its node
+    fn is_newest(&self, (seq, node): &Write) -> bool {
+        self.pending
+            .get(&node.pathway.to_string())
+            .is_some_and(|entry| entry.0 == *seq)
+    }
+
+    /// Drop applied writes from the queue, unless a newer write replaced them
+    fn remove_applied(&self, writes: &[Write]) {
+        for (seq, node) in writes {
+            self.pending
+                .remove_if(&node.pathway.to_string(), |_, (queued, _)| queued == seq);
+        }
+    }
+}
+
+/// Backend whose puts return before the wrapped backend applies them
+pub struct WriteBehindStorage {
+    inner: Arc<dyn StorageBackend>,
+    queue: Arc<Queue>,
+    /// Channel to the background task, started by the first write; the
+    /// lock also keeps sequence numbers in channel order
+    sender: Mutex<Option<mpsc::UnboundedSender<Vec<Write>>>>,
+}
+
+impl WriteBehindStorage {
+    pub fn new(inner: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            inner,
+            queue: Arc::new(Queue {
+                pending: DashMap::new(),
+                accepted: AtomicU64::new(0),
+                applied: watch::Sender::new(0),
+                failed: Mutex::new(None),
+            }),
+            sender: Mutex::new(None),
+        }
+    }
+
+    /// Writes accepted but not yet applied
+    pub fn pending(&self) -> usize {
+        self.queue.pending.len()
+    }
+
+    /// Store failed writes that no newer write replaced, accepting puts
+    /// again once they are stored
+    async fn retry_failed(&self) -> Result<()> {
+        let writes: Vec<Write> = match self.queue.failed.lock().as_ref() {
+            Some(failure) => failure
+                .writes
+                .iter()
+                .filter(|write| self.queue.is_newest(write))
+                .cloned()
+                .collect(),
+            None => return Ok(()),
+        };
+        let nodes: Vec<Node> = writes.iter().map(|(_, node)| node.clone()).collect();
+        if let Err(e) = self.inner.put_batch(&nodes).await {
+            return Err(A3SError::Storage(format!("background write failed: {}", e)));
+        }
+        self.queue.remove_applied(&writes);
+
+        let mut failed = self.queue.failed.lock();
+        if let Some(failure) = failed.as_mut() {
+            failure.writes.retain(|write| self.queue.is_newest(write));
+            if failure.writes.is_empty() {
+                *failed = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn enqueue(&self, nodes: &[Node]) -> Result<()> {
+        if nodes.is_empty() {
+            return Ok(());
+        }
+        let mut sender = self.sender.lock();
+        // Held until the writes are queued, so a retry sees every write
+        // accepted before the failure
+        let failed = self.queue.failed.lock();
+        if let Some(failure) = failed.as_ref() {
+            return Err(A3SError::Storage(format!(
+                "background write failed, settle to retry: {}",
+                failure.error
+            )));
+        }
+        let sender = sender.get_or_insert_with(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(apply(self.inner.clone(), self.queue.clone(), rx));
+            tx
+        });
+        let writes: Vec<Write> = nodes
+            .iter()
+            .map(|node| {
+                let seq = self.queue.accepted.fetch_add(1, Ordering::SeqCst) + 1;
+                self.queue
+                    .pending
+                    .insert(node.pathway.to_string(), (seq, node.clone()));
+                (seq, node.clone())
+            })
+            .collect();
+        sender
+            .send(writes)
+            .map_err(|_| A3SError::Storage("background writer stopped".to_string()))
+    }
+}
+
+/// Apply queued writes in order, batching whatever has queued up meanwhile
+async fn apply(
+    inner: Arc<dyn StorageBackend>,
+    queue: Arc<Queue>,
+    mut rx: mpsc::UnboundedReceiver<Vec<Write>>,
+) {
+    while let Some(mut writes) = rx.recv().await {
+        while let Ok(more) = rx.try_recv() {
+            writes.extend(more);
+        }
+        let last = writes.last().map_or(0, |(seq, _)| *seq);
+
+        // Only the newest write of each node is applied
+        let mut seen = HashSet::new();
+        writes.reverse();
+        writes.retain(|(_, node)| seen.insert(node.pathway.to_string()));
+        writes.reverse();
+
+        let nodes: Vec<Node> = writes.iter().map(|(_, node)| node.clone()).collect();
+        match inner.put_batch(&nodes).await {
+            Ok(()) => queue.remove_applied(&writes),
+            Err(e) => {
+                tracing::warn!(nodes = nodes.len(), error = %e, "background write failed");
+                let mut failed = queue.failed.lock();
+                match failed.as_mut() {
+                    Some(failure) => failure.writes.extend(writes),
+                    None => {
+                        *failed = Some(Failure {
+                            error: e.to_string(),
+                            writes,
+                        })
+                    }
+                }
+            }
+        }
+        queue.applied.send_replace(last);
+    }
+}
+
+#[async_trait]
+impl StorageBackend for WriteBehindStorage {
+    async fn initialize(&self) -> Result<()> {
+        self.inner.initialize().await
+    }
+
+    async fn put(&self, node: &Node) -> Result<()> {
+        self.enqueue(std::slice::from_ref(node))
+    }
+
+    async fn get(&self, pathway: &Pathway) -> Result<Node> {
+        if let Some(entry) = self.queue.pending.get(&pathway.to_string()) {
+            return Ok(entry.1.clone());
+        }
+        self.inner.get(pathway).await
+    }
+
+    async fn exists(&self, pathway: &Pathway) -> Result<bool> {
+        if self.queue.pending.contains_key(&pathway.to_string()) {
+            return Ok(true);
+        }
+        self.inner.exists(pathway).await
+    }
+
+    async fn remove(&self, pathway: &Pathway, recursive: bool) -> Result<()> {
+        self.settle().await?;
+        self.inner.remove(pathway, recursive).await
+    }
+
+    async fn list(&self, pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+        self.inner.list(pathway).await
+    }
+
+    async fn search_vector(
+        &self,
+        vector: &[f32],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.inner
+            .search_vector(vector, namespace, limit, threshold)
+            .await
+    }
+
+    async fn search_vectors(
+        &self,
+        vector: &[f32],
+        names: &[VectorName],
+        namespace: Option<Namespace>,
+        limit: usize,
+        threshold: f32,
+    ) -> Result<Vec<(Pathway, f32)>> {
+        self.inner
+            .search_vectors(vector, names, namespace, limit, threshold)
+            .await
+    }
+
+    async fn search_text(
+        &self,
+        pattern: &str,
+        pathway: &Pathway,
+        options: &TextSearchOptions,
+    ) -> Result<Vec<TextMatch>> {
+        self.inner.search_text(pattern, pathway, options).await
+    }
+
+    async fn stats(&self) -> Result<StorageStats> {
+        self.inner.stats().await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.settle().await?;
+        self.inner.flush().await
+    }
+
+    async fn settle(&self) -> Result<()> {
+        let target = self.queue.accepted.load(Ordering::SeqCst);
+        let mut applied = self.queue.applied.subscribe();
+        applied
+            .wait_for(|applied| *applied >= target)
+            .await
+            .map_err(|_| A3SError::Storage("background writer stopped".to_string()))?;
+        self.retry_failed().await?;
+        self.inner.settle().await
+    }
+
+    async fn get_children(&self, pathway: &Pathway, max_depth: usize) -> Result<Vec<Node>> {
+        self.inner.get_children(pathway, max_depth).await
+    }
+
+    async fn warm(&self, pathway: &Pathway) -> Result<usize> {
+        self.inner.warm(pathway).await
+    }
+
+    async fn update_embedding(&self, pathway: &Pathway, embedding: Vec<f32>) -> Result<()> {
+        self.settle().await?;
+        self.inner.update_embedding(pathway, embedding).await
+    }
+
+    async fn update_digest(&self, pathway: &Pathway, digest: crate::digest::Digest) -> Result<()> {
+        self.settle().await?;
+        self.inner.update_digest(pathway, digest).await
+    }
+
+    async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
+        self.enqueue(nodes)
+    }
+
+    async fn update_metadata(
+        &self,
+        filter: &NodeFilter,
+        ops: &[MetadataOp],
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        self.settle().await?;
+        self.inner.update_metadata(filter, ops, now).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::storage::MemoryStorage;
+    use std::sync::atomic::AtomicBool;
+
+    /// Memory storage whose batch writes fail while `failing` is set
+    struct FlakyStorage {
+        inner: MemoryStorage,
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl StorageBackend for FlakyStorage {
+        async fn initialize(&self) -> Result<()> {
+            self.inner.initialize().await
+        }
+        async fn put(&self, node: &Node) -> Result<()> {
+            self.inner.put(node).await
+        }
+        async fn get(&self, pathway: &Pathway) -> Result<Node> {
+            self.inner.get(pathway).await
+        }
+        async fn exists(&self, pathway: &Pathway) -> Result<bool> {
+            self.inner.exists(pathway).await
+        }
+        async fn remove(&self, pathway: &Pathway, recursive: bool) -> Result<()> {
+            self.inner.remove(pathway, recursive).await
+        }
+        async fn list(&self, pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+            self.inner.list(pathway).await
+        }
+        async fn search_vector(
+            &self,
+            vector: &[f32],
+            namespace: Option<Namespace>,
+            limit: usize,
+            threshold: f32,
+        ) -> Result<Vec<(Pathway, f32)>> {
+            self.inner
+                .search_vector(vector, namespace, limit, threshold)
+                .await
+        }
+        async fn search_vectors(
+            &self,
+            vector: &[f32],
+            names: &[VectorName],
+            namespace: Option<Namespace>,
+            limit: usize,
+            threshold: f32,
+        ) -> Result<Vec<(Pathway, f32)>> {
+            self.inner
+                .search_vectors(vector, names, namespace, limit, threshold)
+                .await
+        }
+        async fn search_text(
+            &self,
+            pattern: &str,
+            pathway: &Pathway,
+            options: &TextSearchOptions,
+        ) -> Result<Vec<TextMatch>> {
+            self.inner.search_text(pattern, pathway, options).await
+        }
+        async fn stats(&self) -> Result<StorageStats> {
+            self.inner.stats().await
+        }
+        async fn flush(&self) -> Result<()> {
+            self.inner.flush().await
+        }
+        async fn get_children(&self, pathway: &Pathway, max_depth: usize) -> Result<Vec<Node>> {
+            self.inner.get_children(pathway, max_depth).await
+        }
+        async fn update_embedding(&self, pathway: &Pathway, embedding: Vec<f32>) -> Result<()> {
+            self.inner.update_embedding(pathway, embedding).await
+        }
+        async fn update_digest(
+            &self,
+            pathway: &Pathway,
+            digest: crate::digest::Digest,
+        ) -> Result<()> {
+            self.inner.update_digest(pathway, digest).await
+        }
+        async fn put_batch(&self, nodes: &[Node]) -> Result<()> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(A3SError::Storage("disk full".to_string()));
+            }
+            self.inner.put_batch(nodes).await
+        }
+    }
+
+    fn node(pathway: &str, embedding: Vec<f32>) -> Node {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            pathway.to_string(),
+        );
+        node.embedding = embedding;
+        node
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_read_at_once_and_searchable_after_settle() {
+        let inner = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        let storage = WriteBehindStorage::new(inner.clone());
+
+        for i in 0..20 {
+            let embedding = vec![1.0, i as f32 / 20.0];
+            storage
+                .put(&node(&format!("a3s://knowledge/doc{}", i), embedding))
+                .await
+                .unwrap();
+        }
+        let doc = Pathway::parse("a3s://knowledge/doc7").unwrap();
+        assert!(storage.exists(&doc).await.unwrap());
+        assert_eq!(storage.get(&doc).await.unwrap().pathway, doc);
+
+        storage.settle().await.unwrap();
+        assert_eq!(storage.pending(), 0);
+        assert!(inner.exists(&doc).await.unwrap());
+        let found = storage
+            .search_vector(&[1.0, 0.0], None, 100, 0.0)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 20);
+
+        // A removal cannot overtake a queued put
+        storage
+            .put(&node("a3s://knowledge/late", vec![0.0, 1.0]))
+            .await
+            .unwrap();
+        let late = Pathway::parse("a3s://knowledge/late").unwrap();
+        storage.remove(&late, false).await.unwrap();
+        assert!(!storage.exists(&late).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_failed_writes_stay_queued_until_retried() {
+        let inner = Arc::new(FlakyStorage {
+            inner: MemoryStorage::new(&VectorIndexConfig::default()),
+            failing: AtomicBool::new(true),
+        });
+        let storage = WriteBehindStorage::new(inner.clone());
+        let doc = Pathway::parse("a3s://knowledge/doc").unwrap();
+        storage
+            .put(&node("a3s://knowledge/doc", vec![1.0, 0.0]))
+            .await
+            .unwrap();
+
+        // The rejected write is still read, and blocks further writes
+        assert!(storage.settle().await.is_err());
+        assert!(storage.exists(&doc).await.unwrap());
+        assert!(!inner.exists(&doc).await.unwrap());
+        assert!(storage
+            .put(&node("a3s://knowledge/other", vec![0.0, 1.0]))
+            .await
+            .is_err());
+
+        inner.failing.store(false, Ordering::SeqCst);
+        storage.settle().await.unwrap();
+        assert_eq!(storage.pending(), 0);
+        assert!(inner.exists(&doc).await.unwrap());
+        storage
+            .put(&node("a3s://knowledge/other", vec![0.0, 1.0]))
+            .await
+            .unwrap();
+        storage.settle().await.unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+//! Syntax-aware splitting of source code
+//!
+//! With the `code-chunking` feature, code in Rust, Python, JavaScript,
+//! TypeScript, or Go is parsed with tree-sitter and split between top-level
+//! definitions such as functions, structs, classes, and impl blocks, so a
+//! chunk holds whole definitions. Comments and attributes stay with the
+//! definition that follows them, and small neighbouring definitions share a
+//! chunk. A definition longer than the chunk size is split between the
+//! items of its body, e.g. the methods of an impl or the statements of a
+//! function; only a single item that is still too long is split by size.
+//!
+//! Each code chunk records the names of the definitions it holds under
+//! [`SYMBOLS_KEY`], and query results show them.
+
+use crate::chunk::Chunk;
+use crate::core::Node;
+use crate::tokenizer::Tokenizer;
+
+/// Metadata key on a code chunk holding the names of its definitions
+pub const SYMBOLS_KEY: &str = "symbols";
+
+/// Names of the definitions recorded on a chunk
+pub fn symbols(node: &Node) -> Vec<String> {
+    node.metadata
+        .custom
+        .get(SYMBOLS_KEY)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Split code longer than `size`, as counted by `measure`, between its
+/// definitions
+///
+/// Returns `None` for unsupported languages and for code that does not
+/// parse, which is then split by size instead.
+#[cfg(feature = "code-chunking")]
+pub fn split_code(
+    content: &str,
+    language: &str,
+    size: usize,
+    overlap: usize,
+    measure: &dyn Tokenizer,
+) -> Option<Vec<Chunk>> {
+    let size = size.max(1);
+    if measure.count(content) <= size {
+        return Some(Vec::new());
+    }
+
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&parse::grammar(language)?).ok()?;
+    let tree = parser.parse(content, None)?;
+    if tree.root_node().has_error() {
+        return None;
+    }
+
+    let mut chunks = Vec::new();
+    let mut pending: Option<(usize, usize, Vec<String>)> = None;
+    for unit in parse::units(tree.root_node(), 0, content.len(), content, size, measure) {
+        if let Some((from, to, symbols)) = pending.take() {
+            if measure.count(&content[from..unit.end]) > size {
+                push(&mut chunks, content, from, to, symbols);
+            } else {
+                pending = Some((from, to, symbols));
+            }
+        }
+        if pending.is_none() && measure.count(&content[unit.start..unit.end]) > size {
+            for piece in crate::chunk::split(&content[unit.start..unit.end], size, overlap, measure)
+            {
+                let symbols = unit.symbol.iter().cloned().collect();
+                push(
+                    &mut chunks,
+                    content,
+                    unit.start + piece.start,
+                    unit.start + piece.end,
+                    symbols,
+                );
+            }
+            continue;
+        }
+
+        let (from, _, mut symbols) = pending.unwrap_or((unit.start, unit.start, Vec::new()));
+        if let Some(symbol) = unit.symbol {
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+        pending = Some((from, unit.end, symbols));
+    }
+    if let Some((from, to, symbols)) = pending {
+        push(&mut chunks, content, from, to, symbols);
+    }
+    Some(chunks)
+}
+
+/// Without tree-sitter, code is always split by size
+#[cfg(not(feature = "code-chunking"))]
+pub fn split_code(
+    _content: &str,
+    _language: &str,
+    _size: usize,
+    _overlap: usize,
+    _measure: &dyn Tokenizer,
+) -> Option<Vec<Chunk>> {
+    None
+}
+
+#[cfg(feature = "code-chunking")]
+fn push(chunks: &mut Vec<Chunk>, content: &str, start: usize, end: usize, symbols: Vec<String>) {
+    chunks.push(Chunk {
+        index: chunks.len(),
+        start,
+        end,
+        text: content[start..end].to_string(),
+        symbols,
+    });
+}
+
+#[cfg(feature = "code-chunking")]
+mod parse {
+    use tree_sitter::{Language, Node};
+
+    use crate::tokenizer::Tokenizer;
+
+    /// Node kinds whose name is recorded as a symbol
+    const DEFINITIONS: &[&str] = &[
+        "function",
+        "method",
+        "struct",
+        "enum",
+        "union",
+        "trait",
+        "impl",
+        "class",
+        "interface",
+        "type",
+        "mod",
+        "macro",
+        "decorated",
+        "export",
+    ];
+
+    /// A span of source ending after a definition
+    pub struct Unit {
+        pub start: usize,
+        pub end: usize,
+        pub symbol: Option<String>,
+    }
+
+    pub fn grammar(language: &str) -> Option<Language> {
+        let language = match language {
+            "rust" => tree_sitter_rust::LANGUAGE,
+            "python" => tree_sitter_python::LANGUAGE,
+            "javascript" => tree_sitter_javascript::LANGUAGE,
+            "typescript" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+            "go" => tree_sitter_go::LANGUAGE,
+            _ => return None,
+        };
+        Some(language.into())
+    }
+
+    /// Break `start..end` after each item among `node`'s children, breaking
+    /// an item longer than `size` between the items of its body
+    pub fn units(
+        node: Node,
+        start: usize,
+        end: usize,
+        source: &str,
+        size: usize,
+        measure: &dyn Tokenizer,
+    ) -> Vec<Unit> {
+        let mut spans = Vec::new();
+        let mut from = start;
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if is_trivia(&child) {
+                continue;
+            }
+            let to = child.end_byte().max(from);
+            let symbol = symbol(&child, source);
+            match body(&child) {
+                Some(body) if measure.count(&source[from..to]) > size => {
+                    let mut inner = units(body, from, to, source, size, measure);
+                    for unit in &mut inner {
+                        unit.symbol = unit.symbol.take().or_else(|| symbol.clone());
+                    }
+                    spans.append(&mut inner);
+                }
+                _ => spans.push(Unit {
+                    start: from,
+                    end: to,
+                    symbol,
+                }),
+            }
+            from = to;
+        }
+
+        match spans.last_mut() {
+            Some(last) => last.end = end,
+            None => spans.push(Unit {
+                start,
+                end,
+                symbol: None,
+            }),
+        }
+        spans
+    }
+
+    /// Comments and attributes, which belong to the item after them
+    fn is_trivia(node: &Node) -> bool {
+        node.is_extra() || node.kind().contains("comment") || node.kind().contains("attribute")
+    }
+
+    /// The child holding most of an item's sub-items, e.g. an impl's
+    /// declaration list or a function's block
+    fn body<'tree>(node: &Node<'tree>) -> Option<Node<'tree>> {
+        let mut cursor = node.walk();
+        let body = node
+            .named_children(&mut cursor)
+            .filter(|child| child.named_child_count() >= 2)
+            .max_by_key(|child| child.named_child_count());
+        body
+    }
+
+    /// Name of a definition, e.g. the type of a Rust impl or the function
+    /// under a Python decorator
+    fn symbol(node: &Node, source: &str) -> Option<String> {
+        if !DEFINITIONS.iter().any(|kind| node.kind().contains(kind)) {
+            return None;
+        }
+        let name = node
+            .child_by_field_name("name")
+            .or_else(|| node.child_by_field_name("type"))
+            .or_else(|| {
+                let mut cursor = node.walk();
+                let name = node
+                    .named_children(&mut cursor)
+                    .find_map(|child| child.child_by_field_name("name"));
+                name
+            })?;
+        name.utf8_text(source.as_bytes()).ok().map(str::to_string)
+    }
+}
+
+#[cfg(all(test, feature = "code-chunking"))]
+mod tests {
+    use super::*;
+    use crate::tokenizer::CharTokenizer;
+
+    #[test]
+    fn test_split_rust_at_definitions() {
+        let content = "use std::fmt;\n\n\
+                       /// A parser\n#[derive(Debug)]\nstruct Parser {\n    depth: usize,\n}\n\n\
+                       fn parse(input: &str) -> usize {\n    input.len()\n}\n\n\
+                       fn render(depth: usize) -> String {\n    format!(\"{}\", depth)\n}\n";
+        let chunks = split_code(content, "rust", 80, 0, &CharTokenizer).unwrap();
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].text, "use std::fmt;\n\n/// A parser\n#[derive(Debug)]\nstruct Parser {\n    depth: usize,\n}");
+        assert_eq!(chunks[0].symbols, vec!["Parser"]);
+        assert_eq!(chunks[1].symbols, vec!["parse"]);
+        assert!(chunks[2].text.trim_start().starts_with("fn render"));
+        assert_eq!(chunks.last().unwrap().end, content.len());
+    }
+
+    #[test]
+    fn test_split_long_impl_between_methods() {
+        let methods: String = (0..4)
+            .map(|i| {
+                format!(
+                    "    fn step{}(&self) -> usize {{\n        {}\n    }}\n",
+                    i, i
+                )
+            })
+            .collect();
+        let content = format!("impl Machine {{\n{}}}\n", methods);
+        let chunks = split_code(&content, "rust", 100, 0, &CharTokenizer).unwrap();
+
+        assert!(chunks.len() > 1);
+        assert!(chunks[0].text.starts_with("impl Machine {"));
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 100));
+        let symbols: Vec<_> = chunks.iter().flat_map(|c| c.symbols.clone()).collect();
+        assert_eq!(symbols, vec!["step0", "step1", "step2", "step3"]);
+        assert_eq!(chunks.last().unwrap().end, content.len());
+    }
+
+    #[test]
+    fn test_split_python_decorated() {
+        let content = "import os\n\n@cache\ndef load(path):\n    return open(path).read()\n\n\
+                       class Store:\n    def get(self, key):\n        return key\n";
+        let chunks = split_code(content, "python", 60, 0, &CharTokenizer).unwrap();
+        let symbols: Vec<_> = chunks.iter().flat_map(|c| c.symbols.clone()).collect();
+        assert_eq!(symbols, vec!["load", "Store"]);
+    }
+
+    #[test]
+    fn test_unsupported_or_invalid_code() {
+        assert!(split_code(&"x".repeat(100), "cobol", 10, 0, &CharTokenizer).is_none());
+        assert!(split_code(
+            "fn broken( {\n".repeat(10).as_str(),
+            "rust",
+            10,
+            0,
+            &CharTokenizer
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,249 @@
+//! Deterministic fixtures for testing code built on A3S Context
+//!
+//! Everything here runs in memory with mock providers, so tests need
+//! neither network access nor a writable disk.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use parking_lot::Mutex;
+
+use crate::clock::Clock;
+use crate::config::{Config, StorageBackend};
+use crate::core::{Node, NodeKind};
+use crate::digest::Digest;
+use crate::error::Result;
+use crate::pathway::Pathway;
+use crate::A3SClient;
+
+/// Embedding dimension used by the test configuration
+pub const TEST_EMBEDDING_DIMENSION: usize = 128;
+
+/// Configuration with in-memory storage, mock embedder, and mock reranker
+pub fn test_config() -> Config {
+    let mut config = Config::default();
+    config.storage.backend = StorageBackend::Memory;
+    config.embedding.provider = "mock".to_string();
+    config.embedding.dimension = TEST_EMBEDDING_DIMENSION;
+    config.llm.auto_digest = false;
+    config.retrieval.rerank = true;
+    config.retrieval.rerank_config.provider = "mock".to_string();
+    config
+}
+
+impl A3SClient {
+    /// Create a client backed by memory storage and mock providers
+    pub async fn in_memory_for_tests() -> Result<Self> {
+        Self::new(test_config()).await
+    }
+}
+
+/// Builder for nodes with explicit, reproducible field values
+#[derive(Debug, Clone)]
+pub struct NodeFixture {
+    node: Node,
+}
+
+impl NodeFixture {
+    /// Start a document fixture at a pathway
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pathway is invalid.
+    pub fn new(pathway: &str) -> Self {
+        let pathway = Pathway::parse(pathway)
+            .unwrap_or_else(|e| panic!("invalid fixture pathway {}: {}", pathway, e));
+        let mut node = Node::new(pathway, NodeKind::Document, String::new());
+        node.created_at = TestClock::epoch();
+        node.updated_at = TestClock::epoch();
+        Self { node }
+    }
+
+    pub fn kind(mut self, kind: NodeKind) -> Self {
+        self.node.kind = kind;
+        self
+    }
+
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.node.content = content.into();
+        self
+    }
+
+    /// Set brief and summary digests
+    pub fn digest(mut self, brief: impl Into<String>, summary: impl Into<String>) -> Self {
+        self.node.digest = Digest::with_content(brief.into(), summary.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.node.metadata.tags.push(tag.into());
+        self
+    }
+
+    /// Set both creation and update time
+    pub fn at(mut self, time: DateTime<Utc>) -> Self {
+        self.node.created_at = time;
+        self.node.updated_at = time;
+        self
+    }
+
+    pub fn updated_at(mut self, time: DateTime<Utc>) -> Self {
+        self.node.updated_at = time;
+        self
+    }
+
+    pub fn embedding(mut self, embedding: Vec<f32>) -> Self {
+        self.node.embedding = embedding;
+        self
+    }
+
+    pub fn build(self) -> Node {
+        self.node
+    }
+
+    /// Store the node in a client, embedding its content unless an
+    /// embedding was set explicitly
+    pub async fn insert(self, client: &A3SClient) -> Result<Node> {
+        let mut node = self.node;
+        if node.embedding.is_empty() {
+            node.embedding = client.embedder.embed(&node.content).await?;
+        }
+        client.storage.put(&node).await?;
+        Ok(node)
+    }
+}
+
+/// Manually advanced clock for reproducible timestamps
+///
+/// Share one with a client through [`A3SClient::with_clock`] to control
+/// the time it stamps writes with and judges expiry and staleness by.
+#[derive(Debug)]
+pub struct TestClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl TestClock {
+    /// Default start time and fixture timestamp: 2024-01-01T00:00:00Z
+    pub fn epoch() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// Start a clock at the given UTC date
+    pub fn at_date(year: i32, month: u32, day: u32) -> Self {
+        let start = Utc
+            .with_ymd_and_hms(year, month, day, 0, 0, 0)
+            .single()
+            .expect("invalid test clock date");
+        Self::new(start)
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        *self.now.lock()
+    }
+
+    /// Move the clock forward, returning the new time
+    pub fn advance(&self, by: Duration) -> DateTime<Utc> {
+        let mut now = self.now.lock();
+        *now += by;
+        *now
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        TestClock::now(self)
+    }
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new(Self::epoch())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixture_insert_and_query() {
+        let client = A3SClient::in_memory_for_tests().await.unwrap();
+        let clock = TestClock::default();
+
+        let node = NodeFixture::new("a3s://knowledge/docs/auth")
+            .content("Tokens expire after one hour")
+            .digest("Token expiry", "Access tokens expire after one hour")
+            .tag("auth")
+            .at(clock.advance(Duration::days(1)))
+            .insert(&client)
+            .await
+            .unwrap();
+
+        assert_eq!(node.embedding.len(), TEST_EMBEDDING_DIMENSION);
+        assert_eq!(node.updated_at, TestClock::epoch() + Duration::days(1));
+
+        let read = client.read("a3s://knowledge/docs/auth").await.unwrap();
+        assert_eq!(read.metadata.tags, vec!["auth".to_string()]);
+
+        let result = client.query("Tokens expire after one hour").await.unwrap();
+        assert_eq!(result.matches[0].pathway, node.pathway);
+    }
+
+    #[test]
+    fn test_clock_advance() {
+        let clock = TestClock::at_date(2024, 6, 1);
+        let start = clock.now();
+        assert_eq!(
+            clock.advance(Duration::hours(2)),
+            start + Duration::hours(2)
+        );
+        assert_eq!(clock.now(), start + Duration::hours(2));
+    }
+
+    #[tokio::test]
+    async fn test_client_clock_controls_age_staleness_and_expiry() {
+        use crate::bulk::MetadataOp;
+        use std::sync::Arc;
+
+        let mut config = test_config();
+        config.retrieval.staleness_horizon_days = Some(30);
+        let clock = Arc::new(TestClock::default());
+        let client = A3SClient::new(config)
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let fact = "Prefers dark mode";
+        let pathway = client.remember(fact, "alice", &[]).await.unwrap();
+        assert_eq!(
+            client.read(pathway.to_string()).await.unwrap().created_at,
+            TestClock::epoch()
+        );
+
+        clock.advance(Duration::days(45));
+        let result = client.recall(fact, "alice", 5).await.unwrap();
+        assert_eq!(result.matches[0].age, 45 * 24 * 60 * 60);
+        assert!(result.matches[0].stale);
+
+        let expiry = MetadataOp::SetExpiry(Some(clock.now() + Duration::days(1)));
+        client
+            .update_metadata(pathway.to_string(), &[expiry])
+            .await
+            .unwrap();
+        assert_eq!(
+            client.recall(fact, "alice", 5).await.unwrap().matches.len(),
+            1
+        );
+        clock.advance(Duration::days(2));
+        assert!(client
+            .recall(fact, "alice", 5)
+            .await
+            .unwrap()
+            .matches
+            .is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Unicode-aware comparison for literal text search
+//!
+//! Content and patterns are compared in a search form: NFC for
+//! case-sensitive search, so precomposed and combining accents match, and
+//! compatibility-normalized with full Unicode case folding for
+//! case-insensitive search, so full-width letters, ligatures, and cases such
+//! as `Straße`/`STRASSE` match too.
+
+use std::ops::Range;
+
+use caseless::Caseless;
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+
+/// The form of `text` that literal search compares
+pub fn search_form(text: &str, case_insensitive: bool) -> String {
+    if text.is_ascii() {
+        return if case_insensitive {
+            text.to_ascii_lowercase()
+        } else {
+            text.to_string()
+        };
+    }
+    if case_insensitive {
+        text.nfd()
+            .default_case_fold()
+            .nfkd()
+            .default_case_fold()
+            .nfkc()
+            .collect()
+    } else {
+        text.nfc().collect()
+    }
+}
+
+/// Whether `content` contains `pattern` once both are in search form
+pub fn contains(content: &str, pattern: &str, case_insensitive: bool) -> bool {
+    search_form(content, case_insensitive).contains(&search_form(pattern, case_insensitive))
+}
+
+/// Byte ranges of `text` whose search form matches `pattern`
+///
+/// Text is normalized one base character and its combining marks at a time,
+/// so each match maps back to whole characters of the original.
+pub fn find_all(text: &str, pattern: &str, case_insensitive: bool) -> Vec<Range<usize>> {
+    let needle = search_form(pattern, case_insensitive);
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    // Each segment's start in the search form and its range in `text`
+    let mut folded = String::new();
+    let mut segments: Vec<(usize, Range<usize>)> = Vec::new();
+    let mut start = 0;
+    let boundaries = text
+        .char_indices()
+        .skip(1)
+        .filter(|(_, c)| canonical_combining_class(*c) == 0)
+        .map(|(i, _)| i)
+        .chain([text.len()]);
+    for end in boundaries {
+        segments.push((folded.len(), start..end));
+        folded.push_str(&search_form(&text[start..end], case_insensitive));
+        start = end;
+    }
+
+    let source = |offset: usize| &segments[segments.partition_point(|s| s.0 <= offset) - 1].1;
+    folded
+        .match_indices(&needle)
+        .map(|(at, found)| source(at).start..source(at + found.len() - 1).end)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_form() {
+        assert_eq!(search_form("API Key", true), "api key");
+        assert_eq!(search_form("API Key", false), "API Key");
+        // Combining acute composes with its base
+        assert_eq!(search_form("cafe\u{301}", false), "café");
+        assert_eq!(search_form("ＡＰＩ", true), "api");
+        assert_eq!(search_form("Straße", true), "strasse");
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(contains("Le café est ouvert", "CAFE\u{301}", true));
+        assert!(contains("Use the ＡＰＩ key", "api key", true));
+        assert!(!contains("Use the ＡＰＩ key", "api key", false));
+        // A bare letter does not match the accented one
+        assert!(!contains("cafe\u{301}", "cafe", true));
+        assert!(!contains("café", "cafe", false));
+    }
+
+    #[test]
+    fn test_find_all() {
+        let text = "Ｃafé, then CAFE\u{301}!";
+        let found = find_all(text, "café", true);
+        assert_eq!(found.len(), 2);
+        assert_eq!(&text[found[0].clone()], "Ｃafé");
+        assert_eq!(&text[found[1].clone()], "CAFE\u{301}");
+        assert_eq!(find_all("plain ascii", "ASCII", true), vec![6..11]);
+        assert!(find_all("anything", "", true).is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+//! Counting and truncating text in model tokens
+//!
+//! Context assembly, ingest token counts, and chunking with
+//! `ingest.chunk_unit: tokens` measure text with a [`Tokenizer`]. The
+//! client uses [`HeuristicTokenizer`] unless another is set with
+//! `A3SClient::set_tokenizer`, so teams on models with their own
+//! vocabulary can budget in that model's tokens. With the `tiktoken`
+//! feature, [`TiktokenTokenizer`] counts exactly with OpenAI's encodings.
+
+/// Measures text in tokens
+pub trait Tokenizer: Send + Sync {
+    /// Number of tokens in a text
+    fn count(&self, text: &str) -> usize;
+
+    /// Longest prefix of at most `max_tokens` tokens, ending on a character
+    /// boundary
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        if self.count(text) <= max_tokens {
+            return text;
+        }
+        // Prefixes grow in tokens with their length, so the longest one
+        // within budget is found by bisecting the character boundaries
+        let bounds: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+        let (mut fits, mut exceeds) = (0, bounds.len());
+        while exceeds - fits > 1 {
+            let mid = (fits + exceeds) / 2;
+            if self.count(&text[..bounds[mid]]) <= max_tokens {
+                fits = mid;
+            } else {
+                exceeds = mid;
+            }
+        }
+        &text[..bounds[fits]]
+    }
+
+    /// Longest suffix of at most `max_tokens` tokens, starting on a
+    /// character boundary
+    fn truncate_start<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        if self.count(text) <= max_tokens {
+            return text;
+        }
+        let bounds: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+        // Suffix starting at bounds[i]; the empty suffix always fits
+        let (mut exceeds, mut fits) = (0, bounds.len());
+        while fits - exceeds > 1 {
+            let mid = (fits + exceeds) / 2;
+            if self.count(&text[bounds[mid]..]) <= max_tokens {
+                fits = mid;
+            } else {
+                exceeds = mid;
+            }
+        }
+        match bounds.get(fits) {
+            Some(&start) => &text[start..],
+            None => "",
+        }
+    }
+}
+
+/// Rough characters-per-token ratio of [`HeuristicTokenizer`]
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimates a token per four characters, which is close for English text
+/// with most current models
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(CHARS_PER_TOKEN)
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        CharTokenizer.truncate(text, max_tokens * CHARS_PER_TOKEN)
+    }
+
+    fn truncate_start<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        CharTokenizer.truncate_start(text, max_tokens * CHARS_PER_TOKEN)
+    }
+}
+
+/// Counts every character as a token, for measuring chunks in characters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CharTokenizer;
+
+impl Tokenizer for CharTokenizer {
+    fn count(&self, text: &str) -> usize {
+        text.chars().count()
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        match text.char_indices().nth(max_tokens) {
+            Some((end, _)) => &text[..end],
+            None => text,
+        }
+    }
+
+    fn truncate_start<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        match max_tokens.checked_sub(1) {
+            Some(n) => match text.char_indices().rev().nth(n) {
+                Some((start, _)) => &text[start..],
+                None => text,
+            },
+            None => "",
+        }
+    }
+}
+
+/// Counts tokens exactly with one of OpenAI's BPE encodings
+#[cfg(feature = "tiktoken")]
+pub struct TiktokenTokenizer {
+    bpe: tiktoken_rs::CoreBPE,
+}
+
+#[cfg(feature = "tiktoken")]
+impl TiktokenTokenizer {
+    /// Tokenizer for an OpenAI model, e.g. `gpt-4o`
+    pub fn for_model(model: &str) -> crate::error::Result<Self> {
+        let bpe = tiktoken_rs::get_bpe_from_model(model).map_err(|e| {
+            crate::error::A3SError::Config(format!("No tokenizer for model {}: {}", model, e))
+        })?;
+        Ok(Self { bpe })
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+impl Tokenizer for TiktokenTokenizer {
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_ordinary(text).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts whitespace-separated words, like a model with a word vocabulary
+    struct WordTokenizer;
+
+    impl Tokenizer for WordTokenizer {
+        fn count(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+    }
+
+    #[test]
+    fn test_heuristic_tokenizer() {
+        assert_eq!(HeuristicTokenizer.count(""), 0);
+        assert_eq!(HeuristicTokenizer.count("héllo"), 2);
+        assert_eq!(HeuristicTokenizer.truncate("abcdefghij", 2), "abcdefgh");
+        assert_eq!(HeuristicTokenizer.truncate_start("abcdefghij", 1), "ghij");
+    }
+
+    #[test]
+    fn test_char_tokenizer() {
+        assert_eq!(CharTokenizer.truncate("日本語", 2), "日本");
+        assert_eq!(CharTokenizer.truncate_start("日本語", 2), "本語");
+        assert_eq!(CharTokenizer.truncate_start("日本語", 0), "");
+        assert_eq!(CharTokenizer.truncate_start("日本語", 5), "日本語");
+    }
+
+    #[test]
+    fn test_default_truncation_bisects() {
+        let text = "one two three four five";
+        assert_eq!(WordTokenizer.truncate(text, 2), "one two ");
+        assert_eq!(WordTokenizer.truncate(text, 10), text);
+        assert_eq!(WordTokenizer.truncate_start(text, 2), " four five");
+        assert_eq!(WordTokenizer.truncate_start(text, 0), "");
+    }
+
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn test_tiktoken_counts_exactly() {
+        let tokenizer = TiktokenTokenizer::for_model("gpt-4o").unwrap();
+        assert_eq!(tokenizer.count("hello world"), 2);
+        assert_eq!(tokenizer.truncate("hello world", 1), "hello");
+        assert!(TiktokenTokenizer::for_model("not-a-model").is_err());
+    }
+}
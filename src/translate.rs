@@ -0,0 +1,131 @@
+//! Translation of ingested documents into the query language
+//!
+//! With `ingest.translation.enabled`, a document whose detected language
+//! differs from the target is translated by the LLM before embedding. The
+//! translation is stored under [`TRANSLATION_KEY`] and embedded in place of
+//! the content, which keeps the original text, so queries in the target
+//! language retrieve across a multilingual corpus. Chunks are embedded in
+//! their original language.
+
+use crate::core::Node;
+
+/// Metadata key holding a node's translation
+pub const TRANSLATION_KEY: &str = "translation";
+
+/// Text embedded for a node's content: its translation when it has one
+pub fn embedding_text(node: &Node) -> &str {
+    node.metadata
+        .custom
+        .get(TRANSLATION_KEY)
+        .and_then(|v| v.as_str())
+        .unwrap_or(&node.content)
+}
+
+#[cfg(feature = "llm-digest")]
+pub use stage::TranslationStage;
+
+#[cfg(feature = "llm-digest")]
+mod stage {
+    use async_trait::async_trait;
+
+    use super::TRANSLATION_KEY;
+    use crate::digest::LLMClient;
+    use crate::error::Result;
+    use crate::language;
+    use crate::pipeline::{IngestItem, PipelineStage};
+
+    /// Content sent for translation; the rest is left untranslated
+    const MAX_CHARS: usize = 4000;
+
+    /// Pipeline stage translating other-language documents, run before
+    /// `embed`
+    pub struct TranslationStage {
+        llm: LLMClient,
+        /// ISO 639-3 code of the target language
+        target: String,
+    }
+
+    impl TranslationStage {
+        pub fn new(llm: LLMClient, target: impl Into<String>) -> Self {
+            Self {
+                llm,
+                target: target.into(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl PipelineStage for TranslationStage {
+        fn name(&self) -> &str {
+            "translate"
+        }
+
+        async fn process(&self, item: &mut IngestItem) -> Result<()> {
+            let custom = &mut item.node.metadata.custom;
+            let source = custom
+                .get(language::LANGUAGE_KEY)
+                .and_then(|v| v.as_str())
+                .filter(|code| !code.eq_ignore_ascii_case(&self.target))
+                .map(str::to_string);
+            let Some(source) = source else {
+                custom.remove(TRANSLATION_KEY);
+                return Ok(());
+            };
+
+            let prompt = prompt(&item.node.content, &source, &self.target);
+            let translation = self.llm.complete(&prompt).await?;
+            item.node.metadata.custom.insert(
+                TRANSLATION_KEY.to_string(),
+                serde_json::json!(translation.trim()),
+            );
+            Ok(())
+        }
+    }
+
+    fn prompt(content: &str, source: &str, target: &str) -> String {
+        let end = content
+            .char_indices()
+            .nth(MAX_CHARS)
+            .map_or(content.len(), |(offset, _)| offset);
+        format!(
+            "Translate the following {} text into {}. \
+             Reply with the translation only.\n\n{}",
+            language::name(source).unwrap_or(source),
+            language::name(target).unwrap_or(target),
+            &content[..end]
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_prompt_names_languages_and_caps_length() {
+            let content = "ä".repeat(MAX_CHARS + 10);
+            let prompt = prompt(&content, "deu", "eng");
+            assert!(prompt.starts_with("Translate the following German text into English."));
+            assert_eq!(prompt.matches('ä').count(), MAX_CHARS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+    use crate::pathway::Pathway;
+
+    #[test]
+    fn test_embedding_text_prefers_translation() {
+        let pathway = Pathway::parse("a3s://knowledge/de/deploy").unwrap();
+        let mut node = Node::new(pathway, NodeKind::Document, "Bereitstellung".to_string());
+        assert_eq!(embedding_text(&node), "Bereitstellung");
+
+        node.metadata
+            .custom
+            .insert(TRANSLATION_KEY.to_string(), serde_json::json!("Deployment"));
+        assert_eq!(embedding_text(&node), "Deployment");
+        assert_eq!(node.content, "Bereitstellung");
+    }
+}
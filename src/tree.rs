@@ -0,0 +1,179 @@
+//! Nested view of a subtree, built from one recursive read
+//!
+//! Levels of a pathway often have no node of their own, e.g. ingesting
+//! `docs/api/auth.md` stores only the document, so walking a hierarchy
+//! with `list` misses them. [`tree`] reads every node below a pathway once
+//! and fills in the levels in between.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Node, NodeKind};
+use crate::error::Result;
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+
+/// A level of a subtree and the levels below it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeNode {
+    pub pathway: Pathway,
+    /// Kind of the stored node; `None` for a level with no node of its own
+    pub kind: Option<NodeKind>,
+    /// A stored directory, or a level with nodes below it
+    pub is_directory: bool,
+    pub brief: String,
+    /// Nodes stored below this level, at any depth
+    pub descendant_count: u64,
+    /// Levels directly below, down to the requested depth, by name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+/// Build the tree under `pathway`, `depth` levels deep
+pub async fn tree(
+    storage: &dyn StorageBackend,
+    pathway: &Pathway,
+    depth: usize,
+) -> Result<TreeNode> {
+    // Lazily loading backends may not have read the subtree yet
+    storage.warm(pathway).await?;
+    let below = storage.get_children(pathway, usize::MAX).await?;
+    let own = storage.get(pathway).await.ok();
+    Ok(build(
+        pathway.clone(),
+        own.as_ref(),
+        below.iter().collect(),
+        depth,
+    ))
+}
+
+fn build(pathway: Pathway, own: Option<&Node>, below: Vec<&Node>, depth: usize) -> TreeNode {
+    let mut children = Vec::new();
+    if depth > 0 {
+        let mut levels: BTreeMap<&str, (Option<&Node>, Vec<&Node>)> = BTreeMap::new();
+        for node in &below {
+            let Some(segment) = node.pathway.segments().get(pathway.depth()) else {
+                continue;
+            };
+            let level = levels.entry(segment.as_str()).or_default();
+            if node.pathway.depth() == pathway.depth() + 1 {
+                level.0 = Some(node);
+            } else {
+                level.1.push(node);
+            }
+        }
+        children = levels
+            .into_iter()
+            .map(|(segment, (own, below))| build(pathway.join(segment), own, below, depth - 1))
+            .collect();
+    }
+
+    TreeNode {
+        kind: own.map(|node| node.kind),
+        is_directory: own.is_some_and(|node| node.is_directory) || !below.is_empty(),
+        brief: own
+            .map(|node| node.digest.brief.clone())
+            .unwrap_or_default(),
+        descendant_count: below.len() as u64,
+        children,
+        pathway,
+    }
+}
+
+/// Draw a tree with box-drawing characters, one level per line
+pub fn render(tree: &TreeNode) -> String {
+    let mut out = format!("{}{}\n", tree.pathway, label(tree));
+    draw_children(tree, "", &mut out);
+    out
+}
+
+fn draw_children(tree: &TreeNode, prefix: &str, out: &mut String) {
+    for (i, child) in tree.children.iter().enumerate() {
+        let last = i + 1 == tree.children.len();
+        out.push_str(&format!(
+            "{}{}{}{}\n",
+            prefix,
+            if last { "└── " } else { "├── " },
+            child.pathway.name().unwrap_or(""),
+            label(child)
+        ));
+        let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        draw_children(child, &prefix, out);
+    }
+}
+
+/// `/ (n)` for directories and ` — brief` for nodes with a brief
+fn label(node: &TreeNode) -> String {
+    let mut label = String::new();
+    if node.is_directory {
+        label.push_str(&format!("/ ({})", node.descendant_count));
+    }
+    if !node.brief.is_empty() {
+        label.push_str(&format!(" — {}", node.brief));
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::digest::Digest;
+    use crate::storage::MemoryStorage;
+
+    async fn storage(pathways: &[&str]) -> MemoryStorage {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        for pathway in pathways {
+            let pathway = Pathway::parse(pathway).unwrap();
+            let mut node = Node::new(pathway.clone(), NodeKind::Document, "text".to_string());
+            node.digest =
+                Digest::with_content(format!("About {}", pathway.name().unwrap()), String::new());
+            storage.put(&node).await.unwrap();
+        }
+        storage
+    }
+
+    #[tokio::test]
+    async fn test_tree_fills_in_levels() {
+        let storage = storage(&[
+            "a3s://knowledge/docs/api/auth.md",
+            "a3s://knowledge/docs/api/limits.md",
+            "a3s://knowledge/docs/guide.md",
+        ])
+        .await;
+        let root = Pathway::parse("a3s://knowledge/docs").unwrap();
+
+        let tree = tree(&storage, &root, 2).await.unwrap();
+        assert!(tree.kind.is_none());
+        assert_eq!(tree.descendant_count, 3);
+        assert_eq!(tree.children.len(), 2);
+
+        let api = &tree.children[0];
+        assert!(api.is_directory && api.kind.is_none());
+        assert_eq!(api.descendant_count, 2);
+        assert_eq!(api.children[0].brief, "About auth.md");
+
+        assert_eq!(
+            render(&tree),
+            "a3s://knowledge/docs/ (3)\n\
+             ├── api/ (2)\n\
+             │   ├── auth.md — About auth.md\n\
+             │   └── limits.md — About limits.md\n\
+             └── guide.md — About guide.md\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tree_depth_limits_levels() {
+        let storage = storage(&["a3s://knowledge/docs/api/auth.md"]).await;
+        let root = Pathway::parse("a3s://knowledge/docs").unwrap();
+
+        let shallow = tree(&storage, &root, 1).await.unwrap();
+        assert_eq!(shallow.children.len(), 1);
+        assert!(shallow.children[0].children.is_empty());
+        assert_eq!(shallow.children[0].descendant_count, 1);
+
+        assert!(tree(&storage, &root, 0).await.unwrap().children.is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+//! Recursive, du-style usage accounting for subtrees, and the nodes still
+//! waiting for an embedding or digest
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::Node;
+use crate::error::Result;
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+use crate::NodeInfo;
+
+/// Usage of a subtree and its immediate children
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Totals for the whole subtree
+    pub total: SubtreeUsage,
+    /// Totals for each immediate child subtree, largest first
+    pub children: Vec<SubtreeUsage>,
+    /// Largest nodes in the subtree, largest first
+    pub largest: Vec<NodeUsage>,
+}
+
+/// Recursive totals for one subtree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtreeUsage {
+    pub pathway: Pathway,
+    pub node_count: u64,
+    pub directory_count: u64,
+    pub size_bytes: u64,
+    /// Nodes with an embedding
+    pub embedded_count: u64,
+    /// Nodes with a generated digest
+    pub digested_count: u64,
+}
+
+/// Size of a single node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeUsage {
+    pub pathway: Pathway,
+    pub size_bytes: u64,
+}
+
+impl SubtreeUsage {
+    fn new(pathway: Pathway) -> Self {
+        Self {
+            pathway,
+            node_count: 0,
+            directory_count: 0,
+            size_bytes: 0,
+            embedded_count: 0,
+            digested_count: 0,
+        }
+    }
+
+    fn record(&mut self, node: &Node) {
+        self.node_count += 1;
+        if node.is_directory {
+            self.directory_count += 1;
+        }
+        self.size_bytes += node.size();
+        if node.is_embedded() {
+            self.embedded_count += 1;
+        }
+        if node.digest.is_generated() {
+            self.digested_count += 1;
+        }
+    }
+
+    /// Fraction of non-directory nodes with a generated digest
+    pub fn digest_coverage(&self) -> f64 {
+        let documents = self.node_count - self.directory_count;
+        if documents == 0 {
+            return 0.0;
+        }
+        self.digested_count as f64 / documents as f64
+    }
+}
+
+/// Compute recursive usage under a pathway
+pub async fn usage(
+    storage: &dyn StorageBackend,
+    pathway: &Pathway,
+    top_n: usize,
+) -> Result<UsageReport> {
+    let mut nodes = storage.get_children(pathway, usize::MAX).await?;
+    if let Ok(node) = storage.get(pathway).await {
+        nodes.push(node);
+    }
+
+    let mut total = SubtreeUsage::new(pathway.clone());
+    let mut children: BTreeMap<String, SubtreeUsage> = BTreeMap::new();
+    let mut largest = Vec::new();
+
+    for node in &nodes {
+        total.record(node);
+
+        if let Some(segment) = node.pathway.segments().get(pathway.depth()) {
+            children
+                .entry(segment.clone())
+                .or_insert_with(|| SubtreeUsage::new(pathway.join(segment)))
+                .record(node);
+        }
+
+        if !node.is_directory {
+            largest.push(NodeUsage {
+                pathway: node.pathway.clone(),
+                size_bytes: node.size(),
+            });
+        }
+    }
+
+    let mut children: Vec<SubtreeUsage> = children.into_values().collect();
+    children.sort_by_key(|n| std::cmp::Reverse(n.size_bytes));
+
+    largest.sort_by_key(|n| std::cmp::Reverse(n.size_bytes));
+    largest.truncate(top_n);
+
+    Ok(UsageReport {
+        total,
+        children,
+        largest,
+    })
+}
+
+/// Non-directory nodes under a pathway that have no embedding
+pub async fn unembedded(storage: &dyn StorageBackend, pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+    missing(storage, pathway, |node| !node.is_embedded()).await
+}
+
+/// Non-directory nodes under a pathway whose digest was never generated
+pub async fn undigested(storage: &dyn StorageBackend, pathway: &Pathway) -> Result<Vec<NodeInfo>> {
+    missing(storage, pathway, |node| !node.digest.is_generated()).await
+}
+
+async fn missing(
+    storage: &dyn StorageBackend,
+    pathway: &Pathway,
+    is_missing: impl Fn(&Node) -> bool,
+) -> Result<Vec<NodeInfo>> {
+    let mut nodes = storage.get_children(pathway, usize::MAX).await?;
+    if let Ok(node) = storage.get(pathway).await {
+        nodes.push(node);
+    }
+
+    let mut infos: Vec<NodeInfo> = nodes
+        .into_iter()
+        .filter(|node| !node.is_directory && is_missing(node))
+        .map(|node| NodeInfo {
+            size: node.size(),
+            pathway: node.pathway,
+            kind: node.kind,
+            is_directory: node.is_directory,
+            created_at: node.created_at,
+            updated_at: node.updated_at,
+        })
+        .collect();
+    infos.sort_by(|a, b| a.pathway.cmp(&b.pathway));
+    Ok(infos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::digest::Digest;
+    use crate::storage::MemoryStorage;
+
+    async fn put(storage: &MemoryStorage, pathway: &str, content: &str, embedded: bool) {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            content.to_string(),
+        );
+        if embedded {
+            node.embedding = vec![1.0, 0.0];
+            node.digest = Digest::with_content("brief".to_string(), "summary".to_string());
+        }
+        storage.put(&node).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_usage_groups_by_child() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        put(&storage, "a3s://knowledge/docs/a", "aaaa", true).await;
+        put(&storage, "a3s://knowledge/docs/b", "bb", false).await;
+        put(&storage, "a3s://knowledge/code/main", "cccccccc", true).await;
+        put(&storage, "a3s://memory/user/x", "ignored", true).await;
+
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+        let report = usage(&storage, &root, 2).await.unwrap();
+
+        assert_eq!(report.total.node_count, 3);
+        assert_eq!(report.total.size_bytes, 14);
+        assert_eq!(report.total.embedded_count, 2);
+        assert_eq!(report.total.digested_count, 2);
+
+        assert_eq!(report.children.len(), 2);
+        assert_eq!(
+            report.children[0].pathway.to_string(),
+            "a3s://knowledge/code"
+        );
+        assert_eq!(report.children[1].node_count, 2);
+        assert!((report.children[1].digest_coverage() - 0.5).abs() < f64::EPSILON);
+
+        assert_eq!(report.largest.len(), 2);
+        assert_eq!(
+            report.largest[0].pathway.to_string(),
+            "a3s://knowledge/code/main"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backlog_lists_missing_nodes() {
+        let storage = MemoryStorage::new(&VectorIndexConfig::default());
+        put(&storage, "a3s://knowledge/docs/a", "done", true).await;
+        put(&storage, "a3s://knowledge/docs/b", "pending", false).await;
+
+        let root = Pathway::parse("a3s://knowledge").unwrap();
+        let pending = unembedded(&storage, &root).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].pathway.to_string(), "a3s://knowledge/docs/b");
+        assert_eq!(undigested(&storage, &root).await.unwrap().len(), 1);
+
+        let done = Pathway::parse("a3s://knowledge/docs/a").unwrap();
+        assert!(unembedded(&storage, &done).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+//! Derived views: directories whose children are the results of a saved query
+//!
+//! A view is a directory node carrying its definition in metadata. Its
+//! children are unembedded copies of the matching nodes, rebuilt on first
+//! access, when older than `refresh_secs`, or on explicit refresh.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Namespace, Node, RelationKind};
+use crate::pathway::Pathway;
+use crate::{MatchedNode, QueryOptions};
+
+/// Metadata key holding a view's definition on its directory node
+pub const VIEW_METADATA_KEY: &str = "view";
+
+/// Metadata key holding when a view was last materialized
+const MATERIALIZED_AT_KEY: &str = "view_materialized_at";
+
+/// Metadata key on view children naming the node they were copied from
+pub const VIEW_SOURCE_KEY: &str = "view_source";
+
+/// Saved query backing a view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewDefinition {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<Namespace>,
+    /// Only include pathways starting with this prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pathway_filter: Option<String>,
+    #[serde(default = "default_view_limit")]
+    pub limit: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+    /// Rebuild on access when the view is older than this; `None` rebuilds
+    /// only on first access and explicit refresh
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_secs: Option<u64>,
+}
+
+fn default_view_limit() -> usize {
+    20
+}
+
+impl ViewDefinition {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            namespace: None,
+            pathway_filter: None,
+            limit: default_view_limit(),
+            threshold: None,
+            refresh_secs: None,
+        }
+    }
+
+    /// Options for running the view's query
+    pub fn query_options(&self) -> QueryOptions {
+        QueryOptions {
+            namespace: self.namespace,
+            limit: Some(self.limit),
+            threshold: self.threshold,
+            include_content: true,
+            pathway_filter: self.pathway_filter.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Directory node for a new, not yet materialized view
+pub fn view_node(at: Pathway, definition: &ViewDefinition) -> Node {
+    let mut node = Node::directory(at);
+    if let Ok(value) = serde_json::to_value(definition) {
+        node.metadata
+            .custom
+            .insert(VIEW_METADATA_KEY.to_string(), value);
+    }
+    node
+}
+
+/// Definition of the view rooted at a node, if it is one
+pub fn definition(node: &Node) -> Option<ViewDefinition> {
+    let value = node.metadata.custom.get(VIEW_METADATA_KEY)?;
+    serde_json::from_value(value.clone()).ok()
+}
+
+/// When a view node was last materialized
+pub fn materialized_at(node: &Node) -> Option<DateTime<Utc>> {
+    let value = node.metadata.custom.get(MATERIALIZED_AT_KEY)?;
+    serde_json::from_value(value.clone()).ok()
+}
+
+/// Whether a view must be rebuilt before it is read at `now`
+pub fn needs_refresh(node: &Node, definition: &ViewDefinition, now: DateTime<Utc>) -> bool {
+    match (materialized_at(node), definition.refresh_secs) {
+        (None, _) => true,
+        (Some(at), Some(secs)) => (now - at).num_seconds() >= secs as i64,
+        (Some(_), None) => false,
+    }
+}
+
+/// Record a materialization time on a view node
+pub fn mark_materialized(node: &mut Node, at: DateTime<Utc>) {
+    node.metadata
+        .custom
+        .insert(MATERIALIZED_AT_KEY.to_string(), serde_json::json!(at));
+}
+
+/// Children for a view, named after their sources
+///
+/// Matches from inside the view itself are skipped; clashing names get a
+/// numeric suffix.
+pub fn materialize(at: &Pathway, matches: &[MatchedNode]) -> Vec<Node> {
+    let mut children: Vec<Node> = Vec::new();
+    for m in matches {
+        if at.is_prefix_of(&m.pathway) {
+            continue;
+        }
+
+        let base = m.pathway.name().unwrap_or(m.pathway.namespace().as_str());
+        let mut name = base.to_string();
+        let mut n = 2;
+        while children
+            .iter()
+            .any(|c| c.pathway.name() == Some(name.as_str()))
+        {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+
+        let mut child = Node::new(
+            at.join(&name),
+            m.node_kind,
+            m.content.clone().unwrap_or_default(),
+        );
+        child.digest = crate::digest::Digest::with_content(
+            m.brief.clone(),
+            m.summary.clone().unwrap_or_default(),
+        );
+        child.metadata.custom.insert(
+            VIEW_SOURCE_KEY.to_string(),
+            serde_json::json!(m.pathway.to_string()),
+        );
+        child.add_relation(
+            m.pathway.clone(),
+            RelationKind::DerivedFrom,
+            "view match".to_string(),
+        );
+        children.push(child);
+    }
+    children
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NodeKind;
+
+    fn matched(pathway: &str) -> MatchedNode {
+        MatchedNode {
+            pathway: Pathway::parse(pathway).unwrap(),
+            node_kind: NodeKind::Document,
+            score: 0.9,
+            brief: "brief".to_string(),
+            summary: None,
+            content: Some("content".to_string()),
+            highlights: Vec::new(),
+            age: 0,
+            stale: false,
+            symbols: Vec::new(),
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn test_view_node_round_trip() {
+        let mut definition = ViewDefinition::new("authentication");
+        definition.refresh_secs = Some(60);
+        let mut node = view_node(
+            Pathway::parse("a3s://knowledge/views/auth").unwrap(),
+            &definition,
+        );
+
+        assert_eq!(super::definition(&node), Some(definition.clone()));
+        let now = Utc::now();
+        assert!(needs_refresh(&node, &definition, now));
+        mark_materialized(&mut node, now);
+        assert!(!needs_refresh(&node, &definition, now));
+        assert!(needs_refresh(
+            &node,
+            &definition,
+            now + chrono::Duration::minutes(2)
+        ));
+    }
+
+    #[test]
+    fn test_materialize_names_children() {
+        let at = Pathway::parse("a3s://knowledge/views/auth").unwrap();
+        let children = materialize(
+            &at,
+            &[
+                matched("a3s://knowledge/docs/login"),
+                matched("a3s://knowledge/api/login"),
+                matched("a3s://knowledge/views/auth/login"),
+            ],
+        );
+
+        let names: Vec<_> = children.iter().map(|c| c.pathway.to_string()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "a3s://knowledge/views/auth/login",
+                "a3s://knowledge/views/auth/login-2"
+            ]
+        );
+        assert_eq!(
+            children[0].relations[0].target.to_string(),
+            "a3s://knowledge/docs/login"
+        );
+    }
+}
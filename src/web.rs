@@ -0,0 +1,378 @@
+//! Website ingestion from single URLs and sitemaps
+//!
+//! An `http://` or `https://` source is fetched as one page. A
+//! `sitemap:<url>` source lists pages in a sitemap, following sitemap
+//! indexes. Pages are fetched with a politeness delay between requests and
+//! a limit on requests in flight. Each page's ETag is stored on its node and
+//! sent back as `If-None-Match`, so a refresh skips unchanged pages.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::error::{A3SError, Result};
+
+/// Source prefix for sitemap ingestion
+pub const SITEMAP_PREFIX: &str = "sitemap:";
+
+/// Metadata key holding the ETag a page was fetched with
+pub const ETAG_KEY: &str = "etag";
+
+/// Contents of a sitemap document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sitemap {
+    /// Page URLs
+    Urls(Vec<String>),
+    /// URLs of further sitemaps
+    Index(Vec<String>),
+}
+
+/// Sitemap URL of a `sitemap:<url>` source
+pub fn parse_source(source: &str) -> Option<&str> {
+    source
+        .strip_prefix(SITEMAP_PREFIX)
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+}
+
+/// URL of a source that is a single web page
+pub fn page_url(source: &str) -> Option<&str> {
+    (source.starts_with("http://") || source.starts_with("https://")).then_some(source)
+}
+
+/// Read the `<loc>` entries of a sitemap or sitemap index
+pub fn parse_sitemap(xml: &str) -> Sitemap {
+    static PATTERNS: OnceLock<[Regex; 2]> = OnceLock::new();
+    let [index, loc] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"(?i)<sitemapindex[\s>]").unwrap(),
+            Regex::new(r"(?is)<loc>\s*(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?\s*</loc>").unwrap(),
+        ]
+    });
+
+    let locs = loc
+        .captures_iter(xml)
+        .map(|caps| decode_entities(&caps[1]))
+        .filter(|url| !url.is_empty())
+        .collect();
+    if index.is_match(xml) {
+        Sitemap::Index(locs)
+    } else {
+        Sitemap::Urls(locs)
+    }
+}
+
+/// Pathway of a page relative to the ingest target, from its URL path
+pub fn page_path(url: &str) -> Result<String> {
+    let parsed = url::Url::parse(url)
+        .map_err(|e| A3SError::Ingest(format!("Invalid page URL {}: {}", url, e)))?;
+    let path = parsed
+        .path()
+        .trim_matches('/')
+        .trim_end_matches(".html")
+        .trim_end_matches(".htm");
+    Ok(if path.is_empty() {
+        "index".to_string()
+    } else {
+        path.to_string()
+    })
+}
+
+/// Whether two URLs share a scheme, host, and port
+pub fn same_origin(a: &str, b: &str) -> bool {
+    match (url::Url::parse(a), url::Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// Elements removed from a page before its text is extracted
+const BOILERPLATE_TAGS: &[&str] = &[
+    "script", "style", "noscript", "head", "header", "nav", "aside", "form", "footer",
+];
+
+/// Markdown content for an HTML page: its title as a heading, then the text
+/// of `<main>` or `<article>` when present, or the whole body otherwise,
+/// without navigation, headers, footers, sidebars, or forms
+pub fn page_content(html: &str) -> String {
+    static PATTERNS: OnceLock<(Regex, Regex, Vec<Regex>)> = OnceLock::new();
+    let (title, main, noise) = PATTERNS.get_or_init(|| {
+        let noise = BOILERPLATE_TAGS
+            .iter()
+            .map(|tag| Regex::new(&format!(r"(?is)<{0}(\s[^>]*)?>.*?</{0}>", tag)).unwrap())
+            .collect();
+        (
+            Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap(),
+            Regex::new(r"(?is)<main[^>]*>(.*)</main>|<article[^>]*>(.*)</article>").unwrap(),
+            noise,
+        )
+    });
+
+    let heading = title
+        .captures(html)
+        .map(|caps| decode_entities(caps[1].trim()))
+        .filter(|t| !t.is_empty());
+    let body = main
+        .captures(html)
+        .and_then(|caps| caps.get(1).or_else(|| caps.get(2)))
+        .map_or(html, |m| m.as_str());
+    let body = noise.iter().fold(body.to_string(), |body, tag| {
+        tag.replace_all(&body, "").into_owned()
+    });
+    let text = html_to_text(&body);
+
+    match heading {
+        Some(heading) => format!("# {}\n\n{}", heading, text),
+        None => text,
+    }
+}
+
+/// Plain text with Markdown headings and bullets from HTML
+pub fn html_to_text(html: &str) -> String {
+    static PATTERNS: OnceLock<[Regex; 6]> = OnceLock::new();
+    let [heading, item, block_end, line_break, tag, blank_lines] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"(?i)<h([1-6])[^>]*>").unwrap(),
+            Regex::new(r"(?i)<li[^>]*>").unwrap(),
+            Regex::new(r"(?i)</(p|h[1-6]|ul|ol|tr|table|pre|blockquote|div)>").unwrap(),
+            Regex::new(r"(?i)<br\s*/?>").unwrap(),
+            Regex::new(r"<[^>]+>").unwrap(),
+            Regex::new(r"\n{3,}").unwrap(),
+        ]
+    });
+
+    let text = heading.replace_all(html, |caps: &regex::Captures| {
+        let level: usize = caps[1].parse().unwrap_or(1);
+        format!("\n{} ", "#".repeat(level))
+    });
+    let text = item.replace_all(&text, "\n- ");
+    let text = block_end.replace_all(&text, "\n\n");
+    let text = line_break.replace_all(&text, "\n");
+    let text = tag.replace_all(&text, "");
+    let text = decode_entities(&text);
+    blank_lines.replace_all(text.trim(), "\n\n").to_string()
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(feature = "web")]
+pub use fetch::{Fetched, Fetcher};
+
+#[cfg(feature = "web")]
+mod fetch {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use tokio::sync::Mutex;
+    use tokio::time::Instant;
+
+    use super::{parse_sitemap, same_origin, Sitemap};
+    use crate::config::WebConfig;
+    use crate::error::{A3SError, Result};
+    use crate::http::HttpClient;
+
+    /// Sitemap indexes followed below the root sitemap
+    const MAX_SITEMAP_DEPTH: usize = 3;
+
+    /// Result of a conditional page request
+    #[derive(Debug)]
+    pub enum Fetched {
+        /// The stored ETag still matches
+        NotModified,
+        Page {
+            body: String,
+            etag: Option<String>,
+        },
+    }
+
+    /// Sends requests no closer together than the politeness delay
+    pub struct Fetcher {
+        http: HttpClient,
+        delay: Duration,
+        timeout: Duration,
+        next_request: Mutex<Instant>,
+    }
+
+    impl Fetcher {
+        pub fn new(http: HttpClient, config: &WebConfig) -> Self {
+            Self {
+                http,
+                delay: Duration::from_millis(config.delay_ms),
+                timeout: Duration::from_secs(config.timeout_secs),
+                next_request: Mutex::new(Instant::now()),
+            }
+        }
+
+        /// Wait for this request's turn and reserve the next slot
+        async fn wait_turn(&self) {
+            let mut next = self.next_request.lock().await;
+            tokio::time::sleep_until(*next).await;
+            *next = Instant::now() + self.delay;
+        }
+
+        /// GET a URL, sending `etag` as `If-None-Match`
+        pub async fn get(&self, url: &str, etag: Option<&str>) -> Result<Fetched> {
+            self.wait_turn().await;
+            let mut request = self.http.get(url).timeout(self.timeout);
+            if let Some(etag) = etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|e| A3SError::Ingest(format!("Request for {} failed: {}", url, e)))?;
+
+            let status = response.status();
+            if status == reqwest::StatusCode::NOT_MODIFIED {
+                return Ok(Fetched::NotModified);
+            }
+            if !status.is_success() {
+                return Err(A3SError::Ingest(format!("{} returned {}", url, status)));
+            }
+            let etag = response
+                .headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let body = response
+                .text()
+                .await
+                .map_err(|e| A3SError::Ingest(format!("Failed to read {}: {}", url, e)))?;
+            Ok(Fetched::Page { body, etag })
+        }
+
+        /// Page URLs listed by a sitemap and the sitemaps it indexes,
+        /// keeping only those on the sitemap's own origin
+        pub async fn sitemap_urls(&self, sitemap: &str) -> Result<Vec<String>> {
+            let mut urls = Vec::new();
+            let mut pending = vec![(sitemap.to_string(), 0)];
+            while let Some((url, depth)) = pending.pop() {
+                let Fetched::Page { body, .. } = self.get(&url, None).await? else {
+                    continue;
+                };
+                match parse_sitemap(&body) {
+                    Sitemap::Urls(pages) => urls.extend(pages),
+                    Sitemap::Index(children) if depth < MAX_SITEMAP_DEPTH => {
+                        pending.extend(children.into_iter().rev().map(|c| (c, depth + 1)))
+                    }
+                    Sitemap::Index(_) => {
+                        tracing::warn!(sitemap = %url, "sitemap index nested too deeply");
+                    }
+                }
+            }
+
+            let listed = urls.len();
+            let mut seen = HashSet::new();
+            urls.retain(|page| same_origin(page, sitemap) && seen.insert(page.clone()));
+            if urls.len() < listed {
+                tracing::debug!(
+                    skipped = listed - urls.len(),
+                    "skipped duplicate sitemap URLs and URLs on other origins"
+                );
+            }
+            Ok(urls)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            parse_source("sitemap:https://docs.example.com/sitemap.xml"),
+            Some("https://docs.example.com/sitemap.xml")
+        );
+        assert_eq!(parse_source("sitemap:./sitemap.xml"), None);
+        assert_eq!(parse_source("./docs"), None);
+    }
+
+    #[test]
+    fn test_page_url() {
+        assert_eq!(
+            page_url("https://example.com/docs"),
+            Some("https://example.com/docs")
+        );
+        assert_eq!(page_url("sitemap:https://example.com/sitemap.xml"), None);
+        assert_eq!(page_url("./docs"), None);
+    }
+
+    #[test]
+    fn test_parse_sitemap() {
+        let urls = r#"<?xml version="1.0"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://docs.example.com/</loc><lastmod>2024-05-01</lastmod></url>
+  <url><loc> https://docs.example.com/guide?a=1&amp;b=2 </loc></url>
+</urlset>"#;
+        assert_eq!(
+            parse_sitemap(urls),
+            Sitemap::Urls(vec![
+                "https://docs.example.com/".to_string(),
+                "https://docs.example.com/guide?a=1&b=2".to_string(),
+            ])
+        );
+
+        let index = "<sitemapindex><sitemap><loc><![CDATA[https://docs.example.com/a.xml]]></loc></sitemap></sitemapindex>";
+        assert_eq!(
+            parse_sitemap(index),
+            Sitemap::Index(vec!["https://docs.example.com/a.xml".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_page_path() {
+        assert_eq!(page_path("https://docs.example.com/").unwrap(), "index");
+        assert_eq!(
+            page_path("https://docs.example.com/guide/install.html").unwrap(),
+            "guide/install"
+        );
+        assert!(same_origin(
+            "https://docs.example.com/a",
+            "https://docs.example.com/sitemap.xml"
+        ));
+        assert!(!same_origin(
+            "https://cdn.example.com/a",
+            "https://docs.example.com/sitemap.xml"
+        ));
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<h2>Deploys</h2><p>Run the <strong>pipeline</strong> &amp; wait.</p>\
+                    <ul><li>Tag</li><li>Ship</li></ul>";
+        assert_eq!(
+            html_to_text(html),
+            "## Deploys\n\nRun the pipeline & wait.\n\n- Tag\n- Ship"
+        );
+    }
+
+    #[test]
+    fn test_page_content_prefers_main() {
+        let html = "<html><head><title>Install</title><style>p{}</style></head>\
+                    <body><nav>Home</nav><main><p>Run the installer.</p>\
+                    <script>track()</script></main></body></html>";
+        assert_eq!(page_content(html), "# Install\n\nRun the installer.");
+    }
+
+    #[test]
+    fn test_page_content_strips_boilerplate() {
+        let html = "<html><head><title>Tokens</title></head><body>\
+                    <header class=\"top\"><nav>Home</nav><p>Sign in</p></header>\
+                    <div><h1>Tokens</h1><p>Tokens expire hourly.</p>\
+                    <aside>Related posts</aside></div>\
+                    <form><input></form><footer>&copy; Example</footer></body></html>";
+        assert_eq!(
+            page_content(html),
+            "# Tokens\n\n# Tokens\n\nTokens expire hourly."
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! Context windows that pick a digest level per node
+//!
+//! [`ContextBuilder`] fills a token budget with the matches of a query,
+//! best first. Each node is given the level [`Digest::get_level`] chooses
+//! for its fair share of the remaining budget, stepping down to a shorter
+//! level when the text does not fit that share, so a few top matches can be read in
+//! full while the rest are still represented by their summary or brief.
+//! A chunk is left out when its parent document or an overlapping chunk of
+//! the same document ranked higher, so no passage is included twice.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::ChunkInfo;
+use crate::core::Node;
+use crate::digest::DigestLevel;
+use crate::error::{A3SError, Result};
+use crate::pathway::Pathway;
+use crate::storage::StorageBackend;
+use crate::tokenizer::Tokenizer;
+use crate::MatchedNode;
+
+/// A prompt-ready block of context and what went into it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextWindow {
+    /// Nodes as `[n] pathway (level)` followed by their text
+    pub text: String,
+    /// One entry per numbered node, in order
+    pub entries: Vec<ContextEntry>,
+    /// Tokens of `text`
+    pub tokens: usize,
+    /// Matched chunks left out because their text was already included
+    pub deduplicated: Vec<Pathway>,
+}
+
+/// A node included in a [`ContextWindow`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextEntry {
+    pub pathway: Pathway,
+    pub level: DigestLevel,
+    /// Tokens of the node's text
+    pub tokens: usize,
+    /// Retrieval score of the node
+    pub score: f32,
+}
+
+/// Assembles query matches into a [`ContextWindow`] within a token budget
+pub struct ContextBuilder {
+    storage: Arc<dyn StorageBackend>,
+    tokenizer: Arc<dyn Tokenizer>,
+    max_tokens: usize,
+}
+
+impl ContextBuilder {
+    pub fn new(
+        storage: Arc<dyn StorageBackend>,
+        tokenizer: Arc<dyn Tokenizer>,
+        max_tokens: usize,
+    ) -> Self {
+        Self {
+            storage,
+            tokenizer,
+            max_tokens,
+        }
+    }
+
+    /// Fill the budget with `matches` in score order
+    pub async fn build(&self, matches: &[MatchedNode]) -> Result<ContextWindow> {
+        let mut ranked: Vec<&MatchedNode> = matches.iter().collect();
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let mut nodes = Vec::with_capacity(ranked.len());
+        for m in ranked {
+            match self.storage.get(&m.pathway).await {
+                Ok(node) => nodes.push((m, node)),
+                // Removed since the query ran
+                Err(A3SError::NodeNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let (nodes, deduplicated) = deduplicate(nodes);
+
+        let mut window = ContextWindow {
+            text: String::new(),
+            entries: Vec::new(),
+            tokens: 0,
+            deduplicated,
+        };
+        let mut budget = self.max_tokens;
+        for (i, (m, node)) in nodes.iter().enumerate() {
+            let share = budget / (nodes.len() - i);
+            let Some((level, text, tokens)) = self.fit(node, share, budget) else {
+                continue;
+            };
+            budget -= tokens;
+            window.text.push_str(&format!(
+                "[{}] {} ({})\n{}\n\n",
+                window.entries.len() + 1,
+                node.pathway,
+                label(level),
+                text
+            ));
+            window.entries.push(ContextEntry {
+                pathway: node.pathway.clone(),
+                level,
+                tokens,
+                score: m.score,
+            });
+        }
+        window.tokens = self.tokenizer.count(&window.text);
+        Ok(window)
+    }
+
+    /// Most detailed text of a node, at or below the level for `share`,
+    /// that fits `share`; failing that, its shortest text within `budget`
+    fn fit<'a>(
+        &self,
+        node: &'a Node,
+        share: usize,
+        budget: usize,
+    ) -> Option<(DigestLevel, &'a str, usize)> {
+        let preferred = node.digest.get_level(share);
+        let candidates: Vec<_> = [DigestLevel::Full, DigestLevel::Summary, DigestLevel::Brief]
+            .into_iter()
+            .skip_while(|level| *level != preferred)
+            .map(|level| (level, text_at(node, level)))
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(level, text)| (level, text, self.tokenizer.count(text)))
+            .collect();
+        candidates
+            .iter()
+            .find(|(_, _, tokens)| *tokens <= share)
+            .or_else(|| candidates.last().filter(|(_, _, tokens)| *tokens <= budget))
+            .copied()
+    }
+}
+
+/// A node's text at a digest level
+fn text_at(node: &Node, level: DigestLevel) -> &str {
+    match level {
+        DigestLevel::Full => &node.content,
+        DigestLevel::Summary => &node.digest.summary,
+        DigestLevel::Brief => &node.digest.brief,
+    }
+}
+
+/// Drop chunks whose parent, or an overlapping chunk of the same parent,
+/// ranked higher
+fn deduplicate(ranked: Vec<(&MatchedNode, Node)>) -> (Vec<(&MatchedNode, Node)>, Vec<Pathway>) {
+    let mut kept: Vec<(&MatchedNode, Node)> = Vec::new();
+    let mut spans: Vec<ChunkInfo> = Vec::new();
+    let mut dropped = Vec::new();
+    for (m, node) in ranked {
+        if let Some(info) = ChunkInfo::of(&node) {
+            let covered = kept.iter().any(|(_, k)| k.pathway == info.parent)
+                || spans
+                    .iter()
+                    .any(|s| s.parent == info.parent && s.start < info.end && info.start < s.end);
+            if covered {
+                dropped.push(node.pathway);
+                continue;
+            }
+            spans.push(info);
+        }
+        kept.push((m, node));
+    }
+    (kept, dropped)
+}
+
+fn label(level: DigestLevel) -> &'static str {
+    match level {
+        DigestLevel::Brief => "brief",
+        DigestLevel::Summary => "summary",
+        DigestLevel::Full => "full",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::core::NodeKind;
+    use crate::digest::Digest;
+    use crate::storage::MemoryStorage;
+    use crate::tokenizer::HeuristicTokenizer;
+
+    fn matched(node: &Node, score: f32) -> MatchedNode {
+        MatchedNode {
+            pathway: node.pathway.clone(),
+            node_kind: node.kind,
+            score,
+            brief: node.digest.brief.clone(),
+            summary: None,
+            content: None,
+            highlights: Vec::new(),
+            age: 0,
+            stale: false,
+            symbols: Vec::new(),
+            explanation: None,
+        }
+    }
+
+    fn node(pathway: &str, content: &str, brief: &str, summary: &str) -> Node {
+        let mut node = Node::new(
+            Pathway::parse(pathway).unwrap(),
+            NodeKind::Document,
+            content.to_string(),
+        );
+        node.digest = Digest::with_content(brief.to_string(), summary.to_string());
+        node
+    }
+
+    fn chunk(parent: &str, index: usize, start: usize, end: usize) -> Node {
+        let parent = Pathway::parse(parent).unwrap();
+        let mut node = Node::new(
+            crate::chunk::chunk_pathway(&parent, index),
+            NodeKind::Document,
+            "x".repeat(end - start),
+        );
+        let info = ChunkInfo {
+            parent,
+            index,
+            start,
+            end,
+            header: None,
+        };
+        node.metadata.custom.insert(
+            crate::chunk::CHUNK_KEY.to_string(),
+            serde_json::to_value(info).unwrap(),
+        );
+        node
+    }
+
+    async fn builder(nodes: &[Node], max_tokens: usize) -> ContextBuilder {
+        let storage = Arc::new(MemoryStorage::new(&VectorIndexConfig::default()));
+        for node in nodes {
+            storage.put(node).await.unwrap();
+        }
+        ContextBuilder::new(storage, Arc::new(HeuristicTokenizer), max_tokens)
+    }
+
+    #[tokio::test]
+    async fn test_levels_follow_budget() {
+        let long = node(
+            "a3s://knowledge/a",
+            &"a".repeat(8000),
+            "Brief A",
+            &"s".repeat(400),
+        );
+        let short = node("a3s://knowledge/b", "Short content", "Brief B", "Summary B");
+        let builder = builder(&[long.clone(), short.clone()], 2400).await;
+
+        let window = builder
+            .build(&[matched(&long, 0.9), matched(&short, 0.8)])
+            .await
+            .unwrap();
+        // A's 2000-token content does not fit its share, so its summary is used
+        assert_eq!(window.entries[0].level, DigestLevel::Summary);
+        assert_eq!(window.entries[0].tokens, 100);
+        assert_eq!(window.entries[1].level, DigestLevel::Full);
+        assert!(window.text.starts_with("[1] a3s://knowledge/a (summary)\n"));
+        assert!(window
+            .text
+            .contains("[2] a3s://knowledge/b (full)\nShort content"));
+
+        // A budget too small for any summary falls back to briefs
+        let tight = ContextBuilder::new(builder.storage.clone(), Arc::new(HeuristicTokenizer), 10);
+        let window = tight
+            .build(&[matched(&long, 0.9), matched(&short, 0.8)])
+            .await
+            .unwrap();
+        assert_eq!(window.entries.len(), 2);
+        assert_eq!(window.entries[0].level, DigestLevel::Brief);
+        assert_eq!(window.entries[1].level, DigestLevel::Brief);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_chunks_are_deduplicated() {
+        let parent = node("a3s://knowledge/doc", "content", "Doc", "Doc summary");
+        let first = chunk("a3s://knowledge/doc", 0, 0, 100);
+        let overlapping = chunk("a3s://knowledge/doc", 1, 80, 180);
+        let separate = chunk("a3s://knowledge/doc", 2, 180, 280);
+        let other = chunk("a3s://knowledge/other", 0, 0, 100);
+        let builder = builder(
+            &[
+                parent.clone(),
+                first.clone(),
+                overlapping.clone(),
+                separate.clone(),
+                other.clone(),
+            ],
+            10_000,
+        )
+        .await;
+
+        let window = builder
+            .build(&[
+                matched(&first, 0.9),
+                matched(&overlapping, 0.8),
+                matched(&separate, 0.7),
+                matched(&other, 0.6),
+            ])
+            .await
+            .unwrap();
+        let included: Vec<_> = window.entries.iter().map(|e| e.pathway.clone()).collect();
+        assert_eq!(
+            included,
+            vec![
+                first.pathway.clone(),
+                separate.pathway.clone(),
+                other.pathway.clone()
+            ]
+        );
+        assert_eq!(window.deduplicated, vec![overlapping.pathway.clone()]);
+
+        // A matched parent covers its chunks
+        let window = builder
+            .build(&[matched(&parent, 0.95), matched(&first, 0.9)])
+            .await
+            .unwrap();
+        assert_eq!(window.entries.len(), 1);
+        assert_eq!(window.deduplicated, vec![first.pathway]);
+    }
+}